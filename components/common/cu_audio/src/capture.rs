@@ -0,0 +1,174 @@
+use crate::{to_i16, AudioDeviceConfig, AudioPayload};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::Stream;
+use cu29::prelude::*;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// What the task reads from its ComponentConfig, on top of the device.
+#[derive(CuConfigStruct)]
+struct CaptureConfig {
+    #[config(default = 512, range = 1..)]
+    frame_size: usize,
+    #[config(default = 16, range = 1..)]
+    pool_size: usize,
+}
+
+/// Cuts the samples coming from the device in frames of a fixed size.
+struct FrameAssembler {
+    frame: Vec<i16>,
+    samples_per_frame: usize,
+}
+
+impl FrameAssembler {
+    fn new(samples_per_frame: usize) -> Self {
+        Self {
+            frame: Vec::with_capacity(samples_per_frame),
+            samples_per_frame,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32], mut on_frame: impl FnMut(&[i16])) {
+        for sample in samples {
+            self.frame.push(to_i16(*sample));
+            if self.frame.len() == self.samples_per_frame {
+                on_frame(&self.frame);
+                self.frame.clear();
+            }
+        }
+    }
+}
+
+/// The frames captured by the device callback, waiting for the task.
+#[derive(Default)]
+struct CaptureQueue {
+    frames: VecDeque<(CuTime, CuHandle<Vec<i16>>)>,
+    /// Frames lost because all the buffers of the pool were in use.
+    dropped: u64,
+}
+
+/// This is a source task capturing PCM frames of a fixed size from an audio input device.
+/// The frames are buffers of a pool, the task needs to run at least at the frame rate
+/// (sample_rate / frame_size) or the pool runs out and the new frames are dropped.
+pub struct AudioCapture {
+    device: AudioDeviceConfig,
+    frame_size: usize,
+    pool: Arc<CuHostMemoryPool<Vec<i16>>>,
+    queue: Arc<Mutex<CaptureQueue>>,
+    stream: Option<Stream>,
+    seq: u64,
+}
+
+impl Freezable for AudioCapture {}
+
+impl<'cl> CuSrcTask<'cl> for AudioCapture {
+    type Output = output_msg!('cl, AudioPayload);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let device = AudioDeviceConfig::from_config(config)?;
+        let CaptureConfig {
+            frame_size,
+            pool_size,
+        } = CaptureConfig::from_config(config)?;
+        let samples = device.format().samples(frame_size);
+        let pool = CuHostMemoryPool::new("cu_audio_capture", pool_size, || vec![0i16; samples])?;
+        Ok(Self {
+            device,
+            frame_size,
+            pool,
+            queue: Default::default(),
+            stream: None,
+            seq: 0,
+        })
+    }
+
+    fn start(&mut self, clock: &RobotClock) -> CuResult<()> {
+        let device = self.device.open(true)?;
+        let format = self.device.format();
+        let frame_duration =
+            CuDuration(self.frame_size as u64 * 1_000_000_000 / format.sample_rate as u64);
+        let mut assembler = FrameAssembler::new(format.samples(self.frame_size));
+        let pool = self.pool.clone();
+        let queue = self.queue.clone();
+        let clock = clock.clone();
+
+        let stream = device
+            .build_input_stream(
+                &self.device.stream_config(),
+                move |data: &[f32], _info: &cpal::InputCallbackInfo| {
+                    assembler.push(data, |frame| {
+                        let mut queue = queue.lock().unwrap();
+                        let Some(handle) = pool.acquire() else {
+                            queue.dropped += 1;
+                            return;
+                        };
+                        handle.with_inner_mut(|inner| inner.copy_from_slice(frame));
+                        // The frame started with its first sample.
+                        let tov = CuDuration(clock.now().0.saturating_sub(frame_duration.0));
+                        queue.frames.push_back((tov, handle));
+                    });
+                },
+                |e| debug!("AudioCapture: Stream error: {}", e.to_string()),
+                None,
+            )
+            .map_err(|e| CuError::new_with_cause("AudioCapture: Failed to open the stream", e))?;
+        stream
+            .play()
+            .map_err(|e| CuError::new_with_cause("AudioCapture: Failed to start the stream", e))?;
+        debug!(
+            "AudioCapture: Capturing {}Hz, {} channel(s)",
+            format.sample_rate, format.channels
+        );
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let Some((tov, handle)) = self.queue.lock().unwrap().frames.pop_front() else {
+            new_msg.clear_payload();
+            return Ok(());
+        };
+        let mut frame = AudioPayload::new(self.device.format(), handle);
+        frame.seq = self.seq;
+        self.seq += 1;
+        new_msg.set_payload(frame);
+        new_msg.metadata.tov = tov.into();
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        // Dropping the stream closes the device.
+        self.stream = None;
+        let mut queue = self.queue.lock().unwrap();
+        if queue.dropped > 0 {
+            debug!("AudioCapture: {} frame(s) dropped", queue.dropped);
+        }
+        *queue = CaptureQueue::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_assembler() {
+        let mut assembler = FrameAssembler::new(4);
+        let mut frames = Vec::new();
+        assembler.push(&[0.0, 0.5, 1.0], |frame| frames.push(frame.to_vec()));
+        assert!(frames.is_empty());
+        assembler.push(&[-1.0, 0.0, 0.0, 0.0, 0.0, 0.25], |frame| {
+            frames.push(frame.to_vec())
+        });
+        assert_eq!(
+            frames,
+            vec![vec![0, 16383, i16::MAX, -i16::MAX], vec![0, 0, 0, 0]]
+        );
+        // The last sample waits for the next callback.
+        assert_eq!(assembler.frame, vec![8191]);
+    }
+}
@@ -0,0 +1,87 @@
+mod capture;
+mod playback;
+
+pub use capture::AudioCapture;
+pub use playback::AudioPlayback;
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{BufferSize, Device, SampleRate, StreamConfig};
+use cu29::prelude::*;
+use cu_sensor_payloads::{CuAudioFormat, CuAudioFrame};
+
+/// The frames exchanged by the audio tasks.
+pub type AudioPayload = CuAudioFrame<Vec<i16>>;
+
+/// What the audio tasks read from their ComponentConfig.
+#[derive(CuConfigStruct)]
+struct AudioDeviceConfig {
+    /// The default device of the host if not set.
+    device: Option<String>,
+    #[config(default = 16000, range = 1..)]
+    sample_rate: u32,
+    #[config(default = 1, range = 1..)]
+    channels: u16,
+}
+
+impl AudioDeviceConfig {
+    fn format(&self) -> CuAudioFormat {
+        CuAudioFormat {
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+
+    fn stream_config(&self) -> StreamConfig {
+        StreamConfig {
+            channels: self.channels,
+            sample_rate: SampleRate(self.sample_rate),
+            buffer_size: BufferSize::Default,
+        }
+    }
+
+    fn open(&self, input: bool) -> CuResult<Device> {
+        let host = cpal::default_host();
+        let device = match (&self.device, input) {
+            (None, true) => host.default_input_device(),
+            (None, false) => host.default_output_device(),
+            (Some(name), _) => {
+                let mut devices = if input {
+                    host.input_devices()
+                } else {
+                    host.output_devices()
+                }
+                .map_err(|e| CuError::new_with_cause("Audio: Failed to list the devices", e))?;
+                devices.find(|device| device.name().is_ok_and(|n| &n == name))
+            }
+        };
+        device.ok_or_else(|| {
+            CuError::from(format!(
+                "Audio: {} device {} not found",
+                if input { "input" } else { "output" },
+                self.device.as_deref().unwrap_or("(default)")
+            ))
+        })
+    }
+}
+
+/// The devices work in f32, the payloads in i16.
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+fn to_f32(sample: i16) -> f32 {
+    sample as f32 / i16::MAX as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_conversions() {
+        assert_eq!(to_i16(1.0), i16::MAX);
+        assert_eq!(to_i16(-2.0), -i16::MAX);
+        assert_eq!(to_i16(0.0), 0);
+        assert_eq!(to_f32(to_i16(0.5)), 16383.0 / i16::MAX as f32);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{to_f32, AudioDeviceConfig, AudioPayload};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::Stream;
+use cu29::prelude::*;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// What the task reads from its ComponentConfig, on top of the device.
+#[derive(CuConfigStruct)]
+struct PlaybackConfig {
+    #[config(default = 200, range = 1..)]
+    max_latency_ms: u32,
+}
+
+/// The samples waiting to be played, bounded to a maximum latency.
+struct PlaybackQueue {
+    samples: VecDeque<i16>,
+    capacity: usize,
+}
+
+impl PlaybackQueue {
+    fn push(&mut self, samples: &[i16]) {
+        self.samples.extend(samples);
+        // Drops the oldest samples to catch up.
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+
+    /// Plays silence when there is nothing to play.
+    fn fill(&mut self, data: &mut [f32]) {
+        for sample in data.iter_mut() {
+            *sample = self.samples.pop_front().map(to_f32).unwrap_or(0.0);
+        }
+    }
+}
+
+/// This is a sink task playing the audio frames it receives on an output device.
+/// The frames need to be in the format of the device given in the config.
+pub struct AudioPlayback {
+    device: AudioDeviceConfig,
+    queue: Arc<Mutex<PlaybackQueue>>,
+    stream: Option<Stream>,
+}
+
+impl Freezable for AudioPlayback {}
+
+impl<'cl> CuSinkTask<'cl> for AudioPlayback {
+    type Input = input_msg!('cl, AudioPayload);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let device = AudioDeviceConfig::from_config(config)?;
+        let PlaybackConfig { max_latency_ms } = PlaybackConfig::from_config(config)?;
+        let format = device.format();
+        let capacity = format.samples(format.sample_rate as usize * max_latency_ms as usize / 1000);
+        Ok(Self {
+            device,
+            queue: Arc::new(Mutex::new(PlaybackQueue {
+                samples: VecDeque::with_capacity(capacity),
+                capacity,
+            })),
+            stream: None,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let device = self.device.open(false)?;
+        let queue = self.queue.clone();
+        let stream = device
+            .build_output_stream(
+                &self.device.stream_config(),
+                move |data: &mut [f32], _info: &cpal::OutputCallbackInfo| {
+                    queue.lock().unwrap().fill(data);
+                },
+                |e| debug!("AudioPlayback: Stream error: {}", e.to_string()),
+                None,
+            )
+            .map_err(|e| CuError::new_with_cause("AudioPlayback: Failed to open the stream", e))?;
+        stream
+            .play()
+            .map_err(|e| CuError::new_with_cause("AudioPlayback: Failed to start the stream", e))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn process(&mut self, _clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        let Some(frame) = input.payload() else {
+            return Ok(());
+        };
+        if frame.format != self.device.format() {
+            return Err(format!(
+                "AudioPlayback: the frame is {}Hz with {} channel(s), the device is {}Hz with {}",
+                frame.format.sample_rate,
+                frame.format.channels,
+                self.device.sample_rate,
+                self.device.channels
+            )
+            .into());
+        }
+        let mut queue = self.queue.lock().unwrap();
+        frame
+            .buffer_handle
+            .with_inner(|samples| queue.push(samples));
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.stream = None;
+        self.queue.lock().unwrap().samples.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_queue() {
+        let mut queue = PlaybackQueue {
+            samples: VecDeque::new(),
+            capacity: 4,
+        };
+        queue.push(&[1, 2, 3]);
+        queue.push(&[4, 5, 6]);
+        assert_eq!(queue.samples, [3, 4, 5, 6]);
+
+        let mut data = [1.0; 6];
+        queue.fill(&mut data);
+        assert_eq!(data[0], to_f32(3));
+        assert_eq!(data[4..], [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,60 @@
+mod sink;
+mod source;
+
+pub use sink::DatasetSink;
+pub use source::DatasetSrc;
+
+use cu29::prelude::*;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetFormat {
+    /// With a header line, the columns are the fields of the payload.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl DatasetFormat {
+    /// The format key of the config, or the extension of the file.
+    fn from_config(format: Option<&str>, file: &str) -> CuResult<Self> {
+        let format = match format {
+            Some(format) => format.to_string(),
+            None => Path::new(file)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default()
+                .to_lowercase(),
+        };
+        match format.as_str() {
+            "csv" => Ok(DatasetFormat::Csv),
+            "jsonl" | "ndjson" => Ok(DatasetFormat::Jsonl),
+            other => Err(format!(
+                "Dataset: the format of {file} should be csv or jsonl, got \"{other}\"."
+            )
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            DatasetFormat::from_config(None, "runs/imu.CSV").unwrap(),
+            DatasetFormat::Csv
+        );
+        assert_eq!(
+            DatasetFormat::from_config(None, "runs/imu.jsonl").unwrap(),
+            DatasetFormat::Jsonl
+        );
+        assert_eq!(
+            DatasetFormat::from_config(Some("jsonl"), "runs/imu.log").unwrap(),
+            DatasetFormat::Jsonl
+        );
+        assert!(DatasetFormat::from_config(None, "runs/imu").is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::DatasetFormat;
+use cu29::prelude::*;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct DatasetSinkConfig {
+    file: String,
+    format: Option<String>,
+}
+
+enum Rows {
+    Csv(csv::Writer<File>),
+    Jsonl(BufWriter<File>),
+}
+
+/// This is a sink task appending its payloads to a CSV or JSONL file, the messages without payload are skipped.
+/// The CSV header is only written if the file is new or empty.
+pub struct DatasetSink<P>
+where
+    P: CuMsgPayload + Serialize,
+{
+    _marker: PhantomData<P>,
+    file: String,
+    format: DatasetFormat,
+    rows: Option<Rows>,
+}
+
+fn write_error_map<E: std::error::Error>(file: &str) -> impl FnOnce(E) -> CuError + '_ {
+    move |e| CuError::new_with_cause(&format!("DatasetSink: Failed to write to {file}"), e)
+}
+
+impl<P> Freezable for DatasetSink<P> where P: CuMsgPayload + Serialize {}
+
+impl<'cl, P> CuSinkTask<'cl> for DatasetSink<P>
+where
+    P: CuMsgPayload + Serialize + 'cl + 'static,
+{
+    type Input = input_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let DatasetSinkConfig { file, format } = DatasetSinkConfig::from_config(config)?;
+        let format = DatasetFormat::from_config(format.as_deref(), &file)?;
+        Ok(Self {
+            _marker: Default::default(),
+            file,
+            format,
+            rows: None,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)
+            .map_err(|e| {
+                CuError::new_with_cause(&format!("DatasetSink: Failed to open {}", self.file), e)
+            })?;
+        self.rows = Some(match self.format {
+            DatasetFormat::Csv => {
+                let empty = file.metadata().map(|m| m.len() == 0).unwrap_or(true);
+                Rows::Csv(
+                    csv::WriterBuilder::new()
+                        .has_headers(empty)
+                        .from_writer(file),
+                )
+            }
+            DatasetFormat::Jsonl => Rows::Jsonl(BufWriter::new(file)),
+        });
+        debug!("DatasetSink: Appending to {}", self.file.as_str());
+        Ok(())
+    }
+
+    fn process(&mut self, _clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        let Some(payload) = input.payload() else {
+            return Ok(());
+        };
+        match self.rows.as_mut() {
+            None => return Err("DatasetSink: File not opened".into()),
+            Some(Rows::Csv(writer)) => writer
+                .serialize(payload)
+                .map_err(write_error_map(&self.file))?,
+            Some(Rows::Jsonl(writer)) => {
+                serde_json::to_writer(&mut *writer, payload)
+                    .map_err(write_error_map(&self.file))?;
+                writer
+                    .write_all(b"\n")
+                    .map_err(write_error_map(&self.file))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        match self.rows.take() {
+            Some(Rows::Csv(mut writer)) => writer.flush(),
+            Some(Rows::Jsonl(mut writer)) => writer.flush(),
+            None => Ok(()),
+        }
+        .map_err(write_error_map(&self.file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatasetSrc;
+    use bincode::{Decode, Encode};
+    use serde::Deserialize;
+
+    #[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+    struct Sample {
+        speed: f64,
+        label: String,
+    }
+
+    fn write_samples(file: &str, samples: &[f64]) {
+        let mut config = ComponentConfig::new();
+        config.set("file", file.to_string());
+        let clock = RobotClock::new();
+        let mut sink = DatasetSink::<Sample>::new(Some(&config)).unwrap();
+        sink.start(&clock).unwrap();
+        for speed in samples {
+            let msg = CuMsg::new(Some(Sample {
+                speed: *speed,
+                label: "go".to_string(),
+            }));
+            sink.process(&clock, &msg).unwrap();
+        }
+        sink.process(&clock, &CuMsg::new(None)).unwrap();
+        sink.stop(&clock).unwrap();
+    }
+
+    fn read_samples(file: &str) -> Vec<f64> {
+        let mut config = ComponentConfig::new();
+        config.set("file", file.to_string());
+        let clock = RobotClock::new();
+        let mut src = DatasetSrc::<Sample>::new(Some(&config)).unwrap();
+        src.start(&clock).unwrap();
+        let mut msg = CuMsg::new(None);
+        let mut speeds = Vec::new();
+        loop {
+            src.process(&clock, &mut msg).unwrap();
+            match msg.payload() {
+                Some(sample) => speeds.push(sample.speed),
+                None => return speeds,
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["golden.csv", "golden.jsonl"] {
+            let file = dir.path().join(name);
+            let file = file.to_str().unwrap();
+            write_samples(file, &[1.0, 2.0]);
+            // A restart appends.
+            write_samples(file, &[3.0]);
+            assert_eq!(read_samples(file), vec![1.0, 2.0, 3.0]);
+        }
+        let csv = std::fs::read_to_string(dir.path().join("golden.csv")).unwrap();
+        assert_eq!(csv.matches("speed,label").count(), 1);
+    }
+}
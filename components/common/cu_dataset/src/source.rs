@@ -0,0 +1,336 @@
+use crate::DatasetFormat;
+use cu29::prelude::*;
+use serde::de::DeserializeOwned;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::marker::PhantomData;
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct DatasetSrcConfig {
+    file: String,
+    format: Option<String>,
+    /// The rows are paced by this column if it is set.
+    timestamp_column: Option<String>,
+    #[config(default = "s")]
+    timestamp_unit: String,
+}
+
+enum Rows {
+    Csv {
+        reader: csv::Reader<File>,
+        headers: csv::StringRecord,
+        timestamp_index: Option<usize>,
+    },
+    Jsonl(Lines<BufReader<File>>),
+}
+
+struct Row<P> {
+    /// ns, in the time base of the file.
+    timestamp: Option<u64>,
+    payload: P,
+}
+
+/// This is a source task streaming the rows of a CSV or JSONL file as payloads, one row per cycle at most.
+/// The rows are deserialized with serde: the columns of the CSV header or the fields of the JSON objects
+/// are the fields of P.
+pub struct DatasetSrc<P>
+where
+    P: CuMsgPayload + DeserializeOwned,
+{
+    _marker: PhantomData<P>,
+    file: String,
+    format: DatasetFormat,
+    timestamp_column: Option<String>,
+    /// ns per unit of the timestamp column.
+    timestamp_scale: f64,
+    rows: Option<Rows>,
+    /// The row read but not emitted yet because its time has not come.
+    pending: Option<Row<P>>,
+    /// The first timestamp of the file matches this Robot time.
+    anchor: Option<(u64, CuTime)>,
+    line: usize,
+}
+
+impl<P> DatasetSrc<P>
+where
+    P: CuMsgPayload + DeserializeOwned,
+{
+    fn row_error(&self, msg: &str, cause: impl std::error::Error) -> CuError {
+        CuError::new_with_cause(
+            &format!("Dataset: {} line {}: {msg}", self.file, self.line),
+            cause,
+        )
+    }
+
+    fn parse_timestamp(&self, value: Option<f64>) -> CuResult<u64> {
+        match value {
+            Some(value) if value >= 0.0 => Ok((value * self.timestamp_scale).round() as u64),
+            _ => Err(format!(
+                "Dataset: {} line {}: missing or invalid {}",
+                self.file,
+                self.line,
+                self.timestamp_column.as_deref().unwrap_or_default()
+            )
+            .into()),
+        }
+    }
+
+    fn next_row(&mut self) -> CuResult<Option<Row<P>>> {
+        self.line += 1;
+        match self.rows.as_mut() {
+            None => Err("Dataset: File not opened".into()),
+            Some(Rows::Csv {
+                reader,
+                headers,
+                timestamp_index,
+            }) => {
+                let mut record = csv::StringRecord::new();
+                let read = reader.read_record(&mut record);
+                let timestamp_index = *timestamp_index;
+                let headers = headers.clone();
+                if !read.map_err(|e| self.row_error("Failed to read", e))? {
+                    return Ok(None);
+                }
+                let payload = record
+                    .deserialize::<P>(Some(&headers))
+                    .map_err(|e| self.row_error("Failed to deserialize", e))?;
+                let timestamp = timestamp_index
+                    .map(|index| {
+                        self.parse_timestamp(
+                            record
+                                .get(index)
+                                .and_then(|value| value.trim().parse().ok()),
+                        )
+                    })
+                    .transpose()?;
+                Ok(Some(Row { timestamp, payload }))
+            }
+            Some(Rows::Jsonl(lines)) => {
+                let line = match lines.next() {
+                    None => return Ok(None),
+                    Some(line) => line.map_err(|e| self.row_error("Failed to read", e))?,
+                };
+                if line.trim().is_empty() {
+                    return self.next_row();
+                }
+                let value: serde_json::Value =
+                    serde_json::from_str(&line).map_err(|e| self.row_error("Invalid JSON", e))?;
+                let timestamp = self
+                    .timestamp_column
+                    .as_deref()
+                    .map(|column| {
+                        self.parse_timestamp(value.get(column).and_then(|value| value.as_f64()))
+                    })
+                    .transpose()?;
+                let payload = serde_json::from_value::<P>(value)
+                    .map_err(|e| self.row_error("Failed to deserialize", e))?;
+                Ok(Some(Row { timestamp, payload }))
+            }
+        }
+    }
+}
+
+impl<P> Freezable for DatasetSrc<P> where P: CuMsgPayload + DeserializeOwned {}
+
+impl<'cl, P> CuSrcTask<'cl> for DatasetSrc<P>
+where
+    P: CuMsgPayload + DeserializeOwned + 'cl + 'static,
+{
+    type Output = output_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let DatasetSrcConfig {
+            file,
+            format,
+            timestamp_column,
+            timestamp_unit,
+        } = DatasetSrcConfig::from_config(config)?;
+        let format = DatasetFormat::from_config(format.as_deref(), &file)?;
+        let timestamp_scale = match timestamp_unit.as_str() {
+            "s" => 1e9,
+            "ms" => 1e6,
+            "us" => 1e3,
+            "ns" => 1.0,
+            other => {
+                return Err(format!(
+                    "DatasetSrc: the timestamp_unit should be s, ms, us or ns, got {other}."
+                )
+                .into())
+            }
+        };
+        Ok(Self {
+            _marker: Default::default(),
+            file,
+            format,
+            timestamp_column,
+            timestamp_scale,
+            rows: None,
+            pending: None,
+            anchor: None,
+            line: 0,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let file = File::open(&self.file).map_err(|e| {
+            CuError::new_with_cause(&format!("DatasetSrc: Failed to open {}", self.file), e)
+        })?;
+        self.rows = Some(match self.format {
+            DatasetFormat::Csv => {
+                let mut reader = csv::Reader::from_reader(file);
+                let headers = reader
+                    .headers()
+                    .map_err(|e| {
+                        CuError::new_with_cause("DatasetSrc: Failed to read the header", e)
+                    })?
+                    .clone();
+                let timestamp_index = match &self.timestamp_column {
+                    Some(column) => Some(
+                        headers
+                            .iter()
+                            .position(|header| header == column)
+                            .ok_or_else(|| {
+                                CuError::from(format!(
+                                    "DatasetSrc: no column {column} in {}",
+                                    self.file
+                                ))
+                            })?,
+                    ),
+                    None => None,
+                };
+                // The header is the first line.
+                self.line = 1;
+                Rows::Csv {
+                    reader,
+                    headers,
+                    timestamp_index,
+                }
+            }
+            DatasetFormat::Jsonl => {
+                self.line = 0;
+                Rows::Jsonl(BufReader::new(file).lines())
+            }
+        });
+        debug!("DatasetSrc: Streaming {}", self.file.as_str());
+        Ok(())
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let row = match self.pending.take() {
+            Some(row) => row,
+            None => match self.next_row()? {
+                Some(row) => row,
+                None => {
+                    new_msg.clear_payload();
+                    return Ok(());
+                }
+            },
+        };
+
+        let tov = match row.timestamp {
+            Some(timestamp) => {
+                let (first, start) = *self.anchor.get_or_insert((timestamp, clock.now()));
+                let tov = start + CuDuration(timestamp.saturating_sub(first));
+                if clock.now() < tov {
+                    self.pending = Some(row);
+                    new_msg.clear_payload();
+                    return Ok(());
+                }
+                tov
+            }
+            None => clock.now(),
+        };
+        new_msg.set_payload(row.payload);
+        new_msg.metadata.tov = tov.into();
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        // A restart streams the file from the beginning.
+        self.rows = None;
+        self.pending = None;
+        self.anchor = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bincode::{Decode, Encode};
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Deserialize)]
+    struct Sample {
+        speed: f64,
+        label: String,
+    }
+
+    fn source(file: &std::path::Path, timestamp_column: Option<&str>) -> DatasetSrc<Sample> {
+        let mut config = ComponentConfig::new();
+        config.set("file", file.to_str().unwrap().to_string());
+        if let Some(column) = timestamp_column {
+            config.set("timestamp_column", column.to_string());
+            config.set("timestamp_unit", "ms".to_string());
+        }
+        DatasetSrc::new(Some(&config)).unwrap()
+    }
+
+    #[test]
+    fn test_csv_paced() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("run.csv");
+        std::fs::write(&file, "time,speed,label\n100,1.5,go\n150,2.5,stop\n").unwrap();
+
+        let (clock, mock) = RobotClock::mock();
+        let mut src = source(&file, Some("time"));
+        src.start(&clock).unwrap();
+        let mut msg = CuMsg::<Sample>::new(None);
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(
+            msg.payload(),
+            Some(&Sample {
+                speed: 1.5,
+                label: "go".to_string()
+            })
+        );
+
+        // The next row is 50ms later.
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), None);
+        mock.increment(Duration::from_millis(50));
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().label, "stop");
+        assert_eq!(msg.metadata.tov, Tov::Time(CuDuration(50_000_000)));
+
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), None);
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("run.jsonl");
+        std::fs::write(
+            &file,
+            "{\"speed\": 1.5, \"label\": \"go\"}\n\n{\"speed\": 2.5, \"label\": \"stop\"}\n{\"speed\": \"fast\"}\n",
+        )
+        .unwrap();
+
+        let clock = RobotClock::new();
+        let mut src = source(&file, None);
+        src.start(&clock).unwrap();
+        let mut msg = CuMsg::<Sample>::new(None);
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().speed, 1.5);
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().speed, 2.5);
+        let error = src.process(&clock, &mut msg).unwrap_err();
+        assert!(error.to_string().contains("line 4"));
+    }
+}
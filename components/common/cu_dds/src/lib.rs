@@ -0,0 +1,207 @@
+mod qos;
+
+pub use qos::{History, QosConfig, Reliability};
+
+use cdds_derive::Topic;
+use cu29::{bincode, prelude::*};
+use cyclonedds_rs::{DdsParticipant, DdsReader, DdsTopic, DdsWriter, SampleBuffer, TopicType};
+use serde::{Deserialize, Serialize};
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// How many samples a subscriber takes from the reader per cycle, only the latest is kept.
+const SAMPLES_PER_CYCLE: usize = 16;
+
+/// What travels on the DDS topics: the bincode encoding of the Copper message.
+/// Copper messages keep their metadata (time of validity...) across the bridge.
+#[derive(Serialize, Deserialize, Topic, Default)]
+pub struct CuDdsSample {
+    pub data: Vec<u8>,
+}
+
+/// What the tasks read from their ComponentConfig, the QoS keys are read by [QosConfig].
+#[derive(CuConfigStruct)]
+struct DdsConfig {
+    topic: String,
+    domain_id: Option<u32>,
+}
+
+struct DdsEndpointConfig {
+    topic: String,
+    domain_id: Option<u32>,
+    qos: QosConfig,
+}
+
+impl DdsEndpointConfig {
+    fn from_config(config: Option<&ComponentConfig>) -> CuResult<Self> {
+        let DdsConfig { topic, domain_id } = DdsConfig::from_config(config)?;
+        Ok(Self {
+            topic: namespaced(config, &topic),
+            domain_id,
+            qos: QosConfig::from_config(config)?,
+        })
+    }
+
+    fn open(&self) -> CuResult<(DdsParticipant, DdsTopic<CuDdsSample>)> {
+        let participant = DdsParticipant::create(self.domain_id, None, None)
+            .map_err(dds_error_map("Dds: Failed to create participant"))?;
+        let topic = CuDdsSample::create_topic(&participant, Some(self.topic.as_str()), None, None)
+            .map_err(dds_error_map("Dds: Failed to create topic"))?;
+        Ok((participant, topic))
+    }
+}
+
+fn dds_error_map<E: std::fmt::Debug>(msg: &str) -> impl FnOnce(E) -> CuError + '_ {
+    move |e| CuError::from(format!("{msg}: {e:?}"))
+}
+
+pub struct DdsPublisherContext {
+    _participant: DdsParticipant,
+    writer: DdsWriter<CuDdsSample>,
+}
+
+/// This is a sink task that publishes messages to a DDS topic.
+/// P is the payload type of the messages.
+pub struct DdsPublisher<P>
+where
+    P: CuMsgPayload,
+{
+    _marker: PhantomData<P>,
+    config: DdsEndpointConfig,
+    ctx: Option<DdsPublisherContext>,
+}
+
+impl<P> Freezable for DdsPublisher<P> where P: CuMsgPayload {}
+
+impl<'cl, P> CuSinkTask<'cl> for DdsPublisher<P>
+where
+    P: CuMsgPayload + 'cl + 'static,
+{
+    type Input = input_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            _marker: Default::default(),
+            config: DdsEndpointConfig::from_config(config)?,
+            ctx: None,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let (participant, topic) = self.config.open()?;
+        let writer = DdsWriter::create(&participant, topic, Some(self.config.qos.to_dds()?), None)
+            .map_err(dds_error_map("DdsPublisher: Failed to create writer"))?;
+        debug!("DdsPublisher: Publishing on {}", self.config.topic.as_str());
+        self.ctx = Some(DdsPublisherContext {
+            _participant: participant,
+            writer,
+        });
+        Ok(())
+    }
+
+    fn process(&mut self, _clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        let ctx = self
+            .ctx
+            .as_mut()
+            .ok_or_else(|| CuError::from("DdsPublisher: Context not found"))?;
+
+        let data = bincode::encode_to_vec(input, CuWireFormat::CONFIG).expect("Encoding failed");
+        ctx.writer
+            .write(Arc::new(CuDdsSample { data }))
+            .map_err(dds_error_map("DdsPublisher: Failed to write sample"))?;
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        // Dropping the entities deletes them in CycloneDDS.
+        self.ctx = None;
+        debug!("DdsPublisher: Stopped");
+        Ok(())
+    }
+}
+
+pub struct DdsSubscriberContext {
+    _participant: DdsParticipant,
+    reader: DdsReader<CuDdsSample>,
+    samples: SampleBuffer<CuDdsSample>,
+}
+
+/// This is a source task that receives messages from a DDS topic.
+/// P is the payload type of the messages, the publisher needs to be a [DdsPublisher] of the same P.
+pub struct DdsSubscriber<P>
+where
+    P: CuMsgPayload,
+{
+    _marker: PhantomData<P>,
+    config: DdsEndpointConfig,
+    ctx: Option<DdsSubscriberContext>,
+}
+
+impl<P> Freezable for DdsSubscriber<P> where P: CuMsgPayload {}
+
+impl<'cl, P> CuSrcTask<'cl> for DdsSubscriber<P>
+where
+    P: CuMsgPayload + 'cl + 'static,
+{
+    type Output = output_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            _marker: Default::default(),
+            config: DdsEndpointConfig::from_config(config)?,
+            ctx: None,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let (participant, topic) = self.config.open()?;
+        let reader = DdsReader::create(&participant, topic, Some(self.config.qos.to_dds()?), None)
+            .map_err(dds_error_map("DdsSubscriber: Failed to create reader"))?;
+        debug!(
+            "DdsSubscriber: Subscribed to {}",
+            self.config.topic.as_str()
+        );
+        self.ctx = Some(DdsSubscriberContext {
+            _participant: participant,
+            reader,
+            samples: SampleBuffer::new(SAMPLES_PER_CYCLE),
+        });
+        Ok(())
+    }
+
+    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let ctx = self
+            .ctx
+            .as_mut()
+            .ok_or_else(|| CuError::from("DdsSubscriber: Context not found"))?;
+
+        let taken = ctx
+            .reader
+            .take_now(&mut ctx.samples)
+            .map_err(dds_error_map("DdsSubscriber: Failed to take samples"))?;
+        let Some(sample) = ctx.samples.iter().take(taken).flatten().last() else {
+            new_msg.clear_payload();
+            return Ok(());
+        };
+        let (received, _): (CuMsg<P>, usize) =
+            bincode::decode_from_slice(&sample.data, CuWireFormat::CONFIG).map_err(|e| {
+                CuError::new_with_cause("DdsSubscriber: Failed to decode sample", e)
+            })?;
+        *new_msg.payload_mut() = received.payload().cloned();
+        new_msg.metadata.tov = received.metadata.tov;
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.ctx = None;
+        debug!("DdsSubscriber: Stopped");
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use cu29::prelude::*;
+use cyclonedds_rs::dds_api::{
+    dds_history_kind_DDS_HISTORY_KEEP_ALL, dds_history_kind_DDS_HISTORY_KEEP_LAST,
+    dds_reliability_kind_DDS_RELIABILITY_BEST_EFFORT,
+    dds_reliability_kind_DDS_RELIABILITY_RELIABLE,
+};
+use cyclonedds_rs::DdsQos;
+use std::time::Duration;
+
+/// How long a reliable writer can block when the history of a reader is full.
+const MAX_BLOCKING_TIME: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reliability {
+    Reliable,
+    BestEffort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum History {
+    KeepLast(u32),
+    KeepAll,
+}
+
+/// The QoS of a DDS endpoint, both sides of a topic need compatible ones to be matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QosConfig {
+    pub reliability: Reliability,
+    pub history: History,
+    /// A sample is expected at least at this period.
+    pub deadline: Option<Duration>,
+}
+
+/// The QoS keys of the ComponentConfig.
+#[derive(CuConfigStruct)]
+struct QosKeys {
+    #[config(default = "reliable")]
+    reliability: String,
+    #[config(default = "keep_last")]
+    history: String,
+    #[config(default = 1, range = 1..)]
+    history_depth: u32,
+    #[config(range = 1..)]
+    deadline_ms: Option<u32>,
+}
+
+impl QosConfig {
+    pub fn from_config(config: Option<&ComponentConfig>) -> CuResult<Self> {
+        let QosKeys {
+            reliability,
+            history,
+            history_depth,
+            deadline_ms,
+        } = QosKeys::from_config(config)?;
+        let reliability = match reliability.as_str() {
+            "reliable" => Reliability::Reliable,
+            "best_effort" => Reliability::BestEffort,
+            other => {
+                return Err(format!(
+                    "Dds: the reliability should be reliable or best_effort, got {other}."
+                )
+                .into())
+            }
+        };
+        let history = match history.as_str() {
+            "keep_last" => History::KeepLast(history_depth),
+            "keep_all" => History::KeepAll,
+            other => {
+                return Err(format!(
+                    "Dds: the history should be keep_last or keep_all, got {other}."
+                )
+                .into())
+            }
+        };
+        Ok(Self {
+            reliability,
+            history,
+            deadline: deadline_ms.map(|ms| Duration::from_millis(ms as u64)),
+        })
+    }
+
+    pub fn to_dds(&self) -> CuResult<DdsQos> {
+        let qos = DdsQos::create()
+            .map_err(|e| CuError::from(format!("Dds: Failed to create the QoS: {e:?}")))?;
+        let qos = match self.reliability {
+            Reliability::Reliable => qos.set_reliability(
+                &dds_reliability_kind_DDS_RELIABILITY_RELIABLE,
+                MAX_BLOCKING_TIME,
+            ),
+            Reliability::BestEffort => qos.set_reliability(
+                &dds_reliability_kind_DDS_RELIABILITY_BEST_EFFORT,
+                MAX_BLOCKING_TIME,
+            ),
+        };
+        let qos = match self.history {
+            History::KeepLast(depth) => {
+                qos.set_history(&dds_history_kind_DDS_HISTORY_KEEP_LAST, depth as i32)
+            }
+            History::KeepAll => qos.set_history(&dds_history_kind_DDS_HISTORY_KEEP_ALL, 0),
+        };
+        Ok(match self.deadline {
+            Some(deadline) => qos.set_deadline(deadline),
+            None => qos,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu29::config::Value;
+
+    fn config(entries: &[(&str, Value)]) -> ComponentConfig {
+        let mut config = ComponentConfig::new();
+        for (key, value) in entries {
+            config.set(key, value.clone());
+        }
+        config
+    }
+
+    #[test]
+    fn test_qos_defaults() {
+        let qos = QosConfig::from_config(None).unwrap();
+        assert_eq!(qos.reliability, Reliability::Reliable);
+        assert_eq!(qos.history, History::KeepLast(1));
+        assert_eq!(qos.deadline, None);
+    }
+
+    #[test]
+    fn test_qos_from_config() {
+        let qos = QosConfig::from_config(Some(&config(&[
+            ("reliability", "best_effort".to_string().into()),
+            ("history_depth", 10u32.into()),
+            ("deadline_ms", 50u32.into()),
+        ])))
+        .unwrap();
+        assert_eq!(qos.reliability, Reliability::BestEffort);
+        assert_eq!(qos.history, History::KeepLast(10));
+        assert_eq!(qos.deadline, Some(Duration::from_millis(50)));
+
+        let qos =
+            QosConfig::from_config(Some(&config(&[("history", "keep_all".to_string().into())])))
+                .unwrap();
+        assert_eq!(qos.history, History::KeepAll);
+
+        let error = QosConfig::from_config(Some(&config(&[(
+            "reliability",
+            "sometimes".to_string().into(),
+        )])))
+        .unwrap_err();
+        assert!(error.to_string().contains("got sometimes"));
+        assert!(QosConfig::from_config(Some(&config(&[("history_depth", 0u32.into())]))).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+//! Uploads the logs of a robot in the background: the slabs of the unified log are sent to an
+//! HTTP server or a directory as soon as they are complete, with resumable and bandwidth limited
+//! transfers, and deleted locally once the destination has them.
+//!
+//! The uploader is configured in the logging section of the configuration:
+//!
+//! ```ron
+//! logging: (
+//!     slab_size_mib: 64,
+//!     upload: (url: "https://logs.example.com/robots/r2", bandwidth_kib_s: 512),
+//! ),
+//! ```
+//!
+//! It learns that a slab is complete from the storage of the log, see [LogUploader::storage].
+//! The logs already in the directory when it starts are from previous runs: they are uploaded
+//! too, so the slabs left when the robot stopped are sent at the next start.
+
+mod target;
+
+pub use target::*;
+
+use cu29::prelude::*;
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The size of the chunks the slabs are sent by.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How long the uploader waits before trying again after a failure.
+const RETRY_PERIOD: Duration = Duration::from_secs(10);
+
+/// The extension of the slabs picked up from the previous runs.
+const LOG_EXTENSION: &str = "copper";
+
+/// The uploader, running in its own thread until it is dropped.
+/// The slabs not uploaded when it is dropped are left in place for the next run.
+pub struct LogUploader {
+    sender: Sender<UploadJob>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl LogUploader {
+    /// Starts the uploader of the logging section of the configuration, if it has one.
+    /// `log_dir` is the directory of the logs, the slabs found there are from the previous runs.
+    pub fn from_config(config: &CuConfig, log_dir: &Path) -> CuResult<Option<Self>> {
+        let Some(upload) = config
+            .logging
+            .as_ref()
+            .and_then(|logging| logging.upload.as_ref())
+        else {
+            return Ok(None);
+        };
+        let target = target_from_url(&upload.url)?;
+        Self::start(target, upload, log_dir).map(Some)
+    }
+
+    /// Starts an uploader to a custom destination.
+    pub fn start(
+        target: Box<dyn UploadTarget>,
+        config: &UploadConfig,
+        log_dir: &Path,
+    ) -> CuResult<Self> {
+        let mut pending = std::fs::read_dir(log_dir)
+            .map_err(|e| {
+                CuError::new_with_cause(
+                    &format!("Log upload: could not list {}", log_dir.display()),
+                    e,
+                )
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension() == Some(OsStr::new(LOG_EXTENSION)))
+            .collect::<Vec<_>>();
+        pending.sort();
+
+        let (sender, receiver) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut worker = UploadWorker {
+            target,
+            bytes_per_s: config.bandwidth_kib_s.map(|kib| kib * 1024),
+            delete_after_upload: config.delete_after_upload,
+            queue: pending.into(),
+            receiver,
+            stop: stop.clone(),
+        };
+        let worker = std::thread::Builder::new()
+            .name("log_upload".to_string())
+            .spawn(move || worker.run())
+            .map_err(|e| CuError::new_with_cause("Log upload: could not start", e))?;
+        Ok(Self {
+            sender,
+            stop,
+            worker: Some(worker),
+        })
+    }
+
+    /// The storage of a log, `base_file_path` being in the directory of the logs: the slabs are the
+    /// usual memory mapped files, queued for the upload when they are closed.
+    /// Give a distinct name to the log of every run, the slabs of a log are overwritten by the
+    /// next one with the same name.
+    pub fn storage(&self, base_file_path: &Path) -> UploadSlabStorage {
+        UploadSlabStorage {
+            files: FileSlabStorage::new(base_file_path),
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Queues a file to upload.
+    pub fn upload(&self, path: &Path) {
+        let _ = self.sender.send(UploadJob::Upload(path.to_path_buf()));
+    }
+}
+
+impl Drop for LogUploader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.sender.send(UploadJob::Stop);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The file storage of a log, queuing its slabs for the upload once closed.
+pub struct UploadSlabStorage {
+    files: FileSlabStorage,
+    sender: Sender<UploadJob>,
+}
+
+impl SlabStorage for UploadSlabStorage {
+    fn create_slab(&mut self, index: usize, size: usize) -> std::io::Result<Box<dyn LogSlab>> {
+        Ok(Box::new(UploadSlab {
+            slab: self.files.create_slab(index, size)?,
+            path: self.files.slab_path(index),
+            sender: self.sender.clone(),
+        }))
+    }
+
+    fn remove_slab(&mut self, index: usize) -> std::io::Result<()> {
+        self.files.remove_slab(index)
+    }
+
+    /// The uploader deletes the slabs it sent, a full log blocking on its storage waits for it.
+    fn is_slab_removed(&self, index: usize) -> bool {
+        self.files.is_slab_removed(index)
+    }
+}
+
+struct UploadSlab {
+    slab: Box<dyn LogSlab>,
+    path: PathBuf,
+    sender: Sender<UploadJob>,
+}
+
+impl LogSlab for UploadSlab {
+    fn buffer(&mut self) -> &mut [u8] {
+        self.slab.buffer()
+    }
+
+    fn flush(&mut self, range: std::ops::Range<usize>) -> std::io::Result<()> {
+        self.slab.flush(range)
+    }
+
+    fn close(&mut self, used: usize) -> std::io::Result<()> {
+        self.slab.close(used)?;
+        // The uploader might be gone, the slab will be picked up by the next one.
+        let _ = self.sender.send(UploadJob::Upload(self.path.clone()));
+        Ok(())
+    }
+}
+
+enum UploadJob {
+    Upload(PathBuf),
+    Stop,
+}
+
+struct UploadWorker {
+    target: Box<dyn UploadTarget>,
+    bytes_per_s: Option<u64>,
+    delete_after_upload: bool,
+    queue: VecDeque<PathBuf>,
+    receiver: Receiver<UploadJob>,
+    stop: Arc<AtomicBool>,
+}
+
+impl UploadWorker {
+    fn run(&mut self) {
+        loop {
+            while let Ok(job) = self.receiver.try_recv() {
+                if !self.queue_job(job) {
+                    return;
+                }
+            }
+            let Some(path) = self.queue.front().cloned() else {
+                let job = self.receiver.recv();
+                if job.is_ok_and(|job| self.queue_job(job)) {
+                    continue;
+                }
+                return;
+            };
+            if !path.exists() {
+                // Queued twice, or deleted in the meantime.
+                self.queue.pop_front();
+                continue;
+            }
+            match self.upload_file(&path) {
+                Ok(true) => {
+                    self.queue.pop_front();
+                    debug!("Log upload: {} uploaded.", path.display().to_string());
+                    if self.delete_after_upload {
+                        if let Err(e) = std::fs::remove_file(&path) {
+                            error!(
+                                "Log upload: could not delete {}: {}",
+                                path.display().to_string(),
+                                e.to_string()
+                            );
+                        }
+                    }
+                }
+                Ok(false) => return,
+                Err(e) => {
+                    error!(
+                        "Log upload: {} failed, retrying: {}",
+                        path.display().to_string(),
+                        e.to_string()
+                    );
+                    match self.receiver.recv_timeout(RETRY_PERIOD) {
+                        Ok(job) => {
+                            if !self.queue_job(job) {
+                                return;
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues the file of the job, false to stop.
+    fn queue_job(&mut self, job: UploadJob) -> bool {
+        match job {
+            UploadJob::Upload(path) => {
+                self.queue.push_back(path);
+                true
+            }
+            UploadJob::Stop => false,
+        }
+    }
+
+    /// Sends the rest of the file, true once the destination has it entirely, false if stopped.
+    fn upload_file(&mut self, path: &Path) -> CuResult<bool> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| CuError::from(format!("invalid file name {}", path.display())))?;
+        let read_error = |e| CuError::new_with_cause(&format!("could not read {name}"), e);
+        let mut file = File::open(path).map_err(read_error)?;
+        let total = file.metadata().map_err(read_error)?.len();
+
+        let mut offset = self.target.uploaded_len(name)?;
+        if offset > total {
+            return Err(CuError::from(format!(
+                "the destination has {offset} bytes of {name}, more than its {total} bytes"
+            )));
+        }
+        std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(offset)).map_err(read_error)?;
+        let mut chunk = vec![0u8; self.chunk_size()];
+        while offset < total {
+            if self.stop.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            let start = Instant::now();
+            let len = (total - offset).min(chunk.len() as u64) as usize;
+            file.read_exact(&mut chunk[..len]).map_err(read_error)?;
+            self.target
+                .upload_chunk(name, offset, &chunk[..len], total)?;
+            offset += len as u64;
+            if let Some(bytes_per_s) = self.bytes_per_s {
+                let budget = Duration::from_secs_f64(len as f64 / bytes_per_s as f64);
+                std::thread::sleep(budget.saturating_sub(start.elapsed()));
+            }
+        }
+        Ok(self.target.uploaded_len(name)? == total)
+    }
+
+    /// About a second of transfer at the bandwidth limit, up to [CHUNK_SIZE].
+    fn chunk_size(&self) -> usize {
+        self.bytes_per_s.map_or(CHUNK_SIZE, |bytes_per_s| {
+            (bytes_per_s as usize).clamp(1, CHUNK_SIZE)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_upload_to_directory() {
+        let tmp_dir = TempDir::new().unwrap();
+        let (log_dir, destination) = (tmp_dir.path().join("logs"), tmp_dir.path().join("dest"));
+        std::fs::create_dir_all(&log_dir).unwrap();
+        // A slab left by a previous run, half uploaded.
+        let previous: Vec<u8> = (0..3_000_000u32).map(|i| i as u8).collect();
+        std::fs::write(log_dir.join("previous_0.copper"), &previous).unwrap();
+        let mut target = DirectoryTarget::new(&destination).unwrap();
+        target
+            .upload_chunk("previous_0.copper", 0, &previous[..1_000_000], 3_000_000)
+            .unwrap();
+
+        let config = UploadConfig {
+            url: format!("file://{}", destination.display()),
+            bandwidth_kib_s: None,
+            delete_after_upload: true,
+        };
+        let uploader = LogUploader::start(Box::new(target), &config, &log_dir).unwrap();
+        {
+            let UnifiedLogger::Write(logger) = UnifiedLoggerBuilder::new()
+                .write(true)
+                .create(true)
+                .storage(uploader.storage(&log_dir.join("run.copper")))
+                .preallocated_size(100 * 1024)
+                .build()
+                .unwrap()
+            else {
+                panic!("Failed to create logger");
+            };
+            let logger = Arc::new(std::sync::Mutex::new(logger));
+            let mut stream = stream_write(logger, UnifiedLogType::StructuredLogLine, 4096);
+            for i in 0..100_000u32 {
+                stream.log(&i).unwrap();
+            }
+        }
+
+        let uploaded = |name: &str| destination.join(name).exists() && !log_dir.join(name).exists();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !(uploaded("previous_0.copper")
+            && uploaded("run_0.copper")
+            && uploaded("run_1.copper"))
+        {
+            assert!(Instant::now() < deadline, "the slabs were not uploaded");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            std::fs::read(destination.join("previous_0.copper")).unwrap(),
+            previous
+        );
+        drop(uploader);
+    }
+}
@@ -0,0 +1,120 @@
+//! The destinations of the uploads.
+
+use cu29::prelude::*;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A destination of the slabs. The uploads are resumable: a slab is sent by chunks appended to
+/// what the destination already has of it, after an interruption the upload restarts from there.
+pub trait UploadTarget: Send {
+    /// How many bytes of the file `name` the destination has, 0 if it doesn't know it.
+    fn uploaded_len(&mut self, name: &str) -> CuResult<u64>;
+
+    /// Writes `chunk` at `offset` in the file `name` of `total` bytes.
+    fn upload_chunk(&mut self, name: &str, offset: u64, chunk: &[u8], total: u64) -> CuResult<()>;
+}
+
+/// The destination of a url of the configuration, see [cu29::config::UploadConfig].
+pub fn target_from_url(url: &str) -> CuResult<Box<dyn UploadTarget>> {
+    if let Some(path) = url.strip_prefix("file://") {
+        Ok(Box::new(DirectoryTarget::new(Path::new(path))?))
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(Box::new(HttpTarget::new(url)))
+    } else {
+        Err(CuError::from(format!(
+            "Log upload: unsupported url \"{url}\", expected http://, https:// or file://."
+        )))
+    }
+}
+
+/// Copies the slabs to a directory, a share mounted over NFS or SFTP (sshfs) for example.
+pub struct DirectoryTarget {
+    directory: PathBuf,
+}
+
+impl DirectoryTarget {
+    pub fn new(directory: &Path) -> CuResult<Self> {
+        std::fs::create_dir_all(directory).map_err(|e| {
+            CuError::new_with_cause(
+                &format!("Log upload: could not create {}", directory.display()),
+                e,
+            )
+        })?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+        })
+    }
+}
+
+impl UploadTarget for DirectoryTarget {
+    fn uploaded_len(&mut self, name: &str) -> CuResult<u64> {
+        match std::fs::metadata(self.directory.join(name)) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(CuError::new_with_cause("Log upload: could not stat", e)),
+        }
+    }
+
+    fn upload_chunk(&mut self, name: &str, offset: u64, chunk: &[u8], _total: u64) -> CuResult<()> {
+        let write = || -> std::io::Result<()> {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(self.directory.join(name))?;
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(chunk)?;
+            file.sync_data()
+        };
+        write().map_err(|e| CuError::new_with_cause("Log upload: could not write", e))
+    }
+}
+
+/// PUTs the slabs under a base url, by chunks with a `Content-Range: bytes first-last/total`
+/// header. A HEAD on the slab gives what the server has of it in its `Content-Length`, the
+/// server answering 404 for the slabs it doesn't know.
+pub struct HttpTarget {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpTarget {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .timeout_read(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn url(&self, name: &str) -> String {
+        format!("{}/{name}", self.base_url)
+    }
+}
+
+impl UploadTarget for HttpTarget {
+    fn uploaded_len(&mut self, name: &str) -> CuResult<u64> {
+        match self.agent.head(&self.url(name)).call() {
+            Ok(response) => Ok(response
+                .header("Content-Length")
+                .and_then(|len| len.parse().ok())
+                .unwrap_or(0)),
+            Err(ureq::Error::Status(404, _)) => Ok(0),
+            Err(e) => Err(CuError::new_with_cause("Log upload: HEAD failed", e)),
+        }
+    }
+
+    fn upload_chunk(&mut self, name: &str, offset: u64, chunk: &[u8], total: u64) -> CuResult<()> {
+        let last = offset + chunk.len() as u64 - 1;
+        self.agent
+            .put(&self.url(name))
+            .set("Content-Range", &format!("bytes {offset}-{last}/{total}"))
+            .send_bytes(chunk)
+            .map_err(|e| CuError::new_with_cause("Log upload: PUT failed", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Maps the timestamps of a device to the Copper robot time.
+//!
+//! A source pairs each device timestamp with the robot time at which the packet was received with
+//! [`DeviceClock::observe`] then converts the timestamps of its data with [`DeviceClock::to_robot_time`].
+use cu29_clock::{CuDuration, CuTime};
+use std::collections::VecDeque;
+
+/// Below this span of device time the drift cannot be told apart from the jitter, the clocks are assumed to tick at the same rate.
+const MIN_FIT_SPAN_NS: f64 = 1e9;
+
+/// Even a cheap crystal stays well within this.
+const MAX_DRIFT: f64 = 1e-3;
+
+/// The line fitted between the device time and the robot time, around the last sample.
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    device: i128,
+    robot: u64,
+    slope: f64,
+    intercept: f64,
+}
+
+/// Tracks the offset and the drift of a device clock against the robot clock.
+///
+/// The slope of the mapping is a linear regression over a sliding window of (device, robot) pairs.
+/// As the transport latency can only delay a reception, the line is then lowered to pass under all the pairs:
+/// the fastest packets give the offset.
+#[derive(Debug, Clone)]
+pub struct DeviceClock {
+    window: usize,
+    rollover: Option<u64>,
+    /// The last device timestamp observed, raw and unwrapped.
+    last: Option<(u64, i128)>,
+    samples: VecDeque<(i128, u64)>,
+    fit: Option<Fit>,
+}
+
+impl DeviceClock {
+    /// Estimates over the last `window` observations.
+    pub fn new(window: usize) -> Self {
+        DeviceClock {
+            window: window.max(1),
+            rollover: None,
+            last: None,
+            samples: VecDeque::with_capacity(window.max(1)),
+            fit: None,
+        }
+    }
+
+    /// The device counter wraps after this period, ie. 1h for the Velodyne timestamps.
+    pub fn with_rollover(mut self, period: CuDuration) -> Self {
+        self.rollover = Some(period.as_nanos()).filter(|p| *p > 0);
+        self
+    }
+
+    /// Forgets everything, ie. when the device is restarted.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.samples.clear();
+        self.fit = None;
+    }
+
+    /// True once at least one observation was made.
+    pub fn is_synced(&self) -> bool {
+        self.fit.is_some()
+    }
+
+    /// How much faster the device clock runs than the robot clock, in parts per million.
+    pub fn drift_ppm(&self) -> f64 {
+        self.fit.map_or(0.0, |fit| (1.0 / fit.slope - 1.0) * 1e6)
+    }
+
+    /// Records that the data stamped `device` by the device was received at `robot`.
+    /// A device clock going backwards means the device restarted and the estimation starts over.
+    pub fn observe(&mut self, device: CuDuration, robot: CuTime) {
+        let raw = device.as_nanos();
+        let unwrapped = self.unwrap(raw);
+        if matches!(self.last, Some((_, last)) if unwrapped < last) {
+            self.reset();
+            self.observe(device, robot);
+            return;
+        }
+        self.last = Some((raw, unwrapped));
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((unwrapped, robot.as_nanos()));
+        self.fit = Some(self.fit_samples());
+    }
+
+    /// The robot time of a device timestamp, None before the first observation.
+    /// With a rollover, the timestamp is taken as the closest one to the last observation.
+    pub fn to_robot_time(&self, device: CuDuration) -> Option<CuTime> {
+        let fit = self.fit?;
+        let x = (self.unwrap(device.as_nanos()) - fit.device) as f64;
+        let robot = fit.robot as f64 + fit.intercept + fit.slope * x;
+        Some(CuDuration(robot.max(0.0).round() as u64))
+    }
+
+    fn unwrap(&self, raw: u64) -> i128 {
+        match (self.rollover, self.last) {
+            (Some(period), Some((last_raw, last))) => {
+                let period = period as i128;
+                let mut delta = (raw as i128 - last_raw as i128).rem_euclid(period);
+                if delta > period / 2 {
+                    delta -= period;
+                }
+                last + delta
+            }
+            _ => raw as i128,
+        }
+    }
+
+    fn fit_samples(&self) -> Fit {
+        let &(device, robot) = self.samples.back().expect("at least one sample");
+        // Centered on the last sample to keep the precision of the f64s.
+        let points = || {
+            self.samples
+                .iter()
+                .map(move |&(d, r)| ((d - device) as f64, r as f64 - robot as f64))
+        };
+        let n = self.samples.len() as f64;
+        let (mean_x, mean_y) =
+            points().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x / n, sy + y / n));
+        let (cov, var) = points().fold((0.0, 0.0), |(cov, var), (x, y)| {
+            (
+                cov + (x - mean_x) * (y - mean_y),
+                var + (x - mean_x) * (x - mean_x),
+            )
+        });
+        let span = points().map(|(x, _)| -x).fold(0.0, f64::max);
+        let slope = if span >= MIN_FIT_SPAN_NS && var > 0.0 {
+            (cov / var).clamp(1.0 - MAX_DRIFT, 1.0 + MAX_DRIFT)
+        } else {
+            1.0
+        };
+        let intercept = points()
+            .map(|(x, y)| y - slope * x)
+            .fold(f64::INFINITY, f64::min);
+        Fit {
+            device,
+            robot,
+            slope,
+            intercept,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small deterministic generator for the jitter of the receptions.
+    struct Jitter(u64);
+
+    impl Jitter {
+        /// Between 0 and max_ns, usually small with a few late packets.
+        fn next(&mut self, max_ns: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let uniform = (self.0 >> 33) % 1000;
+            let late = if uniform > 950 { max_ns } else { max_ns / 10 };
+            (self.0 >> 20) % late.max(1)
+        }
+    }
+
+    const MS: u64 = 1_000_000;
+
+    fn assert_close(actual: CuTime, expected: u64, tolerance: u64) {
+        assert!(
+            actual.as_nanos().abs_diff(expected) <= tolerance,
+            "{} vs {} (+/- {})",
+            actual.as_nanos(),
+            expected,
+            tolerance
+        );
+    }
+
+    #[test]
+    fn test_offset_with_jitter() {
+        let mut clock = DeviceClock::new(64);
+        let mut jitter = Jitter(42);
+        assert!(clock.to_robot_time(CuDuration(0)).is_none());
+        // The device started 3s before the robot clock.
+        for i in 0..200u64 {
+            let device = 3_000 * MS + i * 10 * MS;
+            let robot = i * 10 * MS + 100_000 + jitter.next(5 * MS);
+            clock.observe(CuDuration(device), CuDuration(robot));
+        }
+        let mapped = clock
+            .to_robot_time(CuDuration(3_000 * MS + 2_000 * MS))
+            .unwrap();
+        // the latency floor of 100us is part of the offset.
+        assert_close(mapped, 2_000 * MS + 100_000, 200_000);
+        assert!(clock.drift_ppm().abs() < 50.0);
+    }
+
+    #[test]
+    fn test_drift_with_jitter() {
+        let mut clock = DeviceClock::new(128);
+        let mut jitter = Jitter(7);
+        // The device clock runs 200ppm fast.
+        let device_of = |robot: u64| robot + robot / 5_000;
+        for i in 0..1000u64 {
+            let robot = i * 20 * MS;
+            clock.observe(
+                CuDuration(device_of(robot)),
+                CuDuration(robot + jitter.next(2 * MS)),
+            );
+        }
+        assert!(
+            (clock.drift_ppm() - 200.0).abs() < 20.0,
+            "{}",
+            clock.drift_ppm()
+        );
+        // 1s after the last observation, the drift is extrapolated.
+        let robot = 1000 * 20 * MS;
+        assert_close(
+            clock.to_robot_time(CuDuration(device_of(robot))).unwrap(),
+            robot,
+            200_000,
+        );
+    }
+
+    #[test]
+    fn test_rollover() {
+        const HOUR: u64 = 3_600_000 * MS;
+        let mut clock = DeviceClock::new(32).with_rollover(CuDuration(HOUR));
+        let start = HOUR - 500 * MS;
+        for i in 0..100u64 {
+            let device = (start + i * 10 * MS) % HOUR;
+            clock.observe(CuDuration(device), CuDuration(10_000 * MS + i * 10 * MS));
+        }
+        assert!(clock.drift_ppm().abs() < 1.0);
+        // After the wrap.
+        assert_close(
+            clock.to_robot_time(CuDuration(995 * MS)).unwrap(),
+            10_000 * MS + 1_495 * MS,
+            1000,
+        );
+        // A point stamped just before the wrap, relative to the last packet.
+        assert_close(
+            clock.to_robot_time(CuDuration(HOUR - 100 * MS)).unwrap(),
+            10_000 * MS + 400 * MS,
+            1000,
+        );
+    }
+
+    #[test]
+    fn test_device_restart() {
+        let mut clock = DeviceClock::new(16);
+        for i in 0..10u64 {
+            clock.observe(CuDuration(5_000 * MS + i * MS), CuDuration(i * MS));
+        }
+        // Restarted, its clock starts over.
+        clock.observe(CuDuration(0), CuDuration(20 * MS));
+        assert_close(clock.to_robot_time(CuDuration(MS)).unwrap(), 21 * MS, 0);
+    }
+}
@@ -39,9 +39,10 @@ use tui_widgets::scrollview::{ScrollView, ScrollViewState};
 
 #[cfg(feature = "debug_pane")]
 const MENU_CONTENT: &str =
-    "   [1] SysInfo  [2] DAG  [3] Latencies  [4] Memory Pools [5] Debug Output  [q] Quit   ";
+    "   [1] SysInfo  [2] DAG  [3] Latencies  [4] Memory Pools [5] Tasks [6] Debug Output  [q] Quit   ";
 #[cfg(not(feature = "debug_pane"))]
-const MENU_CONTENT: &str = "   [1] SysInfo  [2] DAG  [3] Latencies  [4] Memory Pools [q] Quit   ";
+const MENU_CONTENT: &str =
+    "   [1] SysInfo  [2] DAG  [3] Latencies  [4] Memory Pools [5] Tasks [q] Quit   ";
 
 #[derive(PartialEq)]
 enum Screen {
@@ -51,6 +52,7 @@ enum Screen {
     #[cfg(feature = "debug_pane")]
     DebugOutput,
     MemoryPools,
+    Tasks,
 }
 
 struct TaskStats {
@@ -182,6 +184,60 @@ struct TaskStatus {
     is_error: bool,
     status_txt: CompactString,
     error: CompactString,
+    error_count: u64,
+    last_error: CompactString,
+    last_exec: CuDuration,
+    exec_count: u64,
+    exec_rate: f32,
+    rate_window: Option<(Instant, u64)>,
+}
+
+impl TaskStatus {
+    fn record_execution(&mut self, duration: CuDuration) {
+        let now = Instant::now();
+        self.last_exec = duration;
+        self.exec_count += 1;
+
+        let (window_start, window_count) = *self.rate_window.get_or_insert((now, self.exec_count));
+        let elapsed = now.duration_since(window_start).as_secs_f32();
+        if elapsed >= 1.0 {
+            self.exec_rate = (self.exec_count - window_count) as f32 / elapsed;
+            self.rate_window = Some((now, self.exec_count));
+        }
+    }
+
+    fn record_error(&mut self, error: &CuError) {
+        self.is_error = true;
+        self.error = error.to_compact_string();
+        self.last_error = self.error.clone();
+        self.error_count += 1;
+    }
+}
+
+/// A connection of the task graph as displayed in the Tasks screen.
+struct ConnectionInfo {
+    src_index: usize,
+    src_id: String,
+    dst_id: String,
+    msg: String,
+}
+
+fn collect_connections(config: &CuConfig) -> Vec<ConnectionInfo> {
+    let graph = config
+        .get_graph(None)
+        .expect("Only supported for simple config"); // FIXME(gbin): Multimission
+    graph
+        .edge_indices()
+        .filter_map(|edge| {
+            let (src, dst) = graph.edge_endpoints(edge)?;
+            Some(ConnectionInfo {
+                src_index: src.index(),
+                src_id: graph[src].get_id(),
+                dst_id: graph[dst].get_id(),
+                msg: graph[edge].msg.clone(),
+            })
+        })
+        .collect()
 }
 
 struct NodesScrollableWidgetState {
@@ -346,6 +402,8 @@ struct UI {
     active_screen: Screen,
     sysinfo: String,
     task_stats: Arc<Mutex<TaskStats>>,
+    task_statuses: Arc<Mutex<Vec<TaskStatus>>>,
+    connections: Vec<ConnectionInfo>,
     nodes_scrollable_widget_state: NodesScrollableWidgetState,
     #[cfg(feature = "debug_pane")]
     error_redirect: gag::BufferRedirect,
@@ -374,6 +432,8 @@ impl UI {
             active_screen: Screen::Neofetch,
             sysinfo: sysinfo::pfetch_info(),
             task_stats,
+            task_statuses,
+            connections: collect_connections(&config),
             nodes_scrollable_widget_state,
             error_redirect,
             debug_output,
@@ -398,6 +458,8 @@ impl UI {
             active_screen: Screen::Neofetch,
             sysinfo: sysinfo::pfetch_info(),
             task_stats,
+            task_statuses,
+            connections: collect_connections(&config),
             nodes_scrollable_widget_state,
             pool_stats,
         }
@@ -602,6 +664,100 @@ impl UI {
         f.render_widget(table, area);
     }
 
+    fn draw_tasks(&self, f: &mut Frame, area: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(area);
+
+        let header_style = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        let task_header = Row::new(
+            ["🛠 Task", "Last Exec", "Runs/s", "Errors", "Last Error"]
+                .iter()
+                .map(|h| Cell::from(*h).style(header_style)),
+        )
+        .bottom_margin(1);
+
+        let task_statuses = self.task_statuses.lock().unwrap();
+        let task_rows = task_statuses
+            .iter()
+            .enumerate()
+            .map(|(i, status)| {
+                let errors = Cell::from(Line::from(status.error_count.to_string()));
+                let errors = if status.error_count > 0 {
+                    errors.light_red()
+                } else {
+                    errors
+                };
+                Row::new(vec![
+                    Cell::from(Line::from(self.task_ids[i])).light_blue(),
+                    Cell::from(Line::from(status.last_exec.to_string())),
+                    Cell::from(Line::from(format!("{:.1}", status.exec_rate))),
+                    errors,
+                    Cell::from(Line::from(status.last_error.to_string())).red(),
+                ])
+            })
+            .collect::<Vec<Row>>();
+
+        let task_table = Table::new(
+            task_rows,
+            &[
+                Constraint::Length(20),
+                Constraint::Length(12),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Min(20),
+            ],
+        )
+        .header(task_header)
+        .block(Block::default().borders(Borders::ALL).title(" Tasks "));
+        f.render_widget(task_table, layout[0]);
+
+        let cnx_header = Row::new(
+            ["Source", "Destination", "Message", "Msgs/s"]
+                .iter()
+                .map(|h| Cell::from(*h).style(header_style)),
+        )
+        .bottom_margin(1);
+
+        // A connection carries one message per execution of its source task.
+        let cnx_rows = self
+            .connections
+            .iter()
+            .map(|cnx| {
+                let rate = task_statuses
+                    .get(cnx.src_index)
+                    .map(|s| s.exec_rate)
+                    .unwrap_or_default();
+                Row::new(vec![
+                    Cell::from(Line::from(cnx.src_id.as_str())).light_blue(),
+                    Cell::from(Line::from(cnx.dst_id.as_str())).light_blue(),
+                    Cell::from(Line::from(cnx.msg.as_str())),
+                    Cell::from(Line::from(format!("{rate:.1}")).alignment(Alignment::Right)),
+                ])
+            })
+            .collect::<Vec<Row>>();
+
+        let cnx_table = Table::new(
+            cnx_rows,
+            &[
+                Constraint::Length(20),
+                Constraint::Length(20),
+                Constraint::Min(20),
+                Constraint::Length(10),
+            ],
+        )
+        .header(cnx_header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Connections "),
+        );
+        f.render_widget(cnx_table, layout[1]);
+    }
+
     fn draw_nodes(&mut self, f: &mut Frame, space: Rect) {
         NodesScrollableWidget {
             _marker: Default::default(),
@@ -652,6 +808,7 @@ impl UI {
             }
             Screen::Latency => self.draw_latency_table(f, layout[1]),
             Screen::MemoryPools => self.draw_memory_pools(f, layout[1]),
+            Screen::Tasks => self.draw_tasks(f, layout[1]),
             #[cfg(feature = "debug_pane")]
             Screen::DebugOutput => self.draw_debug_output(f, layout[1]),
         };
@@ -675,8 +832,9 @@ impl UI {
                         KeyCode::Char('2') => self.active_screen = Screen::Dag,
                         KeyCode::Char('3') => self.active_screen = Screen::Latency,
                         KeyCode::Char('4') => self.active_screen = Screen::MemoryPools,
+                        KeyCode::Char('5') => self.active_screen = Screen::Tasks,
                         #[cfg(feature = "debug_pane")]
-                        KeyCode::Char('5') => self.active_screen = Screen::DebugOutput,
+                        KeyCode::Char('6') => self.active_screen = Screen::DebugOutput,
                         KeyCode::Char('r') => {
                             if self.active_screen == Screen::Latency {
                                 self.task_stats.lock().unwrap().reset()
@@ -848,6 +1006,9 @@ impl CuMonitor for CuConsoleMon {
         {
             let mut task_statuses = self.task_statuses.lock().unwrap();
             for (i, msg) in msgs.iter().enumerate() {
+                task_statuses[i].record_execution(
+                    msg.process_time.end.unwrap() - msg.process_time.start.unwrap(),
+                );
                 let CuCompactString(status_txt) = &msg.status_txt;
                 task_statuses[i].status_txt = status_txt.clone();
                 if task_statuses[i].status_txt.as_bytes()[0] == 0 {
@@ -879,11 +1040,7 @@ impl CuMonitor for CuConsoleMon {
     }
 
     fn process_error(&self, taskid: usize, step: CuTaskState, error: &CuError) -> Decision {
-        {
-            let status = &mut self.task_statuses.lock().unwrap()[taskid];
-            status.is_error = true;
-            status.error = error.to_compact_string();
-        }
+        self.task_statuses.lock().unwrap()[taskid].record_error(error);
         match step {
             CuTaskState::Start => Decision::Shutdown,
             CuTaskState::Preprocess => Decision::Abort,
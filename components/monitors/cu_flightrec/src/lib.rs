@@ -0,0 +1,313 @@
+//! A monitor recording the timings of the tasks at every copperlist and their errors, to a CSV file or
+//! an SQLite database of bounded size, to analyze a run in a spreadsheet.
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use cu29::prelude::*;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What the monitor reads from the config of the monitor section.
+#[derive(CuConfigStruct)]
+struct FlightRecorderConfig {
+    #[config(default = "flightrec.csv")]
+    path: String,
+    format: Option<String>,
+    #[config(default = 100000, range = 1..)]
+    max_rows: u64,
+}
+
+/// One row of the recorder: the execution of a task in a copperlist, an error or an event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row<'a> {
+    /// Start of the process of the task or time of the error.
+    pub time: CuTime,
+    /// None for the errors.
+    pub culist_id: Option<u32>,
+    /// The name of the event for the events.
+    pub task: &'a str,
+    pub step: &'a str,
+    pub duration: Option<CuDuration>,
+    pub validity: Option<String>,
+    /// The status of the task or the error.
+    pub message: String,
+}
+
+/// A storage of the rows, keeping at most `max_rows` of them.
+pub trait Recorder: Send {
+    /// Records the rows of one copperlist.
+    fn record(&mut self, rows: &[Row]) -> CuResult<()>;
+
+    fn flush(&mut self) -> CuResult<()> {
+        Ok(())
+    }
+}
+
+pub const CSV_HEADER: &str = "time_ns,culist,task,step,duration_ns,validity,message";
+
+/// Writes the rows to a CSV file. When it reaches `max_rows`, the file is moved to `<path>.1`
+/// (replacing the previous one) and a new one is started, so the disk holds at most twice `max_rows`.
+pub struct CsvRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    rows: u64,
+    max_rows: u64,
+}
+
+impl CsvRecorder {
+    pub fn new(path: &Path, max_rows: u64) -> CuResult<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Self::create(path)?,
+            rows: 0,
+            max_rows,
+        })
+    }
+
+    fn create(path: &Path) -> CuResult<BufWriter<File>> {
+        let file = File::create(path).map_err(|e| {
+            CuError::new_with_cause(
+                &format!("FlightRecorder: Failed to create {}", path.display()),
+                e,
+            )
+        })?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{CSV_HEADER}").map_err(|e| write_error(path, e))?;
+        Ok(writer)
+    }
+
+    fn rotate(&mut self) -> CuResult<()> {
+        self.flush()?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, &rotated).map_err(|e| {
+            CuError::new_with_cause(
+                &format!("FlightRecorder: Failed to rotate {}", self.path.display()),
+                e,
+            )
+        })?;
+        self.writer = Self::create(&self.path)?;
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+impl Recorder for CsvRecorder {
+    fn record(&mut self, rows: &[Row]) -> CuResult<()> {
+        for row in rows {
+            if self.rows >= self.max_rows {
+                self.rotate()?;
+            }
+            writeln!(
+                self.writer,
+                "{},{},{},{},{},{},{}",
+                row.time.as_nanos(),
+                row.culist_id.map(|id| id.to_string()).unwrap_or_default(),
+                csv_field(row.task),
+                csv_field(row.step),
+                row.duration
+                    .map(|duration| duration.as_nanos().to_string())
+                    .unwrap_or_default(),
+                row.validity.as_deref().map(csv_field).unwrap_or_default(),
+                csv_field(&row.message),
+            )
+            .map_err(|e| write_error(&self.path, e))?;
+            self.rows += 1;
+        }
+        // One write per copperlist so a crash loses at most the last one.
+        self.flush()
+    }
+
+    fn flush(&mut self) -> CuResult<()> {
+        self.writer.flush().map_err(|e| write_error(&self.path, e))
+    }
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> CuError {
+    CuError::new_with_cause(
+        &format!("FlightRecorder: Failed to write to {}", path.display()),
+        e,
+    )
+}
+
+/// Quotes a field if needed, following RFC 4180.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// This is a monitor writing a row per task at every copperlist (the start and duration of its
+/// process, the validity and status of its message) and a row per error, to a CSV file or to an
+/// SQLite database with the `sqlite` feature. It doesn't change the decisions of the runtime:
+/// the errors are ignored like without monitor.
+pub struct CuFlightRecorder {
+    taskids: &'static [&'static str],
+    recorder: Mutex<Box<dyn Recorder>>,
+    clock: Option<RobotClock>,
+}
+
+impl CuFlightRecorder {
+    fn record(&self, rows: &[Row]) -> CuResult<()> {
+        self.recorder.lock().unwrap().record(rows)
+    }
+}
+
+impl CuMonitor for CuFlightRecorder {
+    fn new(config: &CuConfig, taskids: &'static [&'static str]) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let FlightRecorderConfig {
+            path,
+            format,
+            max_rows,
+        } = FlightRecorderConfig::from_config(
+            config
+                .get_monitor_config()
+                .and_then(|monitor| monitor.get_config()),
+        )?;
+        let path = PathBuf::from(path);
+        let format = format.unwrap_or_else(|| {
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some("db" | "sqlite" | "sqlite3") => "sqlite".to_string(),
+                _ => "csv".to_string(),
+            }
+        });
+        let recorder: Box<dyn Recorder> =
+            match format.as_str() {
+                "csv" => Box::new(CsvRecorder::new(&path, max_rows)?),
+                #[cfg(feature = "sqlite")]
+                "sqlite" => Box::new(sqlite::SqliteRecorder::new(&path, max_rows)?),
+                #[cfg(not(feature = "sqlite"))]
+                "sqlite" => return Err(
+                    "FlightRecorder: the sqlite format needs the sqlite feature of cu-flightrec."
+                        .into(),
+                ),
+                _ => {
+                    return Err(CuError::from(format!(
+                        "FlightRecorder: unknown format \"{format}\", expected csv or sqlite."
+                    )))
+                }
+            };
+        Ok(Self {
+            taskids,
+            recorder: Mutex::new(recorder),
+            clock: None,
+        })
+    }
+
+    fn start(&mut self, clock: &RobotClock) -> CuResult<()> {
+        self.clock = Some(clock.clone());
+        Ok(())
+    }
+
+    fn process_copperlist(&self, msgs: &[&CuMsgMetadata]) -> CuResult<()> {
+        let rows: Vec<Row> = msgs
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| !msg.process_time.start.is_none() && !msg.process_time.end.is_none())
+            .map(|(i, msg)| {
+                let start = msg.process_time.start.unwrap();
+                let CuCompactString(status_txt) = &msg.status_txt;
+                Row {
+                    time: start,
+                    culist_id: Some(msg.culist_id),
+                    task: self.taskids.get(i).copied().unwrap_or("?"),
+                    step: "process",
+                    duration: Some(msg.process_time.end.unwrap() - start),
+                    validity: Some(msg.validity.to_string()),
+                    message: status_txt.trim_end_matches('\0').to_string(),
+                }
+            })
+            .collect();
+        self.record(&rows)
+    }
+
+    fn process_error(&self, taskid: usize, step: CuTaskState, error: &CuError) -> Decision {
+        let step = format!("{step:?}").to_lowercase();
+        let row = Row {
+            time: self
+                .clock
+                .as_ref()
+                .map(|clock| clock.now())
+                .unwrap_or_default(),
+            culist_id: None,
+            task: self.taskids.get(taskid).copied().unwrap_or("?"),
+            step: &step,
+            duration: None,
+            validity: None,
+            message: error.to_string(),
+        };
+        // The decision can't wait for a failing disk, the row is lost.
+        let _ = self.record(&[row]);
+        Decision::Ignore
+    }
+
+    fn process_event(&self, event: &CuEvent) {
+        let row = Row {
+            time: event.time,
+            culist_id: Some(event.culist_id),
+            task: &event.name,
+            step: "event",
+            duration: None,
+            validity: None,
+            message: event.metadata.clone(),
+        };
+        let mut recorder = self.recorder.lock().unwrap();
+        let _ = recorder.record(&[row]);
+        if event.flush {
+            let _ = recorder.flush();
+        }
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.recorder.lock().unwrap().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(culist_id: u32, message: &str) -> Row<'static> {
+        Row {
+            time: CuDuration(1_000),
+            culist_id: Some(culist_id),
+            task: "lidar",
+            step: "process",
+            duration: Some(CuDuration(250)),
+            validity: Some("fresh".to_string()),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_csv_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flightrec.csv");
+        let mut recorder = CsvRecorder::new(&path, 2).unwrap();
+        recorder
+            .record(&[row(0, "ok"), row(1, "1,2 \"quoted\"")])
+            .unwrap();
+        recorder.record(&[row(2, "")]).unwrap();
+
+        let rotated = fs::read_to_string(dir.path().join("flightrec.csv.1")).unwrap();
+        assert_eq!(
+            rotated,
+            format!(
+                "{CSV_HEADER}\n1000,0,lidar,process,250,fresh,ok\n1000,1,lidar,process,250,fresh,\"1,2 \"\"quoted\"\"\"\n"
+            )
+        );
+        let current = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            current,
+            format!("{CSV_HEADER}\n1000,2,lidar,process,250,fresh,\n")
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Recorder, Row};
+use cu29::prelude::*;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+/// Number of rows between two deletions of the oldest rows beyond `max_rows`.
+const TRIM_PERIOD: u64 = 1000;
+
+/// Writes the rows to the table `timings` of an SQLite database, one transaction per copperlist.
+/// The oldest rows beyond `max_rows` are regularly deleted.
+pub struct SqliteRecorder {
+    connection: Connection,
+    max_rows: u64,
+    since_trim: u64,
+}
+
+fn sqlite_error(e: rusqlite::Error) -> CuError {
+    CuError::new_with_cause("FlightRecorder: SQLite error", e)
+}
+
+impl SqliteRecorder {
+    pub fn new(path: &Path, max_rows: u64) -> CuResult<Self> {
+        let connection = Connection::open(path).map_err(|e| {
+            CuError::new_with_cause(
+                &format!("FlightRecorder: Failed to open {}", path.display()),
+                e,
+            )
+        })?;
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA synchronous = NORMAL;
+                 CREATE TABLE IF NOT EXISTS timings (
+                     id INTEGER PRIMARY KEY,
+                     time_ns INTEGER NOT NULL,
+                     culist INTEGER,
+                     task TEXT NOT NULL,
+                     step TEXT NOT NULL,
+                     duration_ns INTEGER,
+                     validity TEXT,
+                     message TEXT NOT NULL
+                 );",
+            )
+            .map_err(sqlite_error)?;
+        Ok(Self {
+            connection,
+            max_rows,
+            since_trim: 0,
+        })
+    }
+
+    fn trim(&mut self) -> CuResult<()> {
+        self.connection
+            .execute(
+                "DELETE FROM timings WHERE id <= (SELECT MAX(id) FROM timings) - ?1",
+                params![self.max_rows],
+            )
+            .map_err(sqlite_error)?;
+        self.since_trim = 0;
+        Ok(())
+    }
+}
+
+impl Recorder for SqliteRecorder {
+    fn record(&mut self, rows: &[Row]) -> CuResult<()> {
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        {
+            let mut insert = transaction
+                .prepare_cached(
+                    "INSERT INTO timings (time_ns, culist, task, step, duration_ns, validity, message)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(sqlite_error)?;
+            for row in rows {
+                insert
+                    .execute(params![
+                        row.time.as_nanos(),
+                        row.culist_id,
+                        row.task,
+                        row.step,
+                        row.duration.map(|duration| duration.as_nanos()),
+                        row.validity,
+                        row.message,
+                    ])
+                    .map_err(sqlite_error)?;
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+        self.since_trim += rows.len() as u64;
+        if self.since_trim >= TRIM_PERIOD {
+            self.trim()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> CuResult<()> {
+        self.trim()
+    }
+}
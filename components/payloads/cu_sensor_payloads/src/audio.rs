@@ -0,0 +1,69 @@
+use bincode::de::Decoder;
+use bincode::error::DecodeError;
+use bincode::{Decode, Encode};
+use cu29::prelude::{ArrayLike, CuHandle};
+use std::fmt::Debug;
+
+#[derive(Default, Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct CuAudioFormat {
+    /// Samples per second and per channel.
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl CuAudioFormat {
+    /// The number of samples (all channels) of a frame of `frame_size` samples per channel.
+    pub fn samples(&self, frame_size: usize) -> usize {
+        frame_size * self.channels as usize
+    }
+}
+
+/// A frame of PCM audio: signed 16 bits samples, interleaved if there are several channels.
+#[derive(Debug, Default, Clone, Encode)]
+pub struct CuAudioFrame<A>
+where
+    A: ArrayLike<Element = i16>,
+{
+    pub seq: u64,
+    pub format: CuAudioFormat,
+    pub buffer_handle: CuHandle<A>,
+}
+
+impl Decode<()> for CuAudioFrame<Vec<i16>> {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let seq = u64::decode(decoder)?;
+        let format = CuAudioFormat::decode(decoder)?;
+        let buffer = Vec::decode(decoder)?;
+        let buffer_handle = CuHandle::new_detached(buffer);
+
+        Ok(Self {
+            seq,
+            format,
+            buffer_handle,
+        })
+    }
+}
+
+impl<A> CuAudioFrame<A>
+where
+    A: ArrayLike<Element = i16>,
+{
+    pub fn new(format: CuAudioFormat, buffer_handle: CuHandle<A>) -> Self {
+        assert!(
+            buffer_handle
+                .with_inner(|i| i.len())
+                .is_multiple_of(format.channels.max(1) as usize),
+            "The buffer must hold whole samples for all the channels."
+        );
+        CuAudioFrame {
+            seq: 0,
+            format,
+            buffer_handle,
+        }
+    }
+
+    /// The number of samples per channel.
+    pub fn frame_size(&self) -> usize {
+        self.buffer_handle.with_inner(|i| i.len()) / self.format.channels.max(1) as usize
+    }
+}
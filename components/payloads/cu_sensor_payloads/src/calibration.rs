@@ -0,0 +1,90 @@
+use bincode::{Decode, Encode};
+
+/// The distortion of a lens, with the coefficients of the OpenCV models.
+#[derive(Default, Debug, Encode, Decode, Clone, Copy, PartialEq)]
+pub enum CameraDistortion {
+    #[default]
+    None,
+    /// Radial and tangential (plumb bob), "radtan" in Kalibr.
+    RadTan {
+        k1: f64,
+        k2: f64,
+        k3: f64,
+        p1: f64,
+        p2: f64,
+    },
+    /// Equidistant fisheye (Kannala-Brandt), "equidistant" in Kalibr.
+    Fisheye { k1: f64, k2: f64, k3: f64, k4: f64 },
+}
+
+impl CameraDistortion {
+    /// Distorts the point (x, y) of the normalized image plane.
+    pub fn distort(&self, x: f64, y: f64) -> (f64, f64) {
+        match *self {
+            CameraDistortion::None => (x, y),
+            CameraDistortion::RadTan { k1, k2, k3, p1, p2 } => {
+                let r2 = x * x + y * y;
+                let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                (
+                    x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+                    y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+                )
+            }
+            CameraDistortion::Fisheye { k1, k2, k3, k4 } => {
+                let r = (x * x + y * y).sqrt();
+                if r < 1e-9 {
+                    return (x, y);
+                }
+                let theta = r.atan();
+                let theta2 = theta * theta;
+                let theta_d =
+                    theta * (1.0 + theta2 * (k1 + theta2 * (k2 + theta2 * (k3 + theta2 * k4))));
+                (x * theta_d / r, y * theta_d / r)
+            }
+        }
+    }
+}
+
+/// The calibration of a camera: the pinhole intrinsics in pixels for images of `width` x `height`,
+/// the distortion of the lens and where the camera is on the robot.
+#[derive(Default, Debug, Encode, Decode, Clone, Copy, PartialEq)]
+pub struct CameraCalibration {
+    pub width: u32,
+    pub height: u32,
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    pub distortion: CameraDistortion,
+    /// The row major homogeneous transform from the body (IMU) frame to the camera frame,
+    /// T_cam_imu in Kalibr, if it was calibrated.
+    pub extrinsics: Option<[[f64; 4]; 4]>,
+}
+
+impl CameraCalibration {
+    /// Projects a point of the normalized image plane to pixels with the distortion of the lens.
+    pub fn distort_to_pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = self.distortion.distort(x, y);
+        (self.fx * x + self.cx, self.fy * y + self.cy)
+    }
+
+    /// Projects a point of the camera frame (z forward) to pixels, None if it is behind the camera.
+    pub fn project(&self, point: [f64; 3]) -> Option<(f64, f64)> {
+        let [x, y, z] = point;
+        if z <= 0.0 {
+            return None;
+        }
+        Some(self.distort_to_pixel(x / z, y / z))
+    }
+
+    /// Transforms a point of the body frame to the camera frame with the extrinsics.
+    pub fn body_to_camera(&self, point: [f64; 3]) -> Option<[f64; 3]> {
+        let m = self.extrinsics?;
+        let [x, y, z] = point;
+        Some([
+            m[0][0] * x + m[0][1] * y + m[0][2] * z + m[0][3],
+            m[1][0] * x + m[1][1] * y + m[1][2] * z + m[1][3],
+            m[2][0] * x + m[2][1] * y + m[2][2] * z + m[2][3],
+        ])
+    }
+}
@@ -1,8 +1,8 @@
 use bincode::de::Decoder;
 use bincode::error::DecodeError;
 use bincode::{Decode, Encode};
+use cu29::export::{write_png, CuExportFormat, CuPayloadExport, PngColor};
 use cu29::prelude::{ArrayLike, CuHandle};
-#[allow(unused_imports)]
 use cu29::{CuError, CuResult};
 use std::fmt::Debug;
 
@@ -67,6 +67,49 @@ where
     }
 }
 
+/// Exports the 8 bits gray (GRAY or GREY), RGB (RGB3), BGR (BGR3) and RGBA images to PNG.
+impl<A> CuPayloadExport for CuImage<A>
+where
+    A: ArrayLike<Element = u8>,
+{
+    fn export_formats(&self) -> &'static [CuExportFormat] {
+        &[CuExportFormat::Png]
+    }
+
+    fn export(&self, _format: CuExportFormat, out: &mut dyn std::io::Write) -> CuResult<()> {
+        let CuImageBufferFormat {
+            width,
+            height,
+            stride,
+            pixel_format,
+        } = self.format;
+        let color = match &pixel_format {
+            b"GRAY" | b"GREY" => PngColor::Gray,
+            b"RGB3" | b"BGR3" => PngColor::Rgb,
+            b"RGBA" => PngColor::Rgba,
+            _ => {
+                return Err(CuError::from(format!(
+                    "Images in {} can't be exported to PNG.",
+                    String::from_utf8_lossy(&pixel_format)
+                )))
+            }
+        };
+        self.buffer_handle.with_inner(|inner| {
+            let data: &[u8] = inner;
+            if &pixel_format != b"BGR3" {
+                return write_png(out, width, height, stride as usize, color, data);
+            }
+            let mut rgb = data.to_vec();
+            for row in rgb.chunks_mut(stride as usize) {
+                for pixel in row.chunks_exact_mut(3).take(width as usize) {
+                    pixel.swap(0, 2);
+                }
+            }
+            write_png(out, width, height, stride as usize, color, &rgb)
+        })
+    }
+}
+
 impl<A> CuImage<A>
 where
     A: ArrayLike<Element = u8>,
@@ -1,6 +1,12 @@
+mod audio;
+mod calibration;
 mod image;
 mod pointcloud;
+mod range;
 
+pub use audio::*;
+pub use calibration::*;
 #[allow(unused_imports)]
 pub use image::*;
 pub use pointcloud::*;
+pub use range::*;
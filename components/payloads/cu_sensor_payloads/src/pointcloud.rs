@@ -1,77 +1,20 @@
-use bincode::de::{BorrowDecode, BorrowDecoder, Decode, Decoder};
+use bincode::de::{Decode, Decoder};
 use bincode::enc::{Encode, Encoder};
 use bincode::error::{DecodeError, EncodeError};
+use cu29::export::{write_points, CuExportFormat, CuPayloadExport};
+use cu29::{cu_quantity, CuResult};
 use cu29_clock::CuTime;
 use cu29_soa_derive::Soa;
-use derive_more::{Add, Deref, Div, From, Mul, Sub};
 use uom::si::f32::{Length, Ratio};
 use uom::si::length::meter;
 use uom::si::ratio::percent;
 
-#[derive(Default, PartialEq, Debug, Copy, Clone, Add, Deref, Sub, From, Mul, Div)]
-pub struct Reflectivity(Ratio);
+cu_quantity! {
+    /// Reflectivity of a point, encoded as a f32 in percent.
+    pub struct Reflectivity(Ratio) in percent as f32;
 
-impl From<f32> for Reflectivity {
-    fn from(value: f32) -> Self {
-        Self(Ratio::new::<percent>(value))
-    }
-}
-
-/// Encode as f32
-impl Encode for Reflectivity {
-    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
-        let Reflectivity(ratio) = self;
-        Encode::encode(&ratio.value, encoder)
-    }
-}
-
-/// Decode as f32
-impl Decode<()> for Reflectivity {
-    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let value: f32 = Decode::decode(decoder)?;
-        Ok(Reflectivity(Ratio::new::<percent>(value)))
-    }
-}
-
-/// Decode as f32
-impl<'de> BorrowDecode<'de, ()> for Reflectivity {
-    fn borrow_decode<D: BorrowDecoder<'de>>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let value: f32 = Decode::decode(decoder)?;
-        Ok(Reflectivity(Ratio::new::<percent>(value)))
-    }
-}
-
-#[derive(Default, PartialEq, Debug, Copy, Clone, Add, Deref, Sub, From, Mul, Div)]
-pub struct Distance(pub Length);
-
-/// Encode it as a f32 in m
-impl Encode for Distance {
-    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
-        let Distance(length) = self;
-        Encode::encode(&length.value, encoder)
-    }
-}
-
-impl From<f32> for Distance {
-    fn from(value: f32) -> Self {
-        Self(Length::new::<meter>(value))
-    }
-}
-
-/// Decode it as a f32 in m
-impl Decode<()> for Distance {
-    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let value: f32 = Decode::decode(decoder)?;
-        Ok(Distance(Length::new::<meter>(value)))
-    }
-}
-
-/// Decode it as a f32 in m
-impl<'de> BorrowDecode<'de, ()> for Distance {
-    fn borrow_decode<D: BorrowDecoder<'de>>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let value: f32 = Decode::decode(decoder)?;
-        Ok(Distance(Length::new::<meter>(value)))
-    }
+    /// Distance along an axis, encoded as a f32 in m.
+    pub struct Distance(Length) in meter as f32;
 }
 
 /// Standardized PointCloud.
@@ -149,6 +92,27 @@ impl PointCloud {
     }
 }
 
+/// Exports the points with their reflectivity in percent as intensity.
+impl<const N: usize> CuPayloadExport for PointCloudSoa<N> {
+    fn export_formats(&self) -> &'static [CuExportFormat] {
+        &[CuExportFormat::Ply, CuExportFormat::Pcd]
+    }
+
+    fn export(&self, format: CuExportFormat, out: &mut dyn std::io::Write) -> CuResult<()> {
+        let points: Vec<[f32; 4]> = (0..self.len)
+            .map(|index| {
+                [
+                    self.x[index].0.get::<meter>(),
+                    self.y[index].0.get::<meter>(),
+                    self.z[index].0.get::<meter>(),
+                    self.i[index].0.get::<percent>(),
+                ]
+            })
+            .collect();
+        write_points(out, format, &points)
+    }
+}
+
 impl<const N: usize> PointCloudSoa<N> {
     /// Sort in place the point cloud so it can be ready for merge sorts for example
     pub fn sort(&mut self) {
@@ -203,6 +167,17 @@ mod tests {
         assert_eq!(payload.z.0.value, 3.0);
     }
 
+    #[test]
+    fn test_export_ply() {
+        let mut soa = PointCloudSoa::<4>::default();
+        soa.push(PointCloud::new(CuDuration(1), 1.0, 2.0, 3.0, 50.0, None));
+        let mut ply = Vec::new();
+        soa.export(CuExportFormat::Ply, &mut ply).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.contains("element vertex 1\n"));
+        assert!(ply.ends_with("end_header\n1 2 3 50\n"));
+    }
+
     #[test]
     fn test_length_add_sub() {
         let a = Distance(Length::new::<meter>(1.0));
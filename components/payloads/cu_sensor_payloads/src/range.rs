@@ -0,0 +1,33 @@
+use bincode::{Decode, Encode};
+
+/// A measurement of a single beam range sensor (ultrasonic, time of flight, IR...) with where the
+/// sensor is on the robot, so the consumers can place the echo without knowing the sensor.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct RangeReading {
+    /// The distances in meters, the readings out of min..=max being no echo or out of range.
+    pub distance: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Position in meters and heading in radians of the sensor in the frame of the robot.
+    pub mount_x: f32,
+    pub mount_y: f32,
+    pub mount_yaw: f32,
+}
+
+impl RangeReading {
+    pub fn is_valid(&self) -> bool {
+        (self.min..=self.max).contains(&self.distance)
+    }
+
+    /// The position of the echo in the frame of the robot, None if the reading is not valid.
+    pub fn echo(&self) -> Option<(f32, f32)> {
+        if !self.is_valid() {
+            return None;
+        }
+        let (sin, cos) = self.mount_yaw.sin_cos();
+        Some((
+            self.mount_x + self.distance * cos,
+            self.mount_y + self.distance * sin,
+        ))
+    }
+}
@@ -3,6 +3,10 @@ use core::fmt::Debug;
 use serde::{Deserialize, Serialize};
 use uom::si::angle::radian;
 use uom::si::length::meter;
+
+mod nav;
+pub use nav::*;
+
 pub type Pose<T> = Transform3D<T>;
 use uom::si::f32::Angle as Angle32;
 use uom::si::f32::Length as Length32;
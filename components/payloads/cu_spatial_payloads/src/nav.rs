@@ -0,0 +1,109 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// A planar pose: the position in meters and the heading in radians, counterclockwise from x.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct Pose2D {
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+}
+
+impl Pose2D {
+    pub fn new(x: f64, y: f64, theta: f64) -> Self {
+        Self { x, y, theta }
+    }
+
+    pub fn distance(&self, other: &Pose2D) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+}
+
+/// A planar velocity command or measurement in the frame of the robot.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct Twist2D {
+    /// m/s, positive forward.
+    pub linear: f64,
+    /// rad/s, positive counterclockwise.
+    pub angular: f64,
+}
+
+/// The pose of the robot in the frame of the map and its velocity, from the odometry or the localization.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct Odometry2D {
+    pub pose: Pose2D,
+    pub twist: Twist2D,
+}
+
+/// The value of the cells never observed.
+pub const UNKNOWN_CELL: i8 = -1;
+
+/// A 2D occupancy grid like the nav_msgs/OccupancyGrid of ROS: row major cells from the origin,
+/// each one being [UNKNOWN_CELL] or the probability of occupation from 0 (free) to 100 (occupied).
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct OccupancyGrid {
+    pub width: u32,
+    pub height: u32,
+    /// Size of the cells in meters.
+    pub resolution: f64,
+    /// Position in meters of the corner of the cell (0, 0), the grid being aligned with the axes.
+    pub origin_x: f64,
+    pub origin_y: f64,
+    /// Bumped by the mapper every time the cells change.
+    pub revision: u64,
+    pub data: Vec<i8>,
+}
+
+impl OccupancyGrid {
+    /// A grid of unknown cells.
+    pub fn new(width: u32, height: u32, resolution: f64, origin_x: f64, origin_y: f64) -> Self {
+        Self {
+            width,
+            height,
+            resolution,
+            origin_x,
+            origin_y,
+            revision: 0,
+            data: vec![UNKNOWN_CELL; width as usize * height as usize],
+        }
+    }
+
+    /// The cell containing the point (x, y), if it is in the grid.
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(u32, u32)> {
+        let cx = ((x - self.origin_x) / self.resolution).floor();
+        let cy = ((y - self.origin_y) / self.resolution).floor();
+        if cx < 0.0 || cy < 0.0 || cx >= self.width as f64 || cy >= self.height as f64 {
+            return None;
+        }
+        Some((cx as u32, cy as u32))
+    }
+
+    /// The position of the center of a cell.
+    pub fn cell_center(&self, cx: u32, cy: u32) -> (f64, f64) {
+        (
+            self.origin_x + (cx as f64 + 0.5) * self.resolution,
+            self.origin_y + (cy as f64 + 0.5) * self.resolution,
+        )
+    }
+
+    pub fn get(&self, cx: u32, cy: u32) -> Option<i8> {
+        if cx >= self.width || cy >= self.height {
+            return None;
+        }
+        self.data
+            .get(cy as usize * self.width as usize + cx as usize)
+            .copied()
+    }
+
+    pub fn set(&mut self, cx: u32, cy: u32, value: i8) {
+        if cx < self.width && cy < self.height {
+            self.data[cy as usize * self.width as usize + cx as usize] = value;
+        }
+    }
+}
+
+/// A path to follow, from the start to the goal. An empty path means there is no way to the goal.
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct Path2D {
+    pub poses: Vec<Pose2D>,
+}
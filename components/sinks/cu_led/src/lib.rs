@@ -0,0 +1,212 @@
+//! A LED on a GPIO line of a Linux GPIO character device, switched on, off or blinking by the
+//! pattern of its input. It started as the GPIO handling of the cu_caterpillar example.
+
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(hardware)]
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+
+/// What the LED does until the next pattern comes in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub enum LedPattern {
+    #[default]
+    Off,
+    On,
+    /// On for `on_ms` then off for `off_ms`, repeatedly.
+    Blink {
+        on_ms: u32,
+        off_ms: u32,
+    },
+    /// `count` flashes of `flash_ms` separated by as long, then off for `pause_ms`, repeatedly,
+    /// to show an error code for example.
+    Flashes {
+        count: u8,
+        flash_ms: u32,
+        pause_ms: u32,
+    },
+}
+
+impl LedPattern {
+    /// Is the LED lit this long after the pattern started?
+    pub fn is_lit(&self, elapsed: CuDuration) -> bool {
+        let elapsed_ms = elapsed.as_millis();
+        match *self {
+            LedPattern::Off => false,
+            LedPattern::On => true,
+            LedPattern::Blink { on_ms, off_ms } => {
+                let period = on_ms as u64 + off_ms as u64;
+                period > 0 && elapsed_ms % period < on_ms as u64
+            }
+            LedPattern::Flashes {
+                count,
+                flash_ms,
+                pause_ms,
+            } => {
+                let flashes = 2 * count as u64 * flash_ms as u64;
+                let period = flashes + pause_ms as u64;
+                if period == 0 {
+                    return false;
+                }
+                let in_period = elapsed_ms % period;
+                in_period < flashes && (in_period / flash_ms as u64) % 2 == 0
+            }
+        }
+    }
+}
+
+/// The input of the LED.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct LedPayload {
+    pub pattern: LedPattern,
+}
+
+impl LedPayload {
+    pub fn blink(on_ms: u32, off_ms: u32) -> Self {
+        Self {
+            pattern: LedPattern::Blink { on_ms, off_ms },
+        }
+    }
+}
+
+impl From<bool> for LedPayload {
+    fn from(on: bool) -> Self {
+        Self {
+            pattern: if on { LedPattern::On } else { LedPattern::Off },
+        }
+    }
+}
+
+#[derive(CuConfigStruct)]
+struct CuLedConfig {
+    #[config(default = "/dev/gpiochip0")]
+    chip: String,
+    line: u32,
+    #[config(default)]
+    active_low: bool,
+}
+
+/// The sink driving the LED, it keeps the pattern of its last input.
+pub struct CuLed {
+    #[cfg(hardware)]
+    handle: LineHandle,
+    #[cfg(mock)]
+    line: u32,
+    active_low: bool,
+    pattern: LedPattern,
+    pattern_start: CuTime,
+    /// What was last written to the line, None before the first write.
+    lit: Option<bool>,
+}
+
+impl CuLed {
+    fn write(&mut self, lit: bool) -> CuResult<()> {
+        if self.lit == Some(lit) {
+            return Ok(());
+        }
+        let level = (lit != self.active_low) as u8;
+        #[cfg(hardware)]
+        self.handle
+            .set_value(level)
+            .map_err(|e| CuError::new_with_cause("CuLed: could not set the line", e))?;
+        #[cfg(mock)]
+        debug!("CuLed: would set the line {} to {}.", self.line, level);
+        self.lit = Some(lit);
+        Ok(())
+    }
+}
+
+impl Freezable for CuLed {}
+
+impl<'cl> CuSinkTask<'cl> for CuLed {
+    type Input = input_msg!('cl, LedPayload);
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = Some(CuLedConfig::SCHEMA);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = CuLedConfig::from_config(config)?;
+        #[cfg(hardware)]
+        let handle = Chip::new(&config.chip)
+            .and_then(|mut chip| chip.get_line(config.line))
+            .and_then(|line| {
+                line.request(LineRequestFlags::OUTPUT, config.active_low as u8, "copper")
+            })
+            .map_err(|e| {
+                CuError::new_with_cause(
+                    &format!(
+                        "CuLed: could not get the line {} of {}",
+                        config.line, config.chip
+                    ),
+                    e,
+                )
+            })?;
+        #[cfg(mock)]
+        let _ = &config.chip;
+        Ok(Self {
+            #[cfg(hardware)]
+            handle,
+            #[cfg(mock)]
+            line: config.line,
+            active_low: config.active_low,
+            pattern: LedPattern::Off,
+            pattern_start: CuTime::default(),
+            lit: None,
+        })
+    }
+
+    fn process(&mut self, clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        let now = clock.now();
+        if let Some(payload) = input.payload() {
+            if payload.pattern != self.pattern {
+                self.pattern = payload.pattern;
+                self.pattern_start = now;
+            }
+        }
+        self.write(self.pattern.is_lit(now - self.pattern_start))
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.write(false)
+    }
+
+    fn safe_state(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.write(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        let at = |ms: u64| CuDuration::from_millis(ms);
+        let blink = LedPayload::blink(100, 300).pattern;
+        assert!(blink.is_lit(at(0)) && blink.is_lit(at(99)));
+        assert!(!blink.is_lit(at(100)) && !blink.is_lit(at(399)));
+        assert!(blink.is_lit(at(400)));
+
+        // 2 flashes of 50 ms then a pause of 500 ms.
+        let flashes = LedPattern::Flashes {
+            count: 2,
+            flash_ms: 50,
+            pause_ms: 500,
+        };
+        let lit: Vec<bool> = [0, 60, 110, 160, 210, 650, 700]
+            .into_iter()
+            .map(|ms| flashes.is_lit(at(ms)))
+            .collect();
+        assert_eq!(lit, vec![true, false, true, false, false, false, true]);
+
+        assert!(LedPayload::from(true).pattern.is_lit(at(12345)));
+        assert!(!LedPattern::Off.is_lit(at(0)));
+        assert!(!LedPattern::Blink {
+            on_ms: 0,
+            off_ms: 0
+        }
+        .is_lit(at(0)));
+    }
+}
@@ -97,4 +97,14 @@ impl<'cl> CuSinkTask<'cl> for RPGpio {
 
         Ok(())
     }
+
+    fn safe_state(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        #[cfg(hardware)]
+        self.pin.write(RPGpioPayload { on: false }.into());
+
+        #[cfg(mock)]
+        debug!("Would switch off pin {} for the e-stop.", self.pin);
+
+        Ok(())
+    }
 }
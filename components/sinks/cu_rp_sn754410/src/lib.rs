@@ -209,6 +209,15 @@ impl<'cl> CuSinkTask<'cl> for SN754410 {
         Ok(())
     }
 
+    fn safe_state(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        if self.current_power != 0.0 {
+            debug!("E-stop engaged, stopping the motor.");
+            self.current_power = 0.0;
+            self.stop()?;
+        }
+        Ok(())
+    }
+
     fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
         debug!("Disabling SN754410.");
         self.disable_pwms()
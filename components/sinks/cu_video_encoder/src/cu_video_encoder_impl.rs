@@ -0,0 +1,144 @@
+use crate::{gst_frame, pipeline_description, VideoEncoderConfig};
+use cu29::prelude::*;
+use cu_sensor_payloads::{CuImage, CuImageBufferFormat};
+use gstreamer::prelude::*;
+use gstreamer::{parse, Buffer, ClockTime, MessageView, Pipeline};
+use gstreamer_app::AppSrc;
+
+/// How long the pipeline has to finalize the file (the moov atom of an MP4...) when the task stops.
+const EOS_TIMEOUT_S: u64 = 5;
+
+struct Encoding {
+    pipeline: Pipeline,
+    appsrc: AppSrc,
+    format: CuImageBufferFormat,
+    first_frame: CuTime,
+}
+
+/// This is a sink task encoding the CuImage frames it receives to H.264 or HEVC, in an MP4/MKV file or
+/// an RTSP stream, so camera footage can be reviewed without logging the raw frames.
+/// The pipeline is created at the first frame, from its size and pixel format.
+pub struct CuVideoEncoder {
+    config: VideoEncoderConfig,
+    encoding: Option<Encoding>,
+}
+
+impl CuVideoEncoder {
+    fn start_encoding(
+        &self,
+        format: CuImageBufferFormat,
+        first_frame: CuTime,
+    ) -> CuResult<Encoding> {
+        let description = pipeline_description(&self.config, &format)?;
+        debug!("VideoEncoder: Creating the pipeline {}", &description);
+        let pipeline = parse::launch(&description)
+            .map_err(|e| CuError::new_with_cause("VideoEncoder: Failed to parse pipeline.", e))?
+            .dynamic_cast::<Pipeline>()
+            .map_err(|_| CuError::from("VideoEncoder: Failed to cast pipeline."))?;
+        let appsrc = pipeline
+            .by_name("copper")
+            .and_then(|appsrc| appsrc.dynamic_cast::<AppSrc>().ok())
+            .ok_or_else(|| CuError::from("VideoEncoder: Failed to find the appsrc."))?;
+        pipeline.set_state(gstreamer::State::Playing).map_err(|e| {
+            CuError::new_with_cause("VideoEncoder: Failed to start the pipeline.", e)
+        })?;
+        Ok(Encoding {
+            pipeline,
+            appsrc,
+            format,
+            first_frame,
+        })
+    }
+}
+
+impl Freezable for CuVideoEncoder {}
+
+impl<'cl> CuSinkTask<'cl> for CuVideoEncoder {
+    type Input = input_msg!('cl, CuImage<Vec<u8>>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = VideoEncoderConfig::from_config(config)?;
+        if !gstreamer::INITIALIZED.load(std::sync::atomic::Ordering::SeqCst) {
+            gstreamer::init().map_err(|e| {
+                CuError::new_with_cause("VideoEncoder: Failed to initialize gstreamer.", e)
+            })?;
+        }
+        Ok(Self {
+            config,
+            encoding: None,
+        })
+    }
+
+    fn process(&mut self, clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        let Some(image) = input.payload() else {
+            return Ok(());
+        };
+        let time = match input.metadata.tov {
+            Tov::Time(time) => time,
+            Tov::Range(range) => range.start,
+            Tov::None => clock.now(),
+        };
+        if self.encoding.is_none() {
+            self.encoding = Some(self.start_encoding(image.format, time)?);
+        }
+        let encoding = self.encoding.as_ref().unwrap();
+        let format = &encoding.format;
+        if (
+            image.format.width,
+            image.format.height,
+            image.format.pixel_format,
+        ) != (format.width, format.height, format.pixel_format)
+        {
+            return Err(CuError::from(format!(
+                "VideoEncoder: the frames changed from {}x{} to {}x{} during the recording.",
+                format.width, format.height, image.format.width, image.format.height
+            )));
+        }
+        let frame = image
+            .buffer_handle
+            .with_inner(|inner| gst_frame(&image.format, inner))?;
+        let mut buffer = Buffer::from_mut_slice(frame);
+        let pts = time
+            .as_nanos()
+            .saturating_sub(encoding.first_frame.as_nanos());
+        buffer
+            .get_mut()
+            .expect("a new buffer is writable")
+            .set_pts(ClockTime::from_nseconds(pts));
+        encoding
+            .appsrc
+            .push_buffer(buffer)
+            .map_err(|e| CuError::from(format!("VideoEncoder: Failed to push the frame: {e:?}")))?;
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let Some(Encoding {
+            pipeline, appsrc, ..
+        }) = self.encoding.take()
+        else {
+            return Ok(());
+        };
+        // The muxers write their index at the end of the stream.
+        let _ = appsrc.end_of_stream();
+        if let Some(bus) = pipeline.bus() {
+            let message = bus.timed_pop_filtered(
+                ClockTime::from_seconds(EOS_TIMEOUT_S),
+                &[gstreamer::MessageType::Eos, gstreamer::MessageType::Error],
+            );
+            if let Some(MessageView::Error(error)) = message.as_ref().map(|m| m.view()) {
+                debug!(
+                    "VideoEncoder: Error while finalizing the video: {}",
+                    error.error().to_string()
+                );
+            }
+        }
+        pipeline.set_state(gstreamer::State::Null).map_err(|e| {
+            CuError::new_with_cause("VideoEncoder: Failed to stop the pipeline.", e)
+        })?;
+        Ok(())
+    }
+}
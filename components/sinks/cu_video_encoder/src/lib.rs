@@ -0,0 +1,186 @@
+#[cfg(feature = "gst")]
+mod cu_video_encoder_impl;
+
+#[cfg(feature = "gst")]
+pub use cu_video_encoder_impl::*;
+
+use cu29::prelude::*;
+use cu_sensor_payloads::CuImageBufferFormat;
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct, Debug, Clone, PartialEq)]
+pub struct VideoEncoderConfig {
+    /// An .mp4 or .mkv file, or an rtsp:// url.
+    pub output: String,
+    #[config(default = "h264")]
+    pub codec: String,
+    /// VAAPI instead of x264/x265.
+    #[config(default)]
+    pub hardware_acceleration: bool,
+    #[config(default = 4000, range = 1..)]
+    pub bitrate_kbps: u32,
+    #[config(default = 30, range = 1..)]
+    pub gop: u32,
+    #[config(default = 30, range = 1..)]
+    pub fps: u32,
+}
+
+/// The GStreamer raw video format of a CuImage pixel format and its bytes per pixel in the first
+/// plane (NV12 has an interleaved chroma plane of half the height after the luma one).
+pub fn gst_video_format(pixel_format: &[u8; 4]) -> CuResult<(&'static str, usize)> {
+    match pixel_format {
+        b"GRAY" | b"GREY" => Ok(("GRAY8", 1)),
+        b"RGB3" => Ok(("RGB", 3)),
+        b"BGR3" => Ok(("BGR", 3)),
+        b"RGBA" => Ok(("RGBA", 4)),
+        b"YUYV" => Ok(("YUY2", 2)),
+        b"NV12" => Ok(("NV12", 1)),
+        _ => Err(CuError::from(format!(
+            "VideoEncoder: the pixel format {} is not supported.",
+            String::from_utf8_lossy(pixel_format)
+        ))),
+    }
+}
+
+/// GStreamer expects the rows of the raw video frames aligned on 4 bytes.
+pub fn gst_stride(row_bytes: usize) -> usize {
+    (row_bytes + 3) & !3
+}
+
+/// Number of rows of the buffer of a frame.
+fn buffer_rows(pixel_format: &[u8; 4], height: usize) -> usize {
+    if pixel_format == b"NV12" {
+        height + height.div_ceil(2)
+    } else {
+        height
+    }
+}
+
+/// Copies the rows of a frame to the stride GStreamer expects if needed.
+pub fn gst_frame(format: &CuImageBufferFormat, data: &[u8]) -> CuResult<Vec<u8>> {
+    let (_, bytes_per_pixel) = gst_video_format(&format.pixel_format)?;
+    let row_bytes = format.width as usize * bytes_per_pixel;
+    let stride = format.stride as usize;
+    let rows = buffer_rows(&format.pixel_format, format.height as usize);
+    if stride < row_bytes || data.len() < stride * rows {
+        return Err(CuError::from(format!(
+            "VideoEncoder: the buffer of {} bytes is too small for {}x{} frames with a stride of {stride}.",
+            data.len(),
+            format.width,
+            format.height
+        )));
+    }
+    let gst_stride = gst_stride(row_bytes);
+    if stride == gst_stride {
+        return Ok(data[..stride * rows].to_vec());
+    }
+    let mut frame = vec![0; gst_stride * rows];
+    for (dst, src) in frame.chunks_mut(gst_stride).zip(data.chunks(stride)) {
+        dst[..row_bytes].copy_from_slice(&src[..row_bytes]);
+    }
+    Ok(frame)
+}
+
+/// The GStreamer pipeline encoding the frames pushed in its appsrc named "copper".
+pub fn pipeline_description(
+    config: &VideoEncoderConfig,
+    format: &CuImageBufferFormat,
+) -> CuResult<String> {
+    let (video_format, _) = gst_video_format(&format.pixel_format)?;
+    let VideoEncoderConfig {
+        output,
+        codec,
+        hardware_acceleration,
+        bitrate_kbps,
+        gop,
+        fps,
+    } = config;
+    let encoder = match (codec.to_lowercase().as_str(), hardware_acceleration) {
+        ("h264", false) => format!(
+            "x264enc bitrate={bitrate_kbps} key-int-max={gop} tune=zerolatency speed-preset=veryfast ! h264parse"
+        ),
+        ("h264", true) => format!(
+            "video/x-raw,format=NV12 ! vaapih264enc bitrate={bitrate_kbps} keyframe-period={gop} ! h264parse"
+        ),
+        ("h265" | "hevc", false) => format!(
+            "x265enc bitrate={bitrate_kbps} key-int-max={gop} tune=zerolatency speed-preset=veryfast ! h265parse"
+        ),
+        ("h265" | "hevc", true) => format!(
+            "video/x-raw,format=NV12 ! vaapih265enc bitrate={bitrate_kbps} keyframe-period={gop} ! h265parse"
+        ),
+        _ => {
+            return Err(CuError::from(format!(
+                "VideoEncoder: unknown codec \"{codec}\", expected h264 or h265."
+            )))
+        }
+    };
+    let sink = if output.starts_with("rtsp://") {
+        format!("rtspclientsink location=\"{output}\"")
+    } else if output.ends_with(".mp4") {
+        format!("mp4mux ! filesink location=\"{output}\"")
+    } else if output.ends_with(".mkv") {
+        format!("matroskamux ! filesink location=\"{output}\"")
+    } else {
+        return Err(CuError::from(format!(
+            "VideoEncoder: the output \"{output}\" should be an .mp4 or .mkv file or an rtsp:// url."
+        )));
+    };
+    Ok(format!(
+        "appsrc name=copper is-live=true format=time caps=\"video/x-raw,format={video_format},width={},height={},framerate={fps}/1\" ! videoconvert ! {encoder} ! {sink}",
+        format.width, format.height
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(output: &str) -> VideoEncoderConfig {
+        let mut config = ComponentConfig::new();
+        config.set("output", output.to_string());
+        VideoEncoderConfig::from_config(Some(&config)).unwrap()
+    }
+
+    fn format(width: u32, stride: u32, pixel_format: &[u8; 4]) -> CuImageBufferFormat {
+        CuImageBufferFormat {
+            width,
+            height: 2,
+            stride,
+            pixel_format: *pixel_format,
+        }
+    }
+
+    #[test]
+    fn test_pipeline_description() {
+        let gray = format(640, 640, b"GRAY");
+        assert_eq!(
+            pipeline_description(&config("review.mp4"), &gray).unwrap(),
+            "appsrc name=copper is-live=true format=time caps=\"video/x-raw,format=GRAY8,width=640,height=2,framerate=30/1\" ! videoconvert ! x264enc bitrate=4000 key-int-max=30 tune=zerolatency speed-preset=veryfast ! h264parse ! mp4mux ! filesink location=\"review.mp4\""
+        );
+
+        let mut hevc = config("rtsp://server:8554/front");
+        hevc.codec = "h265".to_string();
+        hevc.hardware_acceleration = true;
+        let description = pipeline_description(&hevc, &gray).unwrap();
+        assert!(description.contains("vaapih265enc bitrate=4000 keyframe-period=30 ! h265parse"));
+        assert!(description.ends_with("rtspclientsink location=\"rtsp://server:8554/front\""));
+
+        assert!(pipeline_description(&config("review.avi"), &gray).is_err());
+        assert!(pipeline_description(&config("review.mkv"), &format(2, 2, b"MJPG")).is_err());
+    }
+
+    #[test]
+    fn test_gst_frame() {
+        // RGB rows of 6 bytes in a buffer with a stride of 7, GStreamer wants 8.
+        let data: Vec<u8> = (0..14).collect();
+        let frame = gst_frame(&format(2, 7, b"RGB3"), &data).unwrap();
+        assert_eq!(
+            frame,
+            vec![0, 1, 2, 3, 4, 5, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0]
+        );
+        // Already aligned, copied as is.
+        let data: Vec<u8> = (0..8).collect();
+        assert_eq!(gst_frame(&format(4, 4, b"GRAY"), &data).unwrap(), data);
+        assert!(gst_frame(&format(4, 4, b"NV12"), &data).is_err());
+    }
+}
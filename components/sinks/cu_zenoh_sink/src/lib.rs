@@ -1,7 +1,13 @@
+use cu29::bincode::enc::write::SizeWriter;
+use cu29::bincode::enc::{Encode, EncoderImpl};
 use cu29::clock::RobotClock;
 use cu29::{bincode, prelude::*};
 
 use zenoh::key_expr::KeyExpr;
+use zenoh::shm::{
+    GarbageCollect, PosixShmProviderBackend, ShmProvider, ShmProviderBuilder, StaticProtocolID,
+    ZShmMut, POSIX_PROTOCOL_ID,
+};
 use zenoh::Config;
 use zenoh::Error as ZenohError;
 
@@ -22,11 +28,67 @@ where
 pub struct ZenohConfig {
     config: zenoh::Config,
     topic: String,
+    /// Size of the shared memory pool if the messages are published through shared memory.
+    shm_pool_size: Option<usize>,
+    /// Bound of the encoded size of the messages, the buffers are allocated at this size.
+    max_size: Option<u32>,
 }
 
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct ZenohSinkConfig {
+    /// Path of a json5 zenoh config, the default zenoh config is used otherwise.
+    zenoh_config_file: Option<String>,
+    #[config(default = "copper")]
+    topic: String,
+    /// Publishes the messages through zenoh shared memory: the subscribers on the same host map the
+    /// buffer instead of receiving a copy, the remote peers still receive the bytes.
+    #[config(default)]
+    shared_memory: bool,
+    /// Size of the shared memory pool, it needs to hold the messages in flight.
+    #[config(default = 16 * 1024 * 1024, range = 1..)]
+    shm_pool_size: usize,
+    /// Bound of the encoded size of the messages, the max_size of the connection feeding the sink.
+    /// The bridges of a partitioned configuration get it from their connection.
+    max_size: Option<u32>,
+}
+
+type PosixShmProvider = ShmProvider<StaticProtocolID<POSIX_PROTOCOL_ID>, PosixShmProviderBackend>;
+
 pub struct ZenohContext {
     session: zenoh::Session,
     publisher: zenoh::pubsub::Publisher<'static>,
+    shm_provider: Option<PosixShmProvider>,
+}
+
+/// Size of the bincode encoding of the value, without encoding it anywhere.
+fn encoded_size<E: Encode>(value: &E) -> CuResult<usize> {
+    let mut encoder = EncoderImpl::new(SizeWriter::default(), CuWireFormat::CONFIG);
+    value
+        .encode(&mut encoder)
+        .map_err(|e| CuError::new_with_cause("ZenohSink: Failed to encode value", e))?;
+    Ok(encoder.into_writer().bytes_written)
+}
+
+impl ZenohContext {
+    /// Encodes the value directly in a shared memory buffer.
+    /// Returns None if the pool is exhausted so the caller can fall back to a plain buffer.
+    fn encode_in_shm<E: Encode>(&self, value: &E) -> CuResult<Option<ZShmMut>> {
+        let Some(provider) = &self.shm_provider else {
+            return Ok(None);
+        };
+        let size = encoded_size(value)?;
+        // Never block the copper loop on the pool, only reclaim the buffers already released.
+        let Ok(mut buffer) =
+            zenoh::Wait::wait(provider.alloc(size).with_policy::<GarbageCollect>())
+        else {
+            debug!("ZenohSink: Shared memory pool exhausted, publishing a copy.");
+            return Ok(None);
+        };
+        bincode::encode_into_slice(value, &mut buffer, CuWireFormat::CONFIG)
+            .map_err(|e| CuError::new_with_cause("ZenohSink: Failed to encode value", e))?;
+        Ok(Some(buffer))
+    }
 }
 
 fn cu_error(msg: &str, error: ZenohError) -> CuError {
@@ -49,25 +111,32 @@ where
     where
         Self: Sized,
     {
-        let config = config.ok_or(CuError::from("ZenohSink: Missing configuration"))?;
-
-        // Get json zenoh config
-        let session_config = config.get::<String>("zenoh_config_file").map_or(
-            // Or default zenoh config otherwise
-            CuResult::Ok(Config::default()),
-            |s| -> CuResult<zenoh::Config> {
-                Config::from_file(&s)
-                    .map_err(cu_error_map("ZenohSink: Failed to create zenoh config"))
-            },
-        )?;
-
-        let topic = config.get::<String>("topic").unwrap_or("copper".to_owned());
+        let ZenohSinkConfig {
+            zenoh_config_file,
+            topic,
+            shared_memory,
+            shm_pool_size,
+            max_size,
+        } = ZenohSinkConfig::from_config(config)?;
+
+        let mut session_config = match zenoh_config_file {
+            Some(file) => Config::from_file(&file)
+                .map_err(cu_error_map("ZenohSink: Failed to create zenoh config"))?,
+            None => Config::default(),
+        };
+        if shared_memory {
+            session_config
+                .insert_json5("transport/shared_memory/enabled", "true")
+                .map_err(cu_error_map("ZenohSink: Failed to enable shared memory"))?;
+        }
 
         Ok(Self {
             _marker: Default::default(),
             config: ZenohConfig {
                 config: session_config,
-                topic,
+                topic: namespaced(config, &topic),
+                shm_pool_size: shared_memory.then_some(shm_pool_size),
+                max_size,
             },
             ctx: None,
         })
@@ -84,7 +153,32 @@ where
         let publisher = zenoh::Wait::wait(session.declare_publisher(key_expr))
             .map_err(cu_error_map("ZenohSink: Failed to create publisher"))?;
 
-        self.ctx = Some(ZenohContext { session, publisher });
+        let shm_provider = match self.config.shm_pool_size {
+            Some(size) => {
+                let backend = PosixShmProviderBackend::builder()
+                    .with_size(size)
+                    .map_err(|e| {
+                        CuError::from(format!(
+                            "ZenohSink: Invalid shared memory pool size {size}: {e:?}"
+                        ))
+                    })?;
+                let backend = zenoh::Wait::wait(backend).map_err(cu_error_map(
+                    "ZenohSink: Failed to create shared memory pool",
+                ))?;
+                Some(zenoh::Wait::wait(
+                    ShmProviderBuilder::builder()
+                        .protocol_id::<POSIX_PROTOCOL_ID>()
+                        .backend(backend),
+                ))
+            }
+            None => None,
+        };
+
+        self.ctx = Some(ZenohContext {
+            session,
+            publisher,
+            shm_provider,
+        });
         Ok(())
     }
 
@@ -94,15 +188,26 @@ where
             .as_mut()
             .ok_or_else(|| CuError::from("ZenohSink: Context not found"))?;
 
-        let encoded =
-            bincode::encode_to_vec(input, bincode::config::standard()).expect("Encoding failed");
-        zenoh::Wait::wait(ctx.publisher.put(encoded))
-            .map_err(cu_error_map("ZenohSink: Failed to put value"))?;
+        let put = match ctx.encode_in_shm(input)? {
+            Some(buffer) => ctx.publisher.put(buffer),
+            None => {
+                // Sized for the biggest message upfront, the encoding never grows it.
+                let mut buffer =
+                    Vec::with_capacity(self.config.max_size.unwrap_or_default() as usize);
+                bincode::encode_into_std_write(input, &mut buffer, CuWireFormat::CONFIG)
+                    .map_err(|e| CuError::new_with_cause("ZenohSink: Failed to encode value", e))?;
+                ctx.publisher.put(buffer)
+            }
+        };
+        zenoh::Wait::wait(put).map_err(cu_error_map("ZenohSink: Failed to put value"))?;
         Ok(())
     }
 
     fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
-        if let Some(ZenohContext { session, publisher }) = self.ctx.take() {
+        if let Some(ZenohContext {
+            session, publisher, ..
+        }) = self.ctx.take()
+        {
             zenoh::Wait::wait(publisher.undeclare())
                 .map_err(cu_error_map("ZenohSink: Failed to undeclare publisher"))?;
             zenoh::Wait::wait(session.close())
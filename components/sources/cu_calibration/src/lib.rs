@@ -0,0 +1,341 @@
+//! A source loading the calibration of a camera from a file at startup and publishing it once, so
+//! the undistortion and projection tasks of the graph share the same one. Connect them with a
+//! latched connection to always get it:
+//!
+//! ```ron
+//! cnx: [
+//!     (src: "front_calib", dst: "projector", msg: "cu_sensor_payloads::CameraCalibration", policy: Some(Latched)),
+//! ]
+//! ```
+//!
+//! The supported files, in YAML or JSON:
+//! - the OpenCV FileStorage of `calibrateCamera` and the ROS camera_info: `image_width`, `image_height`,
+//!   `camera_matrix`, `distortion_coefficients` and optionally `distortion_model`,
+//! - the camchain of Kalibr, a map of the cameras with `intrinsics`, `resolution`, `distortion_model`,
+//!   `distortion_coeffs` and optionally `T_cam_imu`.
+
+use cu29::prelude::*;
+use cu_sensor_payloads::{CameraCalibration, CameraDistortion};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(CuConfigStruct)]
+struct CalibrationConfig {
+    path: String,
+    #[config(default = "cam0")]
+    camera: String,
+}
+
+#[derive(Deserialize)]
+struct Matrix {
+    data: Vec<f64>,
+}
+
+/// OpenCV FileStorage and ROS camera_info.
+#[derive(Deserialize)]
+struct OpenCvCalibration {
+    image_width: u32,
+    image_height: u32,
+    camera_matrix: Matrix,
+    distortion_coefficients: Option<Matrix>,
+    distortion_model: Option<String>,
+}
+
+/// A camera of a Kalibr camchain.
+#[derive(Deserialize)]
+struct KalibrCamera {
+    camera_model: Option<String>,
+    intrinsics: Vec<f64>,
+    resolution: [u32; 2],
+    distortion_model: Option<String>,
+    #[serde(default)]
+    distortion_coeffs: Vec<f64>,
+    #[serde(rename = "T_cam_imu")]
+    t_cam_imu: Option<Vec<Vec<f64>>>,
+}
+
+fn parse_error(e: impl std::error::Error + Send + Sync + 'static) -> CuError {
+    CuError::new_with_cause("Calibration: Failed to parse the calibration", e)
+}
+
+/// The distortion of the lens from the name of its model and its coefficients, following the order
+/// of OpenCV: k1, k2, p1, p2, k3 for radtan and k1, k2, k3, k4 for the fisheyes.
+fn distortion(model: Option<&str>, coefficients: &[f64]) -> CuResult<CameraDistortion> {
+    let coefficient = |i: usize| coefficients.get(i).copied().unwrap_or_default();
+    match model.map(|model| model.to_lowercase()).as_deref() {
+        Some("none") => Ok(CameraDistortion::None),
+        None if coefficients.iter().all(|c| *c == 0.0) => Ok(CameraDistortion::None),
+        None | Some("plumb_bob" | "radtan" | "radial-tangential") => {
+            // The rational model of OpenCV has 8 coefficients or more, the extra ones being zeros
+            // when it was not enabled.
+            if coefficients.len() < 4 || coefficients.iter().skip(5).any(|c| *c != 0.0) {
+                return Err(CuError::from(format!(
+                    "Calibration: expected 4 or 5 radtan coefficients, got {coefficients:?}."
+                )));
+            }
+            Ok(CameraDistortion::RadTan {
+                k1: coefficient(0),
+                k2: coefficient(1),
+                p1: coefficient(2),
+                p2: coefficient(3),
+                k3: coefficient(4),
+            })
+        }
+        Some("equidistant" | "fisheye" | "kannala_brandt") => {
+            if coefficients.len() != 4 {
+                return Err(CuError::from(format!(
+                    "Calibration: expected 4 fisheye coefficients, got {coefficients:?}."
+                )));
+            }
+            Ok(CameraDistortion::Fisheye {
+                k1: coefficient(0),
+                k2: coefficient(1),
+                k3: coefficient(2),
+                k4: coefficient(3),
+            })
+        }
+        Some(model) => Err(CuError::from(format!(
+            "Calibration: the distortion model \"{model}\" is not supported, expected radtan, fisheye or none."
+        ))),
+    }
+}
+
+impl OpenCvCalibration {
+    fn calibration(&self) -> CuResult<CameraCalibration> {
+        let &[fx, _, cx, _, fy, cy, ..] = self.camera_matrix.data.as_slice() else {
+            return Err(CuError::from(
+                "Calibration: the camera_matrix should be a 3x3 matrix.",
+            ));
+        };
+        let coefficients = self
+            .distortion_coefficients
+            .as_ref()
+            .map(|matrix| matrix.data.as_slice())
+            .unwrap_or_default();
+        Ok(CameraCalibration {
+            width: self.image_width,
+            height: self.image_height,
+            fx,
+            fy,
+            cx,
+            cy,
+            distortion: distortion(self.distortion_model.as_deref(), coefficients)?,
+            extrinsics: None,
+        })
+    }
+}
+
+impl KalibrCamera {
+    fn calibration(&self) -> CuResult<CameraCalibration> {
+        if let Some(model) = self.camera_model.as_deref().filter(|m| *m != "pinhole") {
+            return Err(CuError::from(format!(
+                "Calibration: the camera model \"{model}\" is not supported, expected pinhole."
+            )));
+        }
+        let &[fx, fy, cx, cy] = self.intrinsics.as_slice() else {
+            return Err(CuError::from(
+                "Calibration: the intrinsics should be [fu, fv, pu, pv].",
+            ));
+        };
+        let extrinsics = match &self.t_cam_imu {
+            Some(rows) => {
+                let mut matrix = [[0.0; 4]; 4];
+                if rows.len() != 4 || rows.iter().any(|row| row.len() != 4) {
+                    return Err(CuError::from(
+                        "Calibration: T_cam_imu should be a 4x4 matrix.",
+                    ));
+                }
+                for (dst, src) in matrix.iter_mut().zip(rows) {
+                    dst.copy_from_slice(src);
+                }
+                Some(matrix)
+            }
+            None => None,
+        };
+        let [width, height] = self.resolution;
+        Ok(CameraCalibration {
+            width,
+            height,
+            fx,
+            fy,
+            cx,
+            cy,
+            distortion: distortion(self.distortion_model.as_deref(), &self.distortion_coeffs)?,
+            extrinsics,
+        })
+    }
+}
+
+/// Parses a calibration file, `camera` picks the camera of a Kalibr camchain.
+pub fn parse_calibration(text: &str, json: bool, camera: &str) -> CuResult<CameraCalibration> {
+    let document: serde_json::Value = if json {
+        serde_json::from_str(text).map_err(parse_error)?
+    } else {
+        // The YAML of OpenCV starts with a directive and tags its matrices, both unknown to serde_yaml.
+        let text = text
+            .strip_prefix("%YAML:1.0")
+            .unwrap_or(text)
+            .replace("!!opencv-matrix", "");
+        serde_yaml::from_str(&text).map_err(parse_error)?
+    };
+    if document.get("camera_matrix").is_some() {
+        let calibration: OpenCvCalibration =
+            serde_json::from_value(document).map_err(parse_error)?;
+        return calibration.calibration();
+    }
+    let Some(camera_document) = document.get(camera) else {
+        return Err(CuError::from(format!(
+            "Calibration: no camera_matrix and no camera \"{camera}\" in the calibration."
+        )));
+    };
+    let calibration: KalibrCamera =
+        serde_json::from_value(camera_document.clone()).map_err(parse_error)?;
+    calibration.calibration()
+}
+
+/// Loads a calibration file, in JSON if its extension is .json and in YAML otherwise.
+pub fn load_calibration(path: &Path, camera: &str) -> CuResult<CameraCalibration> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        CuError::new_with_cause(
+            &format!("Calibration: Failed to read {}", path.display()),
+            e,
+        )
+    })?;
+    let json = path
+        .extension()
+        .is_some_and(|extension| extension == "json");
+    parse_calibration(&text, json, camera)
+}
+
+/// This is a source publishing the calibration of its config file at the first copperlist,
+/// the file is read in `new()` so a bad calibration stops the robot before it starts.
+pub struct CalibrationSource {
+    calibration: CameraCalibration,
+    published: bool,
+}
+
+impl Freezable for CalibrationSource {}
+
+impl<'cl> CuSrcTask<'cl> for CalibrationSource {
+    type Output = output_msg!('cl, CameraCalibration);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let CalibrationConfig { path, camera } = CalibrationConfig::from_config(config)?;
+        Ok(Self {
+            calibration: load_calibration(&cu29::assets::resolve(&path), &camera)?,
+            published: false,
+        })
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        if self.published {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+        new_msg.set_payload(self.calibration);
+        new_msg.metadata.tov = clock.now().into();
+        self.published = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opencv_and_ros() {
+        let opencv = "%YAML:1.0
+---
+image_width: 640
+image_height: 480
+camera_matrix: !!opencv-matrix
+   rows: 3
+   cols: 3
+   dt: d
+   data: [ 500., 0., 320., 0., 501., 240., 0., 0., 1. ]
+distortion_coefficients: !!opencv-matrix
+   rows: 1
+   cols: 5
+   dt: d
+   data: [ -0.2, 0.05, 0.001, -0.002, 0. ]
+";
+        let calibration = parse_calibration(opencv, false, "cam0").unwrap();
+        assert_eq!(
+            calibration,
+            CameraCalibration {
+                width: 640,
+                height: 480,
+                fx: 500.0,
+                fy: 501.0,
+                cx: 320.0,
+                cy: 240.0,
+                distortion: CameraDistortion::RadTan {
+                    k1: -0.2,
+                    k2: 0.05,
+                    k3: 0.0,
+                    p1: 0.001,
+                    p2: -0.002,
+                },
+                extrinsics: None,
+            }
+        );
+
+        let ros = r#"{
+            "image_width": 640, "image_height": 480, "camera_name": "front",
+            "camera_matrix": {"rows": 3, "cols": 3, "data": [500, 0, 320, 0, 501, 240, 0, 0, 1]},
+            "distortion_model": "equidistant",
+            "distortion_coefficients": {"rows": 1, "cols": 4, "data": [0.1, 0.01, 0, 0]}
+        }"#;
+        let calibration = parse_calibration(ros, true, "cam0").unwrap();
+        assert_eq!(
+            calibration.distortion,
+            CameraDistortion::Fisheye {
+                k1: 0.1,
+                k2: 0.01,
+                k3: 0.0,
+                k4: 0.0,
+            }
+        );
+        assert!(parse_calibration(&ros.replace("equidistant", "omni"), true, "cam0").is_err());
+    }
+
+    #[test]
+    fn test_kalibr() {
+        let camchain = "
+cam0:
+  camera_model: pinhole
+  intrinsics: [458.6, 457.3, 367.2, 248.4]
+  distortion_model: radtan
+  distortion_coeffs: [-0.28, 0.07, 0.0002, 0.00002]
+  resolution: [752, 480]
+  T_cam_imu:
+  - [1.0, 0.0, 0.0, 0.1]
+  - [0.0, 1.0, 0.0, 0.0]
+  - [0.0, 0.0, 1.0, 0.0]
+  - [0.0, 0.0, 0.0, 1.0]
+cam1:
+  camera_model: pinhole
+  intrinsics: [457.6, 456.1, 379.9, 255.2]
+  distortion_model: equidistant
+  distortion_coeffs: [0.01, 0.002, -0.001, 0.0]
+  resolution: [752, 480]
+";
+        let cam0 = parse_calibration(camchain, false, "cam0").unwrap();
+        assert_eq!((cam0.width, cam0.height), (752, 480));
+        assert_eq!((cam0.fx, cam0.cy), (458.6, 248.4));
+        // A point 1m in front of the IMU is 1m in front of the camera, 10cm to the right.
+        let point = cam0.body_to_camera([0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(point, [0.1, 0.0, 1.0]);
+        assert!(cam0.project(point).unwrap().0 > cam0.cx);
+        assert!(cam0.project([0.0, 0.0, -1.0]).is_none());
+
+        let cam1 = parse_calibration(camchain, false, "cam1").unwrap();
+        assert!(matches!(cam1.distortion, CameraDistortion::Fisheye { .. }));
+        assert!(cam1.extrinsics.is_none());
+        assert!(parse_calibration(camchain, false, "cam2").is_err());
+    }
+}
@@ -163,18 +163,18 @@ impl Debug for Header {
 #[repr(C, packed)]
 #[derive(Copy, Clone, Zeroable, Pod)]
 pub struct Block {
-    azimuth: u16,                // Azimuth Angle
+    azimuth: u16,                // Azimuth Angle, raw endianness
     pub channels: [Channel; 32], // 32 channels per block
 }
 
 impl Block {
     pub fn azimuth(&self) -> Angle {
         // it is in 100th of degrees.
-        Angle::new::<degree>(self.azimuth as f32 / 100.0)
+        Angle::new::<degree>(u16_endianness(self.azimuth) as f32 / 100.0)
     }
 
     pub fn check_invariants(self) -> Result<(), HesaiError> {
-        if self.azimuth > 36000 {
+        if u16_endianness(self.azimuth) > 36000 {
             return Err(HesaiError::InvalidPacket(format!(
                 "Invalid azimuth: {}",
                 self.azimuth().into_format_args(degree, Abbreviation)
@@ -291,7 +291,7 @@ pub struct Tail {
     // The "μs time" part of the absolute time of this data packet (defined in Appendix II)
     // Unit: μs
     // Range: 0 to 1000000 μs (1 s)
-    timestamp: u32,
+    timestamp: u32, // !! raw endianness
 
     // Should be 0x42
     factory_info: u8,
@@ -338,7 +338,7 @@ impl Tail {
         ) {
             MappedLocalTime::None => Err(HesaiError::InvalidTimestamp("No such local time".into())),
             MappedLocalTime::Single(t) => {
-                Ok(t + chrono::Duration::microseconds(self.timestamp as i64))
+                Ok(t + chrono::Duration::microseconds(u32_endianness(self.timestamp) as i64))
             }
             MappedLocalTime::Ambiguous(_t1, _t2) => {
                 Err(HesaiError::InvalidTimestamp("Ambiguous time".into()))
@@ -390,7 +390,6 @@ fn u16_endianness(val: u16) -> u16 {
     }
 }
 
-#[allow(dead_code)]
 #[inline(always)]
 fn u32_endianness(val: u32) -> u32 {
     if cfg!(target_endian = "little") {
@@ -1,26 +1,72 @@
 pub mod parser;
 
-use crate::parser::RefTime;
-use chrono::Utc;
+use bincode::{Decode, Encode};
 use cu29::prelude::*;
 use cu_sensor_payloads::{PointCloud, PointCloudSoa};
+use cu_time_sync::DeviceClock;
+use parser::{DeviceStatus, SystemStatus, DATA_FRAME_TYPE2_SIZE, MAX_POINTS_TYPE2};
 use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use std::io::{ErrorKind, Read};
 use std::net::SocketAddr;
 const DEFAULT_ADDR: &str = "0.0.0.0:56001";
 
-pub struct Tele15 {
-    socket: Socket,
-    reftime: RefTime,
+/// Number of packets the device clock is estimated over.
+const SYNC_WINDOW: usize = 256;
+
+/// The alarm raised from the `alarm_source` of the task ("livox" by default, "livox/<lidar>" for
+/// the lidars of a LivoxReceiver) while a lidar reports a warning or an error in its status code.
+pub const LIDAR_HEALTH_ALARM: u32 = 1;
+
+/// The health of a lidar, from the status code of its last frame.
+struct LidarHealth {
+    alarm_source: String,
+    status: DeviceStatus,
 }
 
-impl Tele15 {
-    /// This give the matching timestamps between the UTC time to the Robot time.
-    fn sync(&mut self, robot_clock: &RobotClock) {
-        self.reftime = (Utc::now(), robot_clock.now());
+impl LidarHealth {
+    fn new(alarm_source: String) -> Self {
+        LidarHealth {
+            alarm_source,
+            status: DeviceStatus::default(),
+        }
+    }
+
+    /// Raises LIDAR_HEALTH_ALARM when the lidar starts reporting a warning or an error, clears it
+    /// when it is back to normal.
+    fn update(&mut self, clock: &RobotClock, status: DeviceStatus) {
+        let changed = status.system_status != self.status.system_status;
+        self.status = status;
+        if !changed {
+            return;
+        }
+        let severity = match status.system_status {
+            SystemStatus::Normal => {
+                clear_alarm(&self.alarm_source, LIDAR_HEALTH_ALARM);
+                return;
+            }
+            SystemStatus::Warning => AlarmSeverity::Warning,
+            SystemStatus::Error => AlarmSeverity::Error,
+        };
+        raise_alarm(
+            clock,
+            &self.alarm_source,
+            LIDAR_HEALTH_ALARM,
+            severity,
+            status.to_string(),
+        );
+    }
+
+    fn is_normal(&self) -> bool {
+        self.status.system_status == SystemStatus::Normal
     }
 }
 
+pub struct Tele15 {
+    socket: Socket,
+    device_clock: DeviceClock,
+    health: LidarHealth,
+}
+
 impl Freezable for Tele15 {}
 
 const MAX_POINTS: usize = 100;
@@ -34,6 +80,9 @@ impl<'cl> CuSrcTask<'cl> for Tele15 {
     where
         Self: Sized,
     {
+        let alarm_source = config
+            .and_then(|cfg| cfg.get::<String>("alarm_source"))
+            .unwrap_or_else(|| "livox".to_string());
         let addr: SocketAddr = if let Some(cfg) = config {
             let addr_str = cfg.get("socket_addr").unwrap_or(DEFAULT_ADDR.to_string());
             addr_str.as_str().parse().unwrap()
@@ -45,18 +94,18 @@ impl<'cl> CuSrcTask<'cl> for Tele15 {
         socket.bind(&SockAddr::from(addr)).unwrap();
         socket.set_nonblocking(true).unwrap();
 
-        // just a temporary value, it will be redone at start.
-        let rt: RefTime = (Utc::now(), RobotClock::new().now());
         Ok(Tele15 {
             socket,
-            reftime: rt,
+            device_clock: DeviceClock::new(SYNC_WINDOW),
+            health: LidarHealth::new(alarm_source),
         })
     }
-    fn start(&mut self, robot_clock: &RobotClock) -> CuResult<()> {
-        self.sync(robot_clock);
+    fn start(&mut self, _robot_clock: &RobotClock) -> CuResult<()> {
+        // The sensor could have been restarted in between.
+        self.device_clock.reset();
         Ok(())
     }
-    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
         let payload = new_msg.payload_mut().insert(LidarCuMsgPayload::default());
         let mut buf = [0u8; 1500];
         match self.socket.read(&mut buf) {
@@ -64,10 +113,19 @@ impl<'cl> CuSrcTask<'cl> for Tele15 {
                 let lidar_packet = parser::parse_frame(&buf[..size])
                     .map_err(|e| CuError::new_with_cause("Failed to parse Livox UDP packet", e))?;
 
+                let device_ts = lidar_packet.header.timestamp();
+                self.device_clock.observe(device_ts, clock.now());
+                let tov = self
+                    .device_clock
+                    .to_robot_time(device_ts)
+                    .expect("observed just before");
+                self.health.update(clock, lidar_packet.header.status());
+                new_msg.metadata.set_status(self.health.status.to_string());
+
                 // let is_dual = lidar_packet.header.is_dual_return(); TODO: add dual return support
                 for pt in lidar_packet.points.iter() {
                     payload.push(PointCloud::new_uom(
-                        lidar_packet.header.timestamp(),
+                        tov,
                         pt.x(),
                         pt.y(),
                         pt.z(),
@@ -78,6 +136,7 @@ impl<'cl> CuSrcTask<'cl> for Tele15 {
             }
             Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
                 // Handle no data available (non-blocking behavior)
+                new_msg.metadata.set_status(self.health.status.to_string());
                 new_msg.clear_payload();
                 return Ok(());
             }
@@ -87,11 +146,167 @@ impl<'cl> CuSrcTask<'cl> for Tele15 {
     }
 }
 
+/// The most lidars a LivoxReceiver listens to.
+pub const MAX_LIDARS: usize = 4;
+
+/// The largest datagram received, a jumbo one (MTU 9000) carries several frames back to back.
+const MAX_DATAGRAM_SIZE: usize = 9000;
+
+/// The most points of a datagram.
+pub const MAX_SCAN_POINTS: usize = MAX_DATAGRAM_SIZE / DATA_FRAME_TYPE2_SIZE * MAX_POINTS_TYPE2;
+
+/// The kernel buffer of each socket, to absorb the bursts of a lidar between two cycles.
+const SOCKET_RECV_BUFFER_SIZE: usize = 1 << 20;
+
+/// The points of a datagram of a lidar and the status it reports.
+#[derive(Default, Debug, Clone, Encode, Decode)]
+pub struct LivoxScan {
+    /// The position of the port of the lidar in the `ports` of the receiver.
+    pub lidar: u8,
+    pub status: DeviceStatus,
+    pub points: PointCloudSoa<MAX_SCAN_POINTS>,
+}
+
+/// The scans received in a cycle, at most one per lidar.
+pub type LivoxScans = CuArray<LivoxScan, MAX_LIDARS>;
+
+/// A lidar sending to its own port.
+struct LidarPort {
+    socket: Socket,
+    device_clock: DeviceClock,
+    health: LidarHealth,
+}
+
+/// Receives the point clouds of several Livox lidars, each sending to its own UDP port, and
+/// gives the datagrams received in the cycle tagged with the lidar they come from.
+/// The health the lidars report in their status code raises LIDAR_HEALTH_ALARM and shows in the
+/// status of the task.
+pub struct LivoxReceiver {
+    lidars: Vec<LidarPort>,
+    buf: Vec<u8>,
+}
+
+impl Freezable for LivoxReceiver {}
+
+impl<'cl> CuSrcTask<'cl> for LivoxReceiver {
+    type Output = output_msg!('cl, LivoxScans);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = config.ok_or_else(|| {
+            CuError::from("LivoxReceiver needs the \"ports\" of its lidars, like \"56001,56002\".")
+        })?;
+        let ports = config
+            .get::<String>("ports")
+            .ok_or_else(|| CuError::from("LivoxReceiver needs its \"ports\"."))?;
+        let bind_ip = config
+            .get::<String>("bind_ip")
+            .unwrap_or_else(|| "0.0.0.0".to_string());
+        let alarm_source = config
+            .get::<String>("alarm_source")
+            .unwrap_or_else(|| "livox".to_string());
+
+        let mut lidars = Vec::new();
+        for (index, port) in ports.split(',').map(str::trim).enumerate() {
+            if index == MAX_LIDARS {
+                return Err(
+                    format!("LivoxReceiver listens to {MAX_LIDARS} lidars at most.").into(),
+                );
+            }
+            let addr: SocketAddr = format!("{bind_ip}:{port}").parse().map_err(|e| {
+                CuError::new_with_cause(&format!("LivoxReceiver: invalid port {port}"), e)
+            })?;
+            let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+                .and_then(|socket| {
+                    socket.set_recv_buffer_size(SOCKET_RECV_BUFFER_SIZE)?;
+                    socket.bind(&SockAddr::from(addr))?;
+                    socket.set_nonblocking(true)?;
+                    Ok(socket)
+                })
+                .map_err(|e| {
+                    CuError::new_with_cause(&format!("LivoxReceiver: could not bind {addr}"), e)
+                })?;
+            lidars.push(LidarPort {
+                socket,
+                device_clock: DeviceClock::new(SYNC_WINDOW),
+                health: LidarHealth::new(format!("{alarm_source}/{index}")),
+            });
+        }
+        Ok(LivoxReceiver {
+            lidars,
+            buf: vec![0u8; MAX_DATAGRAM_SIZE],
+        })
+    }
+
+    fn start(&mut self, _robot_clock: &RobotClock) -> CuResult<()> {
+        // The sensors could have been restarted in between.
+        for lidar in &mut self.lidars {
+            lidar.device_clock.reset();
+        }
+        Ok(())
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let mut scans = Vec::with_capacity(self.lidars.len());
+        for (index, lidar) in self.lidars.iter_mut().enumerate() {
+            let size = match lidar.socket.read(&mut self.buf) {
+                Ok(size) => size,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(CuError::new_with_cause("IO Error on UDP socket", e)),
+            };
+            let mut scan = LivoxScan {
+                lidar: index as u8,
+                ..Default::default()
+            };
+            for frame in self.buf[..size].chunks(DATA_FRAME_TYPE2_SIZE) {
+                let lidar_packet = parser::parse_frame(frame)
+                    .map_err(|e| CuError::new_with_cause("Failed to parse Livox UDP packet", e))?;
+                let device_ts = lidar_packet.header.timestamp();
+                lidar.device_clock.observe(device_ts, clock.now());
+                let tov = lidar
+                    .device_clock
+                    .to_robot_time(device_ts)
+                    .expect("observed just before");
+                scan.status = lidar_packet.header.status();
+                for pt in lidar_packet.points.iter() {
+                    scan.points.push(PointCloud::new_uom(
+                        tov,
+                        pt.x(),
+                        pt.y(),
+                        pt.z(),
+                        pt.reflectivity(),
+                        None,
+                    ));
+                }
+            }
+            lidar.health.update(clock, scan.status);
+            scans.push(scan);
+        }
+
+        let healthy = self
+            .lidars
+            .iter()
+            .filter(|lidar| lidar.health.is_normal())
+            .count();
+        new_msg
+            .metadata
+            .set_status(format!("{healthy}/{} ok", self.lidars.len()));
+        if scans.is_empty() {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+        let payload = new_msg.payload_mut().insert(LivoxScans::default());
+        payload.fill_from_iter(scans);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::parser::LidarFrame;
-    use chrono::DateTime;
     use cu29::cutask::CuMsg;
     use cu_udp_inject::PcapStreamer;
 
@@ -106,12 +321,6 @@ mod tests {
         let new_payload = LidarCuMsgPayload::default();
         let mut new_msg = CuMsg::<LidarCuMsgPayload>::new(Some(new_payload));
 
-        // Picking a timestamp from the beginning of the pcap file to align the robot clock with the capture + 1s buffer in the past because ref times are negative.
-        let datetime = DateTime::parse_from_rfc3339("2024-09-17T15:47:11.684855Z")
-            .unwrap()
-            .with_timezone(&Utc);
-
-        tele15.reftime = (datetime, clock.now());
         const PACKET_SIZE: usize = size_of::<LidarFrame>();
         while streamer
             .send_next::<PACKET_SIZE>()
@@ -128,4 +337,54 @@ mod tests {
             break;
         }
     }
+
+    #[test]
+    fn test_receiver() {
+        use bytemuck::Zeroable;
+        use std::net::UdpSocket;
+
+        let mut frame = LidarFrame::zeroed();
+        frame.header.version = 0x05;
+        frame.header.data_type = 0x02;
+        frame.header.timestamp = 1_000_000u64.to_le();
+        let healthy = bytemuck::bytes_of(&frame).to_vec();
+        // A warning: dirty window.
+        frame.header.status_code = 0x4000_0040u32.to_le();
+        let dirty = bytemuck::bytes_of(&frame).to_vec();
+
+        let mut config = ComponentConfig::new();
+        config.set("ports", "56101, 56102".to_string());
+        config.set("bind_ip", "127.0.0.1".to_string());
+        config.set("alarm_source", "test_livox".to_string());
+        let mut receiver = LivoxReceiver::new(Some(&config)).unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(&healthy, "127.0.0.1:56101").unwrap();
+        // A jumbo datagram with 2 frames.
+        sender
+            .send_to(&[dirty.clone(), dirty].concat(), "127.0.0.1:56102")
+            .unwrap();
+
+        let clock = RobotClock::new();
+        let mut msg = CuMsg::<LivoxScans>::default();
+        let mut scans = Vec::new();
+        for _ in 0..100 {
+            receiver.process(&clock, &mut msg).unwrap();
+            if let Some(payload) = msg.payload() {
+                scans.extend(payload.as_slice().iter().cloned());
+            }
+            if scans.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        scans.sort_by_key(|scan| scan.lidar);
+        assert_eq!(scans.len(), 2);
+        assert_eq!(scans[0].points.len(), MAX_POINTS_TYPE2);
+        assert_eq!(scans[1].points.len(), 2 * MAX_POINTS_TYPE2);
+        assert_eq!(scans[1].status.system_status, SystemStatus::Warning);
+        assert!(alarms()
+            .iter()
+            .any(|alarm| alarm.source == "test_livox/1" && alarm.code == LIDAR_HEALTH_ALARM));
+    }
 }
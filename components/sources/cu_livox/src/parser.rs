@@ -1,6 +1,6 @@
+use bincode::{Decode, Encode};
 use bytemuck::{Pod, Zeroable};
-use chrono::{DateTime, Utc};
-use cu29::prelude::{CuDuration, CuTime};
+use cu29::prelude::CuDuration;
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
@@ -170,6 +170,100 @@ impl LidarHeader {
     pub fn timestamp(&self) -> CuDuration {
         CuDuration(u64_endianness(self.timestamp))
     }
+
+    pub fn status(&self) -> DeviceStatus {
+        DeviceStatus::from_code(u32_endianness(self.status_code))
+    }
+}
+
+/// The system_status of the status code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub enum SystemStatus {
+    #[default]
+    Normal,
+    Warning,
+    /// The LiDAR shut down.
+    Error,
+}
+
+/// The status_code of a LiDAR decoded, see the table of its bits above.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub struct DeviceStatus {
+    /// 0: normal, 1: high or low, 2: extremely high or low.
+    pub temp_status: u8,
+    /// 0: normal, 1: high, 2: extremely high.
+    pub volt_status: u8,
+    /// 0: normal, 1: warning, 2: error.
+    pub motor_status: u8,
+    pub dirty: bool,
+    pub firmware_abnormal: bool,
+    pub pps_ok: bool,
+    /// Approaching the end of its service life.
+    pub end_of_life: bool,
+    pub fan_warning: bool,
+    pub self_heating_off: bool,
+    pub ptp_ok: bool,
+    /// 0: none, 1: PTP, 2: GPS, 3: PPS, 4: abnormal.
+    pub time_sync_status: u8,
+    pub system_status: SystemStatus,
+}
+
+impl DeviceStatus {
+    pub fn from_code(code: u32) -> Self {
+        let bits = |shift: u32, mask: u32| ((code >> shift) & mask) as u8;
+        DeviceStatus {
+            temp_status: bits(0, 0b11),
+            volt_status: bits(2, 0b11),
+            motor_status: bits(4, 0b11),
+            dirty: bits(6, 0b11) != 0,
+            firmware_abnormal: bits(8, 1) != 0,
+            pps_ok: bits(9, 1) != 0,
+            end_of_life: bits(10, 1) != 0,
+            fan_warning: bits(11, 1) != 0,
+            self_heating_off: bits(12, 1) != 0,
+            ptp_ok: bits(13, 1) != 0,
+            time_sync_status: bits(14, 0b111),
+            system_status: match bits(30, 0b11) {
+                0 => SystemStatus::Normal,
+                1 => SystemStatus::Warning,
+                _ => SystemStatus::Error,
+            },
+        }
+    }
+}
+
+/// What is wrong with the LiDAR, like "motor warning, dirty window".
+impl fmt::Display for DeviceStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let level = |level: u8, warning: &'static str, error: &'static str| match level {
+            0 => None,
+            1 => Some(warning),
+            _ => Some(error),
+        };
+        let problems: Vec<&str> = [
+            level(
+                self.temp_status,
+                "temperature high or low",
+                "temperature extremely high or low",
+            ),
+            level(self.volt_status, "voltage high", "voltage extremely high"),
+            level(self.motor_status, "motor warning", "motor error"),
+            self.dirty.then_some("dirty window"),
+            self.firmware_abnormal.then_some("firmware abnormal"),
+            self.end_of_life
+                .then_some("end of service life approaching"),
+            self.fan_warning.then_some("fan warning"),
+            (self.time_sync_status == 4).then_some("time synchronization abnormal"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if problems.is_empty() {
+            write!(f, "normal")
+        } else {
+            write!(f, "{}", problems.join(", "))
+        }
+    }
 }
 
 #[repr(C, packed)]
@@ -250,7 +344,7 @@ impl Debug for crate::parser::PointType2 {
 // | timestamp      | 10           | 8           | Nanosecond or UTC Format Timestamp, For details, see 3.2 |
 // | data           | 18           | --          | Data information, For details, see [3.3](#3.3 Point Cloud/IMU Data {#data_type}) |
 
-const MAX_POINTS_TYPE2: usize = 96;
+pub const MAX_POINTS_TYPE2: usize = 96;
 pub const DATA_FRAME_TYPE2_SIZE: usize = size_of::<LidarFrame>();
 
 #[repr(C, packed)]
@@ -271,8 +365,6 @@ impl fmt::Display for LivoxError {
 
 impl Error for LivoxError {}
 
-pub type RefTime = (DateTime<Utc>, CuTime);
-
 pub fn parse_frame(data: &[u8]) -> Result<&LidarFrame, LivoxError> {
     if data[0] != 0x05
     // Protocol version
@@ -309,16 +401,10 @@ pub fn parse_frame(data: &[u8]) -> Result<&LidarFrame, LivoxError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::{parse_frame, LidarFrame, RefTime};
-    use chrono::prelude::*;
-    use cu29::prelude::RobotClock;
+    use crate::parser::{parse_frame, LidarFrame, SystemStatus};
 
     #[test]
     fn test_tele15_packet() {
-        let (robot_clock, mock) = RobotClock::mock();
-        // push the time by 1s because the first emulated test packet could end up in negative time.
-        mock.increment(std::time::Duration::new(1, 0));
-
         let packet_data: [u8; 1362] = [
             0x05, 0x01, 0x01, 0x00, 0x40, 0x68, 0x00, 0x40, 0x01, 0x02, 0x8B, 0x06, 0xAE, 0xE5,
             0xB4, 0x12, 0xF6, 0x17, 0xF6, 0x5C, 0x00, 0x00, 0x08, 0x08, 0x00, 0x00, 0xAB, 0x05,
@@ -426,9 +512,13 @@ mod tests {
 
         let packet = parse_frame(&packet_data).unwrap();
 
-        let datetime = Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap();
-        let _rt: RefTime = (datetime, robot_clock.now());
         let timestamp = packet.header.timestamp;
         println!("Tov: {timestamp}");
+
+        let status = packet.header.status();
+        assert_eq!(status.system_status, SystemStatus::Warning);
+        assert!(status.dirty && status.fan_warning && status.ptp_ok);
+        assert_eq!(status.time_sync_status, 1);
+        assert_eq!(status.to_string(), "dirty window, fan warning");
     }
 }
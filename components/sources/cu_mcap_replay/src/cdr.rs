@@ -0,0 +1,143 @@
+use cu29::prelude::*;
+
+/// Size of the encapsulation header in front of every serialized ROS 2 message.
+const ENCAPSULATION_HEADER_SIZE: usize = 4;
+
+/// A reader for the CDR encoding of the ROS 2 messages (message encoding "cdr" in MCAP).
+/// The fields are read in the order of the .msg definition.
+pub struct CdrReader<'a> {
+    /// The message after the encapsulation header, the alignment is relative to it.
+    data: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+}
+
+macro_rules! read_primitive {
+    ($name:ident, $ty:ty) => {
+        pub fn $name(&mut self) -> CuResult<$ty> {
+            const SIZE: usize = size_of::<$ty>();
+            self.align(SIZE);
+            let bytes: [u8; SIZE] = self.take(SIZE)?.try_into().unwrap();
+            Ok(if self.little_endian {
+                <$ty>::from_le_bytes(bytes)
+            } else {
+                <$ty>::from_be_bytes(bytes)
+            })
+        }
+    };
+}
+
+impl<'a> CdrReader<'a> {
+    pub fn new(data: &'a [u8]) -> CuResult<Self> {
+        if data.len() < ENCAPSULATION_HEADER_SIZE {
+            return Err("Cdr: the message is shorter than its encapsulation header".into());
+        }
+        // CDR_BE = 0x0000, CDR_LE = 0x0001
+        let little_endian = match data[1] {
+            0x00 => false,
+            0x01 => true,
+            other => {
+                return Err(format!("Cdr: unsupported encapsulation kind {other:#04x}").into());
+            }
+        };
+        Ok(Self {
+            data: &data[ENCAPSULATION_HEADER_SIZE..],
+            pos: 0,
+            little_endian,
+        })
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.next_multiple_of(alignment);
+    }
+
+    fn take(&mut self, size: usize) -> CuResult<&'a [u8]> {
+        let end = self.pos + size;
+        if end > self.data.len() {
+            return Err(format!(
+                "Cdr: read of {size} bytes at {} past the end of the message ({} bytes)",
+                self.pos,
+                self.data.len()
+            )
+            .into());
+        }
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    read_primitive!(read_u8, u8);
+    read_primitive!(read_i8, i8);
+    read_primitive!(read_u16, u16);
+    read_primitive!(read_i16, i16);
+    read_primitive!(read_u32, u32);
+    read_primitive!(read_i32, i32);
+    read_primitive!(read_u64, u64);
+    read_primitive!(read_i64, i64);
+    read_primitive!(read_f32, f32);
+    read_primitive!(read_f64, f64);
+
+    pub fn read_bool(&mut self) -> CuResult<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    /// The length of a sequence (unbounded or bounded array), the elements follow.
+    pub fn read_sequence_len(&mut self) -> CuResult<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> CuResult<&'a [u8]> {
+        self.take(len)
+    }
+
+    pub fn read_string(&mut self) -> CuResult<String> {
+        // The length counts the terminating nul.
+        let len = self.read_u32()? as usize;
+        let bytes = self.take(len)?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| CuError::new_with_cause("Cdr: the string is not valid utf-8", e))
+    }
+
+    /// A builtin_interfaces/msg/Time, ie. the stamp of a std_msgs/msg/Header, in ns.
+    pub fn read_time(&mut self) -> CuResult<u64> {
+        let sec = self.read_i32()?;
+        let nanosec = self.read_u32()?;
+        Ok((sec as i64 * 1_000_000_000 + nanosec as i64).max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cdr_reader() {
+        // A std_msgs/msg/Header { stamp: { sec: 2, nanosec: 5 }, frame_id: "map" } then a float64.
+        let mut data = vec![0x00, 0x01, 0x00, 0x00];
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(b"map\0");
+        data.extend_from_slice(&1.5f64.to_le_bytes());
+        data.push(1);
+
+        let mut reader = CdrReader::new(&data).unwrap();
+        assert_eq!(reader.read_time().unwrap(), 2_000_000_005);
+        assert_eq!(reader.read_string().unwrap(), "map");
+        assert_eq!(reader.read_f64().unwrap(), 1.5);
+        assert!(reader.read_bool().unwrap());
+        assert!(reader.read_u8().is_err());
+    }
+
+    #[test]
+    fn test_cdr_alignment_and_endianness() {
+        // A uint8 then a big endian int32 aligned on 4 bytes.
+        let data = [0x00, 0x00, 0x00, 0x00, 7, 0, 0, 0, 0x00, 0x00, 0x01, 0x02];
+        let mut reader = CdrReader::new(&data).unwrap();
+        assert_eq!(reader.read_u8().unwrap(), 7);
+        assert_eq!(reader.read_i32().unwrap(), 0x0102);
+
+        assert!(CdrReader::new(&[0x00, 0x02, 0x00, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+pub mod cdr;
+
+pub use cdr::CdrReader;
+
+use cu29::prelude::*;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// A message of the file, as given to the conversions.
+pub struct McapRecord<'a> {
+    /// The name of the schema of the channel, ie. `sensor_msgs/msg/Imu` for a rosbag2.
+    pub schema_name: &'a str,
+    /// How the message is serialized, ie. `cdr` for a rosbag2.
+    pub message_encoding: &'a str,
+    pub data: &'a [u8],
+}
+
+pub type Conversion<P> = fn(&McapRecord) -> CuResult<P>;
+
+/// The conversion table of a payload: the schemas it can be decoded from.
+/// Implement it for your own payloads to replay them, the conversions of the ROS 2 messages
+/// can use [CdrReader].
+pub trait FromMcap: CuMsgPayload + 'static {
+    fn conversions() -> &'static [(&'static str, Conversion<Self>)];
+}
+
+fn cdr_reader<'a>(record: &McapRecord<'a>) -> CuResult<CdrReader<'a>> {
+    if record.message_encoding != "cdr" {
+        return Err(format!(
+            "McapReplay: {} is expected in cdr, got {}",
+            record.schema_name, record.message_encoding
+        )
+        .into());
+    }
+    CdrReader::new(record.data)
+}
+
+/// The std_msgs with a single `data` field.
+macro_rules! impl_from_std_msgs {
+    ($ty:ty, $schema:literal, $read:ident) => {
+        impl FromMcap for $ty {
+            fn conversions() -> &'static [(&'static str, Conversion<Self>)] {
+                &[($schema, |record| cdr_reader(record)?.$read())]
+            }
+        }
+    };
+}
+
+impl_from_std_msgs!(bool, "std_msgs/msg/Bool", read_bool);
+impl_from_std_msgs!(i32, "std_msgs/msg/Int32", read_i32);
+impl_from_std_msgs!(i64, "std_msgs/msg/Int64", read_i64);
+impl_from_std_msgs!(u32, "std_msgs/msg/UInt32", read_u32);
+impl_from_std_msgs!(u64, "std_msgs/msg/UInt64", read_u64);
+impl_from_std_msgs!(f32, "std_msgs/msg/Float32", read_f32);
+impl_from_std_msgs!(f64, "std_msgs/msg/Float64", read_f64);
+impl_from_std_msgs!(String, "std_msgs/msg/String", read_string);
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct McapReplayConfig {
+    file: String,
+    topic: String,
+    #[config(default = true)]
+    realtime: bool,
+}
+
+struct Replayed {
+    /// ns, the time the message was recorded.
+    log_time: u64,
+    data: Vec<u8>,
+}
+
+/// The messages of the topic, loaded at start.
+struct Replay<P> {
+    schema_name: String,
+    message_encoding: String,
+    conversion: Conversion<P>,
+    messages: VecDeque<Replayed>,
+    /// The log time of the first message matches this Robot time.
+    first_log_time: u64,
+    start: CuTime,
+}
+
+/// This is a source task replaying one topic of an MCAP file, ie. a rosbag2 recorded with the mcap storage.
+/// The messages are converted with the conversion table of P, see [FromMcap].
+pub struct McapReplaySrc<P>
+where
+    P: FromMcap,
+{
+    _marker: PhantomData<P>,
+    file: String,
+    topic: String,
+    realtime: bool,
+    replay: Option<Replay<P>>,
+}
+
+impl<P> McapReplaySrc<P>
+where
+    P: FromMcap,
+{
+    fn load(&self, clock: &RobotClock) -> CuResult<Replay<P>> {
+        let bytes = std::fs::read(&self.file).map_err(|e| {
+            CuError::new_with_cause(&format!("McapReplay: Failed to read {}", self.file), e)
+        })?;
+        let stream = mcap::MessageStream::new(&bytes)
+            .map_err(|e| CuError::new_with_cause("McapReplay: Invalid MCAP file", e))?;
+
+        let mut channel = None;
+        let mut messages = VecDeque::new();
+        for message in stream {
+            let message =
+                message.map_err(|e| CuError::new_with_cause("McapReplay: Invalid message", e))?;
+            if message.channel.topic != self.topic {
+                continue;
+            }
+            if channel.is_none() {
+                let schema_name = message
+                    .channel
+                    .schema
+                    .as_ref()
+                    .map(|schema| schema.name.clone())
+                    .unwrap_or_default();
+                channel = Some((schema_name, message.channel.message_encoding.clone()));
+            }
+            messages.push_back(Replayed {
+                log_time: message.log_time,
+                data: message.data.into_owned(),
+            });
+        }
+
+        let Some((schema_name, message_encoding)) = channel else {
+            return Err(
+                format!("McapReplay: No message on {} in {}", self.topic, self.file).into(),
+            );
+        };
+        let conversion = P::conversions()
+            .iter()
+            .find(|(name, _)| *name == schema_name)
+            .map(|(_, conversion)| *conversion)
+            .ok_or_else(|| {
+                CuError::from(format!(
+                    "McapReplay: No conversion from {schema_name} to {}",
+                    std::any::type_name::<P>()
+                ))
+            })?;
+        // The file is not necessarily sorted by log time.
+        messages
+            .make_contiguous()
+            .sort_by_key(|message| message.log_time);
+        Ok(Replay {
+            schema_name,
+            message_encoding,
+            conversion,
+            first_log_time: messages[0].log_time,
+            messages,
+            start: clock.now(),
+        })
+    }
+}
+
+impl<P> Freezable for McapReplaySrc<P> where P: FromMcap {}
+
+impl<'cl, P> CuSrcTask<'cl> for McapReplaySrc<P>
+where
+    P: FromMcap + 'cl + 'static,
+{
+    type Output = output_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let McapReplayConfig {
+            file,
+            topic,
+            realtime,
+        } = McapReplayConfig::from_config(config)?;
+        Ok(Self {
+            _marker: Default::default(),
+            file,
+            topic,
+            realtime,
+            replay: None,
+        })
+    }
+
+    fn start(&mut self, clock: &RobotClock) -> CuResult<()> {
+        let replay = self.load(clock)?;
+        debug!(
+            "McapReplay: Replaying {} messages of {} ({})",
+            replay.messages.len(),
+            self.topic.as_str(),
+            replay.schema_name.as_str()
+        );
+        self.replay = Some(replay);
+        Ok(())
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let replay = self
+            .replay
+            .as_mut()
+            .ok_or_else(|| CuError::from("McapReplay: Replay not found"))?;
+
+        let Some(next) = replay.messages.front() else {
+            new_msg.clear_payload();
+            return Ok(());
+        };
+        let tov = replay.start + CuDuration(next.log_time - replay.first_log_time);
+        if self.realtime && clock.now() < tov {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+
+        let next = replay.messages.pop_front().unwrap();
+        let payload = (replay.conversion)(&McapRecord {
+            schema_name: &replay.schema_name,
+            message_encoding: &replay.message_encoding,
+            data: &next.data,
+        })?;
+        new_msg.set_payload(payload);
+        new_msg.metadata.tov = tov.into();
+        if replay.messages.is_empty() {
+            debug!("McapReplay: End of {}", self.topic.as_str());
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        // A restart replays the file from the beginning.
+        self.replay = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcap::records::MessageHeader;
+    use std::collections::BTreeMap;
+    use std::io::BufWriter;
+    use std::time::Duration;
+
+    fn float64(value: f64) -> Vec<u8> {
+        let mut data = vec![0x00, 0x01, 0x00, 0x00];
+        data.extend_from_slice(&value.to_le_bytes());
+        data
+    }
+
+    fn write_bag(path: &std::path::Path) {
+        let mut writer =
+            mcap::Writer::new(BufWriter::new(std::fs::File::create(path).unwrap())).unwrap();
+        let schema = writer
+            .add_schema("std_msgs/msg/Float64", "ros2msg", b"float64 data")
+            .unwrap();
+        let speed = writer
+            .add_channel(schema, "/speed", "cdr", &BTreeMap::new())
+            .unwrap();
+        let other = writer
+            .add_channel(schema, "/other", "cdr", &BTreeMap::new())
+            .unwrap();
+        for (sequence, (channel_id, log_time, value)) in [
+            (speed, 1_000_000_000, 1.0),
+            (other, 1_000_000_001, 42.0),
+            (speed, 1_050_000_000, 2.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            writer
+                .write_to_known_channel(
+                    &MessageHeader {
+                        channel_id,
+                        sequence: sequence as u32,
+                        log_time,
+                        publish_time: log_time,
+                    },
+                    &float64(value),
+                )
+                .unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        write_bag(&path);
+
+        let mut config = ComponentConfig::new();
+        config.set("file", path.to_str().unwrap().to_string());
+        config.set("topic", "/speed".to_string());
+        let (clock, mock) = RobotClock::mock();
+        let mut src = McapReplaySrc::<f64>::new(Some(&config)).unwrap();
+        src.start(&clock).unwrap();
+
+        let mut msg = CuMsg::<f64>::new(None);
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), Some(&1.0));
+        assert_eq!(msg.metadata.tov, Tov::Time(CuDuration(0)));
+
+        // Paced by the log times: the next one is 50ms later.
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), None);
+        mock.increment(Duration::from_millis(50));
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), Some(&2.0));
+        assert_eq!(msg.metadata.tov, Tov::Time(CuDuration(50_000_000)));
+
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), None);
+        src.stop(&clock).unwrap();
+
+        // As fast as possible.
+        config.set("realtime", false);
+        let mut src = McapReplaySrc::<f64>::new(Some(&config)).unwrap();
+        src.start(&clock).unwrap();
+        src.process(&clock, &mut msg).unwrap();
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), Some(&2.0));
+
+        // No conversion from Float64.
+        let mut src = McapReplaySrc::<String>::new(Some(&config)).unwrap();
+        assert!(src.start(&clock).is_err());
+    }
+}
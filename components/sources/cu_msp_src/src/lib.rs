@@ -146,8 +146,7 @@ impl<'cl> CuSrcTask<'cl> for MSPSrc {
     }
 }
 
-#[cfg(unix)]
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use serialport::SerialPort;
     use serialport::TTYPort;
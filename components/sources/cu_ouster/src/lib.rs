@@ -0,0 +1,167 @@
+pub mod parser;
+
+use crate::parser::{parse_angles, BeamIntrinsics, COLUMNS_PER_PACKET};
+use cu29::prelude::*;
+use cu_sensor_payloads::{PointCloud, PointCloudSoa};
+use cu_time_sync::DeviceClock;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+
+/// A packet of the largest sensor (OS1-128): 16 columns of 128 beams.
+const MAX_POINTS: usize = COLUMNS_PER_PACKET * 128;
+
+pub type LidarCuMsgPayload = PointCloudSoa<MAX_POINTS>;
+
+/// Number of packets the device clock is estimated over.
+const SYNC_WINDOW: usize = 256;
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct Os1Config {
+    #[config(default = "0.0.0.0:7502")]
+    listen_addr: String,
+    #[config(default = 64)]
+    pixels_per_column: u32,
+    #[config(default = 1024, range = 1..=u16::MAX as u32)]
+    columns_per_frame: u32,
+    beam_altitude_angles: String,
+    beam_azimuth_angles: String,
+    #[config(default = 15.806)]
+    lidar_origin_to_beam_origin_mm: f64,
+}
+
+/// This is a source task for the Ouster OS1, each message is one lidar packet (16 columns).
+pub struct Os1 {
+    listen_addr: String,
+    intrinsics: BeamIntrinsics,
+    socket: Option<UdpSocket>,
+    /// Sized for a packet of the configured sensor.
+    buf: Vec<u8>,
+    /// Maps the time of the sensor to the Robot time.
+    device_clock: DeviceClock,
+}
+
+impl Freezable for Os1 {}
+
+impl<'cl> CuSrcTask<'cl> for Os1 {
+    type Output = output_msg!('cl, LidarCuMsgPayload);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let Os1Config {
+            listen_addr,
+            pixels_per_column,
+            columns_per_frame,
+            beam_altitude_angles,
+            beam_azimuth_angles,
+            lidar_origin_to_beam_origin_mm,
+        } = Os1Config::from_config(config)?;
+        let altitude_angles = parse_angles(&beam_altitude_angles)
+            .map_err(|e| CuError::new_with_cause("Invalid beam_altitude_angles for Os1", e))?;
+        let azimuth_angles = parse_angles(&beam_azimuth_angles)
+            .map_err(|e| CuError::new_with_cause("Invalid beam_azimuth_angles for Os1", e))?;
+        if altitude_angles.len() != pixels_per_column as usize {
+            return Err(format!(
+                "Os1: {} beam_altitude_angles for {pixels_per_column} pixels_per_column.",
+                altitude_angles.len()
+            )
+            .into());
+        }
+        let intrinsics = BeamIntrinsics::new(
+            altitude_angles,
+            azimuth_angles,
+            lidar_origin_to_beam_origin_mm as f32,
+            columns_per_frame as u16,
+        )
+        .map_err(|e| CuError::new_with_cause("Invalid intrinsics for Os1", e))?;
+        let buf = vec![0u8; intrinsics.packet_size()];
+        Ok(Os1 {
+            listen_addr,
+            intrinsics,
+            socket: None,
+            buf,
+            device_clock: DeviceClock::new(SYNC_WINDOW),
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let socket = UdpSocket::bind(&self.listen_addr)
+            .map_err(|e| CuError::new_with_cause("Os1: Failed to bind the UDP socket", e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| CuError::new_with_cause("Os1: Failed to set nonblocking", e))?;
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let socket = self
+            .socket
+            .as_ref()
+            .ok_or_else(|| CuError::from("Os1: Socket not found"))?;
+        let read_size = match socket.recv(&mut self.buf) {
+            Ok(size) => size,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                new_msg.clear_payload();
+                return Ok(());
+            }
+            Err(e) => return Err(CuError::new_with_cause("IO Error on UDP socket", e)),
+        };
+        let columns = parser::parse_packet(&self.buf[..read_size], &self.intrinsics)
+            .map_err(|e| CuError::new_with_cause("Failed to parse Ouster UDP packet", e))?;
+
+        let device_ts = columns[0].header.timestamp();
+        self.device_clock.observe(device_ts, clock.now());
+        let device_clock = &self.device_clock;
+        let to_robot_time = |device| {
+            device_clock
+                .to_robot_time(device)
+                .expect("observed just before")
+        };
+        let payload = new_msg.payload_mut().insert(LidarCuMsgPayload::default());
+        for column in columns.iter().filter(|column| column.is_valid()) {
+            for point in column.points(&self.intrinsics) {
+                payload.push(PointCloud::new(
+                    to_robot_time(point.timestamp),
+                    point.x,
+                    point.y,
+                    point.z,
+                    point.reflectivity as f32,
+                    None,
+                ));
+            }
+        }
+        new_msg.metadata.tov = to_robot_time(device_ts).into();
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.socket = None;
+        // The sensor could have been restarted in between.
+        self.device_clock.reset();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_os1_config() {
+        let mut config = ComponentConfig::new();
+        let angles = vec!["0"; 16].join(",");
+        config.set("pixels_per_column", 16u32);
+        config.set("beam_altitude_angles", angles.clone());
+        config.set("beam_azimuth_angles", angles);
+        let os1 = Os1::new(Some(&config)).unwrap();
+        assert_eq!(os1.intrinsics.packet_size(), 16 * (16 + 16 * 12 + 4));
+        assert_eq!(os1.intrinsics.columns_per_frame, 1024);
+
+        config.set("pixels_per_column", 64u32);
+        assert!(Os1::new(Some(&config)).is_err());
+        assert!(Os1::new(None).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+use bytemuck::{Pod, Zeroable};
+use cu29::prelude::CuDuration;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::fmt::Formatter;
+use std::mem::size_of;
+
+// OS1 Software User Manual, "Lidar Data Format" (legacy profile, firmware < 2.0 and the LEGACY udp_profile_lidar).
+//
+// Lidar data packet, UDP port 7502 by default
+// | Field          | Size (byte)  | Description                                        |
+// | -------------- | ------------ | -------------------------------------------------- |
+// | columns        | 16 x column  | 16 consecutive azimuths                            |
+//
+// Column
+// | Field          | Size (byte)  | Description                                        |
+// | -------------- | ------------ | -------------------------------------------------- |
+// | timestamp      | 8            | ns, from the internal clock, the sync pulse or PTP |
+// | measurement id | 2            | index of the column in the frame                   |
+// | frame id       | 2            | incremented every rotation                         |
+// | encoder count  | 4            | 0 to 90111                                         |
+// | channels       | 12 x N       | one per beam, N = pixels_per_column                |
+// | status         | 4            | 0xFFFFFFFF if the column is valid                  |
+//
+// Channel data
+// | Field          | Size (byte)  | Description                                        |
+// | -------------- | ------------ | -------------------------------------------------- |
+// | range          | 4            | mm in the 20 lower bits, 0 means no return         |
+// | reflectivity   | 2            | calibrated reflectivity                            |
+// | signal         | 2            | signal photons                                     |
+// | near infrared  | 2            | ambient photons                                    |
+// | unused         | 2            |                                                    |
+
+pub const COLUMNS_PER_PACKET: usize = 16;
+const RANGE_MASK: u32 = 0x000F_FFFF;
+const VALID_COLUMN: u32 = 0xFFFF_FFFF;
+
+/// The OS1 default beam_to_lidar_transform translation.
+pub const OS1_LIDAR_ORIGIN_TO_BEAM_ORIGIN_MM: f32 = 15.806;
+
+#[derive(Debug)]
+pub enum OusterError {
+    InvalidPacket(String),
+    InvalidCalibration(String),
+}
+
+impl fmt::Display for OusterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OusterError::InvalidPacket(msg) => write!(f, "Invalid packet: {msg}"),
+            OusterError::InvalidCalibration(msg) => write!(f, "Invalid calibration: {msg}"),
+        }
+    }
+}
+
+impl Error for OusterError {}
+
+#[repr(C, packed)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct ColumnHeader {
+    timestamp: u64,
+    measurement_id: u16,
+    frame_id: u16,
+    encoder_count: u32,
+}
+
+impl ColumnHeader {
+    pub fn timestamp(&self) -> CuDuration {
+        CuDuration(u64::from_le(self.timestamp))
+    }
+
+    pub fn measurement_id(&self) -> u16 {
+        u16::from_le(self.measurement_id)
+    }
+
+    pub fn frame_id(&self) -> u16 {
+        u16::from_le(self.frame_id)
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct ChannelData {
+    range: u32,
+    reflectivity: u16,
+    signal: u16,
+    near_ir: u16,
+    unused: u16,
+}
+
+impl ChannelData {
+    /// Range in mm, 0 if there was no return.
+    pub fn range_mm(&self) -> u32 {
+        u32::from_le(self.range) & RANGE_MASK
+    }
+
+    pub fn reflectivity(&self) -> u16 {
+        u16::from_le(self.reflectivity)
+    }
+}
+
+/// The intrinsics of the sensor, from the `get_beam_intrinsics` and `get_lidar_data_format` commands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeamIntrinsics {
+    /// Elevation of each beam in degrees.
+    pub altitude_angles: Vec<f32>,
+    /// Azimuth offset of each beam in degrees.
+    pub azimuth_angles: Vec<f32>,
+    pub lidar_origin_to_beam_origin_mm: f32,
+    /// The horizontal resolution of the lidar mode, ie. 1024 for 1024x10.
+    pub columns_per_frame: u16,
+}
+
+impl BeamIntrinsics {
+    pub fn new(
+        altitude_angles: Vec<f32>,
+        azimuth_angles: Vec<f32>,
+        lidar_origin_to_beam_origin_mm: f32,
+        columns_per_frame: u16,
+    ) -> Result<Self, OusterError> {
+        if altitude_angles.len() != azimuth_angles.len() {
+            return Err(OusterError::InvalidCalibration(format!(
+                "{} altitude angles but {} azimuth angles",
+                altitude_angles.len(),
+                azimuth_angles.len()
+            )));
+        }
+        if ![16, 32, 64, 128].contains(&altitude_angles.len()) {
+            return Err(OusterError::InvalidCalibration(format!(
+                "{} beams, expected 16, 32, 64 or 128",
+                altitude_angles.len()
+            )));
+        }
+        if columns_per_frame == 0 {
+            return Err(OusterError::InvalidCalibration(
+                "columns_per_frame cannot be 0".to_string(),
+            ));
+        }
+        Ok(Self {
+            altitude_angles,
+            azimuth_angles,
+            lidar_origin_to_beam_origin_mm,
+            columns_per_frame,
+        })
+    }
+
+    pub fn pixels_per_column(&self) -> usize {
+        self.altitude_angles.len()
+    }
+
+    pub fn packet_size(&self) -> usize {
+        COLUMNS_PER_PACKET * column_size(self.pixels_per_column())
+    }
+}
+
+/// Parses a comma separated list of angles, ie. the beam_altitude_angles of the intrinsics.
+pub fn parse_angles(values: &str) -> Result<Vec<f32>, OusterError> {
+    values
+        .split(',')
+        .map(|value| value.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| OusterError::InvalidCalibration(format!("{values}: {e}")))
+}
+
+fn column_size(pixels_per_column: usize) -> usize {
+    size_of::<ColumnHeader>() + pixels_per_column * size_of::<ChannelData>() + size_of::<u32>()
+}
+
+/// A point in the lidar frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OusterPoint {
+    /// The timestamp of the column.
+    pub timestamp: CuDuration,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub reflectivity: u16,
+}
+
+/// One column of a packet.
+pub struct Column<'a> {
+    pub header: &'a ColumnHeader,
+    pub channels: &'a [ChannelData],
+    status: u32,
+}
+
+impl Column<'_> {
+    /// Columns are invalid when the sensor drops them, ie. at startup.
+    pub fn is_valid(&self) -> bool {
+        self.status == VALID_COLUMN
+    }
+
+    /// All the beams with a return.
+    pub fn points<'b>(
+        &'b self,
+        intrinsics: &'b BeamIntrinsics,
+    ) -> impl Iterator<Item = OusterPoint> + 'b {
+        let n = intrinsics.lidar_origin_to_beam_origin_mm;
+        // The encoder angle is computed from the measurement id, the encoder count is not as precise.
+        let theta_encoder = 2.0
+            * PI
+            * (1.0 - self.header.measurement_id() as f32 / intrinsics.columns_per_frame as f32);
+        self.channels
+            .iter()
+            .enumerate()
+            .filter(|(_, channel)| channel.range_mm() != 0)
+            .map(move |(beam, channel)| {
+                let r = channel.range_mm() as f32 - n;
+                let theta_azimuth = -intrinsics.azimuth_angles[beam].to_radians();
+                let phi = intrinsics.altitude_angles[beam].to_radians();
+                let x =
+                    r * (theta_encoder + theta_azimuth).cos() * phi.cos() + n * theta_encoder.cos();
+                let y =
+                    r * (theta_encoder + theta_azimuth).sin() * phi.cos() + n * theta_encoder.sin();
+                let z = r * phi.sin();
+                OusterPoint {
+                    timestamp: self.header.timestamp(),
+                    x: x / 1000.0,
+                    y: y / 1000.0,
+                    z: z / 1000.0,
+                    reflectivity: channel.reflectivity(),
+                }
+            })
+    }
+}
+
+/// Splits a lidar packet in its 16 columns.
+pub fn parse_packet<'a>(
+    data: &'a [u8],
+    intrinsics: &BeamIntrinsics,
+) -> Result<Vec<Column<'a>>, OusterError> {
+    if data.len() != intrinsics.packet_size() {
+        return Err(OusterError::InvalidPacket(format!(
+            "Wrong packet size: {} != {} for {} beams",
+            data.len(),
+            intrinsics.packet_size(),
+            intrinsics.pixels_per_column()
+        )));
+    }
+    let channels_size = intrinsics.pixels_per_column() * size_of::<ChannelData>();
+    let columns = data
+        .chunks_exact(column_size(intrinsics.pixels_per_column()))
+        .map(|column| {
+            let (header, rest) = column.split_at(size_of::<ColumnHeader>());
+            let (channels, status) = rest.split_at(channels_size);
+            let measurement_id = bytemuck::from_bytes::<ColumnHeader>(header).measurement_id();
+            if measurement_id >= intrinsics.columns_per_frame {
+                return Err(OusterError::InvalidPacket(format!(
+                    "Measurement id {measurement_id} out of the {} columns of the frame",
+                    intrinsics.columns_per_frame
+                )));
+            }
+            Ok(Column {
+                header: bytemuck::from_bytes(header),
+                channels: bytemuck::cast_slice(channels),
+                status: u32::from_le_bytes(status.try_into().unwrap()),
+            })
+        })
+        .collect::<Result<Vec<Column>, OusterError>>()?;
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEAMS: usize = 16;
+
+    fn intrinsics() -> BeamIntrinsics {
+        BeamIntrinsics::new(
+            (0..BEAMS).map(|beam| 15.0 - 2.0 * beam as f32).collect(),
+            vec![0.0; BEAMS],
+            OS1_LIDAR_ORIGIN_TO_BEAM_ORIGIN_MM,
+            1024,
+        )
+        .unwrap()
+    }
+
+    /// A packet where every beam of every column measures `range_mm`.
+    fn packet(first_measurement_id: u16, range_mm: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for column in 0..COLUMNS_PER_PACKET as u16 {
+            let header = ColumnHeader {
+                timestamp: (1_000_000 + column as u64 * 97_656).to_le(),
+                measurement_id: (first_measurement_id + column).to_le(),
+                frame_id: 3u16.to_le(),
+                encoder_count: 0,
+            };
+            data.extend_from_slice(bytemuck::bytes_of(&header));
+            for _ in 0..BEAMS {
+                let channel = ChannelData {
+                    range: range_mm.to_le(),
+                    reflectivity: 42u16.to_le(),
+                    signal: 0,
+                    near_ir: 0,
+                    unused: 0,
+                };
+                data.extend_from_slice(bytemuck::bytes_of(&channel));
+            }
+            data.extend_from_slice(&VALID_COLUMN.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_packet() {
+        let intrinsics = intrinsics();
+        let data = packet(0, 10_000);
+        assert_eq!(data.len(), 16 * (16 + 16 * 12 + 4));
+        let columns = parse_packet(&data, &intrinsics).unwrap();
+        assert_eq!(columns.len(), COLUMNS_PER_PACKET);
+        assert!(columns.iter().all(|column| column.is_valid()));
+        assert_eq!(columns[1].header.measurement_id(), 1);
+        assert_eq!(columns[1].header.frame_id(), 3);
+        assert_eq!(columns[1].header.timestamp(), CuDuration(1_097_656));
+
+        // Column 0 looks along +x, the first beam is 15 degrees up.
+        let point = columns[0].points(&intrinsics).next().unwrap();
+        assert_eq!(point.reflectivity, 42);
+        let r = (10_000.0 - OS1_LIDAR_ORIGIN_TO_BEAM_ORIGIN_MM) / 1000.0;
+        let expected_x = r * 15f32.to_radians().cos() + OS1_LIDAR_ORIGIN_TO_BEAM_ORIGIN_MM / 1000.0;
+        assert!((point.x - expected_x).abs() < 1e-5);
+        assert!(point.y.abs() < 1e-5);
+        assert!((point.z - r * 15f32.to_radians().sin()).abs() < 1e-5);
+
+        // A quarter turn later, the sensor rotates clockwise seen from above.
+        let columns_data = packet(256, 10_000);
+        let columns = parse_packet(&columns_data, &intrinsics).unwrap();
+        let point = columns[0].points(&intrinsics).next().unwrap();
+        assert!(point.x.abs() < 1e-5);
+        assert!((point.y + expected_x).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_invalid_packets() {
+        let intrinsics = intrinsics();
+        let data = packet(0, 0);
+        let columns = parse_packet(&data, &intrinsics).unwrap();
+        assert_eq!(columns[0].points(&intrinsics).count(), 0);
+
+        assert!(parse_packet(&data[..100], &intrinsics).is_err());
+        assert!(parse_packet(&packet(1020, 1000), &intrinsics).is_err());
+        assert!(BeamIntrinsics::new(vec![0.0; 16], vec![0.0; 15], 0.0, 1024).is_err());
+        assert!(BeamIntrinsics::new(vec![0.0; 12], vec![0.0; 12], 0.0, 1024).is_err());
+        assert_eq!(parse_angles("1.5, -2").unwrap(), vec![1.5, -2.0]);
+        assert!(parse_angles("1.5, up").is_err());
+    }
+}
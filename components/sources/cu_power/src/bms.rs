@@ -0,0 +1,166 @@
+//! The JBD (Xiaoxiang) smart BMS protocol over serial/UART.
+//! A request is `DD A5 <cmd> <len> <data> <checksum:2> 77`, the answer `DD <cmd> <status> <len> <data> <checksum:2> 77`.
+//! The checksum is the two's complement of the sum of the bytes from the command (request) or status (answer)
+//! to the end of the data.
+
+use crate::{PowerDevice, PowerRail, PowerStatus};
+use cu29::prelude::*;
+use serialport::SerialPort;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+const START: u8 = 0xDD;
+const READ: u8 = 0xA5;
+const END: u8 = 0x77;
+const BASIC_INFO: u8 = 0x03;
+
+fn checksum(bytes: &[u8]) -> u16 {
+    0u16.wrapping_sub(bytes.iter().map(|b| *b as u16).sum())
+}
+
+pub(crate) fn basic_info_request() -> [u8; 7] {
+    let [high, low] = checksum(&[BASIC_INFO, 0]).to_be_bytes();
+    [START, READ, BASIC_INFO, 0, high, low, END]
+}
+
+/// The length of the answer given its first 4 bytes.
+pub(crate) fn frame_len(header: &[u8; 4]) -> usize {
+    4 + header[3] as usize + 3
+}
+
+fn u16_at(data: &[u8], offset: usize) -> CuResult<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "PowerMonitor: BMS basic info too short".into())
+}
+
+/// Decodes the answer to a basic info request: pack voltage and current, state of charge and temperature.
+pub(crate) fn parse_basic_info(frame: &[u8]) -> CuResult<PowerStatus> {
+    if frame.len() < 7
+        || frame[0] != START
+        || frame[1] != BASIC_INFO
+        || frame[frame.len() - 1] != END
+    {
+        return Err("PowerMonitor: Invalid BMS frame".into());
+    }
+    if frame[2] != 0 {
+        return Err(format!(
+            "PowerMonitor: The BMS answered with the error {:#04x}",
+            frame[2]
+        )
+        .into());
+    }
+    let len = frame[3] as usize;
+    if frame.len() != frame_len(&[frame[0], frame[1], frame[2], frame[3]]) {
+        return Err("PowerMonitor: Truncated BMS frame".into());
+    }
+    let expected = u16::from_be_bytes([frame[4 + len], frame[5 + len]]);
+    if checksum(&frame[2..4 + len]) != expected {
+        return Err("PowerMonitor: Bad BMS checksum".into());
+    }
+    let data = &frame[4..4 + len];
+    // 10mV, 10mA (positive when charging).
+    let voltage = u16_at(data, 0)? as f32 * 0.01;
+    let current = u16_at(data, 2)? as i16 as f32 * 0.01;
+    let state_of_charge = *data
+        .get(19)
+        .ok_or_else(|| CuError::from("PowerMonitor: BMS basic info too short"))?;
+    let sensors = data.get(22).copied().unwrap_or(0) as usize;
+    // 0.1K, the hottest sensor.
+    let temperature = (0..sensors)
+        .map(|i| u16_at(data, 23 + 2 * i).map(|t| (t as f32 - 2731.0) * 0.1))
+        .collect::<CuResult<Vec<_>>>()?
+        .into_iter()
+        .reduce(f32::max);
+    Ok(PowerStatus {
+        rails: vec![PowerRail { voltage, current }],
+        state_of_charge: Some(state_of_charge as f32 / 100.0),
+        temperature,
+        ..Default::default()
+    })
+}
+
+pub(crate) struct JbdBms {
+    port: Box<dyn SerialPort>,
+    name: String,
+}
+
+impl JbdBms {
+    pub(crate) fn open(name: &str, baudrate: u32) -> CuResult<Self> {
+        let port = serialport::new(name, baudrate)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .map_err(|e| {
+                CuError::new_with_cause(&format!("PowerMonitor: Failed to open {name}"), e)
+            })?;
+        Ok(Self {
+            port,
+            name: name.to_string(),
+        })
+    }
+}
+
+impl PowerDevice for JbdBms {
+    fn read(&mut self) -> CuResult<PowerStatus> {
+        let map_error = |e| {
+            CuError::new_with_cause(
+                &format!("PowerMonitor: Failed to talk to the BMS on {}", self.name),
+                e,
+            )
+        };
+        self.port
+            .clear(serialport::ClearBuffer::Input)
+            .map_err(|e| {
+                CuError::new_with_cause("PowerMonitor: Failed to clear the BMS input", e)
+            })?;
+        self.port
+            .write_all(&basic_info_request())
+            .map_err(map_error)?;
+        let mut header = [0u8; 4];
+        self.port.read_exact(&mut header).map_err(map_error)?;
+        let mut frame = header.to_vec();
+        frame.resize(frame_len(&header), 0);
+        self.port.read_exact(&mut frame[4..]).map_err(map_error)?;
+        parse_basic_info(&frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        assert_eq!(
+            basic_info_request(),
+            [0xDD, 0xA5, 0x03, 0x00, 0xFF, 0xFD, 0x77]
+        );
+    }
+
+    #[test]
+    fn test_basic_info() {
+        let mut data = vec![0u8; 27];
+        // 25.12V, -1.5A
+        data[0..2].copy_from_slice(&2512u16.to_be_bytes());
+        data[2..4].copy_from_slice(&(-150i16).to_be_bytes());
+        data[19] = 42;
+        data[22] = 2;
+        // 25°C and 30.5°C
+        data[23..25].copy_from_slice(&2981u16.to_be_bytes());
+        data[25..27].copy_from_slice(&3036u16.to_be_bytes());
+        let mut frame = vec![0xDD, 0x03, 0x00, data.len() as u8];
+        frame.extend_from_slice(&data);
+        frame.extend_from_slice(&checksum(&frame[2..]).to_be_bytes());
+        frame.push(0x77);
+
+        let status = parse_basic_info(&frame).unwrap();
+        assert!((status.voltage() - 25.12).abs() < 1e-4);
+        assert!((status.rails[0].current + 1.5).abs() < 1e-4);
+        assert_eq!(status.state_of_charge, Some(0.42));
+        assert!((status.temperature.unwrap() - 30.5).abs() < 1e-4);
+
+        let last = frame.len() - 2;
+        frame[last] ^= 1;
+        assert!(parse_basic_info(&frame).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! The Texas Instruments INA219 (one channel) and INA3221 (three channels) current and voltage monitors.
+//! Both are read with their power-on configuration: continuous shunt and bus conversions.
+
+use crate::PowerRail;
+
+/// Registers are big endian, the conversions are in the upper 13 bits.
+fn conversion(raw: [u8; 2]) -> i16 {
+    i16::from_be_bytes(raw) >> 3
+}
+
+/// INA219: shunt voltage LSB 10µV (full 16 bits), bus voltage LSB 4mV.
+pub(crate) const INA219_SHUNT: u8 = 0x01;
+pub(crate) const INA219_BUS: u8 = 0x02;
+
+pub(crate) fn ina219_rail(shunt: [u8; 2], bus: [u8; 2], shunt_ohms: f32) -> PowerRail {
+    let shunt_voltage = i16::from_be_bytes(shunt) as f32 * 10e-6;
+    let bus_voltage = (u16::from_be_bytes(bus) >> 3) as f32 * 4e-3;
+    PowerRail {
+        voltage: bus_voltage,
+        current: shunt_voltage / shunt_ohms,
+    }
+}
+
+/// INA3221: per channel, a shunt and a bus register, shunt voltage LSB 40µV, bus voltage LSB 8mV.
+pub(crate) const INA3221_CHANNELS: u8 = 3;
+
+pub(crate) fn ina3221_shunt_register(channel: u8) -> u8 {
+    0x01 + 2 * channel
+}
+
+pub(crate) fn ina3221_bus_register(channel: u8) -> u8 {
+    0x02 + 2 * channel
+}
+
+pub(crate) fn ina3221_rail(shunt: [u8; 2], bus: [u8; 2], shunt_ohms: f32) -> PowerRail {
+    let shunt_voltage = conversion(shunt) as f32 * 40e-6;
+    let bus_voltage = conversion(bus) as f32 * 8e-3;
+    PowerRail {
+        voltage: bus_voltage,
+        current: shunt_voltage / shunt_ohms,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use device::{InaDevice, InaModel};
+
+#[cfg(target_os = "linux")]
+mod device {
+    use super::*;
+    use crate::{PowerDevice, PowerStatus};
+    use cu29::prelude::*;
+    use embedded_hal::i2c::I2c;
+    use linux_embedded_hal::I2cdev;
+
+    #[derive(Clone, Copy)]
+    pub(crate) enum InaModel {
+        Ina219,
+        Ina3221,
+    }
+
+    pub(crate) struct InaDevice {
+        i2c: I2cdev,
+        address: u8,
+        model: InaModel,
+        shunt_ohms: f32,
+    }
+
+    impl InaDevice {
+        pub(crate) fn open(
+            bus: &str,
+            address: u8,
+            model: InaModel,
+            shunt_ohms: f32,
+        ) -> CuResult<Self> {
+            let i2c = I2cdev::new(bus).map_err(|e| {
+                CuError::new_with_cause(&format!("PowerMonitor: Failed to open {bus}"), e)
+            })?;
+            Ok(Self {
+                i2c,
+                address,
+                model,
+                shunt_ohms,
+            })
+        }
+
+        fn register(&mut self, register: u8) -> CuResult<[u8; 2]> {
+            let mut raw = [0u8; 2];
+            self.i2c
+                .write_read(self.address, &[register], &mut raw)
+                .map_err(|e| {
+                    CuError::new_with_cause(
+                        &format!(
+                            "PowerMonitor: Failed to read the register {register:#04x} at {:#04x}",
+                            self.address
+                        ),
+                        e,
+                    )
+                })?;
+            Ok(raw)
+        }
+    }
+
+    impl PowerDevice for InaDevice {
+        fn read(&mut self) -> CuResult<PowerStatus> {
+            let rails = match self.model {
+                InaModel::Ina219 => {
+                    let shunt = self.register(INA219_SHUNT)?;
+                    let bus = self.register(INA219_BUS)?;
+                    vec![ina219_rail(shunt, bus, self.shunt_ohms)]
+                }
+                InaModel::Ina3221 => (0..INA3221_CHANNELS)
+                    .map(|channel| {
+                        let shunt = self.register(ina3221_shunt_register(channel))?;
+                        let bus = self.register(ina3221_bus_register(channel))?;
+                        Ok(ina3221_rail(shunt, bus, self.shunt_ohms))
+                    })
+                    .collect::<CuResult<Vec<_>>>()?,
+            };
+            Ok(PowerStatus {
+                rails,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ina219() {
+        // 12.000V on the bus, 32mV on a 0.1 ohm shunt.
+        let rail = ina219_rail(3200i16.to_be_bytes(), (3000u16 << 3).to_be_bytes(), 0.1);
+        assert!((rail.voltage - 12.0).abs() < 1e-4);
+        assert!((rail.current - 0.32).abs() < 1e-4);
+        // Discharging the other way.
+        let rail = ina219_rail((-3200i16).to_be_bytes(), 0u16.to_be_bytes(), 0.1);
+        assert!((rail.current + 0.32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_ina3221() {
+        // 5.000V on the bus, -4mV on a 0.1 ohm shunt.
+        let rail = ina3221_rail(
+            (-100i16 << 3).to_be_bytes(),
+            (625i16 << 3).to_be_bytes(),
+            0.1,
+        );
+        assert!((rail.voltage - 5.0).abs() < 1e-4);
+        assert!((rail.current + 0.04).abs() < 1e-4);
+        assert_eq!(ina3221_shunt_register(2), 0x05);
+        assert_eq!(ina3221_bus_register(2), 0x06);
+    }
+}
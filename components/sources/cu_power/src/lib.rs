@@ -0,0 +1,359 @@
+mod bms;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod ina;
+
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A monitored rail: for a battery, the pack.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct PowerRail {
+    /// V
+    pub voltage: f32,
+    /// A, the sign follows the wiring of the shunt (for a BMS, positive when charging).
+    pub current: f32,
+}
+
+impl PowerRail {
+    /// W
+    pub fn power(&self) -> f32 {
+        self.voltage * self.current
+    }
+}
+
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Encode,
+    Decode,
+    Serialize,
+    Deserialize,
+)]
+pub enum PowerLevel {
+    #[default]
+    Normal,
+    Low,
+    Critical,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct PowerStatus {
+    /// The first rail is the battery the thresholds apply to, the INA3221 has 3 rails.
+    pub rails: Vec<PowerRail>,
+    /// From 0 to 1, if the device estimates it.
+    pub state_of_charge: Option<f32>,
+    /// °C, the hottest sensor of the device if it has any.
+    pub temperature: Option<f32>,
+    pub level: PowerLevel,
+}
+
+impl PowerStatus {
+    /// The battery voltage.
+    pub fn voltage(&self) -> f32 {
+        self.rails
+            .first()
+            .map(|rail| rail.voltage)
+            .unwrap_or_default()
+    }
+}
+
+/// A device measuring the power status.
+pub(crate) trait PowerDevice {
+    fn read(&mut self) -> CuResult<PowerStatus>;
+}
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct PowerMonitorConfig {
+    device: String,
+    #[config(default = "/dev/i2c-1")]
+    i2c_bus: String,
+    #[config(default = 0x40)]
+    address: u8,
+    #[config(default = 0.1)]
+    shunt_ohms: f64,
+    #[config(default = "/dev/ttyUSB0")]
+    serial_port: String,
+    #[config(default = 9600)]
+    baudrate: u32,
+    #[config(default = 100)]
+    period_ms: u32,
+    low_voltage: Option<f64>,
+    critical_voltage: Option<f64>,
+    #[config(range = 0.0..=1.0)]
+    low_soc: Option<f64>,
+    #[config(range = 0.0..=1.0)]
+    critical_soc: Option<f64>,
+    #[config(default = 0.1)]
+    hysteresis_v: f64,
+    #[config(default = 0.02)]
+    hysteresis_soc: f64,
+    #[config(default = "power")]
+    alarm_source: String,
+}
+
+/// A value compared to a low and a critical threshold, with some hysteresis to get out of a level.
+struct Thresholds {
+    low: Option<f32>,
+    critical: Option<f32>,
+    hysteresis: f32,
+}
+
+impl Thresholds {
+    fn level(&self, value: f32, current: PowerLevel) -> PowerLevel {
+        let under = |threshold: Option<f32>, level: PowerLevel| match threshold {
+            // Staying at a level requires to go above the threshold plus the hysteresis.
+            Some(threshold) if current >= level => value < threshold + self.hysteresis,
+            Some(threshold) => value < threshold,
+            None => false,
+        };
+        if under(self.critical, PowerLevel::Critical) {
+            PowerLevel::Critical
+        } else if under(self.low, PowerLevel::Low) {
+            PowerLevel::Low
+        } else {
+            PowerLevel::Normal
+        }
+    }
+}
+
+/// The code of the low battery alarm, its severity follows the level.
+pub const LOW_BATTERY_ALARM: u32 = 1;
+
+/// This is a source task reading the power status from an INA219, an INA3221 or a JBD smart BMS every
+/// `period_ms`. When the battery voltage or state of charge goes under its low (critical) threshold, the task
+/// raises a Warning (Critical) alarm which is forwarded to the monitor, the alarm is cleared once the battery
+/// is above the threshold plus the hysteresis.
+pub struct PowerMonitor {
+    device: Box<dyn PowerDevice + Send>,
+    period: CuDuration,
+    last_read: Option<CuTime>,
+    voltage: Thresholds,
+    soc: Thresholds,
+    level: PowerLevel,
+    alarm_source: String,
+}
+
+impl PowerMonitor {
+    fn update_level(&mut self, clock: &RobotClock, status: &mut PowerStatus) {
+        let mut level = self.voltage.level(status.voltage(), self.level);
+        if let Some(soc) = status.state_of_charge {
+            level = level.max(self.soc.level(soc, self.level));
+        }
+        status.level = level;
+        if level == self.level {
+            return;
+        }
+        self.level = level;
+        let message = match status.state_of_charge {
+            Some(soc) => format!("Battery at {:.2}V, {:.0}%", status.voltage(), soc * 100.0),
+            None => format!("Battery at {:.2}V", status.voltage()),
+        };
+        match level {
+            PowerLevel::Normal => clear_alarm(&self.alarm_source, LOW_BATTERY_ALARM),
+            PowerLevel::Low => raise_alarm(
+                clock,
+                &self.alarm_source,
+                LOW_BATTERY_ALARM,
+                AlarmSeverity::Warning,
+                message,
+            ),
+            PowerLevel::Critical => raise_alarm(
+                clock,
+                &self.alarm_source,
+                LOW_BATTERY_ALARM,
+                AlarmSeverity::Critical,
+                message,
+            ),
+        }
+    }
+}
+
+impl Freezable for PowerMonitor {}
+
+impl<'cl> CuSrcTask<'cl> for PowerMonitor {
+    type Output = output_msg!('cl, PowerStatus);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = PowerMonitorConfig::from_config(config)?;
+        let device: Box<dyn PowerDevice + Send> = match config.device.as_str() {
+            #[cfg(target_os = "linux")]
+            "ina219" => Box::new(ina::InaDevice::open(
+                &config.i2c_bus,
+                config.address,
+                ina::InaModel::Ina219,
+                config.shunt_ohms as f32,
+            )?),
+            #[cfg(target_os = "linux")]
+            "ina3221" => Box::new(ina::InaDevice::open(
+                &config.i2c_bus,
+                config.address,
+                ina::InaModel::Ina3221,
+                config.shunt_ohms as f32,
+            )?),
+            "jbd_bms" => Box::new(bms::JbdBms::open(&config.serial_port, config.baudrate)?),
+            other => {
+                return Err(format!(
+                    "PowerMonitor: Unsupported device {other}, expected ina219, ina3221 (Linux only) or jbd_bms."
+                )
+                .into())
+            }
+        };
+        Ok(Self::with_device(device, &config))
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let now = clock.now();
+        if self
+            .last_read
+            .is_some_and(|last_read| now - last_read < self.period)
+        {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+        self.last_read = Some(now);
+        let mut status = self.device.read()?;
+        self.update_level(clock, &mut status);
+        new_msg.metadata.tov = now.into();
+        new_msg.metadata.set_status(format!(
+            "{:.2}V {:.2}A",
+            status.voltage(),
+            status
+                .rails
+                .first()
+                .map(|rail| rail.current)
+                .unwrap_or_default()
+        ));
+        new_msg.set_payload(status);
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.last_read = None;
+        Ok(())
+    }
+}
+
+impl PowerMonitor {
+    fn with_device(device: Box<dyn PowerDevice + Send>, config: &PowerMonitorConfig) -> Self {
+        Self {
+            device,
+            period: CuDuration::from(config.period_ms as u64 * 1_000_000),
+            last_read: None,
+            voltage: Thresholds {
+                low: config.low_voltage.map(|v| v as f32),
+                critical: config.critical_voltage.map(|v| v as f32),
+                hysteresis: config.hysteresis_v as f32,
+            },
+            soc: Thresholds {
+                low: config.low_soc.map(|v| v as f32),
+                critical: config.critical_soc.map(|v| v as f32),
+                hysteresis: config.hysteresis_soc as f32,
+            },
+            level: PowerLevel::Normal,
+            alarm_source: config.alarm_source.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    struct FakeBattery(Arc<Mutex<f32>>);
+
+    impl PowerDevice for FakeBattery {
+        fn read(&mut self) -> CuResult<PowerStatus> {
+            Ok(PowerStatus {
+                rails: vec![PowerRail {
+                    voltage: *self.0.lock().unwrap(),
+                    current: -2.0,
+                }],
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = Thresholds {
+            low: Some(11.0),
+            critical: Some(10.5),
+            hysteresis: 0.1,
+        };
+        assert_eq!(
+            thresholds.level(12.0, PowerLevel::Normal),
+            PowerLevel::Normal
+        );
+        assert_eq!(thresholds.level(10.9, PowerLevel::Normal), PowerLevel::Low);
+        assert_eq!(thresholds.level(11.05, PowerLevel::Low), PowerLevel::Low);
+        assert_eq!(thresholds.level(11.2, PowerLevel::Low), PowerLevel::Normal);
+        assert_eq!(
+            thresholds.level(10.4, PowerLevel::Low),
+            PowerLevel::Critical
+        );
+        assert_eq!(
+            thresholds.level(10.55, PowerLevel::Critical),
+            PowerLevel::Critical
+        );
+        assert_eq!(
+            thresholds.level(10.7, PowerLevel::Critical),
+            PowerLevel::Low
+        );
+    }
+
+    #[test]
+    fn test_alarms() {
+        let mut config = ComponentConfig::new();
+        config.set("device", "fake".to_string());
+        config.set("low_voltage", 11.0);
+        config.set("critical_voltage", 10.5);
+        config.set("alarm_source", "test_battery".to_string());
+        let config = PowerMonitorConfig::from_config(Some(&config)).unwrap();
+        let voltage = Arc::new(Mutex::new(12.0));
+        let mut monitor =
+            PowerMonitor::with_device(Box::new(FakeBattery(voltage.clone())), &config);
+        let battery_alarm = || {
+            alarms()
+                .into_iter()
+                .find(|alarm| alarm.source == "test_battery" && alarm.code == LOW_BATTERY_ALARM)
+        };
+
+        let (clock, mock) = RobotClock::mock();
+        let mut msg = CuMsg::<PowerStatus>::new(None);
+        monitor.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().level, PowerLevel::Normal);
+        assert!(battery_alarm().is_none());
+
+        // Not read before the period.
+        *voltage.lock().unwrap() = 10.2;
+        mock.increment(Duration::from_millis(50));
+        monitor.process(&clock, &mut msg).unwrap();
+        assert!(msg.payload().is_none());
+
+        mock.increment(Duration::from_millis(50));
+        monitor.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().level, PowerLevel::Critical);
+        let alarm = battery_alarm().unwrap();
+        assert_eq!(alarm.severity, AlarmSeverity::Critical);
+        assert!(alarm.active);
+
+        *voltage.lock().unwrap() = 12.5;
+        mock.increment(Duration::from_millis(100));
+        monitor.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().level, PowerLevel::Normal);
+        assert!(battery_alarm().unwrap().is_latched());
+    }
+}
@@ -0,0 +1,287 @@
+mod probes;
+
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+use probes::{CpuTimes, NetCounters};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct NetInterfaceStats {
+    pub name: String,
+    /// Bytes since the interface is up.
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Bytes/s since the previous reading.
+    pub rx_rate: f32,
+    pub tx_rate: f32,
+    /// Receive and transmit errors and drops since the interface is up.
+    pub errors: u64,
+    pub drops: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct SystemStatus {
+    /// From 0 to 1, all the CPUs since the previous reading.
+    pub cpu_load: f32,
+    /// 1 minute load average.
+    pub load_average: f32,
+    /// Bytes
+    pub memory_total: u64,
+    pub memory_available: u64,
+    /// Bytes of the file system of the log.
+    pub disk_total: u64,
+    pub disk_free: u64,
+    pub interfaces: Vec<NetInterfaceStats>,
+}
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct SysInfoConfig {
+    #[config(default = ".")]
+    log_path: String,
+    interfaces: Option<String>,
+    #[config(default = 1000, range = 1..)]
+    period_ms: u32,
+    disk_warning_mib: Option<u64>,
+    disk_critical_mib: Option<u64>,
+    #[config(default = "sysinfo")]
+    alarm_source: String,
+    #[config(default = "/proc")]
+    procfs_path: String,
+}
+
+/// The code of the low disk space alarm, its severity follows the free space.
+pub const LOW_DISK_ALARM: u32 = 1;
+
+/// This is a source task emitting the system resources every `period_ms`: CPU load, memory, free space on the
+/// file system of the unified log and network interface statistics (Linux).
+/// A Warning (Critical) alarm is raised when the free space goes under `disk_warning_mib` (`disk_critical_mib`),
+/// before the logger fills the disk.
+pub struct SysInfo {
+    procfs_path: PathBuf,
+    log_path: PathBuf,
+    interfaces: Vec<String>,
+    period: CuDuration,
+    disk_warning: Option<u64>,
+    disk_critical: Option<u64>,
+    alarm_source: String,
+    disk_alarm: Option<AlarmSeverity>,
+    last_read: Option<(CuTime, CpuTimes, Vec<NetCounters>)>,
+}
+
+impl SysInfo {
+    fn procfs(&self, file: &str) -> CuResult<String> {
+        let path = self.procfs_path.join(file);
+        fs::read_to_string(&path).map_err(|e| {
+            CuError::new_with_cause(&format!("SysInfo: Failed to read {}", path.display()), e)
+        })
+    }
+
+    fn update_disk_alarm(&mut self, clock: &RobotClock, disk_free: u64) {
+        let under = |threshold: Option<u64>| threshold.is_some_and(|mib| disk_free < mib << 20);
+        let severity = if under(self.disk_critical) {
+            Some(AlarmSeverity::Critical)
+        } else if under(self.disk_warning) {
+            Some(AlarmSeverity::Warning)
+        } else {
+            None
+        };
+        if severity == self.disk_alarm {
+            return;
+        }
+        self.disk_alarm = severity;
+        match severity {
+            Some(severity) => raise_alarm(
+                clock,
+                &self.alarm_source,
+                LOW_DISK_ALARM,
+                severity,
+                format!(
+                    "{}MiB left for the log in {}",
+                    disk_free >> 20,
+                    self.log_path.display()
+                ),
+            ),
+            None => clear_alarm(&self.alarm_source, LOW_DISK_ALARM),
+        }
+    }
+}
+
+impl Freezable for SysInfo {}
+
+impl<'cl> CuSrcTask<'cl> for SysInfo {
+    type Output = output_msg!('cl, SystemStatus);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let SysInfoConfig {
+            log_path,
+            interfaces,
+            period_ms,
+            disk_warning_mib,
+            disk_critical_mib,
+            alarm_source,
+            procfs_path,
+        } = SysInfoConfig::from_config(config)?;
+        Ok(Self {
+            procfs_path: PathBuf::from(procfs_path),
+            log_path: PathBuf::from(log_path),
+            interfaces: interfaces
+                .unwrap_or_default()
+                .split(',')
+                .map(|interface| interface.trim().to_string())
+                .filter(|interface| !interface.is_empty())
+                .collect(),
+            period: CuDuration::from(period_ms as u64 * 1_000_000),
+            disk_warning: disk_warning_mib,
+            disk_critical: disk_critical_mib,
+            alarm_source,
+            disk_alarm: None,
+            last_read: None,
+        })
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let now = clock.now();
+        if self
+            .last_read
+            .as_ref()
+            .is_some_and(|(last_read, ..)| now - *last_read < self.period)
+        {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+        let cpu = probes::parse_stat(&self.procfs("stat")?)?;
+        let load_average = probes::parse_loadavg(&self.procfs("loadavg")?)?;
+        let (memory_total, memory_available) = probes::parse_meminfo(&self.procfs("meminfo")?)?;
+        let (disk_total, disk_free) = probes::disk_usage(&self.log_path)?;
+        let counters = probes::parse_net_dev(&self.procfs("net/dev")?)?
+            .into_iter()
+            .filter(|counters| match self.interfaces.is_empty() {
+                true => counters.name != "lo",
+                false => self.interfaces.contains(&counters.name),
+            })
+            .collect::<Vec<_>>();
+
+        // The rates are computed against the previous reading, if any.
+        let (cpu_load, elapsed, last_counters) = match &self.last_read {
+            Some((last_read, last_cpu, last_counters)) => (
+                cpu.load_since(last_cpu),
+                (now - *last_read).as_nanos() as f32 / 1e9,
+                last_counters.as_slice(),
+            ),
+            None => (0.0, 0.0, [].as_slice()),
+        };
+        let rate = |bytes: u64, last_bytes: u64| bytes.saturating_sub(last_bytes) as f32 / elapsed;
+        let interfaces = counters
+            .iter()
+            .map(|counters| {
+                let last = last_counters.iter().find(|last| last.name == counters.name);
+                NetInterfaceStats {
+                    name: counters.name.clone(),
+                    rx_bytes: counters.rx_bytes,
+                    tx_bytes: counters.tx_bytes,
+                    rx_rate: last.map_or(0.0, |last| rate(counters.rx_bytes, last.rx_bytes)),
+                    tx_rate: last.map_or(0.0, |last| rate(counters.tx_bytes, last.tx_bytes)),
+                    errors: counters.errors,
+                    drops: counters.drops,
+                }
+            })
+            .collect();
+        self.last_read = Some((now, cpu, counters));
+        self.update_disk_alarm(clock, disk_free);
+
+        new_msg.metadata.tov = now.into();
+        new_msg.metadata.set_status(format!(
+            "cpu {:.0}% disk {}MiB",
+            cpu_load * 100.0,
+            disk_free >> 20
+        ));
+        new_msg.set_payload(SystemStatus {
+            cpu_load,
+            load_average,
+            memory_total,
+            memory_available,
+            disk_total,
+            disk_free,
+            interfaces,
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.last_read = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    fn write_procfs(root: &Path, busy: u64, idle: u64, eth0_rx: u64) {
+        fs::create_dir_all(root.join("net")).unwrap();
+        fs::write(
+            root.join("stat"),
+            format!("cpu  {busy} 0 0 {idle} 0 0 0 0 0 0\n"),
+        )
+        .unwrap();
+        fs::write(root.join("loadavg"), "1.50 1.00 0.50 2/300 4242\n").unwrap();
+        fs::write(
+            root.join("meminfo"),
+            "MemTotal:        4000 kB\nMemAvailable:    1000 kB\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("net/dev"),
+            format!(
+                "header\nheader\n    lo: 10 1 0 0 0 0 0 0 10 1 0 0 0 0 0 0\n  eth0: {eth0_rx} 1 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_sysinfo() {
+        let procfs = tempfile::tempdir().unwrap();
+        write_procfs(procfs.path(), 100, 100, 1000);
+        let mut config = ComponentConfig::new();
+        config.set("procfs_path", procfs.path().to_str().unwrap().to_string());
+        // Whatever the disk of the test machine, it has less than that.
+        config.set("disk_warning_mib", u32::MAX);
+        config.set("alarm_source", "test_sysinfo".to_string());
+        let mut sysinfo = SysInfo::new(Some(&config)).unwrap();
+        let (clock, mock) = RobotClock::mock();
+        let mut msg = CuMsg::<SystemStatus>::new(None);
+
+        sysinfo.process(&clock, &mut msg).unwrap();
+        let status = msg.payload().unwrap();
+        assert_eq!(status.load_average, 1.5);
+        assert_eq!(status.memory_available, 1000 * 1024);
+        assert!(status.disk_free > 0);
+        assert_eq!(status.interfaces.len(), 1);
+        assert_eq!(status.interfaces[0].rx_rate, 0.0);
+        let alarm = alarms()
+            .into_iter()
+            .find(|alarm| alarm.source == "test_sysinfo")
+            .unwrap();
+        assert_eq!(alarm.severity, AlarmSeverity::Warning);
+
+        write_procfs(procfs.path(), 400, 200, 3000);
+        mock.increment(Duration::from_millis(500));
+        sysinfo.process(&clock, &mut msg).unwrap();
+        assert!(msg.payload().is_none());
+        mock.increment(Duration::from_millis(500));
+        sysinfo.process(&clock, &mut msg).unwrap();
+        let status = msg.payload().unwrap();
+        assert_eq!(status.cpu_load, 0.75);
+        assert_eq!(status.interfaces[0].name, "eth0");
+        assert_eq!(status.interfaces[0].rx_rate, 2000.0);
+    }
+}
@@ -0,0 +1,197 @@
+//! Parsers of the procfs files and file system statistics.
+
+use cu29::prelude::*;
+use std::path::Path;
+
+/// Cumulated jiffies of all the CPUs, from the first line of /proc/stat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct CpuTimes {
+    pub(crate) busy: u64,
+    pub(crate) total: u64,
+}
+
+impl CpuTimes {
+    /// The load between two readings, from 0 to 1.
+    pub(crate) fn load_since(&self, previous: &CpuTimes) -> f32 {
+        let total = self.total.saturating_sub(previous.total);
+        if total == 0 {
+            return 0.0;
+        }
+        self.busy.saturating_sub(previous.busy) as f32 / total as f32
+    }
+}
+
+pub(crate) fn parse_stat(stat: &str) -> CuResult<CpuTimes> {
+    let values = stat
+        .lines()
+        .find_map(|line| line.strip_prefix("cpu "))
+        .ok_or_else(|| CuError::from("SysInfo: no cpu line in stat"))?
+        .split_whitespace()
+        .map(|value| value.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| CuError::new_with_cause("SysInfo: invalid cpu line in stat", e))?;
+    // user nice system idle iowait irq softirq steal guest guest_nice, the guest times are in user and nice.
+    let total: u64 = values.iter().take(8).sum();
+    let idle = values.get(3).copied().unwrap_or(0) + values.get(4).copied().unwrap_or(0);
+    Ok(CpuTimes {
+        busy: total - idle,
+        total,
+    })
+}
+
+/// The 1 minute load average.
+pub(crate) fn parse_loadavg(loadavg: &str) -> CuResult<f32> {
+    loadavg
+        .split_whitespace()
+        .next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| "SysInfo: invalid loadavg".into())
+}
+
+/// Total and available memory in bytes.
+pub(crate) fn parse_meminfo(meminfo: &str) -> CuResult<(u64, u64)> {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| {
+                value
+                    .trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+            .map(|kib| kib * 1024)
+            .ok_or_else(|| CuError::from(format!("SysInfo: no {name} in meminfo")))
+    };
+    Ok((field("MemTotal")?, field("MemAvailable")?))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NetCounters {
+    pub(crate) name: String,
+    pub(crate) rx_bytes: u64,
+    pub(crate) tx_bytes: u64,
+    pub(crate) errors: u64,
+    pub(crate) drops: u64,
+}
+
+/// The counters of /proc/net/dev: after 2 header lines, `name: rx_bytes packets errs drop fifo frame compressed
+/// multicast tx_bytes packets errs drop ...`.
+pub(crate) fn parse_net_dev(net_dev: &str) -> CuResult<Vec<NetCounters>> {
+    net_dev
+        .lines()
+        .skip(2)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, counters) = line
+                .split_once(':')
+                .ok_or_else(|| CuError::from(format!("SysInfo: invalid net/dev line {line}")))?;
+            let counters = counters
+                .split_whitespace()
+                .map(|value| value.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| CuError::new_with_cause("SysInfo: invalid net/dev counters", e))?;
+            if counters.len() < 12 {
+                return Err(format!("SysInfo: truncated net/dev line {line}").into());
+            }
+            Ok(NetCounters {
+                name: name.trim().to_string(),
+                rx_bytes: counters[0],
+                tx_bytes: counters[8],
+                errors: counters[2] + counters[10],
+                drops: counters[3] + counters[11],
+            })
+        })
+        .collect()
+}
+
+/// Total and available (to a non root user) bytes of the file system of `path`.
+#[cfg(unix)]
+pub(crate) fn disk_usage(path: &Path) -> CuResult<(u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| CuError::new_with_cause("SysInfo: invalid log path", e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid C string and stat is a properly sized output buffer.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(CuError::new_with_cause(
+            &format!("SysInfo: Failed to stat {}", path.display()),
+            std::io::Error::last_os_error(),
+        ));
+    }
+    let fragment = stat.f_frsize as u64;
+    Ok((
+        stat.f_blocks as u64 * fragment,
+        stat.f_bavail as u64 * fragment,
+    ))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn disk_usage(_path: &Path) -> CuResult<(u64, u64)> {
+    Err("SysInfo: the disk usage is only supported on Unix.".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat() {
+        let before =
+            parse_stat("cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 1 2 3 4 5 6 7 8 9 10\n").unwrap();
+        let after = parse_stat("cpu  200 0 200 800 100 0 0 0 0 0\n").unwrap();
+        assert_eq!(
+            before,
+            CpuTimes {
+                busy: 200,
+                total: 1000
+            }
+        );
+        assert!((after.load_since(&before) - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(after.load_since(&after), 0.0);
+    }
+
+    #[test]
+    fn test_meminfo_loadavg() {
+        let meminfo = "MemTotal:       16000000 kB\nMemFree:         1000000 kB\nMemAvailable:    8000000 kB\n";
+        assert_eq!(
+            parse_meminfo(meminfo).unwrap(),
+            (16_000_000 * 1024, 8_000_000 * 1024)
+        );
+        assert!(parse_meminfo("MemTotal: 1 kB\n").is_err());
+        assert_eq!(parse_loadavg("0.52 0.58 0.59 1/467 12345\n").unwrap(), 0.52);
+    }
+
+    #[test]
+    fn test_net_dev() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    5000      50    0    0    0     0          0         0     5000      50    0    0    0     0       0          0
+  eth0: 1000000    900    1    2    0     0          0         0   200000     800    3    4    0     0       0          0
+";
+        let counters = parse_net_dev(net_dev).unwrap();
+        assert_eq!(counters.len(), 2);
+        assert_eq!(
+            counters[1],
+            NetCounters {
+                name: "eth0".to_string(),
+                rx_bytes: 1_000_000,
+                tx_bytes: 200_000,
+                errors: 4,
+                drops: 6,
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disk_usage() {
+        let (total, free) = disk_usage(Path::new(".")).unwrap();
+        assert!(total > 0);
+        assert!(free <= total);
+    }
+}
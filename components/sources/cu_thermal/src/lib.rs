@@ -0,0 +1,453 @@
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct Temperature {
+    /// chip/label as exposed by hwmon, for example "coretemp/Package id 0" or "amdgpu/edge".
+    pub sensor: String,
+    /// °C
+    pub celsius: f32,
+}
+
+/// How much the tasks subscribing to the thermal status should degrade their work.
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Encode,
+    Decode,
+    Serialize,
+    Deserialize,
+)]
+pub enum ThrottleLevel {
+    #[default]
+    None,
+    Light,
+    Heavy,
+    /// Stop everything that is not needed to keep the robot safe.
+    Critical,
+}
+
+impl ThrottleLevel {
+    /// A suggested scale for rates and workloads: 1 for None down to 0 for Critical.
+    pub fn factor(&self) -> f32 {
+        match self {
+            ThrottleLevel::None => 1.0,
+            ThrottleLevel::Light => 0.75,
+            ThrottleLevel::Heavy => 0.5,
+            ThrottleLevel::Critical => 0.0,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct ThermalStatus {
+    pub temperatures: Vec<Temperature>,
+    /// °C, the hottest of the temperatures.
+    pub hottest: f32,
+    pub throttle: ThrottleLevel,
+}
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct ThermalMonitorConfig {
+    sensors: Option<String>,
+    #[config(default = "/sys/class/hwmon")]
+    hwmon_path: String,
+    #[config(default = 1000)]
+    period_ms: u32,
+    #[config(default = 70.0)]
+    warm: f64,
+    #[config(default = 80.0)]
+    hot: f64,
+    #[config(default = 90.0)]
+    critical: f64,
+    #[config(default = 5.0, range = 0.0..)]
+    hysteresis: f64,
+    #[config(default = "thermal")]
+    alarm_source: String,
+}
+
+struct Sensor {
+    name: String,
+    input: PathBuf,
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+/// Lists the temperature inputs of the hwmon chips: <hwmon_path>/hwmonN/tempM_input, the chip is named by
+/// hwmonN/name and the sensor by tempM_label if it exists.
+fn discover(hwmon_path: &Path, filters: &[String]) -> CuResult<Vec<Sensor>> {
+    let chips = fs::read_dir(hwmon_path).map_err(|e| {
+        CuError::new_with_cause(
+            &format!("ThermalMonitor: Failed to list {}", hwmon_path.display()),
+            e,
+        )
+    })?;
+    let mut chips = chips
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    chips.sort();
+    let mut sensors = Vec::new();
+    for chip in chips {
+        let Some(chip_name) = read_trimmed(&chip.join("name")) else {
+            continue;
+        };
+        let Ok(files) = fs::read_dir(&chip) else {
+            continue;
+        };
+        let mut inputs = files
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file = entry.file_name().to_string_lossy().to_string();
+                let index = file.strip_prefix("temp")?.strip_suffix("_input")?;
+                Some((index.parse::<u32>().ok()?, entry.path()))
+            })
+            .collect::<Vec<_>>();
+        inputs.sort();
+        for (index, input) in inputs {
+            let label = read_trimmed(&chip.join(format!("temp{index}_label")))
+                .unwrap_or_else(|| format!("temp{index}"));
+            let name = format!("{chip_name}/{label}");
+            if filters.is_empty()
+                || filters
+                    .iter()
+                    .any(|filter| *filter == chip_name || *filter == name)
+            {
+                sensors.push(Sensor { name, input });
+            }
+        }
+    }
+    Ok(sensors)
+}
+
+/// Temperature thresholds of the throttle levels, a level is left once the temperature is `hysteresis`
+/// under its threshold.
+struct Thresholds {
+    /// Warm, hot and critical.
+    levels: [(f32, ThrottleLevel); 3],
+    hysteresis: f32,
+}
+
+impl Thresholds {
+    fn level(&self, celsius: f32, current: ThrottleLevel) -> ThrottleLevel {
+        self.levels
+            .iter()
+            .rev()
+            .find(|(threshold, level)| {
+                if current >= *level {
+                    celsius > threshold - self.hysteresis
+                } else {
+                    celsius >= *threshold
+                }
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(ThrottleLevel::None)
+    }
+}
+
+/// The code of the overheating alarm, its severity follows the throttle level.
+pub const OVERHEATING_ALARM: u32 = 1;
+
+/// This is a source task reading the hwmon temperatures (CPU, GPU, board...) every `period_ms` and emitting
+/// them with a throttle level computed from the hottest one. The planner, the cameras... can subscribe to it
+/// to degrade gracefully under thermal stress.
+/// A Warning (Heavy) or Critical alarm is raised when the throttle level gets Heavy or Critical, and cleared
+/// when it gets back to Light or None.
+pub struct ThermalMonitor {
+    hwmon_path: PathBuf,
+    filters: Vec<String>,
+    sensors: Vec<Sensor>,
+    period: CuDuration,
+    last_read: Option<CuTime>,
+    thresholds: Thresholds,
+    throttle: ThrottleLevel,
+    alarm_source: String,
+}
+
+impl ThermalMonitor {
+    fn read(&self) -> CuResult<Vec<Temperature>> {
+        // A sensor can vanish, for example when its device is powered down.
+        let temperatures = self
+            .sensors
+            .iter()
+            .filter_map(|sensor| {
+                let millidegrees = read_trimmed(&sensor.input)?.parse::<i64>().ok()?;
+                Some(Temperature {
+                    sensor: sensor.name.clone(),
+                    celsius: millidegrees as f32 / 1000.0,
+                })
+            })
+            .collect::<Vec<_>>();
+        if temperatures.is_empty() {
+            return Err(format!(
+                "ThermalMonitor: No temperature could be read from {}",
+                self.hwmon_path.display()
+            )
+            .into());
+        }
+        Ok(temperatures)
+    }
+
+    fn update_throttle(&mut self, clock: &RobotClock, status: &ThermalStatus) {
+        let throttle = status.throttle;
+        if throttle == self.throttle {
+            return;
+        }
+        debug!("ThermalMonitor: throttle level {}", throttle);
+        self.throttle = throttle;
+        let severity = match throttle {
+            ThrottleLevel::None | ThrottleLevel::Light => {
+                clear_alarm(&self.alarm_source, OVERHEATING_ALARM);
+                return;
+            }
+            ThrottleLevel::Heavy => AlarmSeverity::Warning,
+            ThrottleLevel::Critical => AlarmSeverity::Critical,
+        };
+        let hottest = status
+            .temperatures
+            .iter()
+            .max_by(|a, b| a.celsius.total_cmp(&b.celsius))
+            .map(|t| t.sensor.as_str())
+            .unwrap_or_default();
+        raise_alarm(
+            clock,
+            &self.alarm_source,
+            OVERHEATING_ALARM,
+            severity,
+            format!("{hottest} at {:.1}°C", status.hottest),
+        );
+    }
+}
+
+impl Freezable for ThermalMonitor {}
+
+impl<'cl> CuSrcTask<'cl> for ThermalMonitor {
+    type Output = output_msg!('cl, ThermalStatus);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let ThermalMonitorConfig {
+            sensors,
+            hwmon_path,
+            period_ms,
+            warm,
+            hot,
+            critical,
+            hysteresis,
+            alarm_source,
+        } = ThermalMonitorConfig::from_config(config)?;
+        if !(warm <= hot && hot <= critical) {
+            return Err("ThermalMonitor: the thresholds should be warm <= hot <= critical.".into());
+        }
+        let filters = sensors
+            .unwrap_or_default()
+            .split(',')
+            .map(|filter| filter.trim().to_string())
+            .filter(|filter| !filter.is_empty())
+            .collect();
+        Ok(Self {
+            hwmon_path: PathBuf::from(hwmon_path),
+            filters,
+            sensors: Vec::new(),
+            period: CuDuration::from(period_ms as u64 * 1_000_000),
+            last_read: None,
+            thresholds: Thresholds {
+                levels: [
+                    (warm as f32, ThrottleLevel::Light),
+                    (hot as f32, ThrottleLevel::Heavy),
+                    (critical as f32, ThrottleLevel::Critical),
+                ],
+                hysteresis: hysteresis as f32,
+            },
+            throttle: ThrottleLevel::None,
+            alarm_source,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.sensors = discover(&self.hwmon_path, &self.filters)?;
+        if self.sensors.is_empty() {
+            return Err(format!(
+                "ThermalMonitor: No temperature sensor found in {}",
+                self.hwmon_path.display()
+            )
+            .into());
+        }
+        for sensor in &self.sensors {
+            debug!("ThermalMonitor: Monitoring {}", sensor.name.as_str());
+        }
+        Ok(())
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let now = clock.now();
+        if self
+            .last_read
+            .is_some_and(|last_read| now - last_read < self.period)
+        {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+        self.last_read = Some(now);
+        let temperatures = self.read()?;
+        let hottest = temperatures
+            .iter()
+            .map(|t| t.celsius)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let status = ThermalStatus {
+            throttle: self.thresholds.level(hottest, self.throttle),
+            temperatures,
+            hottest,
+        };
+        self.update_throttle(clock, &status);
+        new_msg.metadata.tov = now.into();
+        new_msg
+            .metadata
+            .set_status(format!("{:.1}°C {:?}", status.hottest, status.throttle));
+        new_msg.set_payload(status);
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.last_read = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn chip(root: &Path, dir: &str, name: &str, temps: &[(u32, Option<&str>, i64)]) {
+        let chip = root.join(dir);
+        fs::create_dir_all(&chip).unwrap();
+        fs::write(chip.join("name"), format!("{name}\n")).unwrap();
+        for (index, label, millidegrees) in temps {
+            fs::write(
+                chip.join(format!("temp{index}_input")),
+                format!("{millidegrees}\n"),
+            )
+            .unwrap();
+            if let Some(label) = label {
+                fs::write(chip.join(format!("temp{index}_label")), label).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_discover() {
+        let root = tempfile::tempdir().unwrap();
+        chip(
+            root.path(),
+            "hwmon0",
+            "coretemp",
+            &[(1, Some("Package id 0"), 45000), (2, Some("Core 0"), 44000)],
+        );
+        chip(root.path(), "hwmon1", "amdgpu", &[(1, None, 50000)]);
+        let names = |filters: &[&str]| {
+            let filters = filters.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+            discover(root.path(), &filters)
+                .unwrap()
+                .into_iter()
+                .map(|sensor| sensor.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&[]),
+            vec!["coretemp/Package id 0", "coretemp/Core 0", "amdgpu/temp1"]
+        );
+        assert_eq!(
+            names(&["coretemp/Package id 0", "amdgpu"]),
+            vec!["coretemp/Package id 0", "amdgpu/temp1"]
+        );
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = Thresholds {
+            levels: [
+                (70.0, ThrottleLevel::Light),
+                (80.0, ThrottleLevel::Heavy),
+                (90.0, ThrottleLevel::Critical),
+            ],
+            hysteresis: 5.0,
+        };
+        assert_eq!(
+            thresholds.level(60.0, ThrottleLevel::None),
+            ThrottleLevel::None
+        );
+        assert_eq!(
+            thresholds.level(82.0, ThrottleLevel::None),
+            ThrottleLevel::Heavy
+        );
+        assert_eq!(
+            thresholds.level(77.0, ThrottleLevel::Heavy),
+            ThrottleLevel::Heavy
+        );
+        assert_eq!(
+            thresholds.level(74.0, ThrottleLevel::Heavy),
+            ThrottleLevel::Light
+        );
+        assert_eq!(
+            thresholds.level(95.0, ThrottleLevel::Light),
+            ThrottleLevel::Critical
+        );
+        assert_eq!(
+            thresholds.level(60.0, ThrottleLevel::Critical),
+            ThrottleLevel::None
+        );
+    }
+
+    #[test]
+    fn test_monitor() {
+        let root = tempfile::tempdir().unwrap();
+        chip(root.path(), "hwmon0", "cpu_thermal", &[(1, None, 65000)]);
+        let mut config = ComponentConfig::new();
+        config.set("hwmon_path", root.path().to_str().unwrap().to_string());
+        config.set("alarm_source", "test_thermal".to_string());
+        let (clock, mock) = RobotClock::mock();
+        let mut monitor = ThermalMonitor::new(Some(&config)).unwrap();
+        monitor.start(&clock).unwrap();
+        let alarm = || {
+            alarms()
+                .into_iter()
+                .find(|alarm| alarm.source == "test_thermal")
+        };
+
+        let mut msg = CuMsg::<ThermalStatus>::new(None);
+        monitor.process(&clock, &mut msg).unwrap();
+        let status = msg.payload().unwrap();
+        assert_eq!(status.hottest, 65.0);
+        assert_eq!(status.throttle, ThrottleLevel::None);
+
+        chip(root.path(), "hwmon0", "cpu_thermal", &[(1, None, 92500)]);
+        monitor.process(&clock, &mut msg).unwrap();
+        assert!(msg.payload().is_none());
+        mock.increment(Duration::from_secs(1));
+        monitor.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().throttle, ThrottleLevel::Critical);
+        assert_eq!(alarm().unwrap().severity, AlarmSeverity::Critical);
+
+        chip(root.path(), "hwmon0", "cpu_thermal", &[(1, None, 72000)]);
+        mock.increment(Duration::from_secs(1));
+        monitor.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload().unwrap().throttle, ThrottleLevel::Light);
+        assert!(alarm().unwrap().is_latched());
+    }
+}
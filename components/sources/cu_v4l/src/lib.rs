@@ -1,4 +1,6 @@
 #[cfg(target_os = "linux")]
+mod sync;
+#[cfg(target_os = "linux")]
 mod v4lstream;
 
 // This allows this module to be used on simulation on Windows and MacOS
@@ -25,20 +27,48 @@ mod empty_impl {
             Ok(())
         }
     }
+
+    pub struct V4lMulti<const N: usize> {}
+
+    impl<const N: usize> Freezable for V4lMulti<N> {}
+
+    impl<'cl, const N: usize> CuSrcTask<'cl> for V4lMulti<N> {
+        type Output = output_msg!('cl, CuArray<CuImage<Vec<u8>>, N>);
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+        where
+            Self: Sized,
+        {
+            Ok(Self {})
+        }
+
+        fn process(&mut self, _clock: &RobotClock, _new_msg: Self::Output) -> CuResult<()> {
+            Ok(())
+        }
+    }
 }
 
 #[cfg(not(target_os = "linux"))]
-pub use empty_impl::V4l;
+pub use empty_impl::{V4l, V4lMulti};
 
 #[cfg(target_os = "linux")]
-pub use linux_impl::V4l;
+pub use linux_impl::{V4l, V4lMulti};
+
+/// The 2 cameras of a stereo rig, the left one first.
+pub type V4lStereo = V4lMulti<2>;
+
+/// The alarm raised from `alarm_source` ("v4l" by default) while a device is lost, a V4lMulti
+/// raises it from `alarm_source/<device>`.
+pub const CAMERA_LOST_ALARM: u32 = 1;
 
 #[cfg(target_os = "linux")]
 mod linux_impl {
     use std::time::Duration;
     use v4l::video::Capture;
 
-    use crate::v4lstream::CuV4LStream;
+    use crate::sync::lagging;
+    use crate::v4lstream::{is_disconnection, CuV4LStream};
+    use crate::CAMERA_LOST_ALARM;
     use cu29::prelude::*;
     use cu_sensor_payloads::{CuImage, CuImageBufferFormat};
 
@@ -51,10 +81,18 @@ mod linux_impl {
     pub use v4l::video::capture::Parameters;
     pub use v4l::{Format, FourCC, Timestamp};
 
+    /// How far apart in time the frames of a V4lMulti can be, by default.
+    const DEFAULT_MAX_SKEW_MS: u32 = 5;
+    /// The wait before reopening a lost device, doubled after every failed attempt up to the max.
+    const RECONNECT_BACKOFF_MIN_MS: u64 = 100;
+    const RECONNECT_BACKOFF_MAX_MS: u64 = 5_000;
+
     // A Copper source task that reads frames from a V4L device.
+    // It reopens its device when it is lost (unplugged USB camera...), giving empty messages
+    // and raising CAMERA_LOST_ALARM in the meantime.
     pub struct V4l {
-        stream: CuV4LStream,
-        settled_format: CuImageBufferFormat,
+        camera: Camera,
+        settings: CaptureSettings,
         v4l_clock_time_offset_ns: i64,
     }
 
@@ -65,191 +103,477 @@ mod linux_impl {
         ((duration.as_nanos() as i64 + offset_ns) as u64).into()
     }
 
-    impl<'cl> CuSrcTask<'cl> for V4l {
-        type Output = output_msg!('cl, CuImage<Vec<u8>>);
+    /// The offset between the monotonic clock stamping the frames and the robot clock.
+    fn v4l_clock_time_offset_ns(robot_clock: &RobotClock) -> CuResult<i64> {
+        let rb_ns = robot_clock.now().as_nanos();
+        clock_gettime(ClockId::CLOCK_MONOTONIC)
+            .map(|ts| ts.tv_sec() * 1_000_000_000 + ts.tv_nsec() - rb_ns as i64)
+            .map_err(|e| CuError::new_with_cause("Failed to get the current time", e))
+    }
 
-        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
-        where
-            Self: Sized,
-        {
+    /// The capture settings of the config, shared by all the devices of a V4lMulti.
+    struct CaptureSettings {
+        width: Option<u32>,
+        height: Option<u32>,
+        fps: Option<u32>,
+        fourcc: Option<String>,
+        buffers: u32,
+        timeout: Duration,
+    }
+
+    impl CaptureSettings {
+        fn from_config(config: Option<&ComponentConfig>) -> Self {
             // reasonable defaults
-            let mut v4l_device = 0usize;
-            let mut req_width: Option<u32> = None;
-            let mut req_height: Option<u32> = None;
-            let mut req_fps: Option<u32> = None;
-            let mut req_fourcc: Option<String> = None;
-            let mut req_buffers: u32 = 4;
-            let mut req_timeout: Duration = Duration::from_millis(500); // 500ms tolerance to get a frame
-
-            if let Some(config) = _config {
-                if let Some(device) = config.get::<u32>("device") {
-                    v4l_device = device as usize;
-                }
-                if let Some(width) = config.get::<u32>("width") {
-                    req_width = Some(width);
-                }
-                if let Some(height) = config.get::<u32>("height") {
-                    req_height = Some(height);
-                }
-                if let Some(fps) = config.get::<u32>("fps") {
-                    req_fps = Some(fps);
-                }
-                if let Some(fourcc) = config.get::<String>("fourcc") {
-                    req_fourcc = Some(fourcc);
-                }
+            let mut settings = CaptureSettings {
+                width: None,
+                height: None,
+                fps: None,
+                fourcc: None,
+                buffers: 4,
+                timeout: Duration::from_millis(500), // 500ms tolerance to get a frame
+            };
+            if let Some(config) = config {
+                settings.width = config.get::<u32>("width");
+                settings.height = config.get::<u32>("height");
+                settings.fps = config.get::<u32>("fps");
+                settings.fourcc = config.get::<String>("fourcc");
                 if let Some(buffers) = config.get::<u32>("buffers") {
-                    req_buffers = buffers;
+                    settings.buffers = buffers;
                 }
                 if let Some(timeout) = config.get::<u32>("timeout_ms") {
-                    req_timeout = Duration::from_millis(timeout as u64);
+                    settings.timeout = Duration::from_millis(timeout as u64);
                 }
             }
-            let dev = Device::new(v4l_device)
-                .map_err(|e| CuError::new_with_cause("Failed to open camera", e))?;
+            settings
+        }
+    }
 
-            // List all formats supported by the device
-            let formats = dev
-                .enum_formats()
-                .map_err(|e| CuError::new_with_cause("Failed to enum formats", e))?;
+    /// Opens a V4L device and negotiates its format, returns its stream and the format it
+    /// settled on.
+    fn open_device(
+        v4l_device: usize,
+        settings: &CaptureSettings,
+    ) -> CuResult<(CuV4LStream, CuImageBufferFormat)> {
+        let dev = Device::new(v4l_device)
+            .map_err(|e| CuError::new_with_cause("Failed to open camera", e))?;
+
+        // List all formats supported by the device
+        let formats = dev
+            .enum_formats()
+            .map_err(|e| CuError::new_with_cause("Failed to enum formats", e))?;
+
+        if formats.is_empty() {
+            return Err("The V4l device did not provide any video format.".into());
+        }
 
-            if formats.is_empty() {
-                return Err("The V4l device did not provide any video format.".into());
+        // Either use the 4CC or just pick one for the user
+        let fourcc: FourCC = if let Some(fourcc) = &settings.fourcc {
+            if fourcc.len() != 4 {
+                return Err("Invalid fourcc provided".into());
             }
-
-            // Either use the 4CC or just pick one for the user
-            let fourcc: FourCC = if let Some(fourcc) = req_fourcc {
-                if fourcc.len() != 4 {
-                    return Err("Invalid fourcc provided".into());
-                }
-                FourCC::new(fourcc.as_bytes()[0..4].try_into().unwrap())
-            } else {
-                debug!("No fourcc provided, just use the first one we can find.");
-                formats.first().unwrap().fourcc
-            };
-            debug!("V4L: Using fourcc: {}", fourcc.to_string());
-            let actual_fmt = if let Some(format) = formats.iter().find(|f| f.fourcc == fourcc) {
-                // Enumerate resolutions for the BGR3 format
-                let resolutions = dev
-                    .enum_framesizes(format.fourcc)
-                    .map_err(|e| CuError::new_with_cause("Failed to enum frame sizes", e))?;
-                let (width, height) =
-                    if let (Some(req_width), Some(req_height)) = (req_width, req_height) {
-                        let mut frame_size: (u32, u32) = (0, 0);
-                        for frame in resolutions.iter() {
-                            let FrameSizeEnum::Discrete(size) = &frame.size else {
-                                todo!()
-                            };
-                            if size.width == req_width && size.height == req_height {
-                                frame_size = (size.width, size.height);
-                                break;
-                            }
-                        }
-                        frame_size
-                    } else {
-                        // just pick the first available
-                        let fs = resolutions.first().unwrap();
-                        let FrameSizeEnum::Discrete(size) = &fs.size else {
-                            todo!()
+            FourCC::new(fourcc.as_bytes()[0..4].try_into().unwrap())
+        } else {
+            debug!("No fourcc provided, just use the first one we can find.");
+            formats
+                .first()
+                .ok_or_else(|| CuError::from("V4L: The device has no format"))?
+                .fourcc
+        };
+        debug!("V4L: Using fourcc: {}", fourcc.to_string());
+        let actual_fmt = if let Some(format) = formats.iter().find(|f| f.fourcc == fourcc) {
+            // Enumerate resolutions for the BGR3 format
+            let resolutions = dev
+                .enum_framesizes(format.fourcc)
+                .map_err(|e| CuError::new_with_cause("Failed to enum frame sizes", e))?;
+            let (width, height) =
+                if let (Some(req_width), Some(req_height)) = (settings.width, settings.height) {
+                    let mut frame_size: (u32, u32) = (0, 0);
+                    for frame in resolutions.iter() {
+                        let FrameSizeEnum::Discrete(size) = &frame.size else {
+                            return Err("V4L: Only discrete frame sizes are supported".into());
                         };
-                        (size.width, size.height)
+                        if size.width == req_width && size.height == req_height {
+                            frame_size = (size.width, size.height);
+                            break;
+                        }
+                    }
+                    frame_size
+                } else {
+                    // just pick the first available
+                    let fs = resolutions
+                        .first()
+                        .ok_or_else(|| CuError::from("V4L: The format has no frame size"))?;
+                    let FrameSizeEnum::Discrete(size) = &fs.size else {
+                        return Err("V4L: Only discrete frame sizes are supported".into());
                     };
-
-                // Set the format with the chosen resolution
-                let req_fmt = Format::new(width, height, fourcc);
-                let actual_fmt = dev
-                    .set_format(&req_fmt)
-                    .map_err(|e| CuError::new_with_cause("Failed to set format", e))?;
-
-                if let Some(fps) = req_fps {
-                    debug!("V4L: Set fps to {}", fps);
-                    let new_params = Parameters::with_fps(fps);
-                    dev.set_params(&new_params)
-                        .map_err(|e| CuError::new_with_cause("Failed to set params", e))?;
-                }
-                debug!(
-                    "V4L: Negotiated resolution: {}x{}",
-                    actual_fmt.width, actual_fmt.height
-                );
-                actual_fmt
-            } else {
-                return Err(format!(
-                    "The V4l device {v4l_device} does not provide a format with the FourCC {fourcc}."
-                )
-                .into());
-            };
+                    (size.width, size.height)
+                };
+
+            // Set the format with the chosen resolution
+            let req_fmt = Format::new(width, height, fourcc);
+            let actual_fmt = dev
+                .set_format(&req_fmt)
+                .map_err(|e| CuError::new_with_cause("Failed to set format", e))?;
+
+            if let Some(fps) = settings.fps {
+                debug!("V4L: Set fps to {}", fps);
+                let new_params = Parameters::with_fps(fps);
+                dev.set_params(&new_params)
+                    .map_err(|e| CuError::new_with_cause("Failed to set params", e))?;
+            }
             debug!(
-                "V4L: Init stream: device {} with {} buffers of size {} bytes",
-                v4l_device, req_buffers, actual_fmt.size
+                "V4L: Negotiated resolution: {}x{}",
+                actual_fmt.width, actual_fmt.height
             );
-
-            let mut stream = CuV4LStream::with_buffers(
-                &dev,
-                Type::VideoCapture,
-                req_buffers,
-                CuHostMemoryPool::new(
-                    format!("V4L Host Pool {v4l_device}").as_str(),
-                    req_buffers as usize + 1,
-                    || vec![0; actual_fmt.size as usize],
-                )
-                .map_err(|e| {
-                    CuError::new_with_cause(
-                        "Could not create host memory pool backing the V4lStream",
-                        e,
-                    )
-                })?,
+            actual_fmt
+        } else {
+            return Err(format!(
+                "The V4l device {v4l_device} does not provide a format with the FourCC {fourcc}."
             )
-            .map_err(|e| CuError::new_with_cause("Could not create the V4lStream", e))?;
-            debug!("V4L: Set timeout to {} ms", req_timeout.as_millis() as u64);
-            stream.set_timeout(req_timeout);
-
-            let cuformat = CuImageBufferFormat {
-                width: actual_fmt.width,
-                height: actual_fmt.height,
-                stride: actual_fmt.stride,
-                pixel_format: actual_fmt.fourcc.repr,
+            .into());
+        };
+        debug!(
+            "V4L: Init stream: device {} with {} buffers of size {} bytes",
+            v4l_device, settings.buffers, actual_fmt.size
+        );
+
+        let mut stream = CuV4LStream::with_buffers(
+            &dev,
+            Type::VideoCapture,
+            settings.buffers,
+            CuHostMemoryPool::new(
+                format!("V4L Host Pool {v4l_device}").as_str(),
+                settings.buffers as usize + 1,
+                || vec![0; actual_fmt.size as usize],
+            )
+            .map_err(|e| {
+                CuError::new_with_cause(
+                    "Could not create host memory pool backing the V4lStream",
+                    e,
+                )
+            })?,
+        )
+        .map_err(|e| CuError::new_with_cause("Could not create the V4lStream", e))?;
+        debug!(
+            "V4L: Set timeout to {} ms",
+            settings.timeout.as_millis() as u64
+        );
+        stream.set_timeout(settings.timeout);
+
+        let cuformat = CuImageBufferFormat {
+            width: actual_fmt.width,
+            height: actual_fmt.height,
+            stride: actual_fmt.stride,
+            pixel_format: actual_fmt.fourcc.repr,
+        };
+        Ok((stream, cuformat))
+    }
+
+    /// A device read by a task, reopened when it is lost.
+    struct Camera {
+        device: usize,
+        /// None while the device is lost.
+        stream: Option<CuV4LStream>,
+        settled_format: CuImageBufferFormat,
+        alarm_source: String,
+        backoff_ms: u64,
+        reconnect_at: CuTime,
+    }
+
+    impl Camera {
+        fn open(device: usize, settings: &CaptureSettings, alarm_source: String) -> CuResult<Self> {
+            let (stream, settled_format) = open_device(device, settings)?;
+            Ok(Camera {
+                device,
+                stream: Some(stream),
+                settled_format,
+                alarm_source,
+                backoff_ms: RECONNECT_BACKOFF_MIN_MS,
+                reconnect_at: CuTime::default(),
+            })
+        }
+
+        fn is_lost(&self) -> bool {
+            self.stream.is_none()
+        }
+
+        fn start(&mut self) -> CuResult<()> {
+            match &mut self.stream {
+                Some(stream) => stream
+                    .start()
+                    .map_err(|e| CuError::new_with_cause("could not start stream", e)),
+                None => Ok(()),
+            }
+        }
+
+        fn stop(&mut self) -> CuResult<()> {
+            match &mut self.stream {
+                Some(stream) => stream
+                    .stop()
+                    .map_err(|e| CuError::new_with_cause("could not stop stream", e)),
+                None => Ok(()),
+            }
+        }
+
+        fn lose(&mut self, clock: &RobotClock) {
+            debug!("V4L: lost the device {}, reconnecting.", self.device);
+            self.stream = None;
+            self.backoff_ms = RECONNECT_BACKOFF_MIN_MS;
+            self.reconnect_at = clock.now() + CuDuration::from_millis(self.backoff_ms);
+            raise_alarm(
+                clock,
+                &self.alarm_source,
+                CAMERA_LOST_ALARM,
+                AlarmSeverity::Warning,
+                format!(
+                    "The camera /dev/video{} is lost, reconnecting.",
+                    self.device
+                ),
+            );
+        }
+
+        /// Tries to reopen a lost device once its backoff elapsed.
+        fn reconnect(&mut self, clock: &RobotClock, settings: &CaptureSettings) {
+            if !self.is_lost() || clock.now() < self.reconnect_at {
+                return;
+            }
+            let reopened = open_device(self.device, settings).and_then(|(mut stream, format)| {
+                stream
+                    .start()
+                    .map_err(|e| CuError::new_with_cause("could not start stream", e))?;
+                Ok((stream, format))
+            });
+            match reopened {
+                Ok((stream, settled_format)) => {
+                    debug!("V4L: reconnected the device {}.", self.device);
+                    self.stream = Some(stream);
+                    self.settled_format = settled_format;
+                    self.backoff_ms = RECONNECT_BACKOFF_MIN_MS;
+                    clear_alarm(&self.alarm_source, CAMERA_LOST_ALARM);
+                }
+                Err(e) => {
+                    debug!(
+                        "V4L: could not reopen the device {}: {}",
+                        self.device,
+                        e.to_string()
+                    );
+                    self.backoff_ms = (self.backoff_ms * 2).min(RECONNECT_BACKOFF_MAX_MS);
+                    self.reconnect_at = clock.now() + CuDuration::from_millis(self.backoff_ms);
+                }
+            }
+        }
+
+        /// The next frame and the time it was taken at, None if it is empty or if the device is
+        /// lost.
+        fn next_frame(
+            &mut self,
+            clock: &RobotClock,
+            offset_ns: i64,
+        ) -> CuResult<Option<(CuImage<Vec<u8>>, CuTime)>> {
+            let Some(stream) = &mut self.stream else {
+                return Ok(None);
             };
+            let frame = stream
+                .next()
+                .map(|(handle, meta)| (handle.clone(), meta.bytesused, meta.timestamp));
+            match frame {
+                Ok((_, 0, _)) => {
+                    debug!("Empty frame received");
+                    Ok(None)
+                }
+                Ok((handle, _, timestamp)) => Ok(Some((
+                    CuImage::new(self.settled_format, handle),
+                    cutime_from_v4ltime(offset_ns, timestamp),
+                ))),
+                Err(e) if is_disconnection(&e) => {
+                    self.lose(clock);
+                    Ok(None)
+                }
+                Err(e) => Err(CuError::new_with_cause(
+                    "could not get next frame from stream",
+                    e,
+                )),
+            }
+        }
+    }
+
+    fn alarm_source(config: Option<&ComponentConfig>) -> String {
+        config
+            .and_then(|config| config.get::<String>("alarm_source"))
+            .unwrap_or_else(|| "v4l".to_string())
+    }
+
+    impl<'cl> CuSrcTask<'cl> for V4l {
+        type Output = output_msg!('cl, CuImage<Vec<u8>>);
+
+        fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+        where
+            Self: Sized,
+        {
+            let v4l_device = config
+                .and_then(|config| config.get::<u32>("device"))
+                .unwrap_or(0) as usize;
+            let settings = CaptureSettings::from_config(config);
+            let camera = Camera::open(v4l_device, &settings, alarm_source(config))?;
 
             Ok(Self {
-                stream,
-                settled_format: cuformat,
+                camera,
+                settings,
                 v4l_clock_time_offset_ns: 0, // will be set at start
             })
         }
 
         fn start(&mut self, robot_clock: &RobotClock) -> CuResult<()> {
-            let rb_ns = robot_clock.now().as_nanos();
-            clock_gettime(ClockId::CLOCK_MONOTONIC)
-                .map(|ts| {
-                    self.v4l_clock_time_offset_ns =
-                        ts.tv_sec() * 1_000_000_000 + ts.tv_nsec() - rb_ns as i64
+            self.v4l_clock_time_offset_ns = v4l_clock_time_offset_ns(robot_clock)?;
+            self.camera.start()
+        }
+
+        fn preprocess(&mut self, clock: &RobotClock) -> CuResult<()> {
+            self.camera.reconnect(clock, &self.settings);
+            Ok(())
+        }
+
+        fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+            match self
+                .camera
+                .next_frame(clock, self.v4l_clock_time_offset_ns)?
+            {
+                Some((image, cutime)) => {
+                    new_msg.set_payload(image);
+                    new_msg.metadata.tov = Tov::Time(cutime);
+                }
+                None if self.camera.is_lost() => new_msg.metadata.set_status("camera lost"),
+                None => {}
+            }
+            Ok(())
+        }
+
+        fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+            self.camera.stop()
+        }
+    }
+
+    /// A Copper source task reading frames from several V4L devices, the cameras of a stereo
+    /// rig for example, and giving one frame of each per cycle in the order of its `devices`.
+    /// The frames are matched by their hardware timestamps: a camera lagging behind the others
+    /// is read again until all the frames are within `max_skew_ms` of each other.
+    /// A lost device is reopened like with V4l, the cycles give nothing until it is back.
+    pub struct V4lMulti<const N: usize> {
+        cameras: Vec<Camera>,
+        settings: CaptureSettings,
+        max_skew: CuDuration,
+        /// How many frames can be read again in a cycle before giving up on aligning them.
+        max_rereads: usize,
+        v4l_clock_time_offset_ns: i64,
+    }
+
+    impl<const N: usize> Freezable for V4lMulti<N> {}
+
+    impl<'cl, const N: usize> CuSrcTask<'cl> for V4lMulti<N> {
+        type Output = output_msg!('cl, CuArray<CuImage<Vec<u8>>, N>);
+
+        fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+        where
+            Self: Sized,
+        {
+            let devices: Vec<usize> = config
+                .and_then(|config| config.get::<String>("devices"))
+                .ok_or_else(|| CuError::from("V4lMulti needs its \"devices\", like \"0,2\"."))?
+                .split(',')
+                .map(|device| {
+                    device.trim().parse::<usize>().map_err(|e| {
+                        CuError::new_with_cause(&format!("V4lMulti: invalid device {device}"), e)
+                    })
                 })
-                .map_err(|e| CuError::new_with_cause("Failed to get the current time", e))?;
+                .collect::<CuResult<_>>()?;
+            if devices.len() != N {
+                return Err(format!(
+                    "V4lMulti: {} devices are given for {N} cameras.",
+                    devices.len()
+                )
+                .into());
+            }
+            let settings = CaptureSettings::from_config(config);
+            let max_skew_ms = config
+                .and_then(|config| config.get::<u32>("max_skew_ms"))
+                .unwrap_or(DEFAULT_MAX_SKEW_MS);
+            let alarm_source = alarm_source(config);
+            let cameras = devices
+                .into_iter()
+                .map(|device| Camera::open(device, &settings, format!("{alarm_source}/{device}")))
+                .collect::<CuResult<_>>()?;
+            Ok(Self {
+                cameras,
+                max_skew: CuDuration::from_millis(max_skew_ms as u64),
+                max_rereads: settings.buffers as usize * N,
+                settings,
+                v4l_clock_time_offset_ns: 0, // will be set at start
+            })
+        }
 
-            self.stream
-                .start()
-                .map_err(|e| CuError::new_with_cause("could not start stream", e))
+        fn start(&mut self, robot_clock: &RobotClock) -> CuResult<()> {
+            self.v4l_clock_time_offset_ns = v4l_clock_time_offset_ns(robot_clock)?;
+            self.cameras.iter_mut().try_for_each(Camera::start)
         }
 
-        fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
-            let (handle, meta) = self
-                .stream
-                .next()
-                .map_err(|e| CuError::new_with_cause("could not get next frame from stream", e))?;
-            if meta.bytesused != 0 {
-                let cutime = cutime_from_v4ltime(self.v4l_clock_time_offset_ns, meta.timestamp);
-                let image = CuImage::new(self.settled_format, handle.clone());
-                new_msg.set_payload(image);
-                new_msg.metadata.tov = Tov::Time(cutime);
-            } else {
-                debug!("Empty frame received");
+        fn preprocess(&mut self, clock: &RobotClock) -> CuResult<()> {
+            for camera in &mut self.cameras {
+                camera.reconnect(clock, &self.settings);
             }
             Ok(())
         }
 
+        fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+            let offset_ns = self.v4l_clock_time_offset_ns;
+            let mut frames = Vec::with_capacity(N);
+            for camera in &mut self.cameras {
+                match camera.next_frame(clock, offset_ns)? {
+                    Some(frame) => frames.push(frame),
+                    None => break,
+                }
+            }
+            if frames.len() < N {
+                if self.cameras.iter().any(Camera::is_lost) {
+                    new_msg.metadata.set_status("camera lost");
+                }
+                return Ok(());
+            }
+            let mut rereads = 0;
+            loop {
+                let stamps: Vec<CuTime> = frames.iter().map(|(_, stamp)| *stamp).collect();
+                let Some(index) = lagging(&stamps, self.max_skew) else {
+                    break;
+                };
+                if rereads == self.max_rereads {
+                    debug!("V4lMulti: could not align the frames, dropping them.");
+                    return Ok(());
+                }
+                rereads += 1;
+                match self.cameras[index].next_frame(clock, offset_ns)? {
+                    Some(frame) => frames[index] = frame,
+                    None => return Ok(()),
+                }
+            }
+
+            let start = frames
+                .iter()
+                .map(|(_, stamp)| *stamp)
+                .min()
+                .unwrap_or_default();
+            let end = frames
+                .iter()
+                .map(|(_, stamp)| *stamp)
+                .max()
+                .unwrap_or_default();
+            let mut images = CuArray::new();
+            images.fill_from_iter(frames.into_iter().map(|(image, _)| image));
+            new_msg.set_payload(images);
+            new_msg.metadata.tov = Tov::Range(CuTimeRange { start, end });
+            Ok(())
+        }
+
         fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
-            self.stream
-                .stop()
-                .map_err(|e| CuError::new_with_cause("could not stop stream", e))
+            self.cameras.iter_mut().try_for_each(Camera::stop)
         }
     }
 
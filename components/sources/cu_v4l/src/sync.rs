@@ -0,0 +1,23 @@
+use cu29::prelude::*;
+
+/// The camera to read again for the frames of all the cameras to be taken within `max_skew` of
+/// each other: the one with the oldest frame. None if they already are.
+pub fn lagging(stamps: &[CuTime], max_skew: CuDuration) -> Option<usize> {
+    let newest = *stamps.iter().max()?;
+    let (oldest_index, oldest) = stamps.iter().enumerate().min_by_key(|(_, stamp)| **stamp)?;
+    (newest - *oldest > max_skew).then_some(oldest_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lagging() {
+        let ms = |ms: u64| CuDuration::from_millis(ms);
+        assert_eq!(lagging(&[ms(100), ms(102)], ms(5)), None);
+        // The second camera delivered its previous frame, it needs to catch up.
+        assert_eq!(lagging(&[ms(133), ms(100), ms(134)], ms(5)), Some(1));
+        assert_eq!(lagging(&[], ms(5)), None);
+    }
+}
@@ -154,17 +154,19 @@ impl CuV4LStream {
     }
 }
 
+/// Is the error the sign of a device gone? ENODEV means the file descriptor wrapped in the handle
+/// became invalid, most likely because the device was unplugged or the connection (USB, PCI, ..)
+/// broke down, some drivers report it as EPIPE while streaming.
+pub fn is_disconnection(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::ENODEV) | Some(libc::EPIPE))
+}
+
 impl Drop for CuV4LStream {
     fn drop(&mut self) {
         if let Err(e) = self.stop() {
-            if let Some(code) = e.raw_os_error() {
-                // ENODEV means the file descriptor wrapped in the handle became invalid, most
-                // likely because the device was unplugged or the connection (USB, PCI, ..)
-                // broke down. Handle this case gracefully by ignoring it.
-                if code == 19 {
-                    /* ignore */
-                    return;
-                }
+            // Handle a device gone gracefully by ignoring it.
+            if is_disconnection(&e) {
+                return;
             }
 
             panic!("{e:?}")
@@ -1,28 +1,34 @@
-use velodyne_lidar::{Config, Config16};
-use velodyne_lidar::{DataPacket, Packet};
+pub mod parser;
 
-use cu29::clock::RobotClock;
-use cu29::config::ComponentConfig;
-use cu29::cutask::{CuMsg, CuSrcTask, Freezable};
-use cu29::{output_msg, CuResult};
+use crate::parser::{parse_laser_values, Calibration, PACKET_SIZE};
+use cu29::prelude::*;
 use cu_sensor_payloads::{PointCloud, PointCloudSoa};
+use cu_time_sync::DeviceClock;
+use std::io::ErrorKind;
 use std::net::UdpSocket;
-use std::time::Duration;
-use velodyne_lidar::iter::try_packet_to_frame_xyz;
-use velodyne_lidar::types::frame_xyz::FrameXyz;
+
+/// The timestamps of the packets wrap at the top of the hour.
+const HOUR_NS: u64 = 3_600_000_000_000;
+
+/// Number of packets the device clock is estimated over, about 1/3s of data.
+const SYNC_WINDOW: usize = 256;
+
+const MAX_POINTS: usize = 10000;
+
+pub type LidarCuMsgPayload = PointCloudSoa<MAX_POINTS>;
 
 pub struct Vlp16 {
-    velo_config: Config,
     listen_addr: String,
-    #[allow(dead_code)]
-    test_mode: bool,
+    calibration: Calibration,
     socket: Option<UdpSocket>,
+    /// Maps the time of the sensor (us since the top of the hour) to the Robot time.
+    device_clock: DeviceClock,
 }
 
 impl Freezable for Vlp16 {}
 
 impl<'cl> CuSrcTask<'cl> for Vlp16 {
-    type Output = output_msg!('cl, PointCloudSoa<10000>);
+    type Output = output_msg!('cl, LidarCuMsgPayload);
 
     fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
     where
@@ -32,64 +38,84 @@ impl<'cl> CuSrcTask<'cl> for Vlp16 {
         let listen_addr: String = config
             .get("listen_addr")
             .unwrap_or("0.0.0.0:2368".to_string());
-        let return_type: String = config.get("return_type").unwrap_or("last".to_string());
-        let test_mode: String = config.get("test_mode").unwrap_or("false".to_string());
-        let velo_config = match return_type.as_str() {
-            "strongest" => Config16::new_vlp_16_strongest(),
-            "last" => Config16::new_vlp_16_last(),
-            "dual" => Config16::new_vlp_16_dual(),
-            _ => {
-                return Err("Invalid return_type for Vlp16.".into());
-            }
-        };
+        if config.get::<String>("return_type").is_some() {
+            return Err(
+                "Vlp16: return_type was removed, the return mode is read from the packets.".into(),
+            );
+        }
+        let mut calibration = Calibration::default();
+        if let Some(angles) = config.get::<String>("vertical_angles") {
+            calibration.vertical_angles = parse_laser_values(&angles)
+                .map_err(|e| CuError::new_with_cause("Invalid vertical_angles for Vlp16", e))?;
+        }
+        if let Some(offsets) = config.get::<String>("vertical_offsets_mm") {
+            calibration.vertical_offsets = parse_laser_values(&offsets)
+                .map_err(|e| CuError::new_with_cause("Invalid vertical_offsets_mm for Vlp16", e))?;
+        }
         Ok(Vlp16 {
-            velo_config: velo_config.into(),
             listen_addr,
-            test_mode: test_mode == "true",
+            calibration,
             socket: None,
+            device_clock: DeviceClock::new(SYNC_WINDOW).with_rollover(CuDuration(HOUR_NS)),
         })
     }
 
     fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
-        let socket = UdpSocket::bind(&self.listen_addr).unwrap();
+        let socket = UdpSocket::bind(&self.listen_addr)
+            .map_err(|e| CuError::new_with_cause("Vlp16: Failed to bind the UDP socket", e))?;
         socket
-            .set_read_timeout(Some(Duration::from_millis(100)))
-            .unwrap();
+            .set_nonblocking(true)
+            .map_err(|e| CuError::new_with_cause("Vlp16: Failed to set nonblocking", e))?;
         self.socket = Some(socket);
         Ok(())
     }
 
-    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
-        let socket = self.socket.as_ref().unwrap();
-        let mut packet = [0u8; size_of::<DataPacket>()];
-        let (read_size, _peer_addr) = socket.recv_from(&mut packet).unwrap();
-        let packet = &packet[..read_size];
-
-        let packet = Packet::from_slice(packet).unwrap();
-        let packets = [Ok::<Packet, ()>(packet)].into_iter(); // 🤮
-        let frame: FrameXyz = try_packet_to_frame_xyz(self.velo_config.clone(), packets)
-            .unwrap()
-            .next()
-            .unwrap()
-            .unwrap();
-        let mut output = PointCloudSoa::<10000>::default();
-
-        frame.firing_iter().for_each(|firing| {
-            firing.point_iter().for_each(|point| {
-                let point = point.as_single().unwrap();
-                let tov = point.toh; // FIXME: Needs to sync the clocks here.
-                let x = point.measurement.xyz[0].as_meters() as f32;
-                let y = point.measurement.xyz[1].as_meters() as f32;
-                let z = point.measurement.xyz[2].as_meters() as f32;
-                let intensity = point.measurement.intensity as f32 / 255.0f32;
-                output.push(PointCloud::new(tov.into(), x, y, z, intensity, None));
-            });
-        });
-        new_msg.set_payload(output);
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let socket = self
+            .socket
+            .as_ref()
+            .ok_or_else(|| CuError::from("Vlp16: Socket not found"))?;
+        let mut buf = [0u8; PACKET_SIZE];
+        let read_size = match socket.recv(&mut buf) {
+            Ok(size) => size,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                new_msg.clear_payload();
+                return Ok(());
+            }
+            Err(e) => return Err(CuError::new_with_cause("IO Error on UDP socket", e)),
+        };
+        let packet = parser::parse_packet(&buf[..read_size])
+            .map_err(|e| CuError::new_with_cause("Failed to parse VLP-16 UDP packet", e))?;
+
+        let device_ts = CuDuration(packet.timestamp_us() as u64 * 1000);
+        self.device_clock.observe(device_ts, clock.now());
+        let tov = self
+            .device_clock
+            .to_robot_time(device_ts)
+            .expect("observed just before");
+        let points = packet
+            .points(&self.calibration)
+            .map_err(|e| CuError::new_with_cause("Failed to parse VLP-16 UDP packet", e))?;
+
+        let payload = new_msg.payload_mut().insert(LidarCuMsgPayload::default());
+        for point in points {
+            payload.push(PointCloud::new(
+                tov + point.time_offset,
+                point.x,
+                point.y,
+                point.z,
+                point.reflectivity as f32 / 255.0f32,
+                Some(point.return_order),
+            ));
+        }
+        new_msg.metadata.tov = tov.into();
         Ok(())
     }
+
     fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
         self.socket = None;
+        // The sensor could have been restarted in between.
+        self.device_clock.reset();
         Ok(())
     }
 }
@@ -99,6 +125,17 @@ mod tests {
     use super::*;
     use cu_udp_inject::PcapStreamer;
 
+    #[test]
+    fn test_device_clock_rollover() {
+        let mut device_clock = DeviceClock::new(SYNC_WINDOW).with_rollover(CuDuration(HOUR_NS));
+        device_clock.observe(CuDuration(3_599_999_000_000), CuDuration(1_000_000_000));
+        // 2ms later, after the top of the hour.
+        assert_eq!(
+            device_clock.to_robot_time(CuDuration(1_000_000)),
+            Some(CuDuration(1_002_000_000))
+        );
+    }
+
     #[test]
     fn vlp16_end_2_end_test() {
         let clk = RobotClock::new();
@@ -109,16 +146,15 @@ mod tests {
 
         drv.start(&clk).unwrap();
 
-        const PACKET_SIZE: usize = size_of::<DataPacket>();
-
         // Read test.pcap
         if streamer
             .send_next::<PACKET_SIZE>()
             .expect("Failed to send packet")
         {
-            let mut msg = CuMsg::new(Some(PointCloudSoa::<10000>::default()));
+            let mut msg = CuMsg::new(Some(LidarCuMsgPayload::default()));
             drv.process(&clk, &mut msg).unwrap();
-            assert_eq!(-0.05115497, msg.payload().unwrap().x[0].value);
+            let x = msg.payload().unwrap().x[0].value;
+            assert!((x - -0.05115497).abs() < 1e-6);
         }
         drv.stop(&clk).unwrap();
     }
@@ -0,0 +1,361 @@
+use bytemuck::{Pod, Zeroable};
+use cu29::prelude::CuDuration;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::mem::size_of;
+
+// VLP-16 User Manual, chapter 9 "Sensor Data".
+// https://velodynelidar.com/wp-content/uploads/2019/12/63-9243-Rev-E-VLP-16-User-Manual.pdf
+//
+// Data packet (1206 bytes, UDP port 2368 by default)
+// | Field          | Size (byte)  | Description                                        |
+// | -------------- | ------------ | -------------------------------------------------- |
+// | data blocks    | 12 x 100     | see below                                          |
+// | timestamp      | 4            | us since the top of the hour                       |
+// | return mode    | 1            | 0x37: strongest, 0x38: last, 0x39: dual            |
+// | product id     | 1            | 0x22: VLP-16                                       |
+//
+// Data block
+// | Field          | Size (byte)  | Description                                        |
+// | -------------- | ------------ | -------------------------------------------------- |
+// | flag           | 2            | 0xFFEE                                             |
+// | azimuth        | 2            | 0.01 degree of the first firing of the block       |
+// | channels       | 32 x 3       | 2 firing sequences of the 16 lasers                |
+//
+// Channel data
+// | Field          | Size (byte)  | Description                                        |
+// | -------------- | ------------ | -------------------------------------------------- |
+// | distance       | 2            | unit: 2mm, 0 means no return                       |
+// | reflectivity   | 1            | calibrated reflectivity 0-255                      |
+
+pub const LASERS: usize = 16;
+const BLOCKS: usize = 12;
+const FIRINGS_PER_BLOCK: usize = 2;
+const BLOCK_FLAG: u16 = 0xEEFF;
+const PRODUCT_ID_VLP16: u8 = 0x22;
+
+/// Time between two firing sequences of the 16 lasers.
+const FIRING_SEQUENCE_NS: u64 = 55_296;
+/// Time between two lasers of the same firing sequence.
+const LASER_NS: u64 = 2_304;
+
+pub const PACKET_SIZE: usize = size_of::<DataPacket>();
+
+#[inline(always)]
+fn u16_endianness(val: u16) -> u16 {
+    if cfg!(target_endian = "little") {
+        val
+    } else {
+        u16::from_le(val)
+    }
+}
+
+#[inline(always)]
+fn u32_endianness(val: u32) -> u32 {
+    if cfg!(target_endian = "little") {
+        val
+    } else {
+        u32::from_le(val)
+    }
+}
+
+#[derive(Debug)]
+pub enum VelodyneError {
+    InvalidPacket(String),
+    InvalidCalibration(String),
+}
+
+impl fmt::Display for VelodyneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VelodyneError::InvalidPacket(msg) => write!(f, "Invalid packet: {msg}"),
+            VelodyneError::InvalidCalibration(msg) => write!(f, "Invalid calibration: {msg}"),
+        }
+    }
+}
+
+impl Error for VelodyneError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnMode {
+    Strongest,
+    Last,
+    /// The blocks come in pairs with the same azimuth: the last return then the strongest.
+    Dual,
+}
+
+#[repr(C, packed)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct ChannelData {
+    distance: u16,
+    pub reflectivity: u8,
+}
+
+impl ChannelData {
+    /// Distance in meters, 0 if there was no return.
+    pub fn distance(&self) -> f32 {
+        u16_endianness(self.distance) as f32 * 0.002
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct DataBlock {
+    flag: u16,
+    azimuth: u16,
+    pub channels: [ChannelData; LASERS * FIRINGS_PER_BLOCK],
+}
+
+impl DataBlock {
+    /// Azimuth of the first firing of the block in 0.01 degree.
+    pub fn azimuth(&self) -> u16 {
+        u16_endianness(self.azimuth)
+    }
+}
+
+impl Debug for DataBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Block: azimuth {}", self.azimuth())
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct DataPacket {
+    pub blocks: [DataBlock; BLOCKS],
+    timestamp: u32,
+    pub return_mode: u8,
+    pub product_id: u8,
+}
+
+impl Debug for DataPacket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Packet: timestamp {}us, return mode {:2X}, product {:2X}",
+            self.timestamp_us(),
+            self.return_mode,
+            self.product_id
+        )
+    }
+}
+
+/// The vertical geometry of the lasers, it is in the calibration sheet of the sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    /// Elevation of each laser in degrees, in firing order.
+    pub vertical_angles: [f32; LASERS],
+    /// Vertical offset of each laser from the optical center in mm, in firing order.
+    pub vertical_offsets: [f32; LASERS],
+}
+
+impl Default for Calibration {
+    /// The nominal values of the VLP-16 user manual.
+    fn default() -> Self {
+        Self {
+            vertical_angles: [
+                -15.0, 1.0, -13.0, 3.0, -11.0, 5.0, -9.0, 7.0, -7.0, 9.0, -5.0, 11.0, -3.0, 13.0,
+                -1.0, 15.0,
+            ],
+            vertical_offsets: [
+                11.2, -0.7, 9.7, -2.2, 8.1, -3.7, 6.6, -5.1, 5.1, -6.6, 3.7, -8.1, 2.2, -9.7, 0.7,
+                -11.2,
+            ],
+        }
+    }
+}
+
+/// Parses a comma separated list of one value per laser, ie. a row of the calibration sheet.
+pub fn parse_laser_values(values: &str) -> Result<[f32; LASERS], VelodyneError> {
+    let parsed = values
+        .split(',')
+        .map(|value| value.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| VelodyneError::InvalidCalibration(format!("{values}: {e}")))?;
+    parsed.try_into().map_err(|parsed: Vec<f32>| {
+        VelodyneError::InvalidCalibration(format!("expected {LASERS} values, got {}", parsed.len()))
+    })
+}
+
+/// A point in the sensor frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VelodynePoint {
+    /// Time of the firing since the timestamp of the packet.
+    pub time_offset: CuDuration,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub reflectivity: u8,
+    /// 0 for the last (or only) return, 1 for the strongest return in dual mode.
+    pub return_order: u8,
+}
+
+impl DataPacket {
+    /// Time of the first firing of the packet in us since the top of the hour.
+    pub fn timestamp_us(&self) -> u32 {
+        u32_endianness(self.timestamp)
+    }
+
+    pub fn mode(&self) -> Result<ReturnMode, VelodyneError> {
+        match self.return_mode {
+            0x37 => Ok(ReturnMode::Strongest),
+            0x38 => Ok(ReturnMode::Last),
+            0x39 => Ok(ReturnMode::Dual),
+            other => Err(VelodyneError::InvalidPacket(format!(
+                "Unknown return mode {other:2X}"
+            ))),
+        }
+    }
+
+    /// All the points with a return, in firing order.
+    pub fn points<'a>(
+        &'a self,
+        calibration: &'a Calibration,
+    ) -> Result<impl Iterator<Item = VelodynePoint> + 'a, VelodyneError> {
+        let mode = self.mode()?;
+        // In dual mode the 2 blocks of a pair are the same firings.
+        let stride = if mode == ReturnMode::Dual { 2 } else { 1 };
+        Ok((0..BLOCKS).flat_map(move |block_index| {
+            let block = &self.blocks[block_index];
+            let azimuth = block.azimuth() as f32;
+            // The azimuth of the lasers is interpolated with the rotation until the next block.
+            let azimuth_gap = if block_index + stride < BLOCKS {
+                self.blocks[block_index + stride].azimuth() as f32 - azimuth
+            } else {
+                azimuth - self.blocks[block_index - stride].azimuth() as f32
+            }
+            .rem_euclid(36000.0);
+            let return_order = if mode == ReturnMode::Dual {
+                (block_index % 2) as u8
+            } else {
+                0
+            };
+            let first_sequence = (block_index / stride * FIRINGS_PER_BLOCK) as u64;
+            block
+                .channels
+                .iter()
+                .enumerate()
+                .filter(|(_, channel)| channel.distance() > 0.0)
+                .map(move |(index, channel)| {
+                    let sequence = (index / LASERS) as u64;
+                    let laser = index % LASERS;
+                    let offset_in_block = sequence * FIRING_SEQUENCE_NS + laser as u64 * LASER_NS;
+                    let azimuth = (azimuth
+                        + azimuth_gap * offset_in_block as f32
+                            / (FIRINGS_PER_BLOCK as u64 * FIRING_SEQUENCE_NS) as f32)
+                        .rem_euclid(36000.0);
+                    let (x, y, z) = to_xyz(channel.distance(), azimuth, laser, calibration);
+                    VelodynePoint {
+                        time_offset: CuDuration(
+                            (first_sequence + sequence) * FIRING_SEQUENCE_NS
+                                + laser as u64 * LASER_NS,
+                        ),
+                        x,
+                        y,
+                        z,
+                        reflectivity: channel.reflectivity,
+                        return_order,
+                    }
+                })
+        }))
+    }
+}
+
+/// Converts a return in the Velodyne frame: x to the right, y forward, z up.
+fn to_xyz(distance: f32, azimuth: f32, laser: usize, calibration: &Calibration) -> (f32, f32, f32) {
+    let omega = calibration.vertical_angles[laser].to_radians();
+    let alpha = (azimuth / 100.0).to_radians();
+    let offset = calibration.vertical_offsets[laser] / 1000.0;
+    let xy = distance * omega.cos() - offset * omega.sin();
+    (
+        xy * alpha.sin(),
+        xy * alpha.cos(),
+        distance * omega.sin() + offset * omega.cos(),
+    )
+}
+
+pub fn parse_packet(data: &[u8]) -> Result<&DataPacket, VelodyneError> {
+    if data.len() != PACKET_SIZE {
+        return Err(VelodyneError::InvalidPacket(format!(
+            "Wrong packet size: {} != {}",
+            data.len(),
+            PACKET_SIZE
+        )));
+    }
+    let packet: &DataPacket = bytemuck::from_bytes(data);
+    if packet.product_id != PRODUCT_ID_VLP16 {
+        return Err(VelodyneError::InvalidPacket(format!(
+            "Not a VLP-16 packet, product id {:2X}",
+            packet.product_id
+        )));
+    }
+    if let Some(block) = packet
+        .blocks
+        .iter()
+        .find(|block| u16_endianness(block.flag) != BLOCK_FLAG)
+    {
+        return Err(VelodyneError::InvalidPacket(format!(
+            "Wrong block flag: {:4X}",
+            u16_endianness(block.flag)
+        )));
+    }
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The first UDP payload of a pcap capture.
+    fn first_packet(pcap: &str) -> Vec<u8> {
+        const PCAP_HEADER: usize = 24;
+        const RECORD_HEADER: usize = 16;
+        const ETH_IP_UDP_HEADERS: usize = 42;
+        let data = std::fs::read(pcap).unwrap();
+        let record = &data[PCAP_HEADER..];
+        let captured = u32::from_le_bytes(record[8..12].try_into().unwrap()) as usize;
+        record[RECORD_HEADER + ETH_IP_UDP_HEADERS..RECORD_HEADER + captured].to_vec()
+    }
+
+    #[test]
+    fn test_vlp16_packet() {
+        let data = first_packet("test/VLP_16_Single.pcap");
+        let packet = parse_packet(&data).unwrap();
+        assert_eq!(packet.mode().unwrap(), ReturnMode::Strongest);
+        assert_eq!(packet.timestamp_us(), 476000528);
+        assert_eq!(packet.blocks[0].azimuth(), 35751);
+
+        let calibration = Calibration::default();
+        let points: Vec<VelodynePoint> = packet.points(&calibration).unwrap().collect();
+        assert!(points.len() <= BLOCKS * LASERS * FIRINGS_PER_BLOCK);
+        let first = points[0];
+        assert_eq!(first.time_offset, CuDuration(0));
+        assert!((first.x - -0.05115497).abs() < 1e-6);
+        assert!((first.y - 1.1763528).abs() < 1e-6);
+        assert!((first.z - -0.3039056).abs() < 1e-6);
+        assert_eq!(first.reflectivity, 13);
+        // The firings are 2.304us apart and the points are in firing order.
+        assert!(points
+            .windows(2)
+            .all(|pair| pair[0].time_offset < pair[1].time_offset));
+        assert!(points.last().unwrap().time_offset.0 < 24 * FIRING_SEQUENCE_NS);
+
+        let mut wrong = data.clone();
+        wrong[PACKET_SIZE - 1] = 0x28; // a VLP-32C
+        assert!(parse_packet(&wrong).is_err());
+        assert!(parse_packet(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn test_laser_values() {
+        let calibration = Calibration::default();
+        let angles = "-15, 1, -13, 3, -11, 5, -9, 7, -7, 9, -5, 11, -3, 13, -1, 15";
+        assert_eq!(
+            parse_laser_values(angles).unwrap(),
+            calibration.vertical_angles
+        );
+        assert!(parse_laser_values("1, 2, 3").is_err());
+        assert!(parse_laser_values("a").is_err());
+    }
+}
@@ -1,6 +1,3 @@
-use bincode::de::Decoder;
-use bincode::enc::Encoder;
-use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
 use cu29::prelude::*;
 #[cfg(hardware)]
@@ -56,8 +53,7 @@ impl Registers {
 
 use cu29_log_derive::debug;
 use cu29_traits::CuError;
-use serde::de::{Deserialize, Deserializer};
-use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
 use uom::fmt::DisplayStyle::Abbreviation;
 
 pub struct WT901 {
@@ -65,20 +61,28 @@ pub struct WT901 {
     i2c: Box<dyn I2c<Error = I2CError>>,
 }
 
-#[derive(Default, Clone, Debug)]
+// The readings are logged in SI units.
+cu_quantity! {
+    pub struct Accel(Acceleration) in meter_per_second_squared as f32;
+    pub struct AngVel(AngularVelocity) in radian_per_second as f32;
+    pub struct MagField(MagneticFluxDensity) in tesla as f32;
+    pub struct Orientation(Angle) in radian as f32;
+}
+
+#[derive(Default, Clone, Debug, Encode, Decode, Serialize, Deserialize)]
 pub struct PositionalReadingsPayload {
-    acc_x: Acceleration,
-    acc_y: Acceleration,
-    acc_z: Acceleration,
-    gyro_x: AngularVelocity,
-    gyro_y: AngularVelocity,
-    gyro_z: AngularVelocity,
-    mag_x: MagneticFluxDensity,
-    mag_y: MagneticFluxDensity,
-    mag_z: MagneticFluxDensity,
-    roll: Angle,
-    pitch: Angle,
-    yaw: Angle,
+    acc_x: Accel,
+    acc_y: Accel,
+    acc_z: Accel,
+    gyro_x: AngVel,
+    gyro_y: AngVel,
+    gyro_z: AngVel,
+    mag_x: MagField,
+    mag_y: MagField,
+    mag_z: MagField,
+    roll: Orientation,
+    pitch: Orientation,
+    yaw: Orientation,
 }
 
 impl Display for PositionalReadingsPayload {
@@ -91,92 +95,14 @@ impl Display for PositionalReadingsPayload {
         write!(
             f,
             "acc_x: {}, acc_y: {}, acc_z: {}\n gyro_x: {}, gyro_y: {}, gyro_z: {}\nmag_x: {}, mag_y: {}, mag_z: {}\nroll: {}, pitch: {}, yaw: {}",
-            acc_style.with(self.acc_x), acc_style.with(self.acc_y), acc_style.with(self.acc_z),
-            angv_style.with(self.gyro_x), angv_style.with(self.gyro_y), angv_style.with(self.gyro_z),
-            mag_style.with(self.mag_x), mag_style.with(self.mag_y), mag_style.with(self.mag_z),
-            angle_style.with(self.roll), angle_style.with(self.pitch), angle_style.with(self.yaw)
+            acc_style.with(*self.acc_x), acc_style.with(*self.acc_y), acc_style.with(*self.acc_z),
+            angv_style.with(*self.gyro_x), angv_style.with(*self.gyro_y), angv_style.with(*self.gyro_z),
+            mag_style.with(*self.mag_x), mag_style.with(*self.mag_y), mag_style.with(*self.mag_z),
+            angle_style.with(*self.roll), angle_style.with(*self.pitch), angle_style.with(*self.yaw)
         )
     }
 }
 
-impl Serialize for PositionalReadingsPayload {
-    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut s = serializer.serialize_struct("PositionalReadings", 12)?;
-        s.serialize_field("acc_x", &self.acc_x.value)?;
-        s.serialize_field("acc_y", &self.acc_y.value)?;
-        s.serialize_field("acc_z", &self.acc_z.value)?;
-        s.serialize_field("gyro_x", &self.gyro_x.value)?;
-        s.serialize_field("gyro_y", &self.gyro_y.value)?;
-        s.serialize_field("gyro_z", &self.gyro_z.value)?;
-        s.serialize_field("mag_x", &self.mag_x.value)?;
-        s.serialize_field("mag_y", &self.mag_y.value)?;
-        s.serialize_field("mag_z", &self.mag_z.value)?;
-        s.serialize_field("roll", &self.roll.value)?;
-        s.serialize_field("pitch", &self.pitch.value)?;
-        s.serialize_field("yaw", &self.yaw.value)?;
-        s.end()
-    }
-}
-
-impl<'de> Deserialize<'de> for PositionalReadingsPayload {
-    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let values = <[f32; 12]>::deserialize(deserializer)?;
-        Ok(PositionalReadingsPayload {
-            acc_x: Acceleration::new::<standard_gravity>(values[0]),
-            acc_y: Acceleration::new::<standard_gravity>(values[1]),
-            acc_z: Acceleration::new::<standard_gravity>(values[2]),
-            gyro_x: AngularVelocity::new::<degree_per_second>(values[3]),
-            gyro_y: AngularVelocity::new::<degree_per_second>(values[4]),
-            gyro_z: AngularVelocity::new::<degree_per_second>(values[5]),
-            mag_x: MagneticFluxDensity::new::<nanotesla>(values[6]),
-            mag_y: MagneticFluxDensity::new::<nanotesla>(values[7]),
-            mag_z: MagneticFluxDensity::new::<nanotesla>(values[8]),
-            roll: Angle::new::<degree>(values[9]),
-            pitch: Angle::new::<degree>(values[10]),
-            yaw: Angle::new::<degree>(values[11]),
-        })
-    }
-}
-
-impl Encode for PositionalReadingsPayload {
-    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
-        // Encode in natural SI units
-        Encode::encode(&self.acc_x.value, encoder)?;
-        bincode::Encode::encode(&self.acc_y.value, encoder)?;
-        bincode::Encode::encode(&self.acc_z.value, encoder)?;
-        bincode::Encode::encode(&self.gyro_x.value, encoder)?;
-        bincode::Encode::encode(&self.gyro_y.value, encoder)?;
-        bincode::Encode::encode(&self.gyro_z.value, encoder)?;
-        bincode::Encode::encode(&self.mag_x.value, encoder)?;
-        bincode::Encode::encode(&self.mag_y.value, encoder)?;
-        bincode::Encode::encode(&self.mag_z.value, encoder)?;
-        bincode::Encode::encode(&self.roll.value, encoder)?;
-        bincode::Encode::encode(&self.pitch.value, encoder)?;
-        bincode::Encode::encode(&self.yaw.value, encoder)?;
-        Ok(())
-    }
-}
-
-impl Decode<()> for PositionalReadingsPayload {
-    fn decode<D: Decoder<Context = ()>>(decoder: &mut D) -> Result<Self, DecodeError> {
-        Ok(PositionalReadingsPayload {
-            // Decode back from the natural SI units
-            acc_x: Acceleration::new::<meter_per_second_squared>(bincode::Decode::decode(decoder)?),
-            acc_y: Acceleration::new::<meter_per_second_squared>(bincode::Decode::decode(decoder)?),
-            acc_z: Acceleration::new::<meter_per_second_squared>(bincode::Decode::decode(decoder)?),
-            gyro_x: AngularVelocity::new::<radian_per_second>(bincode::Decode::decode(decoder)?),
-            gyro_y: AngularVelocity::new::<radian_per_second>(bincode::Decode::decode(decoder)?),
-            gyro_z: AngularVelocity::new::<radian_per_second>(bincode::Decode::decode(decoder)?),
-            mag_x: MagneticFluxDensity::new::<tesla>(bincode::Decode::decode(decoder)?),
-            mag_y: MagneticFluxDensity::new::<tesla>(bincode::Decode::decode(decoder)?),
-            mag_z: MagneticFluxDensity::new::<tesla>(bincode::Decode::decode(decoder)?),
-            roll: Angle::new::<radian>(bincode::Decode::decode(decoder)?),
-            pitch: Angle::new::<radian>(bincode::Decode::decode(decoder)?),
-            yaw: Angle::new::<radian>(bincode::Decode::decode(decoder)?),
-        })
-    }
-}
-
 // Number of registers to read in one go
 #[allow(unused)]
 const REGISTER_SPAN_SIZE: usize = ((Registers::Yaw as u8 - Registers::AccX as u8) * 2 + 2) as usize;
@@ -192,18 +118,18 @@ impl WT901 {
             self.i2c
                 .write_read(WT901_I2C_ADDRESS, &[Registers::AccX as u8], &mut buf)
                 .expect("Error reading WT901");
-            pr.acc_x = convert_acc(get_vec_i16(&buf, Registers::AccX.offset()));
-            pr.acc_y = convert_acc(get_vec_i16(&buf, Registers::AccY.offset()));
-            pr.acc_z = convert_acc(get_vec_i16(&buf, Registers::AccZ.offset()));
-            pr.gyro_x = convert_ang_vel(get_vec_i16(&buf, Registers::GyroX.offset()));
-            pr.gyro_y = convert_ang_vel(get_vec_i16(&buf, Registers::GyroY.offset()));
-            pr.gyro_z = convert_ang_vel(get_vec_i16(&buf, Registers::GyroZ.offset()));
-            pr.mag_x = convert_mag(get_vec_i16(&buf, Registers::MagX.offset()));
-            pr.mag_y = convert_mag(get_vec_i16(&buf, Registers::MagY.offset()));
-            pr.mag_z = convert_mag(get_vec_i16(&buf, Registers::MagZ.offset()));
-            pr.roll = convert_angle(get_vec_i16(&buf, Registers::Roll.offset()));
-            pr.pitch = convert_angle(get_vec_i16(&buf, Registers::Pitch.offset()));
-            pr.yaw = convert_angle(get_vec_i16(&buf, Registers::Yaw.offset()));
+            pr.acc_x = convert_acc(get_vec_i16(&buf, Registers::AccX.offset())).into();
+            pr.acc_y = convert_acc(get_vec_i16(&buf, Registers::AccY.offset())).into();
+            pr.acc_z = convert_acc(get_vec_i16(&buf, Registers::AccZ.offset())).into();
+            pr.gyro_x = convert_ang_vel(get_vec_i16(&buf, Registers::GyroX.offset())).into();
+            pr.gyro_y = convert_ang_vel(get_vec_i16(&buf, Registers::GyroY.offset())).into();
+            pr.gyro_z = convert_ang_vel(get_vec_i16(&buf, Registers::GyroZ.offset())).into();
+            pr.mag_x = convert_mag(get_vec_i16(&buf, Registers::MagX.offset())).into();
+            pr.mag_y = convert_mag(get_vec_i16(&buf, Registers::MagY.offset())).into();
+            pr.mag_z = convert_mag(get_vec_i16(&buf, Registers::MagZ.offset())).into();
+            pr.roll = convert_angle(get_vec_i16(&buf, Registers::Roll.offset())).into();
+            pr.pitch = convert_angle(get_vec_i16(&buf, Registers::Pitch.offset())).into();
+            pr.yaw = convert_angle(get_vec_i16(&buf, Registers::Yaw.offset())).into();
         }
         Ok(())
     }
@@ -0,0 +1,247 @@
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use zenoh::key_expr::KeyExpr;
+use zenoh::liveliness::LivelinessToken;
+use zenoh::sample::SampleKind;
+use zenoh::Config;
+use zenoh::Error as ZenohError;
+
+use std::collections::BTreeSet;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub enum PeerChange {
+    #[default]
+    Appeared,
+    Disappeared,
+}
+
+/// A peer token appeared or disappeared.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct PeerEvent {
+    /// The full key expression of the liveliness token, ie. `copper/presence/basestation`.
+    pub key: String,
+    pub change: PeerChange,
+}
+
+/// Emitted only on the cycles where the presence of a peer changed.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct PeerPresence {
+    /// What changed since the last message, in the order zenoh reported it.
+    pub events: Vec<PeerEvent>,
+    /// All the peers currently alive after those events.
+    pub alive: Vec<String>,
+}
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct ZenohLivelinessConfig {
+    /// Path of a json5 zenoh config, the default zenoh config is used otherwise.
+    zenoh_config_file: Option<String>,
+    #[config(default = "copper/presence")]
+    prefix: String,
+    /// If set, this process also declares `<prefix>/<token>` so the others can watch it.
+    token: Option<String>,
+}
+
+/// The set of alive peers, it turns the zenoh samples into appeared/disappeared events.
+#[derive(Default)]
+struct PeerTracker {
+    alive: BTreeSet<String>,
+}
+
+impl PeerTracker {
+    /// Returns the event if the sample changes the presence of the peer.
+    /// Zenoh can repeat a token, for example when the history is replayed, those are ignored.
+    fn update(&mut self, key: &str, kind: SampleKind) -> Option<PeerEvent> {
+        let changed = match kind {
+            SampleKind::Put => self.alive.insert(key.to_string()),
+            SampleKind::Delete => self.alive.remove(key),
+        };
+        changed.then(|| PeerEvent {
+            key: key.to_string(),
+            change: match kind {
+                SampleKind::Put => PeerChange::Appeared,
+                SampleKind::Delete => PeerChange::Disappeared,
+            },
+        })
+    }
+}
+
+/// This is a source task watching the zenoh liveliness tokens declared under a key prefix.
+/// It lets the mission layer react when a companion process or a base station goes offline.
+pub struct ZenohLivelinessSrc {
+    config: Config,
+    prefix: String,
+    token: Option<String>,
+    tracker: PeerTracker,
+    ctx: Option<ZenohContext>,
+}
+
+pub struct ZenohContext {
+    session: zenoh::Session,
+    subscriber:
+        zenoh::pubsub::Subscriber<zenoh::handlers::FifoChannelHandler<zenoh::sample::Sample>>,
+    token: Option<LivelinessToken>,
+}
+
+fn cu_error(msg: &str, error: ZenohError) -> CuError {
+    CuError::new_with_cause(msg, error.as_ref())
+}
+
+fn cu_error_map(msg: &str) -> impl FnOnce(ZenohError) -> CuError + '_ {
+    |e| cu_error(msg, e)
+}
+
+impl Freezable for ZenohLivelinessSrc {}
+
+impl<'cl> CuSrcTask<'cl> for ZenohLivelinessSrc {
+    type Output = output_msg!('cl, PeerPresence);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let ZenohLivelinessConfig {
+            zenoh_config_file,
+            prefix,
+            token,
+        } = ZenohLivelinessConfig::from_config(config)?;
+        let prefix = namespaced(config, prefix.trim_end_matches('/'));
+
+        let config = match zenoh_config_file {
+            Some(file) => Config::from_file(&file).map_err(cu_error_map(
+                "ZenohLiveliness: Failed to create zenoh config",
+            ))?,
+            None => Config::default(),
+        };
+
+        Ok(Self {
+            config,
+            prefix,
+            token,
+            tracker: PeerTracker::default(),
+            ctx: None,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let session = zenoh::Wait::wait(zenoh::open(self.config.clone()))
+            .map_err(cu_error_map("ZenohLiveliness: Failed to open session"))?;
+
+        let key_expr = KeyExpr::<'static>::new(format!("{}/**", self.prefix))
+            .map_err(cu_error_map("ZenohLiveliness: Invalid prefix"))?;
+        // The history reports the peers that were already alive before this task started.
+        let subscriber = zenoh::Wait::wait(
+            session
+                .liveliness()
+                .declare_subscriber(key_expr)
+                .history(true),
+        )
+        .map_err(cu_error_map("ZenohLiveliness: Failed to subscribe"))?;
+
+        let token = match &self.token {
+            Some(token) => {
+                let key_expr = KeyExpr::<'static>::new(format!("{}/{token}", self.prefix))
+                    .map_err(cu_error_map("ZenohLiveliness: Invalid token"))?;
+                Some(
+                    zenoh::Wait::wait(session.liveliness().declare_token(key_expr))
+                        .map_err(cu_error_map("ZenohLiveliness: Failed to declare token"))?,
+                )
+            }
+            None => None,
+        };
+
+        debug!("ZenohLiveliness: Watching {}", self.prefix.as_str());
+        self.ctx = Some(ZenohContext {
+            session,
+            subscriber,
+            token,
+        });
+        Ok(())
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let ctx = self
+            .ctx
+            .as_mut()
+            .ok_or_else(|| CuError::from("ZenohLiveliness: Context not found"))?;
+
+        let mut events = Vec::new();
+        while let Some(sample) = ctx
+            .subscriber
+            .try_recv()
+            .map_err(cu_error_map("ZenohLiveliness: Failed to receive"))?
+        {
+            if let Some(event) = self
+                .tracker
+                .update(sample.key_expr().as_str(), sample.kind())
+            {
+                events.push(event);
+            }
+        }
+
+        if events.is_empty() {
+            new_msg.clear_payload();
+            return Ok(());
+        }
+        new_msg.metadata.tov = Tov::Time(clock.now());
+        new_msg.set_payload(PeerPresence {
+            events,
+            alive: self.tracker.alive.iter().cloned().collect(),
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        if let Some(ZenohContext {
+            session,
+            subscriber,
+            token,
+        }) = self.ctx.take()
+        {
+            if let Some(token) = token {
+                zenoh::Wait::wait(token.undeclare())
+                    .map_err(cu_error_map("ZenohLiveliness: Failed to undeclare token"))?;
+            }
+            zenoh::Wait::wait(subscriber.undeclare()).map_err(cu_error_map(
+                "ZenohLiveliness: Failed to undeclare subscriber",
+            ))?;
+            zenoh::Wait::wait(session.close())
+                .map_err(cu_error_map("ZenohLiveliness: Failed to close session"))?;
+        }
+        // The peers will be reported again by the history on the next start.
+        self.tracker = PeerTracker::default();
+        debug!("ZenohLiveliness: Stopped");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_tracker() {
+        let mut tracker = PeerTracker::default();
+        let event = tracker.update("copper/presence/base", SampleKind::Put);
+        assert_eq!(event.unwrap().change, PeerChange::Appeared);
+        // Replayed by the history.
+        assert!(tracker
+            .update("copper/presence/base", SampleKind::Put)
+            .is_none());
+        let event = tracker.update("copper/presence/base", SampleKind::Delete);
+        assert_eq!(
+            event,
+            Some(PeerEvent {
+                key: "copper/presence/base".to_string(),
+                change: PeerChange::Disappeared,
+            })
+        );
+        assert!(tracker
+            .update("copper/presence/base", SampleKind::Delete)
+            .is_none());
+        assert!(tracker.alive.is_empty());
+    }
+}
@@ -0,0 +1,557 @@
+use cu29::bincode;
+use cu29::clock::{CuTimeRange, RobotClock, Tov};
+use cu29::prelude::*;
+
+use zenoh::handlers::{RingChannel, RingChannelHandler};
+use zenoh::key_expr::KeyExpr;
+use zenoh::sample::Sample;
+use zenoh::Config;
+use zenoh::Error as ZenohError;
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+/// The depth of the queue when the task drains it and the config doesn't give one.
+const DRAIN_DEPTH: usize = 64;
+
+/// This is a source task that receives the messages of a zenoh topic, published by a ZenohSink.
+/// P is the payload type of the messages.
+/// It gives one message per cycle, or with `drain` the latest of all the messages pending.
+pub struct ZenohSrc<P>
+where
+    P: CuMsgPayload,
+{
+    _marker: PhantomData<P>,
+    receiver: ZenohReceiver,
+    drain: bool,
+}
+
+/// This is a source task giving in every cycle the payloads of all the messages of a zenoh topic
+/// received since the previous one, published by a ZenohSink.
+/// P is the payload type of the messages.
+pub struct ZenohBatchSrc<P>
+where
+    P: CuMsgPayload,
+{
+    _marker: PhantomData<P>,
+    receiver: ZenohReceiver,
+}
+
+/// What the tasks read from their ComponentConfig.
+#[derive(CuConfigStruct)]
+struct ZenohSrcConfig {
+    /// Path of a json5 zenoh config, the default zenoh config is used otherwise.
+    zenoh_config_file: Option<String>,
+    #[config(default = "copper")]
+    topic: String,
+    /// Receives the messages of the publishers of the same host through shared memory.
+    #[config(default)]
+    shared_memory: bool,
+    /// Messages kept until the task gives them, the oldest are dropped. 1 by default, 64 when the
+    /// queue is drained.
+    #[config(range = 1..)]
+    depth: Option<usize>,
+    /// Takes all the pending messages in every cycle and gives the latest one (ZenohSrc only).
+    #[config(default)]
+    drain: bool,
+}
+
+/// The accounting of the queue of the received messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZenohSrcStats {
+    /// Messages received.
+    pub received: u64,
+    /// Messages lost between the sink and the task, by the network or by the queue when it is full,
+    /// from the gaps in their sequence numbers.
+    pub missed: u64,
+    /// Messages pending in the queue at the last cycle, when it is drained.
+    pub last_depth: usize,
+    /// Messages pending in the queue at one cycle at most, when it is drained.
+    pub max_depth: usize,
+}
+
+/// The subscription shared by the tasks.
+struct ZenohReceiver {
+    config: Config,
+    topic: String,
+    depth: usize,
+    ctx: Option<ZenohContext>,
+    queue: MsgQueue,
+}
+
+/// The decoding and the accounting of the received messages, apart from zenoh.
+#[derive(Default)]
+struct MsgQueue {
+    stats: ZenohSrcStats,
+    last_seq: Option<u64>,
+}
+
+/// What a message is decoded from: a zenoh sample, or bytes in the tests.
+trait Encoded {
+    fn bytes(&self) -> Cow<'_, [u8]>;
+}
+
+impl Encoded for Sample {
+    fn bytes(&self) -> Cow<'_, [u8]> {
+        self.payload().to_bytes()
+    }
+}
+
+pub struct ZenohContext {
+    session: zenoh::Session,
+    subscriber: zenoh::pubsub::Subscriber<RingChannelHandler<Sample>>,
+}
+
+fn cu_error(msg: &str, error: ZenohError) -> CuError {
+    CuError::new_with_cause(msg, error.as_ref())
+}
+
+fn cu_error_map(msg: &str) -> impl FnOnce(ZenohError) -> CuError + '_ {
+    |e| cu_error(msg, e)
+}
+
+impl ZenohReceiver {
+    /// `drained` tells if the queue is drained whatever the config, for the batches.
+    fn new(config: Option<&ComponentConfig>, drained: bool) -> CuResult<(Self, bool)> {
+        let ZenohSrcConfig {
+            zenoh_config_file,
+            topic,
+            shared_memory,
+            depth,
+            drain,
+        } = ZenohSrcConfig::from_config(config)?;
+
+        let mut session_config = match zenoh_config_file {
+            Some(file) => Config::from_file(&file)
+                .map_err(cu_error_map("ZenohSrc: Failed to create zenoh config"))?,
+            None => Config::default(),
+        };
+        if shared_memory {
+            session_config
+                .insert_json5("transport/shared_memory/enabled", "true")
+                .map_err(cu_error_map("ZenohSrc: Failed to enable shared memory"))?;
+        }
+
+        let depth = match (depth, drain || drained) {
+            (Some(1), true) => {
+                return Err(CuError::from(
+                    "ZenohSrc: A drained queue needs a depth of 2 at least, it only keeps the newest message otherwise.",
+                ))
+            }
+            (Some(depth), _) => depth,
+            (None, true) => DRAIN_DEPTH,
+            (None, false) => 1,
+        };
+        let receiver = Self {
+            config: session_config,
+            topic: namespaced(config, &topic),
+            depth,
+            ctx: None,
+            queue: MsgQueue::default(),
+        };
+        Ok((receiver, drain))
+    }
+
+    fn start(&mut self) -> CuResult<()> {
+        let session = zenoh::Wait::wait(zenoh::open(self.config.clone()))
+            .map_err(cu_error_map("ZenohSrc: Failed to open session"))?;
+
+        let key_expr = KeyExpr::<'static>::new(self.topic.clone())
+            .map_err(cu_error_map("ZenohSrc: Invalid topic string"))?;
+        let subscriber = zenoh::Wait::wait(
+            session
+                .declare_subscriber(key_expr)
+                .with(RingChannel::new(self.depth)),
+        )
+        .map_err(cu_error_map("ZenohSrc: Failed to subscribe"))?;
+
+        debug!("ZenohSrc: Subscribed to {}", self.topic.as_str());
+        self.ctx = Some(ZenohContext {
+            session,
+            subscriber,
+        });
+        Ok(())
+    }
+
+    /// The next pending message, None if there is none.
+    fn try_recv<P: CuMsgPayload>(&mut self) -> CuResult<Option<CuMsg<P>>> {
+        let ctx = self
+            .ctx
+            .as_ref()
+            .ok_or_else(|| CuError::from("ZenohSrc: Context not found"))?;
+        let sample = ctx
+            .subscriber
+            .try_recv()
+            .map_err(cu_error_map("ZenohSrc: Failed to receive"))?;
+        sample.map(|sample| self.queue.decode(&sample)).transpose()
+    }
+
+    /// Takes all the pending messages, calling `received` for each of them in their order.
+    fn drain<P: CuMsgPayload>(&mut self, received: impl FnMut(CuMsg<P>)) -> CuResult<()> {
+        let ctx = self
+            .ctx
+            .as_ref()
+            .ok_or_else(|| CuError::from("ZenohSrc: Context not found"))?;
+        let next = || {
+            ctx.subscriber
+                .try_recv()
+                .map_err(cu_error_map("ZenohSrc: Failed to receive"))
+        };
+        self.queue.drain(next, received)
+    }
+
+    /// The status of the output showing the accounting of the queue to the monitor.
+    fn status(&self, drained: bool) -> String {
+        let stats = &self.queue.stats;
+        if drained {
+            format!("{}/{} lost {}", stats.last_depth, self.depth, stats.missed)
+        } else {
+            format!("lost {}", stats.missed)
+        }
+    }
+
+    fn stop(&mut self) -> CuResult<()> {
+        if let Some(ZenohContext {
+            session,
+            subscriber,
+        }) = self.ctx.take()
+        {
+            zenoh::Wait::wait(subscriber.undeclare())
+                .map_err(cu_error_map("ZenohSrc: Failed to undeclare subscriber"))?;
+            zenoh::Wait::wait(session.close())
+                .map_err(cu_error_map("ZenohSrc: Failed to close session"))?;
+        }
+        debug!("ZenohSrc: Stopped");
+        Ok(())
+    }
+}
+
+impl MsgQueue {
+    /// Decodes a message and accounts for it.
+    fn decode<P: CuMsgPayload>(&mut self, encoded: &impl Encoded) -> CuResult<CuMsg<P>> {
+        // The sink publishes the whole message, its metadata included.
+        let (msg, _): (CuMsg<P>, _) =
+            bincode::decode_from_slice(&encoded.bytes(), CuWireFormat::CONFIG)
+                .map_err(|e| CuError::new_with_cause("ZenohSrc: Failed to decode message", e))?;
+        self.stats.received += 1;
+        // The sink publishes every cycle, a gap in the sequence numbers is a lost message.
+        if let Some(last_seq) = self.last_seq {
+            self.stats.missed += msg.metadata.seq.saturating_sub(last_seq + 1);
+        }
+        self.last_seq = Some(msg.metadata.seq);
+        Ok(msg)
+    }
+
+    /// Decodes the messages given by `next` until it has none, calling `received` for each of
+    /// them in their order, and records the depth of the queue.
+    fn drain<E: Encoded, P: CuMsgPayload>(
+        &mut self,
+        mut next: impl FnMut() -> CuResult<Option<E>>,
+        mut received: impl FnMut(CuMsg<P>),
+    ) -> CuResult<()> {
+        let mut depth = 0;
+        while let Some(encoded) = next()? {
+            received(self.decode(&encoded)?);
+            depth += 1;
+        }
+        self.stats.last_depth = depth;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        Ok(())
+    }
+}
+
+/// Keeps the latest message with a payload of a drained queue, an empty message after it doesn't
+/// hide it.
+fn keep_latest<P: CuMsgPayload>(latest: &mut Option<CuMsg<P>>, msg: CuMsg<P>) {
+    if msg.payload().is_some() || latest.is_none() {
+        *latest = Some(msg);
+    }
+}
+
+/// The payloads of a drained queue and the time of validity they span.
+struct Batch<P> {
+    payloads: Vec<P>,
+    first: Tov,
+    last: Tov,
+}
+
+impl<P: CuMsgPayload> Batch<P> {
+    /// Starts from the vector of a previous batch to keep its allocation.
+    fn new(mut payloads: Vec<P>) -> Self {
+        payloads.clear();
+        Self {
+            payloads,
+            first: Tov::None,
+            last: Tov::None,
+        }
+    }
+
+    fn push(&mut self, msg: CuMsg<P>) {
+        let tov = msg.metadata.tov;
+        let Some(payload) = msg.payload() else {
+            return;
+        };
+        self.payloads.push(payload.clone());
+        if self.first == Tov::None {
+            self.first = tov;
+        }
+        self.last = tov;
+    }
+
+    /// Gives the batch in the output, empty if there is no payload.
+    fn finish(self, output: &mut CuMsg<Vec<P>>) {
+        if self.payloads.is_empty() {
+            output.clear_payload();
+            return;
+        }
+        output.set_payload(self.payloads);
+        output.metadata.tov = batch_tov(self.first, self.last);
+    }
+}
+
+impl<P> ZenohSrc<P>
+where
+    P: CuMsgPayload,
+{
+    /// The accounting of the queue of the received messages, also shown in the status of the
+    /// output.
+    pub fn stats(&self) -> ZenohSrcStats {
+        self.receiver.queue.stats
+    }
+}
+
+impl<P> Freezable for ZenohSrc<P> where P: CuMsgPayload {}
+
+impl<'cl, P> CuSrcTask<'cl> for ZenohSrc<P>
+where
+    P: CuMsgPayload + 'cl + 'static,
+{
+    type Output = output_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let (receiver, drain) = ZenohReceiver::new(config, false)?;
+        Ok(Self {
+            _marker: Default::default(),
+            receiver,
+            drain,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.receiver.start()
+    }
+
+    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let msg = if self.drain {
+            let mut latest = None;
+            self.receiver
+                .drain(|msg: CuMsg<P>| keep_latest(&mut latest, msg))?;
+            latest
+        } else {
+            self.receiver.try_recv()?
+        };
+        new_msg
+            .metadata
+            .set_status(self.receiver.status(self.drain));
+        let Some(msg) = msg else {
+            new_msg.clear_payload();
+            return Ok(());
+        };
+        match msg.payload() {
+            Some(payload) => new_msg.set_payload(payload.clone()),
+            None => new_msg.clear_payload(),
+        }
+        new_msg.metadata.tov = msg.metadata.tov;
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.receiver.stop()
+    }
+}
+
+impl<P> ZenohBatchSrc<P>
+where
+    P: CuMsgPayload,
+{
+    /// The accounting of the queue of the received messages, also shown in the status of the
+    /// output.
+    pub fn stats(&self) -> ZenohSrcStats {
+        self.receiver.queue.stats
+    }
+}
+
+impl<P> Freezable for ZenohBatchSrc<P> where P: CuMsgPayload {}
+
+impl<'cl, P> CuSrcTask<'cl> for ZenohBatchSrc<P>
+where
+    P: CuMsgPayload + 'cl + 'static,
+{
+    type Output = output_msg!('cl, Vec<P>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let (receiver, _) = ZenohReceiver::new(config, true)?;
+        Ok(Self {
+            _marker: Default::default(),
+            receiver,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.receiver.start()
+    }
+
+    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        // The copper list is reused, the batch of a previous cycle is still there.
+        let mut batch = Batch::new(new_msg.payload_mut().take().unwrap_or_default());
+        self.receiver.drain(|msg: CuMsg<P>| batch.push(msg))?;
+        batch.finish(new_msg);
+        new_msg.metadata.set_status(self.receiver.status(true));
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.receiver.stop()
+    }
+}
+
+/// The time of validity of a batch, from the start of its first message to the end of its last.
+fn batch_tov(first: Tov, last: Tov) -> Tov {
+    let start = match first {
+        Tov::Time(time) => time,
+        Tov::Range(range) => range.start,
+        Tov::None => return last,
+    };
+    match last {
+        Tov::Time(end) | Tov::Range(CuTimeRange { end, .. }) if end > start => {
+            Tov::Range(CuTimeRange { start, end })
+        }
+        _ => first,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu29::clock::CuDuration;
+
+    impl Encoded for Vec<u8> {
+        fn bytes(&self) -> Cow<'_, [u8]> {
+            Cow::Borrowed(self)
+        }
+    }
+
+    /// The messages as published by the sink, with their sequence numbers.
+    fn published(msgs: &[(u64, Option<u32>)]) -> Vec<Vec<u8>> {
+        msgs.iter()
+            .map(|(seq, payload)| {
+                let mut msg = CuMsg::new(*payload);
+                msg.metadata.seq = *seq;
+                msg.metadata.tov = Tov::Time(CuDuration::from_millis(*seq));
+                CuWireFormat::encode_to_vec(&msg).unwrap()
+            })
+            .collect()
+    }
+
+    fn drain<P: CuMsgPayload>(
+        queue: &mut MsgQueue,
+        pending: Vec<Vec<u8>>,
+        received: impl FnMut(CuMsg<P>),
+    ) {
+        let mut pending = pending.into_iter();
+        queue.drain(|| Ok(pending.next()), received).unwrap();
+    }
+
+    #[test]
+    fn test_drain_latest() {
+        let mut queue = MsgQueue::default();
+        let mut latest = None;
+        let pending = published(&[(0, Some(1)), (1, Some(2)), (2, None)]);
+        drain(&mut queue, pending, |msg| keep_latest(&mut latest, msg));
+        // The empty message after the last payload doesn't hide it.
+        let latest: CuMsg<u32> = latest.unwrap();
+        assert_eq!(latest.payload(), Some(&2));
+        assert_eq!(latest.metadata.seq, 1);
+        assert_eq!((queue.stats.last_depth, queue.stats.max_depth), (3, 3));
+
+        let mut latest = None;
+        drain(&mut queue, published(&[(3, None)]), |msg| {
+            keep_latest(&mut latest, msg)
+        });
+        assert_eq!(latest.unwrap().payload(), None::<&u32>);
+        drain::<u32>(&mut queue, Vec::new(), |_| panic!("nothing is pending"));
+        assert_eq!((queue.stats.last_depth, queue.stats.max_depth), (0, 3));
+    }
+
+    #[test]
+    fn test_drain_batch() {
+        let mut queue = MsgQueue::default();
+        let mut output = CuMsg::<Vec<u32>>::default();
+        let mut batch = Batch::new(Vec::new());
+        let pending = published(&[(0, Some(1)), (1, None), (2, Some(3))]);
+        drain(&mut queue, pending, |msg| batch.push(msg));
+        batch.finish(&mut output);
+        assert_eq!(output.payload(), Some(&vec![1, 3]));
+        assert_eq!(
+            output.metadata.tov,
+            batch_tov(
+                Tov::Time(CuDuration::from_millis(0)),
+                Tov::Time(CuDuration::from_millis(2))
+            )
+        );
+
+        // The vector of the previous batch is reused, without its payloads.
+        let previous = output.payload_mut().take().unwrap();
+        let capacity = previous.capacity();
+        let mut batch = Batch::new(previous);
+        drain(&mut queue, published(&[(3, Some(4))]), |msg| {
+            batch.push(msg)
+        });
+        batch.finish(&mut output);
+        assert_eq!(output.payload(), Some(&vec![4]));
+        assert_eq!(output.payload().unwrap().capacity(), capacity);
+
+        let mut batch = Batch::new(output.payload_mut().take().unwrap());
+        drain(&mut queue, published(&[(4, None)]), |msg| batch.push(msg));
+        batch.finish(&mut output);
+        assert_eq!(output.payload(), None);
+    }
+
+    #[test]
+    fn test_missed() {
+        let mut queue = MsgQueue::default();
+        for encoded in published(&[(5, Some(1)), (6, None), (9, Some(2)), (10, Some(3))]) {
+            queue.decode::<u32>(&encoded).unwrap();
+        }
+        // 7 and 8 were lost, the first message received is not a gap.
+        assert_eq!(queue.stats.received, 4);
+        assert_eq!(queue.stats.missed, 2);
+        // A restarted sink starts its sequence again.
+        queue.decode::<u32>(&published(&[(0, None)])[0]).unwrap();
+        assert_eq!(queue.stats.missed, 2);
+        assert!(queue.decode::<u32>(&vec![0xFF]).is_err());
+    }
+
+    #[test]
+    fn test_batch_tov() {
+        let time = |ms| Tov::Time(CuDuration::from_millis(ms));
+        let range = |start, end| {
+            Tov::Range(CuTimeRange {
+                start: CuDuration::from_millis(start),
+                end: CuDuration::from_millis(end),
+            })
+        };
+        assert_eq!(batch_tov(time(10), time(30)), range(10, 30));
+        assert_eq!(batch_tov(range(5, 10), range(20, 25)), range(5, 25));
+        // A single message keeps its time of validity.
+        assert_eq!(batch_tov(time(10), time(10)), time(10));
+        assert_eq!(batch_tov(Tov::None, time(30)), time(30));
+        assert_eq!(batch_tov(time(10), Tov::None), time(10));
+    }
+}
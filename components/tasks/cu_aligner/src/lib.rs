@@ -72,9 +72,11 @@ macro_rules! define_task {
                 // this is a tuple of iterators of CuMsgs
                 let tuple_of_iters = tuple_of_iters.unwrap();
 
-                // Populate the CuArray fields in the output message
+                // Populate the CuArray fields in the output message, the payloads are cloned so
+                // the ones holding a buffer handle (images...) are only shared, not copied.
+                let aligned = output.payload_mut().get_or_insert_with(Default::default);
                 $(
-                    output.payload_mut().as_mut().unwrap().$index.fill_from_iter(tuple_of_iters.$index.map(|msg| *msg.payload().unwrap()));
+                    aligned.$index.fill_from_iter(tuple_of_iters.$index.filter_map(|msg| msg.payload().cloned()));
                 )*
                 Ok(())
             }
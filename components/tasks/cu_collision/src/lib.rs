@@ -0,0 +1,249 @@
+//! A collision watchdog between the controller and the base: it checks the obstacles seen by the
+//! range sensors against a stop zone around the robot, swept along the commanded velocity, and
+//! overrides the commands with a stop, or engages the e-stop, while they are in it.
+//!
+//! The obstacles are in the frame of the robot, from a point cloud, range readings or arrays of
+//! them, see [Obstacles].
+
+mod zone;
+
+pub use zone::*;
+
+use cu29::estop;
+use cu29::payload::CuArray;
+use cu29::prelude::*;
+use cu_sensor_payloads::{PointCloudSoa, RangeReading};
+use cu_spatial_payloads::Twist2D;
+use std::marker::PhantomData;
+use uom::si::length::meter;
+
+/// The payloads of obstacles the watchdog can check.
+pub trait Obstacles {
+    /// Calls `f` with the planar position in the frame of the robot of the obstacles between the
+    /// heights `min_z` and `max_z` when they have one, until it returns true. Returns true then.
+    fn any_point(&self, min_z: f32, max_z: f32, f: impl FnMut(f32, f32) -> bool) -> bool;
+}
+
+impl<const N: usize> Obstacles for PointCloudSoa<N> {
+    fn any_point(&self, min_z: f32, max_z: f32, mut f: impl FnMut(f32, f32) -> bool) -> bool {
+        let len = self.len;
+        self.x[..len]
+            .iter()
+            .zip(&self.y[..len])
+            .zip(&self.z[..len])
+            .any(|((x, y), z)| {
+                (min_z..=max_z).contains(&z.0.get::<meter>())
+                    && f(x.0.get::<meter>(), y.0.get::<meter>())
+            })
+    }
+}
+
+/// The echoes of the range sensors, which are not filtered by height.
+impl Obstacles for RangeReading {
+    fn any_point(&self, _min_z: f32, _max_z: f32, mut f: impl FnMut(f32, f32) -> bool) -> bool {
+        self.echo().is_some_and(|(x, y)| f(x, y))
+    }
+}
+
+impl<T: Obstacles, const N: usize> Obstacles for CuArray<T, N> {
+    fn any_point(&self, min_z: f32, max_z: f32, mut f: impl FnMut(f32, f32) -> bool) -> bool {
+        self.as_slice()
+            .iter()
+            .any(|obstacles| obstacles.any_point(min_z, max_z, &mut f))
+    }
+}
+
+#[derive(CuConfigStruct)]
+struct CollisionWatchdogConfig {
+    /// "x,y; x,y; ..." in meters, the rectangle of length_m x width_m otherwise.
+    stop_polygon: Option<String>,
+    #[config(default = 0.6, range = 0.0..)]
+    length_m: f64,
+    #[config(default = 0.4, range = 0.0..)]
+    width_m: f64,
+    #[config(default = 0.1, range = 0.0..)]
+    margin_m: f64,
+    #[config(default = 0.5, range = 0.0..)]
+    braking_time_s: f64,
+    #[config(default = 0.05)]
+    min_z_m: f64,
+    #[config(default = 2.0)]
+    max_z_m: f64,
+    #[config(default = "stop")]
+    action: String,
+    #[config(default = 500)]
+    clear_time_ms: u32,
+    #[config(default = 500)]
+    sensor_timeout_ms: u32,
+}
+
+/// What the watchdog does when an obstacle is in the stop zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// Outputs zero velocities instead of the commands until the zone is clear.
+    Stop,
+    /// Also engages the e-stop, the sinks going to their safe state until it is reset.
+    EStop,
+}
+
+/// This is the task overriding the velocity commands, the first input, with a stop while the
+/// obstacles of the second input are in the stop zone and for `clear_time_ms` after they left it.
+/// It also stops when the sensor has been silent for `sensor_timeout_ms` (0 to disable).
+pub struct CollisionWatchdog<P> {
+    _marker: PhantomData<P>,
+    zone: StopZone,
+    min_z: f32,
+    max_z: f32,
+    action: WatchdogAction,
+    clear_time: CuDuration,
+    sensor_timeout: Option<CuDuration>,
+    last_detection: Option<CuTime>,
+    last_reading: Option<CuTime>,
+    stopping: bool,
+}
+
+impl<P> Freezable for CollisionWatchdog<P> {}
+
+impl<'cl, P> CuTask<'cl> for CollisionWatchdog<P>
+where
+    P: Obstacles + CuMsgPayload + 'cl,
+{
+    type Input = input_msg!('cl, Twist2D, P);
+    type Output = output_msg!('cl, Twist2D);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = CollisionWatchdogConfig::from_config(config)?;
+        let polygon = match &config.stop_polygon {
+            Some(polygon) => StopZone::parse_polygon(polygon)?,
+            None => StopZone::rectangle(config.length_m as f32, config.width_m as f32),
+        };
+        let action = match config.action.as_str() {
+            "stop" => WatchdogAction::Stop,
+            "estop" => WatchdogAction::EStop,
+            action => {
+                return Err(CuError::from(format!(
+                    "CollisionWatchdog: unknown action \"{action}\", expected stop or estop."
+                )))
+            }
+        };
+        let millis = |ms: u32| CuDuration::from(ms as u64 * 1_000_000);
+        Ok(Self {
+            _marker: PhantomData,
+            zone: StopZone::new(
+                polygon,
+                config.margin_m as f32,
+                config.braking_time_s as f32,
+            )?,
+            min_z: config.min_z_m as f32,
+            max_z: config.max_z_m as f32,
+            action,
+            clear_time: millis(config.clear_time_ms),
+            sensor_timeout: (config.sensor_timeout_ms > 0)
+                .then(|| millis(config.sensor_timeout_ms)),
+            last_detection: None,
+            last_reading: None,
+            stopping: false,
+        })
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        let now = clock.now();
+        let (command, obstacles) = input;
+        let twist = command.payload().copied();
+        // The sensor gets its timeout to start from the first cycle.
+        let last_reading = *self.last_reading.get_or_insert(now);
+
+        if let Some(obstacles) = obstacles.payload() {
+            self.last_reading = Some(now);
+            let sweep = self.zone.sweep(&twist.unwrap_or_default());
+            let zone = &self.zone;
+            if obstacles.any_point(self.min_z, self.max_z, |x, y| zone.intrudes(x, y, &sweep)) {
+                self.last_detection = Some(now);
+            }
+        }
+
+        let silent = obstacles.payload().is_none()
+            && self
+                .sensor_timeout
+                .is_some_and(|timeout| now - last_reading > timeout);
+        let obstacle = self
+            .last_detection
+            .is_some_and(|last| now - last <= self.clear_time);
+        let stopping = silent || obstacle;
+        if stopping != self.stopping {
+            if stopping && silent {
+                debug!("CollisionWatchdog: the sensor is silent, stopping.");
+            } else if stopping {
+                debug!("CollisionWatchdog: obstacle in the stop zone, stopping.");
+            } else {
+                debug!("CollisionWatchdog: the stop zone is clear.");
+            }
+            self.stopping = stopping;
+        }
+
+        if stopping {
+            if self.action == WatchdogAction::EStop {
+                estop::engage(if silent {
+                    "Collision watchdog: the sensor is silent"
+                } else {
+                    "Collision watchdog: obstacle in the stop zone"
+                });
+            }
+            output.set_payload(Twist2D::default());
+            output.metadata.tov = now.into();
+            return Ok(());
+        }
+        match twist {
+            Some(twist) => {
+                output.set_payload(twist);
+                output.metadata.tov = command.metadata.tov;
+            }
+            None => output.clear_payload(),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_obstacles() {
+        // A sonar at the front looking forward, echoing 30cm away.
+        let reading = RangeReading {
+            distance: 0.3,
+            min: 0.02,
+            max: 4.0,
+            mount_x: 0.3,
+            mount_y: 0.0,
+            mount_yaw: 0.0,
+        };
+        assert_eq!(reading.echo(), Some((0.6, 0.0)));
+        let no_echo = RangeReading {
+            distance: 4.5,
+            ..reading
+        };
+        assert_eq!(no_echo.echo(), None);
+
+        // Clear standing, in the way at 0.5m/s within 0.5s.
+        let zone = StopZone::new(StopZone::rectangle(0.6, 0.4), 0.1, 0.5).unwrap();
+        let standing = zone.sweep(&Twist2D::default());
+        let forward = zone.sweep(&Twist2D {
+            linear: 0.5,
+            angular: 0.0,
+        });
+        let mut sonars = CuArray::<RangeReading, 4>::new();
+        sonars.fill_from_iter([no_echo, reading]);
+        assert!(!sonars.any_point(0.0, 0.0, |x, y| zone.intrudes(x, y, &standing)));
+        assert!(sonars.any_point(0.0, 0.0, |x, y| zone.intrudes(x, y, &forward)));
+    }
+}
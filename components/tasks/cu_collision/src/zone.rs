@@ -0,0 +1,165 @@
+//! The stop zone: a polygon around the footprint of the robot, grown by a margin and swept along
+//! the commanded velocity over the braking time.
+
+use cu29::prelude::*;
+use cu_spatial_payloads::Twist2D;
+
+/// The number of poses checked along the sweep, besides the current one.
+const SWEEP_STEPS: usize = 4;
+
+/// A pose of the robot along the sweep, in the current frame of the robot: x, y and yaw.
+pub type SweepPose = (f32, f32, f32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopZone {
+    polygon: Vec<(f32, f32)>,
+    margin: f32,
+    braking_time: f32,
+    /// The distance from the origin of the robot beyond which no point can be in the zone.
+    radius: f32,
+}
+
+impl StopZone {
+    /// A zone from the vertices of a polygon in the frame of the robot, in meters.
+    pub fn new(polygon: Vec<(f32, f32)>, margin: f32, braking_time: f32) -> CuResult<Self> {
+        if polygon.len() < 3 {
+            return Err(CuError::from(format!(
+                "CollisionWatchdog: the stop polygon needs at least 3 vertices, got {}.",
+                polygon.len()
+            )));
+        }
+        let radius = polygon.iter().map(|(x, y)| x.hypot(*y)).fold(0.0, f32::max) + margin.max(0.0);
+        Ok(Self {
+            polygon,
+            margin: margin.max(0.0),
+            braking_time: braking_time.max(0.0),
+            radius,
+        })
+    }
+
+    /// The vertices of a rectangle centered on the robot, `length` along x.
+    pub fn rectangle(length: f32, width: f32) -> Vec<(f32, f32)> {
+        let (x, y) = (length / 2.0, width / 2.0);
+        vec![(x, y), (-x, y), (-x, -y), (x, -y)]
+    }
+
+    /// Parses vertices written "x,y; x,y; ...".
+    pub fn parse_polygon(text: &str) -> CuResult<Vec<(f32, f32)>> {
+        text.split(';')
+            .filter(|vertex| !vertex.trim().is_empty())
+            .map(|vertex| {
+                let coordinates: Option<Vec<f32>> = vertex
+                    .split(',')
+                    .map(|c| c.trim().parse::<f32>().ok())
+                    .collect();
+                match coordinates.as_deref() {
+                    Some(&[x, y]) => Ok((x, y)),
+                    _ => Err(CuError::from(format!(
+                        "CollisionWatchdog: invalid vertex \"{vertex}\" in the stop polygon, expected \"x,y\"."
+                    ))),
+                }
+            })
+            .collect()
+    }
+
+    /// If the point is in the polygon or within the margin of its edges.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let mut inside = false;
+        let mut previous = self.polygon[self.polygon.len() - 1];
+        for &vertex in &self.polygon {
+            let ((x0, y0), (x1, y1)) = (previous, vertex);
+            if (y0 > y) != (y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+                inside = !inside;
+            }
+            if self.margin > 0.0 && segment_distance((x, y), previous, vertex) <= self.margin {
+                return true;
+            }
+            previous = vertex;
+        }
+        inside
+    }
+
+    /// The poses of the robot following `twist` over the braking time, starting with the current one.
+    pub fn sweep(&self, twist: &Twist2D) -> [SweepPose; SWEEP_STEPS + 1] {
+        let mut poses: [SweepPose; SWEEP_STEPS + 1] = [(0.0, 0.0, 0.0); SWEEP_STEPS + 1];
+        let dt = self.braking_time / SWEEP_STEPS as f32;
+        let (linear, angular) = (twist.linear as f32, twist.angular as f32);
+        for i in 1..=SWEEP_STEPS {
+            let (x, y, yaw) = poses[i - 1];
+            let (sin, cos) = yaw.sin_cos();
+            poses[i] = (
+                x + linear * cos * dt,
+                y + linear * sin * dt,
+                yaw + angular * dt,
+            );
+        }
+        poses
+    }
+
+    /// If the point is in the zone at one of the poses of the sweep.
+    pub fn intrudes(&self, x: f32, y: f32, sweep: &[SweepPose]) -> bool {
+        sweep.iter().any(|&(px, py, yaw)| {
+            let (dx, dy) = (x - px, y - py);
+            if dx.hypot(dy) > self.radius {
+                return false;
+            }
+            let (sin, cos) = yaw.sin_cos();
+            self.contains(cos * dx + sin * dy, cos * dy - sin * dx)
+        })
+    }
+}
+
+fn segment_distance(point: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (abx, aby) = (b.0 - a.0, b.1 - a.1);
+    let (apx, apy) = (point.0 - a.0, point.1 - a.1);
+    let length2 = abx * abx + aby * aby;
+    let t = if length2 > 0.0 {
+        ((apx * abx + apy * aby) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (apx - t * abx).hypot(apy - t * aby)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone() {
+        let polygon = StopZone::parse_polygon("0.5,0.3; -0.5,0.3; -0.5,-0.3; 0.5,-0.3").unwrap();
+        assert_eq!(polygon, StopZone::rectangle(1.0, 0.6));
+        assert!(StopZone::parse_polygon("0.5,0.3; 1").is_err());
+        assert!(StopZone::new(polygon[..2].to_vec(), 0.0, 0.0).is_err());
+
+        let zone = StopZone::new(polygon.clone(), 0.1, 1.0).unwrap();
+        assert!(zone.contains(0.0, 0.0) && zone.contains(0.45, -0.25));
+        // In the margin, and out of it.
+        assert!(zone.contains(0.58, 0.0) && zone.contains(0.0, -0.38));
+        assert!(!zone.contains(0.62, 0.0) && !zone.contains(0.58, 0.38));
+
+        // 1m ahead, hit at 0.5m/s in 1s but not when standing or backing up.
+        let forward = zone.sweep(&Twist2D {
+            linear: 0.5,
+            angular: 0.0,
+        });
+        assert_eq!(forward[0], (0.0, 0.0, 0.0));
+        assert!((forward[4].0 - 0.5).abs() < 1e-6);
+        assert!(zone.intrudes(1.05, 0.0, &forward));
+        assert!(!zone.intrudes(1.05, 0.0, &zone.sweep(&Twist2D::default())));
+        let backward = zone.sweep(&Twist2D {
+            linear: -0.5,
+            angular: 0.0,
+        });
+        assert!(!zone.intrudes(1.05, 0.0, &backward));
+        assert!(zone.intrudes(-1.05, 0.0, &backward));
+
+        // Turning in place sweeps the corners.
+        let turning = zone.sweep(&Twist2D {
+            linear: 0.0,
+            angular: 1.0,
+        });
+        assert!(!zone.intrudes(0.0, 0.55, &zone.sweep(&Twist2D::default())));
+        assert!(zone.intrudes(0.0, 0.55, &turning));
+    }
+}
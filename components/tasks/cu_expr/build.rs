@@ -0,0 +1,10 @@
+fn main() {
+    let evalexpr_enabled = std::env::var("CARGO_FEATURE_EVALEXPR").is_ok();
+    if !evalexpr_enabled {
+        println!("cargo:warning=evalexpr feature is not enabled. Skipping cu_expr build.");
+    }
+    println!(
+        "cargo:rustc-env=LOG_INDEX_DIR={}",
+        std::env::var("OUT_DIR").unwrap()
+    );
+}
@@ -0,0 +1,221 @@
+use crate::paths::{leaves, set};
+use cu29::prelude::*;
+use evalexpr::{
+    build_operator_tree, ContextWithMutableVariables, HashMapContext, Node, Value as ExprValue,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+/// The name of the input payload in the expressions.
+const INPUT: &str = "in";
+/// The config key of the expression of the whole output, `out.<field>` for its fields.
+const OUTPUT: &str = "out";
+
+/// An expression and the node of the output it replaces.
+struct Assignment {
+    key: String,
+    path: Vec<String>,
+    expression: Node,
+}
+
+fn expr_value(leaf: &Value) -> ExprValue {
+    match leaf {
+        Value::Bool(b) => ExprValue::Boolean(*b),
+        Value::U8(n) => ExprValue::Int(*n as i64),
+        Value::U16(n) => ExprValue::Int(*n as i64),
+        Value::U32(n) => ExprValue::Int(*n as i64),
+        Value::U64(n) => ExprValue::Int(*n as i64),
+        Value::I8(n) => ExprValue::Int(*n as i64),
+        Value::I16(n) => ExprValue::Int(*n as i64),
+        Value::I32(n) => ExprValue::Int(*n as i64),
+        Value::I64(n) => ExprValue::Int(*n),
+        Value::F32(f) => ExprValue::Float(*f as f64),
+        Value::F64(f) => ExprValue::Float(*f),
+        Value::Char(c) => ExprValue::String(c.to_string()),
+        Value::String(s) => ExprValue::String(s.clone()),
+        Value::CuTime(CuDuration(nanos)) => ExprValue::Int(*nanos as i64),
+        _ => ExprValue::Empty,
+    }
+}
+
+fn payload_value(result: ExprValue) -> Value {
+    match result {
+        ExprValue::Boolean(b) => Value::Bool(b),
+        ExprValue::Int(n) => Value::I64(n),
+        ExprValue::Float(f) => Value::F64(f),
+        ExprValue::String(s) => Value::String(s),
+        ExprValue::Tuple(items) => Value::Seq(items.into_iter().map(payload_value).collect()),
+        ExprValue::Empty => Value::Unit,
+    }
+}
+
+/// A task computing its output from its input with expressions given in its config, to convert
+/// units or to glue components whose payloads do not match without writing a task.
+/// The input is `in` in the expressions and its fields `in.<field>`, the config key `out` gives
+/// the whole output and the keys `out.<field>` its fields, the other fields keep their default.
+/// See the README of this crate.
+pub struct ExprTask<I, O> {
+    assignments: Vec<Assignment>,
+    _payloads: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O> Freezable for ExprTask<I, O> {}
+
+impl<'cl, I, O> CuTask<'cl> for ExprTask<I, O>
+where
+    I: CuMsgPayload + Serialize + 'cl,
+    O: CuMsgPayload + Serialize + DeserializeOwned + 'cl,
+{
+    type Input = input_msg!('cl, I);
+    type Output = output_msg!('cl, O);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let ComponentConfig(config) =
+            config.ok_or_else(|| CuError::from("ExprTask needs a config."))?;
+        let mut assignments = Vec::new();
+        for (key, value) in config {
+            let path: Vec<String> = if key == OUTPUT {
+                Vec::new()
+            } else if let Some(path) = key.strip_prefix("out.") {
+                path.split('.').map(String::from).collect()
+            } else {
+                continue;
+            };
+            let expression = build_operator_tree(&value.to_string()).map_err(|e| {
+                CuError::new_with_cause(&format!("ExprTask: could not parse \"{key}\""), e)
+            })?;
+            assignments.push(Assignment {
+                key: key.clone(),
+                path,
+                expression,
+            });
+        }
+        if assignments.is_empty() {
+            return Err(
+                "ExprTask needs an \"out\" or \"out.<field>\" expression in its config.".into(),
+            );
+        }
+        // The whole output first, then its fields.
+        assignments.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(ExprTask {
+            assignments,
+            _payloads: PhantomData,
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        output.metadata.tov = input.metadata.tov;
+        let Some(payload) = input.payload() else {
+            output.clear_payload();
+            return Ok(());
+        };
+        let payload = to_value(payload)
+            .map_err(|e| CuError::new_with_cause("ExprTask: could not read the input", e))?;
+        let mut input_leaves = Vec::new();
+        leaves(&payload, INPUT.to_string(), &mut input_leaves);
+        // A context per call, the type of an optional field can change from a call to the next.
+        let mut context = HashMapContext::new();
+        for (path, leaf) in input_leaves {
+            context
+                .set_value(path, expr_value(leaf))
+                .map_err(|e| CuError::new_with_cause("ExprTask: could not bind the input", e))?;
+        }
+
+        let mut value = to_value(O::default())
+            .map_err(|e| CuError::new_with_cause("ExprTask: could not build the output", e))?;
+        for assignment in &self.assignments {
+            let result = assignment
+                .expression
+                .eval_with_context(&context)
+                .map_err(|e| {
+                    CuError::new_with_cause(
+                        &format!("ExprTask: could not evaluate \"{}\"", assignment.key),
+                        e,
+                    )
+                })?;
+            let path: Vec<&str> = assignment.path.iter().map(String::as_str).collect();
+            set(&mut value, &path, payload_value(result)).map_err(|e| {
+                CuError::from(format!(
+                    "ExprTask: could not write \"{}\": {e}",
+                    assignment.key
+                ))
+            })?;
+        }
+        let payload: O = value
+            .deserialize_into()
+            .map_err(|e| CuError::new_with_cause("ExprTask: could not build the output", e))?;
+        output.set_payload(payload);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bincode::{Decode, Encode};
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Default, Encode, Decode, Serialize, Deserialize)]
+    struct Wheel {
+        rpm: i32,
+        radius_m: f32,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Encode, Decode, Serialize, Deserialize)]
+    struct Speed {
+        mps: f32,
+        forward: bool,
+        source: String,
+    }
+
+    #[test]
+    fn test_expressions() {
+        let mut config = ComponentConfig::new();
+        config.set("out.mps", "in.rpm / 60.0 * in.radius_m".to_string());
+        config.set("out.forward", "in.rpm >= 0".to_string());
+        let mut task = ExprTask::<Wheel, Speed>::new(Some(&config)).unwrap();
+
+        let clock = RobotClock::new();
+        let input = CuMsg::new(Some(Wheel {
+            rpm: -60,
+            radius_m: 0.5,
+        }));
+        let mut output = CuMsg::<Speed>::new(None);
+        task.process(&clock, &input, &mut output).unwrap();
+        let speed = output.payload().unwrap();
+        assert_eq!(speed.mps, -0.5);
+        assert!(!speed.forward);
+        // Not given, left to its default.
+        assert_eq!(speed.source, "");
+
+        let input = CuMsg::<Wheel>::new(None);
+        task.process(&clock, &input, &mut output).unwrap();
+        assert_eq!(output.payload(), None);
+
+        // A scalar conversion.
+        let mut config = ComponentConfig::new();
+        config.set("out", "in / 1000.0".to_string());
+        let mut task = ExprTask::<u32, f64>::new(Some(&config)).unwrap();
+        let mut output = CuMsg::<f64>::new(None);
+        task.process(&clock, &CuMsg::new(Some(1500u32)), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), Some(&1.5));
+
+        let mut config = ComponentConfig::new();
+        config.set("out.nope", "1".to_string());
+        let mut task = ExprTask::<u32, Speed>::new(Some(&config)).unwrap();
+        let mut output = CuMsg::<Speed>::new(None);
+        assert!(task
+            .process(&clock, &CuMsg::new(Some(1u32)), &mut output)
+            .is_err());
+    }
+}
@@ -0,0 +1,7 @@
+#[cfg(feature = "evalexpr")]
+mod cu_expr_impl;
+#[cfg(feature = "evalexpr")]
+mod paths;
+
+#[cfg(feature = "evalexpr")]
+pub use cu_expr_impl::*;
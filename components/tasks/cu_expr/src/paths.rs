@@ -0,0 +1,139 @@
+//! The payloads seen as trees of values: the expressions read the leaves of the input by their
+//! path and their results replace leaves of the output.
+
+use cu29::prelude::*;
+
+/// Collects the leaves of a payload by path, `in.pose.x` for the field x of the field pose of the
+/// input `in`. The items of a sequence are numbered: `in.ranges.0`.
+pub(crate) fn leaves<'a>(value: &'a Value, path: String, found: &mut Vec<(String, &'a Value)>) {
+    match value {
+        Value::Option(Some(inner)) | Value::Newtype(inner) => leaves(inner, path, found),
+        Value::Seq(items) => {
+            for (index, item) in items.iter().enumerate() {
+                leaves(item, format!("{path}.{index}"), found);
+            }
+        }
+        Value::Map(fields) => {
+            for (key, field) in fields {
+                leaves(field, format!("{path}.{key}"), found);
+            }
+        }
+        // Raw bytes mean nothing to an expression.
+        Value::Bytes(_) => {}
+        leaf => found.push((path, leaf)),
+    }
+}
+
+/// Replaces the node of a payload at this path, the fields below its root, by a result cast to
+/// the type of the node it replaces.
+pub(crate) fn set(value: &mut Value, path: &[&str], result: Value) -> Result<(), String> {
+    match (value, path) {
+        (Value::Option(Some(inner)), [_, ..]) | (Value::Newtype(inner), [_, ..]) => {
+            set(inner, path, result)
+        }
+        (value, []) => {
+            *value = cast_like(value, result)?;
+            Ok(())
+        }
+        (Value::Map(fields), [field, rest @ ..]) => {
+            let node = fields
+                .get_mut(&Value::String(field.to_string()))
+                .ok_or_else(|| format!("the output has no field `{field}`"))?;
+            set(node, rest, result)
+        }
+        (Value::Seq(items), [index, rest @ ..]) => {
+            let node = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index))
+                .ok_or_else(|| format!("the output has no item `{index}`"))?;
+            set(node, rest, result)
+        }
+        (_, [field, ..]) => Err(format!("the output has no field `{field}`")),
+    }
+}
+
+/// Casts a result to the type of a leaf of the payload, the numbers with `as` so a float result
+/// written in an integer field is truncated.
+fn cast_like(target: &Value, result: Value) -> Result<Value, String> {
+    let number = match result {
+        Value::I64(n) => Some((n, n as f64)),
+        Value::F64(f) => Some((f as i64, f)),
+        _ => None,
+    };
+    Ok(match (target, result) {
+        (Value::Option(_), Value::Unit) => Value::Option(None),
+        (Value::Option(Some(inner)), result) => {
+            Value::Option(Some(Box::new(cast_like(inner, result)?)))
+        }
+        // The type of an absent value is unknown, the deserialization of the output converts it.
+        (Value::Option(None), result) => Value::Option(Some(Box::new(result))),
+        (Value::Newtype(inner), result) => Value::Newtype(Box::new(cast_like(inner, result)?)),
+        (Value::Bool(_), Value::Bool(b)) => Value::Bool(b),
+        (Value::String(_), Value::String(s)) => Value::String(s),
+        (Value::Char(_), Value::String(s)) if s.chars().count() == 1 => {
+            Value::Char(s.chars().next().unwrap_or_default())
+        }
+        (Value::Seq(_), Value::Seq(items)) => Value::Seq(items),
+        (target, result) => match (target, number) {
+            (Value::U8(_), Some((int, _))) => Value::U8(int as u8),
+            (Value::U16(_), Some((int, _))) => Value::U16(int as u16),
+            (Value::U32(_), Some((int, _))) => Value::U32(int as u32),
+            (Value::U64(_), Some((int, _))) => Value::U64(int as u64),
+            (Value::I8(_), Some((int, _))) => Value::I8(int as i8),
+            (Value::I16(_), Some((int, _))) => Value::I16(int as i16),
+            (Value::I32(_), Some((int, _))) => Value::I32(int as i32),
+            (Value::I64(_), Some((int, _))) => Value::I64(int),
+            (Value::F32(_), Some((_, float))) => Value::F32(float as f32),
+            (Value::F64(_), Some((_, float))) => Value::F64(float),
+            (Value::CuTime(_), Some((int, _))) => Value::CuTime(CuTime::from(int as u64)),
+            _ => return Err(format!("{result} cannot replace {target}")),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Pose {
+        x: f32,
+        heading: Option<f64>,
+        samples: Vec<u16>,
+    }
+
+    #[test]
+    fn test_leaves_and_set() {
+        let pose = Pose {
+            x: 1.5,
+            heading: None,
+            samples: vec![3, 4],
+        };
+        let value = to_value(&pose).unwrap();
+        let mut found = Vec::new();
+        leaves(&value, "in".to_string(), &mut found);
+        let paths: Vec<&str> = found.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["in.heading", "in.samples.0", "in.samples.1", "in.x"]
+        );
+
+        let mut value = to_value(Pose::default()).unwrap();
+        set(&mut value, &["x"], Value::I64(2)).unwrap();
+        set(&mut value, &["heading"], Value::F64(0.5)).unwrap();
+        set(&mut value, &["samples"], Value::Seq(vec![Value::I64(7)])).unwrap();
+        let pose: Pose = value.clone().deserialize_into().unwrap();
+        assert_eq!(
+            pose,
+            Pose {
+                x: 2.0,
+                heading: Some(0.5),
+                samples: vec![7],
+            }
+        );
+        assert!(set(&mut value, &["y"], Value::I64(2)).is_err());
+        assert!(set(&mut value, &["x"], Value::Bool(true)).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{output_pool, packed_format, process_image};
+use cu29::prelude::*;
+use cu_sensor_payloads::{CuImage, CuImageBufferFormat};
+use std::sync::Arc;
+
+#[derive(CuConfigStruct)]
+struct YuvToRgbConfig {
+    #[config(range = 1..)]
+    width: u32,
+    #[config(range = 1..)]
+    height: u32,
+}
+
+/// BT.601 limited range conversion of a pixel, the usual one of the webcams.
+#[inline]
+pub fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = 298 * (y as i32 - 16);
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    [
+        clamp(c + 409 * e),
+        clamp(c - 100 * d - 208 * e),
+        clamp(c + 516 * d),
+    ]
+}
+
+/// Converts a YUYV (YUV 4:2:2 interleaved) or NV12 (YUV 4:2:0 with a Y plane followed by an
+/// interleaved UV plane, both with the same stride) image to a packed RGB one in `dst`.
+pub fn convert_to_rgb(format: &CuImageBufferFormat, src: &[u8], dst: &mut [u8]) -> CuResult<()> {
+    let (width, height, stride) = (
+        format.width as usize,
+        format.height as usize,
+        format.stride as usize,
+    );
+    let (row_bytes, rows) = match &format.pixel_format {
+        b"YUYV" => (width.div_ceil(2) * 4, height),
+        b"NV12" => (width.div_ceil(2) * 2, height + height.div_ceil(2)),
+        _ => {
+            return Err(CuError::from(format!(
+                "YuvToRgb: the pixel format {} is not supported, expected YUYV or NV12.",
+                String::from_utf8_lossy(&format.pixel_format)
+            )))
+        }
+    };
+    if stride < row_bytes || src.len() < stride * rows || dst.len() < width * height * 3 {
+        return Err(CuError::from(format!(
+            "YuvToRgb: the buffer of {} bytes doesn't fit {width}x{height} images with a stride of {stride}.",
+            src.len()
+        )));
+    }
+    let chroma = &src[stride * height..];
+    for (y, dst_row) in dst.chunks_exact_mut(width * 3).take(height).enumerate() {
+        let src_row = &src[y * stride..];
+        for (x, rgb) in dst_row.chunks_exact_mut(3).enumerate() {
+            let (luma, u, v) = if &format.pixel_format == b"YUYV" {
+                let pair = &src_row[x / 2 * 4..];
+                (src_row[x * 2], pair[1], pair[3])
+            } else {
+                let uv = &chroma[y / 2 * stride + x / 2 * 2..];
+                (src_row[x], uv[0], uv[1])
+            };
+            rgb.copy_from_slice(&yuv_to_rgb(luma, u, v));
+        }
+    }
+    Ok(())
+}
+
+/// This is a task converting the YUYV or NV12 images of the cameras to RGB (RGB3).
+pub struct YuvToRgb {
+    pool: Arc<CuHostMemoryPool<Vec<u8>>>,
+}
+
+impl Freezable for YuvToRgb {}
+
+impl<'cl> CuTask<'cl> for YuvToRgb {
+    type Input = input_msg!('cl, CuImage<Vec<u8>>);
+    type Output = output_msg!('cl, CuImage<Vec<u8>>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let YuvToRgbConfig { width, height } = YuvToRgbConfig::from_config(config)?;
+        Ok(Self {
+            pool: output_pool("imgproc_yuv_to_rgb", width, height)?,
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        process_image(&self.pool, input, output, |format, src, dst| {
+            convert_to_rgb(format, src, dst)?;
+            Ok(packed_format(
+                format.width as usize,
+                format.height as usize,
+                3,
+                *b"RGB3",
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_to_rgb() {
+        assert_eq!(yuv_to_rgb(16, 128, 128), [0, 0, 0]);
+        assert_eq!(yuv_to_rgb(235, 128, 128), [255, 255, 255]);
+        assert_eq!(yuv_to_rgb(81, 90, 240), [255, 0, 0]);
+
+        // 2x2 pixels: black and white on the first row, red on the second.
+        let yuyv = CuImageBufferFormat {
+            width: 2,
+            height: 2,
+            stride: 4,
+            pixel_format: *b"YUYV",
+        };
+        let mut dst = [0u8; 12];
+        convert_to_rgb(&yuyv, &[16, 128, 235, 128, 81, 90, 81, 240], &mut dst).unwrap();
+        assert_eq!(dst, [0, 0, 0, 255, 255, 255, 255, 0, 0, 255, 0, 0]);
+
+        // The same chroma for the 4 pixels in NV12.
+        let nv12 = CuImageBufferFormat {
+            pixel_format: *b"NV12",
+            stride: 2,
+            ..yuyv
+        };
+        convert_to_rgb(&nv12, &[81, 81, 81, 81, 90, 240], &mut dst).unwrap();
+        assert_eq!(dst, [255, 0, 0, 255, 0, 0, 255, 0, 0, 255, 0, 0]);
+
+        let bgr = CuImageBufferFormat {
+            pixel_format: *b"BGR3",
+            ..yuyv
+        };
+        assert!(convert_to_rgb(&bgr, &[0; 12], &mut dst).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{output_pool, packed_format, process_image, Pixels};
+use cu29::prelude::*;
+use cu_sensor_payloads::CuImage;
+use std::sync::Arc;
+
+#[derive(CuConfigStruct)]
+struct CropConfig {
+    #[config(range = 1..)]
+    width: u32,
+    #[config(range = 1..)]
+    height: u32,
+    x: Option<u32>,
+    y: Option<u32>,
+}
+
+/// A region of interest of `width` x `height` pixels, at (`x`, `y`) or centered in the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Roi {
+    pub x: Option<usize>,
+    pub y: Option<usize>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Roi {
+    /// The top left corner of the region in an image of `width` x `height` pixels.
+    pub fn origin(&self, width: usize, height: usize) -> CuResult<(usize, usize)> {
+        let x = self.x.unwrap_or(width.saturating_sub(self.width) / 2);
+        let y = self.y.unwrap_or(height.saturating_sub(self.height) / 2);
+        if x + self.width > width || y + self.height > height {
+            return Err(CuError::from(format!(
+                "Crop: the region of {}x{} at ({x}, {y}) is outside of the {width}x{height} image.",
+                self.width, self.height
+            )));
+        }
+        Ok((x, y))
+    }
+}
+
+/// Copies the region of interest of `src` to a packed image in `dst`.
+pub fn crop(src: &Pixels, dst: &mut [u8], roi: &Roi) -> CuResult<()> {
+    let (x, y) = roi.origin(src.width, src.height)?;
+    let row_bytes = roi.width * src.bytes_per_pixel;
+    let start = y * src.stride + x * src.bytes_per_pixel;
+    for (dst_row, src_row) in dst
+        .chunks_exact_mut(row_bytes)
+        .zip(src.data[start..].chunks(src.stride))
+        .take(roi.height)
+    {
+        dst_row.copy_from_slice(&src_row[..row_bytes]);
+    }
+    Ok(())
+}
+
+/// This is a task cropping the images to a region of interest, centered if its config has no x and y.
+pub struct Crop {
+    pool: Arc<CuHostMemoryPool<Vec<u8>>>,
+    roi: Roi,
+}
+
+impl Freezable for Crop {}
+
+impl<'cl> CuTask<'cl> for Crop {
+    type Input = input_msg!('cl, CuImage<Vec<u8>>);
+    type Output = output_msg!('cl, CuImage<Vec<u8>>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let CropConfig {
+            width,
+            height,
+            x,
+            y,
+        } = CropConfig::from_config(config)?;
+        Ok(Self {
+            pool: output_pool("imgproc_crop", width, height)?,
+            roi: Roi {
+                x: x.map(|x| x as usize),
+                y: y.map(|y| y as usize),
+                width: width as usize,
+                height: height as usize,
+            },
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        process_image(&self.pool, input, output, |format, src, dst| {
+            let src = Pixels::new(format, src)?;
+            crop(&src, dst, &self.roi)?;
+            Ok(packed_format(
+                self.roi.width,
+                self.roi.height,
+                src.bytes_per_pixel,
+                format.pixel_format,
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu_sensor_payloads::CuImageBufferFormat;
+
+    #[test]
+    fn test_crop() {
+        let format = CuImageBufferFormat {
+            width: 4,
+            height: 4,
+            stride: 5,
+            pixel_format: *b"GRAY",
+        };
+        let data: Vec<u8> = (0..20).collect();
+        let src = Pixels::new(&format, &data).unwrap();
+        let mut roi = Roi {
+            x: None,
+            y: None,
+            width: 2,
+            height: 2,
+        };
+
+        let mut dst = [0u8; 4];
+        crop(&src, &mut dst, &roi).unwrap();
+        assert_eq!(dst, [6, 7, 11, 12]);
+
+        roi.x = Some(2);
+        roi.y = Some(0);
+        crop(&src, &mut dst, &roi).unwrap();
+        assert_eq!(dst, [2, 3, 7, 8]);
+
+        roi.x = Some(3);
+        assert!(crop(&src, &mut dst, &roi).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Image preprocessing tasks, the usual glue between a camera source and an inference task:
+//! [Resize], [Crop], [YuvToRgb] and [Undistort].
+//!
+//! They take a `CuImage<Vec<u8>>` and output a new one from a pool allocated in `new()`, with the
+//! size from their config, so nothing is allocated while the robot runs.
+//! The packed 8 bits formats are supported: GRAY (or GREY), RGB3, BGR3 and RGBA.
+
+mod colorspace;
+mod crop;
+mod resize;
+mod undistort;
+
+pub use colorspace::*;
+pub use crop::*;
+pub use resize::*;
+pub use undistort::*;
+
+use cu29::prelude::*;
+use cu_sensor_payloads::{CuImage, CuImageBufferFormat};
+use std::sync::Arc;
+
+/// Number of images of the output pools, enough for the copperlists in flight.
+const POOL_SIZE: usize = 4;
+
+/// The bytes per pixel of the packed pixel formats.
+pub fn bytes_per_pixel(pixel_format: &[u8; 4]) -> CuResult<usize> {
+    match pixel_format {
+        b"GRAY" | b"GREY" => Ok(1),
+        b"RGB3" | b"BGR3" => Ok(3),
+        b"RGBA" => Ok(4),
+        _ => Err(CuError::from(format!(
+            "The pixel format {} is not supported, expected GRAY, RGB3, BGR3 or RGBA.",
+            String::from_utf8_lossy(pixel_format)
+        ))),
+    }
+}
+
+/// A view on the pixels of an image with a packed pixel format.
+#[derive(Debug, Clone, Copy)]
+pub struct Pixels<'a> {
+    pub data: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+    pub bytes_per_pixel: usize,
+}
+
+impl<'a> Pixels<'a> {
+    pub fn new(format: &CuImageBufferFormat, data: &'a [u8]) -> CuResult<Self> {
+        let pixels = Self {
+            data,
+            width: format.width as usize,
+            height: format.height as usize,
+            stride: format.stride as usize,
+            bytes_per_pixel: bytes_per_pixel(&format.pixel_format)?,
+        };
+        if pixels.width == 0
+            || pixels.height == 0
+            || pixels.stride < pixels.width * pixels.bytes_per_pixel
+            || data.len() < pixels.stride * pixels.height
+        {
+            return Err(CuError::from(format!(
+                "The buffer of {} bytes doesn't fit {}x{} images with a stride of {}.",
+                data.len(),
+                pixels.width,
+                pixels.height,
+                pixels.stride
+            )));
+        }
+        Ok(pixels)
+    }
+
+    #[inline]
+    fn pixel(&self, x: usize, y: usize) -> &'a [u8] {
+        let start = y * self.stride + x * self.bytes_per_pixel;
+        &self.data[start..start + self.bytes_per_pixel]
+    }
+
+    /// Bilinear interpolation at (x, y) in pixel coordinates, the center of the first pixel being (0, 0).
+    /// The coordinates are clamped to the image.
+    #[inline]
+    pub fn sample_bilinear(&self, x: f32, y: f32, out: &mut [u8]) {
+        let x = x.clamp(0.0, (self.width - 1) as f32);
+        let y = y.clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let (p00, p10, p01, p11) = (
+            self.pixel(x0, y0),
+            self.pixel(x1, y0),
+            self.pixel(x0, y1),
+            self.pixel(x1, y1),
+        );
+        for (c, value) in out.iter_mut().enumerate() {
+            let top = p00[c] as f32 * (1.0 - fx) + p10[c] as f32 * fx;
+            let bottom = p01[c] as f32 * (1.0 - fx) + p11[c] as f32 * fx;
+            *value = (top * (1.0 - fy) + bottom * fy + 0.5) as u8;
+        }
+    }
+}
+
+/// The pool of the output images of a task, holding `width` x `height` images of up to 4 bytes per pixel.
+fn output_pool(id: &str, width: u32, height: u32) -> CuResult<Arc<CuHostMemoryPool<Vec<u8>>>> {
+    let size = width as usize * height as usize * 4;
+    CuHostMemoryPool::new(id, POOL_SIZE, || vec![0u8; size])
+}
+
+/// Fills an image from the pool with `fill` and sets it as the payload of `output`, with the time of
+/// validity of `input`. An empty input gives an empty output.
+fn process_image<F>(
+    pool: &CuHostMemoryPool<Vec<u8>>,
+    input: &CuMsg<CuImage<Vec<u8>>>,
+    output: &mut CuMsg<CuImage<Vec<u8>>>,
+    fill: F,
+) -> CuResult<()>
+where
+    F: FnOnce(&CuImageBufferFormat, &[u8], &mut [u8]) -> CuResult<CuImageBufferFormat>,
+{
+    let Some(image) = input.payload() else {
+        output.clear_payload();
+        return Ok(());
+    };
+    let handle = pool
+        .acquire()
+        .ok_or(CuError::from("Failed to acquire buffer from pool"))?;
+    let format = image.buffer_handle.with_inner(|src| {
+        handle.with_inner_mut(|dst| fill(&image.format, &src[..], &mut dst[..]))
+    })?;
+    let mut processed = CuImage::new(format, handle);
+    processed.seq = image.seq;
+    output.metadata.tov = input.metadata.tov;
+    output.set_payload(processed);
+    Ok(())
+}
+
+/// The format of a packed image without padding.
+fn packed_format(
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    pixel_format: [u8; 4],
+) -> CuImageBufferFormat {
+    CuImageBufferFormat {
+        width: width as u32,
+        height: height as u32,
+        stride: (width * bytes_per_pixel) as u32,
+        pixel_format,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_bilinear() {
+        let format = CuImageBufferFormat {
+            width: 2,
+            height: 2,
+            stride: 3,
+            pixel_format: *b"GRAY",
+        };
+        let data = [0, 100, 7, 100, 200, 7];
+        let pixels = Pixels::new(&format, &data).unwrap();
+        let mut value = [0u8];
+        pixels.sample_bilinear(0.5, 0.5, &mut value);
+        assert_eq!(value, [100]);
+        pixels.sample_bilinear(1.0, 0.0, &mut value);
+        assert_eq!(value, [100]);
+        // Clamped to the last row and column, the padding is never read.
+        pixels.sample_bilinear(5.0, 5.0, &mut value);
+        assert_eq!(value, [200]);
+        assert!(Pixels::new(&format, &data[..5]).is_err());
+    }
+}
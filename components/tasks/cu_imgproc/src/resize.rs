@@ -0,0 +1,142 @@
+use crate::{output_pool, packed_format, process_image, Pixels};
+use cu29::prelude::*;
+use cu_sensor_payloads::CuImage;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How the pixels of the resized images are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    Bilinear,
+}
+
+impl FromStr for Interpolation {
+    type Err = CuError;
+
+    fn from_str(s: &str) -> CuResult<Self> {
+        match s {
+            "nearest" => Ok(Interpolation::Nearest),
+            "bilinear" => Ok(Interpolation::Bilinear),
+            _ => Err(CuError::from(format!(
+                "Resize: unknown interpolation \"{s}\", expected nearest or bilinear."
+            ))),
+        }
+    }
+}
+
+#[derive(CuConfigStruct)]
+struct ResizeConfig {
+    #[config(range = 1..)]
+    width: u32,
+    #[config(range = 1..)]
+    height: u32,
+    #[config(default = "bilinear")]
+    interpolation: String,
+}
+
+/// Resizes `src` to a packed `width` x `height` image in `dst`. The borders of both images are
+/// aligned, the pixels being sampled at their centers like OpenCV does.
+pub fn resize(
+    src: &Pixels,
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    interpolation: Interpolation,
+) {
+    let bpp = src.bytes_per_pixel;
+    let scale_x = src.width as f32 / width as f32;
+    let scale_y = src.height as f32 / height as f32;
+    for (y, row) in dst.chunks_exact_mut(width * bpp).take(height).enumerate() {
+        let sy = (y as f32 + 0.5) * scale_y - 0.5;
+        for (x, pixel) in row.chunks_exact_mut(bpp).enumerate() {
+            let sx = (x as f32 + 0.5) * scale_x - 0.5;
+            match interpolation {
+                Interpolation::Nearest => {
+                    let (nx, ny) = (sx.round().max(0.0) as usize, sy.round().max(0.0) as usize);
+                    pixel.copy_from_slice(src.pixel(nx.min(src.width - 1), ny.min(src.height - 1)));
+                }
+                Interpolation::Bilinear => src.sample_bilinear(sx, sy, pixel),
+            }
+        }
+    }
+}
+
+/// This is a task resizing the images to the size of its config, keeping their pixel format.
+pub struct Resize {
+    pool: Arc<CuHostMemoryPool<Vec<u8>>>,
+    width: usize,
+    height: usize,
+    interpolation: Interpolation,
+}
+
+impl Freezable for Resize {}
+
+impl<'cl> CuTask<'cl> for Resize {
+    type Input = input_msg!('cl, CuImage<Vec<u8>>);
+    type Output = output_msg!('cl, CuImage<Vec<u8>>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let ResizeConfig {
+            width,
+            height,
+            interpolation,
+        } = ResizeConfig::from_config(config)?;
+        Ok(Self {
+            pool: output_pool("imgproc_resize", width, height)?,
+            width: width as usize,
+            height: height as usize,
+            interpolation: interpolation.parse()?,
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        process_image(&self.pool, input, output, |format, src, dst| {
+            let src = Pixels::new(format, src)?;
+            resize(&src, dst, self.width, self.height, self.interpolation);
+            Ok(packed_format(
+                self.width,
+                self.height,
+                src.bytes_per_pixel,
+                format.pixel_format,
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu_sensor_payloads::CuImageBufferFormat;
+
+    #[test]
+    fn test_resize() {
+        let format = CuImageBufferFormat {
+            width: 4,
+            height: 2,
+            stride: 4,
+            pixel_format: *b"GRAY",
+        };
+        let data = [0, 10, 20, 30, 40, 50, 60, 70];
+        let src = Pixels::new(&format, &data).unwrap();
+
+        let mut dst = [0u8; 2];
+        resize(&src, &mut dst, 2, 1, Interpolation::Bilinear);
+        assert_eq!(dst, [25, 45]);
+        resize(&src, &mut dst, 2, 1, Interpolation::Nearest);
+        assert_eq!(dst, [50, 70]);
+
+        // Upscaling repeats the pixels.
+        let mut dst = [0u8; 16];
+        resize(&src, &mut dst, 8, 2, Interpolation::Nearest);
+        assert_eq!(dst[..8], [0, 0, 10, 10, 20, 20, 30, 30]);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{output_pool, packed_format, process_image, Pixels};
+use cu29::prelude::*;
+use cu_sensor_payloads::{CameraCalibration, CameraDistortion, CuImage};
+use std::sync::Arc;
+
+#[derive(CuConfigStruct)]
+struct UndistortConfig {
+    #[config(range = 1..)]
+    width: u32,
+    #[config(range = 1..)]
+    height: u32,
+    fx: f64,
+    fy: f64,
+    cx: f64,
+    cy: f64,
+    #[config(default = "radtan")]
+    model: String,
+    #[config(default)]
+    k1: f64,
+    #[config(default)]
+    k2: f64,
+    #[config(default)]
+    k3: f64,
+    #[config(default)]
+    k4: f64,
+    #[config(default)]
+    p1: f64,
+    #[config(default)]
+    p2: f64,
+}
+
+/// For every pixel of the undistorted image, where to sample it in the distorted one.
+/// The undistorted image has the same intrinsics as the camera.
+pub fn undistortion_map(calibration: &CameraCalibration) -> Vec<(f32, f32)> {
+    let CameraCalibration {
+        width,
+        height,
+        fx,
+        fy,
+        cx,
+        cy,
+        ..
+    } = *calibration;
+    let mut map = Vec::with_capacity(width as usize * height as usize);
+    for v in 0..height {
+        for u in 0..width {
+            let (x, y) = calibration.distort_to_pixel((u as f64 - cx) / fx, (v as f64 - cy) / fy);
+            map.push((x as f32, y as f32));
+        }
+    }
+    map
+}
+
+/// Remaps `src` to a packed image in `dst` following `map`, the pixels sampled outside of `src`
+/// are black.
+pub fn remap(src: &Pixels, dst: &mut [u8], map: &[(f32, f32)]) {
+    let bpp = src.bytes_per_pixel;
+    let (max_x, max_y) = ((src.width - 1) as f32, (src.height - 1) as f32);
+    for (pixel, &(x, y)) in dst.chunks_exact_mut(bpp).zip(map) {
+        if (0.0..=max_x).contains(&x) && (0.0..=max_y).contains(&y) {
+            src.sample_bilinear(x, y, pixel);
+        } else {
+            pixel.fill(0);
+        }
+    }
+}
+
+/// This is a task undistorting the images of a camera with the radtan or fisheye model calibrated
+/// with OpenCV or Kalibr. The remapping is computed once in `new()`.
+pub struct Undistort {
+    pool: Arc<CuHostMemoryPool<Vec<u8>>>,
+    width: usize,
+    height: usize,
+    map: Vec<(f32, f32)>,
+}
+
+impl Freezable for Undistort {}
+
+impl<'cl> CuTask<'cl> for Undistort {
+    type Input = input_msg!('cl, CuImage<Vec<u8>>);
+    type Output = output_msg!('cl, CuImage<Vec<u8>>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let UndistortConfig {
+            width,
+            height,
+            fx,
+            fy,
+            cx,
+            cy,
+            model,
+            k1,
+            k2,
+            k3,
+            k4,
+            p1,
+            p2,
+        } = UndistortConfig::from_config(config)?;
+        let distortion = match model.as_str() {
+            "radtan" => CameraDistortion::RadTan { k1, k2, k3, p1, p2 },
+            "fisheye" => CameraDistortion::Fisheye { k1, k2, k3, k4 },
+            _ => {
+                return Err(CuError::from(format!(
+                    "Undistort: unknown model \"{model}\", expected radtan or fisheye."
+                )))
+            }
+        };
+        let calibration = CameraCalibration {
+            width,
+            height,
+            fx,
+            fy,
+            cx,
+            cy,
+            distortion,
+            extrinsics: None,
+        };
+        Ok(Self {
+            pool: output_pool("imgproc_undistort", width, height)?,
+            width: width as usize,
+            height: height as usize,
+            map: undistortion_map(&calibration),
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        process_image(&self.pool, input, output, |format, src, dst| {
+            let src = Pixels::new(format, src)?;
+            if (src.width, src.height) != (self.width, self.height) {
+                return Err(CuError::from(format!(
+                    "Undistort: the images are {}x{}, the calibration is for {}x{}.",
+                    src.width, src.height, self.width, self.height
+                )));
+            }
+            remap(&src, dst, &self.map);
+            Ok(packed_format(
+                self.width,
+                self.height,
+                src.bytes_per_pixel,
+                format.pixel_format,
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu_sensor_payloads::CuImageBufferFormat;
+
+    #[test]
+    fn test_undistort() {
+        let mut calibration = CameraCalibration {
+            width: 4,
+            height: 4,
+            fx: 2.0,
+            fy: 2.0,
+            cx: 1.5,
+            cy: 1.5,
+            ..Default::default()
+        };
+        // Without distortion, every pixel maps to itself.
+        let map = undistortion_map(&calibration);
+        assert_eq!(map[5], (1.0, 1.0));
+        assert_eq!(map[15], (3.0, 3.0));
+
+        // The barrel distortion of a wide angle lens brings the corners toward the center.
+        calibration.distortion = CameraDistortion::RadTan {
+            k1: -0.2,
+            k2: 0.0,
+            k3: 0.0,
+            p1: 0.0,
+            p2: 0.0,
+        };
+        let map = undistortion_map(&calibration);
+        assert!(map[15].0 < 3.0 && map[15].0 > 1.5);
+        // The principal point is where it is on both.
+        assert_eq!(calibration.distortion.distort(0.0, 0.0), (0.0, 0.0));
+
+        // An equidistant fisheye without coefficients still maps the angles linearly.
+        let fisheye = CameraDistortion::Fisheye {
+            k1: 0.0,
+            k2: 0.0,
+            k3: 0.0,
+            k4: 0.0,
+        };
+        let (x, _) = fisheye.distort(1.0, 0.0);
+        assert!((x - std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+
+        let format = CuImageBufferFormat {
+            width: 2,
+            height: 1,
+            stride: 2,
+            pixel_format: *b"GRAY",
+        };
+        let src = Pixels::new(&format, &[10, 20]).unwrap();
+        let mut dst = [0u8; 3];
+        remap(&src, &mut dst, &[(1.0, 0.0), (0.5, 0.0), (2.5, 0.0)]);
+        assert_eq!(dst, [20, 15, 0]);
+    }
+}
@@ -2,6 +2,7 @@ use bincode::de::Decoder;
 use bincode::enc::Encoder;
 use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
+use cu29::params::{register_param, CuParam};
 use cu29::prelude::*;
 use std::marker::PhantomData;
 
@@ -122,6 +123,38 @@ impl PIDController {
     }
 }
 
+/// The gains of the controller tunable at runtime, see cu29::params.
+struct TunableGains {
+    kp: CuParam,
+    ki: CuParam,
+    kd: CuParam,
+}
+
+impl TunableGains {
+    /// Registers the gains as `<prefix>.kp`, `<prefix>.ki` and `<prefix>.kd`.
+    fn register(prefix: &str, pid: &PIDController) -> CuResult<Self> {
+        let gain = |name: &str, value: f32, description: &str| {
+            register_param(
+                &format!("{prefix}.{name}"),
+                value as f64,
+                Some((0.0, f64::MAX)),
+                description,
+            )
+        };
+        Ok(Self {
+            kp: gain("kp", pid.kp, "Proportional gain")?,
+            ki: gain("ki", pid.ki, "Integral gain")?,
+            kd: gain("kd", pid.kd, "Derivative gain")?,
+        })
+    }
+
+    fn apply(&self, pid: &mut PIDController) {
+        pid.kp = self.kp.get_f64() as f32;
+        pid.ki = self.ki.get_f64() as f32;
+        pid.kd = self.kd.get_f64() as f32;
+    }
+}
+
 /// This is the Copper task encapsulating the PID controller.
 pub struct GenericPIDTask<I>
 where
@@ -129,6 +162,7 @@ where
 {
     _marker: PhantomData<I>,
     pid: PIDController,
+    gains: Option<TunableGains>,
     first_run: bool,
     last_tov: CuTime,
     setpoint: f32,
@@ -193,9 +227,15 @@ where
                     sampling,
                 );
 
+                let gains = match config.get::<String>("params") {
+                    Some(prefix) => Some(TunableGains::register(&prefix, &pid)?),
+                    None => None,
+                };
+
                 Ok(Self {
                     _marker: PhantomData,
                     pid,
+                    gains,
                     first_run: true,
                     last_tov: CuTime::default(),
                     setpoint,
@@ -231,6 +271,10 @@ where
                 let dt = tov - self.last_tov;
                 self.last_tov = tov;
 
+                if let Some(gains) = &self.gains {
+                    gains.apply(&mut self.pid);
+                }
+
                 // update the status of the pid.
                 let state = self.pid.next_control_output(measure, dt);
                 // But safety check if the input is within operational margins and cut power if it is not.
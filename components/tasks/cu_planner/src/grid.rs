@@ -0,0 +1,305 @@
+//! The cost map the planners search: the occupancy grid thresholded and inflated by the radius of
+//! the robot, so the robot can be planned as a point. Plus A* on its 8-connected cells.
+
+use cu29::prelude::*;
+use cu_spatial_payloads::{OccupancyGrid, UNKNOWN_CELL};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// How the cells of the occupancy grid become obstacles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostMapParams {
+    /// Occupation from which a cell is an obstacle, in 0..=100.
+    pub occupied_threshold: i8,
+    /// If the unknown cells can be crossed.
+    pub allow_unknown: bool,
+    /// The obstacles grow by this radius in meters.
+    pub inflation_radius: f64,
+}
+
+/// The blocked cells of an occupancy grid, its buffers being reused across the map updates.
+#[derive(Debug, Default)]
+pub struct CostMap {
+    pub width: usize,
+    pub height: usize,
+    pub resolution: f64,
+    pub origin: (f64, f64),
+    blocked: Vec<bool>,
+    /// The cell offsets covered by the inflation disk.
+    disk: Vec<(i64, i64)>,
+}
+
+impl CostMap {
+    pub fn update(&mut self, grid: &OccupancyGrid, params: &CostMapParams) -> CuResult<()> {
+        let (width, height) = (grid.width as usize, grid.height as usize);
+        if grid.data.len() != width * height || grid.resolution <= 0.0 {
+            return Err(CuError::from(format!(
+                "Planner: invalid occupancy grid of {width}x{height} cells of {}m with {} values.",
+                grid.resolution,
+                grid.data.len()
+            )));
+        }
+        self.width = width;
+        self.height = height;
+        self.resolution = grid.resolution;
+        self.origin = (grid.origin_x, grid.origin_y);
+
+        let radius = (params.inflation_radius / grid.resolution).ceil() as i64;
+        self.disk.clear();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if ((dx * dx + dy * dy) as f64).sqrt() * grid.resolution <= params.inflation_radius
+                {
+                    self.disk.push((dx, dy));
+                }
+            }
+        }
+
+        self.blocked.clear();
+        self.blocked.resize(width * height, false);
+        for (i, &value) in grid.data.iter().enumerate() {
+            let obstacle = if value == UNKNOWN_CELL {
+                !params.allow_unknown
+            } else {
+                value >= params.occupied_threshold
+            };
+            if !obstacle {
+                continue;
+            }
+            let (cx, cy) = ((i % width) as i64, (i / width) as i64);
+            for &(dx, dy) in &self.disk {
+                let (x, y) = (cx + dx, cy + dy);
+                if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                    self.blocked[y as usize * width + x as usize] = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// If the robot can't be in the cell, the cells out of the map being blocked.
+    #[inline]
+    pub fn is_blocked(&self, cx: i64, cy: i64) -> bool {
+        if cx < 0 || cy < 0 || cx as usize >= self.width || cy as usize >= self.height {
+            return true;
+        }
+        self.blocked[cy as usize * self.width + cx as usize]
+    }
+
+    /// The cell containing the point (x, y), even out of the map.
+    #[inline]
+    pub fn cell_at(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            ((x - self.origin.0) / self.resolution).floor() as i64,
+            ((y - self.origin.1) / self.resolution).floor() as i64,
+        )
+    }
+
+    #[inline]
+    pub fn cell_center(&self, cx: usize, cy: usize) -> (f64, f64) {
+        (
+            self.origin.0 + (cx as f64 + 0.5) * self.resolution,
+            self.origin.1 + (cy as f64 + 0.5) * self.resolution,
+        )
+    }
+}
+
+/// The cost of a straight and a diagonal move in tenths of cells.
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+const NEIGHBORS: [(i64, i64, u32); 8] = [
+    (1, 0, STRAIGHT),
+    (-1, 0, STRAIGHT),
+    (0, 1, STRAIGHT),
+    (0, -1, STRAIGHT),
+    (1, 1, DIAGONAL),
+    (1, -1, DIAGONAL),
+    (-1, 1, DIAGONAL),
+    (-1, -1, DIAGONAL),
+];
+
+/// The distance in tenths of cells of the 8-connected moves.
+#[inline]
+fn octile(a: (usize, usize), b: (usize, usize)) -> u32 {
+    let (dx, dy) = (a.0.abs_diff(b.0) as u32, a.1.abs_diff(b.1) as u32);
+    STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+}
+
+/// A* on the 8-connected cells of a cost map, its buffers being reused across the plans.
+#[derive(Debug, Default)]
+pub struct GridAStar {
+    cost: Vec<u32>,
+    parent: Vec<u32>,
+    open: BinaryHeap<Reverse<(u32, u32)>>,
+}
+
+impl GridAStar {
+    fn reset(&mut self, map: &CostMap) {
+        self.cost.clear();
+        self.cost.resize(map.width * map.height, u32::MAX);
+        self.parent.clear();
+        self.parent.resize(map.width * map.height, u32::MAX);
+        self.open.clear();
+    }
+
+    /// Expands the cells from `start` in the order of their cost plus `heuristic`, until `stop`
+    /// accepts one. The diagonal moves don't cut the corners of the obstacles, the start cell
+    /// can be blocked so the robot can get out of an inflated obstacle.
+    fn search(
+        &mut self,
+        map: &CostMap,
+        start: (usize, usize),
+        heuristic: impl Fn((usize, usize)) -> u32,
+        stop: impl Fn((usize, usize)) -> bool,
+    ) -> Option<usize> {
+        self.reset(map);
+        let width = map.width;
+        let start_index = start.1 * width + start.0;
+        self.cost[start_index] = 0;
+        self.open
+            .push(Reverse((heuristic(start), start_index as u32)));
+        while let Some(Reverse((priority, index))) = self.open.pop() {
+            let index = index as usize;
+            let cell = (index % width, index / width);
+            let cost = self.cost[index];
+            if priority > cost.saturating_add(heuristic(cell)) {
+                // Already expanded with a lower cost.
+                continue;
+            }
+            if stop(cell) {
+                return Some(index);
+            }
+            for (dx, dy, step) in NEIGHBORS {
+                let (x, y) = (cell.0 as i64 + dx, cell.1 as i64 + dy);
+                let cuts_corner = dx != 0
+                    && dy != 0
+                    && (map.is_blocked(cell.0 as i64 + dx, cell.1 as i64)
+                        || map.is_blocked(cell.0 as i64, cell.1 as i64 + dy));
+                if map.is_blocked(x, y) || cuts_corner {
+                    continue;
+                }
+                let (x, y) = (x as usize, y as usize);
+                let next = y * width + x;
+                let next_cost = cost + step;
+                if next_cost < self.cost[next] {
+                    self.cost[next] = next_cost;
+                    self.parent[next] = index as u32;
+                    self.open
+                        .push(Reverse((next_cost + heuristic((x, y)), next as u32)));
+                }
+            }
+        }
+        None
+    }
+
+    /// The cells of the shortest path from `start` to `goal`, both included, in `path`.
+    /// Returns false if the goal can't be reached.
+    pub fn plan(
+        &mut self,
+        map: &CostMap,
+        start: (usize, usize),
+        goal: (usize, usize),
+        path: &mut Vec<(usize, usize)>,
+    ) -> bool {
+        path.clear();
+        if map.is_blocked(goal.0 as i64, goal.1 as i64) {
+            return false;
+        }
+        let Some(mut index) =
+            self.search(map, start, |cell| octile(cell, goal), |cell| cell == goal)
+        else {
+            return false;
+        };
+        loop {
+            path.push((index % map.width, index / map.width));
+            if self.parent[index] == u32::MAX {
+                break;
+            }
+            index = self.parent[index] as usize;
+        }
+        path.reverse();
+        true
+    }
+
+    /// The distances in tenths of cells from every cell to `goal` around the obstacles,
+    /// u32::MAX for the unreachable ones.
+    pub fn distances(&mut self, map: &CostMap, goal: (usize, usize)) -> &[u32] {
+        // The moves are symmetric: the distances from the goal are the distances to it.
+        self.search(map, goal, |_| 0, |_| false);
+        &self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 10x10 grid of 1m cells with a wall at x = 5 from y = 0 to 7.
+    fn walled_grid() -> OccupancyGrid {
+        let mut grid = OccupancyGrid::new(10, 10, 1.0, 0.0, 0.0);
+        grid.data.fill(0);
+        for y in 0..8 {
+            grid.set(5, y, 100);
+        }
+        grid
+    }
+
+    #[test]
+    fn test_cost_map() {
+        let mut grid = walled_grid();
+        grid.set(0, 9, UNKNOWN_CELL);
+        let mut params = CostMapParams {
+            occupied_threshold: 50,
+            allow_unknown: false,
+            inflation_radius: 0.0,
+        };
+        let mut map = CostMap::default();
+        map.update(&grid, &params).unwrap();
+        assert!(map.is_blocked(5, 0) && !map.is_blocked(4, 0) && !map.is_blocked(5, 8));
+        assert!(map.is_blocked(0, 9) && map.is_blocked(-1, 0) && map.is_blocked(10, 0));
+
+        params.allow_unknown = true;
+        params.inflation_radius = 1.0;
+        map.update(&grid, &params).unwrap();
+        assert!(!map.is_blocked(0, 9));
+        assert!(map.is_blocked(4, 0) && map.is_blocked(6, 7) && map.is_blocked(5, 8));
+        assert!(!map.is_blocked(4, 8) && !map.is_blocked(3, 0));
+
+        grid.data.pop();
+        assert!(map.update(&grid, &params).is_err());
+    }
+
+    #[test]
+    fn test_astar() {
+        let mut map = CostMap::default();
+        let params = CostMapParams {
+            occupied_threshold: 50,
+            allow_unknown: false,
+            inflation_radius: 0.0,
+        };
+        map.update(&walled_grid(), &params).unwrap();
+        let mut astar = GridAStar::default();
+        let mut path = Vec::new();
+        assert!(astar.plan(&map, (2, 2), (8, 2), &mut path));
+        assert_eq!(path.first(), Some(&(2, 2)));
+        assert_eq!(path.last(), Some(&(8, 2)));
+        // Around the end of the wall, without crossing it or cutting its corner.
+        assert!(path.contains(&(5, 8)));
+        assert!(path
+            .iter()
+            .all(|&(x, y)| !map.is_blocked(x as i64, y as i64)));
+        for step in path.windows(2) {
+            assert!(step[0].0.abs_diff(step[1].0) <= 1 && step[0].1.abs_diff(step[1].1) <= 1);
+        }
+
+        let distances = astar.distances(&map, (8, 2));
+        assert_eq!(distances[2 * 10 + 8], 0);
+        assert_eq!(distances[2 * 10 + 9], STRAIGHT);
+        assert_eq!(distances[5], u32::MAX);
+
+        // Into the wall.
+        assert!(!astar.plan(&map, (2, 2), (5, 2), &mut path));
+        assert!(path.is_empty());
+    }
+}
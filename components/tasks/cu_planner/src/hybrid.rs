@@ -0,0 +1,262 @@
+//! Hybrid A* (Dolgov et al. 2008): A* on continuous poses moving along the arcs a car-like robot can
+//! follow, the poses being pruned per cell and heading bin. The distances to the goal around the
+//! obstacles, from the grid A*, guide the search.
+
+use crate::grid::{CostMap, GridAStar};
+use cu_spatial_payloads::Pose2D;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::f64::consts::{PI, TAU};
+
+/// The kinematics of the robot and the limits of the search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HybridParams {
+    /// Smallest turning radius of the robot in meters.
+    pub turning_radius: f64,
+    /// Number of headings told apart in a cell.
+    pub heading_bins: usize,
+    /// How close to the goal in meters and radians the path has to end.
+    pub goal_tolerance: f64,
+    pub heading_tolerance: f64,
+    /// The search gives up after expanding this many poses.
+    pub max_expansions: usize,
+}
+
+/// The turn penalty makes the paths go straight when they can.
+const TURN_PENALTY: f64 = 1.05;
+
+/// The absolute difference of two angles in radians, in 0..=PI.
+pub fn angle_difference(a: f64, b: f64) -> f64 {
+    let d = (a - b).rem_euclid(TAU);
+    if d > PI {
+        TAU - d
+    } else {
+        d
+    }
+}
+
+/// The pose after moving `length` meters forward with the curvature `curvature`.
+fn arc(pose: &Pose2D, curvature: f64, length: f64) -> Pose2D {
+    if curvature == 0.0 {
+        return Pose2D::new(
+            pose.x + length * pose.theta.cos(),
+            pose.y + length * pose.theta.sin(),
+            pose.theta,
+        );
+    }
+    let theta = pose.theta + curvature * length;
+    Pose2D::new(
+        pose.x + (theta.sin() - pose.theta.sin()) / curvature,
+        pose.y + (pose.theta.cos() - theta.cos()) / curvature,
+        theta.rem_euclid(TAU),
+    )
+}
+
+#[derive(Debug)]
+struct Node {
+    pose: Pose2D,
+    cost: f64,
+    parent: Option<usize>,
+}
+
+/// A node to expand, the lowest priority first.
+#[derive(Debug, PartialEq)]
+struct Open {
+    priority: f64,
+    node: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Hybrid A* on a cost map, its buffers being reused across the plans.
+#[derive(Debug, Default)]
+pub struct HybridAStar {
+    grid: GridAStar,
+    nodes: Vec<Node>,
+    open: BinaryHeap<Open>,
+    closed: HashSet<(i64, i64, usize)>,
+}
+
+impl HybridAStar {
+    /// The poses of a path from `start` to within the tolerances of `goal` in `path`, driving forward.
+    /// Returns false if the goal can't be reached or the search gave up.
+    pub fn plan(
+        &mut self,
+        map: &CostMap,
+        start: Pose2D,
+        goal: Pose2D,
+        params: &HybridParams,
+        path: &mut Vec<Pose2D>,
+    ) -> bool {
+        path.clear();
+        let (goal_x, goal_y) = map.cell_at(goal.x, goal.y);
+        if map.is_blocked(goal_x, goal_y) {
+            return false;
+        }
+        let distances = self.grid.distances(map, (goal_x as usize, goal_y as usize));
+        let resolution = map.resolution;
+        // The grid distance is shortened by the size of the start and goal cells to never overestimate.
+        let heuristic = |pose: &Pose2D| {
+            let (x, y) = map.cell_at(pose.x, pose.y);
+            let euclidean = pose.distance(&goal);
+            if map.is_blocked(x, y) {
+                return euclidean;
+            }
+            let cells = distances[y as usize * map.width + x as usize] as f64 / 10.0;
+            euclidean.max((cells - 1.5) * resolution)
+        };
+
+        let bins = params.heading_bins.max(1);
+        let key = |pose: &Pose2D| {
+            let (x, y) = map.cell_at(pose.x, pose.y);
+            let bin = (pose.theta.rem_euclid(TAU) / TAU * bins as f64) as usize % bins;
+            (x, y, bin)
+        };
+        let curvature = 1.0 / params.turning_radius;
+        // Long enough to leave the cell and, turning, the heading bin.
+        let step = (1.5 * resolution).max(params.turning_radius * TAU / bins as f64 * 1.1);
+        let samples = (step / (0.5 * resolution)).ceil() as usize;
+
+        self.nodes.clear();
+        self.open.clear();
+        self.closed.clear();
+        self.nodes.push(Node {
+            pose: start,
+            cost: 0.0,
+            parent: None,
+        });
+        self.open.push(Open {
+            priority: heuristic(&start),
+            node: 0,
+        });
+        let mut expansions = 0;
+        while let Some(Open { node, .. }) = self.open.pop() {
+            let Node { pose, cost, .. } = self.nodes[node];
+            if !self.closed.insert(key(&pose)) {
+                continue;
+            }
+            if pose.distance(&goal) <= params.goal_tolerance
+                && angle_difference(pose.theta, goal.theta) <= params.heading_tolerance
+            {
+                let mut current = Some(node);
+                while let Some(index) = current {
+                    path.push(self.nodes[index].pose);
+                    current = self.nodes[index].parent;
+                }
+                path.reverse();
+                return true;
+            }
+            expansions += 1;
+            if expansions > params.max_expansions {
+                break;
+            }
+            for (k, penalty) in [
+                (-curvature, TURN_PENALTY),
+                (0.0, 1.0),
+                (curvature, TURN_PENALTY),
+            ] {
+                let next = arc(&pose, k, step);
+                if self.closed.contains(&key(&next)) {
+                    continue;
+                }
+                let collides = (1..=samples).any(|i| {
+                    let sample = arc(&pose, k, step * i as f64 / samples as f64);
+                    let (x, y) = map.cell_at(sample.x, sample.y);
+                    map.is_blocked(x, y)
+                });
+                if collides {
+                    continue;
+                }
+                let next_cost = cost + step * penalty;
+                self.nodes.push(Node {
+                    pose: next,
+                    cost: next_cost,
+                    parent: Some(node),
+                });
+                self.open.push(Open {
+                    priority: next_cost + heuristic(&next),
+                    node: self.nodes.len() - 1,
+                });
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::CostMapParams;
+    use cu_spatial_payloads::OccupancyGrid;
+
+    #[test]
+    fn test_hybrid_astar() {
+        // 10x10m of 0.25m cells with a wall at x = 5m from y = 0 to 6m.
+        let mut grid = OccupancyGrid::new(40, 40, 0.25, 0.0, 0.0);
+        grid.data.fill(0);
+        for y in 0..24 {
+            grid.set(20, y, 100);
+        }
+        let mut map = CostMap::default();
+        map.update(
+            &grid,
+            &CostMapParams {
+                occupied_threshold: 50,
+                allow_unknown: false,
+                inflation_radius: 0.3,
+            },
+        )
+        .unwrap();
+        let params = HybridParams {
+            turning_radius: 1.0,
+            heading_bins: 72,
+            goal_tolerance: 0.3,
+            heading_tolerance: 0.3,
+            max_expansions: 100_000,
+        };
+        let (start, goal) = (Pose2D::new(2.0, 2.0, 0.0), Pose2D::new(8.0, 2.0, -PI / 2.0));
+        let mut hybrid = HybridAStar::default();
+        let mut path = Vec::new();
+        assert!(hybrid.plan(&map, start, goal, &params, &mut path));
+        assert_eq!(path[0], start);
+        let end = path.last().unwrap();
+        assert!(end.distance(&goal) <= 0.3);
+        assert!(angle_difference(end.theta, goal.theta) <= 0.3);
+        // Over the wall, turning no tighter than the robot can.
+        assert!(path.iter().any(|pose| pose.y > 6.0));
+        for pair in path.windows(2) {
+            let turn = angle_difference(pair[0].theta, pair[1].theta);
+            // The chord between the poses is a bit shorter than the arc.
+            assert!(turn <= pair[0].distance(&pair[1]) * 1.01 / params.turning_radius);
+            let (x, y) = map.cell_at(pair[1].x, pair[1].y);
+            assert!(!map.is_blocked(x, y));
+        }
+
+        // A goal in the wall and a search cut short.
+        assert!(!hybrid.plan(&map, start, Pose2D::new(5.1, 2.0, 0.0), &params, &mut path));
+        let params = HybridParams {
+            max_expansions: 10,
+            ..params
+        };
+        assert!(!hybrid.plan(&map, start, goal, &params, &mut path));
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn test_angle_difference() {
+        assert!((angle_difference(0.1, TAU - 0.1) - 0.2).abs() < 1e-12);
+        assert!((angle_difference(-PI / 2.0, PI) - PI / 2.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,265 @@
+//! A path planner over occupancy grids: A* on the cells for the robots turning in place, or hybrid
+//! A* for the car-like ones.
+//!
+//! The planner takes the occupancy grid, the goal and the odometry of the robot and outputs
+//! a path only when it plans: for a new goal, when the map changes and at most every `rate_hz`
+//! otherwise. Connect its inputs and the path to the controller with latched connections so they
+//! see the last value in between:
+//!
+//! ```ron
+//! cnx: [
+//!     (src: "mapper", dst: "planner", msg: "cu_spatial_payloads::OccupancyGrid", policy: Some(Latched)),
+//!     (src: "mission", dst: "planner", msg: "cu_spatial_payloads::Pose2D", policy: Some(Latched)),
+//!     (src: "odometry", dst: "planner", msg: "cu_spatial_payloads::Odometry2D"),
+//!     (src: "planner", dst: "controller", msg: "cu_spatial_payloads::Path2D", policy: Some(Latched)),
+//! ]
+//! ```
+
+mod grid;
+mod hybrid;
+
+pub use grid::*;
+pub use hybrid::*;
+
+use cu29::prelude::*;
+use cu_spatial_payloads::{OccupancyGrid, Odometry2D, Path2D, Pose2D};
+use std::time::Duration;
+
+#[derive(CuConfigStruct)]
+struct PlannerConfig {
+    #[config(default = "astar")]
+    algorithm: String,
+    #[config(default = 1.0, range = 0.0..)]
+    rate_hz: f64,
+    #[config(default = true)]
+    replan_on_map_update: bool,
+    #[config(default = 0.2, range = 0.0..)]
+    robot_radius_m: f64,
+    #[config(default = 50, range = 0..=100)]
+    occupied_threshold: u32,
+    #[config(default)]
+    allow_unknown: bool,
+    #[config(default = 0.5)]
+    turning_radius_m: f64,
+    #[config(default = 72, range = 1..=720)]
+    heading_bins: u32,
+    #[config(default = 0.2)]
+    goal_tolerance_m: f64,
+    #[config(default = 0.2)]
+    heading_tolerance_rad: f64,
+    #[config(default = 200000, range = 1..)]
+    max_expansions: u32,
+}
+
+/// The search of the planner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// Shortest path on the 8-connected cells, the headings following the path.
+    AStar,
+    /// Forward arcs no tighter than the turning radius of the robot.
+    HybridAStar(HybridParams),
+}
+
+/// This is a task planning a path from the current pose of the robot to the goal on the last
+/// occupancy grid. The planning itself runs in the copperlist of the inputs that triggered it,
+/// the rate only bounds how often it happens.
+pub struct Planner {
+    algorithm: Algorithm,
+    cost_map_params: CostMapParams,
+    period: CuDuration,
+    replan_on_map_update: bool,
+    map: CostMap,
+    astar: GridAStar,
+    hybrid: HybridAStar,
+    cells: Vec<(usize, usize)>,
+    /// The revision of the grid of the cost map and the goal and time of the last plan.
+    map_revision: Option<u64>,
+    goal: Option<Pose2D>,
+    last_plan: Option<CuTime>,
+}
+
+impl Planner {
+    /// Plans from `start` to `goal` on the last map in `path`, returns false if there is no path.
+    pub fn plan(&mut self, start: Pose2D, goal: Pose2D, path: &mut Vec<Pose2D>) -> bool {
+        match self.algorithm {
+            Algorithm::AStar => {
+                let (sx, sy) = self.map.cell_at(start.x, start.y);
+                let (gx, gy) = self.map.cell_at(goal.x, goal.y);
+                let in_map = |x: i64, y: i64| {
+                    x >= 0
+                        && y >= 0
+                        && (x as usize) < self.map.width
+                        && (y as usize) < self.map.height
+                };
+                if !in_map(sx, sy) || !in_map(gx, gy) {
+                    path.clear();
+                    return false;
+                }
+                let (start_cell, goal_cell) =
+                    ((sx as usize, sy as usize), (gx as usize, gy as usize));
+                if !self
+                    .astar
+                    .plan(&self.map, start_cell, goal_cell, &mut self.cells)
+                {
+                    path.clear();
+                    return false;
+                }
+                // The exact start and goal, through the centers of the cells in between.
+                path.clear();
+                path.push(start);
+                let count = self.cells.len();
+                for &(x, y) in self.cells.iter().take(count - 1).skip(1) {
+                    let (x, y) = self.map.cell_center(x, y);
+                    path.push(Pose2D::new(x, y, 0.0));
+                }
+                path.push(goal);
+                for i in 1..path.len().saturating_sub(1) {
+                    let (from, to) = (path[i], path[i + 1]);
+                    path[i].theta = (to.y - from.y).atan2(to.x - from.x);
+                }
+                if let Some(last) = path.last_mut() {
+                    last.theta = goal.theta;
+                }
+                true
+            }
+            Algorithm::HybridAStar(params) => {
+                self.hybrid.plan(&self.map, start, goal, &params, path)
+            }
+        }
+    }
+}
+
+impl Freezable for Planner {}
+
+impl<'cl> CuTask<'cl> for Planner {
+    type Input = input_msg!('cl, OccupancyGrid, Pose2D, Odometry2D);
+    type Output = output_msg!('cl, Path2D);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = PlannerConfig::from_config(config)?;
+        let algorithm = match config.algorithm.as_str() {
+            "astar" => Algorithm::AStar,
+            "hybrid_astar" => {
+                if config.turning_radius_m <= 0.0 {
+                    return Err(CuError::from(
+                        "Planner: the turning radius of hybrid A* should be positive.",
+                    ));
+                }
+                Algorithm::HybridAStar(HybridParams {
+                    turning_radius: config.turning_radius_m,
+                    heading_bins: config.heading_bins as usize,
+                    goal_tolerance: config.goal_tolerance_m,
+                    heading_tolerance: config.heading_tolerance_rad,
+                    max_expansions: config.max_expansions as usize,
+                })
+            }
+            algorithm => {
+                return Err(CuError::from(format!(
+                    "Planner: unknown algorithm \"{algorithm}\", expected astar or hybrid_astar."
+                )))
+            }
+        };
+        // A rate of 0 only plans for new goals and maps.
+        let period = if config.rate_hz > 0.0 {
+            Duration::from_secs_f64(1.0 / config.rate_hz).into()
+        } else {
+            CuDuration(u64::MAX)
+        };
+        Ok(Self {
+            algorithm,
+            cost_map_params: CostMapParams {
+                occupied_threshold: config.occupied_threshold as i8,
+                allow_unknown: config.allow_unknown,
+                inflation_radius: config.robot_radius_m,
+            },
+            period,
+            replan_on_map_update: config.replan_on_map_update,
+            map: CostMap::default(),
+            astar: GridAStar::default(),
+            hybrid: HybridAStar::default(),
+            cells: Vec::new(),
+            map_revision: None,
+            goal: None,
+            last_plan: None,
+        })
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        let (grid, goal, odometry) = input;
+        let mut map_updated = false;
+        if let Some(grid) = grid.payload() {
+            if self.map_revision != Some(grid.revision) {
+                self.map.update(grid, &self.cost_map_params)?;
+                map_updated = self.map_revision.is_some();
+                self.map_revision = Some(grid.revision);
+            }
+        }
+        let new_goal = match goal.payload() {
+            Some(goal) if self.goal != Some(*goal) => {
+                self.goal = Some(*goal);
+                true
+            }
+            _ => false,
+        };
+        let (Some(goal), Some(current), Some(_)) =
+            (self.goal, odometry.payload(), self.map_revision)
+        else {
+            output.clear_payload();
+            return Ok(());
+        };
+
+        let now = clock.now();
+        let due = self.last_plan.is_none_or(|last| now - last >= self.period);
+        if !(new_goal || due || (map_updated && self.replan_on_map_update)) {
+            output.clear_payload();
+            return Ok(());
+        }
+        self.last_plan = Some(now);
+
+        let mut path = output.payload_mut().take().unwrap_or_default();
+        if !self.plan(current.pose, goal, &mut path.poses) {
+            debug!("Planner: no path to the goal.");
+        }
+        output.set_payload(path);
+        output.metadata.tov = now.into();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let mut grid = OccupancyGrid::new(10, 10, 1.0, -5.0, -5.0);
+        grid.data.fill(0);
+        for y in 0..8 {
+            grid.set(5, y, 100);
+        }
+        let mut planner = Planner::new(None).unwrap();
+        planner.map.update(&grid, &planner.cost_map_params).unwrap();
+
+        let (start, goal) = (Pose2D::new(-2.8, -2.9, 1.0), Pose2D::new(3.3, -2.6, 0.5));
+        let mut path = Vec::new();
+        assert!(planner.plan(start, goal, &mut path));
+        assert_eq!(path.first().map(|p| (p.x, p.y)), Some((start.x, start.y)));
+        assert_eq!(path.last(), Some(&goal));
+        // Heading toward the next pose, around the wall.
+        assert!(path.iter().any(|p| p.y > 3.0));
+        let (a, b) = (path[1], path[2]);
+        assert!((a.theta - (b.y - a.y).atan2(b.x - a.x)).abs() < 1e-12);
+
+        // Out of the map.
+        assert!(!planner.plan(start, Pose2D::new(6.0, 0.0, 0.0), &mut path));
+        assert!(path.is_empty());
+    }
+}
@@ -0,0 +1,10 @@
+fn main() {
+    let rhai_enabled = std::env::var("CARGO_FEATURE_RHAI").is_ok();
+    if !rhai_enabled {
+        println!("cargo:warning=rhai feature is not enabled. Skipping cu_rhai build.");
+    }
+    println!(
+        "cargo:rustc-env=LOG_INDEX_DIR={}",
+        std::env::var("OUT_DIR").unwrap()
+    );
+}
@@ -0,0 +1,176 @@
+use cu29::prelude::*;
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+/// The names of the payloads and of the state kept from a call to the next in the scripts.
+const INPUT: &str = "input";
+const OUTPUT: &str = "output";
+const STATE: &str = "state";
+
+/// A task computing its output from its input with a Rhai script given in its config, to try some
+/// logic on the robot before writing it in Rust.
+/// The script reads the input in `input`, writes the fields of `output`, starting from its default
+/// value, and can keep what it wants in the map `state` from a call to the next.
+/// See the README of this crate.
+pub struct ScriptTask<I, O> {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    _payloads: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O> Freezable for ScriptTask<I, O> {}
+
+impl<'cl, I, O> CuTask<'cl> for ScriptTask<I, O>
+where
+    I: CuMsgPayload + Serialize + 'cl,
+    O: CuMsgPayload + Serialize + DeserializeOwned + 'cl,
+{
+    type Input = input_msg!('cl, I);
+    type Output = output_msg!('cl, O);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let script = config
+            .and_then(|config| config.get::<String>("script"))
+            .ok_or_else(|| CuError::from("ScriptTask needs the path of its \"script\"."))?;
+        let engine = Engine::new();
+        let ast = engine.compile_file(script.clone().into()).map_err(|e| {
+            CuError::new_with_cause(&format!("ScriptTask: could not load {script}"), e)
+        })?;
+        let mut scope = Scope::new();
+        scope.push(STATE, Map::new());
+        Ok(ScriptTask {
+            engine,
+            ast,
+            scope,
+            _payloads: PhantomData,
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        output.metadata.tov = input.metadata.tov;
+        let Some(payload) = input.payload() else {
+            output.clear_payload();
+            return Ok(());
+        };
+        let payload = to_dynamic(payload)
+            .map_err(|e| CuError::new_with_cause("ScriptTask: could not read the input", e))?;
+        let default = to_dynamic(O::default())
+            .map_err(|e| CuError::new_with_cause("ScriptTask: could not build the output", e))?;
+
+        // Only the state stays in the scope from a call to the next.
+        let kept = self.scope.len();
+        self.scope.push_dynamic(INPUT, payload);
+        self.scope.push_dynamic(OUTPUT, default);
+        let result = self
+            .engine
+            .run_ast_with_scope(&mut self.scope, &self.ast)
+            .map_err(|e| CuError::new_with_cause("ScriptTask: the script failed", e))
+            .and_then(|()| {
+                let value: Dynamic = self.scope.get_value(OUTPUT).unwrap_or_default();
+                from_dynamic::<O>(&value).map_err(|e| {
+                    CuError::new_with_cause("ScriptTask: the output of the script is invalid", e)
+                })
+            });
+        self.scope.rewind(kept);
+        output.set_payload(result?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bincode::{Decode, Encode};
+    use serde::Deserialize;
+    use std::io::Write;
+
+    #[derive(Debug, Clone, Default, Encode, Decode, Serialize, Deserialize)]
+    struct Range {
+        distance_m: f64,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Encode, Decode, Serialize, Deserialize)]
+    struct Command {
+        speed: f64,
+        stopped: bool,
+        count: i64,
+    }
+
+    fn task(script: &str) -> CuResult<ScriptTask<Range, Command>> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(script.as_bytes()).unwrap();
+        let mut config = ComponentConfig::new();
+        config.set("script", file.path().to_str().unwrap().to_string());
+        ScriptTask::new(Some(&config))
+    }
+
+    #[test]
+    fn test_script() {
+        let mut task = task(
+            r#"
+            let stop = input.distance_m < 0.5;
+            output.stopped = stop;
+            output.speed = if stop { 0.0 } else { input.distance_m * 0.2 };
+            state.count = (state.count ?? 0) + 1;
+            output.count = state.count;
+            "#,
+        )
+        .unwrap();
+        let clock = RobotClock::new();
+        let mut output = CuMsg::<Command>::new(None);
+        task.process(
+            &clock,
+            &CuMsg::new(Some(Range { distance_m: 2.0 })),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            output.payload(),
+            Some(&Command {
+                speed: 0.4,
+                stopped: false,
+                count: 1
+            })
+        );
+        task.process(
+            &clock,
+            &CuMsg::new(Some(Range { distance_m: 0.1 })),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            output.payload(),
+            Some(&Command {
+                speed: 0.0,
+                stopped: true,
+                count: 2
+            })
+        );
+
+        task.process(&clock, &CuMsg::<Range>::new(None), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), None);
+
+        assert!(task("let x = ;").is_err());
+        let mut failing = task("output.speed = input.nope.x;").unwrap();
+        assert!(failing
+            .process(
+                &clock,
+                &CuMsg::new(Some(Range { distance_m: 1.0 })),
+                &mut output
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,5 @@
+#[cfg(feature = "rhai")]
+mod cu_rhai_impl;
+
+#[cfg(feature = "rhai")]
+pub use cu_rhai_impl::*;
@@ -0,0 +1,222 @@
+//! A stereo matching task computing the disparity or the depth of the left image of a rectified
+//! stereo pair with semi-global block matching.
+//!
+//! The pairs come from a `cu_aligner` task synchronizing the two cameras, the task
+//! matches the most recent left image with the most recent right one of the window.
+
+mod sgbm;
+
+pub use sgbm::*;
+
+use cu29::payload::CuArray;
+use cu29::prelude::*;
+use cu_sensor_payloads::{CuImage, CuImageBufferFormat};
+use std::sync::{Arc, Mutex, Weak};
+
+/// The output of an aligner of two image streams keeping up to N images of each in its window.
+pub type StereoPair<const N: usize> = (CuArray<CuImage<Vec<u8>>, N>, CuArray<CuImage<Vec<u8>>, N>);
+
+/// The pixel format of the outputs: 16 bits little endian, like the V4L2 one.
+pub const Y16: [u8; 4] = *b"Y16 ";
+
+#[derive(CuConfigStruct)]
+struct StereoConfig {
+    #[config(range = 1..)]
+    width: u32,
+    #[config(range = 1..)]
+    height: u32,
+    #[config(default)]
+    min_disparity: i32,
+    #[config(default = 64, range = 1..=256)]
+    num_disparities: u32,
+    #[config(default = 5, range = 1..=31)]
+    block_size: u32,
+    #[config(default = 8, range = 0..=1000)]
+    p1: u32,
+    #[config(default = 32, range = 0..=8000)]
+    p2: u32,
+    #[config(default = 10, range = 0..=100)]
+    uniqueness_ratio: u32,
+    #[config(default = "disparity")]
+    output: String,
+    focal_px: Option<f64>,
+    baseline_m: Option<f64>,
+}
+
+/// What the task outputs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoOutput {
+    /// The disparities in 1/16 pixels.
+    Disparity,
+    /// The depths in millimeters from the focal length in pixels and the baseline in meters.
+    Depth { focal_px: f64, baseline_m: f64 },
+}
+
+/// The depth in millimeters of a disparity in 1/16 pixels, 0 for the invalid ones.
+pub fn disparity_to_depth_mm(disparity: u16, focal_px: f64, baseline_m: f64) -> u16 {
+    if disparity == INVALID_DISPARITY {
+        return 0;
+    }
+    let pixels = disparity as f64 / DISPARITY_SCALE as f64;
+    (focal_px * baseline_m * 1000.0 / pixels)
+        .round()
+        .min(u16::MAX as f64) as u16
+}
+
+/// The luma of a GRAY or NV12 image.
+fn gray_image<'a>(format: &CuImageBufferFormat, data: &'a [u8]) -> CuResult<GrayImage<'a>> {
+    match &format.pixel_format {
+        b"GRAY" | b"GREY" | b"NV12" => Ok(GrayImage {
+            data,
+            width: format.width as usize,
+            height: format.height as usize,
+            stride: format.stride as usize,
+        }),
+        _ => Err(CuError::from(format!(
+            "Stereo: the pixel format {} is not supported, expected GRAY or NV12.",
+            String::from_utf8_lossy(&format.pixel_format)
+        ))),
+    }
+}
+
+/// This is a task matching rectified stereo pairs with SGBM, outputting the disparity or the depth
+/// of the left image as a Y16 CuImage. The images are only matched once, the aligner giving the
+/// same ones until new images come.
+pub struct SgbmStereo<const N: usize> {
+    sgbm: Sgbm,
+    output: StereoOutput,
+    pool: Arc<CuHostMemoryPool<Vec<u8>>>,
+    disparity: Vec<u16>,
+    width: usize,
+    height: usize,
+    /// The left image of the last match, the weak reference keeps its address unique.
+    last_left: Weak<Mutex<CuHandleInner<Vec<u8>>>>,
+}
+
+impl<const N: usize> Freezable for SgbmStereo<N> {}
+
+impl<'cl, const N: usize> CuTask<'cl> for SgbmStereo<N> {
+    type Input = input_msg!('cl, StereoPair<N>);
+    type Output = output_msg!('cl, CuImage<Vec<u8>>);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = StereoConfig::from_config(config)?;
+        let output = match (config.output.as_str(), config.focal_px, config.baseline_m) {
+            ("disparity", _, _) => StereoOutput::Disparity,
+            ("depth", Some(focal_px), Some(baseline_m)) => StereoOutput::Depth {
+                focal_px,
+                baseline_m,
+            },
+            ("depth", _, _) => {
+                return Err(CuError::from(
+                    "Stereo: the depth output needs focal_px and baseline_m.",
+                ))
+            }
+            (output, _, _) => {
+                return Err(CuError::from(format!(
+                    "Stereo: unknown output \"{output}\", expected disparity or depth."
+                )))
+            }
+        };
+        let (width, height) = (config.width as usize, config.height as usize);
+        let sgbm = Sgbm::new(
+            width,
+            height,
+            SgbmParams {
+                min_disparity: config.min_disparity,
+                num_disparities: config.num_disparities as usize,
+                block_size: config.block_size as usize,
+                p1: config.p1 as u16,
+                p2: config.p2 as u16,
+                uniqueness_ratio: config.uniqueness_ratio,
+            },
+        )?;
+        Ok(Self {
+            sgbm,
+            output,
+            pool: CuHostMemoryPool::new("stereo", 4, || vec![0u8; width * height * 2])?,
+            disparity: vec![0; width * height],
+            width,
+            height,
+            last_left: Weak::new(),
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        let pair = input.payload().and_then(|(lefts, rights)| {
+            Some((lefts.as_slice().last()?, rights.as_slice().last()?))
+        });
+        let Some((left, right)) = pair else {
+            output.clear_payload();
+            return Ok(());
+        };
+        let left_handle: &Arc<Mutex<CuHandleInner<Vec<u8>>>> = &left.buffer_handle;
+        if Weak::as_ptr(&self.last_left) == Arc::as_ptr(left_handle) {
+            output.clear_payload();
+            return Ok(());
+        }
+
+        left.buffer_handle.with_inner(|left_data| {
+            right.buffer_handle.with_inner(|right_data| {
+                self.sgbm.compute(
+                    &gray_image(&left.format, &left_data[..])?,
+                    &gray_image(&right.format, &right_data[..])?,
+                    &mut self.disparity,
+                )
+            })
+        })?;
+        self.last_left = Arc::downgrade(left_handle);
+
+        let handle = self
+            .pool
+            .acquire()
+            .ok_or(CuError::from("Failed to acquire buffer from pool"))?;
+        handle.with_inner_mut(|dst| {
+            for (pixel, &disparity) in dst.chunks_exact_mut(2).zip(&self.disparity) {
+                let value = match self.output {
+                    StereoOutput::Disparity => disparity,
+                    StereoOutput::Depth {
+                        focal_px,
+                        baseline_m,
+                    } => disparity_to_depth_mm(disparity, focal_px, baseline_m),
+                };
+                pixel.copy_from_slice(&value.to_le_bytes());
+            }
+        });
+        let mut image = CuImage::new(
+            CuImageBufferFormat {
+                width: self.width as u32,
+                height: self.height as u32,
+                stride: self.width as u32 * 2,
+                pixel_format: Y16,
+            },
+            handle,
+        );
+        image.seq = left.seq;
+        output.metadata.tov = input.metadata.tov;
+        output.set_payload(image);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth() {
+        // 700px of focal length and 12cm of baseline: 8.4px at 10m.
+        assert_eq!(disparity_to_depth_mm(134, 700.0, 0.12), 10030);
+        assert_eq!(disparity_to_depth_mm(INVALID_DISPARITY, 700.0, 0.12), 0);
+        // Too far for millimeters on 16 bits.
+        assert_eq!(disparity_to_depth_mm(1, 700.0, 0.12), u16::MAX);
+    }
+}
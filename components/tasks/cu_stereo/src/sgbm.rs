@@ -0,0 +1,338 @@
+//! Semi-global block matching (Hirschmüller 2008, as in OpenCV StereoSGBM) on rectified 8 bits images:
+//! the matching cost is the mean absolute difference over a block, aggregated along 4 paths
+//! (left, right, up and down) with the P1 and P2 smoothness penalties.
+
+use cu29::prelude::*;
+
+/// A rectified 8 bits grayscale image.
+#[derive(Debug, Clone, Copy)]
+pub struct GrayImage<'a> {
+    pub data: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+}
+
+impl GrayImage<'_> {
+    #[inline]
+    fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.stride + x]
+    }
+}
+
+/// The parameters of the matching, named like the ones of OpenCV.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SgbmParams {
+    pub min_disparity: i32,
+    pub num_disparities: usize,
+    /// Odd size of the block of the matching cost.
+    pub block_size: usize,
+    /// Penalty of a disparity change of 1 pixel between neighbors, for a cost per pixel in 0..=255.
+    pub p1: u16,
+    /// Penalty of the larger disparity changes, should be greater than p1.
+    pub p2: u16,
+    /// Margin in percent by which the best cost must win over the other disparities.
+    pub uniqueness_ratio: u32,
+}
+
+/// The number of subpixel steps of a disparity, the disparities are fixed point like in OpenCV.
+pub const DISPARITY_SCALE: u16 = 16;
+
+/// The value of the pixels without a valid disparity.
+pub const INVALID_DISPARITY: u16 = 0;
+
+/// The matcher of a fixed image size, all its buffers are allocated at construction.
+pub struct Sgbm {
+    params: SgbmParams,
+    width: usize,
+    height: usize,
+    /// Matching cost per pixel and disparity, disparity being the fastest axis.
+    cost: Vec<u16>,
+    /// Sum of the costs aggregated along the paths.
+    aggregated: Vec<u16>,
+    /// Absolute differences and their horizontal box sums for one disparity.
+    diff: Vec<u16>,
+    box_sum: Vec<u16>,
+    /// Aggregated costs of the previous and current pixels (or rows) along a path.
+    previous: Vec<u16>,
+    current: Vec<u16>,
+}
+
+impl Sgbm {
+    pub fn new(width: usize, height: usize, params: SgbmParams) -> CuResult<Self> {
+        if params.block_size.is_multiple_of(2) || params.block_size > 255 {
+            return Err(CuError::from(format!(
+                "Stereo: the block size should be odd and at most 255, got {}.",
+                params.block_size
+            )));
+        }
+        if params.num_disparities == 0 || params.p2 < params.p1 {
+            return Err(CuError::from(
+                "Stereo: num_disparities should be positive and p2 at least p1.",
+            ));
+        }
+        let volume = width * height * params.num_disparities;
+        Ok(Self {
+            params,
+            width,
+            height,
+            cost: vec![0; volume],
+            aggregated: vec![0; volume],
+            diff: vec![0; width * height],
+            box_sum: vec![0; width * height],
+            previous: vec![0; width * params.num_disparities],
+            current: vec![0; width * params.num_disparities],
+        })
+    }
+
+    /// Computes the disparities of the left image in `disparity`, in 1/[DISPARITY_SCALE] pixels,
+    /// [INVALID_DISPARITY] where the match is ambiguous or out of the right image.
+    pub fn compute(
+        &mut self,
+        left: &GrayImage,
+        right: &GrayImage,
+        disparity: &mut [u16],
+    ) -> CuResult<()> {
+        for image in [left, right] {
+            if (image.width, image.height) != (self.width, self.height)
+                || image.stride < image.width
+                || image.data.len() < image.stride * image.height
+            {
+                return Err(CuError::from(format!(
+                    "Stereo: expected {}x{} images, got {}x{} with a stride of {} in {} bytes.",
+                    self.width,
+                    self.height,
+                    image.width,
+                    image.height,
+                    image.stride,
+                    image.data.len()
+                )));
+            }
+        }
+        if disparity.len() < self.width * self.height {
+            return Err(CuError::from("Stereo: the disparity buffer is too small."));
+        }
+        self.matching_cost(left, right);
+        self.aggregate();
+        self.select_disparities(disparity);
+        Ok(())
+    }
+
+    fn matching_cost(&mut self, left: &GrayImage, right: &GrayImage) {
+        let (width, height, disparities) = (self.width, self.height, self.params.num_disparities);
+        let radius = self.params.block_size / 2;
+        for d in 0..disparities {
+            let shift = self.params.min_disparity + d as i32;
+            for y in 0..height {
+                for x in 0..width {
+                    let xr = x as i32 - shift;
+                    self.diff[y * width + x] = if xr >= 0 && (xr as usize) < width {
+                        left.pixel(x, y).abs_diff(right.pixel(xr as usize, y)) as u16
+                    } else {
+                        u8::MAX as u16
+                    };
+                }
+            }
+            // Box filter clamped to the image, normalized to a mean difference per pixel.
+            for y in 0..height {
+                let row = &self.diff[y * width..(y + 1) * width];
+                for x in 0..width {
+                    let (x0, x1) = (x.saturating_sub(radius), (x + radius).min(width - 1));
+                    self.box_sum[y * width + x] = row[x0..=x1].iter().sum();
+                }
+            }
+            for y in 0..height {
+                let (y0, y1) = (y.saturating_sub(radius), (y + radius).min(height - 1));
+                for x in 0..width {
+                    let (x0, x1) = (x.saturating_sub(radius), (x + radius).min(width - 1));
+                    let sum: u32 = (y0..=y1)
+                        .map(|yy| self.box_sum[yy * width + x] as u32)
+                        .sum();
+                    let count = ((y1 - y0 + 1) * (x1 - x0 + 1)) as u32;
+                    self.cost[(y * width + x) * disparities + d] = (sum / count) as u16;
+                }
+            }
+        }
+    }
+
+    fn aggregate(&mut self) {
+        let (width, height, disparities) = (self.width, self.height, self.params.num_disparities);
+        let (p1, p2) = (self.params.p1, self.params.p2);
+        self.aggregated.fill(0);
+
+        // Along the rows, left to right then right to left.
+        for reverse in [false, true] {
+            for y in 0..height {
+                for i in 0..width {
+                    let x = if reverse { width - 1 - i } else { i };
+                    let p = (y * width + x) * disparities;
+                    let cost = &self.cost[p..p + disparities];
+                    let current = &mut self.current[..disparities];
+                    if i == 0 {
+                        current.copy_from_slice(cost);
+                    } else {
+                        path_step(cost, &self.previous[..disparities], current, p1, p2);
+                    }
+                    accumulate(&mut self.aggregated[p..p + disparities], current);
+                    std::mem::swap(&mut self.previous, &mut self.current);
+                }
+            }
+        }
+
+        // Along the columns, top to bottom then bottom to top, a row at a time.
+        for reverse in [false, true] {
+            for i in 0..height {
+                let y = if reverse { height - 1 - i } else { i };
+                for x in 0..width {
+                    let p = (y * width + x) * disparities;
+                    let q = x * disparities;
+                    let cost = &self.cost[p..p + disparities];
+                    let current = &mut self.current[q..q + disparities];
+                    if i == 0 {
+                        current.copy_from_slice(cost);
+                    } else {
+                        path_step(cost, &self.previous[q..q + disparities], current, p1, p2);
+                    }
+                    accumulate(&mut self.aggregated[p..p + disparities], current);
+                }
+                std::mem::swap(&mut self.previous, &mut self.current);
+            }
+        }
+    }
+
+    fn select_disparities(&self, disparity: &mut [u16]) {
+        let disparities = self.params.num_disparities;
+        let uniqueness = 100 - self.params.uniqueness_ratio.min(100);
+        for (p, out) in disparity
+            .iter_mut()
+            .take(self.width * self.height)
+            .enumerate()
+        {
+            let costs = &self.aggregated[p * disparities..(p + 1) * disparities];
+            let (best, &min_cost) = costs
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, cost)| **cost)
+                .expect("at least one disparity");
+            let ambiguous = costs.iter().enumerate().any(|(d, &cost)| {
+                d.abs_diff(best) > 1 && cost as u32 * uniqueness < min_cost as u32 * 100
+            });
+            let shift = self.params.min_disparity + best as i32;
+            if ambiguous || shift <= 0 {
+                *out = INVALID_DISPARITY;
+                continue;
+            }
+            // Parabola through the costs around the best disparity for the subpixel part.
+            let mut value = shift as f32 * DISPARITY_SCALE as f32;
+            if best > 0 && best + 1 < disparities {
+                let (before, after) = (costs[best - 1] as f32, costs[best + 1] as f32);
+                let denominator = before + after - 2.0 * min_cost as f32;
+                if denominator > 0.0 {
+                    value += (before - after) / (2.0 * denominator) * DISPARITY_SCALE as f32;
+                }
+            }
+            *out = (value.round() as u16).max(1);
+        }
+    }
+}
+
+/// One step of a path: the cost of the pixel plus the best of the previous pixel on the path,
+/// penalized when the disparity changes.
+#[inline]
+fn path_step(cost: &[u16], previous: &[u16], current: &mut [u16], p1: u16, p2: u16) {
+    let min_previous = *previous.iter().min().expect("at least one disparity");
+    let last = previous.len() - 1;
+    for (d, out) in current.iter_mut().enumerate() {
+        let mut best = previous[d].min(min_previous + p2);
+        if d > 0 {
+            best = best.min(previous[d - 1] + p1);
+        }
+        if d < last {
+            best = best.min(previous[d + 1] + p1);
+        }
+        *out = cost[d] + best - min_previous;
+    }
+}
+
+#[inline]
+fn accumulate(aggregated: &mut [u16], path: &[u16]) {
+    for (sum, cost) in aggregated.iter_mut().zip(path) {
+        *sum = sum.saturating_add(*cost);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A random texture, the right image seeing it `shift` pixels to the left.
+    fn stereo_pair(width: usize, height: usize, shift: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut state = 0x2545_f491u32;
+        let texture: Vec<u8> = (0..(width + shift) * height)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect();
+        let mut left = Vec::with_capacity(width * height);
+        let mut right = Vec::with_capacity(width * height);
+        for row in texture.chunks(width + shift) {
+            left.extend_from_slice(&row[..width]);
+            right.extend_from_slice(&row[shift..]);
+        }
+        (left, right)
+    }
+
+    #[test]
+    fn test_sgbm() {
+        let (width, height) = (48, 16);
+        let (left, right) = stereo_pair(width, height, 5);
+        let gray = |data| GrayImage {
+            data,
+            width,
+            height,
+            stride: width,
+        };
+        let params = SgbmParams {
+            min_disparity: 0,
+            num_disparities: 16,
+            block_size: 5,
+            p1: 8,
+            p2: 32,
+            uniqueness_ratio: 10,
+        };
+        let mut sgbm = Sgbm::new(width, height, params).unwrap();
+        let mut disparity = vec![0u16; width * height];
+        sgbm.compute(&gray(&left), &gray(&right), &mut disparity)
+            .unwrap();
+
+        // Away from the left border where the right camera doesn't see the scene.
+        let valid: Vec<u16> = (0..height)
+            .flat_map(|y| disparity[y * width + 16..(y + 1) * width].iter().copied())
+            .collect();
+        let matching = valid
+            .iter()
+            .filter(|d| d.abs_diff(5 * DISPARITY_SCALE) <= DISPARITY_SCALE / 2)
+            .count();
+        assert!(
+            matching * 100 >= valid.len() * 95,
+            "{matching}/{}",
+            valid.len()
+        );
+
+        assert!(Sgbm::new(
+            width,
+            height,
+            SgbmParams {
+                block_size: 4,
+                ..params
+            }
+        )
+        .is_err());
+        assert!(sgbm
+            .compute(&gray(&left[1..]), &gray(&right), &mut disparity)
+            .is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::ClockOffsetEstimate;
+use cu29::prelude::*;
+use std::collections::VecDeque;
+
+/// One ping/pong exchange, NTP style.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    /// ns, local time of the middle of the exchange.
+    local: i128,
+    /// ns, remote time - local time.
+    offset: i128,
+    /// Round trip minus the time spent on the remote side.
+    delay: u64,
+}
+
+/// Estimates the offset and the drift of a remote clock from the last `window` exchanges.
+pub(crate) struct Estimator {
+    window: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl Estimator {
+    pub(crate) fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Adds the exchange: the ping left at `origin` and the pong came back at `destination` (local times),
+    /// the remote received the ping at `receive` and sent the pong at `transmit` (remote times).
+    /// Returns false if the timestamps are inconsistent.
+    pub(crate) fn add(
+        &mut self,
+        origin: CuTime,
+        receive: CuTime,
+        transmit: CuTime,
+        destination: CuTime,
+    ) -> bool {
+        let [t1, t2, t3, t4] = [origin, receive, transmit, destination].map(|t| t.0 as i128);
+        let delay = (t4 - t1) - (t3 - t2);
+        if t4 < t1 || t3 < t2 || delay < 0 {
+            return false;
+        }
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            local: (t1 + t4) / 2,
+            offset: ((t2 - t1) + (t3 - t4)) / 2,
+            delay: delay as u64,
+        });
+        true
+    }
+
+    /// The exchanges with the longest delays are the most asymmetric ones, only the fastest half is kept.
+    /// The offset is fitted linearly against the local time to get the drift.
+    pub(crate) fn estimate(&self) -> Option<ClockOffsetEstimate> {
+        let mut samples = self.samples.iter().copied().collect::<Vec<_>>();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by_key(|sample| sample.delay);
+        samples.truncate(samples.len().div_ceil(2));
+
+        let count = samples.len() as i128;
+        let reference = samples.iter().map(|s| s.local).sum::<i128>() / count;
+        let mean_offset = samples.iter().map(|s| s.offset).sum::<i128>() / count;
+        let (covariance, variance) = samples.iter().fold((0.0, 0.0), |(cov, var), s| {
+            let dx = (s.local - reference) as f64;
+            let dy = (s.offset - mean_offset) as f64;
+            (cov + dx * dy, var + dx * dx)
+        });
+        let drift = if variance > 0.0 {
+            covariance / variance
+        } else {
+            0.0
+        };
+        Some(ClockOffsetEstimate {
+            reference: CuDuration(reference as u64),
+            offset_ns: mean_offset as i64,
+            drift_ppm: drift * 1e6,
+            round_trip: CuDuration(samples[0].delay),
+            samples: self.samples.len() as u32,
+        })
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A remote clock 5s ahead, running 100ppm faster.
+    fn remote(local: u64) -> CuTime {
+        CuDuration(local + 5_000_000_000 + local / 10_000)
+    }
+
+    #[test]
+    fn test_offset_and_drift() {
+        let mut estimator = Estimator::new(16);
+        for i in 0..16u64 {
+            let t1 = 1_000_000_000 * (i + 1);
+            // 1ms each way, every other exchange has a slow way back.
+            let back = if i % 2 == 0 { 1_000_000 } else { 9_000_000 };
+            let t2 = remote(t1 + 1_000_000);
+            let t3 = remote(t1 + 1_100_000);
+            let t4 = CuDuration(t1 + 1_100_000 + back);
+            assert!(estimator.add(CuDuration(t1), t2, t3, t4));
+        }
+        let estimate = estimator.estimate().unwrap();
+        assert!((estimate.drift_ppm - 100.0).abs() < 1.0);
+        assert!((estimate.round_trip.0 as i64 - 2_000_000).abs() < 100);
+        assert_eq!(estimate.samples, 16);
+        let local = CuDuration(20_000_000_000);
+        let error = estimate.to_remote(local).0 as i64 - remote(local.0).0 as i64;
+        assert!(error.abs() < 10_000, "error {error}ns");
+        let back = estimate.to_local(estimate.to_remote(local));
+        assert!((back.0 as i64 - local.0 as i64).abs() <= 1);
+    }
+
+    #[test]
+    fn test_inconsistent() {
+        let mut estimator = Estimator::new(4);
+        assert!(estimator.estimate().is_none());
+        // The pong came back before the ping left.
+        assert!(!estimator.add(CuDuration(10), CuDuration(5), CuDuration(6), CuDuration(9)));
+        assert!(estimator.add(
+            CuDuration(10),
+            CuDuration(105),
+            CuDuration(106),
+            CuDuration(12)
+        ));
+        let estimate = estimator.estimate().unwrap();
+        assert_eq!(estimate.offset_ns, 94);
+        assert_eq!(estimate.drift_ppm, 0.0);
+    }
+}
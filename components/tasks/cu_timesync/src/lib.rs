@@ -0,0 +1,271 @@
+mod estimator;
+
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+use estimator::Estimator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub enum TimeSyncKind {
+    #[default]
+    Ping,
+    Pong,
+}
+
+/// What the time sync tasks exchange over the transport (Zenoh, DDS, ...).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct TimeSyncPacket {
+    pub kind: TimeSyncKind,
+    pub seq: u32,
+    /// When the ping left, client time.
+    pub origin: CuTime,
+    /// When the ping was received and the pong sent, server time. Only set in a pong.
+    pub receive: CuTime,
+    pub transmit: CuTime,
+}
+
+/// The relation between the local RobotClock and the RobotClock of a peer:
+/// remote = local + offset + drift * (local - reference).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct ClockOffsetEstimate {
+    /// Local time the offset is given at.
+    pub reference: CuTime,
+    /// ns, remote time - local time at the reference.
+    pub offset_ns: i64,
+    /// How much faster the remote clock runs, in µs per s.
+    pub drift_ppm: f64,
+    /// The fastest exchange, the offset is accurate to half of it at worst.
+    pub round_trip: CuDuration,
+    /// Number of exchanges the estimate comes from.
+    pub samples: u32,
+}
+
+impl ClockOffsetEstimate {
+    /// The remote time at the given local time.
+    pub fn to_remote(&self, local: CuTime) -> CuTime {
+        let elapsed = local.0 as i128 - self.reference.0 as i128;
+        let drift = (elapsed as f64 * self.drift_ppm * 1e-6).round() as i128;
+        CuDuration((local.0 as i128 + self.offset_ns as i128 + drift).max(0) as u64)
+    }
+
+    /// The local time at the given remote time, to align the messages and the logs of a peer.
+    pub fn to_local(&self, remote: CuTime) -> CuTime {
+        let remote_elapsed = remote.0 as i128 - self.offset_ns as i128 - self.reference.0 as i128;
+        let elapsed = (remote_elapsed as f64 / (1.0 + self.drift_ppm * 1e-6)).round() as i128;
+        CuDuration((self.reference.0 as i128 + elapsed).max(0) as u64)
+    }
+}
+
+static ESTIMATES: OnceLock<Mutex<HashMap<String, ClockOffsetEstimate>>> = OnceLock::new();
+
+fn estimates() -> &'static Mutex<HashMap<String, ClockOffsetEstimate>> {
+    ESTIMATES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The latest estimate of the clock of a peer, as named by the `peer` config of its TimeSyncClient.
+pub fn clock_offset(peer: &str) -> Option<ClockOffsetEstimate> {
+    estimates().lock().unwrap().get(peer).copied()
+}
+
+/// What the client task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct TimeSyncClientConfig {
+    peer: String,
+    #[config(default = 1000, range = 1..)]
+    period_ms: u32,
+    #[config(default = 16, range = 1..)]
+    window: usize,
+}
+
+/// This task pings a TimeSyncServer of another Copper application every `period_ms` and estimates the offset
+/// and the drift of its clock from the pongs, NTP style.
+/// Connect its output to a publisher and its input to a subscriber of the transport between the two
+/// applications. The estimate is available through [clock_offset] and logged at each update.
+pub struct TimeSyncClient {
+    peer: String,
+    period: CuDuration,
+    estimator: Estimator,
+    seq: u32,
+    last_ping: Option<CuTime>,
+}
+
+impl Freezable for TimeSyncClient {}
+
+impl<'cl> CuTask<'cl> for TimeSyncClient {
+    type Input = input_msg!('cl, TimeSyncPacket);
+    type Output = output_msg!('cl, TimeSyncPacket);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let TimeSyncClientConfig {
+            peer,
+            period_ms,
+            window,
+        } = TimeSyncClientConfig::from_config(config)?;
+        Ok(Self {
+            peer,
+            period: CuDuration::from(period_ms as u64 * 1_000_000),
+            estimator: Estimator::new(window),
+            seq: 0,
+            last_ping: None,
+        })
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        if let Some(pong) = input.payload() {
+            // A pong of a previous run or a ping from another client is not an answer.
+            if pong.kind == TimeSyncKind::Pong
+                && pong.seq <= self.seq
+                && self
+                    .estimator
+                    .add(pong.origin, pong.receive, pong.transmit, clock.now())
+            {
+                if let Some(estimate) = self.estimator.estimate() {
+                    debug!(
+                        "TimeSync: {} offset {}ns drift {}ppm round trip {}",
+                        self.peer.as_str(),
+                        estimate.offset_ns,
+                        estimate.drift_ppm,
+                        estimate.round_trip
+                    );
+                    output.metadata.set_status(format!(
+                        "{:+.3}ms {:+.1}ppm",
+                        estimate.offset_ns as f64 / 1e6,
+                        estimate.drift_ppm
+                    ));
+                    estimates()
+                        .lock()
+                        .unwrap()
+                        .insert(self.peer.clone(), estimate);
+                }
+            }
+        }
+
+        let now = clock.now();
+        if self
+            .last_ping
+            .is_some_and(|last_ping| now - last_ping < self.period)
+        {
+            output.clear_payload();
+            return Ok(());
+        }
+        self.last_ping = Some(now);
+        self.seq = self.seq.wrapping_add(1);
+        output.metadata.tov = now.into();
+        output.set_payload(TimeSyncPacket {
+            kind: TimeSyncKind::Ping,
+            seq: self.seq,
+            origin: now,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.estimator.reset();
+        self.last_ping = None;
+        Ok(())
+    }
+}
+
+/// This task answers the pings of the TimeSyncClients of other Copper applications with its own time.
+pub struct TimeSyncServer;
+
+impl Freezable for TimeSyncServer {}
+
+impl<'cl> CuTask<'cl> for TimeSyncServer {
+    type Input = input_msg!('cl, TimeSyncPacket);
+    type Output = output_msg!('cl, TimeSyncPacket);
+
+    fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self)
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        match input.payload() {
+            Some(ping) if ping.kind == TimeSyncKind::Ping => {
+                // The ping is answered in the cycle it is received, the time spent in the transport tasks
+                // counts as network delay.
+                let now = clock.now();
+                output.metadata.tov = now.into();
+                output.set_payload(TimeSyncPacket {
+                    kind: TimeSyncKind::Pong,
+                    receive: now,
+                    transmit: now,
+                    ..*ping
+                });
+            }
+            _ => output.clear_payload(),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_exchange() {
+        let (client_clock, client_mock) = RobotClock::mock();
+        let (server_clock, server_mock) = RobotClock::mock();
+        client_mock.increment(Duration::from_secs(1));
+        server_mock.increment(Duration::from_secs(6));
+        let advance = |ms| {
+            client_mock.increment(Duration::from_millis(ms));
+            server_mock.increment(Duration::from_millis(ms));
+        };
+
+        let mut config = ComponentConfig::new();
+        config.set("peer", "test_peer".to_string());
+        let mut client = TimeSyncClient::new(Some(&config)).unwrap();
+        let mut server = TimeSyncServer::new(None).unwrap();
+        let mut ping = CuMsg::<TimeSyncPacket>::new(None);
+        let mut pong = CuMsg::<TimeSyncPacket>::new(None);
+
+        client
+            .process(&client_clock, &CuMsg::new(None), &mut ping)
+            .unwrap();
+        assert_eq!(ping.payload().unwrap().kind, TimeSyncKind::Ping);
+        advance(1);
+        server.process(&server_clock, &ping, &mut pong).unwrap();
+        assert_eq!(pong.payload().unwrap().kind, TimeSyncKind::Pong);
+        advance(1);
+        let mut next_ping = CuMsg::<TimeSyncPacket>::new(None);
+        client
+            .process(&client_clock, &pong, &mut next_ping)
+            .unwrap();
+        // Not time to ping again yet.
+        assert!(next_ping.payload().is_none());
+
+        let estimate = clock_offset("test_peer").unwrap();
+        assert_eq!(estimate.offset_ns, 5_000_000_000);
+        assert_eq!(estimate.round_trip, CuDuration(2_000_000));
+        assert_eq!(
+            estimate.to_local(CuDuration(7_000_000_000)),
+            CuDuration(2_000_000_000)
+        );
+
+        // A server does not answer pongs.
+        server.process(&server_clock, &pong, &mut ping).unwrap();
+        assert!(ping.payload().is_none());
+    }
+}
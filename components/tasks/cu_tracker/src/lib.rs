@@ -0,0 +1,128 @@
+//! A trajectory tracking controller following the paths of `cu_planner` with pure pursuit or
+//! Stanley, outputting the velocity commands of the base from the odometry.
+//!
+//! With the planner, it makes the reference navigation stack of the components:
+//!
+//! ```ron
+//! cnx: [
+//!     (src: "planner", dst: "tracker", msg: "cu_spatial_payloads::Path2D", policy: Some(Latched)),
+//!     (src: "odometry", dst: "tracker", msg: "cu_spatial_payloads::Odometry2D"),
+//!     (src: "tracker", dst: "base", msg: "cu_spatial_payloads::Twist2D"),
+//! ]
+//! ```
+
+mod tracker;
+
+pub use tracker::*;
+
+use cu29::prelude::*;
+use cu_spatial_payloads::{Odometry2D, Path2D, Pose2D, Twist2D};
+
+#[derive(CuConfigStruct)]
+struct PathTrackerConfig {
+    #[config(default = "pure_pursuit")]
+    algorithm: String,
+    #[config(default = 0.5, range = 0.0..)]
+    lookahead_m: f64,
+    #[config(default)]
+    lookahead_gain_s: f64,
+    #[config(default = 1.0)]
+    stanley_gain: f64,
+    #[config(default = 0.5)]
+    wheelbase_m: f64,
+    #[config(default = 0.5, range = 0.0..)]
+    max_linear_mps: f64,
+    #[config(default = 1.0, range = 0.0..)]
+    max_angular_radps: f64,
+    #[config(default = 0.5, range = 0.0..)]
+    slowdown_distance_m: f64,
+    #[config(default = 0.1, range = 0.0..)]
+    goal_tolerance_m: f64,
+    rotate_in_place_rad: Option<f64>,
+}
+
+/// This is a task following the last path it received, the progress along it restarting with
+/// every new path. It stops the robot until it has a path and the odometry, and at the goal.
+pub struct PathTracker {
+    tracker: Tracker,
+    path: Vec<Pose2D>,
+    /// The time of validity of the path being followed, telling the new ones apart.
+    path_tov: Option<Tov>,
+    odometry: Option<Odometry2D>,
+}
+
+impl Freezable for PathTracker {}
+
+impl<'cl> CuTask<'cl> for PathTracker {
+    type Input = input_msg!('cl, Path2D, Odometry2D);
+    type Output = output_msg!('cl, Twist2D);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = PathTrackerConfig::from_config(config)?;
+        let law = match config.algorithm.as_str() {
+            "pure_pursuit" => TrackingLaw::PurePursuit {
+                lookahead: config.lookahead_m,
+                lookahead_gain: config.lookahead_gain_s,
+            },
+            "stanley" => {
+                if config.wheelbase_m <= 0.0 {
+                    return Err(CuError::from(
+                        "PathTracker: the wheelbase of Stanley should be positive.",
+                    ));
+                }
+                TrackingLaw::Stanley {
+                    gain: config.stanley_gain,
+                    wheelbase: config.wheelbase_m,
+                }
+            }
+            algorithm => {
+                return Err(CuError::from(format!(
+                    "PathTracker: unknown algorithm \"{algorithm}\", use pure_pursuit or stanley."
+                )))
+            }
+        };
+        Ok(Self {
+            tracker: Tracker::new(TrackerParams {
+                law,
+                max_linear: config.max_linear_mps,
+                max_angular: config.max_angular_radps,
+                slowdown_distance: config.slowdown_distance_m,
+                goal_tolerance: config.goal_tolerance_m,
+                rotate_in_place: config.rotate_in_place_rad,
+            }),
+            path: Vec::new(),
+            path_tov: None,
+            odometry: None,
+        })
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        let (path, odometry) = input;
+        if let Some(new_path) = path.payload() {
+            if self.path_tov != Some(path.metadata.tov) {
+                self.path.clear();
+                self.path.extend_from_slice(&new_path.poses);
+                self.path_tov = Some(path.metadata.tov);
+                self.tracker.reset();
+            }
+        }
+        if let Some(odometry) = odometry.payload() {
+            self.odometry = Some(*odometry);
+        }
+        let command = match &self.odometry {
+            Some(odometry) => self.tracker.command(&self.path, odometry),
+            None => Twist2D::default(),
+        };
+        output.set_payload(command);
+        output.metadata.tov = clock.now().into();
+        Ok(())
+    }
+}
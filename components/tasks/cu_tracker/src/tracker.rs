@@ -0,0 +1,263 @@
+//! The tracking laws: pure pursuit (Coulter 1992) steers on the arc through a point of the path
+//! ahead of the robot, Stanley (Thrun et al. 2006) corrects the heading and the cross track errors
+//! of the closest segment.
+
+use cu_spatial_payloads::{Odometry2D, Pose2D, Twist2D};
+use std::f64::consts::{PI, TAU};
+
+/// The speed under which the Stanley cross track correction stops growing, in m/s.
+const STANLEY_SOFTENING: f64 = 0.1;
+/// The largest steering angle of Stanley, keeping its tangent finite.
+const STANLEY_MAX_STEERING: f64 = 1.2;
+
+/// The angle in -PI..PI.
+pub fn normalize_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// How the tracker steers toward the path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackingLaw {
+    /// Follows the arc to the first pose of the path at least `lookahead` meters away, the lookahead
+    /// growing with the speed by `lookahead_gain` seconds.
+    PurePursuit { lookahead: f64, lookahead_gain: f64 },
+    /// Steers a bicycle of `wheelbase` meters by the heading error plus the arctangent of the
+    /// cross track error times `gain` over the speed.
+    Stanley { gain: f64, wheelbase: f64 },
+}
+
+/// The law and the limits of the commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackerParams {
+    pub law: TrackingLaw,
+    pub max_linear: f64,
+    pub max_angular: f64,
+    /// The speed decreases linearly to 0 over this distance to the goal.
+    pub slowdown_distance: f64,
+    /// The robot stops within this distance of the goal.
+    pub goal_tolerance: f64,
+    /// The robot turns in place when it heads further than this from the path.
+    pub rotate_in_place: Option<f64>,
+}
+
+/// The tracking controller of a path, keeping the progress along it so the paths crossing
+/// themselves are followed in order.
+#[derive(Debug, Clone)]
+pub struct Tracker {
+    params: TrackerParams,
+    progress: usize,
+}
+
+impl Tracker {
+    pub fn new(params: TrackerParams) -> Self {
+        Self {
+            params,
+            progress: 0,
+        }
+    }
+
+    /// Restarts from the beginning of the path, for a new one.
+    pub fn reset(&mut self) {
+        self.progress = 0;
+    }
+
+    /// The index of the pose of the path closest to the robot at the last command.
+    pub fn progress(&self) -> usize {
+        self.progress
+    }
+
+    /// The velocity command following `path` from `odometry`, a stop without a path or at its end.
+    pub fn command(&mut self, path: &[Pose2D], odometry: &Odometry2D) -> Twist2D {
+        let params = self.params;
+        let pose = odometry.pose;
+        let Some(goal) = path.last() else {
+            return Twist2D::default();
+        };
+        let to_goal = pose.distance(goal);
+        if to_goal <= params.goal_tolerance {
+            return Twist2D::default();
+        }
+
+        // The closest pose is searched forward from the last one, until the poses get further.
+        let mut closest = self.progress.min(path.len() - 1);
+        while closest + 1 < path.len()
+            && pose.distance(&path[closest + 1]) <= pose.distance(&path[closest])
+        {
+            closest += 1;
+        }
+        self.progress = closest;
+
+        let (heading_error, curvature) = match params.law {
+            TrackingLaw::PurePursuit {
+                lookahead,
+                lookahead_gain,
+            } => {
+                let lookahead = lookahead.max(lookahead_gain * odometry.twist.linear.abs());
+                let target = path[closest..]
+                    .iter()
+                    .find(|target| pose.distance(target) >= lookahead)
+                    .unwrap_or(goal);
+                // The target in the frame of the robot.
+                let (dx, dy) = (target.x - pose.x, target.y - pose.y);
+                let (sin, cos) = pose.theta.sin_cos();
+                let (x, y) = (cos * dx + sin * dy, cos * dy - sin * dx);
+                (y.atan2(x), 2.0 * y / (x * x + y * y))
+            }
+            TrackingLaw::Stanley { gain, wheelbase } => {
+                let (from, to) = if path.len() == 1 {
+                    (pose, *goal)
+                } else if closest + 1 < path.len() {
+                    (path[closest], path[closest + 1])
+                } else {
+                    (path[closest - 1], path[closest])
+                };
+                let path_heading = (to.y - from.y).atan2(to.x - from.x);
+                let heading_error = normalize_angle(path_heading - pose.theta);
+                // Positive when the robot is on the left of the path.
+                let (sin, cos) = path_heading.sin_cos();
+                let cross_track = cos * (pose.y - from.y) - sin * (pose.x - from.x);
+                let steering = heading_error
+                    - (gain * cross_track).atan2(STANLEY_SOFTENING + odometry.twist.linear.abs());
+                let steering = steering.clamp(-STANLEY_MAX_STEERING, STANLEY_MAX_STEERING);
+                (heading_error, steering.tan() / wheelbase)
+            }
+        };
+
+        if params
+            .rotate_in_place
+            .is_some_and(|threshold| heading_error.abs() > threshold)
+        {
+            return Twist2D {
+                linear: 0.0,
+                angular: params.max_angular.copysign(heading_error),
+            };
+        }
+        let mut linear = params.max_linear;
+        if params.slowdown_distance > 0.0 {
+            linear = linear.min(params.max_linear * to_goal / params.slowdown_distance);
+        }
+        // Slower in the tight turns to keep the curvature within the angular limit.
+        if linear * curvature.abs() > params.max_angular {
+            linear = params.max_angular / curvature.abs();
+        }
+        Twist2D {
+            linear,
+            angular: linear * curvature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(law: TrackingLaw) -> TrackerParams {
+        TrackerParams {
+            law,
+            max_linear: 0.5,
+            max_angular: 1.0,
+            slowdown_distance: 0.5,
+            goal_tolerance: 0.05,
+            rotate_in_place: None,
+        }
+    }
+
+    const PURE_PURSUIT: TrackingLaw = TrackingLaw::PurePursuit {
+        lookahead: 0.4,
+        lookahead_gain: 0.0,
+    };
+    const STANLEY: TrackingLaw = TrackingLaw::Stanley {
+        gain: 2.0,
+        wheelbase: 0.3,
+    };
+
+    /// An L: 3m along x then 2m along y, every 10cm.
+    fn l_path() -> Vec<Pose2D> {
+        let mut path: Vec<Pose2D> = (0..=30)
+            .map(|i| Pose2D::new(i as f64 * 0.1, 0.0, 0.0))
+            .collect();
+        path.extend((1..=20).map(|i| Pose2D::new(3.0, i as f64 * 0.1, PI / 2.0)));
+        path
+    }
+
+    fn odometry(x: f64, y: f64, theta: f64) -> Odometry2D {
+        Odometry2D {
+            pose: Pose2D::new(x, y, theta),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_steering() {
+        let path = l_path();
+        for law in [PURE_PURSUIT, STANLEY] {
+            let mut tracker = Tracker::new(params(law));
+            // On the path, straight ahead at full speed.
+            let command = tracker.command(&path, &odometry(1.0, 0.0, 0.0));
+            assert_eq!(command.linear, 0.5, "{law:?}");
+            assert!(command.angular.abs() < 1e-9, "{law:?}");
+            assert_eq!(tracker.progress(), 10);
+            // On the left of the path, turning right, and the other way around.
+            assert!(tracker.command(&path, &odometry(1.0, 0.2, 0.0)).angular < 0.0);
+            assert!(tracker.command(&path, &odometry(1.0, -0.2, 0.0)).angular > 0.0);
+            // Heading left of the path, turning right.
+            assert!(tracker.command(&path, &odometry(1.0, 0.0, 0.3)).angular < 0.0);
+            // Close to the goal, slowing down, then stopped.
+            let command = tracker.command(&path, &odometry(3.0, 1.8, PI / 2.0));
+            assert!(command.linear > 0.0 && command.linear < 0.25, "{law:?}");
+            let command = tracker.command(&path, &odometry(3.0, 1.98, PI / 2.0));
+            assert_eq!(command, Twist2D::default());
+            assert_eq!(
+                tracker.command(&[], &odometry(0.0, 0.0, 0.0)),
+                Twist2D::default()
+            );
+        }
+
+        let mut tracker = Tracker::new(TrackerParams {
+            rotate_in_place: Some(1.0),
+            ..params(PURE_PURSUIT)
+        });
+        let command = tracker.command(&path, &odometry(0.0, 0.0, PI));
+        assert_eq!(command.linear, 0.0);
+        assert_eq!(command.angular.abs(), 1.0);
+    }
+
+    #[test]
+    fn test_tracking() {
+        let path = l_path();
+        for law in [PURE_PURSUIT, STANLEY] {
+            let mut tracker = Tracker::new(params(law));
+            // A unicycle starting 20cm off the path.
+            let mut robot = odometry(0.0, -0.2, 0.0);
+            let dt = 0.05;
+            let mut steps = 0;
+            loop {
+                let command = tracker.command(&path, &robot);
+                if command == Twist2D::default() {
+                    break;
+                }
+                assert!(command.linear <= 0.5 && command.angular.abs() <= 1.0 + 1e-9);
+                let pose = &mut robot.pose;
+                pose.x += command.linear * pose.theta.cos() * dt;
+                pose.y += command.linear * pose.theta.sin() * dt;
+                pose.theta += command.angular * dt;
+                robot.twist = command;
+                steps += 1;
+                assert!(steps < 2000, "{law:?} didn't reach the goal: {robot:?}");
+                // Never far from the L.
+                let pose = robot.pose;
+                assert!(
+                    path.iter().any(|p| p.distance(&pose) < 0.3),
+                    "{law:?}: {robot:?}"
+                );
+            }
+            assert!(robot.pose.distance(path.last().unwrap()) <= 0.05);
+        }
+    }
+
+    #[test]
+    fn test_normalize_angle() {
+        assert!((normalize_angle(3.0 * PI / 2.0) + PI / 2.0).abs() < 1e-12);
+        assert!((normalize_angle(-0.1) + 0.1).abs() < 1e-12);
+    }
+}
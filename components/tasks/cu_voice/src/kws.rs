@@ -0,0 +1,263 @@
+use crate::{Utterance, VoiceCommand};
+use cu29::prelude::*;
+#[cfg(feature = "onnx")]
+use ort::session::Session;
+#[cfg(feature = "onnx")]
+use ort::value::Tensor;
+
+/// A keyword spotting model: a fixed length window of audio in, a score per label out.
+pub trait KeywordModel {
+    /// Loads the model from the `model` path of the config.
+    fn load(path: &str) -> CuResult<Self>
+    where
+        Self: Sized;
+
+    /// `samples` are normalized to [-1, 1], the scores are logits or probabilities.
+    fn scores(&mut self, samples: &[f32]) -> CuResult<Vec<f32>>;
+}
+
+/// A model exported to ONNX taking a [1, input_samples] f32 waveform, like the speech commands models.
+#[cfg(feature = "onnx")]
+pub struct OnnxKeywordModel {
+    session: Session,
+}
+
+#[cfg(feature = "onnx")]
+impl KeywordModel for OnnxKeywordModel {
+    fn load(path: &str) -> CuResult<Self> {
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(path))
+            .map_err(|e| {
+                CuError::new_with_cause(&format!("KeywordSpotter: Failed to load {path}"), e)
+            })?;
+        Ok(Self { session })
+    }
+
+    fn scores(&mut self, samples: &[f32]) -> CuResult<Vec<f32>> {
+        let map_error = |e| CuError::new_with_cause("KeywordSpotter: Inference failed", e);
+        let input =
+            Tensor::from_array(([1usize, samples.len()], samples.to_vec())).map_err(map_error)?;
+        let outputs = self
+            .session
+            .run(ort::inputs![input].map_err(map_error)?)
+            .map_err(map_error)?;
+        let (_, scores) = outputs[0]
+            .try_extract_raw_tensor::<f32>()
+            .map_err(map_error)?;
+        Ok(scores.to_vec())
+    }
+}
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct KeywordSpotterConfig {
+    model: String,
+    labels: String,
+    #[config(default = 16000)]
+    input_samples: usize,
+    #[config(default = 0.7, range = 0.0..=1.0)]
+    threshold: f64,
+    #[config(default = "_silence_,_unknown_")]
+    ignore: String,
+}
+
+fn split_labels(labels: &str) -> Vec<String> {
+    labels
+        .split(',')
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+        .collect()
+}
+
+/// The scores as probabilities: they are passed through a softmax unless they already are.
+fn probabilities(scores: &[f32]) -> Vec<f32> {
+    let sum: f32 = scores.iter().sum();
+    if scores.iter().all(|s| (0.0..=1.0).contains(s)) && (sum - 1.0).abs() < 1e-3 {
+        return scores.to_vec();
+    }
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps = scores.iter().map(|s| (s - max).exp()).collect::<Vec<_>>();
+    let sum: f32 = exps.iter().sum();
+    exps.iter().map(|e| e / sum).collect()
+}
+
+/// The model input: the utterance normalized, centered in the window and padded with silence or cropped.
+fn window(utterance: &Utterance, input_samples: usize) -> Vec<f32> {
+    let mut window = vec![0.0; input_samples];
+    let samples = &utterance.samples;
+    let (src, dst) = if samples.len() >= input_samples {
+        ((samples.len() - input_samples) / 2, 0)
+    } else {
+        (0, (input_samples - samples.len()) / 2)
+    };
+    for (out, sample) in window[dst..].iter_mut().zip(&samples[src..]) {
+        *out = *sample as f32 / 32768.0;
+    }
+    window
+}
+
+/// This task classifies the utterances with a keyword spotting model and emits the recognized keywords.
+/// Nothing is emitted if the best label is ignored or under the threshold.
+/// Without the `onnx` feature, the model has to be given: `KeywordSpotter<MyModel>`.
+pub struct KeywordSpotter<
+    #[cfg(feature = "onnx")] M: KeywordModel = OnnxKeywordModel,
+    #[cfg(not(feature = "onnx"))] M: KeywordModel,
+> {
+    model: M,
+    labels: Vec<String>,
+    ignore: Vec<String>,
+    input_samples: usize,
+    threshold: f32,
+}
+
+impl<M: KeywordModel> KeywordSpotter<M> {
+    fn spot(&mut self, utterance: &Utterance) -> CuResult<Option<VoiceCommand>> {
+        let scores = self.model.scores(&window(utterance, self.input_samples))?;
+        if scores.len() != self.labels.len() {
+            return Err(format!(
+                "KeywordSpotter: the model has {} outputs for {} labels.",
+                scores.len(),
+                self.labels.len()
+            )
+            .into());
+        }
+        let Some((best, confidence)) = probabilities(&scores)
+            .into_iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        else {
+            return Ok(None);
+        };
+        let keyword = &self.labels[best];
+        if confidence < self.threshold || self.ignore.contains(keyword) {
+            return Ok(None);
+        }
+        Ok(Some(VoiceCommand {
+            keyword: keyword.clone(),
+            confidence,
+        }))
+    }
+}
+
+impl<M: KeywordModel> Freezable for KeywordSpotter<M> {}
+
+impl<'cl, M: KeywordModel> CuTask<'cl> for KeywordSpotter<M> {
+    type Input = input_msg!('cl, Utterance);
+    type Output = output_msg!('cl, VoiceCommand);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let KeywordSpotterConfig {
+            model,
+            labels,
+            input_samples,
+            threshold,
+            ignore,
+        } = KeywordSpotterConfig::from_config(config)?;
+        Ok(Self {
+            model: M::load(&model)?,
+            labels: split_labels(&labels),
+            ignore: split_labels(&ignore),
+            input_samples,
+            threshold: threshold as f32,
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        output.clear_payload();
+        let Some(utterance) = input.payload() else {
+            return Ok(());
+        };
+        if let Some(command) = self.spot(utterance)? {
+            debug!(
+                "KeywordSpotter: {} ({})",
+                command.keyword.as_str(),
+                command.confidence
+            );
+            output
+                .metadata
+                .set_status(format!("{} {:.2}", command.keyword, command.confidence));
+            output.metadata.tov = input.metadata.tov;
+            output.set_payload(command);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scores the loudness of the window: "_silence_", "stop".
+    struct LoudnessModel;
+
+    impl KeywordModel for LoudnessModel {
+        fn load(_path: &str) -> CuResult<Self> {
+            Ok(Self)
+        }
+
+        fn scores(&mut self, samples: &[f32]) -> CuResult<Vec<f32>> {
+            let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            Ok(vec![(1.0 - peak) * 10.0, peak * 10.0])
+        }
+    }
+
+    fn spotter() -> KeywordSpotter<LoudnessModel> {
+        KeywordSpotter {
+            model: LoudnessModel,
+            labels: split_labels("_silence_, stop"),
+            ignore: split_labels("_silence_,_unknown_"),
+            input_samples: 8,
+            threshold: 0.7,
+        }
+    }
+
+    #[test]
+    fn test_window() {
+        let utterance = Utterance {
+            sample_rate: 16000,
+            samples: vec![16384, -16384],
+        };
+        assert_eq!(window(&utterance, 4), vec![0.0, 0.5, -0.5, 0.0]);
+        let utterance = Utterance {
+            sample_rate: 16000,
+            samples: (0..6).collect(),
+        };
+        assert_eq!(window(&utterance, 2), vec![2.0 / 32768.0, 3.0 / 32768.0]);
+    }
+
+    #[test]
+    fn test_spot() {
+        let mut spotter = spotter();
+        let loud = Utterance {
+            sample_rate: 16000,
+            samples: vec![32000; 4],
+        };
+        let command = spotter.spot(&loud).unwrap().unwrap();
+        assert_eq!(command.keyword, "stop");
+        assert!(command.confidence > 0.99);
+
+        let quiet = Utterance {
+            sample_rate: 16000,
+            samples: vec![100; 4],
+        };
+        assert_eq!(spotter.spot(&quiet).unwrap(), None);
+
+        // Undecided.
+        let medium = Utterance {
+            sample_rate: 16000,
+            samples: vec![16384; 4],
+        };
+        assert_eq!(spotter.spot(&medium).unwrap(), None);
+
+        spotter.labels.push("go".to_string());
+        assert!(spotter.spot(&loud).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+mod kws;
+mod teleop;
+mod vad;
+
+#[cfg(feature = "onnx")]
+pub use kws::OnnxKeywordModel;
+pub use kws::{KeywordModel, KeywordSpotter};
+pub use teleop::VoiceTeleop;
+pub use vad::VoiceActivityDetector;
+
+use bincode::{Decode, Encode};
+
+/// A segment of speech detected in the audio stream, mono.
+#[derive(Default, Debug, Clone, Encode, Decode)]
+pub struct Utterance {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+impl Utterance {
+    pub fn duration_ms(&self) -> u32 {
+        if self.sample_rate == 0 {
+            return 0;
+        }
+        (self.samples.len() as u64 * 1000 / self.sample_rate as u64) as u32
+    }
+}
+
+/// A keyword recognized in an utterance.
+#[derive(Default, Debug, Clone, PartialEq, Encode, Decode)]
+pub struct VoiceCommand {
+    pub keyword: String,
+    /// Score of the keyword given by the model, between 0 and 1.
+    pub confidence: f32,
+}
+
+/// Velocity command of a mobile base.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct TeleopCommand {
+    /// m/s, positive forward.
+    pub linear: f32,
+    /// rad/s, positive counterclockwise.
+    pub angular: f32,
+}
@@ -0,0 +1,141 @@
+use crate::{TeleopCommand, VoiceCommand};
+use cu29::prelude::*;
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct VoiceTeleopConfig {
+    #[config(default = 0.3)]
+    speed: f64,
+    #[config(default = 0.5)]
+    turn_rate: f64,
+    #[config(default = 3000)]
+    timeout_ms: u32,
+}
+
+/// This task turns the voice commands into velocity commands for a mobile base:
+/// "go"/"forward", "back"/"backward", "left", "right" and "stop", the other keywords are ignored.
+/// The last command is emitted every cycle until `timeout_ms` elapsed, then the base is stopped.
+pub struct VoiceTeleop {
+    speed: f32,
+    turn_rate: f32,
+    timeout: CuDuration,
+    current: TeleopCommand,
+    since: CuTime,
+}
+
+impl VoiceTeleop {
+    fn command(&self, keyword: &str) -> Option<TeleopCommand> {
+        let (linear, angular) = match keyword {
+            "go" | "forward" => (self.speed, 0.0),
+            "back" | "backward" => (-self.speed, 0.0),
+            "left" => (0.0, self.turn_rate),
+            "right" => (0.0, -self.turn_rate),
+            "stop" => (0.0, 0.0),
+            _ => return None,
+        };
+        Some(TeleopCommand { linear, angular })
+    }
+}
+
+impl Freezable for VoiceTeleop {}
+
+impl<'cl> CuTask<'cl> for VoiceTeleop {
+    type Input = input_msg!('cl, VoiceCommand);
+    type Output = output_msg!('cl, TeleopCommand);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let VoiceTeleopConfig {
+            speed,
+            turn_rate,
+            timeout_ms,
+        } = VoiceTeleopConfig::from_config(config)?;
+        Ok(Self {
+            speed: speed as f32,
+            turn_rate: turn_rate as f32,
+            timeout: CuDuration::from(timeout_ms as u64 * 1_000_000),
+            current: TeleopCommand::default(),
+            since: CuTime::default(),
+        })
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        let now = clock.now();
+        if let Some(voice) = input.payload() {
+            match self.command(&voice.keyword) {
+                Some(command) => {
+                    self.current = command;
+                    self.since = now;
+                }
+                None => debug!("VoiceTeleop: Ignoring {}", voice.keyword.as_str()),
+            }
+        }
+        if now - self.since > self.timeout {
+            self.current = TeleopCommand::default();
+        }
+        output.metadata.tov = now.into();
+        output.set_payload(self.current);
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.current = TeleopCommand::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn voice(keyword: &str) -> CuMsg<VoiceCommand> {
+        CuMsg::new(Some(VoiceCommand {
+            keyword: keyword.to_string(),
+            confidence: 0.9,
+        }))
+    }
+
+    #[test]
+    fn test_teleop() {
+        let (clock, mock) = RobotClock::mock();
+        mock.increment(Duration::from_secs(1));
+        let mut teleop = VoiceTeleop::new(None).unwrap();
+        let mut output = CuMsg::<TeleopCommand>::new(None);
+
+        teleop.process(&clock, &voice("go"), &mut output).unwrap();
+        assert_eq!(output.payload().unwrap().linear, 0.3);
+
+        // Unknown keywords and silence keep the command.
+        teleop
+            .process(&clock, &voice("banana"), &mut output)
+            .unwrap();
+        mock.increment(Duration::from_secs(2));
+        teleop
+            .process(&clock, &CuMsg::new(None), &mut output)
+            .unwrap();
+        assert_eq!(output.payload().unwrap().linear, 0.3);
+
+        teleop.process(&clock, &voice("left"), &mut output).unwrap();
+        assert_eq!(
+            output.payload(),
+            Some(&TeleopCommand {
+                linear: 0.0,
+                angular: 0.5
+            })
+        );
+
+        mock.increment(Duration::from_secs(4));
+        teleop
+            .process(&clock, &CuMsg::new(None), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), Some(&TeleopCommand::default()));
+    }
+}
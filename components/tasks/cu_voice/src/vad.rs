@@ -0,0 +1,253 @@
+use crate::Utterance;
+use cu29::prelude::*;
+use cu_sensor_payloads::CuAudioFrame;
+
+/// What the task reads from its ComponentConfig.
+#[derive(CuConfigStruct)]
+struct VadConfig {
+    #[config(default = -40.0)]
+    threshold_db: f64,
+    #[config(default = 300)]
+    hangover_ms: u32,
+    #[config(default = 150)]
+    min_speech_ms: u32,
+    #[config(default = 2000)]
+    max_utterance_ms: u32,
+}
+
+/// Level of a frame in dBFS, -120 for digital silence.
+fn level_db(samples: &[i16]) -> f64 {
+    if samples.is_empty() {
+        return -120.0;
+    }
+    let energy = samples
+        .iter()
+        .map(|s| (*s as f64) * (*s as f64))
+        .sum::<f64>()
+        / samples.len() as f64;
+    let rms = energy.sqrt() / 32768.0;
+    if rms <= 1e-6 {
+        -120.0
+    } else {
+        20.0 * rms.log10()
+    }
+}
+
+/// Averages the interleaved channels.
+fn downmix(samples: &[i16], channels: usize) -> Vec<i16> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels)
+        .map(|frame| (frame.iter().map(|s| *s as i32).sum::<i32>() / channels as i32) as i16)
+        .collect()
+}
+
+struct Speech {
+    start: CuTime,
+    samples: Vec<i16>,
+    /// Duration of the frames above the threshold.
+    voiced_ms: u32,
+    /// Duration of the trailing frames below the threshold.
+    silence_ms: u32,
+}
+
+/// Energy based speech segmentation: an utterance starts with the first frame above the threshold
+/// and ends after `hangover_ms` below it.
+struct Segmenter {
+    threshold_db: f64,
+    hangover_ms: u32,
+    min_speech_ms: u32,
+    max_utterance_ms: u32,
+    speech: Option<Speech>,
+}
+
+impl Segmenter {
+    fn reset(&mut self) {
+        self.speech = None;
+    }
+
+    /// Feeds a mono frame, returns the utterance it completes if any, with the time of its first sample.
+    fn push(
+        &mut self,
+        tov: CuTime,
+        samples: &[i16],
+        sample_rate: u32,
+    ) -> Option<(CuTime, Utterance)> {
+        let frame_ms = (samples.len() as u64 * 1000 / sample_rate.max(1) as u64) as u32;
+        let voiced = level_db(samples) >= self.threshold_db;
+        let speech = match self.speech.as_mut() {
+            Some(speech) => speech,
+            None if voiced => self.speech.insert(Speech {
+                start: tov,
+                samples: Vec::new(),
+                voiced_ms: 0,
+                silence_ms: 0,
+            }),
+            None => return None,
+        };
+        speech.samples.extend_from_slice(samples);
+        if voiced {
+            speech.voiced_ms += frame_ms;
+            speech.silence_ms = 0;
+        } else {
+            speech.silence_ms += frame_ms;
+        }
+        let duration_ms = (speech.samples.len() as u64 * 1000 / sample_rate.max(1) as u64) as u32;
+        if speech.silence_ms < self.hangover_ms && duration_ms < self.max_utterance_ms {
+            return None;
+        }
+        let speech = self.speech.take()?;
+        if speech.voiced_ms < self.min_speech_ms {
+            return None;
+        }
+        Some((
+            speech.start,
+            Utterance {
+                sample_rate,
+                samples: speech.samples,
+            },
+        ))
+    }
+}
+
+/// This task segments the audio stream into utterances. An utterance is emitted once it is complete,
+/// its time of validity is the time of its first sample.
+pub struct VoiceActivityDetector {
+    segmenter: Segmenter,
+    format: Option<cu_sensor_payloads::CuAudioFormat>,
+}
+
+impl Freezable for VoiceActivityDetector {}
+
+impl<'cl> CuTask<'cl> for VoiceActivityDetector {
+    type Input = input_msg!('cl, CuAudioFrame<Vec<i16>>);
+    type Output = output_msg!('cl, Utterance);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let VadConfig {
+            threshold_db,
+            hangover_ms,
+            min_speech_ms,
+            max_utterance_ms,
+        } = VadConfig::from_config(config)?;
+        Ok(Self {
+            segmenter: Segmenter {
+                threshold_db,
+                hangover_ms,
+                min_speech_ms,
+                max_utterance_ms,
+                speech: None,
+            },
+            format: None,
+        })
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        output.clear_payload();
+        let Some(frame) = input.payload() else {
+            return Ok(());
+        };
+        if self.format.is_some_and(|format| format != frame.format) {
+            // The audio stream changed, what was heard so far cannot be stitched to it.
+            self.segmenter.reset();
+        }
+        self.format = Some(frame.format);
+        let tov = match input.metadata.tov {
+            Tov::Time(tov) => tov,
+            _ => clock.now(),
+        };
+        let samples = frame
+            .buffer_handle
+            .with_inner(|samples| downmix(samples, frame.format.channels as usize));
+        if let Some((start, utterance)) =
+            self.segmenter.push(tov, &samples, frame.format.sample_rate)
+        {
+            output
+                .metadata
+                .set_status(format!("{}ms", utterance.duration_ms()));
+            output.metadata.tov = start.into();
+            output.set_payload(utterance);
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.segmenter.reset();
+        self.format = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segmenter() -> Segmenter {
+        Segmenter {
+            threshold_db: -40.0,
+            hangover_ms: 300,
+            min_speech_ms: 150,
+            max_utterance_ms: 2000,
+            speech: None,
+        }
+    }
+
+    /// 100ms at 16kHz, a -20dBFS square wave or silence.
+    fn frame(voiced: bool) -> Vec<i16> {
+        let amplitude = if voiced { 3277 } else { 0 };
+        (0..1600)
+            .map(|i| if i % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn test_level() {
+        assert!((level_db(&frame(true)) + 20.0).abs() < 0.01);
+        assert_eq!(level_db(&frame(false)), -120.0);
+        assert_eq!(downmix(&[100, 300, -2, 0], 2), vec![200, -1]);
+    }
+
+    #[test]
+    fn test_segmentation() {
+        let mut segmenter = segmenter();
+        let mut utterances = Vec::new();
+        // silence, 300ms of speech with a 100ms pause, then silence.
+        let pattern = [false, true, true, false, true, false, false, false, false];
+        for (i, voiced) in pattern.iter().enumerate() {
+            let tov = CuDuration(i as u64 * 100_000_000);
+            utterances.extend(segmenter.push(tov, &frame(*voiced), 16000));
+        }
+        assert_eq!(utterances.len(), 1);
+        let (start, utterance) = &utterances[0];
+        assert_eq!(*start, CuDuration(100_000_000));
+        // The speech and the hangover.
+        assert_eq!(utterance.duration_ms(), 700);
+    }
+
+    #[test]
+    fn test_short_and_long() {
+        let mut segmenter = segmenter();
+        // A 100ms click is not speech.
+        for voiced in [true, false, false, false] {
+            assert!(segmenter
+                .push(CuDuration(0), &frame(voiced), 16000)
+                .is_none());
+        }
+        // Continuous speech is cut.
+        let emitted = (0..25)
+            .filter_map(|_| segmenter.push(CuDuration(0), &frame(true), 16000))
+            .collect::<Vec<_>>();
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(emitted[0].1.duration_ms(), 2000);
+    }
+}
@@ -0,0 +1,10 @@
+fn main() {
+    let wasmtime_enabled = std::env::var("CARGO_FEATURE_WASMTIME").is_ok();
+    if !wasmtime_enabled {
+        println!("cargo:warning=wasmtime feature is not enabled. Skipping cu_wasm build.");
+    }
+    println!(
+        "cargo:rustc-env=LOG_INDEX_DIR={}",
+        std::env::var("OUT_DIR").unwrap()
+    );
+}
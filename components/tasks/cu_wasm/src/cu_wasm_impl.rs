@@ -0,0 +1,334 @@
+use bincode::{decode_from_slice, encode_to_vec};
+use cu29::prelude::*;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use wasmtime::{
+    Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+/// Config keys consumed by the host, all the others are forwarded to the plugin.
+const HOST_KEYS: [&str; 4] = ["module", "hot_reload", "fuel", "max_memory_mib"];
+const DEFAULT_MAX_MEMORY_MIB: u32 = 64;
+
+struct PluginState {
+    limits: StoreLimits,
+}
+
+/// An instantiated plugin and the entry points of its ABI.
+struct Plugin {
+    store: Store<PluginState>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    process: TypedFunc<(i32, i32), i64>,
+    modified: Option<SystemTime>,
+}
+
+fn wasm_error(context: &str, e: impl std::fmt::Display) -> CuError {
+    CuError::from(format!("{context}: {e}"))
+}
+
+fn module_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Plugin {
+    fn load(
+        engine: &Engine,
+        path: &Path,
+        plugin_config: &str,
+        max_memory: usize,
+        fuel: Option<u64>,
+    ) -> CuResult<Self> {
+        let modified = module_modified(path);
+        let module = Module::from_file(engine, path).map_err(|e| {
+            wasm_error(
+                &format!("Could not load the wasm module {}", path.display()),
+                e,
+            )
+        })?;
+        let mut store = Store::new(
+            engine,
+            PluginState {
+                limits: StoreLimitsBuilder::new().memory_size(max_memory).build(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        if let Some(fuel) = fuel {
+            store
+                .set_fuel(fuel)
+                .map_err(|e| wasm_error("Could not set the fuel", e))?;
+        }
+
+        let mut linker = Linker::new(engine);
+        linker
+            .func_wrap(
+                "env",
+                "cu_log",
+                |mut caller: Caller<'_, PluginState>, ptr: i32, len: i32| {
+                    let Some(memory) = caller
+                        .get_export("memory")
+                        .and_then(|export| export.into_memory())
+                    else {
+                        return;
+                    };
+                    let data = memory.data(&caller);
+                    if let Some(bytes) = data.get(ptr as usize..(ptr as usize + len as usize)) {
+                        debug!(
+                            "wasm plugin: {}",
+                            String::from_utf8_lossy(bytes).to_string()
+                        );
+                    }
+                },
+            )
+            .map_err(|e| wasm_error("Could not link cu_log", e))?;
+
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| wasm_error("Could not instantiate the wasm module", e))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| CuError::from("The wasm module does not export its memory."))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "cu_alloc")
+            .map_err(|e| wasm_error("The wasm module does not export cu_alloc(i32) -> i32", e))?;
+        let process = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "cu_process")
+            .map_err(|e| {
+                wasm_error(
+                    "The wasm module does not export cu_process(i32, i32) -> i64",
+                    e,
+                )
+            })?;
+
+        let mut plugin = Plugin {
+            store,
+            memory,
+            alloc,
+            process,
+            modified,
+        };
+
+        // cu_configure is optional, plugins without any parameter don't need it.
+        if let Ok(configure) =
+            instance.get_typed_func::<(i32, i32), i32>(&mut plugin.store, "cu_configure")
+        {
+            let (ptr, len) = plugin.write(plugin_config.as_bytes())?;
+            let code = configure
+                .call(&mut plugin.store, (ptr, len))
+                .map_err(|e| wasm_error("The wasm plugin trapped in cu_configure", e))?;
+            if code != 0 {
+                return Err(CuError::from(format!(
+                    "The wasm plugin rejected its configuration (error code {code})."
+                )));
+            }
+        }
+        Ok(plugin)
+    }
+
+    /// Copies bytes into a buffer allocated by the plugin.
+    fn write(&mut self, bytes: &[u8]) -> CuResult<(i32, i32)> {
+        let len = bytes.len() as i32;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| wasm_error("The wasm plugin trapped in cu_alloc", e))?;
+        self.memory
+            .write(&mut self.store, ptr as usize, bytes)
+            .map_err(|e| wasm_error("cu_alloc returned an invalid buffer", e))?;
+        Ok((ptr, len))
+    }
+
+    fn call_process(&mut self, input: &[u8]) -> CuResult<Vec<u8>> {
+        let (ptr, len) = self.write(input)?;
+        let packed = self
+            .process
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| wasm_error("The wasm plugin trapped in cu_process", e))?;
+        if packed < 0 {
+            return Err(CuError::from(format!(
+                "The wasm plugin failed with the error code {packed}."
+            )));
+        }
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize);
+        self.memory
+            .data(&self.store)
+            .get(out_ptr..out_ptr + out_len)
+            .map(|output| output.to_vec())
+            .ok_or_else(|| CuError::from("cu_process returned an output out of the plugin memory."))
+    }
+}
+
+/// A task running its processing in a sandboxed WebAssembly plugin.
+/// The payloads cross the sandbox boundary bincode encoded (as `Option<I>` and `Option<O>`),
+/// see the README of this crate for the ABI the plugin needs to implement.
+pub struct WasmTask<I, O> {
+    engine: Engine,
+    module_path: PathBuf,
+    plugin_config: String,
+    max_memory: usize,
+    fuel: Option<u64>,
+    hot_reload: bool,
+    plugin: Plugin,
+    _payloads: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O> WasmTask<I, O> {
+    /// Reloads the plugin from its module file, the current plugin is kept if the new one fails
+    /// to load.
+    pub fn reload(&mut self) -> CuResult<()> {
+        self.plugin = Plugin::load(
+            &self.engine,
+            &self.module_path,
+            &self.plugin_config,
+            self.max_memory,
+            self.fuel,
+        )?;
+        Ok(())
+    }
+
+    fn reload_if_changed(&mut self) {
+        let modified = module_modified(&self.module_path);
+        if modified.is_none() || modified == self.plugin.modified {
+            return;
+        }
+        match self.reload() {
+            Ok(()) => debug!(
+                "Reloaded the wasm plugin {}",
+                self.module_path.display().to_string()
+            ),
+            Err(e) => {
+                // Do not try again until the file changes again.
+                self.plugin.modified = modified;
+                debug!(
+                    "Keeping the previous wasm plugin, the new one failed to load: {}",
+                    e.to_string()
+                );
+            }
+        }
+    }
+}
+
+impl<I, O> Freezable for WasmTask<I, O> {}
+
+impl<'cl, I, O> CuTask<'cl> for WasmTask<I, O>
+where
+    I: CuMsgPayload + 'cl,
+    O: CuMsgPayload + 'cl,
+{
+    type Input = input_msg!('cl, I);
+    type Output = output_msg!('cl, O);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let config = config.ok_or_else(|| CuError::from("WasmTask needs a config."))?;
+        let module_path: PathBuf = config
+            .get::<String>("module")
+            .ok_or_else(|| CuError::from("WasmTask needs the path of its wasm module."))?
+            .into();
+        let hot_reload = config.get::<bool>("hot_reload").unwrap_or(false);
+        let fuel = config.get::<u64>("fuel");
+        let max_memory = config
+            .get::<u32>("max_memory_mib")
+            .unwrap_or(DEFAULT_MAX_MEMORY_MIB) as usize
+            * 1024
+            * 1024;
+
+        // The plugin receives its parameters as sorted "key=value" lines.
+        let mut plugin_config: Vec<String> = config
+            .0
+            .iter()
+            .filter(|(key, _)| !HOST_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        plugin_config.sort();
+        let plugin_config = plugin_config.join("\n");
+
+        let mut engine_config = Config::new();
+        engine_config.consume_fuel(fuel.is_some());
+        let engine = Engine::new(&engine_config)
+            .map_err(|e| wasm_error("Could not create the wasm engine", e))?;
+        let plugin = Plugin::load(&engine, &module_path, &plugin_config, max_memory, fuel)?;
+
+        Ok(WasmTask {
+            engine,
+            module_path,
+            plugin_config,
+            max_memory,
+            fuel,
+            hot_reload,
+            plugin,
+            _payloads: PhantomData,
+        })
+    }
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        if self.hot_reload {
+            self.reload_if_changed();
+        }
+        if let Some(fuel) = self.fuel {
+            self.plugin
+                .store
+                .set_fuel(fuel)
+                .map_err(|e| wasm_error("Could not refuel the wasm plugin", e))?;
+        }
+        let encoded = encode_to_vec(input.payload(), CuWireFormat::CONFIG)
+            .map_err(|e| CuError::new_with_cause("Could not encode the plugin input", e))?;
+        let result = self.plugin.call_process(&encoded)?;
+        let (payload, _): (Option<O>, usize) = decode_from_slice(&result, CuWireFormat::CONFIG)
+            .map_err(|e| CuError::new_with_cause("Could not decode the plugin output", e))?;
+        match payload {
+            Some(payload) => output.set_payload(payload),
+            None => output.clear_payload(),
+        }
+        output.metadata.tov = input.metadata.tov;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Echoes its input back, so the output payload is the input payload.
+    const ECHO_WAT: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "cu_alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "cu_configure") (param i32 i32) (result i32) i32.const 0)
+            (func (export "cu_process") (param i32 i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+                    (i64.extend_i32_u (local.get 1)))))
+    "#;
+
+    #[test]
+    fn test_echo_plugin() {
+        let mut module = tempfile::Builder::new().suffix(".wat").tempfile().unwrap();
+        module.write_all(ECHO_WAT.as_bytes()).unwrap();
+
+        let mut config = ComponentConfig::new();
+        config.set("module", module.path().to_str().unwrap().to_string());
+        config.set("fuel", 10_000u32);
+        let mut task = WasmTask::<u32, u32>::new(Some(&config)).unwrap();
+
+        let clock = RobotClock::new();
+        let input = CuMsg::new(Some(42u32));
+        let mut output = CuMsg::<u32>::new(None);
+        task.process(&clock, &input, &mut output).unwrap();
+        assert_eq!(output.payload(), Some(&42));
+
+        let input = CuMsg::<u32>::new(None);
+        task.process(&clock, &input, &mut output).unwrap();
+        assert_eq!(output.payload(), None);
+    }
+}
@@ -0,0 +1,5 @@
+#[cfg(feature = "wasmtime")]
+mod cu_wasm_impl;
+
+#[cfg(feature = "wasmtime")]
+pub use cu_wasm_impl::*;
@@ -1,15 +1,46 @@
 #![doc = include_str!("../README.md")]
 
 // backward compatibility
+pub use cu29_runtime::actor;
+pub use cu29_runtime::alarms;
+pub use cu29_runtime::assets;
+pub use cu29_runtime::budget;
+#[cfg(feature = "chaos")]
+pub use cu29_runtime::chaos;
+pub use cu29_runtime::chunked;
 pub use cu29_runtime::config;
 pub use cu29_runtime::copperlist;
+pub use cu29_runtime::crash;
+pub use cu29_runtime::cu_quantity;
 pub use cu29_runtime::curuntime;
 pub use cu29_runtime::cutask;
+pub use cu29_runtime::delivery;
+pub use cu29_runtime::estop;
+pub use cu29_runtime::events;
+pub use cu29_runtime::export;
+pub use cu29_runtime::fixed;
+pub use cu29_runtime::inject;
 pub use cu29_runtime::input_msg;
+pub use cu29_runtime::introspection;
+pub use cu29_runtime::invariants;
+pub use cu29_runtime::manifest;
 pub use cu29_runtime::monitoring;
+pub use cu29_runtime::noise;
 pub use cu29_runtime::output_msg;
+pub use cu29_runtime::params;
 pub use cu29_runtime::payload;
+#[cfg(feature = "perf")]
+pub use cu29_runtime::perf;
+pub use cu29_runtime::pipeline;
+pub use cu29_runtime::profile;
+pub use cu29_runtime::replay;
+pub use cu29_runtime::schema;
+pub use cu29_runtime::service;
 pub use cu29_runtime::simulation;
+#[cfg(feature = "tap")]
+pub use cu29_runtime::tap;
+pub use cu29_runtime::testing;
+pub use cu29_runtime::units;
 
 pub use bincode;
 pub use cu29_clock as clock;
@@ -23,10 +54,14 @@ pub mod prelude {
     pub use cu29_log::*;
     pub use cu29_log_derive::*;
     pub use cu29_log_runtime::*;
+    pub use cu29_runtime::alarms::*;
     pub use cu29_runtime::config::*;
     pub use cu29_runtime::copperlist::*;
+    pub use cu29_runtime::cu_quantity;
     pub use cu29_runtime::curuntime::*;
     pub use cu29_runtime::cutask::*;
+    pub use cu29_runtime::events::*;
+    pub use cu29_runtime::fixed::*;
     pub use cu29_runtime::input_msg;
     pub use cu29_runtime::monitoring::*;
     pub use cu29_runtime::output_msg;
@@ -0,0 +1,73 @@
+/*
+ * Copper C ABI: exposes a driver written in C or C++ as a Copper task.
+ *
+ * The driver fills a cu_capi_task_vtable and registers it under a name with cu_capi_register_task
+ * before the Copper application is created. The task is then instantiated from the RON config with
+ * one of the generic Rust tasks of cu29-capi (CApiSource, CApiTask or CApiSink) and the
+ * "ctask": "<name>" config entry.
+ *
+ * The payloads cross the boundary encoded with bincode's legacy configuration: little endian,
+ * fixed size integers, so a Rust struct of numbers is laid out like the equivalent packed C struct.
+ */
+#ifndef CU29_CAPI_H
+#define CU29_CAPI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CU_CAPI_ABI_VERSION 1
+
+/* Time of validity of a message in nanoseconds of the robot clock. */
+#define CU_CAPI_TOV_NONE UINT64_MAX
+
+/* A message given to the driver, data is NULL and len 0 if the message has no payload. */
+typedef struct {
+    const uint8_t *data;
+    size_t len;
+    uint64_t tov_ns;
+} cu_capi_input;
+
+/*
+ * A message the driver fills: data points to a buffer of capacity bytes owned by Copper.
+ * Set present to 0 to emit a message without payload.
+ * Sources can leave tov_ns to CU_CAPI_TOV_NONE to be stamped with the current time.
+ */
+typedef struct {
+    uint8_t *data;
+    size_t capacity;
+    size_t len;
+    uint8_t present;
+    uint64_t tov_ns;
+} cu_capi_output;
+
+/* All the callbacks returning an int32_t return 0 on success. */
+typedef struct {
+    /* Must be CU_CAPI_ABI_VERSION. */
+    uint32_t abi_version;
+    /* The name referenced by the "ctask" entry of the configuration. */
+    const char *name;
+    /* Creates an instance from its configuration given as "key=value" lines, NULL on failure. */
+    void *(*create)(const char *config);
+    /* Optional. */
+    int32_t (*start)(void *state);
+    /* input is NULL for the sources, output is NULL for the sinks. */
+    int32_t (*process)(void *state, const cu_capi_input *input, cu_capi_output *output);
+    /* Optional. */
+    int32_t (*stop)(void *state);
+    void (*destroy)(void *state);
+    /* Optional, describes the last error of the instance. */
+    const char *(*last_error)(void *state);
+} cu_capi_task_vtable;
+
+/* Registers a driver, the vtable is copied. Returns 0 on success. */
+int32_t cu_capi_register_task(const cu_capi_task_vtable *vtable);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CU29_CAPI_H */
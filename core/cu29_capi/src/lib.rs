@@ -0,0 +1,513 @@
+//! A stable C ABI to expose drivers written in C or C++ (typically vendor SDKs) as Copper tasks.
+//!
+//! The C side is described in `include/cu29_capi.h`: a driver registers a table of callbacks
+//! ([CuCapiTaskVTable]) under a name, and the generic tasks of this crate ([CApiSource], [CApiTask]
+//! and [CApiSink]) bind to it by that name from the configuration:
+//!
+//! ```ron
+//! (
+//!     id: "lidar",
+//!     type: "drivers::VendorLidar", // pub type VendorLidar = CApiSource<LidarPayload>;
+//!     config: { "ctask": "vendor_lidar", "ip": "192.168.1.201" },
+//! )
+//! ```
+//!
+//! The Rust side owns the lifecycle (create, start, process, stop, destroy) and the marshaling of
+//! the messages: payloads are encoded with the bincode legacy configuration (little endian, fixed
+//! size integers) so a struct of numbers has the layout of the equivalent packed C struct.
+
+use bincode::config::legacy;
+use bincode::{decode_from_slice, encode_to_vec};
+use cu29::prelude::*;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::marker::PhantomData;
+use std::sync::{Mutex, OnceLock};
+
+/// Has to match CU_CAPI_ABI_VERSION in the C header.
+pub const CU_CAPI_ABI_VERSION: u32 = 1;
+
+/// Default size of the buffer given to the drivers for their output.
+const DEFAULT_MAX_OUTPUT_SIZE: usize = 64 * 1024;
+
+/// A message given to a driver.
+#[repr(C)]
+pub struct CuCapiInput {
+    pub data: *const u8,
+    pub len: usize,
+    pub tov_ns: u64,
+}
+
+/// A message filled by a driver.
+#[repr(C)]
+pub struct CuCapiOutput {
+    pub data: *mut u8,
+    pub capacity: usize,
+    pub len: usize,
+    pub present: u8,
+    pub tov_ns: u64,
+}
+
+/// The table of callbacks of a driver, see `include/cu29_capi.h`.
+#[repr(C)]
+pub struct CuCapiTaskVTable {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub create: Option<unsafe extern "C" fn(config: *const c_char) -> *mut c_void>,
+    pub start: Option<unsafe extern "C" fn(state: *mut c_void) -> i32>,
+    pub process: Option<
+        unsafe extern "C" fn(
+            state: *mut c_void,
+            input: *const CuCapiInput,
+            output: *mut CuCapiOutput,
+        ) -> i32,
+    >,
+    pub stop: Option<unsafe extern "C" fn(state: *mut c_void) -> i32>,
+    pub destroy: Option<unsafe extern "C" fn(state: *mut c_void)>,
+    pub last_error: Option<unsafe extern "C" fn(state: *mut c_void) -> *const c_char>,
+}
+
+/// The callbacks of a registered driver.
+#[derive(Clone, Copy)]
+struct RegisteredTask {
+    create: unsafe extern "C" fn(config: *const c_char) -> *mut c_void,
+    start: Option<unsafe extern "C" fn(state: *mut c_void) -> i32>,
+    process: unsafe extern "C" fn(
+        state: *mut c_void,
+        input: *const CuCapiInput,
+        output: *mut CuCapiOutput,
+    ) -> i32,
+    stop: Option<unsafe extern "C" fn(state: *mut c_void) -> i32>,
+    destroy: unsafe extern "C" fn(state: *mut c_void),
+    last_error: Option<unsafe extern "C" fn(state: *mut c_void) -> *const c_char>,
+}
+
+static REGISTRY: OnceLock<Mutex<HashMap<String, RegisteredTask>>> = OnceLock::new();
+
+fn registry() -> &'static Mutex<HashMap<String, RegisteredTask>> {
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Registers a driver, this is the Rust counterpart of `cu_capi_register_task`.
+///
+/// # Safety
+/// The name must be a valid C string and the callbacks must follow the contract of the C header.
+pub unsafe fn register_task(vtable: &CuCapiTaskVTable) -> CuResult<()> {
+    if vtable.abi_version != CU_CAPI_ABI_VERSION {
+        return Err(CuError::from(format!(
+            "The C task was built for the ABI version {}, this runtime implements the version {CU_CAPI_ABI_VERSION}.",
+            vtable.abi_version
+        )));
+    }
+    if vtable.name.is_null() {
+        return Err(CuError::from("A C task needs a name."));
+    }
+    let name = CStr::from_ptr(vtable.name).to_string_lossy().into_owned();
+    let (Some(create), Some(process), Some(destroy)) =
+        (vtable.create, vtable.process, vtable.destroy)
+    else {
+        return Err(CuError::from(format!(
+            "The C task {name} needs at least the create, process and destroy callbacks."
+        )));
+    };
+    registry().lock().unwrap().insert(
+        name,
+        RegisteredTask {
+            create,
+            start: vtable.start,
+            process,
+            stop: vtable.stop,
+            destroy,
+            last_error: vtable.last_error,
+        },
+    );
+    Ok(())
+}
+
+/// The C entry point to register a driver, returns 0 on success.
+///
+/// # Safety
+/// See [register_task].
+#[no_mangle]
+pub unsafe extern "C" fn cu_capi_register_task(vtable: *const CuCapiTaskVTable) -> i32 {
+    match vtable.as_ref() {
+        Some(vtable) => match register_task(vtable) {
+            Ok(()) => 0,
+            Err(_) => -1,
+        },
+        None => -1,
+    }
+}
+
+fn tov_to_ns(tov: &Tov) -> u64 {
+    match tov {
+        Tov::Time(time) => time.0,
+        Tov::Range(range) => range.start.0,
+        Tov::None => u64::MAX,
+    }
+}
+
+/// An instance of a driver and the buffer it writes its output in.
+struct CApiInstance {
+    name: String,
+    task: RegisteredTask,
+    state: *mut c_void,
+    output_buffer: Vec<u8>,
+}
+
+// The instances are only ever used by one thread at a time, the drivers have to be movable
+// across threads like any other task.
+unsafe impl Send for CApiInstance {}
+
+impl CApiInstance {
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self> {
+        let config = config.ok_or_else(|| CuError::from("A C task needs a config."))?;
+        let name = config.get::<String>("ctask").ok_or_else(|| {
+            CuError::from("A C task needs the \"ctask\" entry naming its driver.")
+        })?;
+        let task = *registry().lock().unwrap().get(&name).ok_or_else(|| {
+            CuError::from(format!(
+                "No C task registered under the name {name}, call cu_capi_register_task first."
+            ))
+        })?;
+        let max_output_size = config
+            .get::<u32>("max_output_size")
+            .map(|size| size as usize)
+            .unwrap_or(DEFAULT_MAX_OUTPUT_SIZE);
+
+        // The driver receives the rest of its config as sorted "key=value" lines.
+        let mut lines: Vec<String> = config
+            .0
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "ctask" | "max_output_size"))
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        lines.sort();
+        let driver_config = CString::new(lines.join("\n"))
+            .map_err(|_| CuError::from("The config of a C task cannot contain a NUL character."))?;
+
+        let state = unsafe { (task.create)(driver_config.as_ptr()) };
+        if state.is_null() {
+            return Err(CuError::from(format!(
+                "The C task {name} failed to create its instance."
+            )));
+        }
+        Ok(CApiInstance {
+            name,
+            task,
+            state,
+            output_buffer: vec![0u8; max_output_size],
+        })
+    }
+
+    fn check(&self, callback: &str, code: i32) -> CuResult<()> {
+        if code == 0 {
+            return Ok(());
+        }
+        let detail = self
+            .task
+            .last_error
+            .map(|last_error| unsafe { last_error(self.state) })
+            .filter(|message| !message.is_null())
+            .map(|message| {
+                unsafe { CStr::from_ptr(message) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .unwrap_or_default();
+        Err(CuError::from(format!(
+            "The C task {} failed in {callback} with the code {code}. {detail}",
+            self.name
+        )))
+    }
+
+    fn start(&mut self) -> CuResult<()> {
+        match self.task.start {
+            Some(start) => self.check("start", unsafe { start(self.state) }),
+            None => Ok(()),
+        }
+    }
+
+    fn stop(&mut self) -> CuResult<()> {
+        match self.task.stop {
+            Some(stop) => self.check("stop", unsafe { stop(self.state) }),
+            None => Ok(()),
+        }
+    }
+
+    fn process<I: CuMsgPayload, O: CuMsgPayload>(
+        &mut self,
+        clock: &RobotClock,
+        input: Option<&CuMsg<I>>,
+        output: Option<&mut CuMsg<O>>,
+    ) -> CuResult<()> {
+        let encoded_input = match input.and_then(|msg| msg.payload()) {
+            Some(payload) => Some(encode_to_vec(payload, legacy()).map_err(|e| {
+                CuError::new_with_cause("Could not encode the input of a C task", e)
+            })?),
+            None => None,
+        };
+        let c_input = input.map(|msg| CuCapiInput {
+            data: encoded_input
+                .as_ref()
+                .map_or(std::ptr::null(), |encoded| encoded.as_ptr()),
+            len: encoded_input.as_ref().map_or(0, |encoded| encoded.len()),
+            tov_ns: tov_to_ns(&msg.metadata.tov),
+        });
+        let mut c_output = CuCapiOutput {
+            data: self.output_buffer.as_mut_ptr(),
+            capacity: self.output_buffer.len(),
+            len: 0,
+            present: 0,
+            tov_ns: u64::MAX,
+        };
+        let input_ptr = c_input
+            .as_ref()
+            .map_or(std::ptr::null(), |input| input as *const CuCapiInput);
+        let output_ptr = if output.is_some() {
+            &mut c_output as *mut CuCapiOutput
+        } else {
+            std::ptr::null_mut()
+        };
+        let code = unsafe { (self.task.process)(self.state, input_ptr, output_ptr) };
+        self.check("process", code)?;
+
+        let Some(output) = output else {
+            return Ok(());
+        };
+        if c_output.present == 0 {
+            output.clear_payload();
+        } else {
+            if c_output.len > self.output_buffer.len() {
+                return Err(CuError::from(format!(
+                    "The C task {} wrote {} bytes in a {} bytes buffer.",
+                    self.name,
+                    c_output.len,
+                    self.output_buffer.len()
+                )));
+            }
+            let (payload, _): (O, usize) =
+                decode_from_slice(&self.output_buffer[..c_output.len], legacy()).map_err(|e| {
+                    CuError::new_with_cause("Could not decode the output of a C task", e)
+                })?;
+            output.set_payload(payload);
+        }
+        output.metadata.tov = match c_output.tov_ns {
+            u64::MAX => match input {
+                Some(msg) => msg.metadata.tov,
+                None => Tov::Time(clock.now()),
+            },
+            ns => Tov::Time(CuDuration(ns)),
+        };
+        Ok(())
+    }
+}
+
+impl Drop for CApiInstance {
+    fn drop(&mut self) {
+        unsafe { (self.task.destroy)(self.state) };
+    }
+}
+
+/// A source implemented by a C driver, its process callback receives a NULL input.
+pub struct CApiSource<O> {
+    instance: CApiInstance,
+    _payload: PhantomData<fn() -> O>,
+}
+
+impl<O> Freezable for CApiSource<O> {}
+
+impl<'cl, O: CuMsgPayload + 'cl> CuSrcTask<'cl> for CApiSource<O> {
+    type Output = output_msg!('cl, O);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            instance: CApiInstance::new(config)?,
+            _payload: PhantomData,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.instance.start()
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        self.instance.process::<(), O>(clock, None, Some(new_msg))
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.instance.stop()
+    }
+}
+
+/// A task implemented by a C driver.
+pub struct CApiTask<I, O> {
+    instance: CApiInstance,
+    _payloads: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O> Freezable for CApiTask<I, O> {}
+
+impl<'cl, I: CuMsgPayload + 'cl, O: CuMsgPayload + 'cl> CuTask<'cl> for CApiTask<I, O> {
+    type Input = input_msg!('cl, I);
+    type Output = output_msg!('cl, O);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            instance: CApiInstance::new(config)?,
+            _payloads: PhantomData,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.instance.start()
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        self.instance.process(clock, Some(input), Some(output))
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.instance.stop()
+    }
+}
+
+/// A sink implemented by a C driver, its process callback receives a NULL output.
+pub struct CApiSink<I> {
+    instance: CApiInstance,
+    _payload: PhantomData<fn(I)>,
+}
+
+impl<I> Freezable for CApiSink<I> {}
+
+impl<'cl, I: CuMsgPayload + 'cl> CuSinkTask<'cl> for CApiSink<I> {
+    type Input = input_msg!('cl, I);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            instance: CApiInstance::new(config)?,
+            _payload: PhantomData,
+        })
+    }
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.instance.start()
+    }
+
+    fn process(&mut self, clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        self.instance.process::<I, ()>(clock, Some(input), None)
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        self.instance.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A driver doubling the u32 it receives, written against the C ABI.
+    struct Doubler {
+        started: bool,
+    }
+
+    unsafe extern "C" fn create(_config: *const c_char) -> *mut c_void {
+        Box::into_raw(Box::new(Doubler { started: false })) as *mut c_void
+    }
+
+    unsafe extern "C" fn start(state: *mut c_void) -> i32 {
+        (*(state as *mut Doubler)).started = true;
+        0
+    }
+
+    unsafe extern "C" fn process(
+        state: *mut c_void,
+        input: *const CuCapiInput,
+        output: *mut CuCapiOutput,
+    ) -> i32 {
+        if !(*(state as *mut Doubler)).started {
+            return 1;
+        }
+        let (input, output) = (&*input, &mut *output);
+        if input.data.is_null() {
+            output.present = 0;
+            return 0;
+        }
+        let value = u32::from_le_bytes(*(input.data as *const [u8; 4]));
+        std::ptr::copy_nonoverlapping((value * 2).to_le_bytes().as_ptr(), output.data, 4);
+        output.len = 4;
+        output.present = 1;
+        0
+    }
+
+    unsafe extern "C" fn destroy(state: *mut c_void) {
+        drop(Box::from_raw(state as *mut Doubler));
+    }
+
+    #[test]
+    fn test_c_task() {
+        let name = CString::new("doubler").unwrap();
+        let vtable = CuCapiTaskVTable {
+            abi_version: CU_CAPI_ABI_VERSION,
+            name: name.as_ptr(),
+            create: Some(create),
+            start: Some(start),
+            process: Some(process),
+            stop: None,
+            destroy: Some(destroy),
+            last_error: None,
+        };
+        assert_eq!(unsafe { cu_capi_register_task(&vtable) }, 0);
+
+        let mut config = ComponentConfig::new();
+        config.set("ctask", "doubler".to_string());
+        let mut task = CApiTask::<u32, u32>::new(Some(&config)).unwrap();
+        let clock = RobotClock::new();
+        let mut output = CuMsg::<u32>::new(None);
+
+        // not started yet, the driver fails.
+        assert!(task
+            .process(&clock, &CuMsg::new(Some(21)), &mut output)
+            .is_err());
+
+        task.start(&clock).unwrap();
+        task.process(&clock, &CuMsg::new(Some(21)), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), Some(&42));
+
+        task.process(&clock, &CuMsg::new(None), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), None);
+    }
+
+    #[test]
+    fn test_abi_version_mismatch() {
+        let name = CString::new("future").unwrap();
+        let vtable = CuCapiTaskVTable {
+            abi_version: CU_CAPI_ABI_VERSION + 1,
+            name: name.as_ptr(),
+            create: Some(create),
+            start: None,
+            process: Some(process),
+            stop: None,
+            destroy: Some(destroy),
+            last_error: None,
+        };
+        assert!(unsafe { register_task(&vtable) }.is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! Slaves a [`crate::RobotClock`] to an external time base, either a PTP hardware clock (PHC)
+//! or the system CLOCK_TAI, so the tov of the messages line up with hardware-timestamped sensors.
+//!
+//! The RobotClock keeps reading its fast local monotonic clock and adds a tracked offset to it.
+//! Each resync measures the offset against the external clock: small errors are slewed in to absorb
+//! the drift, large ones (a PTP grandmaster change, a first lock) are stepped. The time read never
+//! goes backwards: after a correction of the offset to the past, it holds until the clock catches up.
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+
+/// Errors above this are stepped instead of slewed.
+const STEP_THRESHOLD_NS: i64 = 1_000_000;
+
+/// Number of reads done to measure the offset, the tightest one wins.
+const SAMPLES: usize = 5;
+
+/// The time base a RobotClock runs on.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ClockSource {
+    /// A private monotonic clock starting at 0, the default.
+    #[default]
+    Monotonic,
+    /// The system CLOCK_TAI, usually kept in sync with PTP by phc2sys.
+    Tai,
+    /// A PTP hardware clock, ie. /dev/ptp0 on the NIC the sensors are connected to.
+    Phc(PathBuf),
+}
+
+impl FromStr for ClockSource {
+    type Err = String;
+
+    /// Parses "monotonic", "tai" or a PHC device as "/dev/ptp0" or "phc:/dev/ptp0".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monotonic" => Ok(ClockSource::Monotonic),
+            "tai" => Ok(ClockSource::Tai),
+            _ => {
+                let path = s.strip_prefix("phc:").unwrap_or(s);
+                if path.starts_with('/') {
+                    Ok(ClockSource::Phc(PathBuf::from(path)))
+                } else {
+                    Err(format!(
+                        "Unknown clock source \"{s}\", expected monotonic, tai or a PHC device like /dev/ptp0."
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Display for ClockSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClockSource::Monotonic => write!(f, "monotonic"),
+            ClockSource::Tai => write!(f, "tai"),
+            ClockSource::Phc(path) => write!(f, "phc:{}", path.display()),
+        }
+    }
+}
+
+/// The shared state of a disciplined clock, all the clones of a RobotClock point to the same one.
+#[derive(Debug)]
+pub(crate) struct Discipline {
+    source: ExternalClock,
+    /// external - local in ns.
+    offset: AtomicI64,
+    locked: AtomicBool,
+    /// The latest time read, in ns.
+    last: AtomicU64,
+}
+
+impl Discipline {
+    pub(crate) fn open(source: &ClockSource) -> io::Result<Self> {
+        Ok(Discipline {
+            source: ExternalClock::open(source)?,
+            offset: AtomicI64::new(0),
+            locked: AtomicBool::new(false),
+            last: AtomicU64::new(0),
+        })
+    }
+
+    /// The external time at `local` (in ns), never before a time read earlier.
+    #[inline]
+    pub(crate) fn now(&self, local: u64) -> u64 {
+        let now = local.wrapping_add_signed(self.offset());
+        self.last.fetch_max(now, Ordering::Relaxed).max(now)
+    }
+
+    #[inline]
+    pub(crate) fn offset(&self) -> i64 {
+        self.offset.load(Ordering::Relaxed)
+    }
+
+    /// Measures the offset between the external clock and `local` (in ns) and folds it in.
+    /// Returns the error that was measured before the correction.
+    pub(crate) fn resync(&self, local: impl Fn() -> u64) -> io::Result<i64> {
+        let mut best: Option<(u64, i64)> = None;
+        for _ in 0..SAMPLES {
+            let before = local();
+            let external = self.source.now()?;
+            let after = local();
+            let width = after.saturating_sub(before);
+            let midpoint = before + width / 2;
+            let measured = external as i64 - midpoint as i64;
+            if best.is_none_or(|(best_width, _)| width < best_width) {
+                best = Some((width, measured));
+            }
+        }
+        let (_, measured) = best.expect("at least one sample");
+        Ok(self.update(measured))
+    }
+
+    /// Steps to the first measure and to large errors, slews half of the small ones.
+    fn update(&self, measured: i64) -> i64 {
+        let current = self.offset();
+        let error = measured - current;
+        if !self.locked.swap(true, Ordering::Relaxed) || error.abs() > STEP_THRESHOLD_NS {
+            self.offset.store(measured, Ordering::Relaxed);
+        } else {
+            self.offset.store(current + error / 2, Ordering::Relaxed);
+        }
+        error
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct ExternalClock {
+    clockid: libc::clockid_t,
+    // Keeps the PHC file descriptor open, the clockid is derived from it.
+    _device: Option<std::fs::File>,
+}
+
+#[cfg(target_os = "linux")]
+impl ExternalClock {
+    fn open(source: &ClockSource) -> io::Result<Self> {
+        use std::os::fd::AsRawFd;
+        match source {
+            ClockSource::Monotonic => Ok(ExternalClock {
+                clockid: libc::CLOCK_MONOTONIC,
+                _device: None,
+            }),
+            ClockSource::Tai => Ok(ExternalClock {
+                clockid: libc::CLOCK_TAI,
+                _device: None,
+            }),
+            ClockSource::Phc(path) => {
+                let device = std::fs::File::open(path)?;
+                // FD_TO_CLOCKID from the kernel's posix-timers.
+                let clockid = ((!device.as_raw_fd()) << 3) | 3;
+                let clock = ExternalClock {
+                    clockid,
+                    _device: Some(device),
+                };
+                // Fails early if the device is not a PHC.
+                clock.now()?;
+                Ok(clock)
+            }
+        }
+    }
+
+    fn now(&self) -> io::Result<u64> {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: ts is a valid timespec and the clockid is either a static one or backed by an open fd.
+        if unsafe { libc::clock_gettime(self.clockid, &mut ts) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug)]
+struct ExternalClock;
+
+#[cfg(not(target_os = "linux"))]
+impl ExternalClock {
+    fn open(source: &ClockSource) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("The clock source {source} is only supported on Linux."),
+        ))
+    }
+
+    fn now(&self) -> io::Result<u64> {
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!("tai".parse(), Ok(ClockSource::Tai));
+        assert_eq!("monotonic".parse(), Ok(ClockSource::Monotonic));
+        assert_eq!(
+            "/dev/ptp1".parse(),
+            Ok(ClockSource::Phc(PathBuf::from("/dev/ptp1")))
+        );
+        assert_eq!(
+            "phc:/dev/ptp0".parse::<ClockSource>().unwrap().to_string(),
+            "phc:/dev/ptp0"
+        );
+        assert!("gps".parse::<ClockSource>().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_step_then_slew() {
+        let discipline = Discipline::open(&ClockSource::Monotonic).unwrap();
+        assert_eq!(discipline.update(5_000_000_000), 5_000_000_000);
+        assert_eq!(discipline.offset(), 5_000_000_000);
+        // small drift is slewed in
+        assert_eq!(discipline.update(5_000_000_400), 400);
+        assert_eq!(discipline.offset(), 5_000_000_200);
+        // a jump is stepped
+        assert_eq!(discipline.update(7_000_000_000), 1_999_999_800);
+        assert_eq!(discipline.offset(), 7_000_000_000);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_monotonic() {
+        let discipline = Discipline::open(&ClockSource::Monotonic).unwrap();
+        discipline.update(5_000_000_000);
+        assert_eq!(discipline.now(1_000), 5_000_001_000);
+        // Stepped 2s back: the time holds until the local clock caught up.
+        discipline.update(3_000_000_000);
+        assert_eq!(discipline.now(2_000), 5_000_001_000);
+        assert_eq!(discipline.now(2_000_001_000), 5_000_001_000);
+        assert_eq!(discipline.now(2_000_002_000), 5_000_002_000);
+        // A slew to the past too.
+        discipline.update(2_999_999_000);
+        assert_eq!(discipline.now(2_000_002_200), 5_000_002_000);
+    }
+}
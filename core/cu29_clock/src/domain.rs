@@ -0,0 +1,366 @@
+//! Clock domains: the time bases of the subsystems that don't run on the [`crate::RobotClock`],
+//! like a GPS-disciplined lidar or a free-running microcontroller.
+//!
+//! A [`DomainTime`] is a time tagged with its domain at the type level: times of the same domain
+//! can be compared and subtracted, times of different domains can't, so fusing mismatched
+//! timestamps is a compile error instead of a silent skew. Going from a domain to another is
+//! explicit, through a [`DomainConversion`].
+//!
+//! ```
+//! use cu29_clock::{clock_domain, CuDuration, DomainConversion, DomainTime, RobotDomain};
+//!
+//! clock_domain!(Lidar, "lidar");
+//!
+//! // The lidar time 1s was the robot time 5s, the lidar clock runs 100ppm fast.
+//! let to_robot = DomainConversion::<Lidar, RobotDomain>::new(
+//!     DomainTime::new(CuDuration(1_000_000_000)),
+//!     DomainTime::new(CuDuration(5_000_000_000)),
+//!     1.0 / 1.0001,
+//! );
+//! let scan: DomainTime<Lidar> = DomainTime::new(CuDuration(1_000_100_000));
+//! assert_eq!(to_robot.convert(scan).time(), CuDuration(5_000_100_000 - 10));
+//! ```
+use crate::{CuDuration, CuTime};
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{BorrowDecode, Decode, Encode};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Add, Sub};
+
+/// A time base, declared with [`crate::clock_domain`].
+/// The name is the one the connections of the configuration are tagged with.
+pub trait ClockDomain: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+/// Declares a clock domain type with its name in the configuration.
+///
+/// ```
+/// cu29_clock::clock_domain!(Gps, "gps");
+/// ```
+#[macro_export]
+macro_rules! clock_domain {
+    ($(#[$meta:meta])* $domain:ident, $name:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct $domain;
+
+        impl $crate::ClockDomain for $domain {
+            const NAME: &'static str = $name;
+        }
+    };
+}
+
+clock_domain!(
+    /// The domain of the [`crate::RobotClock`], the default one of the connections.
+    RobotDomain,
+    "robot"
+);
+
+/// A time in the domain `D`.
+pub struct DomainTime<D: ClockDomain> {
+    time: CuTime,
+    _domain: PhantomData<D>,
+}
+
+impl<D: ClockDomain> DomainTime<D> {
+    pub const fn new(time: CuTime) -> Self {
+        Self {
+            time,
+            _domain: PhantomData,
+        }
+    }
+
+    /// The time without its domain.
+    pub const fn time(&self) -> CuTime {
+        self.time
+    }
+
+    pub fn domain(&self) -> &'static str {
+        D::NAME
+    }
+}
+
+impl From<CuTime> for DomainTime<RobotDomain> {
+    fn from(time: CuTime) -> Self {
+        Self::new(time)
+    }
+}
+
+impl From<DomainTime<RobotDomain>> for CuTime {
+    fn from(time: DomainTime<RobotDomain>) -> Self {
+        time.time
+    }
+}
+
+// The traits are implemented by hand: deriving them would require them from the domain markers.
+impl<D: ClockDomain> Clone for DomainTime<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D: ClockDomain> Copy for DomainTime<D> {}
+
+impl<D: ClockDomain> Default for DomainTime<D> {
+    fn default() -> Self {
+        Self::new(CuDuration::default())
+    }
+}
+
+impl<D: ClockDomain> PartialEq for DomainTime<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.time == other.time
+    }
+}
+
+impl<D: ClockDomain> Eq for DomainTime<D> {}
+
+impl<D: ClockDomain> PartialOrd for DomainTime<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<D: ClockDomain> Ord for DomainTime<D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time.cmp(&other.time)
+    }
+}
+
+impl<D: ClockDomain> Hash for DomainTime<D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.time.0.hash(state);
+    }
+}
+
+impl<D: ClockDomain> Debug for DomainTime<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DomainTime<{}>({})", D::NAME, self.time.0)
+    }
+}
+
+impl<D: ClockDomain> Display for DomainTime<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.time, D::NAME)
+    }
+}
+
+impl<D: ClockDomain> Sub for DomainTime<D> {
+    type Output = CuDuration;
+
+    fn sub(self, rhs: Self) -> CuDuration {
+        self.time - rhs.time
+    }
+}
+
+impl<D: ClockDomain> Add<CuDuration> for DomainTime<D> {
+    type Output = Self;
+
+    fn add(self, rhs: CuDuration) -> Self {
+        Self::new(self.time + rhs)
+    }
+}
+
+impl<D: ClockDomain> Sub<CuDuration> for DomainTime<D> {
+    type Output = Self;
+
+    fn sub(self, rhs: CuDuration) -> Self {
+        Self::new(self.time - rhs)
+    }
+}
+
+impl<D: ClockDomain> Encode for DomainTime<D> {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.time.encode(encoder)
+    }
+}
+
+impl<D: ClockDomain, Context> Decode<Context> for DomainTime<D> {
+    fn decode<De: Decoder<Context = Context>>(decoder: &mut De) -> Result<Self, DecodeError> {
+        Ok(Self::new(CuDuration(u64::decode(decoder)?)))
+    }
+}
+
+impl<'de, D: ClockDomain, Context> BorrowDecode<'de, Context> for DomainTime<D> {
+    fn borrow_decode<De: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut De,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self::new(CuDuration(u64::decode(decoder)?)))
+    }
+}
+
+impl<D: ClockDomain> Serialize for DomainTime<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.time.serialize(serializer)
+    }
+}
+
+impl<'de, D: ClockDomain> Deserialize<'de> for DomainTime<D> {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        CuDuration::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// The conversion of the times of the domain `F` to the domain `T`: a linear map going through
+/// a pair of reference times, one in each domain, with the rate of `T` relative to `F`
+/// (1 for a plain offset, slightly off 1 to compensate a drift).
+pub struct DomainConversion<F: ClockDomain, T: ClockDomain> {
+    from_ref: CuTime,
+    to_ref: CuTime,
+    rate: f64,
+    _domains: PhantomData<(F, T)>,
+}
+
+impl<F: ClockDomain, T: ClockDomain> DomainConversion<F, T> {
+    /// `from_ref` and `to_ref` are the same instant seen from both domains, `rate` is how much
+    /// time passes in `T` for a nanosecond in `F`.
+    pub fn new(from_ref: DomainTime<F>, to_ref: DomainTime<T>, rate: f64) -> Self {
+        Self {
+            from_ref: from_ref.time,
+            to_ref: to_ref.time,
+            rate,
+            _domains: PhantomData,
+        }
+    }
+
+    /// A conversion without drift between the domains.
+    pub fn offset(from_ref: DomainTime<F>, to_ref: DomainTime<T>) -> Self {
+        Self::new(from_ref, to_ref, 1.0)
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// The time of `T` at the instant `time` of `F`, saturating at the bounds of [`CuTime`].
+    pub fn convert(&self, time: DomainTime<F>) -> DomainTime<T> {
+        let delta = time.time.0 as i128 - self.from_ref.0 as i128;
+        let converted = self.to_ref.0 as i128 + (delta as f64 * self.rate).round() as i128;
+        DomainTime::new(CuDuration(
+            converted.clamp(0, CuDuration::MAX.0 as i128) as u64
+        ))
+    }
+
+    /// The length in `T` of a duration measured in `F`.
+    pub fn convert_duration(&self, duration: CuDuration) -> CuDuration {
+        CuDuration((duration.0 as f64 * self.rate).round() as u64)
+    }
+
+    /// The conversion the other way around.
+    pub fn inverse(&self) -> DomainConversion<T, F> {
+        DomainConversion {
+            from_ref: self.to_ref,
+            to_ref: self.from_ref,
+            rate: 1.0 / self.rate,
+            _domains: PhantomData,
+        }
+    }
+
+    /// Chains this conversion with one from `T` to a third domain.
+    pub fn then<U: ClockDomain>(&self, next: &DomainConversion<T, U>) -> DomainConversion<F, U> {
+        DomainConversion {
+            from_ref: self.from_ref,
+            to_ref: next
+                .convert(self.convert(DomainTime::new(self.from_ref)))
+                .time,
+            rate: self.rate * next.rate,
+            _domains: PhantomData,
+        }
+    }
+}
+
+impl<F: ClockDomain, T: ClockDomain> Clone for DomainConversion<F, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: ClockDomain, T: ClockDomain> Copy for DomainConversion<F, T> {}
+
+impl<F: ClockDomain, T: ClockDomain> Debug for DomainConversion<F, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "DomainConversion<{}, {}>({} -> {}, rate {})",
+            F::NAME,
+            T::NAME,
+            self.from_ref.0,
+            self.to_ref.0,
+            self.rate
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    clock_domain!(Gps, "gps");
+    clock_domain!(Mcu, "mcu");
+
+    #[test]
+    fn test_domain_time() {
+        let a = DomainTime::<Gps>::new(CuDuration(100));
+        let b = a + CuDuration(50);
+        assert!(b > a);
+        assert_eq!(b - a, CuDuration(50));
+        assert_eq!(a.domain(), "gps");
+        assert_eq!(format!("{a:?}"), "DomainTime<gps>(100)");
+
+        let encoded = bincode::encode_to_vec(b, bincode::config::standard()).unwrap();
+        let (decoded, _): (DomainTime<Gps>, usize) =
+            bincode::decode_from_slice(&encoded, bincode::config::standard()).unwrap();
+        assert_eq!(decoded, b);
+
+        let robot: DomainTime<RobotDomain> = CuDuration(7).into();
+        assert_eq!(CuTime::from(robot), CuDuration(7));
+    }
+
+    #[test]
+    fn test_domain_conversion() {
+        let mcu_to_gps = DomainConversion::<Mcu, Gps>::new(
+            DomainTime::new(CuDuration(1_000_000)),
+            DomainTime::new(CuDuration(500_000_000)),
+            2.0,
+        );
+        let gps = mcu_to_gps.convert(DomainTime::new(CuDuration(1_001_000)));
+        assert_eq!(gps.time(), CuDuration(500_002_000));
+        assert_eq!(
+            mcu_to_gps.convert(DomainTime::new(CuDuration(0))).time(),
+            CuDuration(498_000_000)
+        );
+        assert_eq!(
+            mcu_to_gps.inverse().convert(gps).time(),
+            CuDuration(1_001_000)
+        );
+        assert_eq!(mcu_to_gps.convert_duration(CuDuration(10)), CuDuration(20));
+
+        // Before the start of the target domain.
+        let early = DomainConversion::<Mcu, Gps>::offset(
+            DomainTime::new(CuDuration(1_000)),
+            DomainTime::new(CuDuration(0)),
+        );
+        assert_eq!(
+            early.convert(DomainTime::new(CuDuration(10))).time(),
+            CuDuration(0)
+        );
+
+        let gps_to_robot = DomainConversion::<Gps, RobotDomain>::offset(
+            DomainTime::new(CuDuration(500_000_000)),
+            DomainTime::new(CuDuration(0)),
+        );
+        let mcu_to_robot = mcu_to_gps.then(&gps_to_robot);
+        assert_eq!(
+            mcu_to_robot
+                .convert(DomainTime::new(CuDuration(1_001_000)))
+                .time(),
+            CuDuration(2_000)
+        );
+    }
+}
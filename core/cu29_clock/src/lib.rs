@@ -1,6 +1,9 @@
 #[cfg(test)]
 #[macro_use]
 extern crate approx;
+mod discipline;
+mod domain;
+
 use bincode::de::BorrowDecoder;
 use bincode::de::Decoder;
 use bincode::enc::Encoder;
@@ -8,13 +11,18 @@ use bincode::error::{DecodeError, EncodeError};
 use bincode::BorrowDecode;
 use bincode::{Decode, Encode};
 use core::ops::{Add, Sub};
+pub use discipline::ClockSource;
+use discipline::Discipline;
+pub use domain::{ClockDomain, DomainConversion, DomainTime, RobotDomain};
 pub use quanta::Instant;
 use quanta::{Clock, Mock};
 use serde::{Deserialize, Serialize};
 use std::convert::Into;
 use std::fmt::{Display, Formatter};
+use std::io;
 use std::ops::{AddAssign, Div, Mul, SubAssign};
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 /// For Robot times, the underlying type is a u64 representing nanoseconds.
@@ -39,10 +47,90 @@ impl CuDuration {
         CuDuration(lhs.min(rhs))
     }
 
+    pub const ZERO: CuDuration = CuDuration(0u64);
+
+    pub const fn from_nanos(nanos: u64) -> CuDuration {
+        CuDuration(nanos)
+    }
+
+    pub const fn from_micros(micros: u64) -> CuDuration {
+        CuDuration(micros * 1_000)
+    }
+
+    pub const fn from_millis(millis: u64) -> CuDuration {
+        CuDuration(millis * 1_000_000)
+    }
+
+    pub const fn from_secs(secs: u64) -> CuDuration {
+        CuDuration(secs * 1_000_000_000)
+    }
+
+    /// Saturates at 0 for negative values and at [CuDuration::MAX].
+    pub fn from_secs_f64(secs: f64) -> CuDuration {
+        CuDuration((secs * 1e9).round().clamp(0.0, Self::MAX.0 as f64) as u64)
+    }
+
     pub fn as_nanos(&self) -> u64 {
         let Self(nanos) = self;
         *nanos
     }
+
+    pub fn as_micros(&self) -> u64 {
+        self.0 / 1_000
+    }
+
+    pub fn as_millis(&self) -> u64 {
+        self.0 / 1_000_000
+    }
+
+    pub fn as_secs(&self) -> u64 {
+        self.0 / 1_000_000_000
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.0 as f64 / 1e9
+    }
+
+    /// None on overflow or if the result goes past [CuDuration::MAX].
+    pub fn checked_add(self, rhs: CuDuration) -> Option<CuDuration> {
+        self.0
+            .checked_add(rhs.0)
+            .filter(|nanos| *nanos <= Self::MAX.0)
+            .map(CuDuration)
+    }
+
+    /// None if rhs is larger than self.
+    pub fn checked_sub(self, rhs: CuDuration) -> Option<CuDuration> {
+        self.0.checked_sub(rhs.0).map(CuDuration)
+    }
+
+    pub fn checked_mul(self, rhs: u64) -> Option<CuDuration> {
+        self.0
+            .checked_mul(rhs)
+            .filter(|nanos| *nanos <= Self::MAX.0)
+            .map(CuDuration)
+    }
+
+    pub fn checked_div(self, rhs: u64) -> Option<CuDuration> {
+        self.0.checked_div(rhs).map(CuDuration)
+    }
+
+    pub fn saturating_add(self, rhs: CuDuration) -> CuDuration {
+        self.checked_add(rhs).unwrap_or(Self::MAX)
+    }
+
+    pub fn saturating_sub(self, rhs: CuDuration) -> CuDuration {
+        CuDuration(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn saturating_mul(self, rhs: u64) -> CuDuration {
+        self.checked_mul(rhs).unwrap_or(Self::MAX)
+    }
+
+    /// The distance between 2 times, whichever comes first.
+    pub fn abs_diff(self, other: CuDuration) -> CuDuration {
+        CuDuration(self.0.abs_diff(other.0))
+    }
 }
 
 /// bridge the API with standard Durations.
@@ -185,27 +273,139 @@ impl<'de, Context> BorrowDecode<'de, Context> for CuDuration {
     }
 }
 
+/// The units of the human readable durations, largest first.
+const UNITS: [(&str, u64); 7] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("µs", 1_000),
+    ("ns", 1),
+];
+
 impl Display for CuDuration {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let Self(nanos) = *self;
-        if nanos >= 86_400_000_000_000 {
-            write!(f, "{:.3} d", nanos as f64 / 86_400_000_000_000.0)
-        } else if nanos >= 3_600_000_000_000 {
-            write!(f, "{:.3} h", nanos as f64 / 3_600_000_000_000.0)
-        } else if nanos >= 60_000_000_000 {
-            write!(f, "{:.3} m", nanos as f64 / 60_000_000_000.0)
-        } else if nanos >= 1_000_000_000 {
-            write!(f, "{:.3} s", nanos as f64 / 1_000_000_000.0)
-        } else if nanos >= 1_000_000 {
-            write!(f, "{:.3} ms", nanos as f64 / 1_000_000.0)
-        } else if nanos >= 1_000 {
-            write!(f, "{:.3} µs", nanos as f64 / 1_000.0)
+        // The precision of the formatter ({:.1}) sets the decimals, 3 by default.
+        let precision = f.precision().unwrap_or(3);
+        match UNITS.iter().find(|(_, unit_nanos)| nanos >= *unit_nanos) {
+            Some((unit, unit_nanos)) if *unit_nanos > 1 => write!(
+                f,
+                "{:.precision$} {unit}",
+                nanos as f64 / *unit_nanos as f64
+            ),
+            _ => write!(f, "{nanos} ns"),
+        }
+    }
+}
+
+/// Parses a duration as displayed, a number followed by a unit: "10 ms", "1.5s", "250 us".
+/// The units are d, h, m, s, ms, µs (or us) and ns, a plain number is a number of nanoseconds.
+impl FromStr for CuDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(s.len());
+        let (number, unit) = (&s[..split], s[split..].trim());
+        let unit = if unit == "us" { "µs" } else { unit };
+        let unit_nanos = match unit {
+            "" => 1,
+            _ => UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, unit_nanos)| *unit_nanos)
+                .ok_or_else(|| {
+                    format!("Unknown unit \"{unit}\" in the duration \"{s}\", expected d, h, m, s, ms, µs or ns.")
+                })?,
+        };
+        let invalid =
+            || format!("Invalid duration \"{s}\", expected a number and a unit like \"10 ms\".");
+        if let Ok(count) = number.parse::<u64>() {
+            CuDuration::from_nanos(count)
+                .checked_mul(unit_nanos)
+                .ok_or_else(|| format!("The duration \"{s}\" is too long."))
         } else {
-            write!(f, "{nanos} ns")
+            let count = number.parse::<f64>().map_err(|_| invalid())?;
+            Ok(CuDuration::from_secs_f64(count * unit_nanos as f64 / 1e9))
         }
     }
 }
 
+#[cfg(feature = "chrono")]
+impl From<CuDuration> for chrono::Duration {
+    /// Saturates at the largest chrono duration, about 292 years.
+    fn from(duration: CuDuration) -> Self {
+        chrono::Duration::nanoseconds(duration.0.min(i64::MAX as u64) as i64)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::Duration> for CuDuration {
+    type Error = String;
+
+    fn try_from(duration: chrono::Duration) -> Result<Self, Self::Error> {
+        match duration.num_nanoseconds() {
+            Some(nanos) if nanos >= 0 => Ok(CuDuration(nanos as u64)),
+            Some(_) => Err(format!("Negative duration {duration}.")),
+            None => Err(format!("The duration {duration} is too long.")),
+        }
+    }
+}
+
+/// Serializes a duration as a human readable string like "10 ms" instead of a number of
+/// nanoseconds, for the configurations: `#[serde(with = "cu29_clock::serde_human")]`.
+/// Both forms are accepted when deserializing.
+pub mod serde_human {
+    use super::{CuDuration, UNITS};
+    use serde::de::{Error, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt::Formatter;
+
+    /// Uses the largest unit the duration is a whole number of, so no precision is lost.
+    pub fn serialize<S: Serializer>(
+        duration: &CuDuration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let (unit, unit_nanos) = UNITS
+            .iter()
+            .find(|(_, unit_nanos)| duration.0.is_multiple_of(*unit_nanos))
+            .expect("every duration is a whole number of ns");
+        serializer.serialize_str(&format!("{} {unit}", duration.0 / unit_nanos))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CuDuration, D::Error> {
+        struct DurationVisitor;
+
+        impl Visitor<'_> for DurationVisitor {
+            type Value = CuDuration;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                write!(f, "a duration like \"10 ms\" or a number of nanoseconds")
+            }
+
+            fn visit_u64<E: Error>(self, nanos: u64) -> Result<CuDuration, E> {
+                Ok(CuDuration(nanos))
+            }
+
+            fn visit_i64<E: Error>(self, nanos: i64) -> Result<CuDuration, E> {
+                u64::try_from(nanos)
+                    .map(CuDuration)
+                    .map_err(|_| E::custom("negative duration"))
+            }
+
+            fn visit_str<E: Error>(self, s: &str) -> Result<CuDuration, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(DurationVisitor)
+    }
+}
+
 /// A robot time is just a duration from a fixed point in time.
 pub type CuTime = CuDuration;
 
@@ -336,8 +536,9 @@ impl From<CuDuration> for Tov {
 /// It is clone resilient, ie a clone will be the same clock, even when mocked.
 #[derive(Clone, Debug)]
 pub struct RobotClock {
-    inner: Clock,      // This is a wrapper on quanta::Clock today.
-    ref_time: Instant, // The reference instant on which this clock is based.
+    inner: Clock,                        // This is a wrapper on quanta::Clock today.
+    ref_time: Instant,                   // The reference instant on which this clock is based.
+    discipline: Option<Arc<Discipline>>, // Set when slaved to an external time base.
 }
 
 /// A mock clock that can be controlled by the user.
@@ -391,9 +592,25 @@ impl RobotClock {
         RobotClock {
             inner: clock,
             ref_time,
+            discipline: None,
         }
     }
 
+    /// Creates a RobotClock running on the given time base.
+    /// With a PHC or CLOCK_TAI, now() returns the time of this external clock instead of starting at 0.
+    /// The offset is measured once here, call [`RobotClock::resync`] or [`RobotClock::track`] to follow the drift.
+    pub fn from_source(source: &ClockSource) -> io::Result<Self> {
+        if *source == ClockSource::Monotonic {
+            return Ok(Self::new());
+        }
+        let clock = RobotClock {
+            discipline: Some(Arc::new(Discipline::open(source)?)),
+            ..Self::new()
+        };
+        clock.resync()?;
+        Ok(clock)
+    }
+
     /// Builds a monotonic clock starting at the given reference time.
     pub fn from_ref_time(ref_time_ns: u64) -> Self {
         let clock = Clock::new();
@@ -401,6 +618,7 @@ impl RobotClock {
         RobotClock {
             inner: Clock::new(),
             ref_time,
+            discipline: None,
         }
     }
 
@@ -413,6 +631,7 @@ impl RobotClock {
             RobotClock {
                 inner: clock,
                 ref_time,
+                discipline: None,
             },
             RobotClockMock(mock),
         )
@@ -424,13 +643,59 @@ impl RobotClock {
     pub fn now(&self) -> CuTime {
         // TODO: this could be further optimized to avoid this constant conversion from 2 fields to one under the hood.
         // Let's say this is the default implementation.
-        (self.inner.now() - self.ref_time).into()
+        self.disciplined((self.inner.now() - self.ref_time).into())
     }
 
     // A less precise but quicker time
     #[inline]
     pub fn recent(&self) -> CuTime {
-        (self.inner.recent() - self.ref_time).into()
+        self.disciplined((self.inner.recent() - self.ref_time).into())
+    }
+
+    #[inline]
+    fn disciplined(&self, local: CuTime) -> CuTime {
+        match &self.discipline {
+            None => local,
+            Some(discipline) => CuDuration(discipline.now(local.0)),
+        }
+    }
+
+    /// Measures the offset to the external clock again and corrects it, all the clones follow.
+    /// Returns the error in ns that was corrected, 0 for a clock without an external time base.
+    /// Small errors are slewed in but a large one steps the clock. now() never goes backwards, after
+    /// a correction to the past it holds until the clock catches up.
+    pub fn resync(&self) -> io::Result<i64> {
+        match &self.discipline {
+            None => Ok(0),
+            Some(discipline) => {
+                discipline.resync(|| (self.inner.now() - self.ref_time).as_nanos() as u64)
+            }
+        }
+    }
+
+    /// The current offset in ns between the external clock and the local monotonic one.
+    pub fn offset(&self) -> i64 {
+        self.discipline.as_ref().map_or(0, |d| d.offset())
+    }
+
+    /// Resyncs the clock every period from a background thread.
+    /// The thread ends by itself when the last clone of this clock is dropped.
+    pub fn track(&self, period: Duration) {
+        let Some(discipline) = &self.discipline else {
+            return;
+        };
+        let weak: Weak<Discipline> = Arc::downgrade(discipline);
+        let inner = self.inner.clone();
+        let ref_time = self.ref_time;
+        std::thread::spawn(move || {
+            while let Some(discipline) = {
+                std::thread::sleep(period);
+                weak.upgrade()
+            } {
+                // A failed read is retried at the next period.
+                let _ = discipline.resync(|| (inner.now() - ref_time).as_nanos() as u64);
+            }
+        });
     }
 }
 
@@ -517,6 +782,23 @@ mod tests {
         assert_eq!(clock2.now(), Duration::from_secs(5).into());
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_tai_source() {
+        let clock = RobotClock::from_source(&ClockSource::Tai).unwrap();
+        let unix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        let tai: Duration = clock.now().into();
+        // TAI is ahead of UTC by the leap seconds, if the kernel knows about them.
+        assert!(tai + Duration::from_secs(1) > unix);
+        assert!(tai < unix + Duration::from_secs(40));
+        let clone = clock.clone();
+        clone.resync().unwrap();
+        assert_eq!(clone.offset(), clock.offset());
+        assert!(RobotClock::from_source(&ClockSource::Phc("/nonexistent/ptp0".into())).is_err());
+    }
+
     #[test]
     fn test_from_ref_time() {
         let tolerance_ms = 10;
@@ -658,6 +940,65 @@ mod tests {
         assert_eq!(day.to_string(), "1.000 d");
     }
 
+    #[test]
+    fn test_cuduration_api() {
+        assert_eq!(CuDuration::from_millis(3), CuDuration(3_000_000));
+        assert_eq!(CuDuration::from_secs_f64(0.25).as_millis(), 250);
+        assert_eq!(CuDuration::from_secs_f64(-1.0), CuDuration::ZERO);
+        assert_eq!(CuDuration::from_micros(1_500).as_secs_f64(), 0.0015);
+
+        let a = CuDuration::from_secs(1);
+        assert_eq!(a.checked_sub(CuDuration::from_secs(2)), None);
+        assert_eq!(a.saturating_sub(CuDuration::from_secs(2)), CuDuration::ZERO);
+        assert_eq!(CuDuration::MAX.checked_add(CuDuration(1)), None);
+        assert_eq!(CuDuration::MAX.saturating_add(a), CuDuration::MAX);
+        assert_eq!(a.saturating_mul(u64::MAX), CuDuration::MAX);
+        assert_eq!(a.checked_div(0), None);
+        assert_eq!(
+            a.abs_diff(CuDuration::from_secs(3)),
+            CuDuration::from_secs(2)
+        );
+
+        assert_eq!(format!("{:.1}", CuDuration::from_micros(1_250)), "1.2 ms");
+        assert_eq!(format!("{:.0}", CuDuration::from_secs(90)), "2 m");
+
+        assert_eq!("10 ms".parse(), Ok(CuDuration::from_millis(10)));
+        assert_eq!("1.5s".parse(), Ok(CuDuration::from_millis(1_500)));
+        assert_eq!(" 250 us ".parse(), Ok(CuDuration::from_micros(250)));
+        assert_eq!("42".parse(), Ok(CuDuration(42)));
+        assert_eq!("1.000 d".parse(), Ok(CuDuration(86_400_000_000_000)));
+        assert!("10 parsecs".parse::<CuDuration>().is_err());
+        assert!("ms".parse::<CuDuration>().is_err());
+    }
+
+    #[test]
+    fn test_serde_human() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Config {
+            #[serde(with = "serde_human")]
+            period: CuDuration,
+        }
+        let config = Config {
+            period: CuDuration::from_micros(1_500),
+        };
+        let text = ron::to_string(&config).unwrap();
+        assert_eq!(text, r#"(period:"1500 µs")"#);
+        assert_eq!(ron::from_str::<Config>(&text).unwrap(), config);
+        assert_eq!(
+            ron::from_str::<Config>("(period: 1500000)").unwrap(),
+            config
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let delta: chrono::Duration = CuDuration::from_millis(5).into();
+        assert_eq!(delta, chrono::Duration::milliseconds(5));
+        assert_eq!(CuDuration::try_from(delta), Ok(CuDuration::from_millis(5)));
+        assert!(CuDuration::try_from(chrono::Duration::milliseconds(-5)).is_err());
+    }
+
     #[test]
     fn test_robot_clock_precision() {
         // Test that RobotClock::now() and RobotClock::recent() return different values
@@ -0,0 +1,193 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Data, DeriveInput, Error, Expr, ExprLit, Fields, GenericArgument, Lit, LitStr, PathArguments,
+    Result, Type,
+};
+
+/// What `#[config(...)]` says about a field.
+#[derive(Default)]
+struct FieldOptions {
+    key: Option<LitStr>,
+    /// `Some(None)` is a bare `default`: `Default::default()`.
+    default: Option<Option<Expr>>,
+    range: Option<Expr>,
+}
+
+fn parse_options(field: &syn::Field) -> Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("config"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("key") {
+                options.key = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("default") {
+                options.default = Some(if meta.input.peek(syn::Token![=]) {
+                    Some(meta.value()?.parse()?)
+                } else {
+                    None
+                });
+            } else if meta.path.is_ident("range") {
+                let range: Expr = meta.value()?.parse()?;
+                if !matches!(range, Expr::Range(_)) {
+                    return Err(Error::new(
+                        range.span(),
+                        "expected a range like 0..=10, 0.0..1.0 or 1..",
+                    ));
+                }
+                options.range = Some(range);
+            } else {
+                return Err(meta.error("unknown config option, expected key, default or range"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+/// Returns T if the type is an `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+pub(crate) fn derive(input: DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let owner = name.to_string();
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "CuConfigStruct can only be derived for structs with named fields",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            data.fields.span(),
+            "CuConfigStruct can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut initializers = Vec::new();
+    let mut schema = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let options = parse_options(field)?;
+        let key = options
+            .key
+            .map(|key| key.value())
+            .unwrap_or_else(|| ident.to_string());
+        let optional = option_inner(&field.ty);
+        let value_type = optional.unwrap_or(&field.ty);
+
+        let required = options.default.is_none() && optional.is_none();
+        let default = match &options.default {
+            Some(Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(default),
+                ..
+            }))) => {
+                let default = default.value();
+                quote! { Some(#default) }
+            }
+            Some(Some(default)) => {
+                let default = quote!(#default).to_string();
+                quote! { Some(#default) }
+            }
+            Some(None) => quote! { Some("Default::default()") },
+            None => quote! { None },
+        };
+        schema.push(quote! {
+            cu29::config::ConfigKey {
+                name: #key,
+                expected: <#value_type as cu29::config::FromConfigValue>::EXPECTED,
+                required: #required,
+                default: #default,
+                accepts: cu29::config::accepts::<#value_type>,
+            }
+        });
+
+        let check_range = options.range.map(|range| {
+            let range_str = quote!(#range).to_string().replace(' ', "");
+            quote! {
+                if !(#range).contains(&value) {
+                    return Err(cu29::CuError::from(format!(
+                        "{}: the config key \"{}\" should be in {}, got {}.",
+                        #owner, #key, #range_str, value
+                    )));
+                }
+            }
+        });
+
+        let absent = match (&options.default, optional) {
+            (Some(_), Some(_)) => {
+                return Err(Error::new(
+                    field.ty.span(),
+                    "an Option field is None when absent, it cannot have a default",
+                ));
+            }
+            // String literals are the only ones that need a conversion, the others infer their type.
+            (
+                Some(Some(
+                    default @ Expr::Lit(ExprLit {
+                        lit: Lit::Str(_), ..
+                    }),
+                )),
+                None,
+            ) => {
+                quote! { ::core::convert::Into::into(#default) }
+            }
+            (Some(Some(default)), None) => quote! { #default },
+            (Some(None), None) => quote! { ::core::default::Default::default() },
+            (None, Some(_)) => quote! { None },
+            (None, None) => quote! {
+                return Err(cu29::CuError::from(format!(
+                    "{}: the config key \"{}\" is required.",
+                    #owner, #key
+                )))
+            },
+        };
+        let present = if optional.is_some() {
+            quote! { Some(value) }
+        } else {
+            quote! { value }
+        };
+
+        initializers.push(quote! {
+            #ident: match cu29::config::config_field::<#value_type>(config, #owner, #key)? {
+                Some(value) => {
+                    #check_range
+                    #present
+                }
+                None => #absent,
+            }
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics cu29::config::CuConfigStruct for #name #ty_generics #where_clause {
+            const SCHEMA: &'static [cu29::config::ConfigKey] = &[#(#schema,)*];
+
+            fn from_config(config: Option<&cu29::config::ComponentConfig>) -> cu29::CuResult<Self> {
+                Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
@@ -1,8 +1,9 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
 use std::fs::read_to_string;
+use std::path::PathBuf;
 use syn::meta::parser;
 use syn::Fields::{Named, Unnamed};
 use syn::{
@@ -11,17 +12,21 @@ use syn::{
 };
 
 use crate::utils::config_id_to_enum;
-use cu29_runtime::config::read_configuration;
-use cu29_runtime::config::CuConfig;
+use cu29_runtime::config::{read_configuration, read_configuration_str};
+use cu29_runtime::config::{CnxPolicy, CuConfig, NodeId, TaskPriority};
 use cu29_runtime::curuntime::{
-    compute_runtime_plan, find_task_type_for_id, CuExecutionLoop, CuExecutionUnit, CuTaskType,
+    compute_runtime_plan, find_task_type_for_id, CuExecutionLoop, CuExecutionStep, CuExecutionUnit,
+    CuTaskType,
 };
+use cu29_runtime::delivery::needs_buffer;
 use cu29_traits::CuResult;
+use std::collections::HashMap;
 
 #[cfg(feature = "macro_debug")]
 use format::{highlight_rust_code, rustfmt_generated_code};
 use proc_macro2::{Ident, Span};
 
+mod config_struct;
 mod format;
 mod utils;
 
@@ -40,6 +45,18 @@ fn return_error(msg: String) -> TokenStream {
         .into()
 }
 
+/// Derives `cu29::config::CuConfigStruct` to read a task config into a typed struct.
+/// Fields are required unless they are an `Option` (None when absent) or have a default:
+/// `#[config(default)]`, `#[config(default = 10)]`. `#[config(range = 0.0..=1.0)]` bounds the value
+/// and `#[config(key = "name")]` reads it from another key than the field name.
+#[proc_macro_derive(CuConfigStruct, attributes(config))]
+pub fn derive_config_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    config_struct::derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
 /// Generates the CopperList content type from a config.
 /// gen_cumsgs!("path/to/config.toml")
 /// It will create a new type called CuMsgs you can pass to the log reader for decoding:
@@ -57,6 +74,9 @@ pub fn gen_cumsgs(config_path_lit: TokenStream) -> TokenStream {
         Ok(cuconfig) => cuconfig,
         Err(e) => return return_error(e.to_string()),
     };
+    if let Err(e) = check_msg_types(&cuconfig) {
+        return return_error(e);
+    }
     let runtime_plan: CuExecutionLoop = match compute_runtime_plan(&cuconfig) {
         Ok(plan) => plan,
         Err(e) => return return_error(format!("Could not compute runtime plan: {e}")),
@@ -89,7 +109,12 @@ pub fn gen_cumsgs(config_path_lit: TokenStream) -> TokenStream {
             .collect::<Vec<_>>()
     );
 
-    let support = gen_culist_support(&runtime_plan, &taskid_order, &all_tasks_member_ids);
+    let support = gen_culist_support(
+        &cuconfig,
+        &runtime_plan,
+        &taskid_order,
+        &all_tasks_member_ids,
+    );
 
     let with_uses = quote! {
         mod cumsgs {
@@ -111,6 +136,7 @@ pub fn gen_cumsgs(config_path_lit: TokenStream) -> TokenStream {
 
 /// Build the inner support of the copper list.
 fn gen_culist_support(
+    config: &CuConfig,
     runtime_plan: &CuExecutionLoop,
     taskid_call_order: &[usize],
     all_tasks_as_struct_member_name: &Vec<String>,
@@ -157,6 +183,170 @@ fn gen_culist_support(
         },
     );
 
+    // The bound of the encoded size of the copper list if all the outputs declare a max_size, the
+    // virtual outputs of the sinks always encode in a byte.
+    let max_sizes: Option<Vec<usize>> = runtime_plan
+        .steps
+        .iter()
+        .filter_map(|unit| match unit {
+            CuExecutionUnit::Step(step) if step.task_type == CuTaskType::Sink => Some(Some(1)),
+            CuExecutionUnit::Step(step) => Some(
+                config
+                    .get_node_output_max_size(step.node_id, None) // FIXME(gbin): Multimission support
+                    .map(|size| size as usize),
+            ),
+            _ => None,
+        })
+        .collect();
+    let culist_max_encoded_size = match max_sizes {
+        Some(sizes) => quote! {
+            Some(cu29::copperlist::HEADER_MAX_ENCODED_SIZE #(+ CuMsgMetadata::MAX_ENCODED_SIZE + #sizes)*)
+        },
+        None => quote! { None },
+    };
+
+    // Compares the outputs of the tasks, the virtual outputs of the sinks carry nothing.
+    let comparisons: Vec<proc_macro2::TokenStream> = runtime_plan
+        .steps
+        .iter()
+        .filter_map(|unit| match unit {
+            CuExecutionUnit::Step(step) if step.task_type != CuTaskType::Sink => {
+                let (index, _) = step.output_msg_index_type.as_ref()?;
+                let index = int2sliceindex(*index);
+                let task_id = step.node.get_id();
+                Some(quote! {
+                    report.compare(culist_id, #task_id, &self.0.#index, &other.0.#index, |payload| (&cu29::invariants::ValueProbe(payload)).as_value());
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Gives the outputs with a payload to the exporter, with their exporter if their type has one.
+    let exports: Vec<proc_macro2::TokenStream> = runtime_plan
+        .steps
+        .iter()
+        .filter_map(|unit| match unit {
+            CuExecutionUnit::Step(step) if step.task_type != CuTaskType::Sink => {
+                let (index, _) = step.output_msg_index_type.as_ref()?;
+                let index = int2sliceindex(*index);
+                let task_id = step.node.get_id();
+                Some(quote! {
+                    if let Some(payload) = self.0.#index.payload() {
+                        visitor(#task_id, &self.0.#index.metadata, (&cu29::export::ExportProbe(payload)).as_export());
+                    }
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Gives all the outputs to the log viewers, the payloads being Debug.
+    let outputs: Vec<proc_macro2::TokenStream> = runtime_plan
+        .steps
+        .iter()
+        .filter_map(|unit| match unit {
+            CuExecutionUnit::Step(step) if step.task_type != CuTaskType::Sink => {
+                let (index, _) = step.output_msg_index_type.as_ref()?;
+                let index = int2sliceindex(*index);
+                let task_id = step.node.get_id();
+                Some(quote! {
+                    visitor(#task_id, &self.0.#index.metadata, self.0.#index.payload().map(|p| p as &dyn core::fmt::Debug));
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    // The types in the plan are already resolved, the config validation made sure they can be.
+    let schema_tags: Vec<proc_macro2::TokenStream> = runtime_plan
+        .steps
+        .iter()
+        .filter_map(|unit| match unit {
+            CuExecutionUnit::Step(step) => {
+                let (_, msg_type) = step.output_msg_index_type.as_ref()?;
+                let task_id = step.node.get_id();
+                let version = config.schema_version(msg_type).unwrap_or_default();
+                Some(quote! {
+                    cu29::schema::CuSchemaTag {
+                        task_id: #task_id.to_string(),
+                        msg_type: #msg_type.to_string(),
+                        version: #version,
+                    }
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Publishes the outputs of the tasks to the taps of their connections.
+    #[cfg(feature = "tap")]
+    let tapped_impl = {
+        let publications: Vec<proc_macro2::TokenStream> = runtime_plan
+            .steps
+            .iter()
+            .filter_map(|unit| match unit {
+                CuExecutionUnit::Step(step) if step.task_type != CuTaskType::Sink => {
+                    let (index, msg_type) = step.output_msg_index_type.as_ref()?;
+                    let index = int2sliceindex(*index);
+                    let task_id = step.node.get_id();
+                    Some(quote! {
+                        taps.publish(culist_id, #task_id, #msg_type, &self.0.#index);
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        quote! {
+            impl cu29::tap::CuTapped for CuMsgs {
+                #[allow(unused_variables)]
+                fn publish_taps(&self, culist_id: u32, taps: &mut cu29::tap::CuTaps) {
+                    #(#publications)*
+                }
+            }
+        }
+    };
+    #[cfg(not(feature = "tap"))]
+    let tapped_impl = quote! {};
+
+    // Gives the outputs of the tasks to the invariants of the configuration, only if it has some.
+    let checked_outputs_impl = config
+        .invariants
+        .as_ref()
+        .filter(|invariants| !invariants.is_empty())
+        .map(|_| {
+            let (ids, indices): (Vec<String>, Vec<syn::Index>) = runtime_plan
+                .steps
+                .iter()
+                .filter_map(|unit| match unit {
+                    CuExecutionUnit::Step(step) if step.task_type != CuTaskType::Sink => {
+                        let (index, _) = step.output_msg_index_type.as_ref()?;
+                        Some((step.node.get_id(), int2sliceindex(*index)))
+                    }
+                    _ => None,
+                })
+                .unzip();
+            quote! {
+                impl cu29::invariants::CuCheckedOutputs for CuMsgs {
+                    fn has_payload(&self, task_id: &str) -> bool {
+                        match task_id {
+                            #(#ids => self.0.#indices.payload().is_some(),)*
+                            _ => false,
+                        }
+                    }
+
+                    fn payload_value(&self, task_id: &str) -> Option<cu29::prelude::Value> {
+                        #[allow(unused_imports)]
+                        use cu29::invariants::{ViaOpaque as _, ViaSerialize as _};
+                        match task_id {
+                            #(#ids => self.0.#indices.payload().and_then(|payload| (&cu29::invariants::ValueProbe(payload)).as_value()),)*
+                            _ => None,
+                        }
+                    }
+                }
+            }
+        });
+
     // This generates a way to get the metadata of every single message of a culist at low cost
     quote! {
         #collect_metadata_function
@@ -165,6 +355,10 @@ fn gen_culist_support(
 
         pub type CuList = CopperList<CuMsgs>;
 
+        /// Bound of the encoded size of a copper list, known when all the connections declare
+        /// the max_size of their messages.
+        pub const CULIST_MAX_ENCODED_SIZE: Option<usize> = #culist_max_encoded_size;
+
         impl CuMsgs {
             #(#methods)*
 
@@ -177,6 +371,41 @@ fn gen_culist_support(
             }
         }
 
+        impl cu29::schema::CuSchemaTagged for CuMsgs {
+            fn schema_tags() -> Vec<cu29::schema::CuSchemaTag> {
+                vec![#(#schema_tags),*]
+            }
+        }
+
+        #tapped_impl
+
+        #checked_outputs_impl
+
+        impl cu29::replay::CuOutputsComparison for CuMsgs {
+            #[allow(unused_variables)]
+            fn compare_outputs(&self, other: &Self, culist_id: u32, report: &mut cu29::replay::ReplayReport) {
+                #[allow(unused_imports)]
+                use cu29::invariants::{ViaOpaque as _, ViaSerialize as _};
+                #(#comparisons)*
+            }
+        }
+
+        impl cu29::export::CuOutputsExport for CuMsgs {
+            #[allow(unused_variables)]
+            fn visit_exports(&self, visitor: &mut cu29::export::ExportVisitor) {
+                #[allow(unused_imports)]
+                use cu29::export::{ViaOpaque as _, ViaPayloadExport as _};
+                #(#exports)*
+            }
+        }
+
+        impl cu29::export::CuOutputsDebug for CuMsgs {
+            #[allow(unused_variables)]
+            fn visit_outputs(&self, visitor: &mut cu29::export::OutputVisitor) {
+                #(#outputs)*
+            }
+        }
+
         // Adds the bincode support for the copper list tuple
         #msgs_types_tuple_encode
         #msgs_types_tuple_decode
@@ -186,6 +415,360 @@ fn gen_culist_support(
     }
 }
 
+/// How an input of a task is delivered: the connection feeding it (its edge index in the graph),
+/// its policy, its slot in CuCnxBuffers if the policy needs a buffer or a profile can disable it
+/// and its key if the connection is keyed.
+struct InputDelivery {
+    cnx: usize,
+    policy: CnxPolicy,
+    buffer: Option<syn::Index>,
+    disableable: bool,
+    msg_type: String,
+    key: Option<String>,
+    /// The position of the connection among the outputs of a source routing its output per cycle.
+    route: Option<usize>,
+    /// The payloads older than this many milliseconds are counted as stale.
+    max_age_ms: Option<u32>,
+}
+
+/// Finds the connection of every input of the tasks, in the order of their inputs, and the
+/// message types of the buffers the connections need.
+fn plan_deliveries(
+    config: &CuConfig,
+    runtime_plan: &CuExecutionLoop,
+) -> (HashMap<NodeId, Vec<InputDelivery>>, Vec<Type>) {
+    let graph = config.get_graph(None).unwrap(); // FIXME(gbin): multimission
+    let steps: Vec<&CuExecutionStep> = runtime_plan
+        .steps
+        .iter()
+        .map(|unit| match unit {
+            CuExecutionUnit::Step(step) => step.as_ref(),
+            CuExecutionUnit::Loop(_) => todo!("Needs to be implemented"),
+        })
+        .collect();
+    let mut buffers_types = Vec::new();
+    let deliveries = steps
+        .iter()
+        .map(|step| {
+            let inputs = step
+                .input_msg_indices_types
+                .iter()
+                .map(|(culist_index, msg_type)| {
+                    let producer = steps
+                        .iter()
+                        .find(|s| matches!(s.output_msg_index_type, Some((i, _)) if i == *culist_index))
+                        .expect("Every input should have been produced by a task");
+                    let edge = graph
+                        .find_edge(producer.node_id.into(), step.node_id.into())
+                        .expect("An edge connecting the input to the output should exist");
+                    let policy = graph[edge].policy.unwrap_or_default();
+                    let key = graph[edge].key.clone();
+                    // The profiles disabling the connection give the empty message of the buffer.
+                    let disableable = config.profiles.iter().flatten().any(|profile| {
+                        profile.disables_cnx(&producer.node.get_id(), &step.node.get_id())
+                    });
+                    // The outputs of a routed source are its connections in the order of the config.
+                    let route = producer.node.is_routed().then(|| {
+                        let mut outputs = config
+                            .get_src_edges(producer.node_id, None)
+                            .expect("The source is in the graph");
+                        outputs.sort();
+                        let route = outputs
+                            .iter()
+                            .position(|output| *output == edge.index())
+                            .expect("The edge is an output of its source");
+                        if route >= 64 {
+                            panic!(
+                                "Task {}: a routed task has 64 outputs at most.",
+                                producer.node.get_id()
+                            );
+                        }
+                        route
+                    });
+                    let buffer = (needs_buffer(policy) || disableable || route.is_some()).then(|| {
+                        buffers_types.push(
+                            parse_str::<Type>(&format!("CuMsg<{msg_type}>"))
+                                .expect("Invalid message type"),
+                        );
+                        int2sliceindex(buffers_types.len() as u32 - 1)
+                    });
+                    InputDelivery {
+                        cnx: edge.index(),
+                        policy,
+                        buffer,
+                        disableable,
+                        msg_type: msg_type.clone(),
+                        key,
+                        route,
+                        max_age_ms: graph[edge].max_age_ms,
+                    }
+                })
+                .collect();
+            (step.node_id, inputs)
+        })
+        .collect();
+    (deliveries, buffers_types)
+}
+
+/// The inputs of a task by position: the keyed connections of a same message type are grouped at
+/// the position of the first one, the optional inputs the graph leaves unconnected are None.
+fn input_positions<'a, T>(
+    step: &CuExecutionStep,
+    inputs: impl IntoIterator<Item = (T, &'a InputDelivery)>,
+) -> Vec<Option<Vec<(T, &'a InputDelivery)>>> {
+    // The connected inputs of the task, each one a message or a keyed group of messages.
+    let mut connected: Vec<Vec<(T, &InputDelivery)>> = Vec::new();
+    for (index, delivery) in inputs {
+        let group = delivery.key.as_ref().and_then(|_| {
+            connected
+                .iter_mut()
+                .find(|group| group[0].1.key.is_some() && group[0].1.msg_type == delivery.msg_type)
+        });
+        match group {
+            Some(group) => group.push((index, delivery)),
+            None => connected.push(vec![(index, delivery)]),
+        }
+    }
+
+    let mut unconnected = step.node.get_unconnected_inputs().to_vec();
+    unconnected.sort();
+    unconnected.dedup();
+    let nb_inputs = connected.len() + unconnected.len();
+    if let Some(position) = unconnected.iter().find(|position| **position >= nb_inputs) {
+        panic!(
+            "Task {}: the unconnected input {position} is out of its {nb_inputs} inputs.",
+            step.node.get_id()
+        );
+    }
+    let mut connected = connected.into_iter();
+    (0..nb_inputs)
+        .map(|position| {
+            (!unconnected.contains(&position)).then(|| connected.next().expect("counted above"))
+        })
+        .collect()
+}
+
+/// Builds the Input given to the process() of a task: its connected messages delivered following
+/// the policies of their connections, the keyed connections of a same message type merged into a
+/// CuKeyedMsgs at the position of the first one, with None at the positions of the optional inputs
+/// the graph leaves unconnected.
+fn gen_task_input(
+    step: &CuExecutionStep,
+    indices: &[syn::Index],
+    deliveries: &[InputDelivery],
+) -> proc_macro2::TokenStream {
+    let inputs: Vec<proc_macro2::TokenStream> =
+        input_positions(step, indices.iter().zip(deliveries))
+            .into_iter()
+            .map(|group| {
+                let Some(group) = group else {
+                    return quote! { None };
+                };
+                let msgs = group
+                    .iter()
+                    .map(|(index, delivery)| gen_delivery(index, delivery));
+                if group[0].1.key.is_none() {
+                    quote! { cu29::cutask::IntoCuInput::into_cu_input(#(#msgs)*) }
+                } else {
+                    let keys = group
+                        .iter()
+                        .map(|(_, delivery)| delivery.key.as_deref().expect("keyed group"));
+                    quote! { cu29::cutask::CuKeyedMsgs::new(&[#((#keys, #msgs)),*]) }
+                }
+            })
+            .collect();
+    quote! { (#(#inputs),*) }
+}
+
+/// Builds the calls to the services of a client given to its process_with_services(), None if the
+/// task calls no service. The providers are borrowed from the tasks tuple for the time of the call.
+fn gen_service_calls(
+    config: &CuConfig,
+    step: &CuExecutionStep,
+    all_tasks_ids: &[String],
+    all_tasks_cutype: &[CuTaskType],
+) -> Option<proc_macro2::TokenStream> {
+    let client = step.node.get_id();
+    let services = config.get_services_of(&client);
+    // A disabled client is replaced by a stub calling nothing.
+    if services.is_empty() || !step.node.is_enabled() {
+        return None;
+    }
+    if step.task_type != CuTaskType::Regular {
+        panic!("The task \"{client}\" calls services but only the tasks with inputs and an output can.");
+    }
+    let calls = services.iter().map(|service| {
+        let provider = all_tasks_ids
+            .iter()
+            .position(|id| id == &service.provider)
+            .unwrap_or_else(|| panic!("The provider \"{}\" is not in the graph.", service.provider));
+        if all_tasks_cutype[provider] != CuTaskType::Regular {
+            panic!(
+                "The task \"{}\" provides a service but only the tasks with inputs and an output can.",
+                service.provider
+            );
+        }
+        let provider = int2sliceindex(provider as u32);
+        let [request, response] = [&service.request, &service.response].map(|msg_type| {
+            let resolved = config
+                .resolve_msg_type(msg_type)
+                .unwrap_or_else(|e| panic!("{e}"));
+            parse_str::<Type>(&resolved)
+                .unwrap_or_else(|_| panic!("Invalid type \"{resolved}\" in the service of \"{client}\"."))
+        });
+        quote! {
+            cu29::service::CuServiceCall::<#request, #response>::new(&self.copper_runtime.clock, &mut self.copper_runtime.tasks.#provider)
+        }
+    });
+    Some(quote! { (#(#calls),*) })
+}
+
+/// Delivers the message of one connection following its policy.
+fn gen_delivery(index: &syn::Index, delivery: &InputDelivery) -> proc_macro2::TokenStream {
+    let cnx = delivery.cnx;
+    let stats = quote! { self.copper_runtime.graph_description.cnx_stats_mut(#cnx) };
+    let delivered = match (delivery.policy, &delivery.buffer) {
+        (CnxPolicy::Latched, Some(buffer)) => quote! {
+            cu29::delivery::latched(&msgs.#index, &mut self.cnx_buffers.#buffer, #stats)
+        },
+        (CnxPolicy::Decimate(n), Some(buffer)) => quote! {
+            cu29::delivery::decimate(#n, &msgs.#index, &mut self.cnx_buffers.#buffer, #stats)
+        },
+        _ => quote! { cu29::delivery::latest(&msgs.#index, #stats) },
+    };
+    let delivered = match (delivery.route, &delivery.buffer) {
+        (Some(route), Some(buffer)) => quote! {
+            if msgs.#index.is_routed_to(#route) {
+                #delivered
+            } else {
+                cu29::delivery::unrouted(&msgs.#index, &mut self.cnx_buffers.#buffer, #stats)
+            }
+        },
+        _ => delivered,
+    };
+    let delivered = match &delivery.buffer {
+        Some(buffer) if delivery.disableable => quote! {
+            if self.copper_runtime.profile.cnx_enabled(#cnx) {
+                #delivered
+            } else {
+                cu29::delivery::disabled(&msgs.#index, &mut self.cnx_buffers.#buffer, #stats)
+            }
+        },
+        _ => delivered,
+    };
+    match delivery.max_age_ms {
+        Some(max_age_ms) => {
+            let max_age_ms = max_age_ms as u64;
+            quote! {
+                cu29::delivery::check_age(#delivered, self.copper_runtime.clock.now(), cu29::clock::CuDuration::from_millis(#max_age_ms), #stats)
+            }
+        }
+        None => delivered,
+    }
+}
+
+/// The location of the declaration of a task in the configuration, for the errors: the first line
+/// with `id: "<task id>"`.
+fn task_config_location(
+    config_file: Option<&str>,
+    config_content: &str,
+    task_id: &str,
+) -> Option<String> {
+    let quoted = format!("\"{task_id}\"");
+    let line = config_content.lines().position(|line| {
+        line.match_indices(&quoted).any(|(start, _)| {
+            line[..start]
+                .trim_end()
+                .strip_suffix(':')
+                .map(str::trim_end)
+                .is_some_and(|before| {
+                    before.ends_with("id")
+                        && !before[..before.len() - 2]
+                            .ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                })
+        })
+    })? + 1;
+    Some(match config_file {
+        Some(config_file) => format!("{config_file}:{line}"),
+        None => format!("line {line} of the inline configuration"),
+    })
+}
+
+/// Generates the compile time checks that a task declares the message types of its connections in
+/// its Input and Output. A mismatch fails on the configuration with the id of the task, its line
+/// and the two types instead of somewhere in the generated code.
+fn gen_task_type_checks(
+    step: &CuExecutionStep,
+    task_type: &Type,
+    deliveries: &[InputDelivery],
+    location: Option<String>,
+    span: Span,
+) -> proc_macro2::TokenStream {
+    let task_id = step.node.get_id();
+    let task = match location {
+        Some(location) => format!("The task \"{task_id}\" ({location})"),
+        None => format!("The task \"{task_id}\""),
+    };
+    let task_trait = match step.task_type {
+        CuTaskType::Source => quote! { cu29::cutask::CuSrcTask<'static> },
+        CuTaskType::Regular => quote! { cu29::cutask::CuTask<'static> },
+        CuTaskType::Sink => quote! { cu29::cutask::CuSinkTask<'static> },
+    };
+
+    // (the associated type, the position in it, the message type of the connection, what it is)
+    let mut expected: Vec<(Ident, usize, &str, String)> = Vec::new();
+    if step.task_type != CuTaskType::Sink {
+        if let Some((_, msg_type)) = &step.output_msg_index_type {
+            expected.push((
+                format_ident!("Output"),
+                0,
+                msg_type.as_str(),
+                "its output".to_string(),
+            ));
+        }
+    }
+    for (position, group) in input_positions(step, deliveries.iter().map(|d| ((), d)))
+        .into_iter()
+        .enumerate()
+    {
+        if let Some(group) = group {
+            let (_, delivery) = group[0];
+            expected.push((
+                format_ident!("Input"),
+                position,
+                delivery.msg_type.as_str(),
+                format!("its input {position}"),
+            ));
+        }
+    }
+
+    let checks = expected.into_iter().map(|(assoc, position, msg_type, what)| {
+        // The braces are the placeholders of the diagnostic, {Self} is the type the task declares.
+        let escaped = msg_type.replace('{', "{{").replace('}', "}}");
+        let message =
+            format!("{task} declares `{{Self}}` for {what} but its connection carries `{escaped}`.");
+        let label = format!("the connection carries `{escaped}`");
+        let msg_type = parse_str::<Type>(msg_type).expect("Message types are checked before expansion");
+        let position = proc_macro2::Literal::usize_unsuffixed(position);
+        let check = quote_spanned! {span=>
+            check::<<<#task_type as #task_trait>::#assoc as cu29::cutask::CuPayloadAt<#position>>::Payload, #msg_type>();
+        };
+        quote! {
+            const _: () = {
+                #[diagnostic::on_unimplemented(message = #message, label = #label)]
+                trait CuTypeCheck<T> {}
+                impl<T> CuTypeCheck<T> for T {}
+                fn check<Found: CuTypeCheck<Expected>, Expected>() {}
+                #[allow(dead_code)]
+                fn checks() {
+                    #check
+                }
+            };
+        }
+    });
+    quote! { #(#checks)* }
+}
+
 fn gen_sim_support(runtime_plan: &CuExecutionLoop) -> proc_macro2::TokenStream {
     #[cfg(feature = "macro_debug")]
     eprintln!("[Sim: Build SimEnum]");
@@ -223,8 +806,182 @@ fn gen_sim_support(runtime_plan: &CuExecutionLoop) -> proc_macro2::TokenStream {
     }
 }
 
+/// Generates the open loop replay callback: the sources outputs are taken from a recorded copperlist
+/// and every other output is compared to its recorded counterpart when it is consumed.
+fn gen_replay_support(runtime_plan: &CuExecutionLoop) -> proc_macro2::TokenStream {
+    let steps: Vec<&CuExecutionStep> = runtime_plan
+        .steps
+        .iter()
+        .map(|unit| match unit {
+            CuExecutionUnit::Step(step) => step.as_ref(),
+            CuExecutionUnit::Loop(_) => todo!("Needs to be implemented"),
+        })
+        .collect();
+
+    // Which task produced each output of the copperlist.
+    let producer_of = |culist_index: u32| -> usize {
+        steps
+            .iter()
+            .find(|s| matches!(s.output_msg_index_type, Some((i, _)) if i == culist_index))
+            .map(|s| s.node_id as usize)
+            .expect("Every input should have been produced by a task")
+    };
+
+    let mut already_compared = std::collections::HashSet::new();
+    let arms: Vec<proc_macro2::TokenStream> = steps
+        .iter()
+        .map(|step| {
+            let enum_ident = Ident::new(
+                &config_id_to_enum(step.node.get_id().as_str()),
+                Span::call_site(),
+            );
+            if step.task_type == CuTaskType::Source {
+                let output_index = int2sliceindex(
+                    step.output_msg_index_type
+                        .as_ref()
+                        .expect("Source task should have an output message index.")
+                        .0,
+                );
+                return quote! {
+                    SimStep::#enum_ident(cu29::simulation::CuTaskCallbackState::Process(_, output)) => {
+                        *output = recorded.msgs.0.#output_index.clone();
+                        cu29::simulation::SimOverride::ExecutedBySim
+                    }
+                    SimStep::#enum_ident(_) => cu29::simulation::SimOverride::ExecutedBySim,
+                };
+            }
+
+            let nb_inputs = step.input_msg_indices_types.len();
+            let comparisons = step
+                .input_msg_indices_types
+                .iter()
+                .enumerate()
+                .filter(|(_, (culist_index, _))| already_compared.insert(*culist_index))
+                .map(|(position, (culist_index, _))| {
+                    let producer = producer_of(*culist_index);
+                    let recorded_index = int2sliceindex(*culist_index);
+                    let replayed = if nb_inputs == 1 {
+                        quote! { inputs }
+                    } else {
+                        let position = syn::Index::from(position);
+                        quote! { inputs.#position }
+                    };
+                    quote! {
+                        report.compare(recorded.id, TASKS_IDS[#producer], &recorded.msgs.0.#recorded_index, #replayed, |payload| (&cu29::invariants::ValueProbe(payload)).as_value());
+                    }
+                })
+                .collect::<Vec<_>>();
+            // The sinks are placeholders in sim mode, nothing should reach the actuators.
+            let decision = if step.task_type == CuTaskType::Sink {
+                quote! { cu29::simulation::SimOverride::ExecutedBySim }
+            } else {
+                quote! { cu29::simulation::SimOverride::ExecuteByRuntime }
+            };
+            let other_steps = if step.task_type == CuTaskType::Sink {
+                quote! { SimStep::#enum_ident(_) => cu29::simulation::SimOverride::ExecutedBySim, }
+            } else {
+                quote! {}
+            };
+            quote! {
+                SimStep::#enum_ident(cu29::simulation::CuTaskCallbackState::Process(inputs, _)) => {
+                    #(#comparisons)*
+                    #decision
+                }
+                #other_steps
+            }
+        })
+        .collect();
+
+    quote! {
+        /// Simulation callback for an open loop replay of a recorded copperlist, see [cu29::replay].
+        #[allow(unused_variables, unreachable_patterns)]
+        pub fn replay_step(recorded: &CuList, report: &mut cu29::replay::ReplayReport, step: SimStep) -> cu29::simulation::SimOverride {
+            #[allow(unused_imports)]
+            use cu29::invariants::{ViaOpaque as _, ViaSerialize as _};
+            match step {
+                #(#arms)*
+                _ => cu29::simulation::SimOverride::ExecuteByRuntime,
+            }
+        }
+    }
+}
+
+/// Generates the simulation callback of the test harness (see [cu29::testing]): the sources give
+/// the payloads injected in the harness and the inputs of the sinks are recorded in it, the other
+/// tasks run their actual code.
+fn gen_harness_support(runtime_plan: &CuExecutionLoop) -> proc_macro2::TokenStream {
+    let arms: Vec<proc_macro2::TokenStream> = runtime_plan
+        .steps
+        .iter()
+        .map(|unit| match unit {
+            CuExecutionUnit::Step(step) => step,
+            CuExecutionUnit::Loop(_) => todo!("Needs to be implemented"),
+        })
+        .map(|step| {
+            let enum_ident = Ident::new(
+                &config_id_to_enum(step.node.get_id().as_str()),
+                Span::call_site(),
+            );
+            let task_id = step.node.get_id();
+            match step.task_type {
+                CuTaskType::Source => quote! {
+                    SimStep::#enum_ident(cu29::simulation::CuTaskCallbackState::Process(_, output)) => {
+                        harness.fill_output(#task_id, output);
+                        cu29::simulation::SimOverride::ExecutedBySim
+                    }
+                    SimStep::#enum_ident(_) => cu29::simulation::SimOverride::ExecutedBySim,
+                },
+                CuTaskType::Sink => {
+                    let nb_inputs = step.input_msg_indices_types.len();
+                    let captures = (0..nb_inputs).map(|position| {
+                        let input = if nb_inputs == 1 {
+                            quote! { inputs }
+                        } else {
+                            let index = syn::Index::from(position);
+                            quote! { inputs.#index }
+                        };
+                        quote! { harness.capture(#task_id, #position, #input); }
+                    });
+                    quote! {
+                        SimStep::#enum_ident(cu29::simulation::CuTaskCallbackState::Process(inputs, _)) => {
+                            #(#captures)*
+                            cu29::simulation::SimOverride::ExecutedBySim
+                        }
+                        SimStep::#enum_ident(_) => cu29::simulation::SimOverride::ExecutedBySim,
+                    }
+                }
+                CuTaskType::Regular => quote! {},
+            }
+        })
+        .collect();
+
+    quote! {
+        /// Simulation callback of a test harness, see [cu29::testing].
+        #[allow(unused_variables, unreachable_patterns)]
+        pub fn harness_step(harness: &mut cu29::testing::CuTestHarness, step: SimStep) -> cu29::simulation::SimOverride {
+            match step {
+                #(#arms)*
+                _ => cu29::simulation::SimOverride::ExecuteByRuntime,
+            }
+        }
+    }
+}
+
 /// Adds #[copper_runtime(config = "path", sim_mode = false/true)] to your application struct to generate the runtime.
 /// if sim_mode is omitted, it is set to false.
+/// The configuration can be given inline instead with `config_inline = "(tasks: [...], cnx: [...])"` (or
+/// `config_str`), for the examples and to test a graph with the harness of cu29::testing.
+/// The configuration is embedded in the binary, at runtime the file is read again if it exists (resolved from the
+/// base directory of cu29::assets) so it can be tuned without a rebuild. With `embed_config = true` only the embedded
+/// one is used, for the targets shipping the binary alone like a phone.
+/// `config_env = "COPPER_CONFIG"` reads the path of the configuration file, absolute or relative to the crate, from
+/// this environment variable at build time; it overrides `config` and `config_inline` when it is set.
+/// An optional `graph_output = "target/graph.dot"` (or the COPPER_GRAPH_OUTPUT environment variable) writes the
+/// compiled graph at build time, in the Mermaid format if the file ends with .mmd or .mermaid, in dot otherwise.
+/// The generated types (CuMsgs, CuList, replay_step...) go in a `default` module, several applications can share
+/// the same module of a binary if they give it a different name with `mod_name = "replay"`.
+/// With `process = "perception"`, the application only runs the tasks the configuration assigns to this process,
+/// the connections with the other processes go through bridges (see cu29::config::CuConfig::partition).
 /// This will add a "runtime" field to your struct and implement the "new" and "run" methods.
 #[proc_macro_attribute]
 pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -232,13 +989,37 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
     eprintln!("[entry]");
     let mut application_struct = parse_macro_input!(input as ItemStruct);
     let mut config_file: Option<LitStr> = None;
+    let mut config_str: Option<LitStr> = None;
+    let mut config_env: Option<LitStr> = None;
+    let mut graph_output: Option<LitStr> = None;
+    let mut mod_name: Option<LitStr> = None;
+    let mut process: Option<LitStr> = None;
     let mut sim_mode = false;
+    let mut embed_config = false;
 
     // Custom parser for the attribute arguments
     let attribute_config_parser = parser(|meta| {
         if meta.path.is_ident("config") {
             config_file = Some(meta.value()?.parse()?);
             Ok(())
+        } else if meta.path.is_ident("config_inline") || meta.path.is_ident("config_str") {
+            config_str = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("config_env") {
+            config_env = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("graph_output") {
+            graph_output = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("mod_name") {
+            mod_name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("process") {
+            process = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("embed_config") {
+            embed_config = meta.value()?.parse::<syn::LitBool>()?.value();
+            Ok(())
         } else if meta.path.is_ident("sim_mode") {
             // Check if `sim_mode` has an explicit value (true/false)
             if meta.input.peek(syn::Token![=]) {
@@ -261,35 +1042,77 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
     // Parse the provided args with the custom parser
     parse_macro_input!(args with attribute_config_parser);
 
-    // Check if the config file was provided
-    let config_file = match config_file {
-        Some(file) => file.value(),
-        None => {
+    // A build farm can give a configuration out of the tree of the crate in an environment variable.
+    if let Some(env) = &config_env {
+        match std::env::var(env.value()) {
+            Ok(path) => {
+                config_file = Some(LitStr::new(&path, env.span()));
+                config_str = None;
+            }
+            Err(_) if config_file.is_some() || config_str.is_some() => {}
+            Err(_) => {
+                return return_error(format!(
+                    "The environment variable `{}` giving the configuration file is not set.",
+                    env.value()
+                ))
+            }
+        }
+    }
+
+    // The errors on the types of the tasks point at the configuration.
+    let config_span = config_file
+        .as_ref()
+        .or(config_str.as_ref())
+        .map_or_else(Span::call_site, LitStr::span);
+
+    // The configuration is a file or given inline, for the examples and the tests (see cu29::testing).
+    let (config_file, copper_config_content) = match (config_file, config_str) {
+        (Some(file), None) => {
+            let config_file = file.value();
+            if !std::path::Path::new(&config_full_path(&config_file)).exists() {
+                return return_error(format!(
+                    "The configuration file `{config_file}` does not exist. Please provide a valid path."
+                ));
+            }
+            match read_to_string(config_full_path(config_file.as_str())) {
+                Ok(content) => (Some(config_file), content),
+                Err(e) => return return_error(format!("Could not read the config file `{config_file}`. {e}"))
+            }
+        }
+        (None, Some(content)) => (None, content.value()),
+        _ => {
             return return_error(
-                "Expected config file attribute like #[CopperRuntime(config = \"path\")]"
+                "Expected a config file attribute like #[copper_runtime(config = \"path\")] or an inline configuration like #[copper_runtime(config_inline = \"(tasks: [...], cnx: [...])\")]"
                     .to_string(),
             )
         }
     };
 
-    if !std::path::Path::new(&config_full_path(&config_file)).exists() {
-        return return_error(format!(
-            "The configuration file `{config_file}` does not exist. Please provide a valid path."
-        ));
-    }
-
-    let copper_config = match read_config(&config_file) {
+    let copper_config = match read_configuration_str(copper_config_content.clone()) {
         Ok(cuconfig) => cuconfig,
         Err(e) => return return_error(e.to_string()),
     };
-    let copper_config_content = match read_to_string(config_full_path(config_file.as_str())) {
-        Ok(ok) => ok,
-        Err(e) => return return_error(format!("Could not read the config file (should not happen because we just succeeded just before). {e}"))
+    // The application of a process only runs its tasks and the bridges to the other processes.
+    let copper_config = match &process {
+        Some(process) => match copper_config.partition(&process.value()) {
+            Ok(cuconfig) => cuconfig,
+            Err(e) => return return_error(e.to_string()),
+        },
+        None => copper_config,
     };
+    if let Err(e) = check_msg_types(&copper_config) {
+        return return_error(e);
+    }
 
-    let mission = "default"; // FIXME(gbin) generate all the missions from the config.
-    let mission_mod =
-        parse_str::<Ident>(mission).expect("Could not make an identifier of the mission name");
+    let mission = mod_name.map_or("default".to_string(), |name| name.value()); // FIXME(gbin) generate all the missions from the config.
+    let mission_mod = match parse_str::<Ident>(&mission) {
+        Ok(ident) => ident,
+        Err(_) => {
+            return return_error(format!(
+                "mod_name should be a valid module name, got \"{mission}\"."
+            ))
+        }
+    };
 
     #[cfg(feature = "macro_debug")]
     eprintln!("[runtime plan for mission {mission}]");
@@ -300,12 +1123,117 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
     #[cfg(feature = "macro_debug")]
     eprintln!("{runtime_plan:?}");
 
+    let graph_output = graph_output
+        .map(|path| path.value())
+        .or_else(|| std::env::var("COPPER_GRAPH_OUTPUT").ok());
+    if let Some(graph_output) = graph_output {
+        if let Err(e) = write_graph_output(&copper_config, &graph_output) {
+            return return_error(e);
+        }
+    }
+
     #[cfg(feature = "macro_debug")]
     eprintln!("[extract tasks ids & types]");
-    let (all_tasks_ids, all_tasks_cutype, all_tasks_types_names, all_tasks_types) =
+    let (all_tasks_ids, all_tasks_cutype, all_tasks_types_names, mut all_tasks_types) =
         extract_tasks_types(&copper_config);
+    // The types of the tasks before they are wrapped or replaced, for the checks of their messages.
+    let user_tasks_types = all_tasks_types.clone();
+
+    let pipelined = copper_config
+        .runtime
+        .as_ref()
+        .is_some_and(|runtime| runtime.pipelined);
+    if pipelined {
+        // The sources acquire the next cycle on their own thread while the graph runs the current one.
+        for ((task_id, cutype), stype) in all_tasks_ids
+            .iter()
+            .zip(&all_tasks_cutype)
+            .zip(all_tasks_types.iter_mut())
+        {
+            if *cutype != CuTaskType::Source {
+                continue;
+            }
+            let msg_type = copper_config
+                .get_node_output_msg_type(task_id.as_str(), None) // FIXME(gbin): Multimission
+                .unwrap_or_else(|| panic!("CuSrcTask {task_id} should have an outgoing connection with a valid output msg type"));
+            let msg_type = copper_config
+                .resolve_msg_type(&msg_type)
+                .expect("Message types are checked before expansion");
+            let pipelined_task_name = format!(
+                "cu29::pipeline::CuPipelinedSrc<{}, {msg_type}>",
+                quote!(#stype)
+            );
+            *stype = parse_str(pipelined_task_name.as_str()).unwrap_or_else(|_| {
+                panic!("Could not build the pipelined source: {pipelined_task_name}")
+            });
+        }
+    }
+
+    // The actors run on their own thread, the copper loop only queues their input.
+    for (((node_id, node), cutype), stype) in copper_config
+        .get_all_nodes(None) // FIXME(gbin): Multimission
+        .into_iter()
+        .zip(&all_tasks_cutype)
+        .zip(all_tasks_types.iter_mut())
+    {
+        // A disabled task is replaced by a stub anyway.
+        let Some(actor) = node.get_actor().filter(|_| node.is_enabled()) else {
+            continue;
+        };
+        let task_id = node.get_id();
+        let inputs = copper_config
+            .get_dst_edges(node_id, None)
+            .map(|edges| edges.len())
+            .unwrap_or_default();
+        if *cutype != CuTaskType::Regular || inputs != 1 {
+            panic!("The actor \"{task_id}\" should be a task with a single input and an output.");
+        }
+        let [input_type, output_type] = [
+            copper_config.get_node_input_msg_type(&task_id, None), // FIXME(gbin): Multimission
+            copper_config.get_node_output_msg_type(&task_id, None),
+        ]
+        .map(|msg_type| {
+            copper_config
+                .resolve_msg_type(&msg_type.expect("The actor has an input and an output"))
+                .expect("Message types are checked before expansion")
+        });
+        let actor_task_name = format!(
+            "cu29::actor::CuActorTask<{}, {input_type}, {output_type}, {}, {}>",
+            quote!(#stype),
+            actor.queue,
+            actor.max_age_ms.unwrap_or_default()
+        );
+        *stype = parse_str(actor_task_name.as_str())
+            .unwrap_or_else(|_| panic!("Could not build the actor: {actor_task_name}"));
+    }
+
+    // The chunked tasks advance their computation by a budgeted chunk in every cycle.
+    for ((node, cutype), stype) in copper_config
+        .get_all_nodes(None) // FIXME(gbin): Multimission
+        .into_iter()
+        .map(|(_, node)| node)
+        .zip(&all_tasks_cutype)
+        .zip(all_tasks_types.iter_mut())
+    {
+        let Some(chunked) = node.get_chunked().filter(|_| node.is_enabled()) else {
+            continue;
+        };
+        if *cutype != CuTaskType::Regular {
+            panic!(
+                "The chunked task \"{}\" should be a task with inputs and an output.",
+                node.get_id()
+            );
+        }
+        let chunked_task_name = format!(
+            "cu29::chunked::CuChunked<{}, {}>",
+            quote!(#stype),
+            chunked.budget_us
+        );
+        *stype = parse_str(chunked_task_name.as_str())
+            .unwrap_or_else(|_| panic!("Could not build the chunked task: {chunked_task_name}"));
+    }
 
-    let all_sim_tasks_types: Vec<Type> = all_tasks_ids
+    let mut all_sim_tasks_types: Vec<Type> = all_tasks_ids
         .iter()
         .zip(&all_tasks_cutype)
         .zip(&all_tasks_types)
@@ -314,6 +1242,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                 let msg_type = copper_config
                     .get_node_output_msg_type(task_id.as_str(), None) // FIXME(gbin): Multimission
                     .unwrap_or_else(|| panic!("CuSrcTask {task_id} should have an outgoing connection with a valid output msg type"));
+                let msg_type = copper_config.resolve_msg_type(&msg_type).expect("Message types are checked before expansion");
                 let sim_task_name = format!("cu29::simulation::CuSimSrcTask<{msg_type}>");
                 parse_str(sim_task_name.as_str()).unwrap_or_else(|_| panic!("Could not build the placeholder for simulation: {sim_task_name}"))
             }
@@ -322,12 +1251,27 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                 let msg_type = copper_config
                     .get_node_input_msg_type(task_id.as_str(), None) // FIXME(gbin): Multimission
                     .unwrap_or_else(|| panic!("CuSinkTask {task_id} should have an incoming connection with a valid input msg type"));
+                let msg_type = copper_config.resolve_msg_type(&msg_type).expect("Message types are checked before expansion");
                 let sim_task_name = format!("cu29::simulation::CuSimSinkTask<{msg_type}>");
                 parse_str(sim_task_name.as_str()).unwrap_or_else(|_| panic!("Could not build the placeholder for simulation: {sim_task_name}"))
             }
         })
         .collect();
 
+    // The tasks disabled in the configuration are replaced by a stub, their type is not used at all.
+    let disabled_tasks: Vec<bool> = copper_config
+        .get_all_nodes(None) // FIXME(gbin): Multimission
+        .iter()
+        .map(|(_, node)| !node.is_enabled())
+        .collect();
+    let stub_task_type: Type = parse_quote!(cu29::cutask::CuStubTask);
+    for (index, disabled) in disabled_tasks.iter().enumerate() {
+        if *disabled {
+            all_tasks_types[index] = stub_task_type.clone();
+            all_sim_tasks_types[index] = stub_task_type.clone();
+        }
+    }
+
     #[cfg(feature = "macro_debug")]
     eprintln!("[build task tuples]");
     // Build the tuple of all those types
@@ -354,6 +1298,9 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
     #[cfg(feature = "macro_debug")]
     eprintln!("[build runtime field]");
     // add that to a new field
+    let cnx_buffers_field: Field = parse_quote! {
+        cnx_buffers: #mission_mod::CuCnxBuffers
+    };
     let runtime_field: Field = if sim_mode {
         parse_quote! {
             copper_runtime: cu29::curuntime::CuRuntime<#mission_mod::CuSimTasks, #mission_mod::CuMsgs, #monitor_type, #DEFAULT_CLNB>
@@ -371,9 +1318,11 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
     match &mut application_struct.fields {
         Named(fields_named) => {
             fields_named.named.push(runtime_field);
+            fields_named.named.push(cnx_buffers_field);
         }
         Unnamed(fields_unnamed) => {
             fields_unnamed.unnamed.push(runtime_field);
+            fields_unnamed.unnamed.push(cnx_buffers_field);
         }
         Fields::Unit => {
             panic!("This struct is a unit struct, it should have named or unnamed fields. use struct Something {{}} and not struct Something;")
@@ -389,8 +1338,15 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
             all_tasks_types_names[index], index
         );
 
+        let task_id = &all_tasks_ids[index];
+        if disabled_tasks[index] {
+            return quote! { cu29::cutask::CuStubTask };
+        }
         quote! {
-        <#ty>::new(all_instances_configs[#index]).map_err(|e| e.add_cause(#additional_error_info))?
+            {
+                cu29::config::check_config_keys(#task_id, <#ty>::CONFIG_SCHEMA, all_instances_configs[#index])?;
+                <#ty>::new(all_instances_configs[#index]).map_err(|e| e.add_cause(#additional_error_info))?
+            }
         }
     }).collect::<Vec<_>>();
 
@@ -411,9 +1367,19 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                 "Failed to get create instance for {}, instance index {}.",
                 all_tasks_types_names[index], index
             );
+            if disabled_tasks[index] {
+                // The stub has no lifecycle.
+                return (quote! { cu29::cutask::CuStubTask }, quote! {}, quote! {}, quote! {}, quote! {});
+            }
             (
-                quote! {
-                    #ty::new(all_instances_configs[#index]).map_err(|e| e.add_cause(#additional_error_info))?
+                {
+                    let task_id = &all_tasks_ids[index];
+                    quote! {
+                        {
+                            cu29::config::check_config_keys(#task_id, <#ty>::CONFIG_SCHEMA, all_instances_configs[#index])?;
+                            <#ty>::new(all_instances_configs[#index]).map_err(|e| e.add_cause(#additional_error_info))?
+                        }
+                    }
                 },
                 {
                     let monitoring_action = quote! {
@@ -460,7 +1426,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
 
                     quote! {
                         #call_sim_callback
-                        if doit {
+                        if doit && self.copper_runtime.profile.task_enabled(#index) {
                             let task = &mut self.copper_runtime.tasks.#task_index;
                             if let Err(error) = task.start(&self.copper_runtime.clock) {
                                 #monitoring_action
@@ -510,7 +1476,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     };
                     quote! {
                         #call_sim_callback
-                        if doit {
+                        if doit && self.copper_runtime.profile.task_enabled(#index) {
                             let task = &mut self.copper_runtime.tasks.#task_index;
                             if let Err(error) = task.stop(&self.copper_runtime.clock) {
                                 #monitoring_action
@@ -559,7 +1525,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     };
                     quote! {
                         #call_sim_callback
-                        if doit {
+                        if doit && self.copper_runtime.profile.task_enabled(#index) {
                             let task = &mut self.copper_runtime.tasks.#task_index;
                             if let Err(error) = task.preprocess(&self.copper_runtime.clock) {
                                 #monitoring_action
@@ -608,7 +1574,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     };
                     quote! {
                         #call_sim_callback
-                        if doit {
+                        if doit && self.copper_runtime.profile.task_enabled(#index) {
                             let task = &mut self.copper_runtime.tasks.#task_index;
                             if let Err(error) = task.postprocess(&self.copper_runtime.clock) {
                                 #monitoring_action
@@ -624,6 +1590,42 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
     // This records the task ids in call order.
     let mut taskid_call_order: Vec<usize> = Vec::new();
 
+    let (deliveries, cnx_buffers_types) = plan_deliveries(&copper_config, &runtime_plan);
+
+    // The placeholders of the sim mode and the stubs of the disabled tasks match by construction.
+    let task_type_checks: Vec<proc_macro2::TokenStream> = runtime_plan
+        .steps
+        .iter()
+        .map(|unit| match unit {
+            CuExecutionUnit::Step(step) => step,
+            CuExecutionUnit::Loop(_) => todo!("Needs to be implemented"),
+        })
+        .filter(|step| {
+            !disabled_tasks[step.node_id as usize]
+                && (!sim_mode || step.task_type == CuTaskType::Regular)
+        })
+        .map(|step| {
+            gen_task_type_checks(
+                step,
+                &user_tasks_types[step.node_id as usize],
+                &deliveries[&step.node_id],
+                task_config_location(
+                    config_file.as_deref(),
+                    &copper_config_content,
+                    &step.node.get_id(),
+                ),
+                config_span,
+            )
+        })
+        .collect();
+    let mut disableable_cnx: Vec<usize> = deliveries
+        .values()
+        .flatten()
+        .filter(|delivery| delivery.disableable)
+        .map(|delivery| delivery.cnx)
+        .collect();
+    disableable_cnx.sort();
+
     let runtime_plan_code: Vec<proc_macro2::TokenStream> = runtime_plan.steps
         .iter()
         .map(|unit| {
@@ -653,10 +1655,108 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     let comment_tokens: proc_macro2::TokenStream = parse_str(&comment_str).unwrap();
                     let tid = step.node_id as usize;
                     taskid_call_order.push(tid);
+                    let service_calls = gen_service_calls(&copper_config, step, &all_tasks_ids, &all_tasks_cutype);
+
+                    #[cfg(feature = "perf")]
+                    let (perf_start, perf_stop) = (
+                        quote! { self.copper_runtime.perf_counters.start(); },
+                        quote! {
+                            let perf_sample = self.copper_runtime.perf_counters.stop();
+                            self.copper_runtime.graph_description.record_perf(#tid, perf_sample);
+                        },
+                    );
+                    #[cfg(not(feature = "perf"))]
+                    let (perf_start, perf_stop) = (quote! {}, quote! {});
+
+                    #[cfg(feature = "chaos")]
+                    let (chaos_start, chaos_stop) = (
+                        quote! { let chaos_fault = self.copper_runtime.chaos.before_process(#tid, id); },
+                        quote! { let maybe_error = cu29::chaos::after_process(chaos_fault, maybe_error, cumsg_output); },
+                    );
+                    #[cfg(not(feature = "chaos"))]
+                    let (chaos_start, chaos_stop) = (quote! {}, quote! {});
+
+                    // Only the tasks with a budget on them pay for these measures.
+                    let budget = step.node.get_budget().copied().unwrap_or_default();
+                    let (budget_start, mut budget_stop) = if budget.memory_bytes.is_some() {
+                        (
+                            quote! { let budget_allocs = cu29::monitoring::ScopedAllocCounter::new(); },
+                            quote! { self.copper_runtime.budgets.record_allocated(#tid, budget_allocs.get_allocated()); },
+                        )
+                    } else {
+                        (quote! {}, quote! {})
+                    };
+                    if budget.bandwidth_bytes_per_s.is_some() {
+                        budget_stop.extend(quote! { self.copper_runtime.budgets.record_output(#tid, cumsg_output); });
+                    }
+                    // An output over the max_size of its connections fails like its process().
+                    if let Some(max_size) = copper_config.get_node_output_max_size(step.node_id, None) {
+                        budget_stop.extend(quote! {
+                            let maybe_error = maybe_error.and_then(|()| cumsg_output.check_max_size(#max_size));
+                        });
+                    }
+
+                    // Fed by routed connections, the task only runs when one of its inputs gets a payload.
+                    let step_deliveries = &deliveries[&step.node_id];
+                    let routed_check = step_deliveries.iter().any(|delivery| delivery.route.is_some()).then(|| {
+                        let has_payload = step
+                            .input_msg_indices_types
+                            .iter()
+                            .zip(step_deliveries)
+                            .map(|((index, _), delivery)| {
+                                let index = int2sliceindex(*index);
+                                let routed = delivery.route.map(|route| quote! { msgs.#index.is_routed_to(#route) && });
+                                match (delivery.policy, &delivery.buffer) {
+                                    (CnxPolicy::Latched, Some(buffer)) => quote! {
+                                        (#routed msgs.#index.payload().is_some() || self.cnx_buffers.#buffer.payload().is_some())
+                                    },
+                                    _ => quote! { (#routed msgs.#index.payload().is_some()) },
+                                }
+                            });
+                        quote! { && (#(#has_payload)||*) }
+                    });
+                    // The routes of the output are chosen again at every cycle.
+                    let reset_routes = step.node.is_routed().then(|| quote! { cumsg_output.route_to_all(); });
+
+                    // The best effort tasks give up their turn when the cycle is late.
+                    let shed_check = (step.priority == TaskPriority::BestEffort)
+                        .then(|| quote! { && !self.copper_runtime.shed_best_effort() });
+
+                    let profile_check = quote! {
+                        #reset_routes
+                        let enabled = self.copper_runtime.profile.task_enabled(#tid) #routed_check #shed_check;
+                        if !enabled {
+                            // Disabled by the run profile, without input routed to it or shed in a
+                            // late cycle, the destinations get an empty message.
+                            cumsg_output.clear_payload();
+                            cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::NotReady;
+                        }
+                    };
 
                     let task_enum_name = config_id_to_enum(&all_tasks_ids[tid]);
                     let enum_name = Ident::new(&task_enum_name, proc_macro2::Span::call_site());
 
+                    if disabled_tasks[tid] {
+                        let (output_index, _) = step
+                            .output_msg_index_type
+                            .as_ref()
+                            .expect("Every task should have an output message index.");
+                        let output_culist_index = int2sliceindex(*output_index);
+                        return quote! {
+                            {
+                                #comment_tokens
+                                // Disabled in the configuration, the destinations get an empty message.
+                                let cumsg_output = &mut msgs.#output_culist_index;
+                                let now: cu29::clock::OptionCuTime = self.copper_runtime.clock.now().into();
+                                cumsg_output.clear_payload();
+                                cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::NotReady;
+                                cumsg_output.metadata.process_time.start = now;
+                                cumsg_output.metadata.process_time.end = now;
+                                cumsg_output.metadata.stamp(id, &mut self.copper_runtime.msg_seqs[#output_culist_index]);
+                            }
+                        };
+                    }
+
                     let process_call = match step.task_type {
                         CuTaskType::Source => {
                             if let Some((index, _)) = &step.output_msg_index_type {
@@ -669,7 +1769,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                         Decision::Abort => {
                                             debug!("Process: ABORT decision from monitoring. Task '{}' errored out \
                                             during process. Skipping the processing of CL {}.", #mission_mod::TASKS_IDS[#tid], id);
+                                            self.copper_runtime.graph_description.record_stats(&#mission_mod::collect_metadata(&culist));
+                                            self.copper_runtime.budgets.end_of_copperlist(&self.copper_runtime.clock, &#mission_mod::collect_metadata(&culist));
                                             self.copper_runtime.monitor.process_copperlist(&#mission_mod::collect_metadata(&culist))?;
+                                            self.copper_runtime.monitor.process_cnx_stats(self.copper_runtime.graph_description.connections());
                                             self.copper_runtime.end_of_processing(id);
                                             return Ok(()); // this returns early from the one iteration call.
 
@@ -678,6 +1781,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                             debug!("Process: IGNORE decision from monitoring. Task '{}' errored out \
                                             during process. The runtime will continue with a forced empty message.", #mission_mod::TASKS_IDS[#tid]);
                                             cumsg_output.clear_payload();
+                                            cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::Error(0);
                                         }
                                         Decision::Shutdown => {
                                             debug!("Process: SHUTDOWN decision from monitoring. Task '{}' errored out \
@@ -710,14 +1814,25 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                         #comment_tokens
                                         {
                                             let cumsg_output = &mut msgs.#output_culist_index;
+                                            cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::Fresh;
+                                            #profile_check
                                             #call_sim_callback
                                             cumsg_output.metadata.process_time.start = self.copper_runtime.clock.now().into();
-                                            let maybe_error = if doit {
+                                            cu29::crash::enter_task(#tid, id);
+                                            #chaos_start
+                                            #perf_start
+                                            #budget_start
+                                            let maybe_error = if doit && enabled {
                                                 #task_instance.process(&self.copper_runtime.clock, cumsg_output)
                                             } else {
                                                 Ok(())
                                             };
+                                            #perf_stop
+                                            #chaos_stop
+                                            #budget_stop
                                             cumsg_output.metadata.process_time.end = self.copper_runtime.clock.now().into();
+                                            cumsg_output.metadata.stamp(id, &mut self.copper_runtime.msg_seqs[#output_culist_index]);
+                                            cu29::curuntime::track_validity(#mission_mod::TASKS_IDS[#tid], &mut self.copper_runtime.msg_validities[#tid], cumsg_output.metadata.validity);
                                             if let Err(error) = maybe_error {
                                                 #monitoring_action
                                             }
@@ -730,7 +1845,8 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                         }
                         CuTaskType::Sink => {
                             // collect the indices
-                            let indices = step.input_msg_indices_types.iter().map(|(index, _)| int2sliceindex(*index));
+                            let indices: Vec<_> = step.input_msg_indices_types.iter().map(|(index, _)| int2sliceindex(*index)).collect();
+                            let task_input = gen_task_input(step, &indices, &deliveries[&step.node_id]);
                             if let Some((output_index, _)) = &step.output_msg_index_type {
                                 let output_culist_index = int2sliceindex(*output_index);
 
@@ -741,7 +1857,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                         Decision::Abort => {
                                             debug!("Process: ABORT decision from monitoring. Task '{}' errored out \
                                             during process. Skipping the processing of CL {}.", #mission_mod::TASKS_IDS[#tid], id);
+                                            self.copper_runtime.graph_description.record_stats(&#mission_mod::collect_metadata(&culist));
+                                            self.copper_runtime.budgets.end_of_copperlist(&self.copper_runtime.clock, &#mission_mod::collect_metadata(&culist));
                                             self.copper_runtime.monitor.process_copperlist(&#mission_mod::collect_metadata(&culist))?;
+                                            self.copper_runtime.monitor.process_cnx_stats(self.copper_runtime.graph_description.connections());
                                             self.copper_runtime.end_of_processing(id);
                                             return Ok(()); // this returns early from the one iteration call.
 
@@ -750,6 +1869,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                             debug!("Process: IGNORE decision from monitoring. Task '{}' errored out \
                                             during process. The runtime will continue with a forced empty message.", #mission_mod::TASKS_IDS[#tid]);
                                             cumsg_output.clear_payload();
+                                            cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::Error(0);
                                         }
                                         Decision::Shutdown => {
                                             debug!("Process: SHUTDOWN decision from monitoring. Task '{}' errored out \
@@ -784,10 +1904,30 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                         let cumsg_input = (#(&msgs.#indices),*);
                                         // This is the virtual output for the sink
                                         let cumsg_output = &mut msgs.#output_culist_index;
+                                        cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::worst([#(msgs.#indices.metadata.validity),*]);
+                                        #profile_check
                                         #call_sim_callback
                                         cumsg_output.metadata.process_time.start = self.copper_runtime.clock.now().into();
-                                        let maybe_error = if doit {#task_instance.process(&self.copper_runtime.clock, cumsg_input)} else {Ok(())};
+                                        cu29::crash::enter_task(#tid, id);
+                                        #chaos_start
+                                        #perf_start
+                                        #budget_start
+                                        let maybe_error = if !enabled {
+                                            Ok(())
+                                        } else if cu29::estop::is_engaged() {
+                                            // Actuation is blocked until the e-stop is reset.
+                                            #task_instance.safe_state(&self.copper_runtime.clock)
+                                        } else if doit {
+                                            #task_instance.process(&self.copper_runtime.clock, #task_input)
+                                        } else {
+                                            Ok(())
+                                        };
+                                        #perf_stop
+                                        #chaos_stop
+                                        #budget_stop
                                         cumsg_output.metadata.process_time.end = self.copper_runtime.clock.now().into();
+                                        cumsg_output.metadata.stamp(id, &mut self.copper_runtime.msg_seqs[#output_culist_index]);
+                                        cu29::curuntime::track_validity(#mission_mod::TASKS_IDS[#tid], &mut self.copper_runtime.msg_validities[#tid], cumsg_output.metadata.validity);
                                         if let Err(error) = maybe_error {
                                             #monitoring_action
                                         }
@@ -798,9 +1938,16 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                             }
                         }
                         CuTaskType::Regular => {
-                            let indices = step.input_msg_indices_types.iter().map(|(index, _)| int2sliceindex(*index));
+                            let indices: Vec<_> = step.input_msg_indices_types.iter().map(|(index, _)| int2sliceindex(*index)).collect();
+                            let task_input = gen_task_input(step, &indices, &deliveries[&step.node_id]);
                             if let Some((output_index, _)) = &step.output_msg_index_type {
                                 let output_culist_index = int2sliceindex(*output_index);
+                                let task_process = match &service_calls {
+                                    Some(calls) => quote! {
+                                        cu29::service::CuServiceClient::process_with_services(&mut #task_instance, &self.copper_runtime.clock, #calls, #task_input, cumsg_output)
+                                    },
+                                    None => quote! { #task_instance.process(&self.copper_runtime.clock, #task_input, cumsg_output) },
+                                };
 
                                 let monitoring_action = quote! {
                                     debug!("Task {}: Error during process: {}", #mission_mod::TASKS_IDS[#tid], &error);
@@ -809,7 +1956,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                         Decision::Abort => {
                                             debug!("Process: ABORT decision from monitoring. Task '{}' errored out \
                                             during process. Skipping the processing of CL {}.", #mission_mod::TASKS_IDS[#tid], id);
+                                            self.copper_runtime.graph_description.record_stats(&#mission_mod::collect_metadata(&culist));
+                                            self.copper_runtime.budgets.end_of_copperlist(&self.copper_runtime.clock, &#mission_mod::collect_metadata(&culist));
                                             self.copper_runtime.monitor.process_copperlist(&#mission_mod::collect_metadata(&culist))?;
+                                            self.copper_runtime.monitor.process_cnx_stats(self.copper_runtime.graph_description.connections());
                                             self.copper_runtime.end_of_processing(id);
                                             return Ok(()); // this returns early from the one iteration call.
 
@@ -818,6 +1968,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                             debug!("Process: IGNORE decision from monitoring. Task '{}' errored out \
                                             during process. The runtime will continue with a forced empty message.", #mission_mod::TASKS_IDS[#tid]);
                                             cumsg_output.clear_payload();
+                                            cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::Error(0);
                                         }
                                         Decision::Shutdown => {
                                             debug!("Process: SHUTDOWN decision from monitoring. Task '{}' errored out \
@@ -852,10 +2003,21 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                                         #comment_tokens
                                         let cumsg_input = (#(&msgs.#indices),*);
                                         let cumsg_output = &mut msgs.#output_culist_index;
+                                        cumsg_output.metadata.validity = cu29::cutask::CuMsgValidity::worst([#(msgs.#indices.metadata.validity),*]);
+                                        #profile_check
                                         #call_sim_callback
                                         cumsg_output.metadata.process_time.start = self.copper_runtime.clock.now().into();
-                                        let maybe_error = if doit {#task_instance.process(&self.copper_runtime.clock, cumsg_input, cumsg_output)} else {Ok(())};
+                                        cu29::crash::enter_task(#tid, id);
+                                        #chaos_start
+                                        #perf_start
+                                        #budget_start
+                                        let maybe_error = if doit && enabled {#task_process} else {Ok(())};
+                                        #perf_stop
+                                        #chaos_stop
+                                        #budget_stop
                                         cumsg_output.metadata.process_time.end = self.copper_runtime.clock.now().into();
+                                        cumsg_output.metadata.stamp(id, &mut self.copper_runtime.msg_seqs[#output_culist_index]);
+                                        cu29::curuntime::track_validity(#mission_mod::TASKS_IDS[#tid], &mut self.copper_runtime.msg_validities[#tid], cumsg_output.metadata.validity);
                                         if let Err(error) = maybe_error {
                                             #monitoring_action
                                         }
@@ -883,12 +2045,26 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
 
     #[cfg(feature = "macro_debug")]
     eprintln!("[build the copperlist support]");
-    let culist_support: proc_macro2::TokenStream =
-        gen_culist_support(&runtime_plan, &taskid_call_order, &all_tasks_member_ids);
+    let culist_support: proc_macro2::TokenStream = gen_culist_support(
+        &copper_config,
+        &runtime_plan,
+        &taskid_call_order,
+        &all_tasks_member_ids,
+    );
 
     #[cfg(feature = "macro_debug")]
     eprintln!("[build the sim support]");
     let sim_support: proc_macro2::TokenStream = gen_sim_support(&runtime_plan);
+    let replay_support: Option<proc_macro2::TokenStream> = if sim_mode {
+        Some(gen_replay_support(&runtime_plan))
+    } else {
+        None
+    };
+    let harness_support: Option<proc_macro2::TokenStream> = if sim_mode {
+        Some(gen_harness_support(&runtime_plan))
+    } else {
+        None
+    };
 
     let (new, run_one_iteration, start_all_tasks, stop_all_tasks, run) = if sim_mode {
         (
@@ -939,6 +2115,13 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
         None
     };
 
+    // In simulation the clock is driven by the simulator, it is up to it to pace the loop.
+    let wait_for_next_cycle = if sim_mode {
+        None
+    } else {
+        Some(quote!(self.copper_runtime.wait_for_next_cycle();))
+    };
+
     let sim_callback_on_new_calls = all_tasks_ids.iter().enumerate().map(|(i, id)| {
         let enum_name = config_id_to_enum(id);
         let enum_ident = Ident::new(&enum_name, Span::call_site());
@@ -950,17 +2133,120 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let sim_callback_on_new = if sim_mode {
         Some(quote! {
-            let all_instances_configs: Vec<Option<&ComponentConfig>> = config
-                .get_all_nodes(None) // FIXME(gbin): Multimission
-                .iter()
-                .map(|(_, node)| node.get_instance_config())
-                .collect();
+            let all_tasks_configs = config.get_all_tasks_configs(None); // FIXME(gbin): Multimission
+            let all_instances_configs: Vec<Option<&ComponentConfig>> =
+                all_tasks_configs.iter().map(Option::as_ref).collect();
             #(#sim_callback_on_new_calls)*
         })
     } else {
         None
     };
 
+    // The simulations keep the clock they are given, the others can be slaved to an external time base.
+    let configured_clock = if sim_mode {
+        None
+    } else {
+        Some(quote! {
+            let clock = cu29::curuntime::configured_clock(clock, &config)?;
+        })
+    };
+
+    // Checks the invariants of the connections at the end of every copper list, see cu29::invariants.
+    let check_invariants = copper_config
+        .invariants
+        .as_ref()
+        .filter(|invariants| !invariants.is_empty())
+        .map(|_| {
+            quote! {
+                self.copper_runtime.invariants.check(&self.copper_runtime.clock, &culist.msgs);
+            }
+        });
+
+    // Lets a developer tap the connections from the outside at the end of every copper list.
+    #[cfg(feature = "tap")]
+    let publish_taps = quote! {
+        self.copper_runtime.taps.poll();
+        cu29::tap::CuTapped::publish_taps(&culist.msgs, id, &mut self.copper_runtime.taps);
+    };
+    #[cfg(not(feature = "tap"))]
+    let publish_taps = quote! {};
+
+    #[cfg(feature = "tap")]
+    let tap_methods = quote! {
+        /// Copies the messages of a connection, given as `src->dst` or `src`, to a sink for debugging.
+        pub fn tap(&mut self, cnx: &str, sink: impl cu29::tap::CuTapSink + 'static) -> CuResult<()> {
+            self.copper_runtime.taps.attach(cnx, sink)
+        }
+
+        /// Receives the messages of a connection, given as `src->dst` or `src`, outside of the
+        /// graph, `capacity` messages at most, see [cu29::tap::CuListener].
+        pub fn subscribe<T: cu29::bincode::Decode<()> + Send + 'static>(&mut self, cnx: &str, capacity: usize) -> CuResult<cu29::tap::CuListener<T>> {
+            self.copper_runtime.taps.subscribe(cnx, capacity)
+        }
+
+        /// Lets the `cu29-topic` tool attach taps to this application through this address.
+        pub fn listen_for_taps(&mut self, addr: impl std::net::ToSocketAddrs) -> CuResult<std::net::SocketAddr> {
+            self.copper_runtime.taps.listen(addr)
+        }
+    };
+    #[cfg(not(feature = "tap"))]
+    let tap_methods = quote! {};
+
+    #[cfg(feature = "chaos")]
+    let chaos_methods = quote! {
+        /// Injects the faults of the scenario in the tasks from the next copper list on.
+        pub fn inject_chaos(&mut self, scenario: cu29::chaos::ChaosScenario) -> CuResult<()> {
+            self.copper_runtime.chaos = cu29::chaos::CuChaos::new(&scenario, &self.copper_runtime.graph_description)?;
+            Ok(())
+        }
+    };
+    #[cfg(not(feature = "chaos"))]
+    let chaos_methods = quote! {};
+
+    // Runs the application in a test, see cu29::testing.
+    let harness_methods = if sim_mode {
+        Some(quote! {
+            /// An instance for a test harness: on its mock clock and without log.
+            pub fn new_for_test(harness: &cu29::testing::CuTestHarness) -> CuResult<Self> {
+                let mut sim_callback = |_step: #mission_mod::SimStep<'_>| -> cu29::simulation::SimOverride {
+                    cu29::simulation::SimOverride::ExecuteByRuntime
+                };
+                Self::new(
+                    harness.clock(),
+                    Arc::new(Mutex::new(UnifiedLoggerWrite::null())),
+                    None,
+                    &mut sim_callback,
+                )
+            }
+
+            /// Starts the tasks, runs `cycles` copperlists with the sources given by the harness and
+            /// the inputs of the sinks recorded in it, then stops the tasks.
+            pub fn run_with_harness(&mut self, harness: &mut cu29::testing::CuTestHarness, cycles: usize) -> CuResult<()> {
+                {
+                    let mut sim_callback = |step: #mission_mod::SimStep<'_>| -> cu29::simulation::SimOverride {
+                        #mission_mod::harness_step(harness, step)
+                    };
+                    self.start_all_tasks(&mut sim_callback)?;
+                }
+                for _ in 0..cycles {
+                    {
+                        let mut sim_callback = |step: #mission_mod::SimStep<'_>| -> cu29::simulation::SimOverride {
+                            #mission_mod::harness_step(harness, step)
+                        };
+                        self.run_one_iteration(&mut sim_callback)?;
+                    }
+                    harness.end_of_cycle();
+                }
+                let mut sim_callback = |step: #mission_mod::SimStep<'_>| -> cu29::simulation::SimOverride {
+                    #mission_mod::harness_step(harness, step)
+                };
+                self.stop_all_tasks(&mut sim_callback)
+            }
+        })
+    } else {
+        None
+    };
+
     #[cfg(feature = "macro_debug")]
     eprintln!("[build the run methods]");
     let run_methods = quote! {
@@ -983,7 +2269,13 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     let e2en: u64 = e2e.into();
                 } // drop(md);
 
+                self.copper_runtime.graph_description.record_stats(&#mission_mod::collect_metadata(&culist));
+
+                self.copper_runtime.budgets.end_of_copperlist(&self.copper_runtime.clock, &#mission_mod::collect_metadata(&culist));
+                #check_invariants
                 self.copper_runtime.monitor.process_copperlist(&#mission_mod::collect_metadata(&culist))?;
+                self.copper_runtime.monitor.process_cnx_stats(self.copper_runtime.graph_description.connections());
+                #publish_taps
                 self.copper_runtime.end_of_processing(id);
 
            }// drop(culist); avoids a double mutable borrow
@@ -1010,6 +2302,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                 if error.is_err() {
                     break error;
                 }
+                #wait_for_next_cycle
             };
             debug!("A task errored out: {}", &error);
             self.stop_all_tasks(#sim_callback_arg)?;
@@ -1029,31 +2322,113 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
         quote!(tasks_instanciator)
     };
 
+    // What the manifest of the logs knows from the build, see cu29::manifest.
+    let crate_root = utils::caller_crate_root();
+    let git_commit = match utils::git_commit(&crate_root) {
+        Some(commit) => quote!(Some(#commit)),
+        None => quote!(None),
+    };
+    let monitor_type_name = copper_config
+        .get_monitor_config()
+        .map(|monitor_config| monitor_config.get_type());
+    let components_versions: Vec<proc_macro2::TokenStream> = utils::components_versions(
+        &crate_root,
+        all_tasks_types_names
+            .iter()
+            .map(String::as_str)
+            .chain(monitor_type_name),
+    )
+    .into_iter()
+    .map(|(krate, version)| quote!((#krate, #version)))
+    .collect();
+
+    let read_config = match &config_file {
+        Some(_) if embed_config => quote! {
+            /// The configuration used without override: the one the project was compiled with.
+            pub fn read_config() -> CuResult<CuConfig> {
+                cu29::config::read_configuration_str(Self::get_original_config())
+            }
+        },
+        Some(config_file) => quote! {
+            /// The configuration used without override: the configuration file if it exists at runtime,
+            /// the one the project was compiled with otherwise.
+            pub fn read_config() -> CuResult<CuConfig> {
+                let config_path = cu29::assets::resolve(#config_file);
+                if config_path.exists() {
+                    let config_filename = config_path.to_string_lossy().to_string();
+                    debug!("CuConfig: Reading configuration from file: {}", &config_filename);
+                    cu29::config::read_configuration(&config_filename)
+                } else {
+                    let original_config = Self::get_original_config();
+                    debug!("CuConfig: Using the original configuration the project was compiled with: {}", &original_config);
+                    cu29::config::read_configuration_str(original_config)
+                }
+            }
+        },
+        None => quote! {
+            /// The configuration used without override: the one given inline.
+            pub fn read_config() -> CuResult<CuConfig> {
+                cu29::config::read_configuration_str(Self::get_original_config())
+            }
+        },
+    };
+
+    let partition_config = process.map(|process| {
+        quote! {
+            let config = config.partition(#process)?;
+        }
+    });
+
     let application_impl = quote! {
         impl #name {
 
+            #read_config
+
             #new {
-                let config_filename = #config_file;
-                let config = if config_override.is_some() {
-                    let overridden_config = config_override.unwrap();
-                    debug!("CuConfig: Overridden programmatically: {}", &overridden_config.serialize_ron());
-                    overridden_config
-                } else if std::path::Path::new(config_filename).exists() {
-                    debug!("CuConfig: Reading configuration from file: {}", config_filename);
-                    cu29::config::read_configuration(config_filename)?
-                } else {
-                    let original_config = Self::get_original_config();
-                    debug!("CuConfig: Using the original configuration the project was compiled with: {}", &original_config);
-                    cu29::config::read_configuration_str(original_config)?
+                let config = match config_override {
+                    Some(overridden_config) => {
+                        debug!("CuConfig: Overridden programmatically: {}", &overridden_config.serialize_ron());
+                        overridden_config
+                    }
+                    None => Self::read_config()?,
                 };
+                #partition_config
+
+                // Written first so the log describes itself, see cu29::manifest.
+                let mut manifest_stream = stream_write::<cu29::manifest::CuLogManifest>(
+                    unified_logger.clone(),
+                    UnifiedLogType::Manifest,
+                    4096,
+                );
+                cu29::prelude::WriteStream::log(
+                    &mut manifest_stream,
+                    &cu29::manifest::CuLogManifest::new(
+                        stringify!(#name),
+                        env!("CARGO_PKG_VERSION"),
+                        #git_commit,
+                        &config,
+                        &[#(#components_versions),*],
+                    ),
+                )?;
+                drop(manifest_stream);
 
                 // For simple cases we can say the section is just a bunch of Copper Lists.
                 // But we can now have allocations outside of it so we can override it from the config.
-                let mut default_section_size = std::mem::size_of::<super::#mission_mod::CuList>() * 64;
+                // When all the connections declare the max_size of their messages, the bound of the
+                // encoded copper list is known at compile time and sizes the sections exactly.
+                let culist_max_size = super::#mission_mod::CULIST_MAX_ENCODED_SIZE;
+                let mut default_section_size = culist_max_size.unwrap_or(std::mem::size_of::<super::#mission_mod::CuList>()) * 64;
                 // Check if there is a logging configuration with section_size_mib
                 if let Some(section_size_mib) = config.logging.as_ref().and_then(|l| l.section_size_mib) {
                     // Convert MiB to bytes
                     default_section_size = section_size_mib as usize * 1024usize * 1024usize;
+                    if let Some(culist_max_size) = culist_max_size {
+                        if default_section_size < culist_max_size {
+                            return Err(CuError::from(format!(
+                                "The section_size_mib of {section_size_mib} MiB cannot hold a copper list of up to {culist_max_size} bytes."
+                            )));
+                        }
+                    }
                 }
                 let copperlist_stream = stream_write::<#mission_mod::CuList>(
                     unified_logger.clone(),
@@ -1064,15 +2439,40 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     // This is to be sure we have the size of at least a Culist and some.
                 );
 
+                // Written once so the exporters can tell which payload versions this log holds.
+                let mut schema_stream = stream_write::<Vec<cu29::schema::CuSchemaTag>>(
+                    unified_logger.clone(),
+                    UnifiedLogType::Schema,
+                    4096,
+                );
+                cu29::prelude::WriteStream::log(
+                    &mut schema_stream,
+                    &<#mission_mod::CuMsgs as cu29::schema::CuSchemaTagged>::schema_tags(),
+                )?;
+                drop(schema_stream);
+
                 // FIXME(gbin): mission support
 
+                #configured_clock
+                let mut copper_runtime = CuRuntime::<#mission_mod::#tasks_type, #mission_mod::CuMsgs, #monitor_type, #DEFAULT_CLNB>::new(
+                    clock,
+                    &config,
+                    #mission_mod::#tasks_instanciator,
+                    #mission_mod::monitor_instanciator,
+                    copperlist_stream)?;
+                copper_runtime.profile.check_disableable(&[#(#disableable_cnx),*])?;
+                // The events marked by the tasks, see cu29::events.
+                copper_runtime.set_event_logger(stream_write::<cu29::events::CuEvent>(
+                    unified_logger.clone(),
+                    UnifiedLogType::Event,
+                    4096,
+                ));
+                // Bounds the log as configured and surfaces it reaching its capacity.
+                copper_runtime.set_log_storage(&config, unified_logger.clone())?;
+
                 let application = Ok(#name {
-                    copper_runtime: CuRuntime::<#mission_mod::#tasks_type, #mission_mod::CuMsgs, #monitor_type, #DEFAULT_CLNB>::new(
-                        clock,
-                        &config,
-                        #mission_mod::#tasks_instanciator,
-                        #mission_mod::monitor_instanciator,
-                        copperlist_stream)?,
+                    copper_runtime,
+                    cnx_buffers: Default::default(),
                 });
 
                 #sim_callback_on_new
@@ -1084,6 +2484,22 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                 #copper_config_content.to_string()
             }
 
+            /// The tasks and connections of the application with their live counters.
+            pub fn introspect(&self) -> cu29::introspection::CuIntrospection {
+                self.copper_runtime.introspect()
+            }
+
+            /// Lets the `cu29-param` tool list, get and set the parameters through this address.
+            pub fn listen_for_params(&mut self, addr: impl std::net::ToSocketAddrs) -> CuResult<std::net::SocketAddr> {
+                self.copper_runtime.params.listen(addr)
+            }
+
+            #tap_methods
+
+            #chaos_methods
+
+            #harness_methods
+
             #run_methods
         }
     };
@@ -1102,6 +2518,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     clock: Option<RobotClock>,
                     unified_logger: Option<Arc<Mutex<UnifiedLoggerWrite>>>,
                     config_override: Option<CuConfig>,
+                    namespace: Option<String>,
+                    profile: Option<String>,
+                    crash_hook: bool,
+                    crash_context_file: Option<std::path::PathBuf>,
                     sim_callback: Option<&'a mut F>
                 }
             },
@@ -1111,6 +2531,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                         clock: None,
                         unified_logger: None,
                         config_override: None,
+                        namespace: None,
+                        profile: None,
+                        crash_hook: false,
+                        crash_context_file: None,
                         sim_callback: None,
                     }
                 }
@@ -1139,6 +2563,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     clock: Option<RobotClock>,
                     unified_logger: Option<Arc<Mutex<UnifiedLoggerWrite>>>,
                     config_override: Option<CuConfig>,
+                    namespace: Option<String>,
+                    profile: Option<String>,
+                    crash_hook: bool,
+                    crash_context_file: Option<std::path::PathBuf>,
                 }
             },
             quote! {
@@ -1147,6 +2575,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                         clock: None,
                         unified_logger: None,
                         config_override: None,
+                        namespace: None,
+                        profile: None,
+                        crash_hook: false,
+                        crash_context_file: None,
                     }
                 }
             },
@@ -1158,6 +2590,23 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
         )
     };
 
+    // The injection sources are replaced in simulation, their injectors are just dropped.
+    let check_injectors = if sim_mode {
+        quote! {
+            cu29::inject::unclaimed();
+        }
+    } else {
+        quote! {
+            let unclaimed = cu29::inject::unclaimed();
+            if !unclaimed.is_empty() {
+                return Err(CuError::from(format!(
+                    "No ExternalInputTask takes the injectors {}",
+                    unclaimed.join(", ")
+                )));
+            }
+        }
+    };
+
     let application_builder = quote! {
         #builder_struct
 
@@ -1175,6 +2624,12 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                 self
             }
 
+            /// Runs the application without a log: nothing is written and no slab is allocated.
+            pub fn with_null_logger(mut self) -> Self {
+                self.unified_logger = Some(Arc::new(Mutex::new(UnifiedLoggerWrite::null())));
+                self
+            }
+
             pub fn with_context(mut self, copper_ctx: &CopperContext) -> Self {
                 self.clock = Some(copper_ctx.clock.clone());
                 self.unified_logger = Some(copper_ctx.unified_logger.clone());
@@ -1186,21 +2641,97 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
                     self
             }
 
+            /// Overrides the namespace of the configuration, for example with the name of the robot.
+            pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+                self.namespace = Some(namespace.into());
+                self
+            }
+
+            /// Runs the application with this profile of the configuration, see [cu29::profile]. The
+            /// profile of the `COPPER_PROFILE` environment variable is used otherwise, if any.
+            pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+                self.profile = Some(profile.into());
+                self
+            }
+
+            /// Installs a panic hook making the log readable up to the panic and writing a crash
+            /// record (message, task, copperlist) in it before aborting, see [cu29::crash].
+            pub fn with_crash_hook(mut self) -> Self {
+                self.crash_hook = true;
+                self
+            }
+
+            /// Installs the crash hook and writes the crash context in JSON to this file at a
+            /// panic: the last task of every thread, its copperlist and the last log entries.
+            pub fn with_crash_context_file(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+                self.crash_hook = true;
+                self.crash_context_file = Some(path.into());
+                self
+            }
+
             #builder_sim_callback_method
 
+            /// Creates the handle pushing messages to the `cu29::inject::ExternalInputTask` of the
+            /// graph configured with this `name`, it holds `capacity` messages at most.
+            pub fn injector<P: Send + 'static>(&self, name: &str, capacity: usize) -> cu29::inject::CuInjector<P> {
+                cu29::inject::CuInjector::new(name, capacity)
+            }
+
             pub fn build(self) -> CuResult<#name> {
-                #name::new(
+                let profile = self
+                    .profile
+                    .or_else(|| std::env::var(cu29::profile::PROFILE_ENV).ok());
+                let config_override = if self.namespace.is_some() || profile.is_some() {
+                    let mut config = match self.config_override {
+                        Some(config) => config,
+                        None => #name::read_config()?,
+                    };
+                    if let Some(namespace) = self.namespace {
+                        config.namespace = Some(namespace);
+                    }
+                    if let Some(profile) = profile {
+                        config.profile = Some(profile);
+                    }
+                    Some(config)
+                } else {
+                    self.config_override
+                };
+                let unified_logger = self.unified_logger
+                    .ok_or(CuError::from("Unified logger missing from builder"))?;
+                let application = #name::new(
                     self.clock
                         .ok_or(CuError::from("Clock missing from builder"))?,
-                    self.unified_logger
-                        .ok_or(CuError::from("Unified logger missing from builder"))?,
-                    self.config_override,
+                    unified_logger.clone(),
+                    config_override,
                     #builder_build_sim_callback_arg
-                )
+                )?;
+                #check_injectors
+                if self.crash_hook {
+                    cu29::crash::install_crash_hook(
+                        unified_logger,
+                        application.copper_runtime.clock.clone(),
+                        #mission_mod::TASKS_IDS,
+                        self.crash_context_file,
+                    );
+                }
+                Ok(application)
             }
         }
     };
 
+    // Named after the application so several of them can live in the same module.
+    let app_mod = format_ident!(
+        "{}_app",
+        utils::config_id_to_struct_member(&name.to_string())
+    );
+
+    // option_env! makes cargo rebuild the application when the variable changes.
+    let config_env_tracking = config_env.map(|env| {
+        quote! {
+            const _: Option<&str> = option_env!(#env);
+        }
+    });
+
     #[cfg(feature = "macro_debug")]
     eprintln!("[build result]");
     // Convert the modified struct back into a TokenStream
@@ -1253,10 +2784,20 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
 
             pub const TASKS_IDS: &'static [&'static str] = &[#( #all_tasks_ids ),*];
 
+            /// The copies kept by the connections delivering with a Latched or Decimate policy.
+            #[derive(Default)]
+            pub struct CuCnxBuffers(#(pub #cnx_buffers_types),*);
+
             #culist_support
 
             #sim_support
 
+            #replay_support
+
+            #harness_support
+
+            #(#task_type_checks)*
+
             pub fn tasks_instanciator(all_instances_configs: Vec<Option<&ComponentConfig>>) -> CuResult<CuTasks> {
                 Ok(( #(#task_instances_init_code),*, ))
             }
@@ -1270,7 +2811,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
             }
         }
 
-        mod app {
+        mod #app_mod {
             use super::*;  // import the modules the main app did.
             use std::sync::Arc;
             use std::sync::Mutex;
@@ -1290,7 +2831,7 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
             use cu29::config::ComponentConfig;
 
             // FIXME(gbin): this is not good.
-            use default::SimStep;
+            use #mission_mod::SimStep;
 
             pub #application_struct
 
@@ -1299,8 +2840,10 @@ pub fn copper_runtime(args: TokenStream, input: TokenStream) -> TokenStream {
             #application_builder
         }
 
-        use app::#builder_name;
-        use app::#name;
+        use #app_mod::#builder_name;
+        use #app_mod::#name;
+
+        #config_env_tracking
     };
     let tokens: TokenStream = result.into();
 
@@ -1332,6 +2875,55 @@ fn config_full_path(config_file: &str) -> String {
     filename.to_string()
 }
 
+/// Renders the compiled graph to a file relative to the crate root.
+fn write_graph_output(copper_config: &CuConfig, graph_output: &str) -> Result<(), String> {
+    let path = PathBuf::from(config_full_path(graph_output));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Could not create the directory of {}: {e}", path.display()))?;
+    }
+    let mut rendered = Vec::new();
+    let mermaid = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("mmd") | Some("mermaid")
+    );
+    if mermaid {
+        copper_config.render_mermaid(&mut rendered, None)
+    } else {
+        copper_config.render(&mut rendered, None)
+    }
+    .map_err(|e| format!("Could not render the graph: {e}"))?;
+    // Only touch the file when the graph changes so it does not trigger rebuilds of its watchers.
+    if std::fs::read(&path).ok().as_deref() != Some(rendered.as_slice()) {
+        std::fs::write(&path, rendered)
+            .map_err(|e| format!("Could not write the graph to {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Checks that every connection message type, once its aliases are resolved, is a valid Rust type.
+fn check_msg_types(copper_config: &CuConfig) -> Result<(), String> {
+    let graph = copper_config
+        .get_graph(None) // FIXME(gbin): Multimission
+        .map_err(|e| e.to_string())?;
+    for edge in graph.edge_indices() {
+        let (src, dst) = graph.edge_endpoints(edge).unwrap();
+        let msg = &graph[edge].msg;
+        let (src_id, dst_id) = (graph[src].get_id(), graph[dst].get_id());
+        let resolved = copper_config
+            .resolve_msg_type(msg)
+            .map_err(|e| format!("Connection {src_id} -> {dst_id}: {e}"))?;
+        if parse_str::<Type>(&resolved).is_err() {
+            return Err(if resolved == *msg {
+                format!("Connection {src_id} -> {dst_id}: the message type `{msg}` is not a valid Rust type.")
+            } else {
+                format!("Connection {src_id} -> {dst_id}: the message type `{msg}` resolves to `{resolved}` through the `types` table, which is not a valid Rust type.")
+            });
+        }
+    }
+    Ok(())
+}
+
 /// Extract all the tasks types in their index order and their ids.
 fn extract_tasks_types(
     copper_config: &CuConfig,
@@ -1484,4 +3076,27 @@ mod tests {
         let t = trybuild::TestCases::new();
         t.compile_fail("tests/compile_fail/*/*.rs");
     }
+
+    #[test]
+    fn test_task_config_location() {
+        let config = r#"(
+    tasks: [
+        (id: "lidar", type: "tasks::Lidar"),
+        (
+            id : "id",
+            type: "tasks::Planner",
+        ),
+    ],
+    cnx: [(src: "lidar", dst: "id", msg: "Scan")],
+)"#;
+        assert_eq!(
+            super::task_config_location(Some("copperconfig.ron"), config, "lidar").as_deref(),
+            Some("copperconfig.ron:3")
+        );
+        assert_eq!(
+            super::task_config_location(None, config, "id").as_deref(),
+            Some("line 5 of the inline configuration")
+        );
+        assert_eq!(super::task_config_location(None, config, "camera"), None);
+    }
 }
@@ -1,5 +1,6 @@
 use convert_case::{Case, Casing};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use walkdir::WalkDir;
 
 /// Small tool to create a valid enum entry from an identifier.
@@ -71,6 +72,70 @@ pub fn caller_crate_root() -> PathBuf {
     current_dir
 }
 
+/// The commit the crate in `dir` is built from, "-dirty" if its tree has modifications.
+pub(crate) fn git_commit(dir: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(if dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    })
+}
+
+/// The versions locked in the Cargo.lock found from `dir` up, of the crates the component types
+/// come from (the first segment of "cu_pid::GenericPIDTask<f32>" for example).
+pub(crate) fn components_versions<'a>(
+    dir: &Path,
+    types: impl IntoIterator<Item = &'a str>,
+) -> Vec<(String, String)> {
+    let Some(lock) = dir
+        .ancestors()
+        .find_map(|dir| std::fs::read_to_string(dir.join("Cargo.lock")).ok())
+    else {
+        return Vec::new();
+    };
+    let mut crates: Vec<String> = types
+        .into_iter()
+        .filter_map(|ty| {
+            ty.split_once("::")
+                .map(|(krate, _)| krate.replace('_', "-"))
+        })
+        .collect();
+    crates.push("cu29".to_string());
+    crates.sort();
+    crates.dedup();
+    crates
+        .into_iter()
+        .filter_map(|krate| {
+            let version = locked_version(&lock, &krate)?;
+            Some((krate, version))
+        })
+        .collect()
+}
+
+/// The version of a package in a Cargo.lock, the lock lists `name` then `version` for each of them.
+fn locked_version(lock: &str, krate: &str) -> Option<String> {
+    let name = format!("name = \"{krate}\"");
+    let mut lines = lock.lines().map(str::trim);
+    lines.find(|line| *line == name)?;
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+        .map(str::to_string)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::utils::config_id_to_enum;
@@ -133,4 +198,27 @@ mod tests {
             "test_dunder"
         );
     }
+
+    #[test]
+    fn test_locked_version() {
+        let lock = r#"
+[[package]]
+name = "cu-pid"
+version = "0.7.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "cu29"
+version = "0.6.1"
+"#;
+        assert_eq!(
+            crate::utils::locked_version(lock, "cu-pid").as_deref(),
+            Some("0.7.0")
+        );
+        assert_eq!(
+            crate::utils::locked_version(lock, "cu29").as_deref(),
+            Some("0.6.1")
+        );
+        assert_eq!(crate::utils::locked_version(lock, "cu-p"), None);
+    }
 }
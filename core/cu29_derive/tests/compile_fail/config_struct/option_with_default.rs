@@ -0,0 +1,9 @@
+use cu29_derive::CuConfigStruct;
+
+#[derive(CuConfigStruct)]
+struct MyConfig {
+    #[config(default = 1.0)]
+    gain: Option<f64>,
+}
+
+fn main() {}
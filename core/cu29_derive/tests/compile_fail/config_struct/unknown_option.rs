@@ -0,0 +1,9 @@
+use cu29_derive::CuConfigStruct;
+
+#[derive(CuConfigStruct)]
+struct MyConfig {
+    #[config(minimum = 0)]
+    rate_hz: u32,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use cu29_derive::copper_runtime;
+
+#[copper_runtime(config = "config/invalid_type_alias.ron")]
+struct MyApplicationStruct;
+
+fn main() {}
@@ -0,0 +1,6 @@
+use cu29_derive::copper_runtime;
+
+#[copper_runtime(config_env = "COPPER_UNSET_CONFIG")]
+struct MyApplicationStruct;
+
+fn main() {}
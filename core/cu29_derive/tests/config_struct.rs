@@ -0,0 +1,102 @@
+use cu29::config::Value;
+use cu29::prelude::*;
+
+#[derive(CuConfigStruct, Debug)]
+struct SinkConfig {
+    topic: String,
+    #[config(default = "copper")]
+    prefix: String,
+    #[config(default = 10, range = 1..=100)]
+    rate_hz: u32,
+    #[config(range = 0.0..=1.0)]
+    gain: Option<f64>,
+    #[config(default)]
+    dryrun: bool,
+    #[config(key = "zenoh_config_file")]
+    session_config: Option<String>,
+}
+
+fn config(entries: &[(&str, Value)]) -> ComponentConfig {
+    let mut config = ComponentConfig::new();
+    for (key, value) in entries {
+        config.set(key, value.clone());
+    }
+    config
+}
+
+fn error(config: Option<&ComponentConfig>) -> String {
+    SinkConfig::from_config(config).unwrap_err().to_string()
+}
+
+#[test]
+fn test_defaults() {
+    let config = config(&[("topic", "robot/cmd".to_string().into())]);
+    let parsed = SinkConfig::from_config(Some(&config)).unwrap();
+    assert_eq!(parsed.topic, "robot/cmd");
+    assert_eq!(parsed.prefix, "copper");
+    assert_eq!(parsed.rate_hz, 10);
+    assert_eq!(parsed.gain, None);
+    assert!(!parsed.dryrun);
+    assert_eq!(parsed.session_config, None);
+}
+
+#[test]
+fn test_values() {
+    let config = config(&[
+        ("topic", "robot/cmd".to_string().into()),
+        ("rate_hz", 50u32.into()),
+        ("gain", 0.5f64.into()),
+        ("zenoh_config_file", "zenoh.json5".to_string().into()),
+    ]);
+    let parsed = SinkConfig::from_config(Some(&config)).unwrap();
+    assert_eq!(parsed.rate_hz, 50);
+    assert_eq!(parsed.gain, Some(0.5));
+    assert_eq!(parsed.session_config.as_deref(), Some("zenoh.json5"));
+}
+
+#[test]
+fn test_errors() {
+    assert!(error(None).contains("SinkConfig: the config key \"topic\" is required."));
+
+    let wrong_type = config(&[("topic", 42u32.into())]);
+    assert!(error(Some(&wrong_type))
+        .contains("SinkConfig: the config key \"topic\" should be a string, got 42."));
+
+    let out_of_range = config(&[("topic", "t".to_string().into()), ("gain", 2.0f64.into())]);
+    assert!(error(Some(&out_of_range))
+        .contains("SinkConfig: the config key \"gain\" should be in 0.0..=1.0, got 2."));
+
+    let too_large = config(&[
+        ("topic", "t".to_string().into()),
+        ("rate_hz", 500u32.into()),
+    ]);
+    assert!(error(Some(&too_large)).contains("should be in 1..=100, got 500."));
+}
+
+#[test]
+fn test_schema() {
+    let schema = SinkConfig::SCHEMA;
+    let names: Vec<&str> = schema.iter().map(|key| key.name).collect();
+    assert_eq!(
+        names,
+        [
+            "topic",
+            "prefix",
+            "rate_hz",
+            "gain",
+            "dryrun",
+            "zenoh_config_file"
+        ]
+    );
+    assert!(schema[0].required);
+    assert_eq!(schema[1].default, Some("copper"));
+    assert_eq!(schema[2].default, Some("10"));
+    assert!(!schema[3].required);
+    assert_eq!(schema[3].default, None);
+
+    let typo = config(&[("topick", "robot/cmd".to_string().into())]);
+    let error = cu29::config::check_config_keys("sink", Some(schema), Some(&typo))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("unknown config key \"topick\", did you mean \"topic\"?"));
+}
@@ -0,0 +1,99 @@
+use cu29::prelude::*;
+use cu29::testing::CuTestHarness;
+
+mod tasks {
+    use cu29::prelude::*;
+
+    // The harness stands in for the sources and the sinks, they are never built.
+    #[allow(dead_code)]
+    pub struct Source;
+
+    impl Freezable for Source {}
+
+    impl<'cl> CuSrcTask<'cl> for Source {
+        type Output = output_msg!('cl, i32);
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self> {
+            Ok(Self)
+        }
+
+        fn process(&mut self, _clock: &RobotClock, _output: Self::Output) -> CuResult<()> {
+            unreachable!("the harness gives the output of the sources")
+        }
+    }
+
+    pub struct Double;
+
+    impl Freezable for Double {}
+
+    impl<'cl> CuTask<'cl> for Double {
+        type Input = input_msg!('cl, i32);
+        type Output = output_msg!('cl, i32);
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self> {
+            Ok(Self)
+        }
+
+        fn process(
+            &mut self,
+            _clock: &RobotClock,
+            input: Self::Input,
+            output: Self::Output,
+        ) -> CuResult<()> {
+            match input.payload() {
+                Some(value) => output.set_payload(value * 2),
+                None => output.clear_payload(),
+            }
+            Ok(())
+        }
+    }
+
+    #[allow(dead_code)]
+    pub struct Sink;
+
+    impl Freezable for Sink {}
+
+    impl<'cl> CuSinkTask<'cl> for Sink {
+        type Input = input_msg!('cl, i32);
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self> {
+            Ok(Self)
+        }
+
+        fn process(&mut self, _clock: &RobotClock, _input: Self::Input) -> CuResult<()> {
+            unreachable!("the harness records the inputs of the sinks")
+        }
+    }
+}
+
+#[copper_runtime(
+    config_str = r#"(
+    tasks: [
+        (id: "src", type: "tasks::Source"),
+        (id: "double", type: "tasks::Double"),
+        (id: "sink", type: "tasks::Sink"),
+    ],
+    cnx: [
+        (src: "src", dst: "double", msg: "i32"),
+        (src: "double", dst: "sink", msg: "i32"),
+    ],
+)"#,
+    sim_mode = true
+)]
+struct TestApp {}
+
+#[test]
+fn test_double() {
+    let mut harness = CuTestHarness::new();
+    harness.inject("src", 21i32);
+    let mut app = TestApp::new_for_test(&harness).unwrap();
+    app.run_with_harness(&mut harness, 3).unwrap();
+    // Nothing was injected for the 2 last cycles, the source gave empty messages.
+    assert_eq!(harness.captured::<i32>("sink"), vec![Some(42), None, None]);
+    assert_eq!(harness.cycles(), 3);
+
+    harness.inject_all("src", [1, 2]);
+    harness.clear_captured();
+    app.run_with_harness(&mut harness, 2).unwrap();
+    assert_eq!(harness.captured::<i32>("sink"), vec![Some(2), Some(4)]);
+}
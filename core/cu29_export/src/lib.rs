@@ -1,12 +1,18 @@
 use std::fmt::{Display, Formatter};
-use std::io::Read;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
-use bincode::config::standard;
-use bincode::decode_from_std_read;
 use bincode::error::DecodeError;
+use bincode::{decode_from_slice, decode_from_std_read, Decode};
 use clap::{Parser, Subcommand, ValueEnum};
+use cu29::crash::CuCrashRecord;
+use cu29::events::CuEvent;
+use cu29::export::{CuExportFormat, CuOutputsExport, CuPayloadExport};
+use cu29::manifest::CuLogManifest;
 use cu29::prelude::*;
+use cu29::replay::{check_determinism, CuOutputsComparison, ReplayReport};
+use cu29::schema::{check_schema, CuSchemaTag, CuSchemaTagged};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum ExportFormat {
@@ -31,6 +37,14 @@ pub struct LogReaderCli {
     /// for example for toto_0.copper, toto_1.copper ... the base name is toto.copper
     pub unifiedlog_base: PathBuf,
 
+    /// Tails a log still being written, waiting for its new sections until it is closed
+    #[arg(long, global = true)]
+    pub follow: bool,
+
+    /// Skips the damaged sections of a log, after a power loss for example, and reports what was recovered
+    #[arg(long, global = true)]
+    pub salvage: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -44,19 +58,102 @@ pub enum Command {
         #[arg(short, long, default_value_t = ExportFormat::Json)]
         export_format: ExportFormat,
     },
+    /// Shows the payload versions the log was recorded with
+    Schema,
+    /// Shows what wrote the log: application, commit, configuration hash, components, host and start time
+    Manifest,
+    /// Shows the panic that stopped the application, recorded by its crash hook
+    Crash,
+    /// Lists the events marked by the tasks, with the copperlists they happened in
+    Events {
+        /// Only the events with this name
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Writes the payloads of a connection to numbered files (images to PNG, point clouds to PLY or PCD)
+    ExportPayloads {
+        /// The id of the task emitting the messages of the connection
+        #[arg(short, long)]
+        connection: String,
+        /// The first format supported by the payload by default
+        #[arg(short, long)]
+        format: Option<CuExportFormat>,
+        /// Where the files are written
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+}
+
+/// The command line of a determinism checker, see [run_determinism_cli].
+#[derive(Parser)]
+#[command(author, version, about)]
+pub struct DeterminismCli {
+    /// The base path of the reference log, as for [LogReaderCli]
+    pub reference_base: PathBuf,
+
+    /// The base path of the log compared to the reference, recorded from the same inputs
+    pub candidate_base: PathBuf,
+
+    /// Absolute difference accepted on the numbers found in the outputs
+    #[arg(short, long)]
+    pub tolerance: Option<f64>,
+
+    /// Skips the damaged sections of the logs, after a power loss for example
+    #[arg(long)]
+    pub salvage: bool,
+}
+
+/// This is a generator for a main function comparing the copperlists of two logs recorded from the
+/// same inputs, see [cu29::replay::check_determinism].
+pub fn run_determinism_cli<P>() -> CuResult<()>
+where
+    P: CopperListTuple + CuOutputsComparison + CuSchemaTagged,
+{
+    let args = DeterminismCli::parse();
+    check_log_schema::<P>(&args.reference_base)?;
+    check_log_schema::<P>(&args.candidate_base)?;
+    let open = |base: &Path| {
+        let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+            .file_base_name(base)
+            .salvage(args.salvage)
+            .build()
+            .expect("Failed to create logger")
+        else {
+            panic!("Failed to create logger");
+        };
+        UnifiedLoggerIOReader::new(dl, UnifiedLogType::CopperList)
+    };
+    let mut reference = open(&args.reference_base);
+    let mut candidate = open(&args.candidate_base);
+    let mut report = ReplayReport::default();
+    if let Some(tolerance) = args.tolerance {
+        report = report.with_default_tolerance(tolerance);
+    }
+    let report = check_determinism(
+        copperlists_dump::<P>(&mut reference),
+        copperlists_dump::<P>(&mut candidate),
+        report,
+    );
+    print!("{report}");
+    if !report.is_identical() {
+        return Err(CuError::from("The runs diverge."));
+    }
+    Ok(())
 }
 
 /// This is a generator for a main function to build a log extractor.
 /// It depends on the specific type of the CopperList payload that is determined at compile time from the configuration.
 pub fn run_cli<P>() -> CuResult<()>
 where
-    P: CopperListTuple,
+    P: CopperListTuple + CuSchemaTagged + CuOutputsExport,
 {
     let args = LogReaderCli::parse();
     let unifiedlog_base = args.unifiedlog_base;
 
     let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
         .file_base_name(&unifiedlog_base)
+        .follow(args.follow)
+        .salvage(args.salvage)
         .build()
         .expect("Failed to create logger")
     else {
@@ -69,6 +166,7 @@ where
             textlog_dump(reader, &log_index)?;
         }
         Command::ExtractCopperlist { export_format } => {
+            check_log_schema::<P>(&unifiedlog_base)?;
             println!("Extracting copperlists with format: {export_format}");
             let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::CopperList);
             let iter = copperlists_dump::<P>(&mut reader);
@@ -76,18 +174,117 @@ where
                 println!("{entry:#?}");
             }
         }
+        Command::Schema => match read_schema_tags(dl)? {
+            Some(recorded) => {
+                for (slot, tag) in recorded.iter().enumerate() {
+                    println!("{slot}: {tag}");
+                }
+                check_schema(&recorded, &P::schema_tags())?;
+                println!("The log can be decoded with the current payloads.");
+            }
+            None => println!("This log has been recorded without schema tags."),
+        },
+        Command::Manifest => match read_manifest(dl)? {
+            Some(manifest) => println!("{manifest}"),
+            None => println!("This log has been recorded without a manifest."),
+        },
+        Command::Crash => match read_crash_record(dl)? {
+            Some(record) => println!("{record}"),
+            None => println!("No crash recorded in this log."),
+        },
+        Command::Events { name } => {
+            let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::Event);
+            for event in events_dump(&mut reader) {
+                if name.as_ref().is_none_or(|name| *name == event.name) {
+                    println!("{event}");
+                }
+            }
+        }
+        Command::ExportPayloads {
+            connection,
+            format,
+            output,
+        } => {
+            check_log_schema::<P>(&unifiedlog_base)?;
+            let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::CopperList);
+            let count = export_payloads(
+                copperlists_dump::<P>(&mut reader),
+                &connection,
+                format,
+                &output,
+            )?;
+            println!(
+                "Exported {count} payloads of {connection} to {}",
+                output.display()
+            );
+        }
     }
 
     Ok(())
 }
 
+/// Reads the schema tags written by the runtime at startup.
+/// The logs recorded before their introduction don't have any.
+pub fn read_schema_tags(mut dl: UnifiedLoggerRead) -> CuResult<Option<Vec<CuSchemaTag>>> {
+    let Some(section) = dl.read_next_section_type(UnifiedLogType::Schema)? else {
+        return Ok(None);
+    };
+    let (tags, _) = decode_from_slice::<Vec<CuSchemaTag>, _>(&section, CuWireFormat::CONFIG)
+        .map_err(|e| CuError::new_with_cause("Could not decode the schema tags", e))?;
+    Ok(Some(tags))
+}
+
+/// Reads the crash record written by the crash hook of the runtime, see [cu29::crash].
+pub fn read_crash_record(mut dl: UnifiedLoggerRead) -> CuResult<Option<CuCrashRecord>> {
+    let Some(section) = dl.read_next_section_type(UnifiedLogType::Crash)? else {
+        return Ok(None);
+    };
+    let (record, _) = decode_from_slice::<CuCrashRecord, _>(&section, CuWireFormat::CONFIG)
+        .map_err(|e| CuError::new_with_cause("Could not decode the crash record", e))?;
+    Ok(Some(record))
+}
+
+/// Reads the manifest written by the runtime at startup, see [cu29::manifest].
+/// The logs recorded before its introduction don't have any.
+pub fn read_manifest(mut dl: UnifiedLoggerRead) -> CuResult<Option<CuLogManifest>> {
+    let Some(section) = dl.read_next_section_type(UnifiedLogType::Manifest)? else {
+        return Ok(None);
+    };
+    let (manifest, _) = decode_from_slice::<CuLogManifest, _>(&section, CuWireFormat::CONFIG)
+        .map_err(|e| CuError::new_with_cause("Could not decode the manifest", e))?;
+    Ok(Some(manifest))
+}
+
+/// Refuses to decode a log recorded with other payload versions than the ones of P.
+fn check_log_schema<P: CuSchemaTagged>(unifiedlog_base: &Path) -> CuResult<()> {
+    let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+        .file_base_name(unifiedlog_base)
+        .build()
+        .expect("Failed to create logger")
+    else {
+        panic!("Failed to create logger");
+    };
+    match read_schema_tags(dl) {
+        Ok(Some(recorded)) => check_schema(&recorded, &P::schema_tags()),
+        // Nothing to check against, try to decode it anyway.
+        Ok(None) | Err(_) => Ok(()),
+    }
+}
+
 /// Extracts the copper lists from a binary representation.
 /// P is the Payload determined by the configuration of the application.
-pub fn copperlists_dump<P: CopperListTuple>(
-    mut src: impl Read,
-) -> impl Iterator<Item = CopperList<P>> {
+pub fn copperlists_dump<P: CopperListTuple>(src: impl Read) -> impl Iterator<Item = CopperList<P>> {
+    entries_dump(src)
+}
+
+/// Extracts the events marked by the tasks from a binary representation.
+pub fn events_dump(src: impl Read) -> impl Iterator<Item = CuEvent> {
+    entries_dump(src)
+}
+
+fn entries_dump<T: Decode<()>>(mut src: impl Read) -> impl Iterator<Item = T> {
     std::iter::from_fn(move || {
-        let entry = decode_from_std_read::<CopperList<P>, _, _>(&mut src, standard());
+        let entry = decode_from_std_read::<T, _, _>(&mut src, CuWireFormat::CONFIG);
         match entry {
             Ok(entry) => Some(entry),
             Err(e) => match e {
@@ -109,6 +306,60 @@ pub fn copperlists_dump<P: CopperListTuple>(
     })
 }
 
+/// Writes every payload emitted by the task `connection` to `<output>/<connection>_<culist id>.<format>`.
+/// Returns the number of files written.
+pub fn export_payloads<P: CopperListTuple + CuOutputsExport>(
+    copperlists: impl Iterator<Item = CopperList<P>>,
+    connection: &str,
+    format: Option<CuExportFormat>,
+    output: &Path,
+) -> CuResult<usize> {
+    std::fs::create_dir_all(output).map_err(|e| {
+        CuError::new_with_cause(&format!("Could not create {}", output.display()), e)
+    })?;
+    let mut count = 0;
+    for culist in copperlists {
+        let mut result = Ok(());
+        culist
+            .msgs
+            .visit_exports(&mut |task_id, _metadata, exporter| {
+                if task_id == connection && result.is_ok() {
+                    let path = output.join(format!("{connection}_{:06}", culist.id));
+                    result = export_payload(connection, exporter, format, &path);
+                    count += result.is_ok() as usize;
+                }
+            });
+        result?;
+    }
+    Ok(count)
+}
+
+fn export_payload(
+    connection: &str,
+    exporter: Option<&dyn CuPayloadExport>,
+    format: Option<CuExportFormat>,
+    path: &Path,
+) -> CuResult<()> {
+    let exporter = exporter.ok_or_else(|| {
+        CuError::from(format!(
+            "The payload of {connection} can't be exported to a file, its type doesn't implement CuPayloadExport."
+        ))
+    })?;
+    let formats = exporter.export_formats();
+    let format = format.unwrap_or(formats[0]);
+    if !formats.contains(&format) {
+        return Err(CuError::from(format!(
+            "The payload of {connection} can't be exported as {format}."
+        )));
+    }
+    let path = path.with_extension(format.extension());
+    let write_error =
+        |e| CuError::new_with_cause(&format!("Could not write {}", path.display()), e);
+    let mut writer = BufWriter::new(File::create(&path).map_err(write_error)?);
+    exporter.export(format, &mut writer)?;
+    writer.flush().map_err(write_error)
+}
+
 /// Full dump of the copper structured log from its binary representation.
 /// This rebuilds a textual log.
 /// src: the source of the log data
@@ -116,7 +367,7 @@ pub fn copperlists_dump<P: CopperListTuple>(
 pub fn textlog_dump(mut src: impl Read, index: &Path) -> CuResult<()> {
     let all_strings = read_interned_strings(index)?;
     loop {
-        let entry = decode_from_std_read::<CuLogEntry, _, _>(&mut src, standard());
+        let entry = decode_from_std_read::<CuLogEntry, _, _>(&mut src, CuWireFormat::CONFIG);
 
         match entry {
             Err(DecodeError::UnexpectedEnd { .. }) => return Ok(()),
@@ -152,7 +403,6 @@ pub fn textlog_dump(mut src: impl Read, index: &Path) -> CuResult<()> {
 // only for users opting into python interface, not supported on macOS at the moment
 #[cfg(all(feature = "python", not(target_os = "macos")))]
 mod python {
-    use bincode::config::standard;
     use bincode::decode_from_std_read;
     use bincode::error::DecodeError;
     use cu29::prelude::*;
@@ -174,7 +424,7 @@ mod python {
         }
 
         fn __next__(mut slf: PyRefMut<Self>) -> Option<PyResult<PyCuLogEntry>> {
-            match decode_from_std_read::<CuLogEntry, _, _>(&mut slf.reader, standard()) {
+            match decode_from_std_read::<CuLogEntry, _, _>(&mut slf.reader, CuWireFormat::CONFIG) {
                 Ok(entry) => {
                     if entry.msg_index == 0 {
                         None
@@ -352,7 +602,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let temp_path = copy_stringindex_to_temp(&temp_dir);
         let entry = CuLogEntry::new(3);
-        let bytes = bincode::encode_to_vec(&entry, standard()).unwrap();
+        let bytes = bincode::encode_to_vec(&entry, CuWireFormat::CONFIG).unwrap();
         let reader = Cursor::new(bytes.as_slice());
         textlog_dump(reader, temp_path.as_path()).unwrap();
     }
@@ -421,8 +671,12 @@ mod tests {
         let mut offset: usize = 0;
         for pl in mypls.iter() {
             let cl = CopperList::<MyCuPayload>::new(1, *pl);
-            offset +=
-                encode_into_slice(&cl, &mut data.as_mut_slice()[offset..], standard()).unwrap();
+            offset += encode_into_slice(
+                &cl,
+                &mut data.as_mut_slice()[offset..],
+                CuWireFormat::CONFIG,
+            )
+            .unwrap();
         }
 
         let reader = Cursor::new(data);
@@ -433,4 +687,45 @@ mod tests {
         assert_eq!(iter.next().unwrap().msgs, (3, 4, 5.0));
         assert_eq!(iter.next().unwrap().msgs, (4, 5, 6.0));
     }
+
+    #[test]
+    fn test_events_dump() {
+        let dir = tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("test_events_dump.copper");
+        let event = |name: &str, culist_id: u32| CuEvent {
+            name: name.to_string(),
+            time: CuDuration::from(culist_id as u64 * 1_000_000),
+            culist_id,
+            metadata: "attempt: 1".to_string(),
+            flush: false,
+        };
+        let events = [event("grasp_attempt", 12), event("docking", 5000)];
+        {
+            let UnifiedLogger::Write(logger) = UnifiedLoggerBuilder::new()
+                .write(true)
+                .create(true)
+                .file_base_name(&path)
+                .preallocated_size(100000)
+                .build()
+                .expect("Failed to create logger")
+            else {
+                panic!("Failed to create logger")
+            };
+            let data_logger = Arc::new(Mutex::new(logger));
+            let mut stream = stream_write(data_logger.clone(), UnifiedLogType::Event, 1024);
+            stream.log(&events[0]).unwrap();
+            stream.flush().unwrap();
+            stream.log(&events[1]).unwrap();
+        }
+        let UnifiedLogger::Read(logger) = UnifiedLoggerBuilder::new()
+            .file_base_name(&path)
+            .build()
+            .expect("Failed to create logger")
+        else {
+            panic!("Failed to create logger")
+        };
+        let mut reader = UnifiedLoggerIOReader::new(logger, UnifiedLogType::Event);
+        let dumped: Vec<CuEvent> = events_dump(&mut reader).collect();
+        assert_eq!(dumped, events);
+    }
 }
@@ -1,8 +1,11 @@
 use cu29_clock::RobotClock;
 use cu29_log_runtime::LoggerRuntime;
+use cu29_runtime::assets;
 use cu29_runtime::curuntime::CopperContext;
-use cu29_traits::{CuResult, UnifiedLogType};
-use cu29_unifiedlog::{stream_write, UnifiedLogger, UnifiedLoggerBuilder};
+use cu29_traits::{CuError, CuResult, UnifiedLogType};
+use cu29_unifiedlog::{
+    stream_write, SlabStorage, UnifiedLogger, UnifiedLoggerBuilder, UnifiedLoggerWrite,
+};
 use simplelog::TermLogger;
 #[cfg(debug_assertions)]
 use simplelog::{ColorChoice, Config, LevelFilter, TerminalMode};
@@ -14,6 +17,7 @@ use std::sync::{Arc, Mutex};
 ///
 /// unifiedlogger_output_base_name: The base name of the log file. The logger will create a set of files based on this name
 ///                                  for example if named "toto.copper" it will create toto_0.copper, toto_1.copper etc.
+///                                  A relative name is resolved from the base directory of cu29_runtime::assets.
 ///
 /// text_log: if true, the log will be printed to the console as a simple log.
 /// It is useful to debug an application in real-time but should be set to false in production
@@ -33,7 +37,7 @@ pub fn basic_copper_setup(
     let UnifiedLogger::Write(logger) = UnifiedLoggerBuilder::new()
         .write(true)
         .create(true)
-        .file_base_name(unifiedlogger_output_base_name)
+        .file_base_name(&assets::resolve(unifiedlogger_output_base_name))
         .preallocated_size(preallocated_size)
         .build()
         .expect("Failed to create logger")
@@ -41,6 +45,46 @@ pub fn basic_copper_setup(
         panic!("Failed to create logger")
     };
     let unified_logger = Arc::new(Mutex::new(logger));
+    setup_logger_runtime(unified_logger, _text_log, clock)
+}
+
+/// The same setup as [basic_copper_setup] but with the slabs of the log in the given storage instead of files,
+/// for example the storage of an uploader or an in-memory ring (see `cu29_unifiedlog::storage`).
+pub fn storage_copper_setup(
+    storage: impl SlabStorage + 'static,
+    slab_size: Option<usize>,
+    text_log: bool,
+    clock: Option<RobotClock>,
+) -> CuResult<CopperContext> {
+    let preallocated_size = slab_size.unwrap_or(1024 * 1024 * 10);
+    let UnifiedLogger::Write(logger) = UnifiedLoggerBuilder::new()
+        .write(true)
+        .create(true)
+        .storage(storage)
+        .preallocated_size(preallocated_size)
+        .build()
+        .map_err(|e| CuError::new_with_cause("Failed to create logger", e))?
+    else {
+        panic!("Failed to create logger")
+    };
+    let unified_logger = Arc::new(Mutex::new(logger));
+    setup_logger_runtime(unified_logger, text_log, clock)
+}
+
+/// The same setup as [basic_copper_setup] but without any log, for CPU constrained targets or unit tests.
+/// The unified logger is the null logger: no file is created, no slab is allocated and the copperlists are not logged.
+///
+/// text_log: if true, the log lines are still printed to stderr in debug builds, they are dropped otherwise.
+pub fn logless_copper_setup(text_log: bool, clock: Option<RobotClock>) -> CuResult<CopperContext> {
+    let unified_logger = Arc::new(Mutex::new(UnifiedLoggerWrite::null()));
+    setup_logger_runtime(unified_logger, text_log, clock)
+}
+
+fn setup_logger_runtime(
+    unified_logger: Arc<Mutex<UnifiedLoggerWrite>>,
+    _text_log: bool,
+    clock: Option<RobotClock>,
+) -> CuResult<CopperContext> {
     let structured_stream = stream_write(
         unified_logger.clone(),
         UnifiedLogType::StructuredLogLine,
@@ -49,10 +93,16 @@ pub fn basic_copper_setup(
 
     #[cfg(debug_assertions)]
     let extra: Option<TermLogger> = if _text_log {
+        // Without a log, the text log is all there is, keep it apart from the output of the application.
+        let mode = if unified_logger.lock().unwrap().is_null() {
+            TerminalMode::Stderr
+        } else {
+            TerminalMode::Mixed
+        };
         let slow_text_logger = TermLogger::new(
             LevelFilter::Debug,
             Config::default(),
-            TerminalMode::Mixed,
+            mode,
             ColorChoice::Auto,
         );
         Some(*slow_text_logger)
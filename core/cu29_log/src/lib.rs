@@ -18,7 +18,7 @@ pub const ANONYMOUS: u32 = 0;
 pub const MAX_LOG_PARAMS_ON_STACK: usize = 10;
 
 /// This is the basic structure for a log entry in Copper.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CuLogEntry {
     // Approximate time when the log entry was created.
     pub time: CuTime,
@@ -112,6 +112,13 @@ impl CuLogEntry {
         self.paramname_indexes.push(paramname_index);
         self.params.push(param);
     }
+
+    /// Gives the buffers of the parameters back to the pool of this thread once the entry is
+    /// logged, the next strings logged reuse them instead of allocating.
+    pub fn recycle_params(&mut self) {
+        self.paramname_indexes.clear();
+        self.params.drain(..).for_each(cu29_value::recycle);
+    }
 }
 
 /// Text log line formatter.
@@ -17,6 +17,7 @@ use syn::{Expr, ExprAssign, ExprLit, Lit};
 /// The parameters can be named or unnamed.
 /// Named parameters are specified as `name = value`.
 /// Unnamed parameters are specified as `value`.
+/// String literal parameters are interned with the message at compile time, they are free at runtime.
 /// # Example
 /// ```ignore
 /// use cu29_log_derive::debug;
@@ -38,31 +39,47 @@ pub fn debug(input: TokenStream) -> TokenStream {
     let mut exprs_iter = exprs.iter();
 
     let msg_expr = exprs_iter.next().expect("Expected at least one expression");
-    let (index, _msg) = if let Expr::Lit(ExprLit {
+    let Expr::Lit(ExprLit {
         lit: Lit::Str(msg), ..
     }) = msg_expr
-    {
-        let msg = msg.value();
-        let index = intern_string(&msg).expect("Failed to insert log string.");
-        (index, msg)
-    } else {
+    else {
         panic!("The first parameter of the argument needs to be a string literal.");
     };
-    let prefix = quote! {
-        let mut log_entry = CuLogEntry::new(#index);
-    };
+    let mut msg = msg.value();
 
     let mut unnamed_params = vec![];
     let mut named_params = vec![];
 
+    // The string literals are interned in the message: they cost nothing at runtime.
     for expr in exprs_iter {
         if let Expr::Assign(ExprAssign { left, right, .. }) = expr {
-            named_params.push((left, right));
+            let placeholder = format!("{{{}}}", quote!(#left));
+            match str_literal(right) {
+                Some(literal) if msg.contains(&placeholder) => {
+                    msg = msg.replace(&placeholder, &literal);
+                }
+                _ => named_params.push((left, right)),
+            }
         } else {
-            unnamed_params.push(expr);
+            // The placeholders before this one are taken by the parameters given at runtime.
+            let placeholder = msg
+                .match_indices("{}")
+                .nth(unnamed_params.len())
+                .map(|(position, _)| position);
+            match (str_literal(expr), placeholder) {
+                (Some(literal), Some(position)) => {
+                    msg.replace_range(position..position + 2, &literal);
+                }
+                _ => unnamed_params.push(expr),
+            }
         }
     }
 
+    let index = intern_string(&msg).expect("Failed to insert log string.");
+    let prefix = quote! {
+        let mut log_entry = CuLogEntry::new(#index);
+    };
+
     let unnamed_prints = unnamed_params.iter().map(|value| {
         quote! {
             let param = to_value(#value).expect("Failed to convert a parameter to a Value");
@@ -95,12 +112,13 @@ pub fn debug(input: TokenStream) -> TokenStream {
             })
             .collect();
         quote! {
-            let r = log_debug_mode(&mut log_entry, #_msg, &[#(#keys),*]);
+            let r = log_debug_mode(&mut log_entry, #msg, &[#(#keys),*]);
         }
     };
 
     let postfix = quote! {
         #log_stmt
+        log_entry.recycle_params();
         if let Err(e) = r {
             eprintln!("Warning: Failed to log: {}", e);
             let backtrace = std::backtrace::Backtrace::capture();
@@ -119,3 +137,15 @@ pub fn debug(input: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// The value of a string literal that can be part of a message, None if the expression is not one.
+/// A literal with braces is given at runtime as they would be taken for placeholders.
+fn str_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(literal),
+            ..
+        }) => Some(literal.value()).filter(|value| !value.contains(['{', '}'])),
+        _ => None,
+    }
+}
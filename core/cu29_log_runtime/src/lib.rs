@@ -1,20 +1,21 @@
-use bincode::config::Configuration;
 use bincode::enc::write::Writer;
 use bincode::enc::Encode;
 use bincode::enc::{Encoder, EncoderImpl};
 use bincode::error::EncodeError;
 use cu29_clock::RobotClock;
 use cu29_log::CuLogEntry;
-use cu29_traits::{CuResult, WriteStream};
+use cu29_traits::{CuResult, CuWireConfig, CuWireFormat, WriteStream};
 use log::Log;
 
 #[cfg(debug_assertions)]
 use {cu29_log::format_logline, std::collections::HashMap, std::sync::RwLock};
 
+use std::collections::VecDeque;
 use std::fmt::{Debug, Formatter};
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Mutex, OnceLock};
 
 #[derive(Debug)]
@@ -31,6 +32,10 @@ type WriterPair = (Mutex<LogWriter>, RobotClock);
 
 static WRITER: OnceLock<WriterPair> = OnceLock::new();
 
+/// The last entries logged, kept in memory for the crash context, see [keep_recent_entries].
+static RECENT_ENTRIES: Mutex<VecDeque<CuLogEntry>> = Mutex::new(VecDeque::new());
+static RECENT_ENTRIES_CAPACITY: AtomicUsize = AtomicUsize::new(0);
+
 #[cfg(debug_assertions)]
 pub static EXTRA_TEXT_LOGGER: RwLock<Option<Box<dyn Log + 'static>>> = RwLock::new(None);
 
@@ -115,6 +120,14 @@ pub fn log(entry: &mut CuLogEntry) -> CuResult<()> {
     if let Err(err) = writer.lock().unwrap().log(entry) {
         eprintln!("Failed to log data: {err}");
     }
+    let capacity = RECENT_ENTRIES_CAPACITY.load(Ordering::Relaxed);
+    if capacity > 0 {
+        let mut recent = RECENT_ENTRIES.lock().unwrap();
+        while recent.len() >= capacity {
+            recent.pop_front();
+        }
+        recent.push_back(entry.clone());
+    }
     // This is only for debug builds with standard textual logging implemented.
     #[cfg(debug_assertions)]
     {
@@ -125,6 +138,21 @@ pub fn log(entry: &mut CuLogEntry) -> CuResult<()> {
     Ok(())
 }
 
+/// Keeps the last `capacity` entries logged in memory, for the crash context of the runtime.
+/// Off by default: a copy of every entry is made once it is on.
+pub fn keep_recent_entries(capacity: usize) {
+    RECENT_ENTRIES_CAPACITY.store(capacity, Ordering::Relaxed);
+}
+
+/// The last entries logged, oldest first, see [keep_recent_entries].
+/// Empty if they are being updated: this is called from a panic hook, it doesn't wait.
+pub fn recent_entries() -> Vec<CuLogEntry> {
+    RECENT_ENTRIES
+        .try_lock()
+        .map(|recent| recent.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
 /// This version of log is only compiled in debug mode
 /// This allows a normal logging framework to be bridged.
 #[cfg(debug_assertions)]
@@ -211,7 +239,7 @@ impl<W: Write> Writer for OwningIoWriter<W> {
 /// This allows this crate to be used outside of Copper (ie. decoupling it from the unifiedlog.
 pub struct SimpleFileWriter {
     path: PathBuf,
-    encoder: EncoderImpl<OwningIoWriter<File>, Configuration>,
+    encoder: EncoderImpl<OwningIoWriter<File>, CuWireConfig>,
 }
 
 impl SimpleFileWriter {
@@ -224,7 +252,7 @@ impl SimpleFileWriter {
             .map_err(|e| format!("Failed to open file: {e:?}"))?;
 
         let writer = OwningIoWriter::new(file);
-        let encoder = EncoderImpl::new(writer, bincode::config::standard());
+        let encoder = EncoderImpl::new(writer, CuWireFormat::CONFIG);
 
         Ok(SimpleFileWriter {
             path: path.clone(),
@@ -258,8 +286,7 @@ impl WriteStream<CuLogEntry> for SimpleFileWriter {
 
 #[cfg(test)]
 mod tests {
-    use crate::CuLogEntry;
-    use bincode::config::standard;
+    use super::*;
     use cu29_value::Value;
     use smallvec::smallvec;
 
@@ -271,9 +298,31 @@ mod tests {
             paramname_indexes: smallvec![2, 3],
             params: smallvec![Value::String("test".to_string())],
         };
-        let encoded = bincode::encode_to_vec(&log_entry, standard()).unwrap();
+        let encoded = bincode::encode_to_vec(&log_entry, CuWireFormat::CONFIG).unwrap();
         let decoded_tuple: (CuLogEntry, usize) =
-            bincode::decode_from_slice(&encoded, standard()).unwrap();
+            bincode::decode_from_slice(&encoded, CuWireFormat::CONFIG).unwrap();
         assert_eq!(log_entry, decoded_tuple.0);
     }
+
+    #[test]
+    fn test_recent_entries() {
+        #[derive(Debug)]
+        struct Sink;
+        impl WriteStream<CuLogEntry> for Sink {
+            fn log(&mut self, _obj: &CuLogEntry) -> CuResult<()> {
+                Ok(())
+            }
+        }
+        let _runtime = LoggerRuntime::init(RobotClock::new(), Sink, None::<NullLog>);
+        keep_recent_entries(2);
+        for msg_index in 0..5 {
+            let mut entry = CuLogEntry::new(msg_index);
+            log(&mut entry).unwrap();
+        }
+        let recent: Vec<u32> = recent_entries()
+            .iter()
+            .map(|entry| entry.msg_index)
+            .collect();
+        assert_eq!(recent, vec![3, 4]);
+    }
 }
@@ -6,7 +6,9 @@ fn main() {
     );
 
     println!("cargo:rustc-check-cfg=cfg(has_nvidia_gpu)");
-    if Path::new("/proc/driver/nvidia/gpus").exists() {
+    // The GPU of the host says nothing about the one of a cross compilation target.
+    let cross_compiling = std::env::var("HOST").ok() != std::env::var("TARGET").ok();
+    if !cross_compiling && Path::new("/proc/driver/nvidia/gpus").exists() {
         println!("cargo:rustc-cfg=has_nvidia_gpu");
     }
 }
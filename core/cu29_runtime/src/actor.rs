@@ -0,0 +1,402 @@
+//! Execution of the heavy tasks on their own thread ("actor mode").
+//! An inference or a mapping taking longer than a cycle would hold the whole copper loop back. A
+//! task with an `actor` in its configuration is wrapped in a [CuActorTask]: its process() runs on
+//! a worker thread consuming the inputs from a bounded queue, and the copper loop never waits
+//! for it:
+//!
+//! ```ron
+//! (id: "detector", type: "tasks::Detector", actor: (queue: 2, max_age_ms: 200)),
+//! ```
+//!
+//! In every cycle the input with a payload is queued, the oldest input waiting being dropped for
+//! it when the queue is full, and the output is the latest result the worker finished since the
+//! previous cycle, empty if there is none. The result keeps the time of validity of the input it
+//! was computed from and is dropped if this input was queued more than `max_age_ms` ago.
+//!
+//! The actors are tasks with a single input and an output. Like for the pipelined sources, the
+//! state of the task lives on the worker thread so it is not part of the frozen task states, and
+//! the cycle a result comes out in depends on the timing of the worker: the replay gives the
+//! logged results, not the ones a new run of the worker would give.
+
+use crate::config::{ComponentConfig, ConfigKey};
+use crate::cutask::{CuMsg, CuMsgPayload, CuTask, Freezable};
+use crate::log::*;
+use cu29_clock::{CuDuration, CuTime, RobotClock};
+use cu29_traits::{CuError, CuResult};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// An input with the time it was queued.
+type Queued<I> = (CuTime, CuMsg<I>);
+
+/// The inputs waiting for the worker, the oldest is dropped for a new one when it is full.
+struct ActorQueue<I: CuMsgPayload> {
+    /// None once closed.
+    inputs: Mutex<Option<VecDeque<Queued<I>>>>,
+    pushed: Condvar,
+    capacity: usize,
+}
+
+impl<I: CuMsgPayload> ActorQueue<I> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            inputs: Mutex::new(Some(VecDeque::with_capacity(capacity))),
+            pushed: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// Queues an input, false if the oldest one was dropped for it.
+    fn push(&self, queued_at: CuTime, input: CuMsg<I>) -> bool {
+        let mut inputs = self.inputs.lock().unwrap();
+        let Some(inputs) = inputs.as_mut() else {
+            return false;
+        };
+        let dropped = inputs.len() >= self.capacity;
+        if dropped {
+            inputs.pop_front();
+        }
+        inputs.push_back((queued_at, input));
+        self.pushed.notify_one();
+        !dropped
+    }
+
+    /// Waits for the next input, None once the queue is closed.
+    fn pop(&self) -> Option<Queued<I>> {
+        let mut inputs = self.inputs.lock().unwrap();
+        loop {
+            let queued = inputs.as_mut()?;
+            if let Some(input) = queued.pop_front() {
+                return Some(input);
+            }
+            inputs = self.pushed.wait(inputs).unwrap();
+        }
+    }
+
+    fn close(&self) {
+        *self.inputs.lock().unwrap() = None;
+        self.pushed.notify_all();
+    }
+}
+
+/// The result of one process() done by the worker.
+struct Outcome<O: CuMsgPayload> {
+    result: CuResult<()>,
+    queued_at: CuTime,
+    msg: CuMsg<O>,
+}
+
+struct Worker<T, I: CuMsgPayload, O: CuMsgPayload> {
+    queue: Arc<ActorQueue<I>>,
+    outcomes: Receiver<Outcome<O>>,
+    handle: JoinHandle<(T, CuResult<()>)>,
+}
+
+/// Runs the task `T`, from `I` to `O`, on a worker thread fed by a queue of `QUEUE` inputs at most.
+/// The results computed from an input queued more than `MAX_AGE_MS` ago are dropped, 0 keeps them
+/// all.
+pub struct CuActorTask<
+    T,
+    I: CuMsgPayload,
+    O: CuMsgPayload,
+    const QUEUE: usize,
+    const MAX_AGE_MS: u64,
+> {
+    /// The task while it is not running on the worker.
+    idle: Option<T>,
+    worker: Option<Worker<T, I, O>>,
+    /// The inputs dropped because the worker was late on them.
+    dropped_inputs: u64,
+}
+
+impl<T, I: CuMsgPayload, O: CuMsgPayload, const QUEUE: usize, const MAX_AGE_MS: u64> Freezable
+    for CuActorTask<T, I, O, QUEUE, MAX_AGE_MS>
+{
+}
+
+fn run<'m, T, I, O>(
+    task: &mut T,
+    clock: &RobotClock,
+    input: &'m CuMsg<I>,
+    output: &'m mut CuMsg<O>,
+) -> CuResult<()>
+where
+    T: CuTask<'m, Input = &'m CuMsg<I>, Output = &'m mut CuMsg<O>>,
+    I: CuMsgPayload + 'm,
+    O: CuMsgPayload + 'm,
+{
+    task.preprocess(clock)?;
+    task.process(clock, input, output)?;
+    task.postprocess(clock)
+}
+
+fn worker_loop<T, I, O>(
+    mut task: T,
+    clock: RobotClock,
+    queue: Arc<ActorQueue<I>>,
+    outcomes: Sender<Outcome<O>>,
+) -> (T, CuResult<()>)
+where
+    T: for<'m> CuTask<'m, Input = &'m CuMsg<I>, Output = &'m mut CuMsg<O>>,
+    I: CuMsgPayload,
+    O: CuMsgPayload,
+{
+    while let Some((queued_at, input)) = queue.pop() {
+        let mut msg = CuMsg::<O>::default();
+        msg.metadata.tov = input.metadata.tov;
+        let result = run(&mut task, &clock, &input, &mut msg);
+        let outcome = Outcome {
+            result,
+            queued_at,
+            msg,
+        };
+        if outcomes.send(outcome).is_err() {
+            break;
+        }
+    }
+    let result = task.stop(&clock);
+    (task, result)
+}
+
+impl<T, I: CuMsgPayload, O: CuMsgPayload, const QUEUE: usize, const MAX_AGE_MS: u64>
+    CuActorTask<T, I, O, QUEUE, MAX_AGE_MS>
+{
+    fn worker(&mut self) -> CuResult<&mut Worker<T, I, O>> {
+        self.worker
+            .as_mut()
+            .ok_or_else(|| CuError::from("The actor has not been started."))
+    }
+
+    /// The inputs dropped from the queue because the worker was late on them.
+    pub fn dropped_inputs(&self) -> u64 {
+        self.dropped_inputs
+    }
+}
+
+impl<'cl, T, I, O, const QUEUE: usize, const MAX_AGE_MS: u64> CuTask<'cl>
+    for CuActorTask<T, I, O, QUEUE, MAX_AGE_MS>
+where
+    T: for<'m> CuTask<'m, Input = &'m CuMsg<I>, Output = &'m mut CuMsg<O>> + Send + 'static,
+    I: CuMsgPayload + Send + 'static,
+    O: CuMsgPayload + Send + 'static,
+{
+    type Input = &'cl CuMsg<I>;
+    type Output = &'cl mut CuMsg<O>;
+
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = <T as CuTask<'cl>>::CONFIG_SCHEMA;
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            idle: Some(T::new(config)?),
+            worker: None,
+            dropped_inputs: 0,
+        })
+    }
+
+    fn start(&mut self, clock: &RobotClock) -> CuResult<()> {
+        let mut task = self
+            .idle
+            .take()
+            .ok_or_else(|| CuError::from("The actor is already started."))?;
+        task.start(clock)?;
+        let queue = Arc::new(ActorQueue::new(QUEUE.max(1)));
+        let (worker_outcomes, outcomes) = channel();
+        let worker_clock = clock.clone();
+        let worker_queue = queue.clone();
+        let handle = std::thread::Builder::new()
+            .name("cu-actor".to_string())
+            .spawn(move || worker_loop(task, worker_clock, worker_queue, worker_outcomes))
+            .map_err(|e| CuError::new_with_cause("Could not spawn the actor", e))?;
+        self.worker = Some(Worker {
+            queue,
+            outcomes,
+            handle,
+        });
+        debug!("Actor started.");
+        Ok(())
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        let now = clock.now();
+        let worker = self.worker()?;
+        let queued = input.payload().is_none() || worker.queue.push(now, input.clone());
+
+        // Only the latest result is given, an error of the ones it replaces is still reported.
+        let mut latest: Option<Outcome<O>> = None;
+        let mut error = None;
+        loop {
+            match worker.outcomes.try_recv() {
+                Ok(outcome) => {
+                    if let Some(Outcome { result: Err(e), .. }) = latest.replace(outcome) {
+                        error.get_or_insert(e);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(CuError::from("The actor worker stopped."))
+                }
+            }
+        }
+        if !queued {
+            self.dropped_inputs += 1;
+        }
+
+        output.clear_payload();
+        let Some(Outcome {
+            result,
+            queued_at,
+            mut msg,
+        }) = latest
+        else {
+            return error.map_or(Ok(()), Err);
+        };
+        let stale = MAX_AGE_MS > 0 && now - queued_at > CuDuration::from_millis(MAX_AGE_MS);
+        if !stale {
+            // The process time is measured by the runtime, only the result is handed over.
+            *output.payload_mut() = msg.payload_mut().take();
+            output.metadata.tov = msg.metadata.tov;
+            output.metadata.status_txt = msg.metadata.status_txt;
+        }
+        match error {
+            Some(error) => Err(error),
+            None => result,
+        }
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let Some(worker) = self.worker.take() else {
+            return Ok(());
+        };
+        // The inputs still queued are never processed.
+        worker.queue.close();
+        drop(worker.outcomes);
+        let (task, result) = worker
+            .handle
+            .join()
+            .map_err(|_| CuError::from("The actor worker panicked."))?;
+        self.idle = Some(task);
+        debug!("Actor stopped.");
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input_msg, output_msg};
+    use std::sync::mpsc::sync_channel;
+
+    /// Doubles its input once the test lets it go.
+    struct Doubler {
+        gate: Receiver<()>,
+    }
+
+    impl Freezable for Doubler {}
+
+    impl<'cl> CuTask<'cl> for Doubler {
+        type Input = input_msg!('cl, u32);
+        type Output = output_msg!('cl, u64);
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self> {
+            // Never held back.
+            let (_, gate) = sync_channel(0);
+            Ok(Self { gate })
+        }
+
+        fn process(
+            &mut self,
+            _clock: &RobotClock,
+            input: Self::Input,
+            output: Self::Output,
+        ) -> CuResult<()> {
+            let _ = self.gate.recv();
+            let value = *input.payload().unwrap();
+            if value == 0 {
+                return Err("nothing to double".into());
+            }
+            output.set_payload(value as u64 * 2);
+            Ok(())
+        }
+    }
+
+    type Actor = CuActorTask<Doubler, u32, u64, 2, 0>;
+
+    fn queued(actor: &Actor) -> usize {
+        let worker = actor.worker.as_ref().unwrap();
+        worker.queue.inputs.lock().unwrap().as_ref().unwrap().len()
+    }
+
+    #[test]
+    fn test_actor() {
+        let (release, gate) = sync_channel(16);
+        let (clock, _) = RobotClock::mock();
+        let mut actor = Actor {
+            idle: Some(Doubler { gate }),
+            worker: None,
+            dropped_inputs: 0,
+        };
+        let mut output = CuMsg::<u64>::default();
+        assert!(actor
+            .process(&clock, &CuMsg::new(Some(1)), &mut output)
+            .is_err()); // not started
+
+        actor.start(&clock).unwrap();
+        // The worker holds the first input, the loop does not wait for it.
+        actor
+            .process(&clock, &CuMsg::new(Some(1)), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), None);
+        while queued(&actor) > 0 {
+            std::thread::yield_now();
+        }
+        // 2 and 3 wait in the queue, 4 drops 2.
+        for value in 2..5 {
+            actor
+                .process(&clock, &CuMsg::new(Some(value)), &mut output)
+                .unwrap();
+        }
+        assert_eq!(actor.dropped_inputs(), 1);
+
+        // 1, 3 and 4 are processed, the results come out up to the latest one.
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+        let mut received = Vec::new();
+        while received.last() != Some(&8) {
+            actor
+                .process(&clock, &CuMsg::new(None), &mut output)
+                .unwrap();
+            received.extend(output.payload().copied());
+        }
+        assert!(received.iter().all(|value| [2, 6, 8].contains(value)));
+        actor
+            .process(&clock, &CuMsg::new(None), &mut output)
+            .unwrap();
+        assert_eq!(output.payload(), None);
+
+        // An error of the task is reported by the cycle getting its result.
+        actor
+            .process(&clock, &CuMsg::new(Some(0)), &mut output)
+            .unwrap();
+        release.send(()).unwrap();
+        let error = loop {
+            if let Err(error) = actor.process(&clock, &CuMsg::new(None), &mut output) {
+                break error;
+            }
+        };
+        assert!(error.to_string().contains("nothing to double"));
+
+        actor.stop(&clock).unwrap();
+        assert!(actor.idle.is_some());
+    }
+}
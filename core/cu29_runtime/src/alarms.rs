@@ -0,0 +1,300 @@
+//! Alarms are the operator facing counterpart of the CuErrors.
+//! A CuError is transient and handled by the monitor within the cycle, an alarm is raised by a task
+//! when a condition needs the attention of an operator (overheating, low battery, lost sensor...).
+//!
+//! Alarms are latched: once raised, an alarm stays in the registry until its condition has been
+//! cleared by the task AND it has been acknowledged from the outside (CLI, UI, network...).
+//! The runtime forwards every alarm event to the monitor and to the unified logger.
+
+use crate::log::*;
+use bincode::{Decode, Encode};
+use cu29_clock::{CuTime, RobotClock};
+use cu29_traits::{CuError, CuResult};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::{Mutex, OnceLock};
+
+/// How urgently an alarm requires the attention of an operator.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Encode, Decode,
+)]
+pub enum AlarmSeverity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Display for AlarmSeverity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlarmSeverity::Info => write!(f, "INFO"),
+            AlarmSeverity::Warning => write!(f, "WARNING"),
+            AlarmSeverity::Error => write!(f, "ERROR"),
+            AlarmSeverity::Critical => write!(f, "CRITICAL"),
+        }
+    }
+}
+
+/// An alarm as tracked by the runtime.
+/// It is identified by its source (usually the task id) and its code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct CuAlarm {
+    pub source: String,
+    pub code: u32,
+    pub severity: AlarmSeverity,
+    pub message: String,
+    /// When the alarm was raised for the first time since it was last acknowledged.
+    pub first_raised: CuTime,
+    /// When the alarm was raised for the last time.
+    pub last_raised: CuTime,
+    /// How many times the alarm has been raised since it was last acknowledged.
+    pub occurrences: u32,
+    /// The condition that triggered the alarm is still present.
+    pub active: bool,
+    pub acknowledged: bool,
+}
+
+impl CuAlarm {
+    /// The condition is gone but nobody acknowledged the alarm yet.
+    pub fn is_latched(&self) -> bool {
+        !self.active && !self.acknowledged
+    }
+
+    fn matches(&self, source: &str, code: u32) -> bool {
+        self.source == source && self.code == code
+    }
+}
+
+impl Display for CuAlarm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {}#{}: {}",
+            self.severity, self.source, self.code, self.message
+        )
+    }
+}
+
+/// What happened to an alarm, this is what the monitor receives.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlarmEvent {
+    Raised(CuAlarm),
+    Cleared(CuAlarm),
+    Acknowledged(CuAlarm),
+}
+
+impl AlarmEvent {
+    pub fn alarm(&self) -> &CuAlarm {
+        match self {
+            AlarmEvent::Raised(alarm)
+            | AlarmEvent::Cleared(alarm)
+            | AlarmEvent::Acknowledged(alarm) => alarm,
+        }
+    }
+}
+
+#[derive(Default)]
+struct AlarmRegistry {
+    alarms: Vec<CuAlarm>,
+    pending_events: Vec<AlarmEvent>,
+}
+
+impl AlarmRegistry {
+    fn forget_if_resolved(&mut self, index: usize) {
+        let alarm = &self.alarms[index];
+        if !alarm.active && alarm.acknowledged {
+            self.alarms.remove(index);
+        }
+    }
+}
+
+static ALARM_REGISTRY: OnceLock<Mutex<AlarmRegistry>> = OnceLock::new();
+
+fn registry() -> &'static Mutex<AlarmRegistry> {
+    ALARM_REGISTRY.get_or_init(|| Mutex::new(AlarmRegistry::default()))
+}
+
+/// Raises an alarm from a task.
+/// Raising an alarm that is already present only updates it (and reactivates it if it was cleared),
+/// an escalation of severity requires a new acknowledgement.
+pub fn raise_alarm(
+    clock: &RobotClock,
+    source: &str,
+    code: u32,
+    severity: AlarmSeverity,
+    message: impl Into<String>,
+) {
+    let now = clock.now();
+    let message = message.into();
+    let mut registry = registry().lock().unwrap();
+    let alarm = match registry.alarms.iter_mut().find(|a| a.matches(source, code)) {
+        Some(alarm) => {
+            if severity > alarm.severity || !alarm.active {
+                alarm.acknowledged = false;
+            }
+            alarm.severity = alarm.severity.max(severity);
+            alarm.message = message;
+            alarm.last_raised = now;
+            alarm.occurrences += 1;
+            alarm.active = true;
+            alarm.clone()
+        }
+        None => {
+            let alarm = CuAlarm {
+                source: source.to_string(),
+                code,
+                severity,
+                message,
+                first_raised: now,
+                last_raised: now,
+                occurrences: 1,
+                active: true,
+                acknowledged: false,
+            };
+            registry.alarms.push(alarm.clone());
+            alarm
+        }
+    };
+    registry.pending_events.push(AlarmEvent::Raised(alarm));
+}
+
+/// Tells the runtime that the condition behind an alarm is gone.
+/// The alarm stays latched until it is acknowledged.
+pub fn clear_alarm(source: &str, code: u32) {
+    let mut registry = registry().lock().unwrap();
+    let Some(index) = registry
+        .alarms
+        .iter()
+        .position(|a| a.matches(source, code) && a.active)
+    else {
+        return;
+    };
+    registry.alarms[index].active = false;
+    let event = AlarmEvent::Cleared(registry.alarms[index].clone());
+    registry.pending_events.push(event);
+    registry.forget_if_resolved(index);
+}
+
+/// Acknowledges an alarm, typically called from an operator facing interface.
+pub fn acknowledge_alarm(source: &str, code: u32) -> CuResult<()> {
+    let mut registry = registry().lock().unwrap();
+    let index = registry
+        .alarms
+        .iter()
+        .position(|a| a.matches(source, code))
+        .ok_or_else(|| CuError::from(format!("No alarm {code} raised by {source}.")))?;
+    if !registry.alarms[index].acknowledged {
+        registry.alarms[index].acknowledged = true;
+        let event = AlarmEvent::Acknowledged(registry.alarms[index].clone());
+        registry.pending_events.push(event);
+        registry.forget_if_resolved(index);
+    }
+    Ok(())
+}
+
+/// Acknowledges all the alarms currently in the registry.
+pub fn acknowledge_all_alarms() {
+    let pending: Vec<(String, u32)> = registry()
+        .lock()
+        .unwrap()
+        .alarms
+        .iter()
+        .filter(|a| !a.acknowledged)
+        .map(|a| (a.source.clone(), a.code))
+        .collect();
+    for (source, code) in pending {
+        let _ = acknowledge_alarm(&source, code);
+    }
+}
+
+/// All the alarms that are either active or latched, the most severe first.
+pub fn alarms() -> Vec<CuAlarm> {
+    let mut alarms = registry().lock().unwrap().alarms.clone();
+    alarms.sort_by_key(|alarm| std::cmp::Reverse(alarm.severity));
+    alarms
+}
+
+/// Drains the alarm events that happened since the last call.
+/// This is called by the runtime at the end of every copperlist.
+pub(crate) fn take_alarm_events() -> Vec<AlarmEvent> {
+    match ALARM_REGISTRY.get() {
+        Some(registry) => std::mem::take(&mut registry.lock().unwrap().pending_events),
+        None => Vec::new(),
+    }
+}
+
+/// Logs an alarm event to the unified logger.
+pub(crate) fn log_alarm_event(event: &AlarmEvent) {
+    let alarm = event.alarm();
+    let what = match event {
+        AlarmEvent::Raised(_) => "raised",
+        AlarmEvent::Cleared(_) => "cleared",
+        AlarmEvent::Acknowledged(_) => "acknowledged",
+    };
+    debug!(
+        "Alarm {}: [{}] {}#{}: {} (occurrences: {})",
+        what,
+        alarm.severity,
+        alarm.source.as_str(),
+        alarm.code,
+        alarm.message.as_str(),
+        alarm.occurrences
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarm_latching() {
+        let (clock, _) = RobotClock::mock();
+        raise_alarm(&clock, "latch_task", 1, AlarmSeverity::Warning, "too hot");
+        raise_alarm(
+            &clock,
+            "latch_task",
+            1,
+            AlarmSeverity::Warning,
+            "still too hot",
+        );
+        let alarm = alarms()
+            .into_iter()
+            .find(|a| a.matches("latch_task", 1))
+            .unwrap();
+        assert_eq!(alarm.occurrences, 2);
+        assert_eq!(alarm.message, "still too hot");
+
+        clear_alarm("latch_task", 1);
+        let alarm = alarms()
+            .into_iter()
+            .find(|a| a.matches("latch_task", 1))
+            .unwrap();
+        assert!(alarm.is_latched());
+
+        acknowledge_alarm("latch_task", 1).unwrap();
+        assert!(!alarms().iter().any(|a| a.matches("latch_task", 1)));
+        assert!(acknowledge_alarm("latch_task", 1).is_err());
+    }
+
+    #[test]
+    fn test_alarm_escalation_requires_acknowledgement() {
+        let (clock, _) = RobotClock::mock();
+        raise_alarm(&clock, "escalation_task", 2, AlarmSeverity::Warning, "low");
+        acknowledge_alarm("escalation_task", 2).unwrap();
+        raise_alarm(
+            &clock,
+            "escalation_task",
+            2,
+            AlarmSeverity::Critical,
+            "empty",
+        );
+        let alarm = alarms()
+            .into_iter()
+            .find(|a| a.matches("escalation_task", 2))
+            .unwrap();
+        assert_eq!(alarm.severity, AlarmSeverity::Critical);
+        assert!(!alarm.acknowledged);
+        assert!(alarm.active);
+    }
+}
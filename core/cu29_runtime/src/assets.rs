@@ -0,0 +1,70 @@
+//! The base directory the relative paths of an application (configuration, log, calibrations...)
+//! are resolved from. On a desktop they are relative to the working directory as usual, on a phone
+//! or an embedded target the application is started from wherever the OS decides and gives the
+//! directory of its files instead:
+//!
+//! ```rust,ignore
+//! cu29::assets::set_base_dir(activity.internal_data_path().unwrap());
+//! let ctx = basic_copper_setup(Path::new("logs/robot.copper"), None, false, None)?;
+//! ```
+//!
+//! The base directory can also be given with the COPPER_BASE_DIR environment variable, the one set
+//! by [set_base_dir] takes precedence. The absolute paths are never changed.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The environment variable giving the base directory if none is set by the application.
+pub const BASE_DIR_ENV: &str = "COPPER_BASE_DIR";
+
+static BASE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Sets the directory the relative paths are resolved from, for the whole process.
+pub fn set_base_dir(dir: impl Into<PathBuf>) {
+    *BASE_DIR.lock().unwrap() = Some(dir.into());
+}
+
+/// Goes back to the COPPER_BASE_DIR environment variable or the working directory.
+pub fn clear_base_dir() {
+    *BASE_DIR.lock().unwrap() = None;
+}
+
+/// The directory set by [set_base_dir] or in COPPER_BASE_DIR, None for the working directory.
+pub fn base_dir() -> Option<PathBuf> {
+    if let Some(dir) = BASE_DIR.lock().unwrap().clone() {
+        return Some(dir);
+    }
+    std::env::var_os(BASE_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The path to open for `path`: under the base directory if it is relative and one is set, as is
+/// otherwise.
+pub fn resolve(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match base_dir() {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let absolute = std::env::temp_dir().join("robot.copper");
+        set_base_dir("/data/robot");
+        assert_eq!(
+            resolve("logs/robot.copper"),
+            Path::new("/data/robot/logs/robot.copper")
+        );
+        assert_eq!(resolve(&absolute), absolute);
+        clear_base_dir();
+        if std::env::var_os(BASE_DIR_ENV).is_none() {
+            assert_eq!(resolve("logs/robot.copper"), Path::new("logs/robot.copper"));
+        }
+    }
+}
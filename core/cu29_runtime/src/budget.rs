@@ -0,0 +1,248 @@
+//! Resource budgets of the tasks: a task declares in the configuration what it needs at most and
+//! the runtime holds it to it.
+//!
+//! ```ron
+//! (id: "planner", type: "tasks::Planner", budget: (cpu_percent: 30.0, memory_bytes: 4096)),
+//! (id: "lidar", type: "tasks::Lidar", budget: (bandwidth_bytes_per_s: 20000000)),
+//! ```
+//!
+//! The budgets are admitted when the configuration is read, see [CuConfig::validate_budgets].
+//! The usage measured over each second of the run is then checked against them, over the first
+//! second only by default, see [BudgetChecks]:
+//! - `cpu_percent`: the time spent in process() over the time of the copper loop,
+//! - `memory_bytes`: the bytes allocated on the heap by a process() call, the allocations of the
+//!   other threads during the call included,
+//! - `bandwidth_bytes_per_s`: the encoded size of the outputs.
+//!
+//! A task over its budget raises an alarm with the task id as source, cleared when a later
+//! second is back within the budget.
+
+use crate::alarms::{clear_alarm, raise_alarm, AlarmSeverity};
+use crate::config::{BudgetChecks, CuConfig, ResourceBudget};
+use crate::cutask::{encoded_size, CuMsg, CuMsgMetadata, CuMsgPayload};
+use cu29_clock::{CuDuration, CuTime, RobotClock};
+
+/// The time spent in process() is over the cpu budget.
+pub const CPU_BUDGET_ALARM: u32 = 1;
+/// A process() call allocated more than the memory budget.
+pub const MEMORY_BUDGET_ALARM: u32 = 2;
+/// The outputs are over the bandwidth budget.
+pub const BANDWIDTH_BUDGET_ALARM: u32 = 3;
+
+/// The usage is checked over windows of this length.
+const WINDOW: CuDuration = CuDuration::from_nanos(1_000_000_000);
+
+/// The usage of a task in the current window.
+#[derive(Debug, Default)]
+struct Usage {
+    busy: CuDuration,
+    max_allocated: u64,
+    output_bytes: u64,
+}
+
+#[derive(Debug)]
+struct TaskBudget {
+    id: String,
+    budget: ResourceBudget,
+    usage: Usage,
+}
+
+/// The budgets of the tasks and their usage, kept by the runtime.
+#[derive(Debug, Default)]
+pub struct CuBudgets {
+    /// Indexed like the tasks tuple, None for the tasks without budget.
+    tasks: Vec<Option<TaskBudget>>,
+    checks: BudgetChecks,
+    window_start: Option<CuTime>,
+}
+
+impl CuBudgets {
+    pub fn new(config: &CuConfig) -> Self {
+        let checks = config
+            .runtime
+            .as_ref()
+            .map(|runtime| runtime.budget_checks)
+            .unwrap_or_default();
+        let tasks = config
+            .get_all_nodes(None) // FIXME(gbin): Multimission support
+            .into_iter()
+            .map(|(_, node)| {
+                node.get_budget().map(|budget| TaskBudget {
+                    id: node.get_id(),
+                    budget: *budget,
+                    usage: Usage::default(),
+                })
+            })
+            .collect();
+        Self {
+            tasks,
+            checks,
+            window_start: None,
+        }
+    }
+
+    fn usage_mut(&mut self, task: usize) -> Option<&mut Usage> {
+        if self.checks == BudgetChecks::Off {
+            return None;
+        }
+        self.tasks
+            .get_mut(task)?
+            .as_mut()
+            .map(|task| &mut task.usage)
+    }
+
+    /// Accounts for the bytes allocated by one process() call of a task.
+    pub fn record_allocated(&mut self, task: usize, bytes: usize) {
+        if let Some(usage) = self.usage_mut(task) {
+            usage.max_allocated = usage.max_allocated.max(bytes as u64);
+        }
+    }
+
+    /// Accounts for an output of a task.
+    pub fn record_output<T: CuMsgPayload>(&mut self, task: usize, msg: &CuMsg<T>) {
+        if let Some(usage) = self.usage_mut(task) {
+            usage.output_bytes += encoded_size(msg) as u64;
+        }
+    }
+
+    /// Accounts for the process times of a copper list, indexed like the tasks tuple, and checks
+    /// the usage of the tasks at the end of a window.
+    pub fn end_of_copperlist(&mut self, clock: &RobotClock, msgs: &[&CuMsgMetadata]) {
+        if self.checks == BudgetChecks::Off {
+            return;
+        }
+        for (task, metadata) in self.tasks.iter_mut().zip(msgs) {
+            let Some(task) = task else {
+                continue;
+            };
+            if let (Some(start), Some(end)) = (
+                Option::<CuDuration>::from(metadata.process_time.start),
+                Option::<CuDuration>::from(metadata.process_time.end),
+            ) {
+                if end > start {
+                    task.usage.busy += end - start;
+                }
+            }
+        }
+
+        let now = clock.now();
+        let start = *self.window_start.get_or_insert(now);
+        let elapsed = now - start;
+        if elapsed < WINDOW {
+            return;
+        }
+        for task in self.tasks.iter_mut().flatten() {
+            task.check(clock, elapsed);
+        }
+        self.window_start = Some(now);
+        if self.checks == BudgetChecks::Startup {
+            self.checks = BudgetChecks::Off;
+        }
+    }
+}
+
+impl TaskBudget {
+    /// Compares the usage over the window ending now to the budget and starts a new window.
+    fn check(&mut self, clock: &RobotClock, elapsed: CuDuration) {
+        let usage = std::mem::take(&mut self.usage);
+        let seconds = elapsed.as_nanos() as f64 / 1e9;
+        if let Some(budget) = self.budget.cpu_percent {
+            let used = usage.busy.as_nanos() as f64 / elapsed.as_nanos() as f64 * 100.0;
+            self.report(
+                clock,
+                CPU_BUDGET_ALARM,
+                used > budget,
+                format!("{used:.1}% of the loop over the budget of {budget}%"),
+            );
+        }
+        if let Some(budget) = self.budget.memory_bytes {
+            self.report(
+                clock,
+                MEMORY_BUDGET_ALARM,
+                usage.max_allocated > budget,
+                format!(
+                    "{} bytes allocated by a process() over the budget of {budget} bytes",
+                    usage.max_allocated
+                ),
+            );
+        }
+        if let Some(budget) = self.budget.bandwidth_bytes_per_s {
+            let used = usage.output_bytes as f64 / seconds;
+            self.report(
+                clock,
+                BANDWIDTH_BUDGET_ALARM,
+                used > budget as f64,
+                format!("{used:.0} B/s of output over the budget of {budget} B/s"),
+            );
+        }
+    }
+
+    fn report(&self, clock: &RobotClock, code: u32, over: bool, message: String) {
+        if over {
+            raise_alarm(clock, &self.id, code, AlarmSeverity::Warning, message);
+        } else {
+            clear_alarm(&self.id, code);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::alarms;
+    use crate::config::read_configuration_str;
+
+    #[test]
+    fn test_budgets() {
+        let config = read_configuration_str(
+            r#"(
+                tasks: [
+                    (id: "budget_src", type: "Src", budget: (bandwidth_bytes_per_s: 1000)),
+                    (id: "budget_sink", type: "Sink", budget: (cpu_percent: 10.0, memory_bytes: 1000)),
+                ],
+                cnx: [(src: "budget_src", dst: "budget_sink", msg: "u64")],
+                runtime: (budget_checks: Continuous),
+            )"#
+            .to_string(),
+        )
+        .unwrap();
+        let mut budgets = CuBudgets::new(&config);
+        let (clock, mock) = RobotClock::mock();
+        let active = |code: u32| {
+            alarms().iter().any(|alarm| {
+                alarm.source.starts_with("budget_") && alarm.code == code && alarm.active
+            })
+        };
+
+        // The sink is busy 50ms per 100ms cycle and allocates 2000 bytes once.
+        let mut src = CuMsgMetadata::default();
+        let mut sink = CuMsgMetadata::default();
+        for cycle in 0..11u64 {
+            let start = CuDuration::from_nanos(cycle * 100_000_000);
+            sink.process_time.start = start.into();
+            sink.process_time.end = (start + CuDuration::from_nanos(50_000_000)).into();
+            mock.set_value(start.as_nanos());
+            budgets.record_output(0, &CuMsg::new(Some(cycle)));
+            budgets.record_allocated(1, if cycle == 3 { 2000 } else { 10 });
+            budgets.end_of_copperlist(&clock, &[&src, &sink]);
+        }
+        assert!(active(CPU_BUDGET_ALARM));
+        assert!(active(MEMORY_BUDGET_ALARM));
+        assert!(!active(BANDWIDTH_BUDGET_ALARM));
+
+        // Back within the cpu and memory budgets, over the bandwidth one.
+        src.process_time = Default::default();
+        for cycle in 11..21u64 {
+            let start = CuDuration::from_nanos(cycle * 100_000_000);
+            sink.process_time.start = start.into();
+            sink.process_time.end = (start + CuDuration::from_nanos(1_000_000)).into();
+            mock.set_value(start.as_nanos());
+            budgets.record_output(0, &CuMsg::new(Some(vec![0u8; 200])));
+            budgets.record_allocated(1, 10);
+            budgets.end_of_copperlist(&clock, &[&src, &sink]);
+        }
+        assert!(!active(CPU_BUDGET_ALARM));
+        assert!(!active(MEMORY_BUDGET_ALARM));
+        assert!(active(BANDWIDTH_BUDGET_ALARM));
+    }
+}
@@ -0,0 +1,221 @@
+//! Fault injection for testing, enabled with the `chaos` feature.
+//! A scenario scripts delays, dropped messages and errors on the tasks of a running application so
+//! the monitors, watchdogs and degraded modes can be exercised systematically before going to the
+//! field. The production configuration stays untouched, the scenario is a separate RON file:
+//!
+//! ```ron
+//! (
+//!     injections: [
+//!         // Overruns the camera on every copperlist from 100 to 200.
+//!         (task: "cam", from: 100, until: Some(200), fault: Delay(us: 20000)),
+//!         // Loses one detection out of 3 from copperlist 300 on.
+//!         (task: "detector", from: 300, every: 3, fault: Drop),
+//!         (task: "motors", from: 500, until: Some(501), fault: Error("injected driver fault")),
+//!     ],
+//! )
+//! ```
+//!
+//! ```rust,ignore
+//! app.inject_chaos(ChaosScenario::from_file("chaos.ron")?)?;
+//! ```
+//!
+//! The copperlist ids are the time base so a scenario replays the same way on every run.
+
+use crate::cutask::{CuMsg, CuMsgPayload};
+use crate::introspection::GraphDescription;
+use cu29_traits::{CuError, CuResult};
+use serde_derive::{Deserialize, Serialize};
+use std::fs::read_to_string;
+use std::time::Duration;
+
+/// What happens to a task when an injection is active.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChaosFault {
+    /// Sleeps in the process() of the task, the delay is part of its process time.
+    Delay { us: u64 },
+    /// The task runs but its output is lost.
+    Drop,
+    /// The task runs but reports this error to the monitor.
+    Error(String),
+}
+
+fn default_every() -> u32 {
+    1
+}
+
+/// A fault applied to a task over a range of copperlists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChaosInjection {
+    pub task: String,
+    /// The first copperlist id affected.
+    #[serde(default)]
+    pub from: u32,
+    /// The copperlist id where the injection stops, never if None.
+    #[serde(default)]
+    pub until: Option<u32>,
+    /// Only one copperlist out of `every` is affected in the range.
+    #[serde(default = "default_every")]
+    pub every: u32,
+    pub fault: ChaosFault,
+}
+
+impl ChaosInjection {
+    fn is_active(&self, culist_id: u32) -> bool {
+        culist_id >= self.from
+            && self.until.is_none_or(|until| culist_id < until)
+            && (culist_id - self.from) % self.every.max(1) == 0
+    }
+}
+
+/// A scripted list of faults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChaosScenario {
+    pub injections: Vec<ChaosInjection>,
+}
+
+impl ChaosScenario {
+    pub fn from_ron(content: &str) -> CuResult<Self> {
+        ron::from_str(content)
+            .map_err(|e| CuError::from("Invalid chaos scenario").add_cause(&e.to_string()))
+    }
+
+    pub fn from_file(path: &str) -> CuResult<Self> {
+        let content = read_to_string(path).map_err(|e| {
+            CuError::from(format!("Failed to read the chaos scenario: {path}"))
+                .add_cause(&e.to_string())
+        })?;
+        Self::from_ron(&content)
+    }
+}
+
+/// The scenario bound to the tasks of a running application.
+#[derive(Debug, Default)]
+pub struct CuChaos {
+    /// The node id of the task of each injection.
+    injections: Vec<(usize, ChaosInjection)>,
+    injected: u64,
+}
+
+impl CuChaos {
+    pub fn new(scenario: &ChaosScenario, graph: &GraphDescription) -> CuResult<Self> {
+        let injections = scenario
+            .injections
+            .iter()
+            .map(|injection| {
+                graph
+                    .tasks
+                    .iter()
+                    .find(|task| task.id == injection.task)
+                    .map(|task| (task.index, injection.clone()))
+                    .ok_or_else(|| {
+                        CuError::from(format!(
+                            "The chaos scenario targets an unknown task: {}",
+                            injection.task
+                        ))
+                    })
+            })
+            .collect::<CuResult<_>>()?;
+        Ok(Self {
+            injections,
+            injected: 0,
+        })
+    }
+
+    /// Number of faults injected since the start.
+    pub fn injected(&self) -> u64 {
+        self.injected
+    }
+
+    /// The fault to apply to this process() call, this is called by the generated code.
+    /// A delay is applied right away.
+    pub fn before_process(&mut self, node_id: usize, culist_id: u32) -> Option<ChaosFault> {
+        let (_, injection) = self
+            .injections
+            .iter()
+            .find(|(id, injection)| *id == node_id && injection.is_active(culist_id))?;
+        self.injected += 1;
+        if let ChaosFault::Delay { us } = injection.fault {
+            std::thread::sleep(Duration::from_micros(us));
+        }
+        Some(injection.fault.clone())
+    }
+}
+
+/// Applies the fault to the result and the output of a process() call, this is called by the generated code.
+pub fn after_process<T: CuMsgPayload>(
+    fault: Option<ChaosFault>,
+    result: CuResult<()>,
+    output: &mut CuMsg<T>,
+) -> CuResult<()> {
+    match fault {
+        Some(ChaosFault::Drop) => {
+            output.clear_payload();
+            result
+        }
+        Some(ChaosFault::Error(message)) => Err(CuError::from(message)),
+        _ => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CuConfig, Node};
+
+    fn graph() -> GraphDescription {
+        let mut config = CuConfig::default();
+        let cam = config.add_node(Node::new("cam", "Camera"), None).unwrap();
+        let disp = config.add_node(Node::new("disp", "Display"), None).unwrap();
+        config.connect(cam, disp, "i32").unwrap();
+        GraphDescription::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_scenario() {
+        let scenario = ChaosScenario::from_ron(
+            r#"(
+                injections: [
+                    (task: "cam", from: 2, until: Some(8), every: 3, fault: Drop),
+                    (task: "disp", fault: Error("injected")),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.injections[1].from, 0);
+        assert_eq!(scenario.injections[1].every, 1);
+
+        let mut chaos = CuChaos::new(&scenario, &graph()).unwrap();
+        let dropped: Vec<u32> = (0..10)
+            .filter(|id| chaos.before_process(0, *id).is_some())
+            .collect();
+        assert_eq!(dropped, vec![2, 5]);
+        assert_eq!(
+            chaos.before_process(1, 42),
+            Some(ChaosFault::Error("injected".to_string()))
+        );
+        assert_eq!(chaos.injected(), 3);
+    }
+
+    #[test]
+    fn test_unknown_task() {
+        let scenario =
+            ChaosScenario::from_ron(r#"(injections: [(task: "lidar", fault: Delay(us: 10))])"#)
+                .unwrap();
+        assert!(CuChaos::new(&scenario, &graph()).is_err());
+    }
+
+    #[test]
+    fn test_after_process() {
+        let mut msg = CuMsg::new(Some(1i32));
+        assert!(after_process(None, Ok(()), &mut msg).is_ok());
+        assert_eq!(msg.payload(), Some(&1));
+        assert!(after_process(Some(ChaosFault::Drop), Ok(()), &mut msg).is_ok());
+        assert_eq!(msg.payload(), None);
+        let error = after_process(
+            Some(ChaosFault::Error("boom".to_string())),
+            Ok(()),
+            &mut msg,
+        );
+        assert!(error.is_err());
+    }
+}
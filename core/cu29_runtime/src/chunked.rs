@@ -0,0 +1,308 @@
+//! Long computations split in chunks over several cycles ("chunked tasks").
+//! A planner or a mapper taking 50ms would hold a 100Hz copper loop back, and an actor puts it on
+//! a thread. A task with `chunked` in its configuration instead advances its computation by a
+//! chunk of `budget_us` in every cycle and gives its result once it is done:
+//!
+//! ```ron
+//! (id: "planner", type: "tasks::Planner", chunked: (budget_us: 2000)),
+//! ```
+//!
+//! The task implements [CuChunkedTask] and the runtime wraps it in a [CuChunked]. In a cycle where
+//! no computation is in progress, [CuChunkedTask::begin] is given the inputs and copies what the
+//! computation needs. Then [CuChunkedTask::process_chunk] is called in this cycle and the next ones
+//! until it returns [Progress::Done]: its output is empty in the cycles before, and the inputs
+//! coming in while the computation is in progress are not given to the task.
+//!
+//! The chunks are cooperative: the task checks the time against the budget it is given (see
+//! [ChunkDeadline]) and a chunk overrunning it is only counted. Everything runs in the copper loop
+//! so, unlike an actor, the state of the computation is part of the frozen task states and a
+//! replay gives the same results in the same cycles.
+
+use crate::config::{ComponentConfig, ConfigKey};
+use crate::cutask::{CuMsg, CuMsgPack, CuMsgPayload, CuTask, Freezable};
+use crate::log::*;
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use cu29_clock::{CuDuration, CuTime, RobotClock};
+use cu29_traits::CuResult;
+
+/// How far the computation of a chunked task went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    /// Not finished, with the fraction done so far (0.0 to 1.0) if the task knows it. The fraction
+    /// is shown as the status of the empty output.
+    Pending(Option<f32>),
+    /// Finished, the output holds the result.
+    Done,
+}
+
+/// A task computing its output over several cycles, see the module documentation.
+pub trait CuChunkedTask<'cl>: Freezable {
+    type Input: CuMsgPack<'cl>;
+    /// The payload of the output.
+    type Output: CuMsgPayload;
+
+    /// The config keys of the task, usually the SCHEMA of its CuConfigStruct.
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = None;
+
+    fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized;
+
+    fn start(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        Ok(())
+    }
+
+    /// Called in the cycles where no computation is in progress: takes from the inputs what the
+    /// computation needs and returns false if there is nothing to compute.
+    fn begin(&mut self, clock: &RobotClock, input: Self::Input) -> CuResult<bool>;
+
+    /// Advances the computation for `budget` at most. The output is empty, without status, when it
+    /// is called and is sent as the task leaves it when it returns [Progress::Done]. An error
+    /// abandons the computation.
+    fn process_chunk(
+        &mut self,
+        clock: &RobotClock,
+        budget: CuDuration,
+        output: &mut CuMsg<Self::Output>,
+    ) -> CuResult<Progress>;
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        Ok(())
+    }
+}
+
+/// When a chunk has to give the cycle back, to check between two steps of the computation.
+pub struct ChunkDeadline(CuTime);
+
+impl ChunkDeadline {
+    pub fn new(clock: &RobotClock, budget: CuDuration) -> Self {
+        Self(clock.now() + budget)
+    }
+
+    pub fn is_reached(&self, clock: &RobotClock) -> bool {
+        clock.now() >= self.0
+    }
+}
+
+/// Runs the chunked task `T` in the copper loop with `BUDGET_US` for each chunk.
+pub struct CuChunked<T, const BUDGET_US: u64> {
+    task: T,
+    in_progress: bool,
+    /// The chunks which took longer than the budget.
+    overruns: u64,
+}
+
+impl<T, const BUDGET_US: u64> CuChunked<T, BUDGET_US> {
+    /// If a computation is in progress.
+    pub fn in_progress(&self) -> bool {
+        self.in_progress
+    }
+
+    /// The chunks which took longer than their budget.
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+}
+
+/// The state of the task and whether it is in the middle of a computation.
+impl<T: Freezable, const BUDGET_US: u64> Freezable for CuChunked<T, BUDGET_US> {
+    fn freeze<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.in_progress.encode(encoder)?;
+        self.task.freeze(encoder)
+    }
+
+    fn thaw<D: Decoder>(&mut self, decoder: &mut D) -> Result<(), DecodeError> {
+        self.in_progress = Decode::decode(decoder)?;
+        self.task.thaw(decoder)
+    }
+}
+
+impl<'cl, T, const BUDGET_US: u64> CuTask<'cl> for CuChunked<T, BUDGET_US>
+where
+    T: CuChunkedTask<'cl>,
+    T::Output: 'cl,
+{
+    type Input = T::Input;
+    type Output = &'cl mut CuMsg<T::Output>;
+
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = T::CONFIG_SCHEMA;
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            task: T::new(config)?,
+            in_progress: false,
+            overruns: 0,
+        })
+    }
+
+    fn start(&mut self, clock: &RobotClock) -> CuResult<()> {
+        self.task.start(clock)
+    }
+
+    fn process(
+        &mut self,
+        clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        if !self.in_progress {
+            self.in_progress = self.task.begin(clock, input)?;
+        }
+        output.clear_payload();
+        output.metadata.set_status("");
+        if !self.in_progress {
+            return Ok(());
+        }
+
+        let budget = CuDuration::from_micros(BUDGET_US);
+        let started = clock.now();
+        let progress = self.task.process_chunk(clock, budget, output);
+        if clock.now() - started > budget {
+            self.overruns += 1;
+            debug!("Chunk over its budget of {}us.", BUDGET_US);
+        }
+        match progress {
+            Ok(Progress::Done) => self.in_progress = false,
+            Ok(Progress::Pending(done)) => {
+                output.clear_payload();
+                if let Some(done) = done {
+                    output
+                        .metadata
+                        .set_status(format!("{:.0}%", done.clamp(0.0, 1.0) * 100.0));
+                }
+            }
+            Err(error) => {
+                self.in_progress = false;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, clock: &RobotClock) -> CuResult<()> {
+        self.in_progress = false;
+        self.task.stop(clock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_msg;
+    use bincode::config;
+    use bincode::de::read::SliceReader;
+    use bincode::de::DecoderImpl;
+    use bincode::enc::write::SliceWriter;
+    use bincode::enc::EncoderImpl;
+    use cu29_clock::RobotClockMock;
+    use std::time::Duration;
+
+    /// Sums the integers up to its input, 10 of them per millisecond of clock.
+    struct Summer {
+        mock: Option<RobotClockMock>,
+        next: u64,
+        until: u64,
+        sum: u64,
+    }
+
+    impl Freezable for Summer {}
+
+    impl<'cl> CuChunkedTask<'cl> for Summer {
+        type Input = input_msg!('cl, u64);
+        type Output = u64;
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self> {
+            Ok(Self {
+                mock: None,
+                next: 0,
+                until: 0,
+                sum: 0,
+            })
+        }
+
+        fn begin(&mut self, _clock: &RobotClock, input: Self::Input) -> CuResult<bool> {
+            let Some(until) = input.payload() else {
+                return Ok(false);
+            };
+            (self.next, self.until, self.sum) = (1, *until, 0);
+            Ok(true)
+        }
+
+        fn process_chunk(
+            &mut self,
+            clock: &RobotClock,
+            budget: CuDuration,
+            output: &mut CuMsg<u64>,
+        ) -> CuResult<Progress> {
+            let deadline = ChunkDeadline::new(clock, budget);
+            while !deadline.is_reached(clock) {
+                if self.next > self.until {
+                    output.set_payload(self.sum);
+                    return Ok(Progress::Done);
+                }
+                self.sum += self.next;
+                self.next += 1;
+                let mock = self.mock.as_ref().unwrap();
+                mock.increment(Duration::from_micros(100));
+            }
+            Ok(Progress::Pending(Some(
+                self.next as f32 / self.until as f32,
+            )))
+        }
+    }
+
+    #[test]
+    fn test_chunks() {
+        let (clock, mock) = RobotClock::mock();
+        let mut chunked = CuChunked::<Summer, 1000>::new(None).unwrap();
+        chunked.task.mock = Some(mock);
+        chunked.start(&clock).unwrap();
+
+        let mut output = CuMsg::<u64>::default();
+        chunked
+            .process(&clock, &CuMsg::new(None), &mut output)
+            .unwrap();
+        assert!(!chunked.in_progress());
+        assert_eq!(output.payload(), None);
+
+        // 25 numbers to sum, 10 per chunk: the result comes in the third cycle.
+        let mut results = Vec::new();
+        for until in [25, 100, 100, 100] {
+            chunked
+                .process(&clock, &CuMsg::new(Some(until)), &mut output)
+                .unwrap();
+            results.push((
+                output.payload().copied(),
+                output.metadata.status_txt.0.to_string(),
+            ));
+        }
+        assert_eq!(
+            results,
+            vec![
+                (None, "44%".to_string()),
+                (None, "84%".to_string()),
+                (Some(325), String::new()),
+                (None, "11%".to_string()),
+            ]
+        );
+        assert_eq!(chunked.overruns(), 0);
+
+        // A computation in progress is part of the frozen state.
+        let mut frozen = [0u8; 16];
+        let mut encoder = EncoderImpl::new(SliceWriter::new(&mut frozen), config::standard());
+        chunked.freeze(&mut encoder).unwrap();
+        let mut thawed = CuChunked::<Summer, 1000>::new(None).unwrap();
+        let mut decoder = DecoderImpl::new(SliceReader::new(&frozen), config::standard(), ());
+        thawed.thaw(&mut decoder).unwrap();
+        assert!(thawed.in_progress());
+
+        chunked.stop(&clock).unwrap();
+        assert!(!chunked.in_progress());
+    }
+}
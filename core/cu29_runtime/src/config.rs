@@ -3,16 +3,18 @@
 //! The configuration is serialized in the RON format.
 //! The configuration is used to generate the runtime code at compile time.
 
+use cu29_clock::{ClockDomain, ClockSource, RobotDomain};
 use cu29_traits::{CuError, CuResult};
 use html_escape::encode_text;
 use petgraph::stable_graph::{EdgeIndex, StableDiGraph};
-use petgraph::visit::EdgeRef;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
 pub use petgraph::Direction::Incoming;
 pub use petgraph::Direction::Outgoing;
 use ron::extensions::Extensions;
 use ron::value::Value as RonValue;
 use ron::{Number, Options};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
@@ -65,6 +67,35 @@ impl ComponentConfig {
     }
 }
 
+/// The key under which the runtime gives the namespace of the application to the tasks with a config.
+#[allow(dead_code)]
+pub const NAMESPACE_KEY: &str = "_namespace";
+
+impl ComponentConfig {
+    /// The namespace of the application, see [CuConfig::namespace].
+    #[allow(dead_code)]
+    pub fn namespace(&self) -> Option<String> {
+        self.get::<String>(NAMESPACE_KEY)
+    }
+}
+
+/// Prefixes a topic or a key expression with the namespace of the application, if it has one:
+/// "robot1/imu" for the topic "imu" in the namespace "robot1".
+/// The network components call it on the topics of their config.
+#[allow(dead_code)]
+pub fn namespaced(config: Option<&ComponentConfig>, topic: &str) -> String {
+    match config.and_then(ComponentConfig::namespace) {
+        Some(namespace) if !namespace.is_empty() => {
+            format!(
+                "{}/{}",
+                namespace.trim_end_matches('/'),
+                topic.trim_start_matches('/')
+            )
+        }
+        _ => topic.to_string(),
+    }
+}
+
 // The configuration Serialization format is as follows:
 // (
 //   tasks : [ (id: "toto", type: "zorglub::MyType", config: {...}),
@@ -76,6 +107,12 @@ impl ComponentConfig {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Value(RonValue);
 
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value(RonValue::Bool(value))
+    }
+}
+
 impl From<i32> for Value {
     fn from(value: i32) -> Self {
         Value(RonValue::Number(value.into()))
@@ -203,6 +240,241 @@ impl Display for Value {
     }
 }
 
+/// Fallible conversion of a config value, the `From<Value>` conversions panic on a mismatch.
+/// It is what `#[derive(CuConfigStruct)]` uses to read the fields.
+#[allow(dead_code)]
+pub trait FromConfigValue: Sized {
+    /// What the value should look like, for the error messages.
+    const EXPECTED: &'static str;
+
+    fn from_config_value(value: &Value) -> Option<Self>;
+}
+
+impl FromConfigValue for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_config_value(value: &Value) -> Option<Self> {
+        match value {
+            Value(RonValue::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl FromConfigValue for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_config_value(value: &Value) -> Option<Self> {
+        match value {
+            Value(RonValue::String(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn config_value_as_i128(value: &Value) -> Option<i128> {
+    let Value(RonValue::Number(num)) = value else {
+        return None;
+    };
+    match num {
+        Number::I8(n) => Some(*n as i128),
+        Number::I16(n) => Some(*n as i128),
+        Number::I32(n) => Some(*n as i128),
+        Number::I64(n) => Some(*n as i128),
+        Number::U8(n) => Some(*n as i128),
+        Number::U16(n) => Some(*n as i128),
+        Number::U32(n) => Some(*n as i128),
+        Number::U64(n) => Some(*n as i128),
+        _ => None,
+    }
+}
+
+macro_rules! impl_from_config_value_for_int {
+    ($($target:ty),* $(,)?) => {
+        $(
+            impl FromConfigValue for $target {
+                const EXPECTED: &'static str = concat!("an integer fitting in a ", stringify!($target));
+
+                fn from_config_value(value: &Value) -> Option<Self> {
+                    config_value_as_i128(value).and_then(|n| <$target>::try_from(n).ok())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_config_value_for_int!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize);
+
+impl FromConfigValue for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_config_value(value: &Value) -> Option<Self> {
+        match value {
+            Value(RonValue::Number(num)) => Some(num.into_f64()),
+            _ => None,
+        }
+    }
+}
+
+impl FromConfigValue for f32 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_config_value(value: &Value) -> Option<Self> {
+        f64::from_config_value(value).map(|v| v as f32)
+    }
+}
+
+/// A typed view of a ComponentConfig, usually implemented with `#[derive(CuConfigStruct)]`:
+///
+/// ```ignore
+/// #[derive(CuConfigStruct)]
+/// struct MyTaskConfig {
+///     topic: String,                       // required
+///     #[config(default = 10)]
+///     rate_hz: u32,                        // 10 if absent
+///     #[config(range = 0.0..=1.0)]
+///     gain: Option<f64>,                   // None if absent, checked if present
+///     #[config(key = "zenoh_config_file")]
+///     session_config: Option<String>,      // read from another key than its name
+/// }
+///
+/// let config = MyTaskConfig::from_config(config)?;
+/// ```
+///
+/// The task can hand the derived [CuConfigStruct::SCHEMA] to the runtime so the config is checked
+/// for unknown keys at startup:
+///
+/// ```ignore
+/// impl<'cl> CuSrcTask<'cl> for MyTask {
+///     const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = Some(MyTaskConfig::SCHEMA);
+///     ...
+/// }
+/// ```
+#[allow(dead_code)]
+pub trait CuConfigStruct: Sized {
+    /// The keys read by from_config.
+    const SCHEMA: &'static [ConfigKey];
+
+    fn from_config(config: Option<&ComponentConfig>) -> CuResult<Self>;
+}
+
+/// A key a task reads from its config.
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub struct ConfigKey {
+    pub name: &'static str,
+    /// What the value should be, like "a string", see [FromConfigValue::EXPECTED].
+    pub expected: &'static str,
+    pub required: bool,
+    /// The default value as written in the code.
+    pub default: Option<&'static str>,
+    /// True if the value can be read as `value_type`.
+    pub accepts: fn(&Value) -> bool,
+}
+
+/// The [ConfigKey::accepts] of a key read as a T.
+#[allow(dead_code)]
+pub fn accepts<T: FromConfigValue>(value: &Value) -> bool {
+    T::from_config_value(value).is_some()
+}
+
+/// Number of single character edits to go from a to b.
+#[allow(dead_code)]
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The candidate a misspelled key was most likely meant to be.
+#[allow(dead_code)]
+pub fn closest_key<'a>(
+    key: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2 && *distance < candidate.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Checks a task config against the schema the task declares: unknown keys, missing required
+/// keys and values of the wrong type are all reported at once. `owner` is the id of the task.
+/// Nothing is checked if the task doesn't declare a schema.
+#[allow(dead_code)]
+pub fn check_config_keys(
+    owner: &str,
+    schema: Option<&[ConfigKey]>,
+    config: Option<&ComponentConfig>,
+) -> CuResult<()> {
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+    let mut errors = Vec::new();
+    if let Some(ComponentConfig(config)) = config {
+        let mut keys: Vec<&String> = config.keys().collect();
+        keys.sort();
+        for key in keys {
+            if schema.iter().any(|expected| expected.name == key) {
+                continue;
+            }
+            match closest_key(key, schema.iter().map(|expected| expected.name)) {
+                Some(closest) => errors.push(format!(
+                    "{owner}: unknown config key \"{key}\", did you mean \"{closest}\"?"
+                )),
+                None => errors.push(format!("{owner}: unknown config key \"{key}\".")),
+            }
+        }
+    }
+    for expected in schema {
+        match config.and_then(|ComponentConfig(config)| config.get(expected.name)) {
+            None if expected.required => errors.push(format!(
+                "{owner}: the config key \"{}\" is required, expected {}.",
+                expected.name, expected.expected
+            )),
+            Some(value) if !(expected.accepts)(value) => errors.push(format!(
+                "{owner}: the config key \"{}\" should be {}, got {value}.",
+                expected.name, expected.expected
+            )),
+            _ => {}
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CuError::from(errors.join("\n")))
+    }
+}
+
+/// Reads a field of a CuConfigStruct, `owner` is the name of the struct for the error messages.
+#[allow(dead_code)]
+pub fn config_field<T: FromConfigValue>(
+    config: Option<&ComponentConfig>,
+    owner: &str,
+    key: &str,
+) -> CuResult<Option<T>> {
+    let Some(value) = config.and_then(|ComponentConfig(config)| config.get(key)) else {
+        return Ok(None);
+    };
+    T::from_config_value(value).map(Some).ok_or_else(|| {
+        CuError::from(format!(
+            "{owner}: the config key \"{key}\" should be {}, got {value}.",
+            T::EXPECTED
+        ))
+    })
+}
+
 /// A node in the configuration graph.
 /// A node represents a Task in the system Graph.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -216,6 +488,42 @@ pub struct Node {
     config: Option<ComponentConfig>,
 
     missions: Option<Vec<String>>,
+
+    /// The process running the task when the graph is split across processes, see
+    /// [CuConfig::partition].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    process: Option<String>,
+
+    /// The resources the task is expected to use, checked by the runtime, see `cu29::budget`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    budget: Option<ResourceBudget>,
+
+    /// Positions in the Input of the task of the optional inputs this graph leaves unconnected,
+    /// the task receives None for them. See `input_msg!`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unconnected_inputs: Option<Vec<usize>>,
+
+    /// The task picks at every cycle the connections its output goes to, see
+    /// `cu29::cutask::CuMsg::route_to`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    routed: bool,
+
+    /// The priority of the task over the other branches of the graph, see [TaskPriority].
+    #[serde(default, skip_serializing_if = "TaskPriority::is_normal")]
+    priority: TaskPriority,
+
+    /// Runs the task on its own thread, see [ActorConfig].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actor: Option<ActorConfig>,
+
+    /// Runs the computation of the task in chunks over several cycles, see [ChunkedConfig].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunked: Option<ChunkedConfig>,
+
+    /// A disabled task is replaced by a stub at compile time: the task is neither created nor run
+    /// and its output is empty. See `cu29::cutask::CuStubTask`.
+    #[serde(default = "default_as_true", skip_serializing_if = "Clone::clone")]
+    enabled: bool,
 }
 
 impl Node {
@@ -227,6 +535,14 @@ impl Node {
             // base_period_ns: None,
             config: None,
             missions: None,
+            process: None,
+            budget: None,
+            unconnected_inputs: None,
+            routed: false,
+            priority: TaskPriority::Normal,
+            actor: None,
+            chunked: None,
+            enabled: true,
         }
     }
 
@@ -235,6 +551,27 @@ impl Node {
         self.id.clone()
     }
 
+    /// The process running the task, see [CuConfig::partition].
+    #[allow(dead_code)]
+    pub fn get_process(&self) -> Option<&str> {
+        self.process.as_deref()
+    }
+
+    #[allow(dead_code)]
+    pub fn set_process(&mut self, process: Option<String>) {
+        self.process = process;
+    }
+
+    #[allow(dead_code)]
+    pub fn get_budget(&self) -> Option<&ResourceBudget> {
+        self.budget.as_ref()
+    }
+
+    #[allow(dead_code)]
+    pub fn set_budget(&mut self, budget: Option<ResourceBudget>) {
+        self.budget = budget;
+    }
+
     #[allow(dead_code)]
     pub fn set_type(mut self, name: Option<String>) -> Self {
         self.type_ = name;
@@ -246,6 +583,47 @@ impl Node {
         self.type_.as_ref().unwrap()
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[allow(dead_code)]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// If the task routes its output per cycle (`routed: true`).
+    #[allow(dead_code)]
+    pub fn is_routed(&self) -> bool {
+        self.routed
+    }
+
+    #[allow(dead_code)]
+    pub fn get_priority(&self) -> TaskPriority {
+        self.priority
+    }
+
+    #[allow(dead_code)]
+    pub fn set_priority(&mut self, priority: TaskPriority) {
+        self.priority = priority;
+    }
+
+    /// How the task runs on its own thread, None if it runs in the copper loop.
+    pub fn get_actor(&self) -> Option<&ActorConfig> {
+        self.actor.as_ref()
+    }
+
+    /// How the computation of the task is split over the cycles, None if it runs in one process().
+    pub fn get_chunked(&self) -> Option<&ChunkedConfig> {
+        self.chunked.as_ref()
+    }
+
+    /// The positions of the optional inputs left unconnected.
+    #[allow(dead_code)]
+    pub fn get_unconnected_inputs(&self) -> &[usize] {
+        self.unconnected_inputs.as_deref().unwrap_or_default()
+    }
+
     #[allow(dead_code)]
     pub fn get_instance_config(&self) -> Option<&ComponentConfig> {
         self.config.as_ref()
@@ -286,6 +664,55 @@ pub struct Cnx {
 
     /// Tells Copper if it needs to log the messages.
     pub store: Option<bool>,
+
+    /// How the messages are delivered to the destination, see [CnxPolicy].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<CnxPolicy>,
+
+    /// Key of the messages of this connection. The keyed connections carrying the same message
+    /// type to a task are merged into a single input, a map of their messages by key
+    /// (see [crate::cutask::CuKeyedMsgs]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// Clock domain of the timestamps of the messages (see [cu29_clock::ClockDomain]), the robot
+    /// clock if not set. A task can't take inputs from different domains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+
+    /// Maximum encoded size of the payloads of this connection in bytes. Once every output of
+    /// the graph declares one the log sections are sized exactly (see
+    /// [CuConfig::get_node_output_max_size]), and a task producing a bigger payload fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u32>,
+
+    /// Maximum age in milliseconds of the payloads given to the destination, measured from their
+    /// time of validity when its process() starts. The older ones are counted as stale in the
+    /// statistics of the connection (see [crate::introspection::CnxStats]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_ms: Option<u32>,
+}
+
+impl Cnx {
+    /// The clock domain of the connection, "robot" if not tagged.
+    pub fn clock_domain(&self) -> &str {
+        self.domain.as_deref().unwrap_or(RobotDomain::NAME)
+    }
+}
+
+/// How the messages of a connection are delivered to its destination. When an output feeds
+/// several tasks, each of its connections has its own policy and its own delivery counters.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CnxPolicy {
+    /// The destination gets the message of the current copperlist, shared with the other
+    /// destinations without any copy.
+    #[default]
+    Latest,
+    /// When the source produces nothing, the destination gets its last message with a payload
+    /// again. That message is copied when it arrives.
+    Latched,
+    /// The destination gets one message out of n, the others are dropped.
+    Decimate(u32),
 }
 
 pub type CuGraph = StableDiGraph<Node, Cnx, NodeId>;
@@ -400,18 +827,35 @@ impl ConfigGraphs {
                 .clone(),
         );
 
-        let graph = self.get_graph_mut(mission_id)?;
-        graph.add_edge(
-            source.into(),
-            target.into(),
+        self.add_cnx(
+            source,
+            target,
             Cnx {
                 src: src_id,
                 dst: dst_id,
                 msg: msg_type.to_string(),
                 missions,
                 store,
+                policy: None,
+                key: None,
+                domain: None,
+                max_size: None,
+                max_age_ms: None,
             },
-        );
+            mission_id,
+        )
+    }
+
+    /// Adds a connection as written in the configuration.
+    fn add_cnx(
+        &mut self,
+        source: NodeId,
+        target: NodeId,
+        cnx: Cnx,
+        mission_id: Option<&str>,
+    ) -> CuResult<()> {
+        let graph = self.get_graph_mut(mission_id)?;
+        graph.add_edge(source.into(), target.into(), cnx);
         Ok(())
     }
 
@@ -653,6 +1097,23 @@ pub struct CuConfig {
     // This is not what is directly serialized, see the custom serialization below.
     pub monitor: Option<MonitorConfig>,
     pub logging: Option<LoggingConfig>,
+    pub runtime: Option<RuntimeConfig>,
+    pub types: Option<HashMap<String, String>>,
+    /// Versions of the message types, they are logged to be able to migrate old logs.
+    pub schema_versions: Option<HashMap<String, u32>>,
+    /// Prefix of the topics and key expressions of the network components, so several instances of the same
+    /// application (a fleet of robots) can share a broker or router.
+    pub namespace: Option<String>,
+    /// The run profiles of the application, see [ProfileConfig].
+    pub profiles: Option<Vec<ProfileConfig>>,
+    /// The profile the application runs with, all the tasks and connections are enabled if not set.
+    pub profile: Option<String>,
+    /// How the connections between the processes are bridged, see [CuConfig::partition].
+    pub bridges: Option<BridgesConfig>,
+    /// The services provided by tasks to other tasks, see [ServiceConfig].
+    pub services: Option<Vec<ServiceConfig>>,
+    /// The properties of the connections checked at runtime, see [InvariantConfig].
+    pub invariants: Option<Vec<InvariantConfig>>,
     pub graphs: ConfigGraphs,
 }
 
@@ -688,6 +1149,345 @@ pub struct LoggingConfig {
     pub section_size_mib: Option<u64>,
     #[serde(default = "default_as_true", skip_serializing_if = "Clone::clone")]
     pub enable_task_logging: bool,
+    /// Uploads the slabs of the logs in the background once they are complete, see the
+    /// `cu-log-upload` component.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<UploadConfig>,
+    /// Bound of the size of the log in its storage, unbounded otherwise. It needs to hold at
+    /// least 2 slabs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size_mib: Option<u64>,
+    /// What the logger does once the log reaches max_size_mib.
+    #[serde(default)]
+    pub on_full: LogFullPolicy,
+}
+
+/// What the logger does once the log reaches its `max_size_mib`, the runtime surfaces it to the
+/// monitor: the logger stopping raises an alarm, the rotations and the waits are events.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFullPolicy {
+    /// Stops logging, the beginning of the run is kept.
+    #[default]
+    Stop,
+    /// Deletes the oldest slabs of the log but the first one, the end of the run is kept.
+    Rotate,
+    /// Waits for a slab to be deleted by someone else, the uploader deleting what it sent for
+    /// example. The copper loop waits with it.
+    Block,
+}
+
+/// Where and how the uploader sends the logs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UploadConfig {
+    /// "http://..." or "https://..." to PUT the slabs under, "file:///..." to copy them to a
+    /// directory (a mounted share for example).
+    pub url: String,
+    /// Limits the bandwidth used by the uploads, unlimited otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bandwidth_kib_s: Option<u64>,
+    /// Deletes the local slabs once the destination confirmed it has them entirely.
+    #[serde(default = "default_as_true", skip_serializing_if = "Clone::clone")]
+    pub delete_after_upload: bool,
+}
+
+/// The resources a task declares it needs, what it is allowed to use at most.
+/// A task going over one of them raises an alarm, see `cu29::budget`.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct ResourceBudget {
+    /// Share of the time of the copper loop spent in the process() of the task, in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<f64>,
+    /// Bytes allocated on the heap by one process() call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    /// Encoded size of the outputs of the task per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bandwidth_bytes_per_s: Option<u64>,
+}
+
+/// How important a task is when the CPU is scarce. The tasks run the most important first, as
+/// soon as their inputs are ready, and a task inherits the priority of the tasks it feeds so a
+/// whole chain runs before the tasks of a lower priority.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskPriority {
+    /// The safety critical chains, an estimator feeding a controller feeding the actuators.
+    Critical,
+    #[default]
+    Normal,
+    /// Skipped, with an empty output, when the cycle is late on the loop rate (see
+    /// [RuntimeConfig::loop_rate_hz]): the telemetry, the thumbnails of the log...
+    BestEffort,
+}
+
+impl TaskPriority {
+    fn is_normal(&self) -> bool {
+        *self == TaskPriority::Normal
+    }
+}
+
+fn default_actor_queue() -> usize {
+    1
+}
+
+/// A heavy task running on its own thread, fed by a bounded queue, so the copper loop never waits
+/// for it, see `cu29::actor`:
+///
+/// ```ron
+/// (id: "detector", type: "tasks::Detector", actor: (queue: 2, max_age_ms: 200)),
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActorConfig {
+    /// The inputs waiting for the task at most, the oldest one is dropped for a new one.
+    #[serde(default = "default_actor_queue")]
+    pub queue: usize,
+    /// The results computed from an input queued longer ago than this are dropped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_ms: Option<u64>,
+}
+
+fn default_chunk_budget_us() -> u64 {
+    1000
+}
+
+/// A long computation (planning, mapping...) split in chunks of `budget_us` at most, one per cycle,
+/// so the cycle time stays bounded without a thread, see `cu29::chunked`:
+///
+/// ```ron
+/// (id: "planner", type: "tasks::Planner", chunked: (budget_us: 2000)),
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkedConfig {
+    /// The time given to the task to advance its computation in every cycle.
+    #[serde(default = "default_chunk_budget_us")]
+    pub budget_us: u64,
+}
+
+/// The external time base the clock of the runtime is slaved to, so the tov of the messages line up
+/// with hardware-timestamped sensors:
+///
+/// ```ron
+/// runtime: (clock: (source: "/dev/ptp0", resync_period_ms: 500)),
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClockConfig {
+    /// "monotonic", "tai" or a PTP hardware clock as "/dev/ptp0", see [ClockSource].
+    pub source: String,
+    /// The offset to the external clock is measured again at this period.
+    #[serde(default = "default_resync_period_ms")]
+    pub resync_period_ms: u64,
+}
+
+fn default_resync_period_ms() -> u64 {
+    1000
+}
+
+impl ClockConfig {
+    pub fn source(&self) -> CuResult<ClockSource> {
+        self.source.parse().map_err(CuError::from)
+    }
+}
+
+/// When the runtime checks the usage of the tasks against their [ResourceBudget].
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetChecks {
+    /// Over the first second of the run only, to admit the tasks.
+    #[default]
+    Startup,
+    /// Over every second of the run.
+    Continuous,
+    /// The budgets are only validated when the application is built.
+    Off,
+}
+
+/// What the copper loop does when a cycle takes longer than its period.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrunPolicy {
+    /// Starts the next cycles right away until the loop is back on its original schedule.
+    #[default]
+    CatchUp,
+    /// Drops the missed cycles and waits for the next deadline of the schedule.
+    Skip,
+}
+
+/// How the copper loop waits for its next cycle when it runs at a fixed rate, from the lowest
+/// power draw to the most precise start of the cycles.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleStrategy {
+    /// Sleeps until the deadline (clock_nanosleep on Linux), the core can go idle. The cycle starts
+    /// late by the timer slack of the OS, from about 50µs to a millisecond.
+    #[default]
+    Sleep,
+    /// Gives the core to the other threads until the deadline. The cycle starts late by the time
+    /// slice of the thread running then, the core stays busy if there is none.
+    Yield,
+    /// Busy waits until the deadline: the most precise start of the cycles but a core at 100%.
+    Spin,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct RuntimeConfig {
+    /// Runs the copper loop at this fixed frequency instead of as fast as the tasks allow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loop_rate_hz: Option<f64>,
+    #[serde(default)]
+    pub overrun_policy: OverrunPolicy,
+    /// How the loop waits between the cycles, see [IdleStrategy].
+    #[serde(default)]
+    pub idle_strategy: IdleStrategy,
+    /// Acquires the data of the sources for the next cycle while the rest of the graph processes
+    /// the current one (see `cu29::pipeline`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pipelined: bool,
+    /// When the usage of the tasks is checked against their budgets.
+    #[serde(default)]
+    pub budget_checks: BudgetChecks,
+    /// Slaves the clock of the runtime to an external time base, it is not used in simulation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockConfig>,
+}
+
+/// A run profile: a degraded configuration of the graph selected when the application starts, for
+/// example when a sensor fails its hardware checks. The disabled tasks are neither started nor
+/// run and their outputs are empty, the disabled connections deliver empty messages.
+///
+/// ```ron
+/// profiles: [
+///     (id: "full"),
+///     (id: "no_camera", disabled_tasks: ["camera", "detector"]),
+///     (id: "bench", disabled_tasks: ["motors"], disabled_cnx: ["lidar->planner"]),
+/// ],
+/// profile: "full",
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProfileConfig {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_tasks: Vec<String>,
+    /// The connections as "src->dst".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_cnx: Vec<String>,
+}
+
+impl ProfileConfig {
+    /// Whether this profile disables the connection from the task `src` to the task `dst`.
+    #[allow(dead_code)]
+    pub fn disables_cnx(&self, src: &str, dst: &str) -> bool {
+        self.disabled_cnx
+            .iter()
+            .any(|cnx| cnx.split_once("->") == Some((src, dst)))
+    }
+}
+
+/// The transport of the messages between the processes of a graph split across processes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BridgeTransport {
+    /// Iceoryx2 shared memory, for the processes of the same host.
+    #[default]
+    Iceoryx2,
+    /// Zenoh, for the processes spread over several hosts.
+    Zenoh,
+}
+
+impl BridgeTransport {
+    /// The sink and source tasks carrying the messages of this type, and the config key of the
+    /// topic they share.
+    #[allow(dead_code)]
+    fn bridge_tasks(&self, msg_type: &str) -> (String, String, &'static str) {
+        match self {
+            BridgeTransport::Iceoryx2 => (
+                format!("cu_iceoryx2_sink::IceoryxSink<{msg_type}>"),
+                format!("cu_iceoryx2_src::IceoryxSrc<{msg_type}>"),
+                "service",
+            ),
+            BridgeTransport::Zenoh => (
+                format!("cu_zenoh_sink::ZenohSink<{msg_type}>"),
+                format!("cu_zenoh_src::ZenohSrc<{msg_type}>"),
+                "topic",
+            ),
+        }
+    }
+}
+
+/// How the connections between the processes are bridged when the tasks are assigned to
+/// processes, see [CuConfig::partition]:
+///
+/// ```ron
+/// bridges: (transport: Zenoh, config: {"zenoh_config_file": "zenoh.json5"}),
+/// tasks: [
+///     (id: "camera", type: "tasks::Camera", process: "perception"),
+///     (id: "planner", type: "tasks::Planner", process: "control"),
+/// ],
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BridgesConfig {
+    #[serde(default)]
+    pub transport: BridgeTransport,
+    /// Given to all the bridge tasks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ComponentConfig>,
+}
+
+/// A service a task provides to another task of the same process, called synchronously from its
+/// process(), see `cu29::service`:
+///
+/// ```ron
+/// services: [(provider: "map", client: "planner", request: "payloads::Pose", response: "payloads::Cell")],
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ServiceConfig {
+    pub provider: String,
+    pub client: String,
+    pub request: String,
+    pub response: String,
+}
+
+/// The severity of the alarm raised on the violation of an invariant, mirrors
+/// `cu29::alarms::AlarmSeverity` to keep the configuration free of the runtime.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantSeverity {
+    Info,
+    #[default]
+    Warning,
+    Error,
+    Critical,
+}
+
+/// A property of the messages of a connection the runtime checks at every copper list. A
+/// violation raises an alarm from the source "invariants" with the index of the invariant as code,
+/// cleared once the property holds again, see `cu29::invariants`:
+///
+/// ```ron
+/// invariants: [
+///     (cnx: "imu->ekf", min_rate_hz: 180.0),
+///     (cnx: "planner->base", field: "linear", max: 1.5, severity: Error),
+/// ],
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InvariantConfig {
+    /// The connection as "src->dst", the checks apply to the output of src.
+    pub cnx: String,
+    /// The messages are sent at least this often, measured over every second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rate_hz: Option<f64>,
+    /// The path in the payload of the value checked against min and max, as "linear.x", the whole
+    /// payload if there is none. A value holding several numbers, a vector, is checked by its norm.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub severity: InvariantSeverity,
+}
+
+impl InvariantConfig {
+    /// The tasks at both ends of the connection.
+    pub fn endpoints(&self) -> Option<(&str, &str)> {
+        self.cnx
+            .split_once("->")
+            .map(|(src, dst)| (src.trim(), dst.trim()))
+    }
 }
 
 /// Missions are used to generate alternative DAGs within the same configuration.
@@ -704,6 +1504,66 @@ pub struct IncludesConfig {
     pub missions: Option<Vec<String>>,
 }
 
+/// A group of tasks and connections stamped out `instances` times, for example for the identical
+/// wheels of a robot. In every string of the group, `{i}` is replaced by the index of the instance
+/// (from 0):
+///
+/// ```ron
+/// templates: [(
+///     instances: 4,
+///     tasks: [(id: "encoder_{i}", type: "tasks::Encoder", config: {"channel": "{i}"})],
+///     cnx: [(src: "encoder_{i}", dst: "odometry", msg: "Ticks", key: Some("wheel_{i}"))],
+/// )],
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TemplateConfig {
+    pub instances: usize,
+    #[serde(default)]
+    pub tasks: Vec<Node>,
+    #[serde(default)]
+    pub cnx: Vec<Cnx>,
+}
+
+/// Placeholder replaced by the index of the instance in the templates.
+const TEMPLATE_INDEX: &str = "{i}";
+
+impl TemplateConfig {
+    /// The tasks and connections of all the instances of the template.
+    pub fn expand(&self) -> CuResult<(Vec<Node>, Vec<Cnx>)> {
+        if self.instances > 1 {
+            if let Some(task) = self.tasks.iter().find(|t| !t.id.contains(TEMPLATE_INDEX)) {
+                return Err(CuError::from(format!(
+                    "The id of the template task \"{}\" should contain {TEMPLATE_INDEX}, its {} instances would have the same id.",
+                    task.id, self.instances
+                )));
+            }
+        }
+        let options = CuConfig::get_options();
+        let tasks = options
+            .to_string(&self.tasks)
+            .map_err(|e| CuError::new_with_cause("Invalid template tasks", e))?;
+        let cnx = options
+            .to_string(&self.cnx)
+            .map_err(|e| CuError::new_with_cause("Invalid template connections", e))?;
+        let mut all_tasks = Vec::new();
+        let mut all_cnx = Vec::new();
+        for instance in 0..self.instances {
+            let index = instance.to_string();
+            all_tasks.extend(
+                options
+                    .from_str::<Vec<Node>>(&tasks.replace(TEMPLATE_INDEX, &index))
+                    .map_err(|e| CuError::new_with_cause("Invalid template tasks", e))?,
+            );
+            all_cnx.extend(
+                options
+                    .from_str::<Vec<Cnx>>(&cnx.replace(TEMPLATE_INDEX, &index))
+                    .map_err(|e| CuError::new_with_cause("Invalid template connections", e))?,
+            );
+        }
+        Ok((all_tasks, all_cnx))
+    }
+}
+
 /// This is the main Copper configuration representation.
 #[derive(Serialize, Deserialize, Default)]
 struct CuConfigRepresentation {
@@ -711,8 +1571,18 @@ struct CuConfigRepresentation {
     cnx: Option<Vec<Cnx>>,
     monitor: Option<MonitorConfig>,
     logging: Option<LoggingConfig>,
+    runtime: Option<RuntimeConfig>,
     missions: Option<Vec<MissionsConfig>>,
     includes: Option<Vec<IncludesConfig>>,
+    templates: Option<Vec<TemplateConfig>>,
+    types: Option<HashMap<String, String>>,
+    schema_versions: Option<HashMap<String, u32>>,
+    namespace: Option<String>,
+    profiles: Option<Vec<ProfileConfig>>,
+    profile: Option<String>,
+    bridges: Option<BridgesConfig>,
+    services: Option<Vec<ServiceConfig>>,
+    invariants: Option<Vec<InvariantConfig>>,
 }
 
 impl<'de> Deserialize<'de> for CuConfig {
@@ -721,10 +1591,19 @@ impl<'de> Deserialize<'de> for CuConfig {
     where
         D: Deserializer<'de>,
     {
-        let representation =
+        let mut representation =
             CuConfigRepresentation::deserialize(deserializer).map_err(serde::de::Error::custom)?;
         let mut cuconfig = CuConfig::default();
 
+        for template in representation.templates.take().unwrap_or_default() {
+            let (tasks, cnx) = template.expand().map_err(serde::de::Error::custom)?;
+            representation
+                .tasks
+                .get_or_insert_with(Vec::new)
+                .extend(tasks);
+            representation.cnx.get_or_insert_with(Vec::new).extend(cnx);
+        }
+
         if let Some(mission_configs) = &representation.missions {
             // This is the multi-mission case
             let mut missions = Missions(HashMap::new());
@@ -782,13 +1661,14 @@ impl<'de> Deserialize<'de> for CuConfig {
                                         panic!("Destination {} node not found", c.dst)
                                     });
                                 missions
-                                    .connect_ext(
+                                    .add_cnx(
                                         src.index() as NodeId,
                                         dst.index() as NodeId,
-                                        &c.msg,
-                                        c.store,
+                                        Cnx {
+                                            missions: Some(cnx_missions.clone()),
+                                            ..c.clone()
+                                        },
                                         Some(mission_id),
-                                        Some(cnx_missions.clone()),
                                     )
                                     .map_err(serde::de::Error::custom)?;
                             }
@@ -817,13 +1697,14 @@ impl<'de> Deserialize<'de> for CuConfig {
                                 })
                                 .unwrap_or_else(|| panic!("Destination {} node not found", c.dst));
                             missions
-                                .connect_ext(
+                                .add_cnx(
                                     src.index() as NodeId,
                                     dst.index() as NodeId,
-                                    &c.msg,
-                                    c.store,
+                                    Cnx {
+                                        missions: None,
+                                        ..c.clone()
+                                    },
                                     Some(mission_id),
-                                    None,
                                 )
                                 .map_err(serde::de::Error::custom)?;
                         }
@@ -856,12 +1737,13 @@ impl<'de> Deserialize<'de> for CuConfig {
                         .find(|i| graphs.get_node(i.index() as NodeId, None).unwrap().id == c.dst)
                         .unwrap_or_else(|| panic!("Destination {} node not found", c.dst));
                     graphs
-                        .connect_ext(
+                        .add_cnx(
                             src.index() as NodeId,
                             dst.index() as NodeId,
-                            &c.msg,
-                            c.store,
-                            None,
+                            Cnx {
+                                missions: None,
+                                ..c
+                            },
                             None,
                         )
                         .map_err(serde::de::Error::custom)?;
@@ -872,6 +1754,15 @@ impl<'de> Deserialize<'de> for CuConfig {
 
         cuconfig.monitor = representation.monitor;
         cuconfig.logging = representation.logging;
+        cuconfig.runtime = representation.runtime;
+        cuconfig.types = representation.types;
+        cuconfig.schema_versions = representation.schema_versions;
+        cuconfig.namespace = representation.namespace;
+        cuconfig.profiles = representation.profiles;
+        cuconfig.profile = representation.profile;
+        cuconfig.bridges = representation.bridges;
+        cuconfig.services = representation.services;
+        cuconfig.invariants = representation.invariants;
 
         Ok(cuconfig)
     }
@@ -897,8 +1788,18 @@ impl Serialize for CuConfig {
                     cnx: Some(cnx),
                     monitor: self.monitor.clone(),
                     logging: self.logging.clone(),
+                    runtime: self.runtime.clone(),
                     missions: None,
                     includes: None,
+                    templates: None,
+                    types: self.types.clone(),
+                    schema_versions: self.schema_versions.clone(),
+                    namespace: self.namespace.clone(),
+                    profiles: self.profiles.clone(),
+                    profile: self.profile.clone(),
+                    bridges: self.bridges.clone(),
+                    services: self.services.clone(),
+                    invariants: self.invariants.clone(),
                 }
                 .serialize(serializer)
             }
@@ -937,8 +1838,18 @@ impl Serialize for CuConfig {
                     cnx: Some(cnx),
                     monitor: self.monitor.clone(),
                     logging: self.logging.clone(),
+                    runtime: self.runtime.clone(),
                     missions: Some(missions),
                     includes: None,
+                    templates: None,
+                    types: self.types.clone(),
+                    schema_versions: self.schema_versions.clone(),
+                    namespace: self.namespace.clone(),
+                    profiles: self.profiles.clone(),
+                    profile: self.profile.clone(),
+                    bridges: self.bridges.clone(),
+                    services: self.services.clone(),
+                    invariants: self.invariants.clone(),
                 }
                 .serialize(serializer)
             }
@@ -952,6 +1863,15 @@ impl Default for CuConfig {
             graphs: Simple(StableDiGraph::new()),
             monitor: None,
             logging: None,
+            runtime: None,
+            types: None,
+            schema_versions: None,
+            namespace: None,
+            profiles: None,
+            profile: None,
+            bridges: None,
+            services: None,
+            invariants: None,
         }
     }
 }
@@ -970,6 +1890,15 @@ impl CuConfig {
             graphs: Missions(HashMap::new()),
             monitor: None,
             logging: None,
+            runtime: None,
+            types: None,
+            schema_versions: None,
+            namespace: None,
+            profiles: None,
+            profile: None,
+            bridges: None,
+            services: None,
+            invariants: None,
         }
     }
 
@@ -1002,6 +1931,21 @@ impl CuConfig {
         self.graphs.get_node_output_msg_type(node_id, mission_id)
     }
 
+    /// The maximum encoded size of the output of a task, the biggest one declared on its
+    /// connections. None if none of them declares it.
+    #[allow(dead_code)]
+    pub fn get_node_output_max_size(
+        &self,
+        node_id: NodeId,
+        mission_id: Option<&str>,
+    ) -> Option<u32> {
+        let graph = self.get_graph(mission_id).ok()?;
+        graph
+            .edges_directed(node_id.into(), Outgoing)
+            .filter_map(|edge| edge.weight().max_size)
+            .max()
+    }
+
     #[allow(dead_code)] // Used in proc macro
     pub fn get_node_input_msg_type(
         &self,
@@ -1110,7 +2054,11 @@ impl CuConfig {
             };
             writeln!(output, "{} [", index.index()).unwrap();
             writeln!(output, "shape=box,").unwrap();
-            writeln!(output, "style=\"rounded, filled\",").unwrap();
+            if node.enabled {
+                writeln!(output, "style=\"rounded, filled\",").unwrap();
+            } else {
+                writeln!(output, "style=\"rounded, filled, dashed\",").unwrap();
+            }
             writeln!(output, "fontname=\"Noto Sans\"").unwrap();
 
             let is_src = self
@@ -1160,6 +2108,41 @@ impl CuConfig {
         Ok(())
     }
 
+    /// Render the configuration graph as a Mermaid flowchart, it can be embedded as is in markdown.
+    pub fn render_mermaid(
+        &self,
+        output: &mut dyn std::io::Write,
+        mission_id: Option<&str>,
+    ) -> CuResult<()> {
+        let graph = self.graphs.get_graph(mission_id)?;
+        let write_error = |e: std::io::Error| CuError::new_with_cause("Could not render", e);
+
+        writeln!(output, "flowchart LR").map_err(write_error)?;
+        for index in graph.node_indices() {
+            let node = &graph[index];
+            writeln!(
+                output,
+                "    n{}[\"<b>{}</b><br/>{}\"]",
+                index.index(),
+                encode_mermaid(&node.id),
+                encode_mermaid(node.get_type())
+            )
+            .map_err(write_error)?;
+        }
+        for edge in graph.edge_indices() {
+            let (src, dst) = graph.edge_endpoints(edge).unwrap();
+            writeln!(
+                output,
+                "    n{} -->|\"{}\"| n{}",
+                src.index(),
+                encode_mermaid(&graph[edge].msg),
+                dst.index()
+            )
+            .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn get_all_instances_configs(
         &self,
@@ -1171,9 +2154,25 @@ impl CuConfig {
             .collect()
     }
 
+    /// The configs given to the tasks: the instance configs with the namespace of the application if any.
+    /// The tasks without a config are left without one.
     #[allow(dead_code)]
-    pub fn get_graph(&self, mission_id: Option<&str>) -> CuResult<&CuGraph> {
-        self.graphs.get_graph(mission_id)
+    pub fn get_all_tasks_configs(&self, mission_id: Option<&str>) -> Vec<Option<ComponentConfig>> {
+        self.get_all_instances_configs(mission_id)
+            .into_iter()
+            .map(|config| {
+                let mut config = config.cloned()?;
+                if let Some(namespace) = &self.namespace {
+                    config.set(NAMESPACE_KEY, namespace.clone());
+                }
+                Some(config)
+            })
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_graph(&self, mission_id: Option<&str>) -> CuResult<&CuGraph> {
+        self.graphs.get_graph(mission_id)
     }
 
     #[allow(dead_code)]
@@ -1194,6 +2193,72 @@ impl CuConfig {
         }
         Ok(())
     }
+
+    /// Checks that the loop rate, if any, is a positive frequency, that the actors can queue an
+    /// input and that the chunked tasks have some time to run.
+    pub fn validate_runtime_config(&self) -> CuResult<()> {
+        for (_, node) in self.get_all_nodes(None) {
+            if node.get_actor().is_some_and(|actor| actor.queue == 0) {
+                return Err(CuError::from(format!(
+                    "The actor \"{}\" needs a queue of 1 input at least.",
+                    node.get_id()
+                )));
+            }
+            match node.get_chunked() {
+                Some(_) if node.get_actor().is_some() => {
+                    return Err(CuError::from(format!(
+                        "The task \"{}\" can't be both an actor and chunked.",
+                        node.get_id()
+                    )));
+                }
+                Some(chunked) if chunked.budget_us == 0 => {
+                    return Err(CuError::from(format!(
+                        "The chunked task \"{}\" needs a budget of 1us at least.",
+                        node.get_id()
+                    )));
+                }
+                _ => {}
+            }
+        }
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        if let Some(cnx) = graphs
+            .iter()
+            .flat_map(|graph| graph.edge_weights())
+            .find(|cnx| cnx.max_age_ms == Some(0))
+        {
+            return Err(CuError::from(format!(
+                "The connection \"{}->{}\" needs a max_age_ms of 1ms at least.",
+                cnx.src, cnx.dst
+            )));
+        }
+        if let Some(rate) = self
+            .runtime
+            .as_ref()
+            .and_then(|runtime| runtime.loop_rate_hz)
+        {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(CuError::from(format!(
+                    "The loop rate must be a positive frequency, got {rate} Hz."
+                )));
+            }
+        }
+        if let Some(clock) = self
+            .runtime
+            .as_ref()
+            .and_then(|runtime| runtime.clock.as_ref())
+        {
+            clock.source()?;
+            if clock.resync_period_ms == 0 {
+                return Err(CuError::from(
+                    "The clock needs a resync_period_ms of 1ms at least.",
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl LoggingConfig {
@@ -1206,9 +2271,517 @@ impl LoggingConfig {
                 }
             }
         }
+        if let (Some(max_size_mib), Some(slab_size_mib)) = (self.max_size_mib, self.slab_size_mib) {
+            if max_size_mib < 2 * slab_size_mib {
+                return Err(CuError::from(format!("Max size of the log ({max_size_mib} MiB) cannot hold 2 slabs of {slab_size_mib} MiB. Adjust the parameters accordingly.")));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Escapes the characters Mermaid would interpret in a quoted label.
+fn encode_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+/// Maximum alias indirections followed before considering that the type table loops on itself.
+const MAX_TYPE_ALIAS_DEPTH: usize = 16;
+
+impl CuConfig {
+    /// Resolves the message type aliases declared in the `types` table of the configuration.
+    /// Every standalone identifier of the message type matching an alias is replaced by its
+    /// definition (so `Image` and `Vec<Image>` both resolve), aliases can refer to other aliases.
+    /// Identifiers that are part of a path (like `cu_image::Image`) are never substituted.
+    pub fn resolve_msg_type(&self, msg_type: &str) -> CuResult<String> {
+        let Some(types) = &self.types else {
+            return Ok(msg_type.to_string());
+        };
+        let mut resolved = msg_type.to_string();
+        for _ in 0..MAX_TYPE_ALIAS_DEPTH {
+            let (substituted, changed) = substitute_type_aliases(&resolved, types);
+            if !changed {
+                return Ok(resolved);
+            }
+            resolved = substituted;
+        }
+        Err(CuError::from(format!(
+            "Message type \"{msg_type}\" could not be resolved: the type aliases are recursive (stopped at \"{resolved}\")."
+        )))
+    }
+
+    /// Validates the type alias table and checks that every task emits a single message type
+    /// once all the aliases are resolved.
+    pub fn validate_types(&self) -> CuResult<()> {
+        if let Some(types) = &self.types {
+            for (alias, definition) in types {
+                let mut chars = alias.chars();
+                let valid_ident = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                    && chars.all(|c| c.is_alphanumeric() || c == '_');
+                if !valid_ident {
+                    return Err(CuError::from(format!(
+                        "Invalid type alias \"{alias}\": an alias must be a plain identifier like \"Image\"."
+                    )));
+                }
+                if definition.trim().is_empty() {
+                    return Err(CuError::from(format!(
+                        "Type alias \"{alias}\" has an empty definition."
+                    )));
+                }
+            }
+        }
+
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        for graph in graphs {
+            for node_idx in graph.node_indices() {
+                let node = &graph[node_idx];
+                let mut emitted: Option<(&str, String)> = None;
+                for edge in graph.edges_directed(node_idx, Outgoing) {
+                    let msg = edge.weight().msg.as_str();
+                    let resolved = self.resolve_msg_type(msg).map_err(|e| {
+                        CuError::new_with_cause(
+                            &format!(
+                                "Invalid message type on connection {} -> {}",
+                                node.id,
+                                edge.weight().dst
+                            ),
+                            e,
+                        )
+                    })?;
+                    match &emitted {
+                        Some((first_msg, first_resolved)) if *first_resolved != resolved => {
+                            return Err(CuError::from(format!(
+                                "Task \"{}\" emits mismatching message types on its connections: \"{first_msg}\" (resolved as \"{first_resolved}\") and \"{msg}\" (resolved as \"{resolved}\").",
+                                node.id
+                            )));
+                        }
+                        Some(_) => {}
+                        None => emitted = Some((msg, resolved)),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no task mixes the clock domains of the timestamps: all the inputs of a task and
+    /// all its outputs are each in a single domain. The conversion from a domain to another is
+    /// done by a task taking the messages of one domain and emitting them in the other.
+    pub fn validate_clock_domains(&self) -> CuResult<()> {
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        for graph in graphs {
+            for node_idx in graph.node_indices() {
+                let node = &graph[node_idx];
+                for (direction, what) in [(Incoming, "takes inputs"), (Outgoing, "emits")] {
+                    // In the order of the connections in the configuration.
+                    let mut edges: Vec<_> = graph.edges_directed(node_idx, direction).collect();
+                    edges.sort_by_key(|edge| edge.id());
+                    let mut domains = edges.iter().map(|edge| edge.weight().clock_domain());
+                    let Some(first) = domains.next() else {
+                        continue;
+                    };
+                    if let Some(other) = domains.find(|domain| *domain != first) {
+                        return Err(CuError::from(format!(
+                            "Task \"{}\" {what} in different clock domains: \"{first}\" and \"{other}\". Convert the timestamps to a single domain in a task upstream.",
+                            node.id
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Admits the resource budgets of the tasks: the values are positive, the tasks of a graph
+    /// don't need more than the whole loop together and a sink has no output to budget.
+    pub fn validate_budgets(&self) -> CuResult<()> {
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        for graph in graphs {
+            let mut cpu_percent = 0.0;
+            for node_idx in graph.node_indices() {
+                let node = &graph[node_idx];
+                let Some(budget) = &node.budget else {
+                    continue;
+                };
+                if let Some(cpu) = budget.cpu_percent {
+                    if !(cpu.is_finite() && cpu > 0.0 && cpu <= 100.0) {
+                        return Err(CuError::from(format!(
+                            "Task \"{}\": the cpu budget must be a percentage in ]0, 100], got {cpu}.",
+                            node.id
+                        )));
+                    }
+                    cpu_percent += cpu;
+                }
+                if budget.memory_bytes == Some(0) || budget.bandwidth_bytes_per_s == Some(0) {
+                    return Err(CuError::from(format!(
+                        "Task \"{}\": a budget of 0 bytes can never be met.",
+                        node.id
+                    )));
+                }
+                let is_sink = graph.edges_directed(node_idx, Incoming).next().is_some()
+                    && graph.edges_directed(node_idx, Outgoing).next().is_none();
+                if is_sink && budget.bandwidth_bytes_per_s.is_some() {
+                    return Err(CuError::from(format!(
+                        "Task \"{}\" is a sink, it has no output to budget the bandwidth of.",
+                        node.id
+                    )));
+                }
+            }
+            if cpu_percent > 100.0 {
+                return Err(CuError::from(format!(
+                    "The tasks of the graph need {cpu_percent}% of the copper loop together, it can give them 100% at most."
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The profile the application runs with, None if it does not select any.
+    pub fn active_profile(&self) -> CuResult<Option<&ProfileConfig>> {
+        let Some(id) = &self.profile else {
+            return Ok(None);
+        };
+        let profiles = self.profiles.as_deref().unwrap_or_default();
+        match profiles.iter().find(|profile| &profile.id == id) {
+            Some(profile) => Ok(Some(profile)),
+            None => Err(CuError::from(format!(
+                "Unknown profile \"{id}\", the profiles are: {}.",
+                profiles
+                    .iter()
+                    .map(|profile| format!("\"{}\"", profile.id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Checks that the profiles have distinct ids, that they only disable tasks and connections
+    /// of the graph and that the selected profile exists.
+    pub fn validate_profiles(&self) -> CuResult<()> {
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        let profiles = self.profiles.as_deref().unwrap_or_default();
+        for (index, profile) in profiles.iter().enumerate() {
+            if profiles[..index].iter().any(|other| other.id == profile.id) {
+                return Err(CuError::from(format!(
+                    "The profile \"{}\" is declared twice.",
+                    profile.id
+                )));
+            }
+            for task in &profile.disabled_tasks {
+                if !graphs
+                    .iter()
+                    .any(|graph| graph.node_weights().any(|node| &node.id == task))
+                {
+                    return Err(CuError::from(format!(
+                        "The profile \"{}\" disables the task \"{task}\" which is not in the graph.",
+                        profile.id
+                    )));
+                }
+            }
+            for cnx in &profile.disabled_cnx {
+                let exists = cnx.split_once("->").is_some_and(|(src, dst)| {
+                    graphs.iter().any(|graph| {
+                        graph
+                            .edge_weights()
+                            .any(|edge| edge.src == src && edge.dst == dst)
+                    })
+                });
+                if !exists {
+                    return Err(CuError::from(format!(
+                        "The profile \"{}\" disables the connection \"{cnx}\" which is not in the graph, connections are given as \"src->dst\".",
+                        profile.id
+                    )));
+                }
+            }
+        }
+        self.active_profile()?;
+        Ok(())
+    }
+
+    /// Checks that the services are provided to another task of the same process, by a task
+    /// that is enabled.
+    pub fn validate_services(&self) -> CuResult<()> {
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        let find = |id: &str| {
+            graphs
+                .iter()
+                .find_map(|graph| graph.node_weights().find(|node| node.id == id))
+        };
+        for service in self.services.as_deref().unwrap_or_default() {
+            let (Some(provider), Some(client)) = (find(&service.provider), find(&service.client))
+            else {
+                return Err(CuError::from(format!(
+                    "The service from \"{}\" to \"{}\" refers to a task which is not in the graph.",
+                    service.provider, service.client
+                )));
+            };
+            if provider.id == client.id {
+                return Err(CuError::from(format!(
+                    "The task \"{}\" can't provide a service to itself.",
+                    provider.id
+                )));
+            }
+            if provider.process != client.process {
+                return Err(CuError::from(format!(
+                    "The task \"{}\" can't provide a service to \"{}\" which runs in another process.",
+                    provider.id, client.id
+                )));
+            }
+            // The state of an actor lives on its own thread, a chunked task is behind its wrapper.
+            if let Some(actor) = [provider, client]
+                .iter()
+                .find(|node| node.actor.is_some() || node.chunked.is_some())
+            {
+                return Err(CuError::from(format!(
+                    "The task \"{}\" runs as an actor or in chunks, it can neither provide nor call a service.",
+                    actor.id
+                )));
+            }
+            if !provider.is_enabled() {
+                return Err(CuError::from(format!(
+                    "The task \"{}\" provides a service but is disabled.",
+                    provider.id
+                )));
+            }
+        }
+        Ok(())
+    }
 
+    /// Checks that the invariants are on connections of the graph and check something.
+    pub fn validate_invariants(&self) -> CuResult<()> {
+        let graphs: Vec<&CuGraph> = match &self.graphs {
+            Simple(graph) => vec![graph],
+            Missions(graphs) => graphs.values().collect(),
+        };
+        for invariant in self.invariants.as_deref().unwrap_or_default() {
+            let connected = invariant.endpoints().is_some_and(|(src, dst)| {
+                graphs.iter().any(|graph| {
+                    graph.edge_references().any(|edge| {
+                        graph[edge.source()].id == src && graph[edge.target()].id == dst
+                    })
+                })
+            });
+            if !connected {
+                return Err(CuError::from(format!(
+                    "The invariant on \"{}\" is not on a connection of the graph, expected \"src->dst\".",
+                    invariant.cnx
+                )));
+            }
+            if invariant.min_rate_hz.is_none() && invariant.min.is_none() && invariant.max.is_none()
+            {
+                return Err(CuError::from(format!(
+                    "The invariant on \"{}\" checks nothing, give it a min_rate_hz, a min or a max.",
+                    invariant.cnx
+                )));
+            }
+            if invariant
+                .min_rate_hz
+                .is_some_and(|rate| !(rate.is_finite() && rate > 0.0))
+            {
+                return Err(CuError::from(format!(
+                    "The invariant on \"{}\" needs a positive min_rate_hz.",
+                    invariant.cnx
+                )));
+            }
+            if let (Some(min), Some(max)) = (invariant.min, invariant.max) {
+                if min > max {
+                    return Err(CuError::from(format!(
+                        "The invariant on \"{}\" has a min above its max.",
+                        invariant.cnx
+                    )));
+                }
+            }
+        }
         Ok(())
     }
+
+    /// The services called by the task `client`, in the order of the configuration.
+    #[allow(dead_code)]
+    pub fn get_services_of(&self, client: &str) -> Vec<&ServiceConfig> {
+        self.services
+            .iter()
+            .flatten()
+            .filter(|service| service.client == client)
+            .collect()
+    }
+
+    /// The version of a message type declared in the `schema_versions` table, 0 if it is not
+    /// declared. The keys of the table can use the type aliases.
+    #[allow(dead_code)]
+    pub fn schema_version(&self, msg_type: &str) -> CuResult<u32> {
+        let Some(versions) = &self.schema_versions else {
+            return Ok(0);
+        };
+        let resolved = self.resolve_msg_type(msg_type)?;
+        for (declared, version) in versions {
+            if self.resolve_msg_type(declared)? == resolved {
+                return Ok(*version);
+            }
+        }
+        Ok(0)
+    }
+
+    /// The configuration of the application running the tasks of `process` when the tasks are
+    /// assigned to processes: its tasks, with the connections to and from the other processes
+    /// replaced by bridges of the transport of `bridges`. A sink `<src>_bridge` publishes an
+    /// output needed by other processes on the topic `copper/<src>`, a source with the id of the
+    /// remote task `<src>` gives it to the tasks of this process.
+    /// The configuration is unchanged if no task is assigned to a process.
+    #[allow(dead_code)]
+    pub fn partition(&self, process: &str) -> CuResult<CuConfig> {
+        let Simple(graph) = &self.graphs else {
+            return Err("The processes are not supported with missions.".into());
+        };
+        if graph.node_weights().all(|node| node.process.is_none()) {
+            return Ok(self.clone());
+        }
+        if let Some(node) = graph.node_weights().find(|node| node.process.is_none()) {
+            return Err(CuError::from(format!(
+                "The task \"{}\" is not assigned to a process, all the tasks need one when some have.",
+                node.id
+            )));
+        }
+        let in_process = |node: &Node| node.process.as_deref() == Some(process);
+        if !graph.node_weights().any(in_process) {
+            return Err(CuError::from(format!(
+                "No task is assigned to the process \"{process}\"."
+            )));
+        }
+        let bridges = self.bridges.clone().unwrap_or_default();
+
+        let mut partitioned = CuConfig {
+            graphs: Simple(CuGraph::default()),
+            ..self.clone()
+        };
+        // A service never crosses processes, see validate_services.
+        partitioned.services = self.services.as_ref().map(|services| {
+            services
+                .iter()
+                .filter(|service| {
+                    graph
+                        .node_weights()
+                        .any(|node| node.id == service.client && in_process(node))
+                })
+                .cloned()
+                .collect()
+        });
+        // The outputs are checked in the process of their task.
+        partitioned.invariants = self.invariants.as_ref().map(|invariants| {
+            invariants
+                .iter()
+                .filter(|invariant| {
+                    graph.node_weights().any(|node| {
+                        invariant.endpoints().is_some_and(|(src, _)| src == node.id)
+                            && in_process(node)
+                    })
+                })
+                .cloned()
+                .collect()
+        });
+        let mut ids: HashMap<String, NodeId> = HashMap::new();
+        for node in graph.node_weights().filter(|node| in_process(node)) {
+            ids.insert(node.id.clone(), partitioned.add_node(node.clone(), None)?);
+        }
+        for edge in graph.edge_references() {
+            let (src, dst) = (&graph[edge.source()], &graph[edge.target()]);
+            let cnx = edge.weight();
+            let (sink_type, src_type, topic_key) = bridges
+                .transport
+                .bridge_tasks(&self.resolve_msg_type(&cnx.msg)?);
+            let max_size = self.get_node_output_max_size(edge.source().index() as NodeId, None);
+            let mut bridge = |id: &str, task_type: &str| {
+                let mut node = Node::new(id, task_type);
+                let mut config = bridges.config.clone().unwrap_or_default();
+                config.set(topic_key, format!("copper/{}", src.id));
+                // Sizes the buffers of the transport.
+                if let Some(max_size) = max_size {
+                    config.set("max_size", max_size);
+                }
+                node.config = Some(config);
+                node.process = Some(process.to_string());
+                partitioned.add_node(node, None)
+            };
+            match (in_process(src), in_process(dst)) {
+                (true, true) => {
+                    partitioned
+                        .graphs
+                        .add_cnx(ids[&src.id], ids[&dst.id], cnx.clone(), None)?;
+                }
+                // Published once for all the tasks of the other processes.
+                (true, false) => {
+                    if let Entry::Vacant(entry) = ids.entry(format!("{}_bridge", src.id)) {
+                        let sink = bridge(entry.key(), &sink_type)?;
+                        let bridged = Cnx {
+                            dst: entry.key().clone(),
+                            policy: None,
+                            key: None,
+                            max_size,
+                            ..cnx.clone()
+                        };
+                        entry.insert(sink);
+                        partitioned
+                            .graphs
+                            .add_cnx(ids[&src.id], sink, bridged, None)?;
+                    }
+                }
+                (false, true) => {
+                    if let Entry::Vacant(entry) = ids.entry(src.id.clone()) {
+                        entry.insert(bridge(&src.id, &src_type)?);
+                    }
+                    partitioned
+                        .graphs
+                        .add_cnx(ids[&src.id], ids[&dst.id], cnx.clone(), None)?;
+                }
+                (false, false) => {}
+            }
+        }
+        Ok(partitioned)
+    }
+}
+
+/// Replaces in one pass the standalone identifiers of `msg_type` found in `types`.
+fn substitute_type_aliases(msg_type: &str, types: &HashMap<String, String>) -> (String, bool) {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(msg_type.len());
+    let mut changed = false;
+    let mut rest = msg_type;
+    while let Some(start) = rest.find(is_ident_char) {
+        let (before, from_ident) = rest.split_at(start);
+        result.push_str(before);
+        let end = from_ident
+            .find(|c: char| !is_ident_char(c))
+            .unwrap_or(from_ident.len());
+        let (ident, after) = from_ident.split_at(end);
+        let in_path = result.trim_end().ends_with("::") || after.trim_start().starts_with("::");
+        match types.get(ident) {
+            Some(definition) if !in_path => {
+                result.push_str(definition.trim());
+                changed = true;
+            }
+            _ => result.push_str(ident),
+        }
+        rest = after;
+    }
+    result.push_str(rest);
+    (result, changed)
 }
 
 /// Read a copper configuration from a file.
@@ -1227,6 +2800,13 @@ pub fn read_configuration(config_filename: &str) -> CuResult<CuConfig> {
 pub fn read_configuration_str(config_content: String) -> CuResult<CuConfig> {
     let cuconfig = CuConfig::deserialize_ron(&config_content);
     cuconfig.validate_logging_config()?;
+    cuconfig.validate_runtime_config()?;
+    cuconfig.validate_types()?;
+    cuconfig.validate_clock_domains()?;
+    cuconfig.validate_budgets()?;
+    cuconfig.validate_profiles()?;
+    cuconfig.validate_services()?;
+    cuconfig.validate_invariants()?;
 
     Ok(cuconfig)
 }
@@ -1325,6 +2905,448 @@ mod tests {
         assert_eq!(logging_config.slab_size_mib.unwrap(), 1024);
         assert_eq!(logging_config.section_size_mib.unwrap(), 100);
         assert!(logging_config.enable_task_logging);
+        assert!(logging_config.upload.is_none());
+
+        let txt = r#"( tasks: [], cnx: [], logging: ( upload: ( url: "file:///mnt/logs", bandwidth_kib_s: 512 ) ),) "#;
+        let config = CuConfig::deserialize_ron(txt);
+        let upload = config.logging.unwrap().upload.unwrap();
+        assert_eq!(upload.url, "file:///mnt/logs");
+        assert_eq!(upload.bandwidth_kib_s, Some(512));
+        assert!(upload.delete_after_upload);
+    }
+
+    #[test]
+    fn test_runtime_config() {
+        let txt =
+            r#"( tasks: [], cnx: [], runtime: ( loop_rate_hz: 100.0, overrun_policy: Skip ) )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let runtime = config.runtime.as_ref().unwrap();
+        assert_eq!(runtime.loop_rate_hz, Some(100.0));
+        assert_eq!(runtime.overrun_policy, OverrunPolicy::Skip);
+        assert_eq!(runtime.idle_strategy, IdleStrategy::Sleep);
+        assert!(!runtime.pipelined);
+
+        let txt =
+            r#"( tasks: [], cnx: [], runtime: ( loop_rate_hz: 1000.0, idle_strategy: Spin ) )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        assert_eq!(
+            config.runtime.as_ref().unwrap().idle_strategy,
+            IdleStrategy::Spin
+        );
+
+        let txt = r#"( tasks: [], cnx: [], runtime: ( loop_rate_hz: 0.0 ) )"#;
+        assert!(read_configuration_str(txt.to_string()).is_err());
+
+        let txt = r#"( tasks: [], cnx: [], runtime: ( pipelined: true ) )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        assert!(config.runtime.as_ref().unwrap().pipelined);
+        assert_eq!(config.runtime.as_ref().unwrap().clock, None);
+    }
+
+    #[test]
+    fn test_clock_config() {
+        let txt = r#"( tasks: [], cnx: [], runtime: ( clock: ( source: "/dev/ptp0" ) ) )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let clock = config.runtime.as_ref().unwrap().clock.as_ref().unwrap();
+        assert_eq!(
+            clock.source().unwrap(),
+            ClockSource::Phc("/dev/ptp0".into())
+        );
+        assert_eq!(clock.resync_period_ms, 1000);
+
+        let err = read_configuration_str(txt.replace("/dev/ptp0", "gps")).unwrap_err();
+        assert!(err.to_string().contains("Unknown clock source \"gps\""));
+        let txt =
+            r#"( tasks: [], cnx: [], runtime: ( clock: ( source: "tai", resync_period_ms: 0 ) ) )"#;
+        assert!(read_configuration_str(txt.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_budgets() {
+        let txt = r#"(
+            tasks: [
+                (id: "lidar", type: "Lidar", budget: (cpu_percent: 20.0, bandwidth_bytes_per_s: 1000000)),
+                (id: "motors", type: "Motors", budget: (memory_bytes: 64)),
+            ],
+            cnx: [(src: "lidar", dst: "motors", msg: "Scan")],
+            runtime: (budget_checks: Continuous),
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let (_, lidar) = config.get_all_nodes(None)[0];
+        let budget = lidar.get_budget().unwrap();
+        assert_eq!(budget.cpu_percent, Some(20.0));
+        assert_eq!(budget.bandwidth_bytes_per_s, Some(1_000_000));
+        assert_eq!(budget.memory_bytes, None);
+        assert_eq!(
+            config.runtime.as_ref().unwrap().budget_checks,
+            BudgetChecks::Continuous
+        );
+        let reread = CuConfig::deserialize_ron(&config.serialize_ron());
+        assert_eq!(reread.get_all_nodes(None)[0].1.get_budget(), Some(budget));
+
+        // Over the whole loop together.
+        let txt = txt
+            .replace("cpu_percent: 20.0", "cpu_percent: 60.0")
+            .replace(
+                "(memory_bytes: 64)",
+                "(memory_bytes: 64, cpu_percent: 50.0)",
+            );
+        assert!(read_configuration_str(txt).is_err());
+        // A sink has no output.
+        let txt = r#"(
+            tasks: [
+                (id: "lidar", type: "Lidar"),
+                (id: "motors", type: "Motors", budget: (bandwidth_bytes_per_s: 10)),
+            ],
+            cnx: [(src: "lidar", dst: "motors", msg: "Scan")],
+        )"#;
+        assert!(read_configuration_str(txt.to_string()).is_err());
+        // Never met.
+        let txt = txt.replace("bandwidth_bytes_per_s: 10", "memory_bytes: 0");
+        assert!(read_configuration_str(txt).is_err());
+    }
+
+    #[test]
+    fn test_unconnected_inputs() {
+        let txt = r#"(
+            tasks: [
+                (id: "imu", type: "Imu"),
+                (id: "pid", type: "Pid", unconnected_inputs: [1]),
+            ],
+            cnx: [(src: "imu", dst: "pid", msg: "f32")],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let node = |config: &CuConfig, id| config.get_node(id, None).unwrap().clone();
+        assert!(node(&config, 0).get_unconnected_inputs().is_empty());
+        assert_eq!(node(&config, 1).get_unconnected_inputs(), &[1]);
+        // It survives a round trip.
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        assert_eq!(node(&config, 1).get_unconnected_inputs(), &[1]);
+    }
+
+    #[test]
+    fn test_cnx_policy() {
+        let txt = r#"(
+            tasks: [(id: "lidar", type: "Lidar"), (id: "slam", type: "Slam"), (id: "disp", type: "Display")],
+            cnx: [
+                (src: "lidar", dst: "slam", msg: "Scan"),
+                (src: "lidar", dst: "disp", msg: "Scan", policy: Some(Decimate(10))),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let policies: Vec<Option<CnxPolicy>> = config
+            .get_graph(None)
+            .unwrap()
+            .edge_weights()
+            .map(|cnx| cnx.policy)
+            .collect();
+        assert_eq!(policies, vec![None, Some(CnxPolicy::Decimate(10))]);
+        // The policy survives a round trip.
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let graph = config.get_graph(None).unwrap();
+        assert_eq!(
+            graph.edge_weights().nth(1).unwrap().policy,
+            Some(CnxPolicy::Decimate(10))
+        );
+    }
+
+    #[test]
+    fn test_cnx_keys() {
+        let txt = r#"(
+            tasks: [(id: "fl", type: "Encoder"), (id: "fr", type: "Encoder"), (id: "odom", type: "Odometry")],
+            cnx: [
+                (src: "fl", dst: "odom", msg: "Ticks", key: Some("front_left")),
+                (src: "fr", dst: "odom", msg: "Ticks", key: Some("front_right")),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let keys: Vec<Option<&str>> = config
+            .get_graph(None)
+            .unwrap()
+            .edge_weights()
+            .map(|cnx| cnx.key.as_deref())
+            .collect();
+        assert_eq!(keys, vec![Some("front_left"), Some("front_right")]);
+    }
+
+    #[test]
+    fn test_clock_domains() {
+        let txt = r#"(
+            tasks: [(id: "lidar", type: "Lidar"), (id: "mcu", type: "Imu"), (id: "sync", type: "Sync"), (id: "fusion", type: "Fusion")],
+            cnx: [
+                (src: "lidar", dst: "sync", msg: "Scan", domain: Some("gps")),
+                (src: "sync", dst: "fusion", msg: "Scan"),
+                (src: "mcu", dst: "fusion", msg: "Imu"),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let domains: Vec<&str> = config
+            .get_graph(None)
+            .unwrap()
+            .edge_weights()
+            .map(Cnx::clock_domain)
+            .collect();
+        assert_eq!(domains, vec!["gps", "robot", "robot"]);
+
+        // The lidar scans fused with the imu without going through the conversion.
+        let txt = r#"(
+            tasks: [(id: "lidar", type: "Lidar"), (id: "mcu", type: "Imu"), (id: "fusion", type: "Fusion")],
+            cnx: [
+                (src: "lidar", dst: "fusion", msg: "Scan", domain: Some("gps")),
+                (src: "mcu", dst: "fusion", msg: "Imu", domain: Some("mcu")),
+            ],
+        )"#;
+        let err = read_configuration_str(txt.to_string()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("\"fusion\" takes inputs in different clock domains: \"gps\" and \"mcu\""));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let txt = r#"(
+            tasks: [(id: "camera", type: "Camera"), (id: "lidar", type: "Lidar"), (id: "planner", type: "Planner")],
+            cnx: [
+                (src: "camera", dst: "planner", msg: "Image"),
+                (src: "lidar", dst: "planner", msg: "Scan"),
+            ],
+            profiles: [
+                (id: "full"),
+                (id: "no_camera", disabled_tasks: ["camera"], disabled_cnx: ["camera->planner"]),
+            ],
+            profile: "no_camera",
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let profile = config.active_profile().unwrap().unwrap();
+        assert_eq!(profile.disabled_tasks, vec!["camera"]);
+        assert!(profile.disables_cnx("camera", "planner"));
+        assert!(!profile.disables_cnx("lidar", "planner"));
+
+        let mut config = config;
+        config.profile = Some("bench".to_string());
+        let err = config.validate_profiles().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown profile \"bench\", the profiles are: \"full\", \"no_camera\"."));
+
+        let txt = txt.replace("camera->planner", "camera->lidar");
+        let err = read_configuration_str(txt).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("disables the connection \"camera->lidar\" which is not in the graph"));
+    }
+
+    #[test]
+    fn test_services() {
+        let txt = r#"(
+            tasks: [(id: "lidar", type: "Lidar"), (id: "map", type: "Map"), (id: "planner", type: "Planner")],
+            cnx: [
+                (src: "lidar", dst: "map", msg: "Scan"),
+                (src: "lidar", dst: "planner", msg: "Scan"),
+            ],
+            services: [(provider: "map", client: "planner", request: "Pose", response: "Cell")],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let services = config.get_services_of("planner");
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].provider, "map");
+        assert_eq!(services[0].response, "Cell");
+        assert!(config.get_services_of("map").is_empty());
+
+        let err = read_configuration_str(txt.replace("provider: \"map\"", "provider: \"planner\""))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can't provide a service to itself"));
+
+        let txt = txt.replace(
+            "(id: \"map\", type: \"Map\")",
+            "(id: \"map\", type: \"Map\", enabled: false)",
+        );
+        let err = read_configuration_str(txt).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The task \"map\" provides a service but is disabled."));
+    }
+
+    #[test]
+    fn test_invariants() {
+        let txt = r#"(
+            tasks: [
+                (id: "imu", type: "Imu"),
+                (id: "ekf", type: "Ekf"),
+            ],
+            cnx: [(src: "imu", dst: "ekf", msg: "Imu")],
+            invariants: [
+                (cnx: "imu->ekf", min_rate_hz: 180.0),
+                (cnx: "imu -> ekf", field: "accel", max: 40.0, severity: Error),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let invariants = config.invariants.as_ref().unwrap();
+        assert_eq!(invariants[0].severity, InvariantSeverity::Warning);
+        assert_eq!(invariants[1].endpoints(), Some(("imu", "ekf")));
+        assert_eq!(invariants[1].field.as_deref(), Some("accel"));
+        assert_eq!(invariants[1].severity, InvariantSeverity::Error);
+
+        let err = read_configuration_str(txt.replace("\"imu->ekf\"", "\"ekf->imu\"")).unwrap_err();
+        assert!(err.to_string().contains("not on a connection of the graph"));
+        let err = read_configuration_str(txt.replace("min_rate_hz: 180.0", "min_rate_hz: 0.0"))
+            .unwrap_err();
+        assert!(err.to_string().contains("positive min_rate_hz"));
+        let err = read_configuration_str(txt.replace(", max: 40.0", ", min: 50.0, max: 40.0"))
+            .unwrap_err();
+        assert!(err.to_string().contains("min above its max"));
+        let err = read_configuration_str(txt.replace(", max: 40.0", "")).unwrap_err();
+        assert!(err.to_string().contains("checks nothing"));
+    }
+
+    #[test]
+    fn test_actor() {
+        let txt = r#"(
+            tasks: [
+                (id: "camera", type: "Camera"),
+                (id: "detector", type: "Detector", actor: (queue: 2, max_age_ms: 200)),
+                (id: "tracker", type: "Tracker", actor: ()),
+            ],
+            cnx: [
+                (src: "camera", dst: "detector", msg: "Image"),
+                (src: "detector", dst: "tracker", msg: "Detections"),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let actors: Vec<Option<ActorConfig>> = config
+            .get_all_nodes(None)
+            .iter()
+            .map(|(_, node)| node.get_actor().copied())
+            .collect();
+        assert_eq!(
+            actors,
+            vec![
+                None,
+                Some(ActorConfig {
+                    queue: 2,
+                    max_age_ms: Some(200)
+                }),
+                Some(ActorConfig {
+                    queue: 1,
+                    max_age_ms: None
+                }),
+            ]
+        );
+
+        let err = read_configuration_str(txt.replace("queue: 2", "queue: 0")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The actor \"detector\" needs a queue of 1 input at least."));
+    }
+
+    #[test]
+    fn test_chunked() {
+        let txt = r#"(
+            tasks: [
+                (id: "map", type: "Map"),
+                (id: "planner", type: "Planner", chunked: (budget_us: 2000)),
+                (id: "mapper", type: "Mapper", chunked: ()),
+            ],
+            cnx: [
+                (src: "map", dst: "planner", msg: "Map"),
+                (src: "planner", dst: "mapper", msg: "Path"),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let chunked: Vec<Option<u64>> = config
+            .get_all_nodes(None)
+            .iter()
+            .map(|(_, node)| node.get_chunked().map(|chunked| chunked.budget_us))
+            .collect();
+        assert_eq!(chunked, vec![None, Some(2000), Some(1000)]);
+
+        let err = read_configuration_str(txt.replace("2000", "0")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The chunked task \"planner\" needs a budget of 1us at least."));
+        let err = read_configuration_str(txt.replace("chunked: ()", "chunked: (), actor: ()"))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The task \"mapper\" can't be both an actor and chunked."));
+    }
+
+    #[test]
+    fn test_max_age() {
+        let txt = r#"(
+            tasks: [(id: "imu", type: "Imu"), (id: "ekf", type: "Ekf")],
+            cnx: [(src: "imu", dst: "ekf", msg: "Imu", policy: Some(Latched), max_age_ms: 20)],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let config = CuConfig::deserialize_ron(&config.serialize_ron());
+        let graph = config.get_graph(None).unwrap();
+        assert_eq!(graph.edge_weights().next().unwrap().max_age_ms, Some(20));
+
+        let err = read_configuration_str(txt.replace("20", "0")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The connection \"imu->ekf\" needs a max_age_ms of 1ms at least."));
+    }
+
+    #[test]
+    fn test_disabled_node() {
+        let txt = r#"(
+            tasks: [(id: "camera", type: "Camera", enabled: false), (id: "planner", type: "Planner")],
+            cnx: [(src: "camera", dst: "planner", msg: "Image")],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let serialized = config.serialize_ron();
+        assert_eq!(serialized.matches("enabled").count(), 1);
+        let config = CuConfig::deserialize_ron(&serialized);
+        let enabled: Vec<bool> = config
+            .get_all_nodes(None)
+            .iter()
+            .map(|(_, node)| node.is_enabled())
+            .collect();
+        assert_eq!(enabled, vec![false, true]);
+    }
+
+    #[test]
+    fn test_templates() {
+        let txt = r#"(
+            tasks: [(id: "odometry", type: "Odometry")],
+            templates: [(
+                instances: 4,
+                tasks: [(id: "encoder_{i}", type: "Encoder", config: {"channel": "ch{i}"})],
+                cnx: [(src: "encoder_{i}", dst: "odometry", msg: "Ticks", key: Some("wheel_{i}"))],
+            )],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let graph = config.get_graph(None).unwrap();
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(graph.edge_count(), 4);
+        let encoder = config.get_node(3, None).unwrap();
+        assert_eq!(encoder.id, "encoder_2");
+        assert_eq!(
+            encoder.get_param::<String>("channel"),
+            Some("ch2".to_string())
+        );
+        let cnx = graph.edge_weights().nth(3).unwrap();
+        assert_eq!(
+            (cnx.src.as_str(), cnx.key.as_deref()),
+            ("encoder_3", Some("wheel_3"))
+        );
+
+        let txt = r#"( templates: [(instances: 2, tasks: [(id: "encoder", type: "Encoder")])] )"#;
+        let error = CuConfig::get_options()
+            .from_str::<CuConfig>(txt)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("should contain {i}"));
     }
 
     #[test]
@@ -1340,6 +3362,15 @@ mod tests {
             r#"( tasks: [], cnx: [], logging: ( slab_size_mib: 100, section_size_mib: 1024 ) )"#;
         let config = CuConfig::deserialize_ron(txt);
         assert!(config.validate_logging_config().is_err());
+
+        // The log needs to hold 2 slabs
+        let txt = r#"( tasks: [], cnx: [], logging: ( slab_size_mib: 100, max_size_mib: 150, on_full: Rotate ) )"#;
+        let config = CuConfig::deserialize_ron(txt);
+        assert_eq!(
+            config.logging.as_ref().unwrap().on_full,
+            LogFullPolicy::Rotate
+        );
+        assert!(config.validate_logging_config().is_err());
     }
 
     // this test makes sure the edge id is suitable to be used to sort the inputs of a task
@@ -1447,4 +3478,297 @@ mod tests {
         assert_eq!(cnx.msg, "u32");
         assert_eq!(cnx.missions, Some(vec!["m1".to_string()]));
     }
+
+    #[test]
+    fn test_type_aliases_resolution() {
+        let txt = r#"(
+                    types: { "Image": "cu_image::CuImage<RGB8>", "Stamped": "Wrapper<Image, f32>" },
+                    tasks: [(id: "src", type: "a"), (id: "sink", type: "b")],
+                    cnx: [(src: "src", dst: "sink", msg: "Image")],
+              )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        assert_eq!(
+            config.resolve_msg_type("Image").unwrap(),
+            "cu_image::CuImage<RGB8>"
+        );
+        assert_eq!(
+            config.resolve_msg_type("Vec<Stamped>").unwrap(),
+            "Vec<Wrapper<cu_image::CuImage<RGB8>, f32>>"
+        );
+        // Aliases are not substituted within paths.
+        assert_eq!(
+            config.resolve_msg_type("other::Image").unwrap(),
+            "other::Image"
+        );
+        assert_eq!(config.resolve_msg_type("u32").unwrap(), "u32");
+
+        // The aliases survive a round trip.
+        let deserialized = CuConfig::deserialize_ron(&config.serialize_ron());
+        assert_eq!(
+            deserialized.resolve_msg_type("Image").unwrap(),
+            "cu_image::CuImage<RGB8>"
+        );
+    }
+
+    #[test]
+    fn test_type_aliases_errors() {
+        let txt = r#"(
+                    types: { "A": "Vec<B>", "B": "Option<A>" },
+                    tasks: [(id: "src", type: "a"), (id: "sink", type: "b")],
+                    cnx: [(src: "src", dst: "sink", msg: "A")],
+              )"#;
+        assert!(read_configuration_str(txt.to_string()).is_err());
+
+        let txt = r#"(
+                    types: { "my::Image": "u32" },
+                    tasks: [],
+                    cnx: [],
+              )"#;
+        assert!(read_configuration_str(txt.to_string()).is_err());
+
+        // The same output resolving to 2 different types is a mismatch.
+        let txt = r#"(
+                    types: { "Image": "cu_image::CuImage<RGB8>" },
+                    tasks: [(id: "src", type: "a"), (id: "sink1", type: "b"), (id: "sink2", type: "b")],
+                    cnx: [(src: "src", dst: "sink1", msg: "Image"),
+                          (src: "src", dst: "sink2", msg: "cu_image::CuImage<Gray8>")],
+              )"#;
+        let err = read_configuration_str(txt.to_string()).unwrap_err();
+        assert!(err.to_string().contains("mismatching"));
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let txt = r#"(
+                    tasks: [(id: "src", type: "a::Src"), (id: "sink", type: "b::Sink")],
+                    cnx: [(src: "src", dst: "sink", msg: "Vec<u8>")],
+              )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let mut output = Vec::new();
+        config.render_mermaid(&mut output, None).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("flowchart LR"));
+        assert!(output.contains("n0[\"<b>src</b><br/>a::Src\"]"));
+        assert!(output.contains("n0 -->|\"Vec#lt;u8#gt;\"| n1"));
+    }
+
+    #[test]
+    fn test_schema_versions() {
+        let txt = r#"(
+                    types: { "Image": "cu_image::CuImage<RGB8>" },
+                    schema_versions: { "Image": 3, "Pose": 1 },
+                    tasks: [(id: "src", type: "a"), (id: "sink", type: "b")],
+                    cnx: [(src: "src", dst: "sink", msg: "cu_image::CuImage<RGB8>")],
+              )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        assert_eq!(config.schema_version("cu_image::CuImage<RGB8>").unwrap(), 3);
+        assert_eq!(config.schema_version("Pose").unwrap(), 1);
+        assert_eq!(config.schema_version("u32").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_namespace() {
+        let txt = r#"(
+                    namespace: "robot1",
+                    tasks: [(id: "pub", type: "a", config: {"topic": "imu"}), (id: "other", type: "b")],
+                    cnx: [(src: "pub", dst: "other", msg: "u32")],
+              )"#;
+        let mut config = read_configuration_str(txt.to_string()).unwrap();
+        let configs = config.get_all_tasks_configs(None);
+        let publisher = configs[0].as_ref().unwrap();
+        assert_eq!(publisher.namespace().as_deref(), Some("robot1"));
+        assert_eq!(namespaced(Some(publisher), "imu"), "robot1/imu");
+        assert!(configs[1].is_none());
+
+        // The instance configs are untouched, the namespace survives a round trip.
+        let instance = config.get_all_instances_configs(None)[0].unwrap();
+        assert!(instance.namespace().is_none());
+        let config_again = read_configuration_str(config.serialize_ron()).unwrap();
+        assert_eq!(config_again.namespace.as_deref(), Some("robot1"));
+
+        config.namespace = None;
+        let configs = config.get_all_tasks_configs(None);
+        assert_eq!(namespaced(configs[0].as_ref(), "imu"), "imu");
+        assert_eq!(namespaced(None, "imu"), "imu");
+    }
+
+    #[test]
+    fn test_config_field() {
+        let mut config = ComponentConfig::new();
+        config.set("rate", 300u32);
+        config.set("name", "front".to_string());
+        let config = Some(&config);
+        assert_eq!(
+            config_field::<u32>(config, "Cfg", "rate").unwrap(),
+            Some(300)
+        );
+        assert_eq!(
+            config_field::<f32>(config, "Cfg", "rate").unwrap(),
+            Some(300.0)
+        );
+        assert_eq!(config_field::<u32>(config, "Cfg", "missing").unwrap(), None);
+        let error = config_field::<u8>(config, "Cfg", "rate").unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Cfg: the config key \"rate\" should be an integer fitting in a u8, got 300."
+        ));
+        assert!(config_field::<bool>(config, "Cfg", "name").is_err());
+    }
+
+    #[test]
+    fn test_check_config_keys() {
+        const SCHEMA: &[ConfigKey] = &[
+            ConfigKey {
+                name: "topic",
+                expected: <String as FromConfigValue>::EXPECTED,
+                required: true,
+                default: None,
+                accepts: accepts::<String>,
+            },
+            ConfigKey {
+                name: "rate_hz",
+                expected: <u32 as FromConfigValue>::EXPECTED,
+                required: false,
+                default: Some("10"),
+                accepts: accepts::<u32>,
+            },
+        ];
+        let mut config = ComponentConfig::new();
+        config.set("topic", "robot/cmd".to_string());
+        assert!(check_config_keys("sink", Some(SCHEMA), Some(&config)).is_ok());
+        // A task without a schema accepts anything.
+        config.set("whatever", 1u32);
+        assert!(check_config_keys("sink", None, Some(&config)).is_ok());
+
+        let mut config = ComponentConfig::new();
+        config.set("topick", "robot/cmd".to_string());
+        config.set("rate_hz", -1i32);
+        config.set("verbose", true);
+        let error = check_config_keys("sink", Some(SCHEMA), Some(&config))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("sink: unknown config key \"topick\", did you mean \"topic\"?"));
+        assert!(error.contains("sink: unknown config key \"verbose\"."));
+        assert!(error.contains("sink: the config key \"topic\" is required, expected a string."));
+        assert!(error
+            .contains("sink: the config key \"rate_hz\" should be an integer fitting in a u32"));
+
+        assert!(check_config_keys("sink", Some(SCHEMA), None).is_err());
+    }
+
+    #[test]
+    fn test_closest_key() {
+        let keys = ["topic", "rate_hz", "id"];
+        assert_eq!(closest_key("topick", keys), Some("topic"));
+        assert_eq!(closest_key("rate-hz", keys), Some("rate_hz"));
+        assert_eq!(closest_key("x", keys), None);
+        assert_eq!(closest_key("frequency", keys), None);
+    }
+
+    #[test]
+    fn test_partition() {
+        let txt = r#"(
+            bridges: (transport: Zenoh, config: {"zenoh_config_file": "zenoh.json5"}),
+            types: {"Image": "payloads::Image"},
+            tasks: [
+                (id: "cam", type: "tasks::Cam", process: "perception"),
+                (id: "detector", type: "tasks::Detector", process: "perception"),
+                (id: "planner", type: "tasks::Planner", process: "control"),
+                (id: "display", type: "tasks::Display", process: "control"),
+            ],
+            cnx: [
+                (src: "cam", dst: "detector", msg: "Image"),
+                (src: "cam", dst: "display", msg: "Image", policy: Some(Latched), max_size: 4096),
+                (src: "detector", dst: "planner", msg: "i32"),
+            ],
+            services: [(provider: "planner", client: "display", request: "i32", response: "i32")],
+            invariants: [(cnx: "detector->planner", min_rate_hz: 10.0)],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let edges = |config: &CuConfig| -> Vec<(String, String, String)> {
+            let mut edges: Vec<_> = config
+                .get_graph(None)
+                .unwrap()
+                .edge_weights()
+                .map(|cnx| (cnx.src.clone(), cnx.dst.clone(), cnx.msg.clone()))
+                .collect();
+            edges.sort();
+            edges
+        };
+        let cnx =
+            |src: &str, dst: &str, msg: &str| (src.to_string(), dst.to_string(), msg.to_string());
+
+        let perception = config.partition("perception").unwrap();
+        assert_eq!(
+            edges(&perception),
+            vec![
+                cnx("cam", "cam_bridge", "Image"),
+                cnx("cam", "detector", "Image"),
+                cnx("detector", "detector_bridge", "i32"),
+            ]
+        );
+        let nodes = perception.get_all_nodes(None);
+        let (_, sink) = nodes
+            .iter()
+            .find(|(_, node)| node.get_id() == "cam_bridge")
+            .unwrap();
+        assert_eq!(sink.get_type(), "cu_zenoh_sink::ZenohSink<payloads::Image>");
+        assert_eq!(
+            sink.get_param::<String>("topic").as_deref(),
+            Some("copper/cam")
+        );
+        assert_eq!(
+            sink.get_param::<String>("zenoh_config_file").as_deref(),
+            Some("zenoh.json5")
+        );
+        assert_eq!(sink.get_param::<u32>("max_size"), Some(4096));
+        let (detector_bridge, _) = nodes
+            .iter()
+            .find(|(_, node)| node.get_id() == "detector_bridge")
+            .unwrap();
+        assert_eq!(perception.get_node_output_max_size(0, None), Some(4096));
+        assert_eq!(
+            perception.get_node_output_max_size(*detector_bridge, None),
+            None
+        );
+
+        // The remote tasks are replaced by sources with their ids, the connections are unchanged.
+        let control = config.partition("control").unwrap();
+        assert_eq!(
+            edges(&control),
+            vec![
+                cnx("cam", "display", "Image"),
+                cnx("detector", "planner", "i32")
+            ]
+        );
+        let nodes = control.get_all_nodes(None);
+        let (_, cam) = nodes
+            .iter()
+            .find(|(_, node)| node.get_id() == "cam")
+            .unwrap();
+        assert_eq!(cam.get_type(), "cu_zenoh_src::ZenohSrc<payloads::Image>");
+        let latched = control
+            .get_graph(None)
+            .unwrap()
+            .edge_weights()
+            .find(|cnx| cnx.dst == "display")
+            .unwrap();
+        assert_eq!(latched.policy, Some(CnxPolicy::Latched));
+        assert_eq!(control.get_services_of("display").len(), 1);
+        assert!(perception.get_services_of("display").is_empty());
+        assert_eq!(perception.invariants.as_ref().unwrap().len(), 1);
+        assert!(control.invariants.as_ref().unwrap().is_empty());
+        // A partitioned configuration stays the same.
+        assert_eq!(
+            edges(&control.partition("control").unwrap()),
+            edges(&control)
+        );
+
+        assert!(config.partition("nope").is_err());
+        let unassigned = txt.replace(r#", process: "control"),"#, "),");
+        let unassigned = read_configuration_str(unassigned).unwrap();
+        assert!(unassigned.partition("perception").is_err());
+        let remote = txt.replace(r#"client: "display""#, r#"client: "detector""#);
+        let err = read_configuration_str(remote).unwrap_err();
+        assert!(err.to_string().contains("which runs in another process"));
+    }
 }
@@ -13,6 +13,9 @@ use std::slice::{Iter as SliceIter, IterMut as SliceIterMut};
 
 const MAX_TASKS: usize = 512;
 
+/// Bound of the encoded size of a copper list without its messages: its id and its state.
+pub const HEADER_MAX_ENCODED_SIZE: usize = 5 + 1;
+
 /// Not implemented yet.
 /// This mask will be used to for example filter out necessary regions of a copper list between remote systems.
 #[derive(Debug, Encode, Decode, PartialEq, Clone, Copy)]
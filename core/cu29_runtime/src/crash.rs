@@ -0,0 +1,352 @@
+//! The crash hook: when a task panics, the sections of the unified log still open are lost with
+//! the process, and with them the last moments before the crash. This panic hook, installed with
+//! `with_crash_hook()` on the builder of the application, makes the log readable up to the panic,
+//! writes a crash record (the panic message, the task and the copperlist it happened in) and
+//! aborts, so a crash can be diagnosed from the log alone.
+//!
+//! The hook never waits long for the logger: if the panic happened while the logger was held (by
+//! the panicking thread itself for example), it gives up on the log after a short while and only
+//! aborts.
+//!
+//! The log reader shows the record with its `crash` command.
+//!
+//! To correlate a core dump with what the control loop was doing, `with_crash_context_file(path)`
+//! also writes a small JSON file at the panic: the process id, the record, the last task executed
+//! by every thread of the runtime with its copperlist, and the last structured log entries
+//! (`debug!`...). Their messages are interned, resolve them with the log index of the build.
+
+use bincode::{Decode, Encode};
+use cu29_clock::{CuTime, RobotClock};
+use cu29_log::CuLogEntry;
+use cu29_traits::{CuError, CuResult, UnifiedLogType};
+use cu29_unifiedlog::UnifiedLoggerWrite;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long the hook tries to get the logger.
+const LOGGER_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How many structured log entries the crash context keeps.
+const RECENT_LOG_ENTRIES: usize = 32;
+
+/// What the application was doing when it crashed.
+#[derive(Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct CuCrashRecord {
+    /// The panic message.
+    pub message: String,
+    /// Where the panic was raised, as file:line:column.
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    /// The task being processed when the panic happened, or the last one processed if it happened
+    /// in between.
+    pub task: Option<String>,
+    /// The copperlist being processed.
+    pub culist_id: Option<u32>,
+    pub time: CuTime,
+}
+
+impl Display for CuCrashRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} panic", self.time)?;
+        if let Some(thread) = &self.thread {
+            write!(f, " in thread '{thread}'")?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        writeln!(f, ": {}", self.message)?;
+        match (&self.task, self.culist_id) {
+            (Some(task), Some(culist_id)) => write!(f, "Task: {task}, culist {culist_id}"),
+            _ => write!(f, "Outside of the tasks."),
+        }
+    }
+}
+
+/// The task being processed by a thread and its copperlist, updated before every task.
+/// Both are packed in a single atomic so the hook never sees a task with the copperlist of another.
+#[derive(Debug, Default)]
+pub struct CuCrashLocation(AtomicU64);
+
+/// The locations of the threads running tasks, for the crash context.
+struct ThreadLocation {
+    thread: String,
+    location: Weak<CuCrashLocation>,
+}
+
+static THREAD_LOCATIONS: Mutex<Vec<ThreadLocation>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static LOCATION: Arc<CuCrashLocation> = register_thread();
+}
+
+fn register_thread() -> Arc<CuCrashLocation> {
+    let location = Arc::new(CuCrashLocation::default());
+    let current = std::thread::current();
+    let thread = current
+        .name()
+        .map_or_else(|| format!("{:?}", current.id()), str::to_string);
+    let mut threads = THREAD_LOCATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    threads.retain(|thread| thread.location.strong_count() > 0);
+    threads.push(ThreadLocation {
+        thread,
+        location: Arc::downgrade(&location),
+    });
+    location
+}
+
+/// Records that the current thread processes the task `task_index` for the copperlist
+/// `culist_id`, called by the generated code before every task.
+#[inline]
+pub fn enter_task(task_index: usize, culist_id: u32) {
+    LOCATION.with(|location| location.enter(task_index, culist_id));
+}
+
+impl CuCrashLocation {
+    #[inline]
+    pub fn enter(&self, task_index: usize, culist_id: u32) {
+        self.0.store(
+            ((culist_id as u64) << 32) | (task_index as u64 + 1),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// The task index and the copperlist, None before the first task.
+    pub fn current(&self) -> Option<(usize, u32)> {
+        let location = self.0.load(Ordering::Relaxed);
+        let task = location & 0xFFFF_FFFF;
+        (task != 0).then(|| ((task - 1) as usize, (location >> 32) as u32))
+    }
+}
+
+impl CuCrashRecord {
+    fn new(info: &PanicHookInfo, time: CuTime, task_ids: &[&str]) -> Self {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic payload".to_string());
+        let current = LOCATION.with(|location| location.current());
+        Self {
+            message,
+            location: info.location().map(|location| location.to_string()),
+            thread: std::thread::current().name().map(str::to_string),
+            task: current.and_then(|(task, _)| task_ids.get(task).map(|id| id.to_string())),
+            culist_id: current.map(|(_, culist_id)| culist_id),
+            time,
+        }
+    }
+}
+
+/// What the control loop was doing at the crash, written as JSON next to the core dump.
+#[derive(Debug, Serialize)]
+pub struct CuCrashContext {
+    pub pid: u32,
+    /// The wall clock time of the crash, in nanoseconds since the UNIX epoch.
+    pub wall_time_ns: u64,
+    pub crash: CuCrashRecord,
+    pub threads: Vec<CuThreadTask>,
+    /// The last structured log entries, oldest first.
+    pub recent_log_entries: Vec<CuLogEntry>,
+}
+
+/// The last task executed by a thread.
+#[derive(Debug, Serialize)]
+pub struct CuThreadTask {
+    pub thread: String,
+    pub task: Option<String>,
+    pub culist_id: u32,
+}
+
+impl CuCrashContext {
+    fn new(record: &CuCrashRecord, task_ids: &[&str]) -> Self {
+        let threads = match THREAD_LOCATIONS.try_lock() {
+            Ok(threads) => threads
+                .iter()
+                .filter_map(|thread| {
+                    let (task, culist_id) = thread.location.upgrade()?.current()?;
+                    Some(CuThreadTask {
+                        thread: thread.thread.clone(),
+                        task: task_ids.get(task).map(|id| id.to_string()),
+                        culist_id,
+                    })
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        Self {
+            pid: std::process::id(),
+            wall_time_ns: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64),
+            crash: record.clone(),
+            threads,
+            recent_log_entries: cu29_log_runtime::recent_entries(),
+        }
+    }
+
+    fn write(&self, path: &Path) -> CuResult<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CuError::new_with_cause("Could not encode the crash context", e))?;
+        std::fs::write(path, json).map_err(|e| {
+            CuError::new_with_cause(
+                &format!("Could not write the crash context to {}", path.display()),
+                e,
+            )
+        })
+    }
+}
+
+/// Installs the crash hook, called by the builder of the application. The hook set before is
+/// still called (to print the panic), then the process aborts.
+/// With a `context_file`, the crash context is written there too.
+pub fn install_crash_hook(
+    unified_logger: Arc<Mutex<UnifiedLoggerWrite>>,
+    clock: RobotClock,
+    task_ids: &'static [&'static str],
+    context_file: Option<PathBuf>,
+) {
+    if context_file.is_some() {
+        cu29_log_runtime::keep_recent_entries(RECENT_LOG_ENTRIES);
+    }
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let record = CuCrashRecord::new(info, clock.now(), task_ids);
+        if let Some(path) = &context_file {
+            if let Err(e) = CuCrashContext::new(&record, task_ids).write(path) {
+                eprintln!("{e}");
+            }
+        }
+        match lock_logger(&unified_logger) {
+            Some(mut logger) => {
+                if let Err(e) = write_crash_record(&mut logger, &record) {
+                    eprintln!("Could not write the crash record in the log: {e}");
+                }
+            }
+            None => eprintln!("The log is busy, the crash record could not be written."),
+        }
+        previous(info);
+        std::process::abort();
+    }));
+}
+
+/// The logger, unless it stays held by someone else: a panicking thread holding it would never
+/// release it. A poisoned logger is still usable, it was poisoned by an earlier panic.
+fn lock_logger(
+    unified_logger: &Mutex<UnifiedLoggerWrite>,
+) -> Option<MutexGuard<'_, UnifiedLoggerWrite>> {
+    let deadline = Instant::now() + LOGGER_TIMEOUT;
+    loop {
+        match unified_logger.try_lock() {
+            Ok(logger) => return Some(logger),
+            Err(TryLockError::Poisoned(poisoned)) => return Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(1))
+            }
+            Err(TryLockError::WouldBlock) => return None,
+        }
+    }
+}
+
+/// Makes what has been logged readable, adds the record and closes the log.
+fn write_crash_record(logger: &mut UnifiedLoggerWrite, record: &CuCrashRecord) -> CuResult<()> {
+    let sync_error = |e| CuError::new_with_cause("Could not flush the log", e);
+    logger.sync().map_err(sync_error)?;
+    logger.write_section(UnifiedLogType::Crash, record)?;
+    logger.write_section(UnifiedLogType::LastEntry, &())?;
+    // The sections added are flushed only up to the sections still in flight.
+    logger.sync().map_err(sync_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_context() {
+        std::thread::Builder::new()
+            .name("worker".to_string())
+            .spawn(|| enter_task(1, 41))
+            .unwrap()
+            .join()
+            .unwrap();
+        let worker = std::thread::Builder::new()
+            .name("source".to_string())
+            .spawn(|| {
+                enter_task(0, 42);
+                // Keeps the thread alive while the context is built.
+                std::thread::park();
+            })
+            .unwrap();
+        while !THREAD_LOCATIONS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|thread| thread.thread == "source")
+        {
+            std::thread::yield_now();
+        }
+        let record = CuCrashRecord {
+            message: "boom".to_string(),
+            location: None,
+            thread: None,
+            task: None,
+            culist_id: None,
+            time: CuTime::default(),
+        };
+        let context = CuCrashContext::new(&record, &["lidar", "fusion"]);
+        worker.thread().unpark();
+        worker.join().unwrap();
+
+        // The exited thread is gone.
+        let threads: Vec<(&str, Option<&str>, u32)> = context
+            .threads
+            .iter()
+            .map(|thread| {
+                (
+                    thread.thread.as_str(),
+                    thread.task.as_deref(),
+                    thread.culist_id,
+                )
+            })
+            .collect();
+        assert_eq!(threads, vec![("source", Some("lidar"), 42)]);
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("crash.json");
+        context.write(&path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["pid"], std::process::id());
+        assert_eq!(json["crash"]["message"], "boom");
+        assert_eq!(json["threads"][0]["culist_id"], 42);
+    }
+
+    #[test]
+    fn test_crash_location() {
+        let location = CuCrashLocation::default();
+        assert_eq!(location.current(), None);
+        location.enter(0, 12);
+        assert_eq!(location.current(), Some((0, 12)));
+        location.enter(3, u32::MAX);
+        assert_eq!(location.current(), Some((3, u32::MAX)));
+
+        let record = CuCrashRecord {
+            message: "index out of bounds".to_string(),
+            location: Some("src/tasks.rs:42:9".to_string()),
+            thread: Some("main".to_string()),
+            task: Some("lidar".to_string()),
+            culist_id: Some(12),
+            time: CuTime::from_millis(1_500),
+        };
+        assert_eq!(
+            record.to_string(),
+            "1.500 s panic in thread 'main' at src/tasks.rs:42:9: index out of bounds\nTask: lidar, culist 12"
+        );
+    }
+}
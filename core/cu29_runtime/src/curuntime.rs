@@ -2,23 +2,44 @@
 //! It is exposed to the user via the `copper_runtime` macro injecting it as a field in their application struct.
 //!
 
-use crate::config::{Cnx, CuConfig, NodeId};
-use crate::config::{ComponentConfig, Node};
+use crate::alarms::{self, raise_alarm, AlarmSeverity};
+use crate::budget::CuBudgets;
+#[cfg(feature = "chaos")]
+use crate::chaos::CuChaos;
+use crate::config::{Cnx, CuConfig, CuGraph, NodeId};
+use crate::config::{
+    ComponentConfig, IdleStrategy, LogFullPolicy, Node, OverrunPolicy, TaskPriority,
+};
 use crate::copperlist::{CopperList, CopperListState, CuListsManager};
+use crate::cutask::CuMsgValidity;
+use crate::events::{self, CuEvent};
+use crate::introspection::{CuIntrospection, GraphDescription};
+use crate::invariants::CuInvariants;
+use crate::log::*;
 use crate::monitoring::CuMonitor;
-use cu29_clock::{ClockProvider, RobotClock};
+use crate::params::{self, CuParamServer};
+#[cfg(feature = "perf")]
+use crate::perf::CuPerfCounters;
+use crate::profile::CuRunProfile;
+#[cfg(feature = "tap")]
+use crate::tap::CuTaps;
+use cu29_clock::{ClockProvider, ClockSource, CuDuration, CuTime, RobotClock};
 use cu29_log_runtime::LoggerRuntime;
 use cu29_traits::CopperListTuple;
-use cu29_traits::CuResult;
 use cu29_traits::WriteStream;
-use cu29_unifiedlog::UnifiedLoggerWrite;
+use cu29_traits::{CuError, CuResult};
+use cu29_unifiedlog::{LogCapacity, LogFullEvent, OnLogFull, UnifiedLoggerWrite};
 use std::sync::{Arc, Mutex};
 
 use petgraph::prelude::*;
 use petgraph::visit::VisitMap;
 use petgraph::visit::Visitable;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+/// The log reached its max_size_mib and the logger stopped, see [LogFullPolicy].
+pub const LOG_FULL_ALARM: u32 = 1;
+
 /// Just a simple struct to hold the various bits needed to run a Copper application.
 pub struct CopperContext {
     pub unified_logger: Arc<Mutex<UnifiedLoggerWrite>>,
@@ -43,6 +64,51 @@ pub struct CuRuntime<CT, P: CopperListTuple, M: CuMonitor, const NBCL: usize> {
 
     /// Logger
     logger: Option<Box<dyn WriteStream<CopperList<P>>>>,
+
+    /// Logger of the events marked by the tasks, see the events module.
+    event_logger: Option<Box<dyn WriteStream<CuEvent>>>,
+
+    /// The unified logger the streams write to, watched for its log reaching its capacity.
+    log_storage: Option<Arc<Mutex<UnifiedLoggerWrite>>>,
+
+    /// Paces the loop if the config sets a loop rate, it runs as fast as possible otherwise.
+    loop_rate_limiter: Option<LoopRateLimiter>,
+
+    /// The tasks and connections of the graph with their live counters, see `introspect()`.
+    pub graph_description: GraphDescription,
+
+    /// The resource budgets of the tasks and their measured usage, see the budget module.
+    pub budgets: CuBudgets,
+
+    /// The invariants of the connections checked at the end of every copper list, see the
+    /// invariants module.
+    pub invariants: CuInvariants,
+
+    /// The next sequence number of the messages of each slot of the copperlist, shared by the
+    /// connections it feeds, see [crate::cutask::CuMsgMetadata::seq].
+    pub msg_seqs: Vec<u64>,
+
+    /// The last validity of the output of each task, to log its transitions.
+    pub msg_validities: Vec<CuMsgValidity>,
+
+    /// The tasks and connections enabled by the profile the application runs with, see the
+    /// profile module.
+    pub profile: CuRunProfile,
+
+    /// Answers the clients listing, getting and setting the parameters, see the params module.
+    pub params: CuParamServer,
+
+    /// The connections tapped for debugging, see the tap module.
+    #[cfg(feature = "tap")]
+    pub taps: CuTaps,
+
+    /// Hardware counters sampled around the process() of every task, see the perf module.
+    #[cfg(feature = "perf")]
+    pub perf_counters: CuPerfCounters,
+
+    /// The faults injected in the tasks for testing, see the chaos module.
+    #[cfg(feature = "chaos")]
+    pub chaos: CuChaos,
 }
 
 /// To be able to share the clock we make the runtime a clock provider.
@@ -62,14 +128,13 @@ impl<CT, P: CopperListTuple + 'static, M: CuMonitor, const NBCL: usize> CuRuntim
         monitor_instanciator: impl Fn(&CuConfig) -> M,
         logger: impl WriteStream<CopperList<P>> + 'static,
     ) -> CuResult<Self> {
-        let all_instances_configs: Vec<Option<&ComponentConfig>> = config
-            .get_all_nodes(None) // FIXME(gbin): Multimission support
-            .iter()
-            .map(|(_, node)| node.get_instance_config())
-            .collect();
+        let all_tasks_configs = config.get_all_tasks_configs(None); // FIXME(gbin): Multimission support
+        let all_instances_configs: Vec<Option<&ComponentConfig>> =
+            all_tasks_configs.iter().map(Option::as_ref).collect();
         let tasks = tasks_instanciator(all_instances_configs)?;
 
         let monitor = monitor_instanciator(config);
+        let profile = CuRunProfile::new(config)?;
 
         // Needed to declare type explicitly as `cargo check` was failing without it
         let logger_: Option<Box<dyn WriteStream<CopperList<P>>>> =
@@ -83,21 +148,161 @@ impl<CT, P: CopperListTuple + 'static, M: CuMonitor, const NBCL: usize> CuRuntim
                 Some(Box::new(logger))
             };
 
+        let loop_rate_limiter = match &config.runtime {
+            Some(runtime) => runtime
+                .loop_rate_hz
+                .map(|rate| {
+                    LoopRateLimiter::new(rate, runtime.overrun_policy)
+                        .map(|limiter| limiter.with_idle_strategy(runtime.idle_strategy))
+                })
+                .transpose()?,
+            None => None,
+        };
+
+        // FIXME(gbin): Multimission support, the missions are not described yet.
+        let graph_description = GraphDescription::from_config(config).unwrap_or_default();
+        #[cfg(feature = "tap")]
+        let taps = CuTaps::new(&graph_description.connections);
+
         let runtime = Self {
             tasks,
             monitor,
             copper_lists_manager: CuListsManager::new(), // placeholder
             clock,
             logger: logger_,
+            event_logger: None,
+            log_storage: None,
+            loop_rate_limiter,
+            graph_description,
+            budgets: CuBudgets::new(config),
+            invariants: CuInvariants::new(config),
+            // Every task has its output slot in the copperlist, the sinks a virtual one.
+            msg_seqs: vec![0; all_tasks_configs.len()],
+            msg_validities: vec![CuMsgValidity::Fresh; all_tasks_configs.len()],
+            profile,
+            params: CuParamServer::default(),
+            #[cfg(feature = "tap")]
+            taps,
+            #[cfg(feature = "perf")]
+            perf_counters: CuPerfCounters::new(),
+            #[cfg(feature = "chaos")]
+            chaos: CuChaos::default(),
         };
 
         Ok(runtime)
     }
 
+    /// Writes the events marked by the tasks to this stream, they are only in the text logs otherwise.
+    pub fn set_event_logger(&mut self, event_logger: impl WriteStream<CuEvent> + 'static) {
+        self.event_logger = Some(Box::new(event_logger));
+    }
+
+    /// Bounds the log of the unified logger as the logging section of the config says and watches
+    /// it: the logger stopping raises a [LOG_FULL_ALARM] from "logger", its rotations and its
+    /// waits are marked as events.
+    pub fn set_log_storage(
+        &mut self,
+        config: &CuConfig,
+        unified_logger: Arc<Mutex<UnifiedLoggerWrite>>,
+    ) -> CuResult<()> {
+        if let Some(logging) = &config.logging {
+            if let Some(max_size_mib) = logging.max_size_mib {
+                let on_full = match logging.on_full {
+                    LogFullPolicy::Stop => OnLogFull::Stop,
+                    LogFullPolicy::Rotate => OnLogFull::Rotate,
+                    LogFullPolicy::Block => OnLogFull::Block,
+                };
+                unified_logger
+                    .lock()
+                    .unwrap()
+                    .set_capacity(max_size_mib as usize * 1024 * 1024, on_full)?;
+            }
+        }
+        self.log_storage = Some(unified_logger);
+        Ok(())
+    }
+
+    /// How much of its capacity the log uses, None if the runtime doesn't watch it.
+    pub fn log_capacity(&self) -> Option<LogCapacity> {
+        self.log_storage
+            .as_ref()
+            .map(|logger| logger.lock().unwrap().capacity())
+    }
+
+    /// Surfaces what the logger did since the last copperlist when the log reached its capacity.
+    fn check_log_storage(&self) {
+        let Some(logger) = &self.log_storage else {
+            return;
+        };
+        // A stream is writing, this is checked again at the next copperlist.
+        let Ok(mut logger) = logger.try_lock() else {
+            return;
+        };
+        for event in logger.take_full_events() {
+            match event {
+                LogFullEvent::Stopped => raise_alarm(
+                    &self.clock,
+                    "logger",
+                    LOG_FULL_ALARM,
+                    AlarmSeverity::Error,
+                    "The log is full, nothing is logged anymore.",
+                ),
+                LogFullEvent::Rotated(slab) => {
+                    events::mark_event(&self.clock, "log_rotated", format!("slab {slab} deleted"))
+                }
+                LogFullEvent::Blocked(waited) => events::mark_event(
+                    &self.clock,
+                    "log_blocked",
+                    format!("waited {} ms for room", waited.as_millis()),
+                ),
+            }
+        }
+    }
+
+    /// Sleeps until the start of the next cycle when the loop runs at a fixed rate.
+    pub fn wait_for_next_cycle(&mut self) {
+        if let Some(limiter) = &mut self.loop_rate_limiter {
+            limiter.wait(&self.clock);
+        }
+    }
+
+    /// If a best effort task is to be skipped: the loop runs at a fixed rate and the current cycle
+    /// is already late, see [TaskPriority::BestEffort].
+    pub fn shed_best_effort(&mut self) -> bool {
+        let Some(limiter) = &mut self.loop_rate_limiter else {
+            return false;
+        };
+        let late = limiter.is_late(self.clock.now());
+        if late {
+            limiter.stats.shed_tasks += 1;
+        }
+        late
+    }
+
+    /// Overrun accounting of the fixed rate loop, None if it runs freely.
+    pub fn loop_stats(&self) -> Option<LoopStats> {
+        self.loop_rate_limiter.as_ref().map(|limiter| limiter.stats)
+    }
+
     pub fn available_copper_lists(&self) -> usize {
         NBCL - self.copper_lists_manager.len()
     }
 
+    /// The graph as instantiated with the live counters.
+    pub fn introspect(&self) -> CuIntrospection {
+        CuIntrospection {
+            tasks: self.graph_description.tasks.clone(),
+            connections: self.graph_description.connections.clone(),
+            copperlists: self.graph_description.copperlists,
+            available_copper_lists: self.available_copper_lists(),
+            loop_stats: self.loop_stats(),
+            active_alarms: alarms::alarms()
+                .iter()
+                .filter(|alarm| alarm.active || alarm.is_latched())
+                .count(),
+        }
+    }
+
     pub fn end_of_processing(&mut self, culistid: u32) {
         let mut is_top = true;
         let mut nb_done = 0;
@@ -121,7 +326,226 @@ impl<CT, P: CopperListTuple + 'static, M: CuMonitor, const NBCL: usize> CuRuntim
         for _ in 0..nb_done {
             let _ = self.copper_lists_manager.pop();
         }
+
+        self.params.poll();
+        for change in params::take_param_changes() {
+            params::log_param_change(&change, culistid);
+        }
+
+        self.check_log_storage();
+
+        for event in alarms::take_alarm_events() {
+            alarms::log_alarm_event(&event);
+            self.monitor.process_alarm(&event);
+        }
+
+        for event in events::take_events(culistid) {
+            events::log_event(&event);
+            if let Some(event_logger) = &mut self.event_logger {
+                if let Err(e) = event_logger.log(&event) {
+                    debug!(
+                        "Could not log the event {}: {}",
+                        event.name.clone(),
+                        e.to_string()
+                    );
+                }
+            }
+            if event.flush {
+                self.flush_logs();
+            }
+            self.monitor.process_event(&event);
+        }
     }
+
+    /// Closes the sections of the unified log written so far so they reach the disk.
+    fn flush_logs(&mut self) {
+        let copperlists = self.logger.as_mut().map(|logger| logger.flush());
+        let event_log = self.event_logger.as_mut().map(|logger| logger.flush());
+        for result in [copperlists, event_log].into_iter().flatten() {
+            if let Err(e) = result {
+                debug!("Could not flush the log: {}", e.to_string());
+            }
+        }
+    }
+}
+
+/// The clock of the runtime: the one given, or one slaved to the external time base of the runtime
+/// section of the config and resynced in the background. This is called by the generated code,
+/// outside of the simulations.
+pub fn configured_clock(clock: RobotClock, config: &CuConfig) -> CuResult<RobotClock> {
+    let Some(clock_config) = config
+        .runtime
+        .as_ref()
+        .and_then(|runtime| runtime.clock.as_ref())
+    else {
+        return Ok(clock);
+    };
+    let source = clock_config.source()?;
+    if source == ClockSource::Monotonic {
+        return Ok(clock);
+    }
+    let clock = RobotClock::from_source(&source).map_err(|e| {
+        CuError::new_with_cause(&format!("Failed to open the clock source {source}"), e)
+    })?;
+    clock.track(std::time::Duration::from_millis(
+        clock_config.resync_period_ms,
+    ));
+    debug!("Clock slaved to {}.", source.to_string());
+    Ok(clock)
+}
+
+/// Logs the validity changes of the output of a task, this is called by the generated code.
+pub fn track_validity(task_id: &str, last: &mut CuMsgValidity, current: CuMsgValidity) {
+    if *last != current {
+        debug!(
+            "Task {}: output {} -> {}.",
+            task_id,
+            last.to_string(),
+            current.to_string()
+        );
+        *last = current;
+    }
+}
+
+/// Overrun accounting of a fixed rate loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoopStats {
+    pub cycles: u64,
+    /// Cycles that ended after the scheduled start of the next one.
+    pub overruns: u64,
+    /// Cycles dropped by the Skip overrun policy.
+    pub skipped_cycles: u64,
+    /// Runs of the best effort tasks skipped because their cycle was late.
+    pub shed_tasks: u64,
+    pub max_overrun: CuDuration,
+}
+
+/// Schedules the cycles of the copper loop on a fixed period.
+/// The schedule is anchored at the end of the first cycle, the next cycles start every period from there.
+pub struct LoopRateLimiter {
+    period: CuDuration,
+    policy: OverrunPolicy,
+    idle_strategy: IdleStrategy,
+    /// Start of the cycle after the one the loop is waiting for.
+    next_start: Option<CuTime>,
+    pub stats: LoopStats,
+}
+
+impl LoopRateLimiter {
+    pub fn new(loop_rate_hz: f64, policy: OverrunPolicy) -> CuResult<Self> {
+        if !(loop_rate_hz.is_finite() && loop_rate_hz > 0.0) {
+            return Err(CuError::from(format!(
+                "The loop rate must be a positive frequency, got {loop_rate_hz} Hz."
+            )));
+        }
+        Ok(Self {
+            period: CuDuration::from_secs_f64(1.0 / loop_rate_hz),
+            policy,
+            idle_strategy: IdleStrategy::default(),
+            next_start: None,
+            stats: LoopStats::default(),
+        })
+    }
+
+    pub fn with_idle_strategy(mut self, idle_strategy: IdleStrategy) -> Self {
+        self.idle_strategy = idle_strategy;
+        self
+    }
+
+    /// Accounts for the cycle that just ended at `now` and returns how long to wait before the
+    /// next one.
+    pub fn end_of_cycle(&mut self, now: CuTime) -> CuDuration {
+        self.stats.cycles += 1;
+        let next_start = self.next_start.unwrap_or(now + self.period);
+        if now <= next_start {
+            self.next_start = Some(next_start + self.period);
+            return next_start - now;
+        }
+
+        let overrun = now - next_start;
+        self.stats.overruns += 1;
+        if overrun > self.stats.max_overrun {
+            self.stats.max_overrun = overrun;
+        }
+        debug!("Loop overrun by {} ns", overrun.as_nanos());
+        match self.policy {
+            OverrunPolicy::CatchUp => {
+                // Keep the schedule, the following cycles start immediately until it is met again.
+                self.next_start = Some(next_start + self.period);
+                CuDuration(0)
+            }
+            OverrunPolicy::Skip => {
+                let missed = overrun.as_nanos() / self.period.as_nanos() + 1;
+                self.stats.skipped_cycles += missed;
+                let start = next_start + self.period * missed;
+                self.next_start = Some(start + self.period);
+                start - now
+            }
+        }
+    }
+
+    /// If the cycle running at `now` is past its deadline, the start of the next cycle.
+    pub fn is_late(&self, now: CuTime) -> bool {
+        self.next_start.is_some_and(|deadline| now > deadline)
+    }
+
+    pub fn wait(&mut self, clock: &RobotClock) {
+        let wait = self.end_of_cycle(clock.now());
+        if wait.as_nanos() > 0 {
+            idle(
+                self.idle_strategy,
+                std::time::Duration::from_nanos(wait.as_nanos()),
+            );
+        }
+    }
+}
+
+/// Waits for `wait` on the monotonic clock of the host as the strategy says, a mock robot clock
+/// does not move by itself.
+fn idle(strategy: IdleStrategy, wait: std::time::Duration) {
+    let deadline = std::time::Instant::now() + wait;
+    match strategy {
+        IdleStrategy::Sleep => sleep_for(wait),
+        IdleStrategy::Yield => {
+            while std::time::Instant::now() < deadline {
+                std::thread::yield_now();
+            }
+        }
+        IdleStrategy::Spin => {
+            while std::time::Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/// Sleeps until an absolute deadline, a signal interrupting the sleep does not push it back.
+#[cfg(target_os = "linux")]
+fn sleep_for(wait: std::time::Duration) {
+    let mut deadline = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: deadline is a valid timespec.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut deadline) };
+    let nanos = deadline.tv_nsec as u64 + wait.subsec_nanos() as u64;
+    deadline.tv_sec += (wait.as_secs() + nanos / 1_000_000_000) as libc::time_t;
+    deadline.tv_nsec = (nanos % 1_000_000_000) as _;
+    // SAFETY: deadline is a valid timespec, the remaining time is only written for a relative sleep.
+    while unsafe {
+        libc::clock_nanosleep(
+            libc::CLOCK_MONOTONIC,
+            libc::TIMER_ABSTIME,
+            &deadline,
+            std::ptr::null_mut(),
+        )
+    } == libc::EINTR
+    {}
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sleep_for(wait: std::time::Duration) {
+    std::thread::sleep(wait);
 }
 
 /// Copper tasks can be of 3 types:
@@ -149,6 +573,9 @@ pub struct CuExecutionStep {
 
     /// the index in the copper list of the output message and its type
     pub output_msg_index_type: Option<(u32, String)>,
+
+    /// The priority of the task, raised to the one of the tasks it feeds.
+    pub priority: TaskPriority,
 }
 
 impl Debug for CuExecutionStep {
@@ -201,7 +628,7 @@ impl Debug for CuExecutionLoop {
 /// This structure represents a step in the execution plan.
 #[derive(Debug)]
 pub enum CuExecutionUnit {
-    Step(CuExecutionStep),
+    Step(Box<CuExecutionStep>),
     Loop(CuExecutionLoop),
 }
 
@@ -284,7 +711,7 @@ fn plan_tasks_tree_branch(
     mut next_culist_output_index: u32,
     starting_point: NodeId,
     plan: &mut Vec<CuExecutionUnit>,
-) -> (u32, bool) {
+) -> CuResult<(u32, bool)> {
     #[cfg(feature = "macro_debug")]
     eprintln!("-- starting branch from node {starting_point}");
 
@@ -308,11 +735,12 @@ fn plan_tasks_tree_branch(
                 eprintln!("    → Source node, assign output index {next_culist_output_index}");
                 output_msg_index_type = Some((
                     next_culist_output_index,
-                    graph
-                        .edge_weight(EdgeIndex::new(config.get_src_edges(id, None).unwrap()[0])) // FIXME(gbin): Error handling and multimission
-                        .unwrap()
-                        .msg
-                        .clone(),
+                    config.resolve_msg_type(
+                        &graph
+                            .edge_weight(EdgeIndex::new(config.get_src_edges(id, None).unwrap()[0])) // FIXME(gbin): Error handling and multimission
+                            .unwrap()
+                            .msg,
+                    )?,
                 ));
                 next_culist_output_index += 1;
             }
@@ -331,7 +759,7 @@ fn plan_tasks_tree_branch(
                     } else {
                         #[cfg(feature = "macro_debug")]
                         eprintln!("      ✗ Input from {pid} not ready, returning");
-                        return (next_culist_output_index, handled);
+                        return Ok((next_culist_output_index, handled));
                     }
                 }
                 output_msg_index_type = Some((next_culist_output_index, "()".to_string()));
@@ -352,16 +780,17 @@ fn plan_tasks_tree_branch(
                     } else {
                         #[cfg(feature = "macro_debug")]
                         eprintln!("      ✗ Input from {pid} not ready, returning");
-                        return (next_culist_output_index, handled);
+                        return Ok((next_culist_output_index, handled));
                     }
                 }
                 output_msg_index_type = Some((
                     next_culist_output_index,
-                    graph
-                        .edge_weight(EdgeIndex::new(config.get_src_edges(id, None).unwrap()[0])) // FIXME(gbin): Error handling and multimission
-                        .unwrap()
-                        .msg
-                        .clone(),
+                    config.resolve_msg_type(
+                        &graph
+                            .edge_weight(EdgeIndex::new(config.get_src_edges(id, None).unwrap()[0])) // FIXME(gbin): Error handling and multimission
+                            .unwrap()
+                            .msg,
+                    )?,
                 ));
                 next_culist_output_index += 1;
             }
@@ -389,8 +818,9 @@ fn plan_tasks_tree_branch(
                 task_type,
                 input_msg_indices_types,
                 output_msg_index_type,
+                priority: node_ref.get_priority(),
             };
-            plan.push(CuExecutionUnit::Step(step));
+            plan.push(CuExecutionUnit::Step(Box::new(step)));
         }
 
         handled = true;
@@ -398,7 +828,7 @@ fn plan_tasks_tree_branch(
 
     #[cfg(feature = "macro_debug")]
     eprintln!("-- finished branch from node {starting_point} with handled={handled}");
-    (next_culist_output_index, handled)
+    Ok((next_culist_output_index, handled))
 }
 
 /// This is the main heuristics to compute an execution plan at compilation time.
@@ -447,7 +877,7 @@ pub fn compute_runtime_plan(config: &CuConfig) -> CuResult<CuExecutionLoop> {
             #[cfg(feature = "macro_debug")]
             eprintln!("    Planning from node {node_id}");
             let (new_index, handled) =
-                plan_tasks_tree_branch(config, next_culist_output_index, node_id, &mut plan);
+                plan_tasks_tree_branch(config, next_culist_output_index, node_id, &mut plan)?;
             next_culist_output_index = new_index;
 
             if !handled {
@@ -471,16 +901,88 @@ pub fn compute_runtime_plan(config: &CuConfig) -> CuResult<CuExecutionLoop> {
     }
 
     Ok(CuExecutionLoop {
-        steps: plan,
+        steps: prioritize_plan(graph, plan),
         loop_count: None,
     })
 }
 
+/// The priority of a task raised to the one of the tasks downstream of it.
+fn inherited_priority(
+    graph: &CuGraph,
+    node: NodeIndex,
+    inherited: &mut HashMap<NodeIndex, TaskPriority>,
+) -> TaskPriority {
+    if let Some(priority) = inherited.get(&node) {
+        return *priority;
+    }
+    let mut priority = graph[node].get_priority();
+    for child in graph.neighbors_directed(node, Outgoing) {
+        priority = priority.min(inherited_priority(graph, child, inherited));
+    }
+    inherited.insert(node, priority);
+    priority
+}
+
+/// Reorders the plan so the most important chains run first: the next task is the one of the
+/// highest inherited priority among the tasks with all their inputs ready, the first one of the
+/// plan on a tie. The outputs are renumbered to keep the copper list in the order of execution.
+/// The plan is unchanged when all the tasks have the same priority.
+fn prioritize_plan(graph: &CuGraph, plan: Vec<CuExecutionUnit>) -> Vec<CuExecutionUnit> {
+    // Only the plans made of steps are reordered.
+    if plan
+        .iter()
+        .any(|unit| matches!(unit, CuExecutionUnit::Loop(_)))
+    {
+        return plan;
+    }
+    let mut inherited = HashMap::new();
+    let mut steps: Vec<Box<CuExecutionStep>> = plan
+        .into_iter()
+        .filter_map(|unit| match unit {
+            CuExecutionUnit::Step(step) => Some(step),
+            CuExecutionUnit::Loop(_) => None,
+        })
+        .collect();
+    for step in &mut steps {
+        step.priority = inherited_priority(graph, step.node_id.into(), &mut inherited);
+    }
+    if steps.iter().all(|step| step.priority == steps[0].priority) {
+        return steps.into_iter().map(CuExecutionUnit::Step).collect();
+    }
+
+    let mut renumbered: HashMap<u32, u32> = HashMap::new();
+    let mut prioritized = Vec::with_capacity(steps.len());
+    while !steps.is_empty() {
+        let (next, _) = steps
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| {
+                step.input_msg_indices_types
+                    .iter()
+                    .all(|(index, _)| renumbered.contains_key(index))
+            })
+            .min_by_key(|(position, step)| (step.priority, *position))
+            .expect("The plan is ordered by dependencies");
+        let mut step = steps.remove(next);
+        for (index, _) in &mut step.input_msg_indices_types {
+            *index = renumbered[index];
+        }
+        if let Some((index, _)) = &mut step.output_msg_index_type {
+            let new_index = renumbered.len() as u32;
+            renumbered.insert(*index, new_index);
+            *index = new_index;
+        }
+        prioritized.push(CuExecutionUnit::Step(step));
+    }
+    prioritized
+}
+
 //tests
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::Node;
+    use crate::cutask::CuMsgMetadata;
     use crate::cutask::CuSinkTask;
     use crate::cutask::{CuSrcTask, Freezable};
     use crate::monitoring::NoMonitor;
@@ -564,6 +1066,41 @@ mod tests {
         assert!(runtime.is_ok());
     }
 
+    #[test]
+    fn test_runtime_introspection() {
+        let mut config = CuConfig::default();
+        let graph = config.get_graph_mut(None).unwrap();
+        graph.add_node(Node::new("a", "TestSource"));
+        graph.add_node(Node::new("b", "TestSink"));
+        config.connect(0, 1, "()").unwrap();
+        let mut runtime = CuRuntime::<Tasks, Msgs, NoMonitor, 2>::new(
+            RobotClock::default(),
+            &config,
+            tasks_instanciator,
+            monitor_instanciator,
+            FakeWriter {},
+        )
+        .unwrap();
+
+        let mut source = CuMsgMetadata::default();
+        source.process_time.start = CuDuration(10).into();
+        source.process_time.end = CuDuration(30).into();
+        runtime
+            .graph_description
+            .record_stats(&[&source, &CuMsgMetadata::default()]);
+
+        let introspection = runtime.introspect();
+        assert_eq!(introspection.copperlists, 1);
+        assert_eq!(introspection.available_copper_lists, 2);
+        assert_eq!(introspection.connections.len(), 1);
+        let a = introspection.task("a").unwrap();
+        assert_eq!(a.task_type, CuTaskType::Source);
+        assert_eq!(a.type_name, "TestSource");
+        assert_eq!(a.stats.runs, 1);
+        assert_eq!(a.stats.last_process_time, CuDuration(20));
+        assert_eq!(introspection.task("b").unwrap().stats.runs, 0);
+    }
+
     #[test]
     fn test_copperlists_manager_lifecycle() {
         let mut config = CuConfig::default();
@@ -771,4 +1308,91 @@ mod tests {
         assert_eq!(broadcast_step.input_msg_indices_types[0].1, "i32");
         assert_eq!(broadcast_step.input_msg_indices_types[1].1, "f32");
     }
+
+    #[test]
+    fn test_runtime_plan_priorities() {
+        let mut config = CuConfig::default();
+        let mut node = |id: &str, priority: TaskPriority| {
+            let mut node = Node::new(id, "tasks::Task");
+            node.set_priority(priority);
+            config.add_node(node, None).unwrap()
+        };
+        let telemetry = node("telemetry", TaskPriority::BestEffort);
+        let thumbnails = node("thumbnails", TaskPriority::BestEffort);
+        let imu = node("imu", TaskPriority::Normal);
+        let estimator = node("estimator", TaskPriority::Normal);
+        let actuator = node("actuator", TaskPriority::Critical);
+        config.connect(telemetry, thumbnails, "u8").unwrap();
+        config.connect(imu, estimator, "f32").unwrap();
+        config.connect(estimator, actuator, "f32").unwrap();
+
+        let plan = compute_runtime_plan(&config).unwrap();
+        let steps: Vec<&CuExecutionStep> = plan
+            .steps
+            .iter()
+            .map(|unit| match unit {
+                CuExecutionUnit::Step(step) => step.as_ref(),
+                CuExecutionUnit::Loop(_) => unreachable!(),
+            })
+            .collect();
+        let order: Vec<String> = steps.iter().map(|step| step.node.get_id()).collect();
+        assert_eq!(
+            order,
+            vec!["imu", "estimator", "actuator", "telemetry", "thumbnails"]
+        );
+        // The chain inherits the priority of the actuator.
+        assert_eq!(steps[0].priority, TaskPriority::Critical);
+        assert_eq!(steps[4].priority, TaskPriority::BestEffort);
+        // The copper list follows the order of execution.
+        let outputs: Vec<u32> = steps
+            .iter()
+            .map(|step| step.output_msg_index_type.as_ref().unwrap().0)
+            .collect();
+        assert_eq!(outputs, vec![0, 1, 2, 3, 4]);
+        assert_eq!(steps[1].input_msg_indices_types[0].0, 0);
+        assert_eq!(steps[4].input_msg_indices_types[0].0, 3);
+    }
+
+    #[test]
+    fn test_loop_rate_catch_up() {
+        // 100Hz, so a 10ms period.
+        let mut limiter = LoopRateLimiter::new(100.0, OverrunPolicy::CatchUp).unwrap();
+        let ms = |ms: u64| CuDuration(ms * 1_000_000);
+        assert_eq!(limiter.end_of_cycle(ms(0)), ms(10)); // anchors the schedule: starts at 10
+        assert_eq!(limiter.end_of_cycle(ms(13)), ms(7)); // starts at 20
+        assert_eq!(limiter.end_of_cycle(ms(45)), ms(0)); // should have started at 30
+        assert_eq!(limiter.end_of_cycle(ms(47)), ms(0)); // should have started at 40
+        assert_eq!(limiter.end_of_cycle(ms(49)), ms(1)); // back on schedule
+        assert_eq!(limiter.stats.cycles, 5);
+        assert_eq!(limiter.stats.overruns, 2);
+        assert_eq!(limiter.stats.max_overrun, ms(15));
+        assert_eq!(limiter.stats.skipped_cycles, 0);
+        // Due at 60, the current cycle is late after it.
+        assert!(!limiter.is_late(ms(55)));
+        assert!(limiter.is_late(ms(61)));
+    }
+
+    #[test]
+    fn test_loop_rate_skip() {
+        let mut limiter = LoopRateLimiter::new(100.0, OverrunPolicy::Skip).unwrap();
+        let ms = |ms: u64| CuDuration(ms * 1_000_000);
+        assert_eq!(limiter.end_of_cycle(ms(0)), ms(10));
+        assert_eq!(limiter.end_of_cycle(ms(13)), ms(7)); // starts at 20
+        assert_eq!(limiter.end_of_cycle(ms(45)), ms(5)); // 30 and 40 are dropped, starts at 50
+        assert_eq!(limiter.end_of_cycle(ms(52)), ms(8)); // starts at 60
+        assert_eq!(limiter.stats.overruns, 1);
+        assert_eq!(limiter.stats.skipped_cycles, 2);
+
+        assert!(LoopRateLimiter::new(0.0, OverrunPolicy::Skip).is_err());
+    }
+
+    #[test]
+    fn test_idle_strategies() {
+        let wait = std::time::Duration::from_millis(2);
+        for strategy in [IdleStrategy::Sleep, IdleStrategy::Yield, IdleStrategy::Spin] {
+            let start = std::time::Instant::now();
+            idle(strategy, wait);
+            assert!(start.elapsed() >= wait, "{strategy:?} woke up early");
+        }
+    }
 }
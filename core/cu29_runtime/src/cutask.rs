@@ -1,16 +1,17 @@
 //! This module contains all the main definition of the traits you need to implement
 //! or interact with to create a Copper task.
 
-use crate::config::ComponentConfig;
+use crate::config::{ComponentConfig, ConfigKey};
 use bincode::de::Decoder;
 use bincode::de::{BorrowDecoder, Decode};
+use bincode::enc::write::SizeWriter;
 use bincode::enc::Encode;
-use bincode::enc::Encoder;
+use bincode::enc::{Encoder, EncoderImpl};
 use bincode::error::{DecodeError, EncodeError};
 use bincode::BorrowDecode;
 use compact_str::{CompactString, ToCompactString};
-use cu29_clock::{PartialCuTimeRange, RobotClock, Tov};
-use cu29_traits::CuResult;
+use cu29_clock::{CuDuration, CuTime, PartialCuTimeRange, RobotClock, Tov};
+use cu29_traits::{CuError, CuResult, CuWireFormat};
 use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
@@ -23,16 +24,115 @@ pub trait CuMsgPack<'cl> {}
 // Also anything that follows this contract can be a payload (blanket implementation)
 impl<T: Default + Debug + Clone + Encode + Decode<()> + Sized> CuMsgPayload for T {}
 
+/// One input of a task: a message, or an optional message if the graph may leave it unconnected.
+pub trait CuInput<'cl> {}
+
+impl<'cl, T: CuMsgPayload> CuInput<'cl> for &'cl CuMsg<T> {}
+impl<'cl, T: CuMsgPayload> CuInput<'cl> for Option<&'cl CuMsg<T>> {}
+impl<'cl, T: CuMsgPayload> CuInput<'cl> for CuKeyedMsgs<'cl, T> {}
+
+/// Converts a connected message to the input the task declares, this is called by the generated code.
+pub trait IntoCuInput<I> {
+    fn into_cu_input(self) -> I;
+}
+
+impl<'cl, T: CuMsgPayload> IntoCuInput<&'cl CuMsg<T>> for &'cl CuMsg<T> {
+    fn into_cu_input(self) -> &'cl CuMsg<T> {
+        self
+    }
+}
+
+impl<'cl, T: CuMsgPayload> IntoCuInput<Option<&'cl CuMsg<T>>> for &'cl CuMsg<T> {
+    fn into_cu_input(self) -> Option<&'cl CuMsg<T>> {
+        Some(self)
+    }
+}
+
+/// Helpers for the optional inputs.
+pub trait CuOptionalInput<'cl, T: CuMsgPayload> {
+    /// The payload of the input, or `default` if it is unconnected or empty.
+    /// With a constant, the default is set at compile time:
+    ///
+    /// ```ignore
+    /// const NO_SETPOINT: f32 = 0.0;
+    /// let setpoint = setpoint.payload_or(&NO_SETPOINT);
+    /// ```
+    fn payload_or(self, default: &'cl T) -> &'cl T;
+}
+
+impl<'cl, T: CuMsgPayload> CuOptionalInput<'cl, T> for Option<&'cl CuMsg<T>> {
+    fn payload_or(self, default: &'cl T) -> &'cl T {
+        self.and_then(CuMsg::payload).unwrap_or(default)
+    }
+}
+
+/// The input merging the keyed connections of a message type into a task: a small map view of
+/// their messages by key, in the order of the connections. For example the 4 wheel encoders of a
+/// robot feeding its odometry:
+///
+/// ```ron
+/// cnx: [
+///     (src: "encoder_fl", dst: "odometry", msg: "Ticks", key: Some("front_left")),
+///     (src: "encoder_fr", dst: "odometry", msg: "Ticks", key: Some("front_right")),
+///     (src: "encoder_rl", dst: "odometry", msg: "Ticks", key: Some("rear_left")),
+///     (src: "encoder_rr", dst: "odometry", msg: "Ticks", key: Some("rear_right")),
+/// ]
+/// ```
+///
+/// The odometry task then has a single input `CuKeyedMsgs<'cl, Ticks>` where
+/// `input.payload("front_left")` is the last reading of the front left wheel.
+#[derive(Debug, Clone, Copy)]
+pub struct CuKeyedMsgs<'cl, T: CuMsgPayload> {
+    entries: &'cl [(&'static str, &'cl CuMsg<T>)],
+}
+
+impl<'cl, T: CuMsgPayload> CuKeyedMsgs<'cl, T> {
+    pub fn new(entries: &'cl [(&'static str, &'cl CuMsg<T>)]) -> Self {
+        Self { entries }
+    }
+
+    /// The message of the connection with this key.
+    pub fn get(&self, key: &str) -> Option<&'cl CuMsg<T>> {
+        self.entries
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, msg)| *msg)
+    }
+
+    /// The payload of the message with this key, None if there is no such key or if it is empty.
+    pub fn payload(&self, key: &str) -> Option<&'cl T> {
+        self.get(key).and_then(CuMsg::payload)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> + 'cl {
+        self.entries.iter().map(|(key, _)| *key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &'cl CuMsg<T>)> + 'cl {
+        self.entries.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 macro_rules! impl_cu_msg_pack {
     ($(($($ty:ident),*)),*) => {
         $(
-            impl<'cl, $($ty: CuMsgPayload + 'cl),*> CuMsgPack<'cl> for ( $( &'cl CuMsg<$ty>, )* ) {}
+            impl<'cl, $($ty: CuInput<'cl>),*> CuMsgPack<'cl> for ( $( $ty, )* ) {}
         )*
     };
 }
 
 impl<'cl, T: CuMsgPayload> CuMsgPack<'cl> for (&'cl CuMsg<T>,) {}
 impl<'cl, T: CuMsgPayload> CuMsgPack<'cl> for &'cl CuMsg<T> {}
+impl<'cl, T: CuMsgPayload> CuMsgPack<'cl> for Option<&'cl CuMsg<T>> {}
+impl<'cl, T: CuMsgPayload> CuMsgPack<'cl> for CuKeyedMsgs<'cl, T> {}
 impl<'cl, T: CuMsgPayload> CuMsgPack<'cl> for (&'cl mut CuMsg<T>,) {}
 impl<'cl, T: CuMsgPayload> CuMsgPack<'cl> for &'cl mut CuMsg<T> {}
 impl CuMsgPack<'_> for () {}
@@ -42,17 +142,88 @@ impl_cu_msg_pack! {
     (T1, T2), (T1, T2, T3), (T1, T2, T3, T4), (T1, T2, T3, T4, T5) // TODO: continue if necessary
 }
 
+/// The payload type of one input or output of a task.
+pub trait CuPayloadType {
+    type Payload;
+}
+
+impl<T: CuMsgPayload> CuPayloadType for &CuMsg<T> {
+    type Payload = T;
+}
+
+impl<T: CuMsgPayload> CuPayloadType for &mut CuMsg<T> {
+    type Payload = T;
+}
+
+impl<T: CuMsgPayload> CuPayloadType for Option<&CuMsg<T>> {
+    type Payload = T;
+}
+
+impl<T: CuMsgPayload> CuPayloadType for CuKeyedMsgs<'_, T> {
+    type Payload = T;
+}
+
+/// The payload type of the input or output at this position of the Input or Output of a task,
+/// the code generated by copper_runtime checks with it that the tasks declare the message types
+/// of their connections.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no message at this position, the task has fewer inputs or outputs than its connections in the configuration"
+)]
+pub trait CuPayloadAt<const POSITION: usize> {
+    type Payload;
+}
+
+impl<T: CuPayloadType> CuPayloadAt<0> for T {
+    type Payload = T::Payload;
+}
+
+macro_rules! impl_cu_payload_at {
+    ($(($($ty:ident),*) $position:literal $at:ident);* $(;)?) => {
+        $(
+            impl<$($ty: CuPayloadType),*> CuPayloadAt<$position> for ( $( $ty, )* ) {
+                type Payload = $at::Payload;
+            }
+        )*
+    };
+}
+
+impl_cu_payload_at! {
+    (T1) 0 T1;
+    (T1, T2) 0 T1; (T1, T2) 1 T2;
+    (T1, T2, T3) 0 T1; (T1, T2, T3) 1 T2; (T1, T2, T3) 2 T3;
+    (T1, T2, T3, T4) 0 T1; (T1, T2, T3, T4) 1 T2; (T1, T2, T3, T4) 2 T3; (T1, T2, T3, T4) 3 T4;
+    (T1, T2, T3, T4, T5) 0 T1; (T1, T2, T3, T4, T5) 1 T2; (T1, T2, T3, T4, T5) 2 T3;
+    (T1, T2, T3, T4, T5) 3 T4; (T1, T2, T3, T4, T5) 4 T5;
+}
+
 // A convenience macro to get from a payload or a list of payloads to a proper CuMsg or CuMsgPack
 // declaration for your tasks used for input messages.
+// An input prefixed with `?` is optional: it is an `Option<&CuMsg<T>>` and the graph can leave it
+// unconnected by listing its position in the `unconnected_inputs` of the node, e.g.
+// `input_msg!('cl, f32, ?f32)` for a PID with an optional setpoint.
 #[macro_export]
 macro_rules! input_msg {
+    // Munches the inputs one by one so the optional ones can be mixed with the others.
+    (@tuple $lifetime:lifetime, [$($done:tt)*]) => {
+        ( $($done)* )
+    };
+    (@tuple $lifetime:lifetime, [$($done:tt)*] ? $ty:ty $(, $($rest:tt)*)?) => {
+        $crate::input_msg!(@tuple $lifetime, [$($done)* Option<&$lifetime CuMsg<$ty>>,] $($($rest)*)?)
+    };
+    (@tuple $lifetime:lifetime, [$($done:tt)*] $ty:ty $(, $($rest:tt)*)?) => {
+        $crate::input_msg!(@tuple $lifetime, [$($done)* &$lifetime CuMsg<$ty>,] $($($rest)*)?)
+    };
+    ($lifetime:lifetime, ? $ty:ty) => {
+        Option<&$lifetime CuMsg<$ty>>
+    };
+    ($lifetime:lifetime, ? $($rest:tt)*) => {
+        $crate::input_msg!(@tuple $lifetime, [] ? $($rest)*)
+    };
     ($lifetime:lifetime, $ty:ty) => {
         &$lifetime CuMsg<$ty>
     };
-    ($lifetime:lifetime, $($ty:ty),*) => {
-        (
-            $( &$lifetime CuMsg<$ty>, )*
-        )
+    ($lifetime:lifetime, $($rest:tt)*) => {
+        $crate::input_msg!(@tuple $lifetime, [] $($rest)*)
     };
 }
 
@@ -94,6 +265,58 @@ impl<'de, Context> BorrowDecode<'de, Context> for CuCompactString {
     }
 }
 
+/// How much a message can be trusted, beyond the presence of its payload.
+/// The variants are ordered from the best to the worst.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    bincode::Encode,
+    bincode::Decode,
+    Serialize,
+    Deserialize,
+)]
+pub enum CuMsgValidity {
+    /// Produced from up to date data.
+    #[default]
+    Fresh,
+    /// Produced from old data, for example a source repeating its last reading.
+    Stale,
+    /// The producer is not ready yet, for example an estimator still converging.
+    NotReady,
+    /// The producer failed with this code, 0 is used by the runtime for a failed process().
+    Error(u32),
+}
+
+impl CuMsgValidity {
+    /// The validity the runtime gives to the output of a task before its process(): the worst
+    /// validity of its inputs.
+    pub fn worst(validities: impl IntoIterator<Item = CuMsgValidity>) -> CuMsgValidity {
+        validities.into_iter().max().unwrap_or_default()
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        *self == CuMsgValidity::Fresh
+    }
+}
+
+impl Display for CuMsgValidity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CuMsgValidity::Fresh => write!(f, "fresh"),
+            CuMsgValidity::Stale => write!(f, "stale"),
+            CuMsgValidity::NotReady => write!(f, "not ready"),
+            CuMsgValidity::Error(code) => write!(f, "error {code}"),
+        }
+    }
+}
+
 /// CuMsgMetadata is a structure that contains metadata common to all CuMsgs.
 #[derive(Debug, Clone, bincode::Encode, bincode::Decode, Serialize, Deserialize)]
 pub struct CuMsgMetadata {
@@ -105,20 +328,54 @@ pub struct CuMsgMetadata {
     /// A small string for real time feedback purposes.
     /// This is useful for to display on the field when the tasks are operating correctly.
     pub status_txt: CuCompactString,
+    /// Sequence number of the message on its connections, set by the runtime.
+    /// The connections fed by an output share its slot in the copperlist, hence its counter: it
+    /// increases by one at every message of the slot, empty ones included, so a jump means messages
+    /// were lost on the way (by the logger, a tap, a decimated connection...).
+    pub seq: u64,
+    /// The copperlist the message was produced in, set by the runtime.
+    /// It correlates the messages of different connections produced in the same cycle.
+    pub culist_id: u32,
+    /// The runtime sets it to the worst validity of the inputs before process(), the task can
+    /// then change it. A failed process() ignored by the monitor gives `Error(0)`.
+    pub validity: CuMsgValidity,
+    /// The outputs the payload is routed to in this cycle, bit i for the i-th connection from the
+    /// task in the configuration, all of them if None. See [CuMsg::route_to].
+    pub routes: Option<u64>,
 }
 
 impl CuMsgMetadata {
+    /// Bound of the encoded size of the metadata of a message whose status text fits in
+    /// COMPACT_STRING_CAPACITY bytes: the times, the status, the sequence number, the copperlist
+    /// id, the validity and the routes at their widest varint encoding.
+    pub const MAX_ENCODED_SIZE: usize =
+        2 * 9 + (1 + 2 * 9) + (1 + COMPACT_STRING_CAPACITY) + 9 + 5 + (1 + 5) + (1 + 9);
+
     pub fn set_status(&mut self, status: impl ToCompactString) {
         self.status_txt = CuCompactString(status.to_compact_string());
     }
+
+    /// Stamps a message produced in the copperlist `culist_id`, `next_seq` is the sequence counter
+    /// of its connections. This is called by the generated code.
+    pub fn stamp(&mut self, culist_id: u32, next_seq: &mut u64) {
+        self.seq = *next_seq;
+        self.culist_id = culist_id;
+        *next_seq += 1;
+    }
+
+    /// Number of messages lost between a previously received message of the same connection
+    /// and this one.
+    pub fn missed_since(&self, previous: &CuMsgMetadata) -> u64 {
+        self.seq.saturating_sub(previous.seq + 1)
+    }
 }
 
 impl Display for CuMsgMetadata {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "process_time start: {}, process_time end: {}",
-            self.process_time.start, self.process_time.end
+            "seq: {}, culist: {}, process_time start: {}, process_time end: {}",
+            self.seq, self.culist_id, self.process_time.start, self.process_time.end
         )
     }
 }
@@ -142,6 +399,10 @@ impl Default for CuMsgMetadata {
             process_time: PartialCuTimeRange::default(),
             tov: Tov::default(),
             status_txt: CuCompactString(CompactString::with_capacity(COMPACT_STRING_CAPACITY)),
+            seq: 0,
+            culist_id: 0,
+            validity: CuMsgValidity::Fresh,
+            routes: None,
         }
     }
 }
@@ -171,6 +432,73 @@ where
     pub fn payload_mut(&mut self) -> &mut Option<T> {
         &mut self.payload
     }
+
+    pub fn validity(&self) -> CuMsgValidity {
+        self.metadata.validity
+    }
+
+    pub fn set_validity(&mut self, validity: CuMsgValidity) {
+        self.metadata.validity = validity;
+    }
+
+    /// How old the data of the message is at `now`: the time elapsed since its time of validity,
+    /// the end of it for a range. None if the producer didn't set a time of validity.
+    pub fn age(&self, now: CuTime) -> Option<CuDuration> {
+        let tov = match self.metadata.tov {
+            Tov::None => return None,
+            Tov::Time(time) => time,
+            Tov::Range(range) => range.end,
+        };
+        Some(CuDuration(now.0.saturating_sub(tov.0)))
+    }
+
+    /// If the data of the message is older than `max_age` at `now`, for a task to refuse to act on
+    /// old sensor data. A message without time of validity has no age and is always stale.
+    pub fn is_stale(&self, now: CuTime, max_age: CuDuration) -> bool {
+        self.age(now).is_none_or(|age| age > max_age)
+    }
+
+    /// Routes the payload of this cycle to these outputs only, by the position of their
+    /// connections from the task in the configuration (at most 64). The task needs `routed: true`
+    /// in the configuration: the other destinations get an empty message, and the tasks none of
+    /// whose inputs get a payload are skipped.
+    pub fn route_to(&mut self, outputs: &[usize]) {
+        let routes = outputs.iter().fold(0u64, |routes, output| {
+            debug_assert!(*output < 64, "A task routes to 64 outputs at most");
+            routes | 1 << output
+        });
+        self.metadata.routes = Some(routes);
+    }
+
+    /// Routes the payload to all the outputs, this is the default at every cycle.
+    pub fn route_to_all(&mut self) {
+        self.metadata.routes = None;
+    }
+
+    /// If the payload goes to the output at this position, see [CuMsg::route_to].
+    pub fn is_routed_to(&self, output: usize) -> bool {
+        !matches!(self.metadata.routes, Some(routes) if routes & (1 << output) == 0)
+    }
+
+    /// Fails if the payload encodes in more than the `max_size` bytes declared on the connections
+    /// of the message, this is called by the generated code.
+    pub fn check_max_size(&self, max_size: u32) -> CuResult<()> {
+        let size = encoded_size(&self.payload);
+        if size > max_size as usize {
+            return Err(CuError::from(format!(
+                "The payload encodes in {size} bytes, more than the max_size of {max_size} bytes of its connections."
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Size of the bincode encoding of the value, without encoding it anywhere.
+pub fn encoded_size<E: Encode>(value: &E) -> usize {
+    let mut encoder = EncoderImpl::new(SizeWriter::default(), CuWireFormat::CONFIG);
+    // Only a failing writer makes the encoding fail and this one never does.
+    let _ = value.encode(&mut encoder);
+    encoder.into_writer().bytes_written
 }
 
 /// The internal state of a task needs to be serializable
@@ -198,6 +526,10 @@ pub trait Freezable {
 pub trait CuSrcTask<'cl>: Freezable {
     type Output: CuMsgPack<'cl>;
 
+    /// The config keys of the task, usually the SCHEMA of its CuConfigStruct.
+    /// When declared, the runtime rejects a config with unknown or mistyped keys before calling new.
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = None;
+
     /// Here you need to initialize everything your task will need for the duration of its lifetime.
     /// The config allows you to access the configuration of the task.
     fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
@@ -239,6 +571,10 @@ pub trait CuTask<'cl>: Freezable {
     type Input: CuMsgPack<'cl>;
     type Output: CuMsgPack<'cl>;
 
+    /// The config keys of the task, usually the SCHEMA of its CuConfigStruct.
+    /// When declared, the runtime rejects a config with unknown or mistyped keys before calling new.
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = None;
+
     /// Here you need to initialize everything your task will need for the duration of its lifetime.
     /// The config allows you to access the configuration of the task.
     fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
@@ -284,6 +620,10 @@ pub trait CuTask<'cl>: Freezable {
 pub trait CuSinkTask<'cl>: Freezable {
     type Input: CuMsgPack<'cl>;
 
+    /// The config keys of the task, usually the SCHEMA of its CuConfigStruct.
+    /// When declared, the runtime rejects a config with unknown or mistyped keys before calling new.
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = None;
+
     /// Here you need to initialize everything your task will need for the duration of its lifetime.
     /// The config allows you to access the configuration of the task.
     fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
@@ -307,6 +647,13 @@ pub trait CuSinkTask<'cl>: Freezable {
     /// Use preprocess to prepare the task to make this method as short as possible.
     fn process(&mut self, _clock: &RobotClock, input: Self::Input) -> CuResult<()>;
 
+    /// Called by the runtime instead of "process" on every cycle while the emergency stop is engaged
+    /// (see [crate::estop]). Actuators must bring the hardware to a safe state here (motors
+    /// disabled, brakes on...). It is called repeatedly so it needs to be idempotent.
+    fn safe_state(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        Ok(())
+    }
+
     /// This is a method called by the runtime after "process". It is best effort a chance for
     /// the task to update some state after process is out of the way.
     /// It can be use for example to maintain statistics etc. that are not time-critical for the robot.
@@ -320,10 +667,18 @@ pub trait CuSinkTask<'cl>: Freezable {
     }
 }
 
+/// The stub replacing a task disabled in the configuration (`enabled: false` on its node): the
+/// type of the task is not even compiled in, nothing is created nor run for it and the runtime
+/// gives empty, not ready messages to the destinations of its output.
+pub struct CuStubTask;
+
+impl Freezable for CuStubTask {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bincode::{config, decode_from_slice, encode_to_vec};
+    use cu29_clock::{CuDuration, CuTimeRange};
 
     #[test]
     fn test_cucompactstr_encode_decode() {
@@ -334,4 +689,97 @@ mod tests {
             decode_from_slice(&encoded, config).expect("Decoding failed");
         assert_eq!(cstr.0, decoded.0);
     }
+
+    #[test]
+    fn test_msg_stamp() {
+        let mut next_seq = 0;
+        let mut first = CuMsgMetadata::default();
+        first.stamp(10, &mut next_seq);
+        let mut second = CuMsgMetadata::default();
+        second.stamp(11, &mut next_seq);
+        assert_eq!((first.seq, first.culist_id), (0, 10));
+        assert_eq!((second.seq, second.culist_id), (1, 11));
+        assert_eq!(second.missed_since(&first), 0);
+
+        second.stamp(12, &mut next_seq);
+        second.stamp(13, &mut next_seq);
+        assert_eq!(second.missed_since(&first), 2);
+        // The metadata is logged with the message.
+        let encoded = encode_to_vec(&second, config::standard()).unwrap();
+        let (decoded, _): (CuMsgMetadata, usize) =
+            decode_from_slice(&encoded, config::standard()).unwrap();
+        assert_eq!((decoded.seq, decoded.culist_id), (3, 13));
+    }
+
+    #[test]
+    fn test_max_encoded_size() {
+        let mut msg = CuMsg::new(Some(vec![0u8; 10]));
+        msg.metadata.process_time.start = CuDuration(u64::MAX - 1).into();
+        msg.metadata.process_time.end = CuDuration(u64::MAX - 1).into();
+        msg.metadata.tov = Tov::Range(CuTimeRange {
+            start: CuDuration(u64::MAX),
+            end: CuDuration(u64::MAX),
+        });
+        msg.metadata.set_status("x".repeat(COMPACT_STRING_CAPACITY));
+        msg.metadata.seq = u64::MAX;
+        msg.metadata.culist_id = u32::MAX;
+        msg.metadata.validity = CuMsgValidity::Error(u32::MAX);
+        msg.route_to(&[63]);
+        assert_eq!(encoded_size(&msg.metadata), CuMsgMetadata::MAX_ENCODED_SIZE);
+
+        // The length and the bytes, behind the tag of the option.
+        assert!(msg.check_max_size(12).is_ok());
+        assert!(msg.check_max_size(11).is_err());
+    }
+
+    #[test]
+    fn test_msg_validity() {
+        use CuMsgValidity::*;
+        assert_eq!(CuMsgValidity::worst([]), Fresh);
+        assert_eq!(CuMsgValidity::worst([Fresh, Stale]), Stale);
+        assert_eq!(CuMsgValidity::worst([NotReady, Error(3), Stale]), Error(3));
+        assert_eq!(CuMsgValidity::worst([Error(1), Error(3)]), Error(3));
+
+        let mut msg = CuMsg::new(Some(1u32));
+        assert!(msg.validity().is_fresh());
+        msg.set_validity(NotReady);
+        assert_eq!(msg.metadata.validity, NotReady);
+        assert_eq!(Error(7).to_string(), "error 7");
+    }
+
+    #[test]
+    fn test_msg_age() {
+        let mut msg = CuMsg::new(Some(1u32));
+        let now = CuDuration::from_millis(100);
+        assert_eq!(msg.age(now), None);
+        assert!(msg.is_stale(now, CuDuration::from_millis(1000)));
+
+        msg.metadata.tov = Tov::Time(CuDuration::from_millis(70));
+        assert_eq!(msg.age(now), Some(CuDuration::from_millis(30)));
+        assert!(!msg.is_stale(now, CuDuration::from_millis(30)));
+        assert!(msg.is_stale(now, CuDuration::from_millis(20)));
+
+        msg.metadata.tov = Tov::Range(CuTimeRange {
+            start: CuDuration::from_millis(50),
+            end: CuDuration::from_millis(90),
+        });
+        assert_eq!(msg.age(now), Some(CuDuration::from_millis(10)));
+        // A time of validity ahead of the clock is as fresh as it gets.
+        msg.metadata.tov = Tov::Time(CuDuration::from_millis(120));
+        assert_eq!(msg.age(now), Some(CuDuration(0)));
+    }
+
+    #[test]
+    fn test_keyed_msgs() {
+        let left = CuMsg::new(Some(10u32));
+        let right = CuMsg::new(None);
+        let entries = [("left", &left), ("right", &right)];
+        let keyed = CuKeyedMsgs::new(&entries);
+        assert_eq!(keyed.len(), 2);
+        assert_eq!(keyed.payload("left"), Some(&10));
+        assert!(keyed.get("right").is_some());
+        assert_eq!(keyed.payload("right"), None);
+        assert!(keyed.get("middle").is_none());
+        assert_eq!(keyed.keys().collect::<Vec<_>>(), vec!["left", "right"]);
+    }
 }
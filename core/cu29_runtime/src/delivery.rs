@@ -0,0 +1,223 @@
+//! Delivery of the messages of a connection to its destination, following its [CnxPolicy].
+//! An output feeding several tasks lives once in the copperlist, each destination gets it through
+//! its own connection with its own policy and counters:
+//!
+//! ```ron
+//! cnx: [
+//!     (src: "lidar", dst: "slam", msg: "Scan"),
+//!     // The planner runs on the last scan even when the lidar has nothing new.
+//!     (src: "lidar", dst: "planner", msg: "Scan", policy: Some(Latched)),
+//!     // The display only needs one scan out of 10.
+//!     (src: "lidar", dst: "display", msg: "Scan", policy: Some(Decimate(10))),
+//! ]
+//! ```
+//!
+//! The functions are called by the generated code before the process() of the destination.
+
+use crate::config::CnxPolicy;
+use crate::cutask::{CuMsg, CuMsgPayload};
+use crate::introspection::CnxStats;
+use cu29_clock::{CuDuration, CuTime};
+
+/// [CnxPolicy::Latest]: the message of the copperlist itself.
+pub fn latest<'m, T: CuMsgPayload>(msg: &'m CuMsg<T>, stats: &mut CnxStats) -> &'m CuMsg<T> {
+    stats.record(msg);
+    msg
+}
+
+/// [CnxPolicy::Latched]: `buffer` keeps a copy of the last message with a payload.
+pub fn latched<'m, T: CuMsgPayload>(
+    msg: &'m CuMsg<T>,
+    buffer: &'m mut CuMsg<T>,
+    stats: &mut CnxStats,
+) -> &'m CuMsg<T> {
+    if msg.payload().is_some() {
+        buffer.clone_from(msg);
+        return latest(msg, stats);
+    }
+    if buffer.payload().is_none() {
+        return latest(msg, stats);
+    }
+    stats.latched += 1;
+    stats.record(buffer);
+    buffer
+}
+
+/// [CnxPolicy::Decimate]: one message out of `n` following their sequence numbers, `buffer` is
+/// the empty message given instead of the others.
+pub fn decimate<'m, T: CuMsgPayload>(
+    n: u32,
+    msg: &'m CuMsg<T>,
+    buffer: &'m mut CuMsg<T>,
+    stats: &mut CnxStats,
+) -> &'m CuMsg<T> {
+    if msg.metadata.seq.is_multiple_of(u64::from(n.max(1))) {
+        return latest(msg, stats);
+    }
+    buffer.metadata = msg.metadata.clone();
+    buffer.clear_payload();
+    stats.dropped += 1;
+    buffer
+}
+
+/// A connection disabled by the run profile (see the profile module): `buffer` is the empty
+/// message given instead.
+pub fn disabled<'m, T: CuMsgPayload>(
+    msg: &CuMsg<T>,
+    buffer: &'m mut CuMsg<T>,
+    stats: &mut CnxStats,
+) -> &'m CuMsg<T> {
+    buffer.metadata = msg.metadata.clone();
+    buffer.clear_payload();
+    stats.dropped += 1;
+    buffer
+}
+
+/// A connection the source didn't route its payload to in this cycle (see
+/// [CuMsg::route_to]): `buffer` is the empty message given instead.
+pub fn unrouted<'m, T: CuMsgPayload>(
+    msg: &CuMsg<T>,
+    buffer: &'m mut CuMsg<T>,
+    stats: &mut CnxStats,
+) -> &'m CuMsg<T> {
+    buffer.metadata = msg.metadata.clone();
+    buffer.clear_payload();
+    stats.record(buffer);
+    buffer
+}
+
+/// Counts the delivered payload as stale if it is older than `max_age`, for the connections
+/// declaring a `max_age_ms`. The task itself decides what to do with it, see [CuMsg::is_stale].
+pub fn check_age<'m, T: CuMsgPayload>(
+    msg: &'m CuMsg<T>,
+    now: CuTime,
+    max_age: CuDuration,
+    stats: &mut CnxStats,
+) -> &'m CuMsg<T> {
+    if msg.payload().is_some() && msg.is_stale(now, max_age) {
+        stats.stale += 1;
+    }
+    msg
+}
+
+/// The policies needing a buffer per connection.
+pub fn needs_buffer(policy: CnxPolicy) -> bool {
+    policy != CnxPolicy::Latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(seq: u64, payload: Option<u32>) -> CuMsg<u32> {
+        let mut msg = CuMsg::new(payload);
+        msg.metadata.seq = seq;
+        msg
+    }
+
+    #[test]
+    fn test_latched() {
+        let mut stats = CnxStats::default();
+        let mut buffer = CuMsg::<u32>::default();
+        assert_eq!(
+            latched(&msg(0, None), &mut buffer, &mut stats).payload(),
+            None
+        );
+        assert_eq!(
+            latched(&msg(1, Some(7)), &mut buffer, &mut stats).payload(),
+            Some(&7)
+        );
+        assert_eq!(
+            latched(&msg(2, None), &mut buffer, &mut stats).payload(),
+            Some(&7)
+        );
+        assert_eq!(
+            stats,
+            CnxStats {
+                delivered: 2,
+                empty: 1,
+                latched: 1,
+                dropped: 0,
+                stale: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decimate() {
+        let mut stats = CnxStats::default();
+        let mut buffer = CuMsg::<u32>::default();
+        let received: Vec<Option<u32>> = (0..7)
+            .map(|seq| {
+                decimate(3, &msg(seq, Some(seq as u32)), &mut buffer, &mut stats)
+                    .payload()
+                    .copied()
+            })
+            .collect();
+        assert_eq!(
+            received,
+            vec![Some(0), None, None, Some(3), None, None, Some(6)]
+        );
+        assert_eq!(stats.delivered, 3);
+        assert_eq!(stats.dropped, 4);
+        // The empty message still carries the metadata of the original.
+        assert_eq!(buffer.metadata.seq, 5);
+    }
+
+    #[test]
+    fn test_check_age() {
+        let mut stats = CnxStats::default();
+        let mut buffer = CuMsg::<u32>::default();
+        let max_age = CuDuration::from_millis(50);
+        let mut fresh = msg(0, Some(1));
+        fresh.metadata.tov = CuDuration::from_millis(10).into();
+        // A latched payload ages until the source gives a new one.
+        let stale: Vec<bool> = [20u64, 60, 80]
+            .into_iter()
+            .zip([&fresh, &msg(1, None), &msg(2, None)])
+            .map(|(now, msg)| {
+                let delivered = latched(msg, &mut buffer, &mut stats);
+                check_age(delivered, CuDuration::from_millis(now), max_age, &mut stats)
+                    .is_stale(CuDuration::from_millis(now), max_age)
+            })
+            .collect();
+        assert_eq!(stale, vec![false, false, true]);
+        assert_eq!(stats.stale, 1);
+
+        // The empty messages are not counted.
+        check_age(
+            &msg(3, None),
+            CuDuration::from_millis(80),
+            max_age,
+            &mut stats,
+        );
+        assert_eq!(stats.stale, 1);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut stats = CnxStats::default();
+        let mut buffer = CuMsg::<u32>::default();
+        let delivered = disabled(&msg(4, Some(4)), &mut buffer, &mut stats);
+        assert_eq!(delivered.payload(), None);
+        assert_eq!(delivered.metadata.seq, 4);
+        assert_eq!(stats.dropped, 1);
+    }
+
+    #[test]
+    fn test_unrouted() {
+        let mut stats = CnxStats::default();
+        let mut buffer = CuMsg::<u32>::default();
+        let mut routed = msg(2, Some(2));
+        routed.route_to(&[1]);
+        assert!(!routed.is_routed_to(0));
+        assert!(routed.is_routed_to(1));
+        let delivered = unrouted(&routed, &mut buffer, &mut stats);
+        assert_eq!(delivered.payload(), None);
+        assert_eq!(delivered.metadata.seq, 2);
+        // The source sent nothing to this destination, it is not a drop of the connection.
+        assert_eq!((stats.empty, stats.dropped), (1, 0));
+        routed.route_to_all();
+        assert!(routed.is_routed_to(0));
+    }
+}
@@ -0,0 +1,249 @@
+//! Emergency stop facility.
+//! The e-stop is a process wide latch: it can be engaged from anywhere (a task reading a GPIO,
+//! a network command handler, a UI...) and, as soon as it is engaged, the runtime stops calling
+//! `process` on the sinks and calls their `safe_state` hook instead on every cycle.
+//! Normal actuation only resumes after an explicit reset.
+//!
+//! A remote e-stop button or supervisor can drive the latch over the network with a
+//! [NetworkEStop]. It accepts text datagrams: `engage <reason>`, `reset` and `heartbeat`, and can
+//! engage the latch by itself when the heartbeats stop coming:
+//!
+//! ```rust,ignore
+//! let _estop = NetworkEStop::listen("0.0.0.0:7402", Some(Duration::from_millis(200)))?;
+//! ```
+
+use crate::log::*;
+use cu29_traits::{CuError, CuResult};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The latch behind the emergency stop, the runtime checks the process wide one through the free
+/// functions of this module.
+pub struct EStopLatch {
+    engaged: AtomicBool,
+    engagements: AtomicU64,
+    reason: Mutex<String>,
+}
+
+static ESTOP: EStopLatch = EStopLatch::new();
+
+impl EStopLatch {
+    pub const fn new() -> Self {
+        Self {
+            engaged: AtomicBool::new(false),
+            engagements: AtomicU64::new(0),
+            reason: Mutex::new(String::new()),
+        }
+    }
+
+    pub fn engage(&self, reason: &str) {
+        if !self.engaged.swap(true, Ordering::SeqCst) {
+            self.engagements.fetch_add(1, Ordering::SeqCst);
+            *self.reason.lock().unwrap() = reason.to_string();
+            debug!("E-stop engaged: {}", reason);
+        }
+    }
+
+    pub fn reset(&self) {
+        if self.engaged.swap(false, Ordering::SeqCst) {
+            debug!("E-stop reset.");
+        }
+    }
+
+    #[inline]
+    pub fn is_engaged(&self) -> bool {
+        self.engaged.load(Ordering::SeqCst)
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        if self.is_engaged() {
+            Some(self.reason.lock().unwrap().clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn engagement_count(&self) -> u64 {
+        self.engagements.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for EStopLatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Engages the emergency stop.
+/// The sinks will be put in their safe state at the latest on the next cycle.
+pub fn engage(reason: &str) {
+    ESTOP.engage(reason);
+}
+
+/// Releases the emergency stop, the sinks will resume their normal processing on the next cycle.
+pub fn reset() {
+    ESTOP.reset();
+}
+
+/// Checked by the runtime before actuating any sink.
+#[inline]
+pub fn is_engaged() -> bool {
+    ESTOP.is_engaged()
+}
+
+/// Why the emergency stop was engaged the last time.
+pub fn reason() -> Option<String> {
+    ESTOP.reason()
+}
+
+/// How many times the emergency stop has been engaged since the start of the process.
+pub fn engagement_count() -> u64 {
+    ESTOP.engagement_count()
+}
+
+/// Drives the emergency stop from the datagrams received on a UDP socket, see the module
+/// documentation for the commands. The listening thread stops when this is dropped.
+pub struct NetworkEStop {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// How often the listening thread checks if it should stop.
+const NETWORK_ESTOP_TICK: Duration = Duration::from_millis(50);
+
+impl NetworkEStop {
+    /// Listens on this address. With a heartbeat, the e-stop is engaged when nothing is received
+    /// for this long, the first datagram arms it.
+    pub fn listen(addr: impl ToSocketAddrs, heartbeat: Option<Duration>) -> CuResult<Self> {
+        Self::listen_for(&ESTOP, addr, heartbeat)
+    }
+
+    fn listen_for(
+        latch: &'static EStopLatch,
+        addr: impl ToSocketAddrs,
+        heartbeat: Option<Duration>,
+    ) -> CuResult<Self> {
+        let socket = UdpSocket::bind(addr)
+            .map_err(|e| CuError::new_with_cause("Could not bind the e-stop socket", e))?;
+        let tick = heartbeat.map_or(NETWORK_ESTOP_TICK, |h| h.min(NETWORK_ESTOP_TICK));
+        socket
+            .set_read_timeout(Some(tick))
+            .map_err(|e| CuError::new_with_cause("Could not configure the e-stop socket", e))?;
+        let local_addr = socket
+            .local_addr()
+            .map_err(|e| CuError::new_with_cause("Could not configure the e-stop socket", e))?;
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = std::thread::Builder::new()
+            .name("cu29-estop".to_string())
+            .spawn({
+                let running = running.clone();
+                move || serve(latch, socket, heartbeat, &running)
+            })
+            .map_err(|e| CuError::new_with_cause("Could not start the e-stop thread", e))?;
+        Ok(Self {
+            local_addr,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for NetworkEStop {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(latch: &EStopLatch, socket: UdpSocket, heartbeat: Option<Duration>, running: &AtomicBool) {
+    let mut buffer = [0u8; 512];
+    let mut last_received: Option<Instant> = None;
+    while running.load(Ordering::SeqCst) {
+        match socket.recv_from(&mut buffer) {
+            Ok((len, _)) => {
+                last_received = Some(Instant::now());
+                let command = String::from_utf8_lossy(&buffer[..len]);
+                let (verb, argument) = command
+                    .trim()
+                    .split_once(' ')
+                    .unwrap_or((command.trim(), ""));
+                match verb {
+                    "engage" if argument.is_empty() => latch.engage("network e-stop"),
+                    "engage" => latch.engage(argument.trim()),
+                    "reset" => latch.reset(),
+                    // Anything else is a heartbeat: a fault of the sender must not release the stop.
+                    _ => {}
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => {
+                latch.engage("network e-stop socket failure");
+                return;
+            }
+        }
+        if let (Some(heartbeat), Some(last)) = (heartbeat, last_received) {
+            if last.elapsed() > heartbeat {
+                latch.engage("network e-stop heartbeat lost");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engage_and_reset() {
+        // A latch of its own, the process wide one is not touched by the tests.
+        let latch = EStopLatch::new();
+        latch.engage("test");
+        latch.engage("engaged twice");
+        assert!(latch.is_engaged());
+        assert_eq!(latch.reason(), Some("test".to_string()));
+        assert_eq!(latch.engagement_count(), 1);
+        latch.reset();
+        assert!(!latch.is_engaged());
+        assert_eq!(latch.reason(), None);
+    }
+
+    #[test]
+    fn test_network_estop() {
+        static LATCH: EStopLatch = EStopLatch::new();
+        let estop =
+            NetworkEStop::listen_for(&LATCH, "127.0.0.1:0", Some(Duration::from_millis(200)))
+                .unwrap();
+        let button = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send = |command: &str| {
+            button
+                .send_to(command.as_bytes(), estop.local_addr())
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(30));
+        };
+
+        send("heartbeat");
+        assert!(!LATCH.is_engaged());
+        send("engage button pressed");
+        assert_eq!(LATCH.reason(), Some("button pressed".to_string()));
+        send("reset");
+        assert!(!LATCH.is_engaged());
+
+        // The heartbeats stop.
+        std::thread::sleep(Duration::from_millis(450));
+        assert_eq!(
+            LATCH.reason(),
+            Some("network e-stop heartbeat lost".to_string())
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Events are the annotations of the interesting moments of a run (a grasp attempt, a docking, a
+//! near miss...), marked by the tasks so the postprocessing can find them in hours of logs without
+//! replaying everything.
+//!
+//! The runtime writes every event marked during a copperlist in the `Event` sections of the
+//! unified log, tagged with the id of the copperlist, and forwards it to the monitor.
+//! An event can also ask for a flush: the runtime then closes the sections of the unified log
+//! written so far and the monitor (the flight recorder for example) flushes what it buffered, so
+//! the moment is on disk even if the robot crashes right after it.
+//!
+//! ```rust,ignore
+//! mark_event(clock, "grasp_attempt", format!("object: {}", object_id));
+//! mark_event_and_flush(clock, "near_miss", "");
+//! ```
+
+use crate::log::*;
+use bincode::{Decode, Encode};
+use cu29_clock::{CuTime, RobotClock};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+/// An annotation of the log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct CuEvent {
+    pub name: String,
+    pub time: CuTime,
+    /// The copperlist at the end of which the runtime logged the event, the one being processed
+    /// when it was marked from a task.
+    pub culist_id: u32,
+    /// Free form details, for example "object: cup, attempt: 3".
+    pub metadata: String,
+    /// The event asked for the log and the monitor to be flushed.
+    pub flush: bool,
+}
+
+impl Display for CuEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [culist {}] {}", self.time, self.culist_id, self.name)?;
+        if !self.metadata.is_empty() {
+            write!(f, ": {}", self.metadata)?;
+        }
+        Ok(())
+    }
+}
+
+/// The events marked since the end of the last copperlist.
+struct EventQueue {
+    events: Vec<CuEvent>,
+}
+
+impl EventQueue {
+    fn push(&mut self, clock: &RobotClock, name: &str, metadata: String, flush: bool) {
+        self.events.push(CuEvent {
+            name: name.to_string(),
+            time: clock.now(),
+            culist_id: 0,
+            metadata,
+            flush,
+        });
+    }
+
+    fn take(&mut self, culist_id: u32) -> Vec<CuEvent> {
+        let mut events = std::mem::take(&mut self.events);
+        for event in events.iter_mut() {
+            event.culist_id = culist_id;
+        }
+        events
+    }
+}
+
+static PENDING_EVENTS: Mutex<EventQueue> = Mutex::new(EventQueue { events: Vec::new() });
+
+/// Marks an interesting moment, it will be logged at the end of the current copperlist.
+pub fn mark_event(clock: &RobotClock, name: &str, metadata: impl Into<String>) {
+    PENDING_EVENTS
+        .lock()
+        .unwrap()
+        .push(clock, name, metadata.into(), false);
+}
+
+/// Marks an interesting moment and asks for the log and the monitor to be flushed once it has been
+/// logged. A flush closes the current sections of the log, keep it for the rare moments.
+pub fn mark_event_and_flush(clock: &RobotClock, name: &str, metadata: impl Into<String>) {
+    PENDING_EVENTS
+        .lock()
+        .unwrap()
+        .push(clock, name, metadata.into(), true);
+}
+
+/// Drains the events marked since the last call and tags them with the copperlist id.
+/// This is called by the runtime at the end of every copperlist.
+pub(crate) fn take_events(culist_id: u32) -> Vec<CuEvent> {
+    PENDING_EVENTS.lock().unwrap().take(culist_id)
+}
+
+/// Logs an event to the text logs, the runtime also writes it to the event sections.
+pub(crate) fn log_event(event: &CuEvent) {
+    debug!(
+        "Event {}: {} (culist {})",
+        event.name.clone(),
+        event.metadata.clone(),
+        event.culist_id
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu29_clock::CuDuration;
+    use std::time::Duration;
+
+    #[test]
+    fn test_event_queue() {
+        let (clock, mock) = RobotClock::mock();
+        let mut queue = EventQueue { events: Vec::new() };
+        mock.increment(Duration::from_millis(1));
+        queue.push(&clock, "grasp_attempt", "object: cup".to_string(), false);
+        queue.push(&clock, "near_miss", String::new(), true);
+
+        let events = queue.take(42);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].time, CuDuration::from(1_000_000));
+        assert_eq!(events[0].culist_id, 42);
+        assert_eq!(
+            events[0].to_string(),
+            "1.000 ms [culist 42] grasp_attempt: object: cup"
+        );
+        assert!(!events[0].flush && events[1].flush);
+        assert!(queue.take(43).is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! Extraction of the payloads of a log to standard files: images to PNG, point clouds to PLY or PCD...
+//! A payload type opts in by implementing [CuPayloadExport], the log exporter then writes one file per
+//! message of a connection, the other payloads only export as structured text.
+//!
+//! The generated copperlist implements [CuOutputsExport] to give the exporter the outputs of the tasks
+//! that implement it, and [CuOutputsDebug] to give the log viewers all of them.
+
+use crate::cutask::CuMsgMetadata;
+use cu29_traits::{CuError, CuResult};
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
+use std::str::FromStr;
+
+/// A file format a payload can be extracted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CuExportFormat {
+    Png,
+    Ply,
+    Pcd,
+}
+
+impl CuExportFormat {
+    /// The extension of the exported files.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CuExportFormat::Png => "png",
+            CuExportFormat::Ply => "ply",
+            CuExportFormat::Pcd => "pcd",
+        }
+    }
+}
+
+impl Display for CuExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for CuExportFormat {
+    type Err = CuError;
+
+    fn from_str(s: &str) -> CuResult<Self> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(CuExportFormat::Png),
+            "ply" => Ok(CuExportFormat::Ply),
+            "pcd" => Ok(CuExportFormat::Pcd),
+            _ => Err(CuError::from(format!(
+                "Unknown export format \"{s}\", expected png, ply or pcd."
+            ))),
+        }
+    }
+}
+
+/// Implemented by the payloads that can be written to a standard file format.
+pub trait CuPayloadExport {
+    /// The formats this payload can be exported to, the first one is the default.
+    fn export_formats(&self) -> &'static [CuExportFormat];
+
+    /// Writes the payload in one of its formats.
+    fn export(&self, format: CuExportFormat, out: &mut dyn Write) -> CuResult<()>;
+}
+
+/// Implemented by the generated copperlists to visit the outputs of the tasks with a payload,
+/// with their exporter if their payload type implements [CuPayloadExport].
+pub trait CuOutputsExport {
+    fn visit_exports(&self, visitor: &mut ExportVisitor);
+}
+
+/// Called with the id of the task, the metadata of its output and its exporter.
+pub type ExportVisitor<'a> =
+    dyn FnMut(&'static str, &CuMsgMetadata, Option<&dyn CuPayloadExport>) + 'a;
+
+/// Implemented by the generated copperlists to visit the outputs of all the tasks, with their
+/// payload if they have one, for the tools browsing the logs.
+pub trait CuOutputsDebug {
+    fn visit_outputs(&self, visitor: &mut OutputVisitor);
+}
+
+/// Called with the id of the task, the metadata of its output and its payload.
+pub type OutputVisitor<'a> = dyn FnMut(&'static str, &CuMsgMetadata, Option<&dyn Debug>) + 'a;
+
+/// The generated code wraps the payloads in a probe and calls `(&ExportProbe(payload)).as_export()`:
+/// the method of [ViaPayloadExport] is found first when the payload implements [CuPayloadExport],
+/// the one of [ViaOpaque] otherwise, without specialization.
+#[doc(hidden)]
+pub struct ExportProbe<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ViaPayloadExport {
+    fn as_export(&self) -> Option<&dyn CuPayloadExport>;
+}
+
+impl<T: CuPayloadExport> ViaPayloadExport for ExportProbe<'_, T> {
+    fn as_export(&self) -> Option<&dyn CuPayloadExport> {
+        Some(self.0)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaOpaque {
+    fn as_export(&self) -> Option<&dyn CuPayloadExport>;
+}
+
+impl<T> ViaOpaque for &ExportProbe<'_, T> {
+    fn as_export(&self) -> Option<&dyn CuPayloadExport> {
+        None
+    }
+}
+
+fn write_error(e: std::io::Error) -> CuError {
+    CuError::new_with_cause("Failed to write the exported payload", e)
+}
+
+/// The color types of the PNG images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngColor {
+    Gray,
+    Rgb,
+    Rgba,
+}
+
+impl PngColor {
+    pub fn channels(&self) -> usize {
+        match self {
+            PngColor::Gray => 1,
+            PngColor::Rgb => 3,
+            PngColor::Rgba => 4,
+        }
+    }
+
+    fn color_type(&self) -> u8 {
+        match self {
+            PngColor::Gray => 0,
+            PngColor::Rgb => 2,
+            PngColor::Rgba => 6,
+        }
+    }
+}
+
+/// Writes an 8 bits per channel PNG. `pixels` has `stride` bytes per row, only the first
+/// `width * channels` are written. The image data is stored without compression.
+pub fn write_png(
+    out: &mut dyn Write,
+    width: u32,
+    height: u32,
+    stride: usize,
+    color: PngColor,
+    pixels: &[u8],
+) -> CuResult<()> {
+    let row_size = width as usize * color.channels();
+    if stride < row_size || pixels.len() < stride * height as usize {
+        return Err(CuError::from(format!(
+            "The buffer of {} bytes is too small for a {width}x{height} image with a stride of {stride}.",
+            pixels.len()
+        )));
+    }
+    // Every row is prefixed with its filter type, 0 for none.
+    let mut raw = Vec::with_capacity((row_size + 1) * height as usize);
+    for row in pixels.chunks(stride).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(&row[..row_size]);
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, color.color_type(), 0, 0, 0]);
+
+    out.write_all(b"\x89PNG\r\n\x1a\n").map_err(write_error)?;
+    write_png_chunk(out, b"IHDR", &ihdr)?;
+    write_png_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+    write_png_chunk(out, b"IEND", &[])
+}
+
+fn write_png_chunk(out: &mut dyn Write, kind: &[u8; 4], data: &[u8]) -> CuResult<()> {
+    let crc = crc32(&[kind.as_slice(), data]);
+    out.write_all(&(data.len() as u32).to_be_bytes())
+        .and_then(|_| out.write_all(kind))
+        .and_then(|_| out.write_all(data))
+        .and_then(|_| out.write_all(&crc.to_be_bytes()))
+        .map_err(write_error)
+}
+
+/// A zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = u16::MAX as usize;
+    let mut stream = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 16);
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(last as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    stream
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Writes points with an intensity as an ASCII PLY or PCD file.
+pub fn write_points(
+    out: &mut dyn Write,
+    format: CuExportFormat,
+    points: &[[f32; 4]],
+) -> CuResult<()> {
+    let count = points.len();
+    match format {
+        CuExportFormat::Ply => write!(
+            out,
+            "ply\nformat ascii 1.0\nelement vertex {count}\nproperty float x\nproperty float y\nproperty float z\nproperty float intensity\nend_header\n"
+        ),
+        CuExportFormat::Pcd => write!(
+            out,
+            "# .PCD v0.7 - Point Cloud Data file format\nVERSION 0.7\nFIELDS x y z intensity\nSIZE 4 4 4 4\nTYPE F F F F\nCOUNT 1 1 1 1\nWIDTH {count}\nHEIGHT 1\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS {count}\nDATA ascii\n"
+        ),
+        CuExportFormat::Png => {
+            return Err(CuError::from("Points can't be exported as PNG."));
+        }
+    }
+    .map_err(write_error)?;
+    for [x, y, z, intensity] in points {
+        writeln!(out, "{x} {y} {z} {intensity}").map_err(write_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Points;
+
+    impl CuPayloadExport for Points {
+        fn export_formats(&self) -> &'static [CuExportFormat] {
+            &[CuExportFormat::Ply]
+        }
+
+        fn export(&self, format: CuExportFormat, out: &mut dyn Write) -> CuResult<()> {
+            write_points(out, format, &[[1.0, 2.0, 3.0, 0.5]])
+        }
+    }
+
+    #[test]
+    fn test_export_probe() {
+        // The explicit borrow is what the generated code writes, it selects the probe impl.
+        #[allow(clippy::needless_borrow)]
+        let exportable = (&ExportProbe(&Points)).as_export();
+        assert_eq!(exportable.unwrap().export_formats(), &[CuExportFormat::Ply]);
+        assert!((&ExportProbe(&42u32)).as_export().is_none());
+
+        let mut ply = Vec::new();
+        exportable
+            .unwrap()
+            .export(CuExportFormat::Ply, &mut ply)
+            .unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.starts_with("ply\nformat ascii 1.0\nelement vertex 1\n"));
+        assert!(ply.ends_with("end_header\n1 2 3 0.5\n"));
+    }
+
+    #[test]
+    fn test_write_png() {
+        // A 2x2 gray image in a buffer with a stride of 3.
+        let pixels = [0, 255, 9, 128, 64, 9];
+        let mut png = Vec::new();
+        write_png(&mut png, 2, 2, 3, PngColor::Gray, &pixels).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // The CRC of an IEND chunk is always the same.
+        assert_eq!(
+            &png[png.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+        // The rows are stored without their padding.
+        let idat = &png[8 + 25 + 8..];
+        assert_eq!(&idat[7..13], &[0, 0, 255, 0, 128, 64]);
+
+        assert!(write_png(&mut png, 4, 2, 3, PngColor::Gray, &pixels).is_err());
+        assert_eq!(
+            "PCD".parse::<CuExportFormat>().unwrap(),
+            CuExportFormat::Pcd
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Fixed-point numbers (Q format) for the payloads exchanged with the microcontrollers. A [Fixed]
+//! is an integer holding the number scaled by 2^FRAC: the co-processors compute with it without an
+//! FPU, it goes on the wire as the integer so nothing is lost to float formatting, and the
+//! arithmetic being integer arithmetic a replay gives the same bits on every architecture.
+//!
+//! ```rust,ignore
+//! #[derive(Default, Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+//! pub struct MotorCommand {
+//!     pub duty: Q15, // -1.0..1.0
+//!     pub current_limit: Q16_16, // in A
+//! }
+//!
+//! let command = MotorCommand { duty: Q15::from_f32(0.25), current_limit: Q16_16::from_f32(2.5) };
+//! ```
+//!
+//! The conversions from floats round to the nearest and the arithmetic saturates at the bounds of
+//! the format, like the DSP instructions of the microcontrollers.
+
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{BorrowDecode, Decode, Encode};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// The integers a [Fixed] can be stored in.
+pub trait FixedStorage: Copy + Default + Eq + Ord + Debug + Encode + 'static {
+    const BITS: u32;
+    fn widen(self) -> i128;
+    /// The integer closest to `value` in the range of the storage.
+    fn saturate(value: i128) -> Self;
+}
+
+macro_rules! fixed_storage {
+    ($($int:ty),*) => {$(
+        impl FixedStorage for $int {
+            const BITS: u32 = <$int>::BITS;
+
+            fn widen(self) -> i128 {
+                self as i128
+            }
+
+            fn saturate(value: i128) -> Self {
+                value.clamp(<$int>::MIN as i128, <$int>::MAX as i128) as $int
+            }
+        }
+    )*};
+}
+
+fixed_storage!(i8, i16, i32, i64, u8, u16, u32);
+
+/// A number stored as an integer `T` with `FRAC` fractional bits.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed<T: FixedStorage, const FRAC: u32>(T);
+
+/// Q7: -1.0 to 1.0 with a resolution of 2^-7.
+pub type Q7 = Fixed<i8, 7>;
+/// Q15: -1.0 to 1.0 with a resolution of 2^-15, the usual format of the duty cycles and the audio.
+pub type Q15 = Fixed<i16, 15>;
+/// Q31: -1.0 to 1.0 with a resolution of 2^-31.
+pub type Q31 = Fixed<i32, 31>;
+/// Q16.16: -32768.0 to 32768.0 with a resolution of 2^-16.
+#[allow(non_camel_case_types)]
+pub type Q16_16 = Fixed<i32, 16>;
+
+impl<T: FixedStorage, const FRAC: u32> Fixed<T, FRAC> {
+    /// The number whose integer representation is `raw`, as received from a co-processor.
+    pub fn from_raw(raw: T) -> Self {
+        const { assert!(FRAC < T::BITS, "more fractional bits than the storage has") };
+        Self(raw)
+    }
+
+    /// The integer representation, as sent to a co-processor.
+    pub fn raw(self) -> T {
+        self.0
+    }
+
+    pub fn min_value() -> Self {
+        Self::from_raw(T::saturate(i128::MIN))
+    }
+
+    pub fn max_value() -> Self {
+        Self::from_raw(T::saturate(i128::MAX))
+    }
+
+    /// The smallest step of the format.
+    pub fn epsilon() -> Self {
+        Self::from_raw(T::saturate(1))
+    }
+
+    /// The closest number to `value`, saturated to the range of the format (NaN gives 0).
+    pub fn from_f64(value: f64) -> Self {
+        // the cast of a float to an integer saturates and maps NaN to 0.
+        Self::from_raw(T::saturate((value * (1u64 << FRAC) as f64).round() as i128))
+    }
+
+    pub fn from_f32(value: f32) -> Self {
+        Self::from_f64(value as f64)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0.widen() as f64 / (1u64 << FRAC) as f64
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    /// The same number in another format, rounded to the nearest and saturated.
+    pub fn convert<U: FixedStorage, const TO: u32>(self) -> Fixed<U, TO> {
+        let raw = self.0.widen();
+        let raw = if TO >= FRAC {
+            raw << (TO - FRAC)
+        } else {
+            rounding_shr(raw, FRAC - TO)
+        };
+        Fixed::from_raw(U::saturate(raw))
+    }
+}
+
+/// `value / 2^shift` rounded to the nearest, the halves away from zero.
+fn rounding_shr(value: i128, shift: u32) -> i128 {
+    if shift == 0 {
+        return value;
+    }
+    let half = 1i128 << (shift - 1);
+    if value >= 0 {
+        (value + half) >> shift
+    } else {
+        -((-value + half) >> shift)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Add for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::from_raw(T::saturate(self.0.widen() + rhs.0.widen()))
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Sub for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::from_raw(T::saturate(self.0.widen() - rhs.0.widen()))
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Mul for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let product = self.0.widen() * rhs.0.widen();
+        Self::from_raw(T::saturate(rounding_shr(product, FRAC)))
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Neg for Fixed<T, FRAC> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from_raw(T::saturate(-self.0.widen()))
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> From<Fixed<T, FRAC>> for f64 {
+    fn from(value: Fixed<T, FRAC>) -> Self {
+        value.to_f64()
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> From<Fixed<T, FRAC>> for f32 {
+    fn from(value: Fixed<T, FRAC>) -> Self {
+        value.to_f32()
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Debug for Fixed<T, FRAC> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?})", self.to_f64(), self.0)
+    }
+}
+
+impl<T: FixedStorage, const FRAC: u32> Display for Fixed<T, FRAC> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.to_f64(), f)
+    }
+}
+
+/// Encoded as the integer.
+impl<T: FixedStorage, const FRAC: u32> Encode for Fixed<T, FRAC> {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.0.encode(encoder)
+    }
+}
+
+impl<Context, T: FixedStorage + Decode<Context>, const FRAC: u32> Decode<Context>
+    for Fixed<T, FRAC>
+{
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self::from_raw(T::decode(decoder)?))
+    }
+}
+
+impl<'de, Context, T: FixedStorage + Decode<Context>, const FRAC: u32> BorrowDecode<'de, Context>
+    for Fixed<T, FRAC>
+{
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self::from_raw(T::decode(decoder)?))
+    }
+}
+
+/// Serialized as the number it holds so the exports and the invariants see the value.
+impl<T: FixedStorage, const FRAC: u32> Serialize for Fixed<T, FRAC> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl<'de, T: FixedStorage, const FRAC: u32> Deserialize<'de> for Fixed<T, FRAC> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        f64::deserialize(deserializer).map(Self::from_f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Q15::from_f32(0.5).raw(), 0x4000);
+        assert_eq!(Q15::from_f32(-1.0).raw(), i16::MIN);
+        assert_eq!(Q15::from_f32(1.0), Q15::max_value());
+        assert_eq!(Q15::from_f64(f64::NAN).raw(), 0);
+        assert_eq!(Q16_16::from_f64(-2.75).to_f64(), -2.75);
+        assert_eq!(Q15::from_raw(1), Q15::epsilon());
+        assert_eq!(
+            Q15::from_f32(0.25).convert::<i32, 16>(),
+            Q16_16::from_f32(0.25)
+        );
+        assert_eq!(Q16_16::from_f32(3.0).convert::<i16, 15>(), Q15::max_value());
+        assert_eq!(Q16_16::from_raw(3).convert::<i16, 14>().raw(), 1);
+        assert_eq!(Q16_16::from_raw(-3).convert::<i16, 14>().raw(), -1);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let half = Q15::from_f32(0.5);
+        let quarter = Q15::from_f32(0.25);
+        assert_eq!(half * half, quarter);
+        assert_eq!(half + quarter, Q15::from_f32(0.75));
+        assert_eq!(quarter - half, Q15::from_f32(-0.25));
+        assert_eq!(half + half, Q15::max_value());
+        assert_eq!(-Q15::min_value(), Q15::max_value());
+        assert_eq!(
+            Q16_16::from_f32(1.5) * Q16_16::from_f32(-3.0),
+            Q16_16::from_f32(-4.5)
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let config = bincode::config::standard().with_fixed_int_encoding();
+        let duty = Q15::from_f32(-0.5);
+        let encoded = bincode::encode_to_vec(duty, config).unwrap();
+        assert_eq!(encoded, (-0x4000i16).to_le_bytes());
+        let (decoded, _): (Q15, usize) = bincode::decode_from_slice(&encoded, config).unwrap();
+        assert_eq!(decoded, duty);
+        assert_eq!(serde_json::to_string(&duty).unwrap(), "-0.5");
+        let parsed: Q16_16 = serde_json::from_str("1.25").unwrap();
+        assert_eq!(parsed.raw(), 0x14000);
+    }
+}
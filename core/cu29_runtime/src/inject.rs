@@ -0,0 +1,173 @@
+//! Injection of messages into the graph from the code embedding the application.
+//! A GUI or a script commands the robot through an [ExternalInputTask] of the graph, a source
+//! giving the messages pushed to its [CuInjector] without any network hop:
+//!
+//! ```ron
+//! (id: "teleop", type: "cu29::inject::ExternalInputTask<mymod::Twist>", config: {"name": "teleop"}),
+//! ```
+//!
+//! ```rust,ignore
+//! let builder = MyAppBuilder::new().with_context(&copper_ctx);
+//! let teleop = builder.injector::<Twist>("teleop", 8);
+//! let mut app = builder.build()?;
+//! std::thread::spawn(move || teleop.send(Twist::forward(0.5)));
+//! ```
+
+use crate::config::ComponentConfig;
+use crate::cutask::{CuMsg, CuMsgPayload, CuSrcTask, Freezable};
+use crate::output_msg;
+use cu29_clock::{RobotClock, Tov};
+use cu29_traits::{CuError, CuResult};
+use std::any::Any;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+
+/// The receivers of the injectors created, until their task takes them when the application is
+/// built.
+static PENDING: Mutex<Vec<(String, Box<dyn Any + Send>)>> = Mutex::new(Vec::new());
+
+/// Pushes messages to the [ExternalInputTask] with the same name, from any thread.
+pub struct CuInjector<P> {
+    name: String,
+    sender: SyncSender<P>,
+}
+
+impl<P> Clone for CuInjector<P> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<P: Send + 'static> CuInjector<P> {
+    /// An injector holding `capacity` messages at most until its task gives them. Created before
+    /// the application is built, usually through the `injector` method of its builder.
+    pub fn new(name: &str, capacity: usize) -> Self {
+        let (sender, receiver) = sync_channel(capacity);
+        let mut pending = PENDING.lock().unwrap();
+        // A new injector replaces the one of a previous application.
+        pending.retain(|(pending_name, _)| pending_name != name);
+        pending.push((name.to_string(), Box::new(receiver)));
+        Self {
+            name: name.to_string(),
+            sender,
+        }
+    }
+}
+
+impl<P> CuInjector<P> {
+    /// Queues a message for the next cycles, this never waits for the application.
+    pub fn send(&self, payload: P) -> CuResult<()> {
+        self.sender.try_send(payload).map_err(|e| match e {
+            TrySendError::Full(_) => CuError::from(format!("The injector {} is full", self.name)),
+            TrySendError::Disconnected(_) => {
+                CuError::from(format!("The task of the injector {} is gone", self.name))
+            }
+        })
+    }
+}
+
+/// Takes the receiver of the injector of this name.
+fn take<P: 'static>(name: &str) -> CuResult<Receiver<P>> {
+    let mut pending = PENDING.lock().unwrap();
+    let index = pending
+        .iter()
+        .position(|(pending_name, _)| pending_name == name)
+        .ok_or_else(|| {
+            CuError::from(format!(
+                "No injector {name}, create it before building the application"
+            ))
+        })?;
+    let (_, receiver) = pending.remove(index);
+    receiver
+        .downcast::<Receiver<P>>()
+        .map(|receiver| *receiver)
+        .map_err(|_| {
+            CuError::from(format!(
+                "The injector {name} is not of the type of its task"
+            ))
+        })
+}
+
+/// The injectors created without a task taking them, called when the application is built.
+pub fn unclaimed() -> Vec<String> {
+    PENDING
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// A source giving a message pushed to the [CuInjector] of its `name` per cycle, the oldest first,
+/// no payload if none is pending. The time of validity is the time it is given at.
+pub struct ExternalInputTask<P> {
+    receiver: Receiver<P>,
+}
+
+impl<P> Freezable for ExternalInputTask<P> {}
+
+impl<'cl, P: CuMsgPayload + Send + 'static> CuSrcTask<'cl> for ExternalInputTask<P> {
+    type Output = output_msg!('cl, P);
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        let name = config
+            .and_then(|config| config.get::<String>("name"))
+            .ok_or_else(|| CuError::from("ExternalInputTask needs the \"name\" of its injector"))?;
+        Ok(Self {
+            receiver: take(&name)?,
+        })
+    }
+
+    fn process(&mut self, clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        match self.receiver.try_recv() {
+            Ok(payload) => {
+                new_msg.set_payload(payload);
+                new_msg.metadata.tov = Tov::Time(clock.now());
+            }
+            Err(_) => new_msg.clear_payload(),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_injection() {
+        let mut config = ComponentConfig::new();
+        config.set("name", "test_injection".to_string());
+        assert!(ExternalInputTask::<i32>::new(Some(&config)).is_err());
+
+        let injector = CuInjector::<i32>::new("test_injection", 2);
+        let mut task = ExternalInputTask::<i32>::new(Some(&config)).unwrap();
+        let sender = injector.clone();
+        std::thread::spawn(move || sender.send(1).unwrap())
+            .join()
+            .unwrap();
+        injector.send(2).unwrap();
+        assert!(injector.send(3).is_err());
+
+        let clock = RobotClock::new();
+        let mut msg = CuMsg::<i32>::new(None);
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            task.process(&clock, &mut msg).unwrap();
+            received.push(msg.payload().copied());
+        }
+        assert_eq!(received, vec![Some(1), Some(2), None]);
+
+        drop(task);
+        assert!(injector.send(4).is_err());
+
+        let _ = CuInjector::<u8>::new("test_injection_unclaimed", 1);
+        assert!(unclaimed().contains(&"test_injection_unclaimed".to_string()));
+    }
+}
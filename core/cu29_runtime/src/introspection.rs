@@ -0,0 +1,275 @@
+//! Structured description of a running application: its tasks, its connections and live counters.
+//! It is returned by the `introspect()` method of the generated application so embedding programs
+//! and debug UIs don't need to parse the configuration again.
+
+use crate::config::{CnxPolicy, CuConfig};
+use crate::curuntime::{find_task_type_for_id, CuTaskType, LoopStats};
+use crate::cutask::{CuMsg, CuMsgMetadata, CuMsgPayload};
+#[cfg(feature = "perf")]
+use crate::perf::{PerfSample, PerfStats};
+use cu29_clock::{CuDuration, PartialCuTimeRange};
+use cu29_traits::CuResult;
+
+/// A task instantiated by the runtime.
+#[derive(Debug, Clone)]
+pub struct TaskIntrospection {
+    /// Index of the task in the tasks tuple of the runtime.
+    pub index: usize,
+    pub id: String,
+    /// The Rust type from the configuration.
+    pub type_name: String,
+    pub task_type: CuTaskType,
+    /// The message type produced by the task, None for a sink.
+    pub output_msg: Option<String>,
+    pub stats: TaskStats,
+}
+
+/// A connection of the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CnxIntrospection {
+    pub src: String,
+    pub dst: String,
+    pub msg: String,
+    /// The messages of this connection are logged.
+    pub store: bool,
+    pub policy: CnxPolicy,
+    pub stats: CnxStats,
+}
+
+/// Delivery counters of a connection, updated before every process() of its destination.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CnxStats {
+    /// Messages with a payload given to the destination, the latched ones included.
+    pub delivered: u64,
+    /// Empty messages given to the destination.
+    pub empty: u64,
+    /// Messages given again by a latched connection.
+    pub latched: u64,
+    /// Messages dropped by a decimated connection.
+    pub dropped: u64,
+    /// Payloads older than the `max_age_ms` of the connection when given to the destination.
+    pub stale: u64,
+}
+
+impl CnxStats {
+    /// Accounts for a message given to the destination.
+    pub fn record<T: CuMsgPayload>(&mut self, msg: &CuMsg<T>) {
+        if msg.payload().is_some() {
+            self.delivered += 1;
+        } else {
+            self.empty += 1;
+        }
+    }
+}
+
+/// Live counters of a task, updated at the end of every copper list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskStats {
+    /// Number of times the task was processed.
+    pub runs: u64,
+    pub last_process_time: CuDuration,
+    pub max_process_time: CuDuration,
+    pub total_process_time: CuDuration,
+    /// Hardware counters of the process() calls.
+    #[cfg(feature = "perf")]
+    pub perf: PerfStats,
+}
+
+impl TaskStats {
+    /// Accounts for one process call, ignored if the task was not run in this copper list.
+    pub fn record(&mut self, process_time: &PartialCuTimeRange) {
+        let (Some(start), Some(end)) = (
+            Option::<CuDuration>::from(process_time.start),
+            Option::<CuDuration>::from(process_time.end),
+        ) else {
+            return;
+        };
+        let duration = if end > start {
+            end - start
+        } else {
+            CuDuration(0)
+        };
+        self.runs += 1;
+        self.last_process_time = duration;
+        self.total_process_time += duration;
+        if duration > self.max_process_time {
+            self.max_process_time = duration;
+        }
+    }
+
+    pub fn mean_process_time(&self) -> CuDuration {
+        CuDuration(
+            self.total_process_time
+                .0
+                .checked_div(self.runs)
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// The graph and the live counters of the application.
+#[derive(Debug, Clone)]
+pub struct CuIntrospection {
+    /// In the order of the tasks tuple.
+    pub tasks: Vec<TaskIntrospection>,
+    pub connections: Vec<CnxIntrospection>,
+    /// Number of copper lists processed since the start.
+    pub copperlists: u64,
+    pub available_copper_lists: usize,
+    /// Overrun accounting if the loop runs at a fixed rate.
+    pub loop_stats: Option<LoopStats>,
+    /// Alarms currently active or latched.
+    pub active_alarms: usize,
+}
+
+impl CuIntrospection {
+    pub fn task(&self, id: &str) -> Option<&TaskIntrospection> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+}
+
+/// The graph built once from the configuration with the counters the runtime keeps up to date.
+#[derive(Debug, Clone, Default)]
+pub struct GraphDescription {
+    pub(crate) tasks: Vec<TaskIntrospection>,
+    pub(crate) connections: Vec<CnxIntrospection>,
+    /// Number of copper lists processed since the start.
+    pub(crate) copperlists: u64,
+}
+
+impl GraphDescription {
+    /// The tasks are listed in the order the runtime instantiates them.
+    pub fn from_config(config: &CuConfig) -> CuResult<Self> {
+        let graph = config.get_graph(None)?; // FIXME(gbin): Multimission support
+        let tasks = config
+            .get_all_nodes(None)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (node_id, node))| {
+                let task_type = find_task_type_for_id(graph, node_id);
+                let output_msg = if task_type == CuTaskType::Sink {
+                    None
+                } else {
+                    config.get_node_output_msg_type(&node.get_id(), None)
+                };
+                TaskIntrospection {
+                    index,
+                    id: node.get_id(),
+                    type_name: node.get_type().to_string(),
+                    task_type,
+                    output_msg,
+                    stats: TaskStats::default(),
+                }
+            })
+            .collect();
+        let connections = graph
+            .edge_indices()
+            .map(|edge| {
+                let (src, dst) = graph.edge_endpoints(edge).unwrap();
+                let cnx = &graph[edge];
+                CnxIntrospection {
+                    src: graph[src].get_id(),
+                    dst: graph[dst].get_id(),
+                    msg: cnx.msg.clone(),
+                    store: cnx.store.unwrap_or(false),
+                    policy: cnx.policy.unwrap_or_default(),
+                    stats: CnxStats::default(),
+                }
+            })
+            .collect();
+        Ok(GraphDescription {
+            tasks,
+            connections,
+            copperlists: 0,
+        })
+    }
+
+    /// Updates the counters of the tasks from the metadata of a copper list, indexed like the tasks tuple.
+    pub fn record_stats(&mut self, msgs: &[&CuMsgMetadata]) {
+        self.copperlists += 1;
+        for (task, metadata) in self.tasks.iter_mut().zip(msgs) {
+            task.stats.record(&metadata.process_time);
+        }
+    }
+
+    /// The connections with their delivery counters, by their index in the graph.
+    pub fn connections(&self) -> &[CnxIntrospection] {
+        &self.connections
+    }
+
+    /// The delivery counters of a connection, by its index in the graph.
+    pub fn cnx_stats_mut(&mut self, cnx: usize) -> &mut CnxStats {
+        &mut self.connections[cnx].stats
+    }
+
+    /// Accounts for the hardware counters of one process() call of the task with this node id.
+    #[cfg(feature = "perf")]
+    pub fn record_perf(&mut self, node_id: usize, sample: PerfSample) {
+        if let Some(task) = self.tasks.get_mut(node_id) {
+            task.stats.perf.record(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Node;
+    use cu29_clock::OptionCuTime;
+
+    #[test]
+    fn test_graph_description() {
+        let mut config = CuConfig::default();
+        let src = config.add_node(Node::new("cam", "Camera"), None).unwrap();
+        let task = config.add_node(Node::new("blur", "Blur"), None).unwrap();
+        let sink = config.add_node(Node::new("disp", "Display"), None).unwrap();
+        config
+            .connect_ext(src, task, "Image", Some(true), None, None)
+            .unwrap();
+        config.connect(task, sink, "Image").unwrap();
+
+        let description = GraphDescription::from_config(&config).unwrap();
+        let types: Vec<_> = description
+            .tasks
+            .iter()
+            .map(|t| (t.id.as_str(), t.task_type, t.output_msg.as_deref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("cam", CuTaskType::Source, Some("Image")),
+                ("blur", CuTaskType::Regular, Some("Image")),
+                ("disp", CuTaskType::Sink, None),
+            ]
+        );
+        assert_eq!(
+            description.connections[0],
+            CnxIntrospection {
+                src: "cam".to_string(),
+                dst: "blur".to_string(),
+                msg: "Image".to_string(),
+                store: true,
+                policy: CnxPolicy::Latest,
+                stats: CnxStats::default(),
+            }
+        );
+        assert!(!description.connections[1].store);
+    }
+
+    #[test]
+    fn test_task_stats() {
+        let mut stats = TaskStats::default();
+        stats.record(&PartialCuTimeRange::default());
+        assert_eq!(stats.runs, 0);
+        for (start, end) in [(100u64, 400u64), (1000, 1100)] {
+            stats.record(&PartialCuTimeRange {
+                start: OptionCuTime::from(CuDuration(start)),
+                end: OptionCuTime::from(CuDuration(end)),
+            });
+        }
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.last_process_time, CuDuration(100));
+        assert_eq!(stats.max_process_time, CuDuration(300));
+        assert_eq!(stats.mean_process_time(), CuDuration(200));
+    }
+}
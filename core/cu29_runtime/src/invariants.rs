@@ -0,0 +1,380 @@
+//! The invariants of the connections declared in the configuration, checked by the runtime at the
+//! end of every copper list so the assumptions on the data flow ("the IMU feeds the EKF at 180Hz at
+//! least", "the commanded speed stays under 1.5m/s") are enforced instead of remembered:
+//!
+//! ```ron
+//! invariants: [
+//!     (cnx: "imu->ekf", min_rate_hz: 180.0),
+//!     (cnx: "planner->base", field: "linear", max: 1.5, severity: Error),
+//! ],
+//! ```
+//!
+//! A violation raises an alarm from the source [INVARIANTS_ALARM_SOURCE] with the index of the
+//! invariant in the configuration as its code, the alarm is cleared when the invariant holds again.
+//! The values of the payloads are read through their serde representation: a payload type which is
+//! not `Serialize` can only be checked for its rate.
+
+use crate::alarms::{clear_alarm, raise_alarm, AlarmSeverity};
+use crate::config::{CuConfig, InvariantConfig, InvariantSeverity};
+use cu29_clock::{CuDuration, CuTime, RobotClock};
+use cu29_value::Value;
+use serde::Serialize;
+
+/// The source of the alarms raised by the violated invariants.
+pub const INVARIANTS_ALARM_SOURCE: &str = "invariants";
+
+/// The rates are measured over windows of this duration.
+const RATE_WINDOW: CuDuration = CuDuration::from_secs(1);
+
+impl From<InvariantSeverity> for AlarmSeverity {
+    fn from(severity: InvariantSeverity) -> Self {
+        match severity {
+            InvariantSeverity::Info => AlarmSeverity::Info,
+            InvariantSeverity::Warning => AlarmSeverity::Warning,
+            InvariantSeverity::Error => AlarmSeverity::Error,
+            InvariantSeverity::Critical => AlarmSeverity::Critical,
+        }
+    }
+}
+
+/// Implemented by the generated copperlists to give the invariants the outputs of the tasks.
+pub trait CuCheckedOutputs {
+    /// If the output of the task has a payload in this copper list.
+    fn has_payload(&self, task_id: &str) -> bool;
+
+    /// The payload of the output of the task, None if it is empty or its type is not `Serialize`.
+    fn payload_value(&self, task_id: &str) -> Option<Value>;
+}
+
+/// The generated code wraps the payloads in a probe and calls `(&ValueProbe(payload)).as_value()`,
+/// see [crate::export::ExportProbe] for the mechanism.
+#[doc(hidden)]
+pub struct ValueProbe<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ViaSerialize {
+    fn as_value(&self) -> Option<Value>;
+}
+
+impl<T: Serialize> ViaSerialize for ValueProbe<'_, T> {
+    fn as_value(&self) -> Option<Value> {
+        cu29_value::to_value(self.0).ok()
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaOpaque {
+    fn as_value(&self) -> Option<Value>;
+}
+
+impl<T> ViaOpaque for &ValueProbe<'_, T> {
+    fn as_value(&self) -> Option<Value> {
+        None
+    }
+}
+
+struct InvariantCheck {
+    code: u32,
+    src: String,
+    config: InvariantConfig,
+    window_start: Option<CuTime>,
+    received: u32,
+    rate_violated: bool,
+    value_violated: bool,
+}
+
+/// The invariants of the configuration and the state of their checks.
+pub struct CuInvariants {
+    checks: Vec<InvariantCheck>,
+}
+
+impl CuInvariants {
+    pub fn new(config: &CuConfig) -> Self {
+        let checks = config
+            .invariants
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(code, invariant)| {
+                let (src, _) = invariant.endpoints()?;
+                Some(InvariantCheck {
+                    code: code as u32,
+                    src: src.to_string(),
+                    config: invariant.clone(),
+                    window_start: None,
+                    received: 0,
+                    rate_violated: false,
+                    value_violated: false,
+                })
+            })
+            .collect();
+        Self { checks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// Checks the outputs of a copper list that just finished processing.
+    pub fn check(&mut self, clock: &RobotClock, outputs: &impl CuCheckedOutputs) {
+        let now = clock.now();
+        for check in &mut self.checks {
+            let received = outputs.has_payload(&check.src);
+            if let Some(min_rate) = check.config.min_rate_hz {
+                check.received += received as u32;
+                match check.window_start {
+                    None => check.window_start = Some(now),
+                    Some(start) if now - start >= RATE_WINDOW => {
+                        let rate = check.received as f64 / (now - start).as_secs_f64();
+                        let violation = (rate < min_rate).then(|| {
+                            format!(
+                                "{} at {rate:.1}Hz, expected {min_rate}Hz at least",
+                                check.config.cnx
+                            )
+                        });
+                        check.report(clock, true, violation);
+                        check.window_start = Some(now);
+                        check.received = 0;
+                    }
+                    Some(_) => {}
+                }
+            }
+            if received && (check.config.min.is_some() || check.config.max.is_some()) {
+                let violation =
+                    value_violation(&check.config, outputs.payload_value(&check.src).as_ref());
+                check.report(clock, false, violation);
+            }
+        }
+    }
+}
+
+impl InvariantCheck {
+    /// Raises the alarm of the invariant when its rate or its value starts being violated, clears
+    /// it when both hold again.
+    fn report(&mut self, clock: &RobotClock, rate: bool, violation: Option<String>) {
+        let (violated, other) = if rate {
+            (&mut self.rate_violated, self.value_violated)
+        } else {
+            (&mut self.value_violated, self.rate_violated)
+        };
+        match violation {
+            Some(message) if !*violated => {
+                *violated = true;
+                raise_alarm(
+                    clock,
+                    INVARIANTS_ALARM_SOURCE,
+                    self.code,
+                    self.config.severity.into(),
+                    message,
+                );
+            }
+            None if *violated => {
+                *violated = false;
+                if !other {
+                    clear_alarm(INVARIANTS_ALARM_SOURCE, self.code);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Why the value of a payload breaks the invariant, None if it holds.
+fn value_violation(invariant: &InvariantConfig, payload: Option<&Value>) -> Option<String> {
+    let field = invariant.field.as_deref().unwrap_or_default();
+    let Some(value) = payload
+        .and_then(|payload| lookup(payload, field))
+        .and_then(magnitude)
+    else {
+        return Some(format!(
+            "{}: no number at \"{field}\" in the payload",
+            invariant.cnx
+        ));
+    };
+    let below = invariant.min.filter(|min| value < *min);
+    let above = invariant.max.filter(|max| value > *max);
+    match (below, above) {
+        (Some(min), _) => Some(format!(
+            "{} {field} at {value}, expected {min} at least",
+            invariant.cnx
+        )),
+        (_, Some(max)) => Some(format!(
+            "{} {field} at {value}, expected {max} at most",
+            invariant.cnx
+        )),
+        _ => None,
+    }
+}
+
+/// The value at a path of fields and indices separated by dots, as "poses.0.x".
+fn lookup<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match transparent(value) {
+            Value::Map(map) => map.get(&Value::String(key.to_string())),
+            Value::Seq(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn transparent(value: &Value) -> &Value {
+    match value {
+        Value::Newtype(inner) => transparent(inner),
+        Value::Option(Some(inner)) => transparent(inner),
+        _ => value,
+    }
+}
+
+pub(crate) fn number(value: &Value) -> Option<f64> {
+    Some(match value {
+        Value::U8(v) => *v as f64,
+        Value::U16(v) => *v as f64,
+        Value::U32(v) => *v as f64,
+        Value::U64(v) => *v as f64,
+        Value::I8(v) => *v as f64,
+        Value::I16(v) => *v as f64,
+        Value::I32(v) => *v as f64,
+        Value::I64(v) => *v as f64,
+        Value::F32(v) => *v as f64,
+        Value::F64(v) => *v,
+        _ => return None,
+    })
+}
+
+/// A number as is, the euclidean norm of the numbers held by a struct or a sequence (the
+/// timestamps and the other fields are ignored).
+fn magnitude(value: &Value) -> Option<f64> {
+    fn squares(value: &Value, sum: &mut f64, count: &mut usize) {
+        match transparent(value) {
+            Value::Map(map) => map.values().for_each(|v| squares(v, sum, count)),
+            Value::Seq(items) => items.iter().for_each(|v| squares(v, sum, count)),
+            value => {
+                if let Some(v) = number(value) {
+                    *sum += v * v;
+                    *count += 1;
+                }
+            }
+        }
+    }
+    let value = transparent(value);
+    if let Some(v) = number(value) {
+        return Some(v);
+    }
+    let (mut sum, mut count) = (0.0, 0);
+    squares(value, &mut sum, &mut count);
+    (count > 0).then(|| sum.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::alarms;
+    use crate::config::read_configuration_str;
+    use serde_derive::Serialize;
+    use std::time::Duration;
+
+    #[derive(Serialize)]
+    struct Vec3 {
+        x: f64,
+        y: f64,
+        z: f64,
+    }
+
+    #[derive(Serialize)]
+    struct Twist {
+        linear: Vec3,
+    }
+
+    struct Outputs {
+        twist: Option<Twist>,
+    }
+
+    impl CuCheckedOutputs for Outputs {
+        fn has_payload(&self, task_id: &str) -> bool {
+            task_id == "planner" && self.twist.is_some()
+        }
+
+        fn payload_value(&self, task_id: &str) -> Option<Value> {
+            match task_id {
+                "planner" => self
+                    .twist
+                    .as_ref()
+                    .and_then(|twist| ValueProbe(twist).as_value()),
+                _ => None,
+            }
+        }
+    }
+
+    fn active(code: u32) -> Option<AlarmSeverity> {
+        alarms()
+            .into_iter()
+            .find(|alarm| alarm.source == INVARIANTS_ALARM_SOURCE && alarm.code == code)
+            .filter(|alarm| alarm.active)
+            .map(|alarm| alarm.severity)
+    }
+
+    #[test]
+    fn test_invariants() {
+        let txt = r#"(
+            tasks: [(id: "planner", type: "Planner"), (id: "base", type: "Base")],
+            cnx: [(src: "planner", dst: "base", msg: "Twist")],
+            invariants: [
+                (cnx: "planner->base", min_rate_hz: 50.0),
+                (cnx: "planner->base", field: "linear", max: 1.5, severity: Error),
+                (cnx: "planner->base", field: "linear.x", min: 0.0),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let mut invariants = CuInvariants::new(&config);
+        let (clock, mock) = RobotClock::mock();
+        let twist = |x, y| Outputs {
+            twist: Some(Twist {
+                linear: Vec3 { x, y, z: 0.0 },
+            }),
+        };
+
+        // 100Hz with a speed of 1.25m/s.
+        for _ in 0..=100 {
+            invariants.check(&clock, &twist(0.75, 1.0));
+            mock.increment(Duration::from_millis(10));
+        }
+        assert_eq!(active(0), None);
+        assert_eq!(active(1), None);
+
+        invariants.check(&clock, &twist(1.2, 1.6));
+        assert_eq!(active(1), Some(AlarmSeverity::Error));
+        invariants.check(&clock, &twist(-0.5, 0.0));
+        assert_eq!(active(1), None);
+        assert_eq!(active(2), Some(AlarmSeverity::Warning));
+
+        // 20Hz for a second.
+        for _ in 0..20 {
+            invariants.check(&clock, &twist(0.5, 0.0));
+            mock.increment(Duration::from_millis(10));
+            for _ in 0..4 {
+                invariants.check(&clock, &Outputs { twist: None });
+                mock.increment(Duration::from_millis(10));
+            }
+        }
+        invariants.check(&clock, &twist(0.5, 0.0));
+        assert_eq!(active(0), Some(AlarmSeverity::Warning));
+        assert_eq!(active(2), None);
+    }
+
+    #[test]
+    fn test_magnitude() {
+        let twist = cu29_value::to_value(Twist {
+            linear: Vec3 {
+                x: 3.0,
+                y: 0.0,
+                z: 4.0,
+            },
+        })
+        .unwrap();
+        assert_eq!(lookup(&twist, "linear.z").and_then(magnitude), Some(4.0));
+        assert_eq!(lookup(&twist, "linear").and_then(magnitude), Some(5.0));
+        assert_eq!(magnitude(&twist), Some(5.0));
+        assert!(lookup(&twist, "angular").is_none());
+        assert!((&ValueProbe(&Outputs { twist: None })).as_value().is_none());
+    }
+}
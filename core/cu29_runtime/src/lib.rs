@@ -1,11 +1,41 @@
 #![doc = include_str!("../README.md")]
 
+pub mod actor;
+pub mod alarms;
+pub mod assets;
+pub mod budget;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod chunked;
 pub mod config;
 pub mod copperlist;
+pub mod crash;
 pub mod curuntime;
 pub mod cutask;
+pub mod delivery;
+pub mod estop;
+pub mod events;
+pub mod export;
+pub mod fixed;
+pub mod inject;
+pub mod introspection;
+pub mod invariants;
 pub(crate) mod log;
+pub mod manifest;
 pub mod monitoring;
+pub mod noise;
+pub mod params;
 pub mod payload;
+#[cfg(feature = "perf")]
+pub mod perf;
+pub mod pipeline;
 pub mod pool;
+pub mod profile;
+pub mod replay;
+pub mod schema;
+pub mod service;
 pub mod simulation;
+#[cfg(feature = "tap")]
+pub mod tap;
+pub mod testing;
+pub mod units;
@@ -0,0 +1,126 @@
+//! The manifest of a log: which application wrote it, from which commit and configuration, with
+//! which components, on which machine and when. The runtime writes it at startup in the
+//! `Manifest` section of the unified log so any log is self-describing, the log reader shows it
+//! with its `manifest` command.
+//!
+//! The git commit and the versions of the components are the ones of the build of the application,
+//! the configuration hash the one of the configuration it actually runs with.
+
+use crate::config::CuConfig;
+use bincode::{Decode, Encode};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode, Serialize, Deserialize)]
+pub struct CuLogManifest {
+    /// The name of the application struct.
+    pub application: String,
+    /// The version of the crate of the application.
+    pub version: String,
+    /// The commit the application was built from, with a "-dirty" suffix if the tree was modified.
+    pub git_commit: Option<String>,
+    /// The FNV-1a hash of the configuration in RON, see [config_hash].
+    pub config_hash: u64,
+    /// The crates of the components (tasks and monitor) with their versions, from the Cargo.lock
+    /// of the application.
+    pub components: Vec<(String, String)>,
+    pub hostname: String,
+    /// The wall clock time of the start, in nanoseconds since the UNIX epoch.
+    pub start_time_ns: u64,
+}
+
+impl CuLogManifest {
+    /// The manifest of the application starting now, called by the generated code.
+    pub fn new(
+        application: &str,
+        version: &str,
+        git_commit: Option<&str>,
+        config: &CuConfig,
+        components: &[(&str, &str)],
+    ) -> Self {
+        Self {
+            application: application.to_string(),
+            version: version.to_string(),
+            git_commit: git_commit.map(str::to_string),
+            config_hash: config_hash(config),
+            components: components
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect(),
+            hostname: hostname(),
+            start_time_ns: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64),
+        }
+    }
+}
+
+impl Display for CuLogManifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Application: {} {}", self.application, self.version)?;
+        writeln!(
+            f,
+            "Git commit:  {}",
+            self.git_commit.as_deref().unwrap_or("unknown")
+        )?;
+        writeln!(f, "Config hash: {:016x}", self.config_hash)?;
+        writeln!(f, "Hostname:    {}", self.hostname)?;
+        let (secs, nanos) = (
+            self.start_time_ns / 1_000_000_000,
+            self.start_time_ns % 1_000_000_000,
+        );
+        writeln!(f, "Started at:  {secs}.{nanos:09} s since the UNIX epoch")?;
+        write!(f, "Components:")?;
+        for (name, version) in &self.components {
+            write!(f, "\n  {name} {version}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A hash of the configuration stable across builds and Rust versions, so two logs can be
+/// compared: FNV-1a over its RON serialization.
+pub fn config_hash(config: &CuConfig) -> u64 {
+    config
+        .serialize_ron()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let config = CuConfig::deserialize_ron(r#"(tasks: [(id: "a", type: "A")], cnx: [])"#);
+        let manifest = CuLogManifest::new(
+            "MyApp",
+            "0.1.0",
+            Some("0123abcd-dirty"),
+            &config,
+            &[("cu-pid", "0.7.0")],
+        );
+        assert_eq!(manifest.config_hash, config_hash(&config));
+        let other = CuConfig::deserialize_ron(r#"(tasks: [(id: "b", type: "A")], cnx: [])"#);
+        assert_ne!(manifest.config_hash, config_hash(&other));
+        assert!(manifest.start_time_ns > 0);
+        assert!(!manifest.hostname.is_empty());
+        let text = manifest.to_string();
+        assert!(text.starts_with("Application: MyApp 0.1.0\nGit commit:  0123abcd-dirty\n"));
+        assert!(text.ends_with("Components:\n  cu-pid 0.7.0"));
+    }
+}
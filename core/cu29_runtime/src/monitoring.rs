@@ -1,8 +1,11 @@
 //! Some basic internal monitoring tooling Copper uses to monitor itself and the tasks it is running.
 //!
 
+use crate::alarms::AlarmEvent;
 use crate::config::CuConfig;
 use crate::cutask::CuMsgMetadata;
+use crate::events::CuEvent;
+use crate::introspection::CnxIntrospection;
 use crate::log::*;
 use cu29_clock::{CuDuration, RobotClock};
 use cu29_traits::{CuError, CuResult};
@@ -45,6 +48,17 @@ pub trait CuMonitor: Sized {
     /// Callbacked when a Task errored out. The runtime requires an immediate decision.
     fn process_error(&self, taskid: usize, step: CuTaskState, error: &CuError) -> Decision;
 
+    /// Callbacked at the end of a copperlist for every alarm raised, cleared or acknowledged since the last one.
+    fn process_alarm(&self, _event: &AlarmEvent) {}
+
+    /// Callbacked at the end of a copperlist for every event marked during it.
+    /// The monitors buffering what they record should flush it when the event asks for it.
+    fn process_event(&self, _event: &CuEvent) {}
+
+    /// Callbacked at the end of every copperlist with the connections and their delivery counters
+    /// (empty, dropped and stale inputs...), in the order of the graph.
+    fn process_cnx_stats(&self, _connections: &[CnxIntrospection]) {}
+
     /// Callbacked when copper is stopping.
     fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
         Ok(())
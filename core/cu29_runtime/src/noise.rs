@@ -0,0 +1,257 @@
+//! Sensor noise injection for replay and simulation.
+//! A [SensorNoise] degrades the output of a source before the rest of the graph sees it: gaussian
+//! noise, a slowly drifting bias and dropped messages. The estimators can then be stress-tested on
+//! recorded or simulated data while the production graph stays unchanged.
+//!
+//! The noise is applied from the simulation callback, here on top of an open loop replay:
+//!
+//! ```rust,ignore
+//! let mut noise = SensorNoise::new(NoiseModel::default().with_gaussian(0.05).with_dropout(0.01));
+//! let mut callback = |step| match step {
+//!     SimStep::Imu(CuTaskCallbackState::Process(_, output)) => {
+//!         *output = recorded.msgs.get_imu_output().clone();
+//!         noise.apply(output);
+//!         SimOverride::ExecutedBySim
+//!     }
+//!     step => default::replay_step(&recorded, &mut report, step),
+//! };
+//! ```
+//!
+//! The random generator is seeded so a degraded run can be reproduced.
+
+use crate::cutask::{CuMsg, CuMsgPayload};
+use serde_derive::{Deserialize, Serialize};
+
+/// Implemented by the payloads that can be degraded, it visits all their measurements.
+pub trait Perturb {
+    /// Calls `noise` on every measurement of the payload, always in the same order.
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64);
+}
+
+impl Perturb for f64 {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        *self = noise(*self);
+    }
+}
+
+impl Perturb for f32 {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        *self = noise(*self as f64) as f32;
+    }
+}
+
+impl<T: Perturb> Perturb for Option<T> {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        if let Some(value) = self {
+            value.perturb(noise);
+        }
+    }
+}
+
+impl<T: Perturb, const N: usize> Perturb for [T; N] {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        self.iter_mut().for_each(|value| value.perturb(noise));
+    }
+}
+
+impl<T: Perturb> Perturb for Vec<T> {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        self.iter_mut().for_each(|value| value.perturb(noise));
+    }
+}
+
+impl<A: Perturb, B: Perturb> Perturb for (A, B) {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        self.0.perturb(noise);
+        self.1.perturb(noise);
+    }
+}
+
+impl<A: Perturb, B: Perturb, C: Perturb> Perturb for (A, B, C) {
+    fn perturb(&mut self, noise: &mut dyn FnMut(f64) -> f64) {
+        self.0.perturb(noise);
+        self.1.perturb(noise);
+        self.2.perturb(noise);
+    }
+}
+
+/// How a sensor is degraded, all the effects are off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoiseModel {
+    /// Standard deviation of the white noise added to every measurement.
+    #[serde(default)]
+    pub gaussian_std: f64,
+    /// Standard deviation of the step of the random walk followed by the bias of every measurement
+    /// at each message.
+    #[serde(default)]
+    pub bias_drift_std: f64,
+    /// Probability to drop a message.
+    #[serde(default)]
+    pub dropout: f64,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl NoiseModel {
+    pub fn with_gaussian(mut self, std: f64) -> Self {
+        self.gaussian_std = std;
+        self
+    }
+
+    pub fn with_bias_drift(mut self, std: f64) -> Self {
+        self.bias_drift_std = std;
+        self
+    }
+
+    pub fn with_dropout(mut self, probability: f64) -> Self {
+        self.dropout = probability;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// SplitMix64, small and good enough for noise.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal with the Box-Muller transform.
+    fn normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.uniform(); // (0, 1] for the log.
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+/// Degrades the messages of one source following a [NoiseModel].
+#[derive(Debug, Clone)]
+pub struct SensorNoise {
+    model: NoiseModel,
+    rng: Rng,
+    /// The bias of every measurement, in the visiting order of [Perturb].
+    biases: Vec<f64>,
+    dropped: u64,
+}
+
+impl SensorNoise {
+    pub fn new(model: NoiseModel) -> Self {
+        Self {
+            model,
+            rng: Rng(model.seed),
+            biases: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Number of messages dropped so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// The current bias of the measurements.
+    pub fn biases(&self) -> &[f64] {
+        &self.biases
+    }
+
+    /// Degrades the message in place, an empty message is left as is.
+    pub fn apply<T: CuMsgPayload + Perturb>(&mut self, msg: &mut CuMsg<T>) {
+        let Some(payload) = msg.payload_mut() else {
+            return;
+        };
+        if self.model.dropout > 0.0 && self.rng.uniform() < self.model.dropout {
+            self.dropped += 1;
+            msg.clear_payload();
+            return;
+        }
+        self.perturb(payload);
+    }
+
+    /// Degrades a payload, without the dropouts.
+    pub fn perturb<T: Perturb>(&mut self, payload: &mut T) {
+        let model = self.model;
+        let rng = &mut self.rng;
+        let biases = &mut self.biases;
+        let mut index = 0;
+        payload.perturb(&mut |value| {
+            if index == biases.len() {
+                biases.push(0.0);
+            }
+            biases[index] += model.bias_drift_std * rng.normal();
+            let noisy = value + biases[index] + model.gaussian_std * rng.normal();
+            index += 1;
+            noisy
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaussian() {
+        let mut noise = SensorNoise::new(NoiseModel::default().with_gaussian(2.0).with_seed(42));
+        let mut values = vec![10.0f64; 10_000];
+        noise.perturb(&mut values);
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+        assert!((mean - 10.0).abs() < 0.1, "mean {mean}");
+        assert!((std - 2.0).abs() < 0.1, "std {std}");
+        assert!(noise.biases().iter().all(|bias| *bias == 0.0));
+    }
+
+    #[test]
+    fn test_bias_drift() {
+        let mut noise = SensorNoise::new(NoiseModel::default().with_bias_drift(0.1).with_seed(7));
+        let mut last = (0.0f32, [0.0f64; 2]);
+        for _ in 0..100 {
+            last = (0.0, [0.0; 2]);
+            noise.perturb(&mut last);
+        }
+        // Without white noise the measurements are exactly their biases.
+        assert_eq!(noise.biases().len(), 3);
+        assert_eq!(noise.biases()[1], last.1[0]);
+        assert_ne!(last.1[0], last.1[1]);
+    }
+
+    #[test]
+    fn test_dropout_is_reproducible() {
+        let run = || {
+            let mut noise = SensorNoise::new(NoiseModel::default().with_dropout(0.25).with_seed(3));
+            let received: Vec<bool> = (0..1000)
+                .map(|_| {
+                    let mut msg = CuMsg::new(Some(1.0f64));
+                    noise.apply(&mut msg);
+                    msg.payload().is_some()
+                })
+                .collect();
+            (received, noise.dropped())
+        };
+        let (received, dropped) = run();
+        assert_eq!(received.iter().filter(|r| !**r).count() as u64, dropped);
+        assert!((200..300).contains(&dropped), "dropped {dropped}");
+        assert_eq!(run().0, received);
+        // The payload itself is untouched by a model without noise.
+        let mut msg = CuMsg::new(Some(1.0f64));
+        SensorNoise::new(NoiseModel::default()).apply(&mut msg);
+        assert_eq!(msg.payload(), Some(&1.0));
+    }
+}
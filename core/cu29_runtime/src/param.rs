@@ -0,0 +1,83 @@
+use bincode::{decode_from_slice, encode_to_vec};
+use clap::{Parser, Subcommand};
+use cu29_runtime::params::{ParamRequest, ParamResponse, ParamValue, MAX_PARAM_DATAGRAM};
+use cu29_traits::CuWireFormat;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the parameters of a running application with their current value
+    List {
+        /// The address the application listens to for parameter requests
+        app: SocketAddr,
+    },
+    /// Prints the current value of a parameter
+    Get {
+        /// The address the application listens to for parameter requests
+        app: SocketAddr,
+        name: String,
+    },
+    /// Sets a parameter, the value is checked by the application against its kind and range
+    Set {
+        /// The address the application listens to for parameter requests
+        app: SocketAddr,
+        name: String,
+        /// true, 42 or 4.2
+        value: ParamValue,
+    },
+}
+
+fn exchange(app: SocketAddr, request: &ParamRequest) -> std::io::Result<ParamResponse> {
+    let bind: SocketAddr = if app.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    // The application answers at the end of its next copper list.
+    socket.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let bytes = encode_to_vec(request, CuWireFormat::CONFIG).expect("Failed to encode the request");
+    socket.send_to(&bytes, app)?;
+
+    let mut buffer = vec![0u8; MAX_PARAM_DATAGRAM];
+    let (len, _) = socket.recv_from(&mut buffer)?;
+    decode_from_slice::<ParamResponse, _>(&buffer[..len], CuWireFormat::CONFIG)
+        .map(|(response, _)| response)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Lists, gets and sets the parameters of a running Copper application, like ros2 param.
+fn main() -> std::io::Result<()> {
+    let (app, request) = match Args::parse().command {
+        Command::List { app } => (app, ParamRequest::List),
+        Command::Get { app, name } => (app, ParamRequest::Get { name }),
+        Command::Set { app, name, value } => (app, ParamRequest::Set { name, value }),
+    };
+    match exchange(app, &request)? {
+        ParamResponse::Params(params) => {
+            for param in params {
+                let range = param
+                    .range
+                    .map(|(min, max)| format!(" [{min}, {max}]"))
+                    .unwrap_or_default();
+                println!(
+                    "{} = {} (default {}{range}) {}",
+                    param.name, param.value, param.default, param.description
+                );
+            }
+        }
+        ParamResponse::Value(value) => println!("{value}"),
+        ParamResponse::Done => {}
+        ParamResponse::Error(message) => return Err(Error::other(message)),
+    }
+    Ok(())
+}
@@ -0,0 +1,470 @@
+//! A lightweight parameter server for runtime tunable values (gains, thresholds...).
+//! Tasks register their parameters, usually in `new`, and keep the returned [CuParam] handle to read
+//! the current value in `process`. External clients (CLI, network bridges, UIs) can list, get and
+//! set them by name. Every mutation is written to the unified log by the runtime at the end of the
+//! copperlist during which it happened so a run can be reproduced.
+//!
+//! The application lets the clients reach its parameters with [CuParamServer::listen], the
+//! `cu29-param` tool then lists, gets and sets them:
+//!
+//! ```text
+//! cu29-param list 127.0.0.1:7401
+//! cu29-param set 127.0.0.1:7401 balance.kp 0.02
+//! ```
+
+use crate::log::*;
+use bincode::{decode_from_slice, encode_to_vec, Decode, Encode};
+use cu29_traits::{CuError, CuResult, CuWireFormat};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Maximum size of a datagram exchanged with a parameter client.
+pub const MAX_PARAM_DATAGRAM: usize = 65507;
+
+/// The value of a parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub enum ParamValue {
+    Bool(bool),
+    I64(i64),
+    F64(f64),
+}
+
+impl ParamValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ParamValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            ParamValue::I64(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Integers are also accepted as floats.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ParamValue::I64(i) => Some(*i as f64),
+            ParamValue::F64(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    fn same_kind(&self, other: &ParamValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl Display for ParamValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamValue::Bool(b) => write!(f, "{b}"),
+            ParamValue::I64(i) => write!(f, "{i}"),
+            ParamValue::F64(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// Parses "true", "42" or "4.2", this is what command line clients use.
+impl FromStr for ParamValue {
+    type Err = CuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(b) = s.parse::<bool>() {
+            return Ok(ParamValue::Bool(b));
+        }
+        if let Ok(i) = s.parse::<i64>() {
+            return Ok(ParamValue::I64(i));
+        }
+        s.parse::<f64>()
+            .map(ParamValue::F64)
+            .map_err(|_| CuError::from(format!("Could not parse \"{s}\" as a parameter value.")))
+    }
+}
+
+impl From<bool> for ParamValue {
+    fn from(value: bool) -> Self {
+        ParamValue::Bool(value)
+    }
+}
+
+impl From<i64> for ParamValue {
+    fn from(value: i64) -> Self {
+        ParamValue::I64(value)
+    }
+}
+
+impl From<f64> for ParamValue {
+    fn from(value: f64) -> Self {
+        ParamValue::F64(value)
+    }
+}
+
+/// Everything a client needs to know about a parameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct ParamDescriptor {
+    pub name: String,
+    pub value: ParamValue,
+    pub default: ParamValue,
+    /// Inclusive range for the numerical parameters.
+    pub range: Option<(f64, f64)>,
+    pub description: String,
+}
+
+/// The handle a task keeps to read the current value of one of its parameters.
+#[derive(Debug, Clone)]
+pub struct CuParam {
+    value: Arc<Mutex<ParamValue>>,
+}
+
+impl CuParam {
+    pub fn get(&self) -> ParamValue {
+        *self.value.lock().unwrap()
+    }
+
+    /// Convenience for the numerical parameters, the kind is checked at registration.
+    pub fn get_f64(&self) -> f64 {
+        self.get().as_f64().unwrap_or_default()
+    }
+
+    pub fn get_i64(&self) -> i64 {
+        self.get().as_i64().unwrap_or_default()
+    }
+
+    pub fn get_bool(&self) -> bool {
+        self.get().as_bool().unwrap_or_default()
+    }
+}
+
+/// A mutation of a parameter, drained and logged by the runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamChange {
+    pub name: String,
+    pub previous: ParamValue,
+    pub value: ParamValue,
+}
+
+struct RegisteredParam {
+    descriptor: ParamDescriptor,
+    value: Arc<Mutex<ParamValue>>,
+}
+
+#[derive(Default)]
+struct ParamRegistry {
+    params: Vec<RegisteredParam>,
+    pending_changes: Vec<ParamChange>,
+}
+
+static PARAM_REGISTRY: OnceLock<Mutex<ParamRegistry>> = OnceLock::new();
+
+fn registry() -> &'static Mutex<ParamRegistry> {
+    PARAM_REGISTRY.get_or_init(|| Mutex::new(ParamRegistry::default()))
+}
+
+fn check_range(name: &str, value: &ParamValue, range: Option<(f64, f64)>) -> CuResult<()> {
+    if let (Some((min, max)), Some(v)) = (range, value.as_f64()) {
+        if v < min || v > max {
+            return Err(CuError::from(format!(
+                "Value {value} is out of the [{min}, {max}] range of parameter {name}."
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Registers a parameter and returns the handle to read it.
+/// Registering again an existing parameter (a task being recreated for example) returns a handle
+/// to the existing one as long as the kind of value matches.
+pub fn register_param(
+    name: &str,
+    default: impl Into<ParamValue>,
+    range: Option<(f64, f64)>,
+    description: &str,
+) -> CuResult<CuParam> {
+    let default = default.into();
+    if matches!(default, ParamValue::Bool(_)) && range.is_some() {
+        return Err(CuError::from(format!(
+            "Parameter {name} is a boolean, it cannot have a range."
+        )));
+    }
+    check_range(name, &default, range)?;
+
+    let mut registry = registry().lock().unwrap();
+    if let Some(existing) = registry.params.iter().find(|p| p.descriptor.name == name) {
+        if !existing.descriptor.default.same_kind(&default) {
+            return Err(CuError::from(format!(
+                "Parameter {name} is already registered with a different kind of value."
+            )));
+        }
+        return Ok(CuParam {
+            value: existing.value.clone(),
+        });
+    }
+    let value = Arc::new(Mutex::new(default));
+    registry.params.push(RegisteredParam {
+        descriptor: ParamDescriptor {
+            name: name.to_string(),
+            value: default,
+            default,
+            range,
+            description: description.to_string(),
+        },
+        value: value.clone(),
+    });
+    Ok(CuParam { value })
+}
+
+/// Lists all the registered parameters with their current value.
+pub fn list_params() -> Vec<ParamDescriptor> {
+    registry()
+        .lock()
+        .unwrap()
+        .params
+        .iter()
+        .map(|p| ParamDescriptor {
+            value: *p.value.lock().unwrap(),
+            ..p.descriptor.clone()
+        })
+        .collect()
+}
+
+/// Gets the current value of a parameter.
+pub fn get_param(name: &str) -> Option<ParamValue> {
+    registry()
+        .lock()
+        .unwrap()
+        .params
+        .iter()
+        .find(|p| p.descriptor.name == name)
+        .map(|p| *p.value.lock().unwrap())
+}
+
+/// Sets a parameter, the value is validated against the kind and range of the parameter.
+/// Integers are accepted for the floating point parameters.
+pub fn set_param(name: &str, value: impl Into<ParamValue>) -> CuResult<()> {
+    let mut value = value.into();
+    let mut registry = registry().lock().unwrap();
+    let param = registry
+        .params
+        .iter()
+        .find(|p| p.descriptor.name == name)
+        .ok_or_else(|| CuError::from(format!("Unknown parameter {name}.")))?;
+
+    if let (ParamValue::F64(_), ParamValue::I64(i)) = (&param.descriptor.default, &value) {
+        value = ParamValue::F64(*i as f64);
+    }
+    if !param.descriptor.default.same_kind(&value) {
+        return Err(CuError::from(format!(
+            "Parameter {name} expects a value like {}, got {value}.",
+            param.descriptor.default
+        )));
+    }
+    check_range(name, &value, param.descriptor.range)?;
+
+    let previous = std::mem::replace(&mut *param.value.lock().unwrap(), value);
+    registry.pending_changes.push(ParamChange {
+        name: name.to_string(),
+        previous,
+        value,
+    });
+    Ok(())
+}
+
+/// Drains the parameter changes that happened since the last call.
+pub(crate) fn take_param_changes() -> Vec<ParamChange> {
+    match PARAM_REGISTRY.get() {
+        Some(registry) => std::mem::take(&mut registry.lock().unwrap().pending_changes),
+        None => Vec::new(),
+    }
+}
+
+/// Logs a parameter change to the unified logger.
+pub(crate) fn log_param_change(change: &ParamChange, culistid: u32) {
+    debug!(
+        "Parameter {} changed from {} to {} at CL {}.",
+        change.name.as_str(),
+        change.previous,
+        change.value,
+        culistid
+    );
+}
+
+/// The requests a client sends to the application listening for parameter requests.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum ParamRequest {
+    List,
+    Get { name: String },
+    Set { name: String, value: ParamValue },
+}
+
+/// The answers of the application to the [ParamRequest]s.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum ParamResponse {
+    Params(Vec<ParamDescriptor>),
+    Value(ParamValue),
+    Done,
+    Error(String),
+}
+
+impl ParamRequest {
+    pub fn handle(self) -> ParamResponse {
+        match self {
+            ParamRequest::List => ParamResponse::Params(list_params()),
+            ParamRequest::Get { name } => match get_param(&name) {
+                Some(value) => ParamResponse::Value(value),
+                None => ParamResponse::Error(format!("Unknown parameter {name}.")),
+            },
+            ParamRequest::Set { name, value } => match set_param(&name, value) {
+                Ok(()) => ParamResponse::Done,
+                Err(e) => ParamResponse::Error(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Answers the [ParamRequest]s of the remote clients of a running application.
+/// The requests are handled at the end of the copper lists so a change is logged along the copper
+/// list that saw it first.
+#[derive(Default)]
+pub struct CuParamServer {
+    server: Option<UdpSocket>,
+}
+
+impl CuParamServer {
+    /// Accepts [ParamRequest]s from the clients on this address, see [CuParamServer::poll].
+    pub fn listen(&mut self, addr: impl ToSocketAddrs) -> CuResult<SocketAddr> {
+        let socket = UdpSocket::bind(addr)
+            .map_err(|e| CuError::new_with_cause("Could not bind the parameter server", e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| CuError::new_with_cause("Could not configure the parameter server", e))?;
+        let local = socket
+            .local_addr()
+            .map_err(|e| CuError::new_with_cause("Could not configure the parameter server", e))?;
+        self.server = Some(socket);
+        Ok(local)
+    }
+
+    /// Handles the pending requests of the clients, this never blocks.
+    pub fn poll(&self) {
+        let Some(server) = &self.server else {
+            return;
+        };
+        let mut buffer = [0u8; 1024];
+        while let Ok((len, peer)) = server.recv_from(&mut buffer) {
+            // Garbage from the network is just ignored.
+            let Ok((request, _)) =
+                decode_from_slice::<ParamRequest, _>(&buffer[..len], CuWireFormat::CONFIG)
+            else {
+                continue;
+            };
+            let mut response = request.handle();
+            let mut bytes = encode_to_vec(&response, CuWireFormat::CONFIG).unwrap_or_default();
+            if bytes.len() > MAX_PARAM_DATAGRAM {
+                response = ParamResponse::Error(format!(
+                    "The list of parameters does not fit in a datagram ({} bytes).",
+                    bytes.len()
+                ));
+                bytes = encode_to_vec(&response, CuWireFormat::CONFIG).unwrap_or_default();
+            }
+            let _ = server.send_to(&bytes, peer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_get_set() {
+        let kp = register_param("test.kp", 1.0, Some((0.0, 10.0)), "proportional gain").unwrap();
+        assert_eq!(kp.get_f64(), 1.0);
+
+        set_param("test.kp", 2.5).unwrap();
+        assert_eq!(kp.get_f64(), 2.5);
+        assert_eq!(get_param("test.kp"), Some(ParamValue::F64(2.5)));
+
+        // integers are promoted to floats
+        set_param("test.kp", 3i64).unwrap();
+        assert_eq!(kp.get_f64(), 3.0);
+
+        assert!(set_param("test.kp", 11.0).is_err());
+        assert!(set_param("test.kp", true).is_err());
+        assert!(set_param("test.unknown", 1.0).is_err());
+        assert_eq!(kp.get_f64(), 3.0);
+
+        let again = register_param("test.kp", 1.0, Some((0.0, 10.0)), "").unwrap();
+        assert_eq!(again.get_f64(), 3.0);
+        assert!(register_param("test.kp", false, None, "").is_err());
+
+        let descriptor = list_params()
+            .into_iter()
+            .find(|p| p.name == "test.kp")
+            .unwrap();
+        assert_eq!(descriptor.value, ParamValue::F64(3.0));
+        assert_eq!(descriptor.default, ParamValue::F64(1.0));
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(
+            "true".parse::<ParamValue>().unwrap(),
+            ParamValue::Bool(true)
+        );
+        assert_eq!("42".parse::<ParamValue>().unwrap(), ParamValue::I64(42));
+        assert_eq!("4.2".parse::<ParamValue>().unwrap(), ParamValue::F64(4.2));
+        assert!("four".parse::<ParamValue>().is_err());
+    }
+
+    #[test]
+    fn test_server() {
+        let mut server = CuParamServer::default();
+        let addr = server.listen("127.0.0.1:0").unwrap();
+        let gain = register_param("test.server.gain", 1.0, Some((0.0, 2.0)), "a gain").unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(std::time::Duration::from_secs(1)))
+            .unwrap();
+        let exchange = |request: ParamRequest| {
+            let bytes = encode_to_vec(&request, CuWireFormat::CONFIG).unwrap();
+            client.send_to(&bytes, addr).unwrap();
+            // Waits for the request to arrive, the server never blocks.
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            server.poll();
+            let mut buffer = vec![0u8; MAX_PARAM_DATAGRAM];
+            let (len, _) = client.recv_from(&mut buffer).unwrap();
+            decode_from_slice::<ParamResponse, _>(&buffer[..len], CuWireFormat::CONFIG)
+                .unwrap()
+                .0
+        };
+
+        let set = ParamRequest::Set {
+            name: "test.server.gain".to_string(),
+            value: ParamValue::F64(1.5),
+        };
+        assert_eq!(exchange(set), ParamResponse::Done);
+        assert_eq!(gain.get_f64(), 1.5);
+        let get = ParamRequest::Get {
+            name: "test.server.gain".to_string(),
+        };
+        assert_eq!(exchange(get), ParamResponse::Value(ParamValue::F64(1.5)));
+        let out_of_range = ParamRequest::Set {
+            name: "test.server.gain".to_string(),
+            value: ParamValue::F64(3.0),
+        };
+        assert!(matches!(exchange(out_of_range), ParamResponse::Error(_)));
+        let ParamResponse::Params(params) = exchange(ParamRequest::List) else {
+            panic!("expected the list of the parameters");
+        };
+        assert!(params.iter().any(|p| p.name == "test.server.gain"));
+    }
+}
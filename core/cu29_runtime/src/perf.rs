@@ -0,0 +1,297 @@
+//! Hardware counters sampled around the process() of every task, enabled with the `perf` feature.
+//! The CPU cycles, instructions and cache misses of each task are accumulated in its
+//! [crate::introspection::TaskStats] to find where the heavy tasks spend their time.
+//!
+//! The counters are opened with perf_event_open on Linux and only count the user space of the
+//! runtime thread. If the kernel refuses them (see /proc/sys/kernel/perf_event_paranoid) or on
+//! other platforms, the runtime runs normally and the counters stay at zero.
+
+use std::io;
+use std::ops::{AddAssign, Sub};
+
+/// The counters of one process() call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerfSample {
+    pub cycles: u64,
+    pub instructions: u64,
+    pub cache_misses: u64,
+}
+
+impl Sub for PerfSample {
+    type Output = PerfSample;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        PerfSample {
+            cycles: self.cycles.saturating_sub(rhs.cycles),
+            instructions: self.instructions.saturating_sub(rhs.instructions),
+            cache_misses: self.cache_misses.saturating_sub(rhs.cache_misses),
+        }
+    }
+}
+
+impl AddAssign for PerfSample {
+    fn add_assign(&mut self, rhs: Self) {
+        self.cycles += rhs.cycles;
+        self.instructions += rhs.instructions;
+        self.cache_misses += rhs.cache_misses;
+    }
+}
+
+/// The hardware counters accumulated for a task.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerfStats {
+    /// Number of sampled process() calls.
+    pub samples: u64,
+    pub last: PerfSample,
+    pub total: PerfSample,
+}
+
+impl PerfStats {
+    pub fn record(&mut self, sample: PerfSample) {
+        self.samples += 1;
+        self.last = sample;
+        self.total += sample;
+    }
+
+    pub fn mean(&self) -> PerfSample {
+        let mean = |total: u64| total.checked_div(self.samples).unwrap_or_default();
+        PerfSample {
+            cycles: mean(self.total.cycles),
+            instructions: mean(self.total.instructions),
+            cache_misses: mean(self.total.cache_misses),
+        }
+    }
+
+    /// Instructions per cycle, a low value points to a task stalling on memory.
+    pub fn ipc(&self) -> f64 {
+        if self.total.cycles == 0 {
+            return 0.0;
+        }
+        self.total.instructions as f64 / self.total.cycles as f64
+    }
+}
+
+/// The counters of the runtime thread, sampled before and after each process() call.
+pub struct CuPerfCounters {
+    group: Option<PerfGroup>,
+    started: PerfSample,
+}
+
+impl Default for CuPerfCounters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CuPerfCounters {
+    /// Opens the counters for the calling thread, this needs to be called from the thread running the tasks.
+    pub fn new() -> Self {
+        Self {
+            group: PerfGroup::open().ok(),
+            started: PerfSample::default(),
+        }
+    }
+
+    /// False if the counters could not be opened, all the samples are then zero.
+    pub fn is_available(&self) -> bool {
+        self.group.is_some()
+    }
+
+    pub fn start(&mut self) {
+        if let Some(group) = &self.group {
+            self.started = group.read().unwrap_or_default();
+        }
+    }
+
+    /// The counters since the last start().
+    pub fn stop(&mut self) -> PerfSample {
+        match &self.group {
+            Some(group) => group
+                .read()
+                .map(|now| now - self.started)
+                .unwrap_or_default(),
+            None => PerfSample::default(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    // From linux/perf_event.h.
+    pub const PERF_TYPE_HARDWARE: u32 = 0;
+    pub const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    pub const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    pub const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+    pub const PERF_FORMAT_GROUP: u64 = 1 << 3;
+    pub const FLAG_DISABLED: u64 = 1 << 0;
+    pub const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    pub const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+    pub const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+    pub const PERF_IOC_FLAG_GROUP: libc::c_ulong = 1;
+
+    /// The first published version of perf_event_attr (PERF_ATTR_SIZE_VER1), the kernel zero
+    /// extends the fields it doesn't get.
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct PerfEventAttr {
+        pub type_: u32,
+        pub size: u32,
+        pub config: u64,
+        pub sample_period: u64,
+        pub sample_type: u64,
+        pub read_format: u64,
+        pub flags: u64,
+        pub wakeup_events: u32,
+        pub bp_type: u32,
+        pub config1: u64,
+        pub config2: u64,
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct PerfGroup {
+    /// The cycles counter is the group leader, the group is read in one go from it.
+    fds: [std::os::fd::OwnedFd; 3],
+}
+
+#[cfg(target_os = "linux")]
+impl PerfGroup {
+    fn open() -> io::Result<Self> {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+        use sys::*;
+
+        let open = |config: u64, group_fd: libc::c_int| -> io::Result<OwnedFd> {
+            let attr = PerfEventAttr {
+                type_: PERF_TYPE_HARDWARE,
+                size: size_of::<PerfEventAttr>() as u32,
+                config,
+                read_format: PERF_FORMAT_GROUP,
+                flags: if group_fd == -1 { FLAG_DISABLED } else { 0 }
+                    | FLAG_EXCLUDE_KERNEL
+                    | FLAG_EXCLUDE_HV,
+                ..Default::default()
+            };
+            // SAFETY: attr is a valid perf_event_attr of the size it declares, pid 0 and cpu -1
+            // count the calling thread on any CPU.
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_perf_event_open,
+                    &attr as *const PerfEventAttr,
+                    0,
+                    -1,
+                    group_fd,
+                    0 as libc::c_ulong,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: the syscall returned a new file descriptor we own.
+            Ok(unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) })
+        };
+
+        let cycles = open(PERF_COUNT_HW_CPU_CYCLES, -1)?;
+        let instructions = open(PERF_COUNT_HW_INSTRUCTIONS, cycles.as_raw_fd())?;
+        let cache_misses = open(PERF_COUNT_HW_CACHE_MISSES, cycles.as_raw_fd())?;
+        // The counters run continuously, the samples are the differences of two reads.
+        // SAFETY: the fd is a perf event group leader.
+        if unsafe {
+            libc::ioctl(
+                cycles.as_raw_fd(),
+                PERF_EVENT_IOC_ENABLE as _,
+                PERF_IOC_FLAG_GROUP,
+            )
+        } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            fds: [cycles, instructions, cache_misses],
+        })
+    }
+
+    fn read(&self) -> io::Result<PerfSample> {
+        use std::os::fd::AsRawFd;
+
+        // PERF_FORMAT_GROUP: the number of counters followed by their values.
+        let mut values = [0u64; 4];
+        // SAFETY: the buffer is large enough for the 3 counters of the group.
+        let len = unsafe {
+            libc::read(
+                self.fds[0].as_raw_fd(),
+                values.as_mut_ptr() as *mut libc::c_void,
+                size_of_val(&values),
+            )
+        };
+        if len != size_of_val(&values) as isize {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PerfSample {
+            cycles: values[1],
+            instructions: values[2],
+            cache_misses: values[3],
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct PerfGroup;
+
+#[cfg(not(target_os = "linux"))]
+impl PerfGroup {
+    fn open() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The hardware counters are only supported on Linux.",
+        ))
+    }
+
+    fn read(&self) -> io::Result<PerfSample> {
+        Ok(PerfSample::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perf_stats() {
+        let mut stats = PerfStats::default();
+        assert_eq!(stats.mean(), PerfSample::default());
+        assert_eq!(stats.ipc(), 0.0);
+        for cycles in [100, 300] {
+            stats.record(PerfSample {
+                cycles,
+                instructions: cycles * 2,
+                cache_misses: 1,
+            });
+        }
+        assert_eq!(stats.samples, 2);
+        assert_eq!(stats.last.cycles, 300);
+        assert_eq!(
+            stats.mean(),
+            PerfSample {
+                cycles: 200,
+                instructions: 400,
+                cache_misses: 1,
+            }
+        );
+        assert_eq!(stats.ipc(), 2.0);
+    }
+
+    #[test]
+    fn test_perf_counters() {
+        let mut counters = CuPerfCounters::new();
+        if !counters.is_available() {
+            // The CI machines usually don't expose the hardware counters.
+            return;
+        }
+        counters.start();
+        let sum: u64 = (0..100_000u64).map(std::hint::black_box).sum();
+        std::hint::black_box(sum);
+        let sample = counters.stop();
+        assert!(sample.instructions > 100_000);
+        assert!(sample.cycles > 0);
+    }
+}
@@ -0,0 +1,247 @@
+//! Pipelined execution of the sources.
+//! Without pipelining, a cycle acquires the data of its sources and then runs the rest of the graph:
+//! acquisition and compute are serialized. With `runtime: (pipelined: true)` in the configuration,
+//! every source is wrapped in a [CuPipelinedSrc] that runs the preprocess/process/postprocess of the
+//! source on its own thread: while the downstream tasks of cycle N are processing, the source is
+//! already acquiring the data of cycle N+1.
+//!
+//! The price is one cycle of latency on the sources and a source state that lives on the worker
+//! thread, so it is not part of the frozen task states.
+
+use crate::config::{ComponentConfig, ConfigKey};
+use crate::cutask::{CuMsg, CuMsgPayload, CuSrcTask, Freezable};
+use crate::log::*;
+use cu29_clock::RobotClock;
+use cu29_traits::{CuError, CuResult};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+
+enum Request {
+    Acquire,
+    Stop,
+}
+
+/// The result of one acquisition done ahead of time by the worker.
+struct Acquisition<P: CuMsgPayload> {
+    result: CuResult<()>,
+    msg: CuMsg<P>,
+}
+
+struct Worker<S, P: CuMsgPayload> {
+    requests: Sender<Request>,
+    acquisitions: Receiver<Acquisition<P>>,
+    handle: JoinHandle<(S, CuResult<()>)>,
+}
+
+/// Runs the source `S` producing `P` messages one cycle ahead on a worker thread.
+/// The process of this task hands over the message acquired during the previous cycle and
+/// immediately asks the worker to acquire the next one.
+pub struct CuPipelinedSrc<S, P: CuMsgPayload> {
+    /// The source while it is not running on the worker.
+    idle: Option<S>,
+    worker: Option<Worker<S, P>>,
+}
+
+impl<S, P: CuMsgPayload> Freezable for CuPipelinedSrc<S, P> {}
+
+fn acquire<'m, S, P>(source: &mut S, clock: &RobotClock, msg: &'m mut CuMsg<P>) -> CuResult<()>
+where
+    S: CuSrcTask<'m, Output = &'m mut CuMsg<P>>,
+    P: CuMsgPayload + 'm,
+{
+    source.preprocess(clock)?;
+    source.process(clock, msg)?;
+    source.postprocess(clock)
+}
+
+fn worker_loop<S, P>(
+    mut source: S,
+    clock: RobotClock,
+    requests: Receiver<Request>,
+    acquisitions: Sender<Acquisition<P>>,
+) -> (S, CuResult<()>)
+where
+    S: for<'m> CuSrcTask<'m, Output = &'m mut CuMsg<P>>,
+    P: CuMsgPayload,
+{
+    // A closed channel means the pipelined task was dropped without being stopped.
+    while let Ok(Request::Acquire) = requests.recv() {
+        let mut msg = CuMsg::<P>::default();
+        let result = acquire(&mut source, &clock, &mut msg);
+        if acquisitions.send(Acquisition { result, msg }).is_err() {
+            break;
+        }
+    }
+    let result = source.stop(&clock);
+    (source, result)
+}
+
+impl<S, P: CuMsgPayload> CuPipelinedSrc<S, P> {
+    fn worker(&mut self) -> CuResult<&mut Worker<S, P>> {
+        self.worker
+            .as_mut()
+            .ok_or_else(|| CuError::from("The pipelined source has not been started."))
+    }
+}
+
+impl<'cl, S, P> CuSrcTask<'cl> for CuPipelinedSrc<S, P>
+where
+    S: for<'m> CuSrcTask<'m, Output = &'m mut CuMsg<P>> + Send + 'static,
+    P: CuMsgPayload + Send + 'static,
+{
+    type Output = &'cl mut CuMsg<P>;
+
+    const CONFIG_SCHEMA: Option<&'static [ConfigKey]> = <S as CuSrcTask<'cl>>::CONFIG_SCHEMA;
+
+    fn new(config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            idle: Some(S::new(config)?),
+            worker: None,
+        })
+    }
+
+    fn start(&mut self, clock: &RobotClock) -> CuResult<()> {
+        let mut source = self
+            .idle
+            .take()
+            .ok_or_else(|| CuError::from("The pipelined source is already started."))?;
+        source.start(clock)?;
+        let (requests, worker_requests) = channel();
+        let (worker_acquisitions, acquisitions) = channel();
+        let worker_clock = clock.clone();
+        let handle = std::thread::Builder::new()
+            .name("cu-pipelined-src".to_string())
+            .spawn(move || worker_loop(source, worker_clock, worker_requests, worker_acquisitions))
+            .map_err(|e| CuError::new_with_cause("Could not spawn the pipelined source", e))?;
+        // Prefetches the first cycle.
+        requests
+            .send(Request::Acquire)
+            .map_err(|_| CuError::from("The pipelined source worker stopped."))?;
+        self.worker = Some(Worker {
+            requests,
+            acquisitions,
+            handle,
+        });
+        debug!("Pipelined source started.");
+        Ok(())
+    }
+
+    fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+        let worker = self.worker()?;
+        let Acquisition { result, mut msg } = worker
+            .acquisitions
+            .recv()
+            .map_err(|_| CuError::from("The pipelined source worker stopped."))?;
+        // Lets the source acquire the next cycle while the rest of the graph processes this one.
+        worker
+            .requests
+            .send(Request::Acquire)
+            .map_err(|_| CuError::from("The pipelined source worker stopped."))?;
+        // The process time is measured by the runtime, only the acquired data is handed over.
+        *new_msg.payload_mut() = msg.payload_mut().take();
+        new_msg.metadata.tov = msg.metadata.tov;
+        new_msg.metadata.status_txt = msg.metadata.status_txt;
+        result
+    }
+
+    fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        let Some(worker) = self.worker.take() else {
+            return Ok(());
+        };
+        // The worker might be in the middle of an acquisition for a cycle that will never come.
+        let _ = worker.requests.send(Request::Stop);
+        drop(worker.acquisitions);
+        let (source, result) = worker
+            .handle
+            .join()
+            .map_err(|_| CuError::from("The pipelined source worker panicked."))?;
+        self.idle = Some(source);
+        debug!("Pipelined source stopped.");
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_msg;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    static STOPS: AtomicU32 = AtomicU32::new(0);
+
+    struct Counter {
+        count: u32,
+        threads: Arc<std::sync::Mutex<Vec<std::thread::ThreadId>>>,
+    }
+
+    impl Freezable for Counter {}
+
+    impl<'cl> CuSrcTask<'cl> for Counter {
+        type Output = output_msg!('cl, u32);
+
+        fn new(_config: Option<&ComponentConfig>) -> CuResult<Self> {
+            Ok(Self {
+                count: 0,
+                threads: Arc::default(),
+            })
+        }
+
+        fn process(&mut self, _clock: &RobotClock, new_msg: Self::Output) -> CuResult<()> {
+            self.threads
+                .lock()
+                .unwrap()
+                .push(std::thread::current().id());
+            self.count += 1;
+            if self.count == 3 {
+                return Err("third acquisition failed".into());
+            }
+            new_msg.set_payload(self.count);
+            Ok(())
+        }
+
+        fn stop(&mut self, _clock: &RobotClock) -> CuResult<()> {
+            STOPS.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_pipelined_source() {
+        let (clock, _) = RobotClock::mock();
+        let mut src = CuPipelinedSrc::<Counter, u32>::new(None).unwrap();
+        let threads = src.idle.as_ref().unwrap().threads.clone();
+        let mut msg = CuMsg::<u32>::default();
+        assert!(src.process(&clock, &mut msg).is_err()); // not started
+
+        src.start(&clock).unwrap();
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), Some(&1));
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), Some(&2));
+        let error = src.process(&clock, &mut msg).unwrap_err();
+        assert!(error.to_string().contains("third acquisition failed"));
+        assert_eq!(msg.payload(), None);
+        src.process(&clock, &mut msg).unwrap();
+        assert_eq!(msg.payload(), Some(&4));
+        src.stop(&clock).unwrap();
+        assert_eq!(STOPS.load(Ordering::SeqCst), 1);
+
+        // The acquisitions never ran on the calling thread.
+        assert!(threads
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|id| *id != std::thread::current().id()));
+
+        // It can be restarted, the source keeps its state.
+        src.start(&clock).unwrap();
+        src.process(&clock, &mut msg).unwrap();
+        assert!(*msg.payload().unwrap() > 4);
+        src.stop(&clock).unwrap();
+        assert_eq!(STOPS.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,122 @@
+//! Run profiles: the same binary runs a degraded configuration of its graph selected when it
+//! starts, for example without the camera when it fails its hardware checks. The profiles are
+//! declared in the configuration (see [crate::config::ProfileConfig]) and the one to run with is,
+//! by order of precedence:
+//!
+//! - the one given to `with_profile` on the builder of the application,
+//! - the one in the `COPPER_PROFILE` environment variable,
+//! - the one of the `profile` field of the configuration.
+//!
+//! The disabled tasks are still created but neither started nor run, their outputs are empty and
+//! not ready. The disabled connections deliver empty messages to their destination.
+
+use crate::config::CuConfig;
+use cu29_traits::{CuError, CuResult};
+use petgraph::visit::{EdgeIndexable, NodeIndexable};
+
+/// The environment variable selecting the profile when the builder does not.
+pub const PROFILE_ENV: &str = "COPPER_PROFILE";
+
+/// The tasks and connections enabled by the profile the runtime runs with.
+#[derive(Debug, Clone, Default)]
+pub struct CuRunProfile {
+    name: Option<String>,
+    /// By node id.
+    disabled_tasks: Vec<bool>,
+    /// By edge index.
+    disabled_cnx: Vec<bool>,
+}
+
+impl CuRunProfile {
+    /// The profile selected by the configuration, everything is enabled if it does not select any.
+    pub fn new(config: &CuConfig) -> CuResult<Self> {
+        let Some(profile) = config.active_profile()? else {
+            return Ok(Self::default());
+        };
+        let graph = config.get_graph(None)?; // FIXME(gbin): multimission
+        let mut disabled_tasks = vec![false; graph.node_bound()];
+        for node_idx in graph.node_indices() {
+            disabled_tasks[node_idx.index()] =
+                profile.disabled_tasks.contains(&graph[node_idx].get_id());
+        }
+        let mut disabled_cnx = vec![false; graph.edge_bound()];
+        for edge_idx in graph.edge_indices() {
+            if let Some((src, dst)) = graph.edge_endpoints(edge_idx) {
+                disabled_cnx[edge_idx.index()] =
+                    profile.disables_cnx(&graph[src].get_id(), &graph[dst].get_id());
+            }
+        }
+        Ok(Self {
+            name: Some(profile.id.clone()),
+            disabled_tasks,
+            disabled_cnx,
+        })
+    }
+
+    /// The id of the profile, None if the application runs without.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn task_enabled(&self, node_id: usize) -> bool {
+        !self.disabled_tasks.get(node_id).copied().unwrap_or(false)
+    }
+
+    pub fn cnx_enabled(&self, cnx: usize) -> bool {
+        !self.disabled_cnx.get(cnx).copied().unwrap_or(false)
+    }
+
+    /// Checks that the profile only disables connections the application was built to disable:
+    /// the generated code keeps a buffer for the empty message of the connections disabled by
+    /// the profiles of the configuration it was compiled with.
+    pub fn check_disableable(&self, disableable_cnx: &[usize]) -> CuResult<()> {
+        match (0..self.disabled_cnx.len())
+            .find(|cnx| !self.cnx_enabled(*cnx) && !disableable_cnx.contains(cnx))
+        {
+            Some(cnx) => Err(CuError::from(format!(
+                "The profile \"{}\" disables the connection {cnx} which no profile disabled when the application was built, rebuild it with this configuration.",
+                self.name().unwrap_or_default()
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::read_configuration_str;
+
+    #[test]
+    fn test_run_profile() {
+        let txt = r#"(
+            tasks: [(id: "camera", type: "Camera"), (id: "lidar", type: "Lidar"), (id: "planner", type: "Planner")],
+            cnx: [
+                (src: "camera", dst: "planner", msg: "Image"),
+                (src: "lidar", dst: "planner", msg: "Scan"),
+            ],
+            profiles: [(id: "no_camera", disabled_tasks: ["camera"]), (id: "no_lidar", disabled_cnx: ["lidar->planner"])],
+        )"#;
+        let mut config = read_configuration_str(txt.to_string()).unwrap();
+        let profile = CuRunProfile::new(&config).unwrap();
+        assert_eq!(profile.name(), None);
+        assert!((0..3).all(|task| profile.task_enabled(task)));
+
+        config.profile = Some("no_camera".to_string());
+        let profile = CuRunProfile::new(&config).unwrap();
+        assert_eq!(profile.name(), Some("no_camera"));
+        assert_eq!(
+            (0..3)
+                .map(|task| profile.task_enabled(task))
+                .collect::<Vec<_>>(),
+            vec![false, true, true]
+        );
+        assert!(profile.cnx_enabled(0) && profile.cnx_enabled(1));
+
+        config.profile = Some("no_lidar".to_string());
+        let profile = CuRunProfile::new(&config).unwrap();
+        assert!(profile.cnx_enabled(0) && !profile.cnx_enabled(1));
+        assert!(profile.check_disableable(&[1]).is_ok());
+        assert!(profile.check_disableable(&[0]).is_err());
+    }
+}
@@ -17,6 +17,9 @@ struct Args {
     /// Open the SVG in the default system viewer
     #[clap(long)]
     open: bool,
+    /// Print the Mermaid rendering to the standard output instead of generating an SVG
+    #[clap(long)]
+    mermaid: bool,
 }
 
 /// Render the configuration file to a dot file then convert it to an SVG and optionally opens it with inkscape.
@@ -26,6 +29,12 @@ fn main() -> std::io::Result<()> {
 
     let config = read_configuration(args.config.to_str().unwrap())
         .expect("Failed to read configuration file");
+    if args.mermaid {
+        config
+            .render_mermaid(&mut std::io::stdout(), None)
+            .expect("Failed to render the configuration");
+        return Ok(());
+    }
     let mut content = Vec::<u8>::new();
     {
         let mut cursor = Cursor::new(&mut content);
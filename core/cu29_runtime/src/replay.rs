@@ -0,0 +1,335 @@
+//! Open loop replay support.
+//! In an open loop replay, only the outputs of the source tasks are taken from a recorded log, all the
+//! other tasks run their actual code. This allows to evaluate a new version of the downstream
+//! algorithms against recorded sensor data.
+//!
+//! For a runtime generated with `sim_mode = true`, the macro generates a `replay_step` function in the
+//! mission module that can be used as a simulation callback:
+//!
+//! ```rust,ignore
+//! let mut report = ReplayReport::default();
+//! for recorded in copperlists_dump::<default::CuMsgs>(&mut reader) {
+//!     // optionally sync the mocked clock to the recorded one here.
+//!     let mut callback = |step| default::replay_step(&recorded, &mut report, step);
+//!     app.run_one_iteration(&mut callback)?;
+//! }
+//! println!("{report}");
+//! ```
+//!
+//! Every replayed output is compared to the recorded one (on their serialized payloads) and the
+//! differences are collected in a [ReplayReport].
+//!
+//! The same report is used by [check_determinism] to compare two logs recorded from the same inputs,
+//! for example two replays of the same log or a replay made with two versions of the code.
+//! Floating point outputs can be given a tolerance with [ReplayReport::with_tolerance], it applies
+//! to the numbers of the payloads which are `Serialize`, the others have to be identical.
+
+use crate::copperlist::CopperList;
+use crate::cutask::{CuMsg, CuMsgPayload};
+use crate::invariants::number;
+use bincode::encode_to_vec;
+use cu29_traits::{CopperListTuple, CuWireFormat};
+use cu29_value::Value;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// An output of a task that differs from the recorded one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// The id of the recorded copperlist.
+    pub culist_id: u32,
+    /// The task that produced the output.
+    pub task_id: &'static str,
+    /// The payloads are absent on one side only.
+    pub presence_mismatch: bool,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.presence_mismatch {
+            write!(
+                f,
+                "CL {}: output of {} is missing on one side",
+                self.culist_id, self.task_id
+            )
+        } else {
+            write!(
+                f,
+                "CL {}: output of {} differs",
+                self.culist_id, self.task_id
+            )
+        }
+    }
+}
+
+/// The result of an open loop replay or of a determinism check.
+#[derive(Debug, Default, Clone)]
+pub struct ReplayReport {
+    /// Number of messages compared between the recording and the replay.
+    pub compared: u64,
+    pub divergences: Vec<Divergence>,
+    /// Set when the two runs did not have the same number of copperlists.
+    pub length_mismatch: Option<(u64, u64)>,
+    /// The ids of the first copperlists compared that were not numbered the same in the two runs.
+    pub id_mismatch: Option<(u32, u32)>,
+    default_tolerance: Option<f64>,
+    tolerances: HashMap<String, f64>,
+}
+
+/// Implemented by the generated copperlist payloads to compare all their outputs in one go.
+pub trait CuOutputsComparison {
+    fn compare_outputs(&self, other: &Self, culist_id: u32, report: &mut ReplayReport);
+}
+
+impl ReplayReport {
+    /// Accepts an absolute difference on the numbers found in the outputs of all the tasks.
+    pub fn with_default_tolerance(mut self, tolerance: f64) -> Self {
+        self.default_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Accepts an absolute difference on the numbers found in the outputs of the given task.
+    pub fn with_tolerance(mut self, task_id: &str, tolerance: f64) -> Self {
+        self.tolerances.insert(task_id.to_string(), tolerance);
+        self
+    }
+
+    fn tolerance_for(&self, task_id: &str) -> Option<f64> {
+        self.tolerances
+            .get(task_id)
+            .copied()
+            .or(self.default_tolerance)
+    }
+
+    /// Compares a replayed message with its recorded counterpart, this is called by the generated code.
+    /// `as_value` gives the fields of the payload to compare them with the tolerance, None if its type
+    /// is not `Serialize`, see [crate::invariants::ValueProbe].
+    pub fn compare<T: CuMsgPayload>(
+        &mut self,
+        culist_id: u32,
+        task_id: &'static str,
+        recorded: &CuMsg<T>,
+        replayed: &CuMsg<T>,
+        as_value: impl Fn(&T) -> Option<Value>,
+    ) {
+        self.compared += 1;
+        let divergence = match (recorded.payload(), replayed.payload()) {
+            (None, None) => None,
+            (Some(recorded), Some(replayed)) => {
+                let recorded_bytes = encode_to_vec(recorded, CuWireFormat::CONFIG);
+                let replayed_bytes = encode_to_vec(replayed, CuWireFormat::CONFIG);
+                match (recorded_bytes, replayed_bytes) {
+                    (Ok(a), Ok(b)) if a == b => None,
+                    _ => match (
+                        self.tolerance_for(task_id),
+                        as_value(recorded),
+                        as_value(replayed),
+                    ) {
+                        (Some(tolerance), Some(a), Some(b))
+                            if within_tolerance(&a, &b, tolerance) =>
+                        {
+                            None
+                        }
+                        _ => Some(false),
+                    },
+                }
+            }
+            _ => Some(true),
+        };
+        if let Some(presence_mismatch) = divergence {
+            self.divergences.push(Divergence {
+                culist_id,
+                task_id,
+                presence_mismatch,
+            });
+        }
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.divergences.is_empty() && self.length_mismatch.is_none() && self.id_mismatch.is_none()
+    }
+
+    /// The first divergence in the replay order.
+    pub fn first_divergence(&self) -> Option<&Divergence> {
+        self.divergences.first()
+    }
+}
+
+impl Display for ReplayReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} messages compared, {} divergences.",
+            self.compared,
+            self.divergences.len()
+        )?;
+        if let Some((reference, candidate)) = self.length_mismatch {
+            writeln!(
+                f,
+                "  the runs have a different length: {reference} vs {candidate} copperlists"
+            )?;
+        }
+        if let Some((reference, candidate)) = self.id_mismatch {
+            writeln!(
+                f,
+                "  the copperlists are numbered differently from CL {reference} vs CL {candidate} on"
+            )?;
+        }
+        for divergence in &self.divergences {
+            writeln!(f, "  {divergence}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two runs copperlist by copperlist, typically decoded from two logs recorded from the
+/// same inputs. Any divergence points to a nondeterminism (uninitialized state, dependency on the
+/// wall clock, unordered containers...) in the task that produced the output.
+pub fn check_determinism<P: CopperListTuple + CuOutputsComparison>(
+    reference: impl Iterator<Item = CopperList<P>>,
+    candidate: impl Iterator<Item = CopperList<P>>,
+    mut report: ReplayReport,
+) -> ReplayReport {
+    let mut reference = reference.fuse();
+    let mut candidate = candidate.fuse();
+    let (mut nb_reference, mut nb_candidate) = (0u64, 0u64);
+    loop {
+        match (reference.next(), candidate.next()) {
+            (Some(a), Some(b)) => {
+                if a.id != b.id && report.id_mismatch.is_none() {
+                    report.id_mismatch = Some((a.id, b.id));
+                }
+                a.msgs.compare_outputs(&b.msgs, a.id, &mut report);
+                nb_reference += 1;
+                nb_candidate += 1;
+            }
+            (Some(_), None) => nb_reference += 1,
+            (None, Some(_)) => nb_candidate += 1,
+            (None, None) => break,
+        }
+    }
+    if nb_reference != nb_candidate {
+        report.length_mismatch = Some((nb_reference, nb_candidate));
+    }
+    report
+}
+
+/// Compares two payloads field by field, the numbers they contain are allowed to differ by the
+/// tolerance, everything else has to be identical.
+fn within_tolerance(a: &Value, b: &Value, tolerance: f64) -> bool {
+    match (a, b) {
+        (Value::Seq(a), Value::Seq(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| within_tolerance(a, b, tolerance))
+        }
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|((key_a, a), (key_b, b))| {
+                    key_a == key_b && within_tolerance(a, b, tolerance)
+                })
+        }
+        (Value::Option(Some(a)), Value::Option(Some(b)))
+        | (Value::Newtype(a), Value::Newtype(b)) => within_tolerance(a, b, tolerance),
+        _ => match (number(a), number(b)) {
+            (Some(a), Some(b)) => (a - b).abs() <= tolerance || (a.is_nan() && b.is_nan()),
+            _ => a == b,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn as_value<T: serde::Serialize>(payload: &T) -> Option<Value> {
+        cu29_value::to_value(payload).ok()
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut report = ReplayReport::default();
+        let a = CuMsg::new(Some(42u32));
+        let b = CuMsg::new(Some(43u32));
+        let none = CuMsg::<u32>::new(None);
+        report.compare(1, "task", &a, &a.clone(), as_value);
+        assert!(report.is_identical());
+        report.compare(2, "task", &a, &b, as_value);
+        report.compare(3, "task", &a, &none, as_value);
+        assert_eq!(report.compared, 3);
+        assert_eq!(report.divergences.len(), 2);
+        assert_eq!(report.first_divergence().unwrap().culist_id, 2);
+        assert!(report.divergences[1].presence_mismatch);
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let a = CuMsg::new(Some((1.0f64, 2.0f64)));
+        let b = CuMsg::new(Some((1.0f64, 2.0000001f64)));
+        let mut report = ReplayReport::default();
+        report.compare(1, "task", &a, &b, as_value);
+        assert!(!report.is_identical());
+
+        let mut report = ReplayReport::default().with_tolerance("task", 1e-3);
+        report.compare(1, "task", &a, &b, as_value);
+        report.compare(1, "other", &a, &b, as_value);
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.first_divergence().unwrap().task_id, "other");
+
+        // Without the fields of the payloads, only the identical ones pass.
+        let mut report = ReplayReport::default().with_default_tolerance(1e-3);
+        report.compare(1, "task", &a, &b, |_| None);
+        assert!(!report.is_identical());
+
+        let pose = |x: f64, name: &str| {
+            let mut fields = std::collections::BTreeMap::new();
+            fields.insert("x".to_string(), x);
+            as_value(&(fields, name.to_string())).unwrap()
+        };
+        assert!(within_tolerance(
+            &pose(1.5e-3, "a"),
+            &pose(1.6e-3, "a"),
+            1e-3
+        ));
+        assert!(!within_tolerance(&pose(1.0, "a"), &pose(1.0, "b"), 1e-3));
+        assert!(!within_tolerance(
+            &as_value(&Some(1.0)).unwrap(),
+            &as_value(&None::<f64>).unwrap(),
+            1e-3
+        ));
+    }
+
+    #[test]
+    fn test_check_determinism() {
+        #[derive(Debug, bincode::Encode, bincode::Decode)]
+        struct Msgs(u32);
+
+        impl CuOutputsComparison for Msgs {
+            fn compare_outputs(&self, other: &Self, culist_id: u32, report: &mut ReplayReport) {
+                report.compare(
+                    culist_id,
+                    "src",
+                    &CuMsg::new(Some(self.0)),
+                    &CuMsg::new(Some(other.0)),
+                    as_value,
+                );
+            }
+        }
+
+        let reference = (0..3).map(|i| CopperList::new(i, Msgs(i)));
+        let candidate = (0..4).map(|i| CopperList::new(i, Msgs(if i == 2 { 0 } else { i })));
+        let report = check_determinism(reference, candidate, ReplayReport::default());
+        assert_eq!(report.compared, 3);
+        assert_eq!(report.first_divergence().unwrap().culist_id, 2);
+        assert_eq!(report.length_mismatch, Some((3, 4)));
+        assert_eq!(report.id_mismatch, None);
+
+        let reference = (0..3).map(|i| CopperList::new(i, Msgs(i)));
+        let candidate = (0..3).map(|i| CopperList::new(i + (i == 2) as u32, Msgs(i)));
+        let report = check_determinism(reference, candidate, ReplayReport::default());
+        assert!(report.divergences.is_empty());
+        assert_eq!(report.id_mismatch, Some((2, 3)));
+        assert!(!report.is_identical());
+    }
+}
@@ -0,0 +1,173 @@
+//! Schema evolution of the logged payloads.
+//! At startup the runtime writes in the log the schema tags of the copperlist: for every slot the
+//! task producing it, its message type and its version. The versions are declared in the
+//! `schema_versions` table of the configuration and must be bumped when the encoding of a payload
+//! changes:
+//!
+//! ```ron
+//! (
+//!     tasks: [ ... ],
+//!     cnx: [ ... ],
+//!     schema_versions: { "cu_sensor_payloads::Pose": 2 },
+//! )
+//! ```
+//!
+//! A log written with an older schema can still be decoded by an exporter generated from the old
+//! configuration (with `gen_cumsgs!`), its payloads can then be migrated to the current types with
+//! [CuPayloadUpgrade].
+
+use crate::cutask::{CuMsg, CuMsgPayload};
+use bincode::{Decode, Encode};
+use cu29_traits::{CuError, CuResult};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Describes one slot of the copperlist.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct CuSchemaTag {
+    pub task_id: String,
+    pub msg_type: String,
+    pub version: u32,
+}
+
+impl Display for CuSchemaTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} v{}", self.task_id, self.msg_type, self.version)
+    }
+}
+
+/// Implemented by the generated copperlist payloads.
+pub trait CuSchemaTagged {
+    /// The schema tags in the copperlist order.
+    fn schema_tags() -> Vec<CuSchemaTag>;
+}
+
+/// Migrates a payload recorded as `From` to its current version `To`.
+/// It is implemented on a marker type so several migrations can coexist for the same types:
+///
+/// ```rust,ignore
+/// struct PoseV1ToV2;
+///
+/// impl CuPayloadUpgrade<PoseV1, Pose> for PoseV1ToV2 {
+///     fn upgrade(from: PoseV1) -> CuResult<Pose> {
+///         Ok(Pose { x: from.x, y: from.y, theta: 0.0 })
+///     }
+/// }
+///
+/// let pose: CuMsg<Pose> = upgrade_msg::<PoseV1ToV2, _, _>(old_msg)?;
+/// ```
+pub trait CuPayloadUpgrade<From: CuMsgPayload, To: CuMsgPayload> {
+    fn upgrade(from: From) -> CuResult<To>;
+}
+
+/// The identity migration for the slots that did not change.
+pub struct NoUpgrade;
+
+impl<T: CuMsgPayload> CuPayloadUpgrade<T, T> for NoUpgrade {
+    fn upgrade(from: T) -> CuResult<T> {
+        Ok(from)
+    }
+}
+
+/// Migrates a recorded message, its metadata is kept as is.
+pub fn upgrade_msg<U, From, To>(msg: CuMsg<From>) -> CuResult<CuMsg<To>>
+where
+    U: CuPayloadUpgrade<From, To>,
+    From: CuMsgPayload,
+    To: CuMsgPayload,
+{
+    let payload = match msg.payload() {
+        Some(payload) => Some(U::upgrade(payload.clone())?),
+        None => None,
+    };
+    let mut upgraded = CuMsg::new(payload);
+    upgraded.metadata = msg.metadata;
+    Ok(upgraded)
+}
+
+/// Lists the slots whose recorded schema differs from the current one, an empty result means the
+/// log can be decoded directly with the current types.
+pub fn schema_differences(recorded: &[CuSchemaTag], current: &[CuSchemaTag]) -> Vec<String> {
+    let mut differences: Vec<String> = recorded
+        .iter()
+        .zip(current.iter())
+        .enumerate()
+        .filter(|(_, (recorded, current))| recorded != current)
+        .map(|(slot, (recorded, current))| {
+            format!("slot {slot}: recorded {recorded}, now {current}")
+        })
+        .collect();
+    if recorded.len() != current.len() {
+        differences.push(format!(
+            "the copperlist had {} slots, it now has {}",
+            recorded.len(),
+            current.len()
+        ));
+    }
+    differences
+}
+
+/// Fails with an explanation if a log recorded with `recorded` cannot be decoded with the current
+/// types.
+pub fn check_schema(recorded: &[CuSchemaTag], current: &[CuSchemaTag]) -> CuResult<()> {
+    let differences = schema_differences(recorded, current);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    Err(CuError::from(format!(
+        "The log was recorded with a different schema, decode it with the configuration it was recorded with and migrate it with CuPayloadUpgrade:\n  {}",
+        differences.join("\n  ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default, Debug, Clone, Encode, Decode)]
+    struct PoseV1 {
+        x: f32,
+    }
+
+    #[derive(Default, Debug, Clone, Encode, Decode, PartialEq)]
+    struct Pose {
+        x: f32,
+        theta: f32,
+    }
+
+    struct PoseV1ToV2;
+
+    impl CuPayloadUpgrade<PoseV1, Pose> for PoseV1ToV2 {
+        fn upgrade(from: PoseV1) -> CuResult<Pose> {
+            Ok(Pose {
+                x: from.x,
+                theta: 0.0,
+            })
+        }
+    }
+
+    #[test]
+    fn test_upgrade_msg() {
+        let mut old = CuMsg::new(Some(PoseV1 { x: 1.0 }));
+        old.metadata.set_status("recorded");
+        let new = upgrade_msg::<PoseV1ToV2, _, _>(old).unwrap();
+        assert_eq!(new.payload(), Some(&Pose { x: 1.0, theta: 0.0 }));
+        assert_eq!(new.metadata.status_txt.0, "recorded");
+
+        let same = upgrade_msg::<NoUpgrade, _, _>(CuMsg::new(Some(3u32))).unwrap();
+        assert_eq!(same.payload(), Some(&3));
+    }
+
+    #[test]
+    fn test_schema_differences() {
+        let tag = |version| CuSchemaTag {
+            task_id: "src".to_string(),
+            msg_type: "Pose".to_string(),
+            version,
+        };
+        assert!(check_schema(&[tag(1)], &[tag(1)]).is_ok());
+        assert_eq!(schema_differences(&[tag(1)], &[tag(2)]).len(), 1);
+        assert_eq!(schema_differences(&[tag(1)], &[tag(1), tag(1)]).len(), 1);
+        assert!(check_schema(&[tag(1)], &[tag(2)]).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Services provided by a task to the other tasks of the same application, for the requests that
+//! are awkward to express as a stream of messages ("what is the cell of the map under this pose").
+//! A client calls the service synchronously from its process(), the provider answers right away
+//! from its own state, within the same copper list and without any copy or network hop:
+//!
+//! ```ron
+//! services: [(provider: "map", client: "planner", request: "payloads::Pose", response: "payloads::Cell")],
+//! ```
+//!
+//! ```rust,ignore
+//! impl CuService<Pose, Cell> for MapServer {
+//!     fn serve(&mut self, _clock: &RobotClock, pose: &Pose) -> CuResult<Cell> {
+//!         Ok(self.grid.cell_at(pose))
+//!     }
+//! }
+//!
+//! impl<'cl> CuServiceClient<'cl> for Planner {
+//!     type Services<'s> = CuServiceCall<'s, Pose, Cell>;
+//!
+//!     fn process_with_services(
+//!         &mut self,
+//!         _clock: &RobotClock,
+//!         mut map: Self::Services<'_>,
+//!         input: Self::Input,
+//!         output: Self::Output,
+//!     ) -> CuResult<()> {
+//!         let cell = map.call(input.payload().unwrap())?;
+//!         ...
+//!     }
+//! }
+//! ```
+//!
+//! The provider and the client are tasks with inputs and an output, running in the same process.
+//! The types of the calls are checked when the application is compiled: the provider has to
+//! implement [CuService] for the types of the configuration and the client to declare the calls
+//! in the order of its services in the configuration, as a tuple if it has several.
+
+use crate::cutask::CuTask;
+use cu29_clock::RobotClock;
+use cu29_traits::CuResult;
+
+/// A task answering the requests of type `Req` of other tasks with a `Resp`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not provide the service from `{Req}` to `{Resp}` declared in the configuration",
+    label = "the provider of the service"
+)]
+pub trait CuService<Req, Resp> {
+    /// Called from the process() of a client, the provider can't be running at the same time.
+    fn serve(&mut self, clock: &RobotClock, request: &Req) -> CuResult<Resp>;
+}
+
+/// The calls of a client to the provider of one of its services, valid for one process().
+pub struct CuServiceCall<'s, Req, Resp> {
+    clock: &'s RobotClock,
+    provider: &'s mut dyn CuService<Req, Resp>,
+}
+
+impl<'s, Req, Resp> CuServiceCall<'s, Req, Resp> {
+    pub fn new(clock: &'s RobotClock, provider: &'s mut dyn CuService<Req, Resp>) -> Self {
+        Self { clock, provider }
+    }
+
+    /// Asks the provider, an error of the provider fails the call only.
+    pub fn call(&mut self, request: &Req) -> CuResult<Resp> {
+        self.provider.serve(self.clock, request)
+    }
+}
+
+/// A task calling services of other tasks. The runtime calls its process_with_services() instead
+/// of its process().
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` calls services in the configuration but does not implement `CuServiceClient`"
+)]
+pub trait CuServiceClient<'cl>: CuTask<'cl> {
+    /// The calls to the services of the task: a [CuServiceCall] or a tuple of them, in the order
+    /// of the services of the task in the configuration.
+    type Services<'s>;
+
+    /// Like [CuTask::process], with the services of the task to call.
+    fn process_with_services(
+        &mut self,
+        clock: &RobotClock,
+        services: Self::Services<'_>,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Doubler {
+        calls: u32,
+    }
+
+    impl CuService<u32, u64> for Doubler {
+        fn serve(&mut self, _clock: &RobotClock, request: &u32) -> CuResult<u64> {
+            self.calls += 1;
+            if *request == 0 {
+                return Err("Nothing to double".into());
+            }
+            Ok(*request as u64 * 2)
+        }
+    }
+
+    #[test]
+    fn test_service_call() {
+        let clock = RobotClock::new();
+        let mut provider = Doubler { calls: 0 };
+        {
+            let mut doubler = CuServiceCall::new(&clock, &mut provider);
+            assert_eq!(doubler.call(&21).unwrap(), 42);
+            assert!(doubler.call(&0).is_err());
+        }
+        assert_eq!(provider.calls, 2);
+    }
+}
@@ -0,0 +1,512 @@
+//! Connection tapping for debugging, enabled with the `tap` feature.
+//! A tap copies the messages flowing through a connection to a callback or to a remote peer at the
+//! end of every copper list, without touching the configuration of the application:
+//!
+//! ```rust,ignore
+//! app.tap("cam->blur", |frame: &TapFrame| println!("{frame}"))?;
+//! ```
+//!
+//! The application can also let a developer attach taps from the outside with
+//! [CuTaps::listen]. The `cu29-topic` tool then echoes a connection of the running application:
+//!
+//! ```text
+//! cu29-topic echo 127.0.0.1:7400 cam->blur
+//! ```
+//!
+//! Code living outside of the graph, a GUI thread for example, can also receive the messages of a
+//! connection decoded with [CuTaps::subscribe]:
+//!
+//! ```rust,ignore
+//! let listener = app.subscribe::<CuImage<Vec<u8>>>("cam->blur", 4)?;
+//! std::thread::spawn(move || {
+//!     while let Some(msg) = listener.recv_timeout(Duration::from_secs(1)) { /* ... */ }
+//! });
+//! ```
+//!
+//! The messages of a connection are the output of its source task so a tap on `cam` sees the same
+//! messages whatever the destination is.
+
+use crate::cutask::{CuMsg, CuMsgPayload};
+use crate::introspection::CnxIntrospection;
+use bincode::{decode_from_slice, encode_to_vec, Decode, Encode};
+use cu29_traits::{CuError, CuResult, CuWireFormat};
+use std::fmt::{Display, Formatter};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Maximum size of a datagram exchanged with a remote peer.
+pub const MAX_TAP_DATAGRAM: usize = 65507;
+
+/// A copy of a message flowing through a tapped connection.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct TapFrame {
+    pub culist_id: u32,
+    /// The sequence number of the message, see [crate::cutask::CuMsgMetadata::seq].
+    pub seq: u64,
+    /// The id of the task producing the message.
+    pub src: String,
+    pub msg_type: String,
+    /// The Debug rendering of the payload, None if the task produced nothing.
+    pub payload: Option<String>,
+    /// The bincode serialized payload.
+    pub encoded: Option<Vec<u8>>,
+}
+
+impl Display for TapFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.payload {
+            Some(payload) => write!(
+                f,
+                "CL {} {} [{}]: {}",
+                self.culist_id, self.src, self.msg_type, payload
+            ),
+            None => write!(
+                f,
+                "CL {} {} [{}]: no payload",
+                self.culist_id, self.src, self.msg_type
+            ),
+        }
+    }
+}
+
+/// The requests a remote peer sends to the application listening for taps.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum TapRequest {
+    /// Sends the messages of the connection back to the requesting peer.
+    Echo { cnx: String },
+    /// Stops sending the messages of the connection to the requesting peer.
+    Stop { cnx: String },
+}
+
+/// Receives the messages of a tapped connection.
+/// A sink returning an error is detached.
+pub trait CuTapSink: Send {
+    fn send(&mut self, frame: &TapFrame) -> CuResult<()>;
+}
+
+impl<F: FnMut(&TapFrame) + Send> CuTapSink for F {
+    fn send(&mut self, frame: &TapFrame) -> CuResult<()> {
+        self(frame);
+        Ok(())
+    }
+}
+
+/// Sends the messages of a tapped connection as bincode encoded datagrams to a remote peer.
+pub struct UdpTapSink {
+    socket: UdpSocket,
+    peer: SocketAddr,
+}
+
+impl UdpTapSink {
+    pub fn new(peer: impl ToSocketAddrs) -> CuResult<Self> {
+        let peer = peer
+            .to_socket_addrs()
+            .map_err(|e| CuError::new_with_cause("Invalid tap peer address", e))?
+            .next()
+            .ok_or_else(|| CuError::from("Invalid tap peer address"))?;
+        let bind: SocketAddr = if peer.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind)
+            .map_err(|e| CuError::new_with_cause("Could not bind the tap socket", e))?;
+        Ok(Self { socket, peer })
+    }
+
+    fn from_socket(socket: &UdpSocket, peer: SocketAddr) -> CuResult<Self> {
+        let socket = socket
+            .try_clone()
+            .map_err(|e| CuError::new_with_cause("Could not clone the tap socket", e))?;
+        Ok(Self { socket, peer })
+    }
+}
+
+impl CuTapSink for UdpTapSink {
+    fn send(&mut self, frame: &TapFrame) -> CuResult<()> {
+        let bytes = encode_to_vec(frame, CuWireFormat::CONFIG)
+            .map_err(|e| CuError::new_with_cause("Could not encode the tap frame", e))?;
+        if bytes.len() > MAX_TAP_DATAGRAM {
+            // Too big for a datagram, the peer still sees that a message went through.
+            let truncated = TapFrame {
+                payload: Some(format!("<{} bytes payload truncated>", bytes.len())),
+                encoded: None,
+                ..frame.clone()
+            };
+            return self.send(&truncated);
+        }
+        self.socket
+            .send_to(&bytes, self.peer)
+            .map_err(|e| CuError::new_with_cause("Could not send the tap frame", e))?;
+        Ok(())
+    }
+}
+
+/// A message of a connection received by a [CuListener].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listened<T> {
+    pub culist_id: u32,
+    /// The sequence number of the message, see [crate::cutask::CuMsgMetadata::seq].
+    pub seq: u64,
+    /// None if the task produced nothing.
+    pub payload: Option<T>,
+}
+
+/// Receives the messages of a connection outside of the graph, see [CuTaps::subscribe].
+/// It holds a bounded number of messages: the runtime never waits for the listener and drops the
+/// messages coming while it is full, the listener can be moved to another thread.
+pub struct CuListener<T> {
+    receiver: Receiver<Listened<T>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> CuListener<T> {
+    /// The oldest message received, None if there is none yet.
+    pub fn try_recv(&self) -> Option<Listened<T>> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Waits this long at most for a message, None if none came or if the application is gone.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Listened<T>> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// The newest message received, the older ones are discarded.
+    pub fn latest(&self) -> Option<Listened<T>> {
+        self.receiver.try_iter().last()
+    }
+
+    /// How many messages were dropped because the listener was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// The tap sink of a [CuListener], it is detached when the listener is dropped.
+struct ListenerSink<T> {
+    sender: SyncSender<Listened<T>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T: Decode<()> + Send> CuTapSink for ListenerSink<T> {
+    fn send(&mut self, frame: &TapFrame) -> CuResult<()> {
+        let payload = match &frame.encoded {
+            Some(bytes) => Some(
+                decode_from_slice(bytes, CuWireFormat::CONFIG)
+                    .map_err(|e| {
+                        CuError::new_with_cause(
+                            &format!("The messages of {} are not of this type", frame.src),
+                            e,
+                        )
+                    })?
+                    .0,
+            ),
+            None => None,
+        };
+        let listened = Listened {
+            culist_id: frame.culist_id,
+            seq: frame.seq,
+            payload,
+        };
+        match self.sender.try_send(listened) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err("The listener is gone".into()),
+        }
+    }
+}
+
+struct Tap {
+    src: String,
+    /// The remote peer for the taps attached through [CuTaps::listen].
+    peer: Option<SocketAddr>,
+    sink: Box<dyn CuTapSink>,
+}
+
+/// The taps attached to the connections of a running application.
+#[derive(Default)]
+pub struct CuTaps {
+    /// (src, dst) of the connections of the graph.
+    connections: Vec<(String, String)>,
+    taps: Vec<Tap>,
+    server: Option<UdpSocket>,
+}
+
+impl CuTaps {
+    pub fn new(connections: &[CnxIntrospection]) -> Self {
+        Self {
+            connections: connections
+                .iter()
+                .map(|cnx| (cnx.src.clone(), cnx.dst.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Finds the task producing the messages of a connection given as `src->dst` or just `src`.
+    fn resolve(&self, cnx: &str) -> CuResult<String> {
+        let found = match cnx.split_once("->") {
+            Some((src, dst)) => self
+                .connections
+                .iter()
+                .find(|(s, d)| s == src.trim() && d == dst.trim()),
+            None => self.connections.iter().find(|(s, _)| s == cnx.trim()),
+        };
+        found
+            .map(|(src, _)| src.clone())
+            .ok_or_else(|| CuError::from(format!("Unknown connection {cnx}")))
+    }
+
+    /// Copies the messages of the connection to the sink from the next copper list on.
+    pub fn attach(&mut self, cnx: &str, sink: impl CuTapSink + 'static) -> CuResult<()> {
+        let src = self.resolve(cnx)?;
+        self.taps.push(Tap {
+            src,
+            peer: None,
+            sink: Box::new(sink),
+        });
+        Ok(())
+    }
+
+    /// Sends the messages of the connection, decoded as `T`, to the returned listener from the next
+    /// copper list on. It holds `capacity` messages at most. The listener is detached when it is
+    /// dropped or if `T` is not the type of the messages of the connection.
+    pub fn subscribe<T: Decode<()> + Send + 'static>(
+        &mut self,
+        cnx: &str,
+        capacity: usize,
+    ) -> CuResult<CuListener<T>> {
+        let (sender, receiver) = sync_channel(capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.attach(
+            cnx,
+            ListenerSink {
+                sender,
+                dropped: dropped.clone(),
+            },
+        )?;
+        Ok(CuListener { receiver, dropped })
+    }
+
+    /// Removes all the taps of the connection.
+    pub fn detach(&mut self, cnx: &str) -> CuResult<()> {
+        let src = self.resolve(cnx)?;
+        self.taps.retain(|tap| tap.src != src);
+        Ok(())
+    }
+
+    pub fn is_tapped(&self, src: &str) -> bool {
+        self.taps.iter().any(|tap| tap.src == src)
+    }
+
+    /// Accepts [TapRequest]s from remote peers on this address, see [CuTaps::poll].
+    pub fn listen(&mut self, addr: impl ToSocketAddrs) -> CuResult<SocketAddr> {
+        let socket = UdpSocket::bind(addr)
+            .map_err(|e| CuError::new_with_cause("Could not bind the tap server", e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| CuError::new_with_cause("Could not configure the tap server", e))?;
+        let local = socket
+            .local_addr()
+            .map_err(|e| CuError::new_with_cause("Could not configure the tap server", e))?;
+        self.server = Some(socket);
+        Ok(local)
+    }
+
+    /// Handles the pending requests of the remote peers, this never blocks.
+    pub fn poll(&mut self) {
+        let Some(server) = &self.server else {
+            return;
+        };
+        let mut buffer = [0u8; 1024];
+        let mut requests = Vec::new();
+        while let Ok((len, peer)) = server.recv_from(&mut buffer) {
+            // Garbage from the network is just ignored.
+            if let Ok((request, _)) =
+                decode_from_slice::<TapRequest, _>(&buffer[..len], CuWireFormat::CONFIG)
+            {
+                requests.push((request, peer));
+            }
+        }
+        for (request, peer) in requests {
+            match request {
+                TapRequest::Echo { cnx } => {
+                    let Ok(src) = self.resolve(&cnx) else {
+                        continue;
+                    };
+                    let Some(server) = &self.server else {
+                        return;
+                    };
+                    if let Ok(sink) = UdpTapSink::from_socket(server, peer) {
+                        self.taps.push(Tap {
+                            src,
+                            peer: Some(peer),
+                            sink: Box::new(sink),
+                        });
+                    }
+                }
+                TapRequest::Stop { cnx } => {
+                    if let Ok(src) = self.resolve(&cnx) {
+                        self.taps
+                            .retain(|tap| tap.src != src || tap.peer != Some(peer));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copies a message to the taps of its connection, this is called by the generated code.
+    pub fn publish<T: CuMsgPayload>(
+        &mut self,
+        culist_id: u32,
+        src: &'static str,
+        msg_type: &'static str,
+        msg: &CuMsg<T>,
+    ) {
+        if !self.is_tapped(src) {
+            return;
+        }
+        let frame = TapFrame {
+            culist_id,
+            seq: msg.metadata.seq,
+            src: src.to_string(),
+            msg_type: msg_type.to_string(),
+            payload: msg.payload().map(|payload| format!("{payload:?}")),
+            encoded: msg
+                .payload()
+                .and_then(|payload| encode_to_vec(payload, CuWireFormat::CONFIG).ok()),
+        };
+        self.taps
+            .retain_mut(|tap| tap.src != src || tap.sink.send(&frame).is_ok());
+    }
+}
+
+/// Implemented by the generated copperlist payloads to publish all their tapped outputs in one go.
+pub trait CuTapped {
+    fn publish_taps(&self, culist_id: u32, taps: &mut CuTaps);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn taps() -> CuTaps {
+        let cnx = |src: &str, dst: &str| CnxIntrospection {
+            src: src.to_string(),
+            dst: dst.to_string(),
+            msg: "i32".to_string(),
+            store: false,
+            policy: Default::default(),
+            stats: Default::default(),
+        };
+        CuTaps::new(&[cnx("cam", "blur"), cnx("blur", "disp")])
+    }
+
+    #[test]
+    fn test_attach_and_publish() {
+        let mut taps = taps();
+        assert!(taps.attach("cam->disp", |_: &TapFrame| {}).is_err());
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let received = frames.clone();
+        taps.attach("cam->blur", move |frame: &TapFrame| {
+            received.lock().unwrap().push(frame.clone())
+        })
+        .unwrap();
+        assert!(taps.is_tapped("cam"));
+
+        taps.publish(1, "blur", "i32", &CuMsg::new(Some(2i32)));
+        taps.publish(1, "cam", "i32", &CuMsg::new(Some(42i32)));
+        taps.publish(2, "cam", "i32", &CuMsg::<i32>::new(None));
+        let frames = frames.lock().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].payload.as_deref(), Some("42"));
+        assert_eq!(frames[0].to_string(), "CL 1 cam [i32]: 42");
+        assert_eq!(frames[1].encoded, None);
+
+        taps.detach("cam").unwrap();
+        assert!(!taps.is_tapped("cam"));
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let mut taps = taps();
+        assert!(taps.subscribe::<i32>("nope", 2).is_err());
+        let listener = taps.subscribe::<i32>("blur", 2).unwrap();
+        let listened = std::thread::spawn(move || {
+            let first = listener.recv_timeout(Duration::from_secs(5));
+            (first, listener)
+        });
+        taps.publish(1, "blur", "i32", &CuMsg::new(Some(3i32)));
+        let (first, listener) = listened.join().unwrap();
+        assert_eq!(
+            first,
+            Some(Listened {
+                culist_id: 1,
+                seq: 0,
+                payload: Some(3)
+            })
+        );
+
+        // Full after 2 messages, the runtime does not wait for the listener.
+        for culist_id in 2..6 {
+            taps.publish(
+                culist_id,
+                "blur",
+                "i32",
+                &CuMsg::new(Some(culist_id as i32)),
+            );
+        }
+        assert_eq!(listener.dropped(), 2);
+        assert_eq!(listener.latest().and_then(|msg| msg.payload), Some(3));
+        assert_eq!(listener.try_recv(), None);
+
+        // Gone with its listener.
+        drop(listener);
+        taps.publish(6, "blur", "i32", &CuMsg::new(Some(6i32)));
+        assert!(!taps.is_tapped("blur"));
+    }
+
+    #[test]
+    fn test_remote_echo() {
+        let mut taps = taps();
+        let server = taps.listen("127.0.0.1:0").unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let request = TapRequest::Echo {
+            cnx: "blur->disp".to_string(),
+        };
+        peer.send_to(
+            &encode_to_vec(&request, CuWireFormat::CONFIG).unwrap(),
+            server,
+        )
+        .unwrap();
+
+        // The request is asynchronous, give it a moment to land.
+        for _ in 0..100 {
+            taps.poll();
+            if taps.is_tapped("blur") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        taps.publish(7, "blur", "i32", &CuMsg::new(Some(3i32)));
+
+        let mut buffer = [0u8; MAX_TAP_DATAGRAM];
+        let len = peer.recv(&mut buffer).unwrap();
+        let (frame, _): (TapFrame, _) =
+            decode_from_slice(&buffer[..len], CuWireFormat::CONFIG).unwrap();
+        assert_eq!(frame.culist_id, 7);
+        assert_eq!(frame.src, "blur");
+        let (payload, _): (i32, _) =
+            decode_from_slice(&frame.encoded.unwrap(), CuWireFormat::CONFIG).unwrap();
+        assert_eq!(payload, 3);
+    }
+}
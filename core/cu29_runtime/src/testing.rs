@@ -0,0 +1,224 @@
+//! End to end tests of a graph inside `cargo test`. The application is generated in simulation
+//! mode from a configuration given inline, the harness gives the payloads of the sources and
+//! records what reaches the sinks, every other task runs its actual code:
+//!
+//! ```rust,ignore
+//! #[copper_runtime(config_inline = r#"(
+//!     tasks: [
+//!         (id: "src", type: "tasks::Source"),
+//!         (id: "double", type: "tasks::Double"),
+//!         (id: "sink", type: "tasks::Sink"),
+//!     ],
+//!     cnx: [
+//!         (src: "src", dst: "double", msg: "i32"),
+//!         (src: "double", dst: "sink", msg: "i32"),
+//!     ],
+//! )"#, sim_mode = true)]
+//! struct TestApp {}
+//!
+//! #[test]
+//! fn test_double() {
+//!     let mut harness = CuTestHarness::new();
+//!     harness.inject("src", 21i32);
+//!     let mut app = TestApp::new_for_test(&harness).unwrap();
+//!     app.run_with_harness(&mut harness, 3).unwrap();
+//!     // Nothing was injected for the 2 last cycles, the source gave empty messages.
+//!     assert_eq!(harness.captured::<i32>("sink"), vec![Some(42), None, None]);
+//! }
+//! ```
+//!
+//! The harness runs the application on a mock clock advancing by a fixed period after each
+//! cycle, and without log.
+
+use crate::cutask::{CuMsg, CuMsgPayload};
+use cu29_clock::{CuDuration, RobotClock, RobotClockMock};
+use std::any::{type_name, Any};
+use std::collections::{HashMap, VecDeque};
+
+/// The period of the cycles if none is given.
+const DEFAULT_PERIOD: CuDuration = CuDuration::from_millis(1);
+
+/// Drives an application generated with `sim_mode = true` in a test, see the module
+/// documentation. The generated `harness_step` simulation callback calls it at every step.
+pub struct CuTestHarness {
+    clock: RobotClock,
+    clock_mock: RobotClockMock,
+    period: CuDuration,
+    cycles: u64,
+    /// The payloads given to the sources, one per cycle, by source id.
+    inputs: HashMap<String, VecDeque<Box<dyn Any>>>,
+    /// The payloads received by the sinks, one per cycle, by sink id and position of the input.
+    captured: HashMap<(String, usize), Vec<Box<dyn Any>>>,
+}
+
+impl Default for CuTestHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CuTestHarness {
+    pub fn new() -> Self {
+        let (clock, clock_mock) = RobotClock::mock();
+        Self {
+            clock,
+            clock_mock,
+            period: DEFAULT_PERIOD,
+            cycles: 0,
+            inputs: HashMap::new(),
+            captured: HashMap::new(),
+        }
+    }
+
+    /// The mock clock advances by this period after each cycle, 1 ms by default.
+    pub fn with_period(mut self, period: CuDuration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// The clock to give to the application.
+    pub fn clock(&self) -> RobotClock {
+        self.clock.clone()
+    }
+
+    /// The mock side of the clock, to move the time from the test.
+    pub fn clock_mock(&self) -> &RobotClockMock {
+        &self.clock_mock
+    }
+
+    /// The number of cycles run so far.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Queues the payload of the output of a source for a next cycle, one payload is consumed per
+    /// cycle. The source gives an empty message when its queue is empty.
+    pub fn inject<T: CuMsgPayload + 'static>(&mut self, source: &str, payload: T) {
+        self.inputs
+            .entry(source.to_string())
+            .or_default()
+            .push_back(Box::new(payload));
+    }
+
+    /// Queues a payload per cycle for a source.
+    pub fn inject_all<T: CuMsgPayload + 'static>(
+        &mut self,
+        source: &str,
+        payloads: impl IntoIterator<Item = T>,
+    ) {
+        for payload in payloads {
+            self.inject(source, payload);
+        }
+    }
+
+    /// The payloads received by the first input of a sink, one per cycle.
+    pub fn captured<T: CuMsgPayload + 'static>(&self, sink: &str) -> Vec<Option<T>> {
+        self.captured_input(sink, 0)
+    }
+
+    /// The payloads received by the input of a sink at this position, one per cycle.
+    pub fn captured_input<T: CuMsgPayload + 'static>(
+        &self,
+        sink: &str,
+        position: usize,
+    ) -> Vec<Option<T>> {
+        self.captured
+            .get(&(sink.to_string(), position))
+            .map(|payloads| {
+                payloads
+                    .iter()
+                    .map(|payload| {
+                        payload
+                            .downcast_ref::<Option<T>>()
+                            .unwrap_or_else(|| {
+                                panic!(
+                                    "The input {position} of the sink \"{sink}\" is not a {}.",
+                                    type_name::<T>()
+                                )
+                            })
+                            .clone()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Forgets the payloads captured so far.
+    pub fn clear_captured(&mut self) {
+        self.captured.clear();
+    }
+
+    /// Gives its next injected payload to a source, called by the generated `harness_step`.
+    pub fn fill_output<T: CuMsgPayload + 'static>(&mut self, source: &str, output: &mut CuMsg<T>) {
+        match self
+            .inputs
+            .get_mut(source)
+            .and_then(|payloads| payloads.pop_front())
+        {
+            Some(payload) => {
+                let payload = payload.downcast::<T>().unwrap_or_else(|_| {
+                    panic!(
+                        "The payload injected in the source \"{source}\" is not a {}.",
+                        type_name::<T>()
+                    )
+                });
+                output.set_payload(*payload);
+            }
+            None => output.clear_payload(),
+        }
+    }
+
+    /// Records the input of a sink, called by the generated `harness_step`.
+    pub fn capture<T: CuMsgPayload + 'static>(
+        &mut self,
+        sink: &str,
+        position: usize,
+        input: &CuMsg<T>,
+    ) {
+        self.captured
+            .entry((sink.to_string(), position))
+            .or_default()
+            .push(Box::new(input.payload().cloned()));
+    }
+
+    /// Moves the clock to the next cycle, called by the generated `run_with_harness`.
+    pub fn end_of_cycle(&mut self) {
+        self.cycles += 1;
+        self.clock_mock.increment(self.period.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harness() {
+        let mut harness = CuTestHarness::new().with_period(CuDuration::from_millis(10));
+        harness.inject_all("src", [1u32, 2]);
+        let mut output = CuMsg::<u32>::default();
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            harness.fill_output("src", &mut output);
+            received.push(output.payload().copied());
+            harness.capture("sink", 1, &output);
+            harness.end_of_cycle();
+        }
+        assert_eq!(received, vec![Some(1), Some(2), None]);
+        assert_eq!(
+            harness.captured_input::<u32>("sink", 1),
+            vec![Some(1), Some(2), None]
+        );
+        assert!(harness.captured::<u32>("sink").is_empty());
+        assert_eq!(harness.cycles(), 3);
+        assert_eq!(harness.clock().now(), CuDuration::from_millis(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a u32")]
+    fn test_harness_wrong_type() {
+        let mut harness = CuTestHarness::new();
+        harness.inject("src", 1.0f64);
+        harness.fill_output("src", &mut CuMsg::<u32>::default());
+    }
+}
@@ -0,0 +1,87 @@
+use bincode::{decode_from_slice, encode_to_vec};
+use clap::{Parser, Subcommand};
+use cu29_runtime::tap::{TapFrame, TapRequest, MAX_TAP_DATAGRAM};
+use cu29_traits::CuWireFormat;
+use std::net::{SocketAddr, UdpSocket};
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the messages of a connection of a running application listening for taps
+    Echo {
+        /// The address the application listens to for taps
+        app: SocketAddr,
+        /// The connection as src->dst or just the id of the source task
+        cnx: String,
+        /// Stops after this number of messages
+        #[clap(long)]
+        count: Option<u64>,
+        /// Prints the serialized payloads in hexadecimal instead of their Debug rendering
+        #[clap(long)]
+        raw: bool,
+    },
+}
+
+fn send(socket: &UdpSocket, app: SocketAddr, request: &TapRequest) -> std::io::Result<()> {
+    let bytes = encode_to_vec(request, CuWireFormat::CONFIG).expect("Failed to encode the request");
+    socket.send_to(&bytes, app)?;
+    Ok(())
+}
+
+/// Echoes the messages of a connection of a running Copper application, like ros2 topic echo.
+fn main() -> std::io::Result<()> {
+    let Command::Echo {
+        app,
+        cnx,
+        count,
+        raw,
+    } = Args::parse().command;
+
+    let bind: SocketAddr = if app.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    send(&socket, app, &TapRequest::Echo { cnx: cnx.clone() })?;
+
+    let mut buffer = vec![0u8; MAX_TAP_DATAGRAM];
+    let mut received = 0u64;
+    let mut last_seq: Option<u64> = None;
+    while count.is_none_or(|count| received < count) {
+        let (len, _) = socket.recv_from(&mut buffer)?;
+        let Ok((frame, _)) = decode_from_slice::<TapFrame, _>(&buffer[..len], CuWireFormat::CONFIG)
+        else {
+            eprintln!("Ignoring an invalid tap frame of {len} bytes");
+            continue;
+        };
+        if let Some(missed) = last_seq.and_then(|last| frame.seq.checked_sub(last + 1)) {
+            if missed > 0 {
+                eprintln!("{missed} messages lost before seq {}", frame.seq);
+            }
+        }
+        last_seq = Some(frame.seq);
+        if raw {
+            let hex: String = frame
+                .encoded
+                .unwrap_or_default()
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            println!(
+                "CL {} {} [{}]: {hex}",
+                frame.culist_id, frame.src, frame.msg_type
+            );
+        } else {
+            println!("{frame}");
+        }
+        received += 1;
+    }
+    send(&socket, app, &TapRequest::Stop { cnx })
+}
@@ -0,0 +1,186 @@
+//! The quantities with a unit ([uom](https://docs.rs/uom)) in the payloads. The uom quantities can
+//! be neither logged nor exported as is, [cu_quantity!](crate::cu_quantity) declares a newtype
+//! around one which is encoded, serialized and exported as a plain number in a fixed unit:
+//!
+//! ```rust,ignore
+//! use uom::si::f32::{Length, Velocity};
+//! use uom::si::{length::meter, velocity::meter_per_second};
+//!
+//! cu_quantity! {
+//!     /// A distance, logged in meters.
+//!     pub struct Distance(Length) in meter as f32;
+//!     pub struct Speed(Velocity) in meter_per_second as f32;
+//! }
+//!
+//! #[derive(Default, Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+//! pub struct Odometry {
+//!     pub traveled: Distance,
+//!     pub speed: Speed,
+//! }
+//! ```
+//!
+//! The newtype derefs to its quantity so the tasks keep computing with units, the number in the
+//! log, the exports and the invariants of the configuration is always in the declared unit.
+
+#[doc(hidden)]
+pub mod __private {
+    pub use bincode;
+    pub use serde;
+}
+
+/// Declares newtypes around uom quantities usable as payload fields, see the [units](crate::units)
+/// module.
+#[macro_export]
+macro_rules! cu_quantity {
+    ($(
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($quantity:ty) in $unit:ty as $storage:ty;
+    )+) => {$(
+        $(#[$attr])*
+        #[derive(Default, PartialEq, PartialOrd, Debug, Copy, Clone)]
+        $vis struct $name(pub $quantity);
+
+        impl $name {
+            /// The number in the unit the quantity is logged in.
+            pub fn unit_value(&self) -> $storage {
+                self.0.get::<$unit>()
+            }
+        }
+
+        impl From<$storage> for $name {
+            fn from(value: $storage) -> Self {
+                Self(<$quantity>::new::<$unit>(value))
+            }
+        }
+
+        impl From<$quantity> for $name {
+            fn from(quantity: $quantity) -> Self {
+                Self(quantity)
+            }
+        }
+
+        impl From<$name> for $quantity {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl ::core::ops::Deref for $name {
+            type Target = $quantity;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl ::core::ops::Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl ::core::ops::Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl ::core::ops::Mul<$storage> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: $storage) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl ::core::ops::Div<$storage> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: $storage) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        impl $crate::units::__private::bincode::Encode for $name {
+            fn encode<E: $crate::units::__private::bincode::enc::Encoder>(
+                &self,
+                encoder: &mut E,
+            ) -> Result<(), $crate::units::__private::bincode::error::EncodeError> {
+                $crate::units::__private::bincode::Encode::encode(&self.unit_value(), encoder)
+            }
+        }
+
+        impl<Context> $crate::units::__private::bincode::Decode<Context> for $name {
+            fn decode<D: $crate::units::__private::bincode::de::Decoder<Context = Context>>(
+                decoder: &mut D,
+            ) -> Result<Self, $crate::units::__private::bincode::error::DecodeError> {
+                let value: $storage = $crate::units::__private::bincode::Decode::decode(decoder)?;
+                Ok(Self::from(value))
+            }
+        }
+
+        impl<'de, Context> $crate::units::__private::bincode::BorrowDecode<'de, Context> for $name {
+            fn borrow_decode<
+                D: $crate::units::__private::bincode::de::BorrowDecoder<'de, Context = Context>,
+            >(
+                decoder: &mut D,
+            ) -> Result<Self, $crate::units::__private::bincode::error::DecodeError> {
+                let value: $storage = $crate::units::__private::bincode::Decode::decode(decoder)?;
+                Ok(Self::from(value))
+            }
+        }
+
+        impl $crate::units::__private::serde::Serialize for $name {
+            fn serialize<S: $crate::units::__private::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                $crate::units::__private::serde::Serialize::serialize(&self.unit_value(), serializer)
+            }
+        }
+
+        impl<'de> $crate::units::__private::serde::Deserialize<'de> for $name {
+            fn deserialize<D: $crate::units::__private::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                let value: $storage =
+                    $crate::units::__private::serde::Deserialize::deserialize(deserializer)?;
+                Ok(Self::from(value))
+            }
+        }
+    )+};
+}
+
+#[cfg(test)]
+mod tests {
+    use uom::si::f32::Velocity;
+    use uom::si::velocity::{kilometer_per_hour, meter_per_second};
+
+    crate::cu_quantity! {
+        /// A speed logged in km/h.
+        struct Speed(Velocity) in kilometer_per_hour as f32;
+    }
+
+    #[test]
+    fn test_quantity() {
+        let speed = Speed::from(36.0);
+        assert_eq!(speed.get::<meter_per_second>(), 10.0);
+        assert_eq!((speed + speed * 0.5).unit_value(), 54.0);
+        assert_eq!((speed - speed / 4.0).unit_value(), 27.0);
+        assert!(speed > Speed::default());
+
+        let config = bincode::config::standard();
+        let encoded = bincode::encode_to_vec(speed, config).unwrap();
+        assert_eq!(encoded, bincode::encode_to_vec(36.0f32, config).unwrap());
+        let (decoded, _): (Speed, usize) = bincode::decode_from_slice(&encoded, config).unwrap();
+        assert_eq!(decoded, speed);
+
+        assert_eq!(serde_json::to_string(&speed).unwrap(), "36.0");
+        let parsed: Speed = serde_json::from_str("72").unwrap();
+        assert_eq!(parsed.get::<meter_per_second>(), 20.0);
+    }
+}
@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
+mod wire;
+pub use wire::{CuWireConfig, CuWireFormat};
+
 /// Common copper Error type.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CuError {
@@ -73,6 +76,10 @@ pub enum UnifiedLogType {
     StructuredLogLine, // This is for the structured logs (ie. debug! etc..)
     CopperList,        // This is the actual data log storing activities between tasks.
     LastEntry,         // This is a special entry that is used to signal the end of the log.
+    Schema,            // The schema tags of the copperlists, written once at startup.
+    Event,             // The annotations of the interesting moments marked by the tasks.
+    Manifest, // What wrote the log: application, commit, config, components, host, start time.
+    Crash,    // The panic that stopped the application, written by the crash hook.
 }
 
 /// A CopperListTuple needs to be encodable, decodable and fixed size in memory.
@@ -0,0 +1,97 @@
+//! The wire format of everything Copper encodes: the unified log (its headers, the copper lists, the
+//! structured log lines...) and the messages it sends to other processes (taps, zenoh, dds, wasm).
+//!
+//! It does not depend on the architecture, a log written by an ARM robot decodes on an x86 laptop
+//! and the other way around:
+//! - the integers are little endian, and variable length: up to 250 in one byte, then a marker byte
+//!   followed by the u16, u32, u64 or u128. The signed ones are zigzag encoded first.
+//! - `usize` and `isize` are encoded as `u64` and `i64`, whatever the width of the pointers.
+//! - the floats are their IEEE 754 bits, little endian.
+//! - the lengths of the strings, slices and collections are `u64`, the strings are UTF-8.
+//! - `bool` is one byte, `Option` a byte 0/1 then the value, the enums their variant index as a u32.
+//!
+//! The tests of this module pin the bytes of each of those rules: if one of them fails the format
+//! changed, and [CuWireFormat::VERSION] has to be bumped.
+
+use crate::{CuError, CuResult};
+use bincode::config::{Configuration, LittleEndian, NoLimit, Varint};
+use bincode::{Decode, Encode};
+
+/// The bincode configuration of the Copper wire format, the byte order is part of the type.
+pub type CuWireConfig = Configuration<LittleEndian, Varint, NoLimit>;
+
+/// The Copper wire format, see the module documentation for its rules.
+pub struct CuWireFormat;
+
+impl CuWireFormat {
+    /// The version of the wire format, bumped on any change of the encoding of the types.
+    pub const VERSION: u16 = 1;
+
+    /// The configuration to give to bincode to encode or decode anything written by Copper.
+    pub const CONFIG: CuWireConfig = bincode::config::standard()
+        .with_little_endian()
+        .with_variable_int_encoding()
+        .with_no_limit();
+
+    pub fn encode_to_vec<E: Encode>(value: &E) -> CuResult<Vec<u8>> {
+        bincode::encode_to_vec(value, Self::CONFIG)
+            .map_err(|e| CuError::new_with_cause("Failed to encode to the wire format", e))
+    }
+
+    /// Decodes a value and returns it with the number of bytes read.
+    pub fn decode_from_slice<D: Decode<()>>(bytes: &[u8]) -> CuResult<(D, usize)> {
+        bincode::decode_from_slice(bytes, Self::CONFIG)
+            .map_err(|e| CuError::new_with_cause("Failed to decode from the wire format", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UnifiedLogType;
+    use std::fmt::Debug;
+
+    /// The bytes are the same on every architecture, so are the decoded values.
+    fn golden<T: Encode + Decode<()> + PartialEq + Debug>(value: T, bytes: &[u8]) {
+        assert_eq!(CuWireFormat::encode_to_vec(&value).unwrap(), bytes);
+        let (decoded, read) = CuWireFormat::decode_from_slice::<T>(bytes).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(read, bytes.len());
+    }
+
+    #[test]
+    fn test_integers() {
+        golden(250u32, &[250]);
+        golden(251u32, &[251, 251, 0]);
+        golden(0x1234u16, &[251, 0x34, 0x12]);
+        golden(0x1234_5678u32, &[252, 0x78, 0x56, 0x34, 0x12]);
+        golden(
+            0x0102_0304_0506_0708u64,
+            &[253, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+        );
+        golden(-1i32, &[1]);
+        golden(-200i64, &[251, 0x8F, 0x01]);
+        golden(0x1_0000_0000usize, &[253, 0, 0, 0, 0, 1, 0, 0, 0]);
+        golden(-2isize, &[3]);
+    }
+
+    #[test]
+    fn test_floats() {
+        golden(1.5f32, &[0x00, 0x00, 0xC0, 0x3F]);
+        golden(-0.1f64, &[0x9A, 0x99, 0x99, 0x99, 0x99, 0x99, 0xB9, 0xBF]);
+    }
+
+    #[test]
+    fn test_composites() {
+        golden("cu".to_string(), &[2, b'c', b'u']);
+        golden(vec![1u16, 300], &[2, 1, 251, 0x2C, 0x01]);
+        golden([7u8; 3], &[7, 7, 7]);
+        golden(Some(true), &[1, 1]);
+        golden(None::<u64>, &[0]);
+        golden(UnifiedLogType::CopperList, &[2]);
+        golden(
+            (1u8, UnifiedLogType::Crash, -1.0f32),
+            &[1, 7, 0x00, 0x00, 0x80, 0xBF],
+        );
+    }
+}
@@ -1,21 +1,28 @@
-use memmap2::{Mmap, MmapMut};
-use std::fmt::{Debug, Formatter};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display, Formatter};
 use std::fs::{File, OpenOptions};
-use std::io::Read;
-use std::mem::ManuallyDrop;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::slice::from_raw_parts_mut;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
-use std::{io, mem};
+use std::time::{Duration, Instant};
+use std::{io, mem, thread};
 
-use bincode::config::standard;
 use bincode::decode_from_slice;
 use bincode::encode_into_slice;
 use bincode::error::EncodeError;
 use bincode::{Decode, Encode};
-use cu29_traits::{CuError, CuResult, UnifiedLogType, WriteStream};
+use cu29_traits::{CuError, CuResult, CuWireFormat, UnifiedLogType, WriteStream};
 
-const MAIN_MAGIC: [u8; 4] = [0xB4, 0xA5, 0x50, 0xFF];
+pub mod storage;
+
+use storage::NoSlabStorage;
+pub use storage::{FileSlabStorage, LogSlab, MemorySlabStorage, SlabStorage};
+
+// The last byte is the version of the format, 0xFE since the sections have a checksum.
+const MAIN_MAGIC: [u8; 4] = [0xB4, 0xA5, 0x50, 0xFE];
 
 const SECTION_MAGIC: [u8; 2] = [0xFA, 0x57];
 
@@ -36,6 +43,7 @@ pub struct SectionHeader {
     entry_type: UnifiedLogType,
     section_size: u32, // offset from the first byte of this header to the first byte of the next header (MAGIC to MAGIC).
     filled_size: u32,  // how much of the section is filled.
+    checksum: u32,     // CRC32 of the filled part, written when the section is closed.
 }
 
 const MAX_HEADER_SIZE: usize = mem::size_of::<SectionHeader>() + 3usize; // 3 == additional worse case scenario for the 3 int variable encoding
@@ -47,15 +55,17 @@ impl Default for SectionHeader {
             entry_type: UnifiedLogType::Empty,
             section_size: 0,
             filled_size: 0,
+            checksum: 0,
         }
     }
 }
 
 /// A wrapper around a memory mapped file to write to.
+/// It has no section and drops the objects for a null logger.
 struct MmapStream {
     entry_type: UnifiedLogType,
     parent_logger: Arc<Mutex<UnifiedLoggerWrite>>,
-    current_section: SectionHandle,
+    current_section: Option<SectionHandle>,
     current_position: usize,
     minimum_allocation_amount: usize,
 }
@@ -66,10 +76,12 @@ impl MmapStream {
         parent_logger: Arc<Mutex<UnifiedLoggerWrite>>,
         minimum_allocation_amount: usize,
     ) -> Self {
-        let section = parent_logger
-            .lock()
-            .unwrap()
-            .add_section(entry_type, minimum_allocation_amount);
+        let section = {
+            let mut logger_guard = parent_logger.lock().unwrap();
+            (!logger_guard.is_null())
+                .then(|| logger_guard.add_section(entry_type, minimum_allocation_amount))
+                .flatten()
+        };
         Self {
             entry_type,
             parent_logger,
@@ -88,48 +100,74 @@ impl Debug for MmapStream {
 
 impl<E: Encode> WriteStream<E> for MmapStream {
     fn log(&mut self, obj: &E) -> CuResult<()> {
-        let dst = self.current_section.get_user_buffer();
-        let result = encode_into_slice(obj, dst, standard());
+        let Some(current_section) = self.current_section.as_mut() else {
+            return Ok(());
+        };
+        let dst = current_section.get_user_buffer();
+        let result = encode_into_slice(obj, dst, CuWireFormat::CONFIG);
         match result {
             Ok(nb_bytes) => {
                 self.current_position += nb_bytes;
-                self.current_section.used += nb_bytes as u32;
+                current_section.mark_used(nb_bytes);
                 Ok(())
             }
-            Err(e) => match e {
-                EncodeError::UnexpectedEnd => {
-                    let mut logger_guard = self.parent_logger.lock().unwrap();
-                    logger_guard.flush_section(&mut self.current_section);
-                    self.current_section =
-                        logger_guard.add_section(self.entry_type, self.minimum_allocation_amount);
-
-                    let result = encode_into_slice(
-                        obj,
-                        self.current_section.get_user_buffer(),
-                        standard(),
-                    )
-                    .expect(
-                        "Failed to encode object in a newly minted section. Unrecoverable failure.",
-                    ); // If we fail just after creating a section, there is not much we can do, we need to bail.
-                    self.current_position += result;
-                    self.current_section.used += result as u32;
-                    Ok(())
-                }
-                _ => {
-                    let err =
-                        <&str as Into<CuError>>::into("Unexpected error while encoding object.")
-                            .add_cause(e.to_string().as_str());
-                    Err(err)
-                }
-            },
+            Err(EncodeError::UnexpectedEnd) => {
+                let mut logger_guard = self.parent_logger.lock().unwrap();
+                logger_guard.flush_section(current_section);
+                let Some(section) =
+                    logger_guard.add_section(self.entry_type, self.minimum_allocation_amount)
+                else {
+                    // The log is full and the logger stopped, this stream drops what it is given.
+                    self.current_section = None;
+                    return Ok(());
+                };
+                *current_section = section;
+
+                // If we fail just after creating a section, there is not much we can do, we need to bail.
+                let result = encode_into_slice(
+                    obj,
+                    current_section.get_user_buffer(),
+                    CuWireFormat::CONFIG,
+                )
+                .expect(
+                    "Failed to encode object in a newly minted section. Unrecoverable failure.",
+                );
+                self.current_position += result;
+                current_section.mark_used(result);
+                Ok(())
+            }
+            Err(e) => {
+                let err = <&str as Into<CuError>>::into("Unexpected error while encoding object.")
+                    .add_cause(e.to_string().as_str());
+                Err(err)
+            }
+        }
+    }
+
+    /// Closes the current section so it is written to disk, the next objects go to a new one.
+    fn flush(&mut self) -> CuResult<()> {
+        let Some(current_section) = self.current_section.as_mut() else {
+            return Ok(());
+        };
+        if current_section.used() == 0 {
+            return Ok(());
+        }
+        let mut logger_guard = self.parent_logger.lock().unwrap();
+        logger_guard.flush_section(current_section);
+        match logger_guard.add_section(self.entry_type, self.minimum_allocation_amount) {
+            Some(section) => *current_section = section,
+            None => self.current_section = None,
         }
+        Ok(())
     }
 }
 
 impl Drop for MmapStream {
     fn drop(&mut self) {
-        let mut logger_guard = self.parent_logger.lock().unwrap();
-        logger_guard.flush_section(&mut self.current_section);
+        if let Some(current_section) = self.current_section.as_mut() {
+            let mut logger_guard = self.parent_logger.lock().unwrap();
+            logger_guard.flush_section(current_section);
+        }
     }
 }
 
@@ -151,9 +189,12 @@ pub enum UnifiedLogger {
 /// Use this builder to create a new DataLogger.
 pub struct UnifiedLoggerBuilder {
     file_base_name: Option<PathBuf>,
+    storage: Option<Box<dyn SlabStorage>>,
     preallocated_size: Option<usize>,
     write: bool,
     create: bool,
+    follow: bool,
+    salvage: bool,
 }
 
 impl Default for UnifiedLoggerBuilder {
@@ -166,9 +207,12 @@ impl UnifiedLoggerBuilder {
     pub fn new() -> Self {
         Self {
             file_base_name: None,
+            storage: None,
             preallocated_size: None,
             write: false,
             create: false, // This is the safest default
+            follow: false,
+            salvage: false,
         }
     }
 
@@ -178,6 +222,13 @@ impl UnifiedLoggerBuilder {
         self
     }
 
+    /// For writing: the storage of the slabs instead of the files named after
+    /// [UnifiedLoggerBuilder::file_base_name], see the [storage] module.
+    pub fn storage(mut self, storage: impl SlabStorage + 'static) -> Self {
+        self.storage = Some(Box::new(storage));
+        self
+    }
+
     pub fn preallocated_size(mut self, preallocated_size: usize) -> Self {
         self.preallocated_size = Some(preallocated_size);
         self
@@ -193,41 +244,122 @@ impl UnifiedLoggerBuilder {
         self
     }
 
+    /// For reading: waits for the sections of a log still being written instead of failing at the
+    /// end of what has been written so far. The sections are read once they have been closed by the
+    /// writer and the reading ends with the log.
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// For reading: skips the damaged sections of the log instead of failing on them, and ends at
+    /// the last section written if the log has not been closed, see [SalvageReport].
+    pub fn salvage(mut self, salvage: bool) -> Self {
+        self.salvage = salvage;
+        self
+    }
+
     pub fn build(self) -> io::Result<UnifiedLogger> {
         let page_size = page_size::get();
 
         if self.write && self.create {
-            let ulw = UnifiedLoggerWrite::new(
-                &self.file_base_name.unwrap(),
-                self.preallocated_size.unwrap(),
-                page_size,
-            );
+            let storage = match (self.storage, self.file_base_name) {
+                (Some(storage), _) => storage,
+                (None, Some(file_path)) => Box::new(FileSlabStorage::new(&file_path)),
+                (None, None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "File path or storage is required",
+                    ))
+                }
+            };
+            let ulw = UnifiedLoggerWrite::new(storage, self.preallocated_size.unwrap(), page_size)?;
 
             Ok(UnifiedLogger::Write(ulw))
         } else {
             let file_path = self.file_base_name.ok_or_else(|| {
                 io::Error::new(io::ErrorKind::InvalidInput, "File path is required")
             })?;
-            let ulr = UnifiedLoggerRead::new(&file_path)?;
+            let mut ulr = UnifiedLoggerRead::new(&file_path)?;
+            ulr.follow = self.follow;
+            ulr.salvage = self.salvage;
             Ok(UnifiedLogger::Read(ulr))
         }
     }
 }
 
 /// A read side of the datalogger.
+/// It reads the slabs with plain file reads, not through memory maps, so its memory doesn't grow
+/// with the size of the log and the slabs can be truncated by a writer while it follows them.
 pub struct UnifiedLoggerRead {
     base_file_path: PathBuf,
-    current_mmap_buffer: Mmap,
     current_file: File,
     current_slab_index: usize,
     current_reading_position: usize,
+    /// What is left to read of the content of the current section, as positions in the slab.
+    current_section: Range<usize>,
+    /// The sections are aligned on pages, the first one being at the first page.
+    page_size: usize,
+    follow: bool,
+    salvage: bool,
+    report: SalvageReport,
+    salvage_reported: bool,
+}
+
+/// A section of a log the reader could not read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamagedSection {
+    pub slab: usize,
+    pub offset: usize,
+    pub reason: String,
+}
+
+impl Display for DamagedSection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the damaged section at offset {} of slab {} ({})",
+            self.offset, self.slab, self.reason
+        )
+    }
+}
+
+/// What the reader recovered from a log, typically after a power loss.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SalvageReport {
+    /// The sections read successfully.
+    pub sections: usize,
+    pub damaged_sections: Vec<DamagedSection>,
+    /// The log was never closed by the writer, the end of the run is missing.
+    pub truncated: bool,
+}
+
+impl Display for SalvageReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Salvage: {} sections recovered, {} damaged sections skipped",
+            self.sections,
+            self.damaged_sections.len()
+        )?;
+        if self.truncated {
+            write!(f, ", the log is truncated")?;
+        }
+        Ok(())
+    }
 }
 
 struct SlabEntry {
-    file: File,
-    mmap_buffer: ManuallyDrop<MmapMut>,
+    slab: Box<dyn LogSlab>,
+    /// The index of the slab in the storage.
+    index: usize,
+    /// The end of the buffer kept for the last entry of a bounded log, see
+    /// [UnifiedLoggerWrite::set_capacity].
+    reserved: usize,
+    /// The buffer of the slab, it doesn't move until the slab is dropped, see [LogSlab::buffer].
+    buffer: &'static mut [u8],
     current_global_position: usize,
-    sections_offsets_in_flight: Vec<usize>,
+    sections_in_flight: Vec<InFlightSection>,
     flushed_until_offset: usize,
     page_size: usize,
 }
@@ -235,12 +367,11 @@ struct SlabEntry {
 impl Drop for SlabEntry {
     fn drop(&mut self) {
         self.flush_until(self.current_global_position);
-        unsafe { ManuallyDrop::drop(&mut self.mmap_buffer) };
-        self.file
-            .set_len(self.current_global_position as u64)
-            .expect("Failed to trim datalogger file");
+        self.slab
+            .close(self.current_global_position)
+            .expect("Failed to close the datalogger slab");
 
-        if !self.sections_offsets_in_flight.is_empty() {
+        if !self.sections_in_flight.is_empty() {
             eprintln!("Error: Slab not full flushed.");
         }
     }
@@ -252,14 +383,17 @@ pub enum AllocatedSection {
 }
 
 impl SlabEntry {
-    fn new(file: File, page_size: usize) -> Self {
-        let mmap_buffer =
-            ManuallyDrop::new(unsafe { MmapMut::map_mut(&file).expect("Failed to map file") });
+    fn new(mut slab: Box<dyn LogSlab>, index: usize, page_size: usize) -> Self {
+        let buffer = slab.buffer();
+        // The buffer of a slab doesn't move until it is dropped, the borrow checker cannot see it.
+        let buffer = unsafe { from_raw_parts_mut(buffer.as_mut_ptr(), buffer.len()) };
         Self {
-            file,
-            mmap_buffer,
+            slab,
+            index,
+            reserved: 0,
+            buffer,
             current_global_position: 0,
-            sections_offsets_in_flight: Vec::with_capacity(16),
+            sections_in_flight: Vec::with_capacity(16),
             flushed_until_offset: 0,
             page_size,
         }
@@ -271,27 +405,23 @@ impl SlabEntry {
         if (self.flushed_until_offset == until_position) || (until_position == 0) {
             return;
         }
-        self.mmap_buffer
-            .flush_async_range(
-                self.flushed_until_offset,
-                until_position - self.flushed_until_offset,
-            )
-            .expect("Failed to flush memory map");
+        self.slab
+            .flush(self.flushed_until_offset..until_position)
+            .expect("Failed to flush the datalogger slab");
         self.flushed_until_offset = until_position;
     }
 
     fn is_it_my_section(&self, section: &SectionHandle) -> bool {
-        (section.buffer.as_ptr() >= self.mmap_buffer.as_ptr())
+        (section.buffer.as_ptr() >= self.buffer.as_ptr())
             && (section.buffer.as_ptr() as usize)
-                < (self.mmap_buffer.as_ref().as_ptr() as usize + self.mmap_buffer.as_ref().len())
+                < (self.buffer.as_ptr() as usize + self.buffer.len())
     }
 
     /// Flush the section to disk.
     /// the flushing is permanent and the section is considered closed.
     fn flush_section(&mut self, section: &mut SectionHandle) {
-        if section.buffer.as_ptr() < self.mmap_buffer.as_ptr()
-            || section.buffer.as_ptr() as usize
-                > self.mmap_buffer.as_ptr() as usize + self.mmap_buffer.len()
+        if section.buffer.as_ptr() < self.buffer.as_ptr()
+            || section.buffer.as_ptr() as usize > self.buffer.as_ptr() as usize + self.buffer.len()
         {
             panic!("Invalid section buffer, not in the slab");
         }
@@ -299,20 +429,24 @@ impl SlabEntry {
         // Be sure that the header reflects the actual size of the section.
         section.update_header();
 
-        let _sz = encode_into_slice(&section.section_header, section.buffer, standard())
-            .expect("Failed to encode section header");
+        let _sz = encode_into_slice(
+            &section.section_header,
+            section.buffer,
+            CuWireFormat::CONFIG,
+        )
+        .expect("Failed to encode section header");
 
-        let base = self.mmap_buffer.as_ptr() as usize;
+        let base = self.buffer.as_ptr() as usize;
         let section_buffer_addr = section.buffer.as_ptr() as usize;
-        self.sections_offsets_in_flight
-            .retain(|&x| x != section_buffer_addr - base);
+        self.sections_in_flight
+            .retain(|in_flight| in_flight.offset != section_buffer_addr - base);
 
-        if self.sections_offsets_in_flight.is_empty() {
+        if self.sections_in_flight.is_empty() {
             self.flush_until(self.current_global_position);
             return;
         }
-        if self.flushed_until_offset < self.sections_offsets_in_flight[0] {
-            self.flush_until(self.sections_offsets_in_flight[0]);
+        if self.flushed_until_offset < self.sections_in_flight[0].offset {
+            self.flush_until(self.sections_in_flight[0].offset);
         }
     }
 
@@ -331,8 +465,14 @@ impl SlabEntry {
         self.current_global_position = self.align_to_next_page(self.current_global_position);
         let section_size = self.align_to_next_page(requested_section_size) as u32;
 
-        // We need to have enough space to store the section in that slab
-        if self.current_global_position + section_size as usize > self.mmap_buffer.len() {
+        // We need to have enough space to store the section in that slab, only the last entry
+        // goes to the reserved end.
+        let available = if entry_type == UnifiedLogType::LastEntry {
+            self.buffer.len()
+        } else {
+            self.buffer.len() - self.reserved
+        };
+        if self.current_global_position + section_size as usize > available {
             return AllocatedSection::NoMoreSpace;
         }
 
@@ -341,21 +481,20 @@ impl SlabEntry {
             entry_type,
             section_size,
             filled_size: 0u32,
+            checksum: 0u32,
         };
 
         let nb_bytes = encode_into_slice(
             &section_header,
-            &mut self.mmap_buffer[self.current_global_position..],
-            standard(),
+            &mut self.buffer[self.current_global_position..],
+            CuWireFormat::CONFIG,
         )
         .expect("Failed to encode section header");
         assert!(nb_bytes < self.page_size);
 
-        // save the position to keep track for in flight sections
-        self.sections_offsets_in_flight
-            .push(self.current_global_position);
+        let section_offset = self.current_global_position;
         let end_of_section = self.current_global_position + requested_section_size;
-        let user_buffer = &mut self.mmap_buffer[self.current_global_position..end_of_section];
+        let user_buffer = &mut self.buffer[self.current_global_position..end_of_section];
 
         // here we have the guarantee for exclusive access to that memory for the lifetime of the handle, the borrow checker cannot understand that ever.
         let handle_buffer =
@@ -363,7 +502,45 @@ impl SlabEntry {
 
         self.current_global_position = end_of_section;
 
-        AllocatedSection::Section(SectionHandle::create(section_header, handle_buffer))
+        let handle = SectionHandle::create(section_header, handle_buffer);
+        // save the position to keep track for in flight sections
+        self.sections_in_flight.push(InFlightSection {
+            offset: section_offset,
+            entry_type,
+            section_size,
+            used: handle.used.clone(),
+        });
+        AllocatedSection::Section(handle)
+    }
+
+    /// Writes the headers of the sections in flight with what they hold so far and flushes the
+    /// slab until its current position. The sections stay in flight.
+    fn sync(&mut self) -> io::Result<()> {
+        for in_flight in &self.sections_in_flight {
+            let used = in_flight.used.load(Ordering::Acquire);
+            if used == 0 {
+                continue;
+            }
+            let content = in_flight.offset + MAX_HEADER_SIZE;
+            let section_header = SectionHeader {
+                magic: SECTION_MAGIC,
+                entry_type: in_flight.entry_type,
+                section_size: in_flight.section_size,
+                filled_size: used,
+                checksum: crc32fast::hash(&self.buffer[content..content + used as usize]),
+            };
+            encode_into_slice(
+                &section_header,
+                &mut self.buffer[in_flight.offset..],
+                CuWireFormat::CONFIG,
+            )
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        }
+        if self.flushed_until_offset < self.current_global_position {
+            self.slab
+                .flush(self.flushed_until_offset..self.current_global_position)?;
+        }
+        Ok(())
     }
 
     #[cfg(test)]
@@ -372,13 +549,22 @@ impl SlabEntry {
     }
 }
 
+/// A section allocated and not closed yet, see [SectionHandle].
+struct InFlightSection {
+    offset: usize,
+    entry_type: UnifiedLogType,
+    section_size: u32,
+    /// Shared with the handle of the section.
+    used: Arc<AtomicU32>,
+}
+
 /// A SectionHandle is a handle to a section in the datalogger.
 /// It allows to track the lifecycle of a section of the datalogger.
 #[derive(Default)]
 pub struct SectionHandle {
     section_header: SectionHeader,
     buffer: &'static mut [u8], // This includes the encoded header for end of section patching.
-    used: u32,                 // this is the size of the used part of the buffer.
+    used: Arc<AtomicU32>, // this is the size of the used part of the buffer, shared with the slab.
 }
 
 // This is for a placeholder to unsure an orderly cleanup as we dodge the borrow checker.
@@ -402,40 +588,112 @@ impl SectionHandle {
         Self {
             section_header,
             buffer,
-            used: 0,
+            used: Arc::new(AtomicU32::new(0)),
         }
     }
     pub fn get_user_buffer(&mut self) -> &mut [u8] {
-        &mut self.buffer[MAX_HEADER_SIZE + self.used as usize..]
+        let used = self.used() as usize;
+        &mut self.buffer[MAX_HEADER_SIZE + used..]
+    }
+
+    /// The size of the used part of the buffer.
+    pub fn used(&self) -> u32 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Accounts for `nb_bytes` more written to the user buffer.
+    /// Released so a [UnifiedLoggerWrite::sync] from another thread sees the bytes written before.
+    pub fn mark_used(&mut self, nb_bytes: usize) {
+        self.used.fetch_add(nb_bytes as u32, Ordering::Release);
     }
 
     pub fn update_header(&mut self) {
         // no need to do anything if we never used the section.
-        if self.section_header.entry_type == UnifiedLogType::Empty || self.used == 0 {
+        let used = self.used();
+        if self.section_header.entry_type == UnifiedLogType::Empty || used == 0 {
             return;
         }
-        self.section_header.filled_size = self.used;
+        self.section_header.filled_size = used;
+        self.section_header.checksum =
+            crc32fast::hash(&self.buffer[MAX_HEADER_SIZE..MAX_HEADER_SIZE + used as usize]);
 
         // FIX ME: This was flushed before and cannot be written back to.
-        // let _sz = encode_into_slice(&self.section_header, &mut self.buffer, standard())
+        // let _sz = encode_into_slice(&self.section_header, &mut self.buffer, CuWireFormat::CONFIG)
         //     .expect("Failed to encode section header");
     }
 }
 
 /// A write side of the datalogger.
+/// The null logger, see [UnifiedLoggerWrite::null], has no slab and drops everything written to it.
 pub struct UnifiedLoggerWrite {
-    /// the front slab is the current active slab for any new section.
-    front_slab: SlabEntry,
+    /// the front slab is the current active slab for any new section, None for the null logger.
+    front_slab: Option<SlabEntry>,
     /// the back slab is the previous slab that is being flushed.
     back_slabs: Vec<SlabEntry>,
-    /// base file path to create the backing files from.
-    base_file_path: PathBuf,
+    /// the storage creating the slabs, memory mapped files by default.
+    storage: Box<dyn SlabStorage>,
     /// allocation size for the backing files.
     slab_size: usize,
     /// current suffix for the backing files.
     front_slab_suffix: usize,
+    /// Bound of the bytes of the log in the storage, see [UnifiedLoggerWrite::set_capacity].
+    capacity: Option<usize>,
+    on_full: OnLogFull,
+    /// The index and the size of the slabs closed and still in the storage, oldest first.
+    closed_slabs: VecDeque<(usize, usize)>,
+    /// The log is full and the logger stopped, see [OnLogFull::Stop].
+    stopped: bool,
+    /// What happened since the last [UnifiedLoggerWrite::take_full_events].
+    full_events: Vec<LogFullEvent>,
+}
+
+/// What the logger does once its log reaches its capacity, see [UnifiedLoggerWrite::set_capacity].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnLogFull {
+    /// Stops logging, the streams drop what they are given. The beginning of the run is kept.
+    #[default]
+    Stop,
+    /// Removes the oldest slabs from the storage, the end of the run is kept. The first slab,
+    /// starting with the header of the log, is never removed.
+    Rotate,
+    /// Waits for a closed slab to be removed from the storage by someone else, an uploader
+    /// deleting what it sent for example. Everything logging waits with it.
+    Block,
 }
 
+/// What the logger did when its log reached its capacity.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogFullEvent {
+    /// Nothing is logged anymore.
+    Stopped,
+    /// The slab of this index was removed from the storage.
+    Rotated(usize),
+    /// The logging waited this long for a slab to be removed.
+    Blocked(Duration),
+}
+
+/// How much of its capacity a log uses, see [UnifiedLoggerWrite::capacity].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogCapacity {
+    /// The bytes of the log in the storage.
+    pub used: usize,
+    /// None if the log is not bounded.
+    pub capacity: Option<usize>,
+    /// The log is full and the logger stopped.
+    pub stopped: bool,
+}
+
+impl LogCapacity {
+    /// The bytes left before the log is full, None if it is not bounded.
+    pub fn remaining(&self) -> Option<usize> {
+        self.capacity
+            .map(|capacity| capacity.saturating_sub(self.used))
+    }
+}
+
+/// How often a blocked logger checks if a slab has been removed, see [OnLogFull::Block].
+const BLOCKED_POLL_PERIOD: Duration = Duration::from_millis(10);
+
 fn build_slab_path(base_file_path: &Path, slab_index: usize) -> PathBuf {
     let mut file_path = base_file_path.to_path_buf();
     let file_name = file_path.file_name().unwrap().to_str().unwrap();
@@ -447,30 +705,27 @@ fn build_slab_path(base_file_path: &Path, slab_index: usize) -> PathBuf {
     file_path
 }
 
-fn make_slab_file(base_file_path: &Path, slab_size: usize, slab_suffix: usize) -> File {
-    let file_path = build_slab_path(base_file_path, slab_suffix);
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&file_path)
-        .unwrap_or_else(|_| panic!("Failed to open file: {}", file_path.display()));
-    file.set_len(slab_size as u64)
-        .expect("Failed to set file length");
-    file
-}
-
 impl UnifiedLoggerWrite {
-    fn next_slab(&mut self) -> File {
+    fn next_slab(&mut self, page_size: usize) -> SlabEntry {
         self.front_slab_suffix += 1;
 
-        make_slab_file(&self.base_file_path, self.slab_size, self.front_slab_suffix)
+        let slab = self
+            .storage
+            .create_slab(self.front_slab_suffix, self.slab_size)
+            .expect("Failed to create a new datalogger slab");
+        let mut slab = SlabEntry::new(slab, self.front_slab_suffix, page_size);
+        if self.capacity.is_some() {
+            slab.reserved = page_size;
+        }
+        slab
     }
 
-    fn new(base_file_path: &Path, slab_size: usize, page_size: usize) -> Self {
-        let file = make_slab_file(base_file_path, slab_size, 0);
-        let mut front_slab = SlabEntry::new(file, page_size);
+    fn new(
+        mut storage: Box<dyn SlabStorage>,
+        slab_size: usize,
+        page_size: usize,
+    ) -> io::Result<Self> {
+        let mut front_slab = SlabEntry::new(storage.create_slab(0, slab_size)?, 0, page_size);
 
         // This is the first slab so add the main header.
         let main_header = MainHeader {
@@ -478,18 +733,140 @@ impl UnifiedLoggerWrite {
             first_section_offset: page_size as u16,
             page_size: page_size as u16,
         };
-        let nb_bytes = encode_into_slice(&main_header, &mut front_slab.mmap_buffer[..], standard())
+        let nb_bytes = encode_into_slice(&main_header, front_slab.buffer, CuWireFormat::CONFIG)
             .expect("Failed to encode main header");
         assert!(nb_bytes < page_size);
         front_slab.current_global_position = page_size; // align to the next page
 
-        Self {
-            front_slab,
+        Ok(Self {
+            front_slab: Some(front_slab),
             back_slabs: Vec::new(),
-            base_file_path: base_file_path.to_path_buf(),
+            storage,
             slab_size,
             front_slab_suffix: 0,
+            capacity: None,
+            on_full: OnLogFull::default(),
+            closed_slabs: VecDeque::new(),
+            stopped: false,
+            full_events: Vec::new(),
+        })
+    }
+
+    /// A logger writing nowhere: no file is created and no memory is allocated, the streams
+    /// created on it drop what they are given. This is for the applications running without a log,
+    /// on constrained targets or in unit tests.
+    pub fn null() -> Self {
+        Self {
+            front_slab: None,
+            back_slabs: Vec::new(),
+            storage: Box::new(NoSlabStorage),
+            slab_size: 0,
+            front_slab_suffix: 0,
+            capacity: None,
+            on_full: OnLogFull::default(),
+            closed_slabs: VecDeque::new(),
+            stopped: false,
+            full_events: Vec::new(),
+        }
+    }
+
+    /// Bounds the bytes of the log in the storage, `on_full` telling what to do once they are
+    /// reached. The capacity needs to hold at least 2 slabs, more to rotate: the first slab and
+    /// the slabs with a section in flight are never removed.
+    /// The last page of every slab is then kept for the end of the log, so a stopped log is
+    /// still complete.
+    pub fn set_capacity(&mut self, capacity: usize, on_full: OnLogFull) -> CuResult<()> {
+        let Some(front_slab) = self.front_slab.as_mut() else {
+            return Ok(());
+        };
+        if capacity < 2 * self.slab_size {
+            return Err(CuError::from(format!(
+                "The capacity of the log of {capacity} bytes cannot hold 2 slabs of {} bytes.",
+                self.slab_size
+            )));
+        }
+        front_slab.reserved = front_slab.page_size;
+        self.capacity = Some(capacity);
+        self.on_full = on_full;
+        Ok(())
+    }
+
+    /// How much of its capacity the log uses.
+    pub fn capacity(&self) -> LogCapacity {
+        let closed: usize = self.closed_slabs.iter().map(|(_, size)| size).sum();
+        let open: usize = self
+            .back_slabs
+            .iter()
+            .chain(self.front_slab.as_ref())
+            .map(|slab| slab.current_global_position)
+            .sum();
+        LogCapacity {
+            used: closed + open,
+            capacity: self.capacity,
+            stopped: self.stopped,
+        }
+    }
+
+    /// What the logger did since the last call when the log reached its capacity.
+    pub fn take_full_events(&mut self) -> Vec<LogFullEvent> {
+        mem::take(&mut self.full_events)
+    }
+
+    /// The bytes the slabs of the log take in the storage, the open ones at their full size.
+    fn allocated(&self) -> usize {
+        let closed: usize = self.closed_slabs.iter().map(|(_, size)| size).sum();
+        closed + (self.back_slabs.len() + 1) * self.slab_size
+    }
+
+    /// Makes room in the storage for a new slab as the policy says, false if the logger stops.
+    fn make_room(&mut self) -> bool {
+        let Some(capacity) = self.capacity else {
+            return true;
+        };
+        let storage = &self.storage;
+        self.closed_slabs
+            .retain(|(index, _)| !storage.is_slab_removed(*index));
+        while self.allocated() + self.slab_size > capacity {
+            match self.on_full {
+                OnLogFull::Stop => return false,
+                OnLogFull::Rotate => {
+                    // The first slab starts with the header of the log.
+                    let Some(position) =
+                        self.closed_slabs.iter().position(|(index, _)| *index != 0)
+                    else {
+                        return false;
+                    };
+                    let (index, _) = self.closed_slabs.remove(position).unwrap();
+                    if let Err(e) = self.storage.remove_slab(index) {
+                        eprintln!("Error: could not remove the slab {index} of the log: {e}");
+                        return false;
+                    }
+                    self.full_events.push(LogFullEvent::Rotated(index));
+                }
+                OnLogFull::Block => {
+                    // Only a closed slab can be removed.
+                    if self.closed_slabs.is_empty() {
+                        return false;
+                    }
+                    let start = Instant::now();
+                    let before = self.closed_slabs.len();
+                    while self.closed_slabs.len() == before {
+                        thread::sleep(BLOCKED_POLL_PERIOD);
+                        let storage = &self.storage;
+                        self.closed_slabs
+                            .retain(|(index, _)| !storage.is_slab_removed(*index));
+                    }
+                    self.full_events
+                        .push(LogFullEvent::Blocked(start.elapsed()));
+                }
+            }
         }
+        true
+    }
+
+    /// If this is the null logger, see [UnifiedLoggerWrite::null].
+    pub fn is_null(&self) -> bool {
+        self.front_slab.is_none()
     }
 
     pub fn flush_section(&mut self, section: &mut SectionHandle) {
@@ -499,191 +876,495 @@ impl UnifiedLoggerWrite {
                 return;
             }
         }
-        self.front_slab.flush_section(section);
+        if let Some(front_slab) = self.front_slab.as_mut() {
+            front_slab.flush_section(section);
+        }
+    }
+
+    /// Makes what has been logged so far readable even if the process dies right after, without
+    /// closing anything: the headers of the sections in flight are written with their current
+    /// content and the slabs are flushed. This is for the crashes, see the crash hook of the runtime.
+    pub fn sync(&mut self) -> io::Result<()> {
+        for slab in self.back_slabs.iter_mut().chain(self.front_slab.as_mut()) {
+            slab.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Writes an object alone in a new section, closed right away. This is for the records written
+    /// once outside of any stream.
+    pub fn write_section<E: Encode>(
+        &mut self,
+        entry_type: UnifiedLogType,
+        obj: &E,
+    ) -> CuResult<()> {
+        if self.is_null() {
+            return Ok(());
+        }
+        let encoded = bincode::encode_to_vec(obj, CuWireFormat::CONFIG)
+            .map_err(|e| CuError::new_with_cause("Could not encode the section", e))?;
+        let Some(mut section) = self.add_section(entry_type, MAX_HEADER_SIZE + encoded.len())
+        else {
+            return Ok(());
+        };
+        section.get_user_buffer()[..encoded.len()].copy_from_slice(&encoded);
+        section.mark_used(encoded.len());
+        self.flush_section(&mut section);
+        Ok(())
     }
 
     fn garbage_collect_backslabs(&mut self) {
-        self.back_slabs
-            .retain_mut(|slab| !slab.sections_offsets_in_flight.is_empty());
+        let (open, closed): (Vec<_>, Vec<_>) = mem::take(&mut self.back_slabs)
+            .into_iter()
+            .partition(|slab| !slab.sections_in_flight.is_empty());
+        self.back_slabs = open;
+        for slab in closed {
+            self.closed_slabs
+                .push_back((slab.index, slab.current_global_position));
+        }
     }
 
     /// The returned slice is section_size or greater.
+    /// None once the log is full and the logger stopped, see [OnLogFull::Stop].
     fn add_section(
         &mut self,
         entry_type: UnifiedLogType,
         requested_section_size: usize,
-    ) -> SectionHandle {
+    ) -> Option<SectionHandle> {
         self.garbage_collect_backslabs(); // Take the opportunity to keep up and close stale back slabs.
+        if self.stopped && entry_type != UnifiedLogType::LastEntry {
+            return None;
+        }
 
-        let maybe_section = self
+        let front_slab = self
             .front_slab
-            .add_section(entry_type, requested_section_size);
+            .as_mut()
+            .expect("No section can be added to the null logger");
+        let page_size = front_slab.page_size;
+        let maybe_section = front_slab.add_section(entry_type, requested_section_size);
 
         match maybe_section {
             AllocatedSection::NoMoreSpace => {
+                // The end of a stopped log goes to the reserved end of its last slab.
+                if self.stopped || !self.make_room() {
+                    if !self.stopped {
+                        self.stopped = true;
+                        self.full_events.push(LogFullEvent::Stopped);
+                    }
+                    return None;
+                }
                 // move the front slab to the back slab.
-                let new_slab = SlabEntry::new(self.next_slab(), self.front_slab.page_size);
+                let new_slab = self.next_slab(page_size);
                 // keep the slab until all its sections has been flushed.
-                self.back_slabs
-                    .push(mem::replace(&mut self.front_slab, new_slab));
+                let old_slab = self.front_slab.replace(new_slab).unwrap();
+                self.back_slabs.push(old_slab);
                 match self
                     .front_slab
+                    .as_mut()
+                    .unwrap()
                     .add_section(entry_type, requested_section_size)
                 {
                     AllocatedSection::NoMoreSpace => {
                         panic!("Failed to allocate a section in a new slab");
                     }
-                    AllocatedSection::Section(section) => section,
+                    AllocatedSection::Section(section) => Some(section),
                 }
             }
-            AllocatedSection::Section(section) => section,
+            AllocatedSection::Section(section) => Some(section),
         }
     }
 
     pub fn stats(&self) -> (usize, Vec<usize>, usize) {
-        (
-            self.front_slab.current_global_position,
-            self.front_slab.sections_offsets_in_flight.clone(),
-            self.back_slabs.len(),
-        )
+        match &self.front_slab {
+            Some(front_slab) => (
+                front_slab.current_global_position,
+                front_slab
+                    .sections_in_flight
+                    .iter()
+                    .map(|in_flight| in_flight.offset)
+                    .collect(),
+                self.back_slabs.len(),
+            ),
+            None => (0, Vec::new(), 0),
+        }
+    }
+
+    #[cfg(test)]
+    fn front_slab(&self) -> &SlabEntry {
+        self.front_slab.as_ref().unwrap()
     }
 }
 
 impl Drop for UnifiedLoggerWrite {
     fn drop(&mut self) {
-        let mut section = self.add_section(UnifiedLogType::LastEntry, 80); // TODO: determine that exactly
-        self.front_slab.flush_section(&mut section);
+        if self.is_null() {
+            return;
+        }
+        if let Some(mut section) = self.add_section(UnifiedLogType::LastEntry, 80) {
+            // TODO: determine that exactly
+            self.flush_section(&mut section);
+        }
         self.garbage_collect_backslabs();
     }
 }
 
-fn open_slab_index(base_file_path: &Path, slab_index: usize) -> io::Result<(File, Mmap, u16)> {
-    let mut options = OpenOptions::new();
-    let options = options.read(true);
+/// The index of the first slab of the log after `slab_index`, the slabs in between might have
+/// been removed by a rotation, see [OnLogFull::Rotate].
+fn next_slab_index(base_file_path: &Path, slab_index: usize) -> Option<usize> {
+    if build_slab_path(base_file_path, slab_index + 1).exists() {
+        return Some(slab_index + 1);
+    }
+    let first_slab = build_slab_path(base_file_path, 0);
+    let first_name = first_slab.file_name()?.to_str()?;
+    let (prefix, extension) = first_name.split_once("_0.")?;
+    let directory = match first_slab.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let index = name
+                .to_str()?
+                .strip_prefix(prefix)?
+                .strip_prefix('_')?
+                .strip_suffix(extension)?
+                .strip_suffix('.')?
+                .parse::<usize>()
+                .ok()?;
+            (index > slab_index).then_some(index)
+        })
+        .min()
+}
 
+fn open_slab_index(base_file_path: &Path, slab_index: usize) -> io::Result<(File, u16)> {
     let file_path = build_slab_path(base_file_path, slab_index);
-    let file = options.open(file_path)?;
-    let mmap = unsafe { Mmap::map(&file) }?;
+    let mut file = OpenOptions::new().read(true).open(file_path)?;
     let mut prolog = 0u16;
     if slab_index == 0 {
-        let main_header: MainHeader;
-        let _read: usize;
-        (main_header, _read) =
-            decode_from_slice(&mmap[..], standard()).expect("Failed to decode main header");
+        let mut buffer = [0u8; mem::size_of::<MainHeader>() + 4];
+        let read = read_at(&mut file, 0, &mut buffer)?;
+        let (main_header, _): (MainHeader, usize) =
+            decode_from_slice(&buffer[..read], CuWireFormat::CONFIG).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Failed to decode main header: {e}"),
+                )
+            })?;
         if main_header.magic != MAIN_MAGIC {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
-                "Invalid magic number in main header",
+                "Invalid magic number in main header, is it a log of another version of Copper?",
             ));
         }
         prolog = main_header.first_section_offset;
     }
-    Ok((file, mmap, prolog))
+    Ok((file, prolog))
+}
+
+/// Reads as much as possible of `buffer` from `position`, less at the end of the file.
+fn read_at(file: &mut File, position: usize, buffer: &mut [u8]) -> io::Result<usize> {
+    file.seek(SeekFrom::Start(position as u64))?;
+    let mut read = 0;
+    while read < buffer.len() {
+        match file.read(&mut buffer[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
 }
 
+/// The section header at `position`, None if nothing has been written there (yet).
+fn read_section_header_at(file: &mut File, position: usize) -> io::Result<Option<SectionHeader>> {
+    let mut buffer = [0u8; MAX_HEADER_SIZE];
+    let read = read_at(file, position, &mut buffer)?;
+    match decode_from_slice::<SectionHeader, _>(&buffer[..read], CuWireFormat::CONFIG) {
+        Ok((header, _))
+            if header.magic == SECTION_MAGIC
+                && header.section_size > 0
+                && header.filled_size as usize + MAX_HEADER_SIZE
+                    <= header.section_size as usize =>
+        {
+            Ok(Some(header))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// How often a reader following a log checks for new sections.
+const FOLLOW_POLL_PERIOD: Duration = Duration::from_millis(100);
+
 impl UnifiedLoggerRead {
     pub fn new(base_file_path: &Path) -> io::Result<Self> {
-        let (file, mmap, prolog) = open_slab_index(base_file_path, 0)?;
+        let (file, prolog) = open_slab_index(base_file_path, 0)?;
 
         Ok(Self {
             base_file_path: base_file_path.to_path_buf(),
             current_file: file,
-            current_mmap_buffer: mmap,
             current_slab_index: 0,
             current_reading_position: prolog as usize,
+            current_section: 0..0,
+            page_size: prolog as usize,
+            follow: false,
+            salvage: false,
+            report: SalvageReport::default(),
+            salvage_reported: false,
         })
     }
 
     fn next_slab(&mut self) -> io::Result<()> {
-        self.current_slab_index += 1;
-        let (file, mmap, prolog) = open_slab_index(&self.base_file_path, self.current_slab_index)?;
+        let slab_index = next_slab_index(&self.base_file_path, self.current_slab_index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No next slab in the log"))?;
+        let (file, prolog) = open_slab_index(&self.base_file_path, slab_index)?;
+        self.current_slab_index = slab_index;
         self.current_file = file;
-        self.current_mmap_buffer = mmap;
         self.current_reading_position = prolog as usize;
         Ok(())
     }
 
-    pub fn read_next_section_type(
-        &mut self,
-        datalogtype: UnifiedLogType,
-    ) -> CuResult<Option<Vec<u8>>> {
-        // TODO: eventually implement a 0 copy of this too.
+    fn next_slab_exists(&self) -> bool {
+        next_slab_index(&self.base_file_path, self.current_slab_index).is_some()
+    }
+
+    /// If the writer closed the log after the current position.
+    fn is_log_closed(&self) -> io::Result<bool> {
+        let mut slab_index = self.current_slab_index;
+        let mut file = self.current_file.try_clone()?;
+        let mut position = self.current_reading_position;
         loop {
-            if self.current_reading_position >= self.current_mmap_buffer.len() {
-                self.next_slab().map_err(|e| {
-                    CuError::new_with_cause("Failed to read next slab, is the log complete?", e)
-                })?;
+            match read_section_header_at(&mut file, position)? {
+                Some(header) if header.entry_type == UnifiedLogType::LastEntry => return Ok(true),
+                Some(header) => position += header.section_size as usize,
+                None => {
+                    let Some(next_index) = next_slab_index(&self.base_file_path, slab_index) else {
+                        return Ok(false);
+                    };
+                    let Ok((next_file, prolog)) = open_slab_index(&self.base_file_path, next_index)
+                    else {
+                        return Ok(false);
+                    };
+                    slab_index = next_index;
+                    file = next_file;
+                    position = prolog as usize;
+                }
             }
+        }
+    }
 
-            let header_result = self.read_section_header();
-            if let Err(error) = header_result {
-                return Err(CuError::new_with_cause(
-                    "Could not read a sections header",
-                    error,
+    /// Moves to the next section of the given type, its content can then be read with
+    /// [UnifiedLoggerRead::read_section_chunk]. Returns the size of its content, None at the end
+    /// of the log.
+    /// The checksum of the section is verified first, a damaged section is an error unless the
+    /// reader salvages the log: it is skipped then, see [UnifiedLoggerRead::salvage_report].
+    pub fn seek_next_section_type(
+        &mut self,
+        datalogtype: UnifiedLogType,
+    ) -> CuResult<Option<usize>> {
+        let io_error = |e| CuError::new_with_cause("Could not read the log", e);
+        // A section being closed by the writer while it is read can look damaged once.
+        let mut retried = false;
+        loop {
+            let Some(header) =
+                read_section_header_at(&mut self.current_file, self.current_reading_position)
+                    .map_err(io_error)?
+            else {
+                // Nothing written here: this slab is over or the writer didn't get there yet.
+                // No section can be added to a slab once the next one exists, check it again then.
+                // A log being written has nothing to resync to.
+                if self.salvage && !self.follow && self.resync().map_err(io_error)? {
+                    continue;
+                }
+                if self.next_slab_exists() {
+                    if read_section_header_at(&mut self.current_file, self.current_reading_position)
+                        .map_err(io_error)?
+                        .is_none()
+                    {
+                        self.next_slab().map_err(io_error)?;
+                    }
+                    continue;
+                }
+                if self.follow {
+                    thread::sleep(FOLLOW_POLL_PERIOD);
+                    continue;
+                }
+                if self.salvage {
+                    self.report.truncated = true;
+                    self.end_salvage();
+                    return Ok(None);
+                }
+                return Err(CuError::from(
+                    "Failed to read next slab, is the log complete?",
                 ));
             };
-            let header = header_result.unwrap();
 
             // Reached the end of file
             if header.entry_type == UnifiedLogType::LastEntry {
+                if self.salvage {
+                    self.end_salvage();
+                }
                 return Ok(None);
             }
 
             // Found a section of the requested type
             if header.entry_type == datalogtype {
-                let result = Some(self.read_section_content(&header)?);
+                // The sections in flight are only filled in once closed by the writer.
+                if header.filled_size == 0
+                    && self.follow
+                    && !self.is_log_closed().map_err(io_error)?
+                {
+                    thread::sleep(FOLLOW_POLL_PERIOD);
+                    continue;
+                }
+                if header.filled_size == 0 && !self.salvage {
+                    eprintln!("Warning: read an empty section");
+                }
+                let start_of_data = self.current_reading_position + MAX_HEADER_SIZE;
+                let content = start_of_data..start_of_data + header.filled_size as usize;
+                let damage = match self.checksum(content.clone()) {
+                    Ok(checksum) if checksum == header.checksum => None,
+                    Ok(_) => Some("checksum mismatch"),
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Some("truncated"),
+                    Err(e) => return Err(io_error(e)),
+                };
+                if let Some(reason) = damage {
+                    if self.follow && !retried {
+                        retried = true;
+                        thread::sleep(FOLLOW_POLL_PERIOD);
+                        continue;
+                    }
+                    if !self.salvage {
+                        return Err(CuError::from(format!(
+                            "The section at offset {} of slab {} is damaged ({reason}), salvage the log to skip it.",
+                            self.current_reading_position, self.current_slab_index
+                        )));
+                    }
+                    self.skip_damaged(reason);
+                    self.current_reading_position += header.section_size as usize;
+                    retried = false;
+                    continue;
+                }
+                if header.filled_size > 0 {
+                    self.report.sections += 1;
+                }
+                self.current_section = content;
                 self.current_reading_position += header.section_size as usize;
-                return Ok(result);
+                return Ok(Some(header.filled_size as usize));
             }
 
             // Keep reading until we find the requested type
             self.current_reading_position += header.section_size as usize;
+            retried = false;
         }
     }
 
-    /// Reads the section from the section header pos.
-    pub fn read_section(&mut self) -> CuResult<Vec<u8>> {
-        let read_result = self.read_section_header();
-        if let Err(error) = read_result {
-            return Err(CuError::new_with_cause(
-                "Could not read a sections header",
-                error,
-            ));
-        };
+    /// The CRC32 of a range of the current slab.
+    fn checksum(&mut self, mut range: Range<usize>) -> io::Result<u32> {
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buffer = [0u8; 16 * 1024];
+        while !range.is_empty() {
+            let len = buffer.len().min(range.len());
+            let read = read_at(&mut self.current_file, range.start, &mut buffer[..len])?;
+            if read < len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The section is truncated",
+                ));
+            }
+            hasher.update(&buffer[..len]);
+            range.start += len;
+        }
+        Ok(hasher.finalize())
+    }
 
-        self.read_section_content(&read_result.unwrap())
+    /// Looks for the next valid section header of the current slab, the sections being aligned on
+    /// pages. Returns false if there is none: the slab is over.
+    fn resync(&mut self) -> io::Result<bool> {
+        let mut position = self.current_reading_position;
+        let length = self.current_file.metadata()?.len() as usize;
+        loop {
+            position = (position / self.page_size + 1) * self.page_size;
+            if position >= length {
+                return Ok(false);
+            }
+            if read_section_header_at(&mut self.current_file, position)?.is_some() {
+                self.skip_damaged("unreadable section header");
+                self.current_reading_position = position;
+                return Ok(true);
+            }
+        }
+    }
+
+    fn skip_damaged(&mut self, reason: &str) {
+        let damaged = DamagedSection {
+            slab: self.current_slab_index,
+            offset: self.current_reading_position,
+            reason: reason.to_string(),
+        };
+        eprintln!("Warning: skipping {damaged}");
+        self.report.damaged_sections.push(damaged);
     }
 
-    /// Reads the section content from the section header pos.
-    fn read_section_content(&mut self, header: &SectionHeader) -> CuResult<Vec<u8>> {
-        // TODO: we could optimize by asking the buffer to fill
-        if header.filled_size == 0 {
-            eprintln!("Warning: read an empty section");
+    fn end_salvage(&mut self) {
+        if !self.salvage_reported {
+            self.salvage_reported = true;
+            eprintln!("{}", self.report);
         }
-        let mut section = vec![0; header.filled_size as usize];
-        let start_of_data = self.current_reading_position + MAX_HEADER_SIZE;
-        section.copy_from_slice(
-            &self.current_mmap_buffer[start_of_data..start_of_data + header.filled_size as usize],
-        );
+    }
 
-        Ok(section)
+    /// What has been read and skipped so far when the reader salvages the log.
+    pub fn salvage_report(&self) -> &SalvageReport {
+        &self.report
     }
 
-    fn read_section_header(&mut self) -> CuResult<SectionHeader> {
-        let section_header: SectionHeader;
-        (section_header, _) = decode_from_slice(
-            &self.current_mmap_buffer[self.current_reading_position..],
-            standard(),
-        )
-        .expect("Failed to decode section header");
-        if section_header.magic != SECTION_MAGIC {
-            return Err("Invalid magic number in section header".into());
+    /// Reads the next bytes of the content of the current section, 0 at its end.
+    pub fn read_section_chunk(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let len = buffer.len().min(self.current_section.len());
+        let read = read_at(
+            &mut self.current_file,
+            self.current_section.start,
+            &mut buffer[..len],
+        )?;
+        if read < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The section is truncated",
+            ));
         }
-        Ok(section_header)
+        self.current_section.start += read;
+        Ok(read)
+    }
+
+    /// Reads the whole content of the next section of the given type, None at the end of the log.
+    pub fn read_next_section_type(
+        &mut self,
+        datalogtype: UnifiedLogType,
+    ) -> CuResult<Option<Vec<u8>>> {
+        let Some(size) = self.seek_next_section_type(datalogtype)? else {
+            return Ok(None);
+        };
+        let mut section = vec![0; size];
+        self.read_section_chunk(&mut section)
+            .map_err(|e| CuError::new_with_cause("Could not read a section", e))?;
+        Ok(Some(section))
     }
 }
 
+/// The size of the chunks the UnifiedLoggerIOReader reads the sections by.
+const IO_READER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A position in the stream of a [UnifiedLoggerIOReader], to read it again from there later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogPosition {
+    slab_index: usize,
+    next_section: usize,
+    /// What was left to read of the section, including what the reader had buffered.
+    section: Range<usize>,
+}
+
 /// This a convenience wrapper around the UnifiedLoggerRead to implement the Read trait.
+/// It streams the sections by chunks so it reads logs of any size in constant memory.
 pub struct UnifiedLoggerIOReader {
     logger: UnifiedLoggerRead,
     log_type: UnifiedLogType,
@@ -696,21 +1377,54 @@ impl UnifiedLoggerIOReader {
         Self {
             logger,
             log_type,
-            buffer: Vec::new(),
+            buffer: Vec::with_capacity(IO_READER_CHUNK_SIZE),
             buffer_pos: 0,
         }
     }
 
+    /// Where the next byte will be read from.
+    pub fn position(&self) -> LogPosition {
+        let buffered = self.buffer.len() - self.buffer_pos;
+        LogPosition {
+            slab_index: self.logger.current_slab_index,
+            next_section: self.logger.current_reading_position,
+            section: self.logger.current_section.start - buffered..self.logger.current_section.end,
+        }
+    }
+
+    /// Goes back, or forward, to a position given by this reader or another one of the same log.
+    pub fn seek(&mut self, position: &LogPosition) -> io::Result<()> {
+        if position.slab_index != self.logger.current_slab_index {
+            let (file, _) = open_slab_index(&self.logger.base_file_path, position.slab_index)?;
+            self.logger.current_file = file;
+            self.logger.current_slab_index = position.slab_index;
+        }
+        self.logger.current_reading_position = position.next_section;
+        self.logger.current_section = position.section.clone();
+        self.buffer.clear();
+        self.buffer_pos = 0;
+        Ok(())
+    }
+
     /// returns true if there is more data to read.
     fn fill_buffer(&mut self) -> io::Result<bool> {
-        match self.logger.read_next_section_type(self.log_type) {
-            Ok(Some(section)) => {
-                self.buffer = section;
-                self.buffer_pos = 0;
-                Ok(true)
+        self.buffer.resize(IO_READER_CHUNK_SIZE, 0);
+        self.buffer_pos = 0;
+        loop {
+            let read = self.logger.read_section_chunk(&mut self.buffer)?;
+            if read > 0 {
+                self.buffer.truncate(read);
+                return Ok(true);
+            }
+            match self.logger.seek_next_section_type(self.log_type) {
+                Ok(Some(_)) => continue,
+                Ok(None) => {
+                    // No more sections of this type
+                    self.buffer.clear();
+                    return Ok(false);
+                }
+                Err(e) => return Err(io::Error::other(e.to_string())),
             }
-            Ok(None) => Ok(false), // No more sections of this type
-            Err(e) => Err(io::Error::other(e.to_string())),
         }
     }
 }
@@ -722,11 +1436,6 @@ impl Read for UnifiedLoggerIOReader {
             return Ok(0);
         }
 
-        // If we still have no data after trying to fill the buffer, we're at EOF
-        if self.buffer_pos >= self.buffer.len() {
-            return Ok(0);
-        }
-
         // Copy as much as we can from the buffer to `buf`
         let len = std::cmp::min(buf.len(), self.buffer.len() - self.buffer_pos);
         buf[..len].copy_from_slice(&self.buffer[self.buffer_pos..self.buffer_pos + len]);
@@ -738,7 +1447,7 @@ impl Read for UnifiedLoggerIOReader {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bincode::decode_from_reader;
+    use bincode::{decode_from_reader, decode_from_std_read};
     use std::io::BufReader;
     use std::path::PathBuf;
     use tempfile::TempDir;
@@ -765,6 +1474,31 @@ mod tests {
         (Arc::new(Mutex::new(data_logger)), file_path)
     }
 
+    #[test]
+    fn test_headers_wire_format() {
+        // The bytes a log starts with, the same on every architecture.
+        let main_header = MainHeader {
+            magic: MAIN_MAGIC,
+            first_section_offset: 512,
+            page_size: 4096,
+        };
+        assert_eq!(
+            bincode::encode_to_vec(&main_header, CuWireFormat::CONFIG).unwrap(),
+            [0xB4, 0xA5, 0x50, 0xFE, 251, 0x00, 0x02, 251, 0x00, 0x10]
+        );
+        let section_header = SectionHeader {
+            entry_type: UnifiedLogType::CopperList,
+            section_size: 1024,
+            filled_size: 100,
+            checksum: 0xDEAD_BEEF,
+            ..Default::default()
+        };
+        assert_eq!(
+            bincode::encode_to_vec(&section_header, CuWireFormat::CONFIG).unwrap(),
+            [0xFA, 0x57, 2, 251, 0x00, 0x04, 100, 252, 0xEF, 0xBE, 0xAD, 0xDE]
+        );
+    }
+
     #[test]
     fn test_truncation_and_sections_creations() {
         let tmp_dir = TempDir::new().expect("could not create a tmp dir");
@@ -782,7 +1516,7 @@ mod tests {
             };
             logger.add_section(UnifiedLogType::StructuredLogLine, 1024);
             logger.add_section(UnifiedLogType::CopperList, 2048);
-            let used = logger.front_slab.used();
+            let used = logger.front_slab().used();
             assert!(used < 4 * page_size::get()); // ie. 3 headers, 1 page max per
                                                   // logger drops
 
@@ -801,6 +1535,22 @@ mod tests {
         //);
     }
 
+    #[test]
+    fn test_null_logger() {
+        let logger = Arc::new(Mutex::new(UnifiedLoggerWrite::null()));
+        {
+            let mut stream =
+                stream_write::<u32>(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
+            for i in 0..1000 {
+                stream.log(&i).unwrap();
+            }
+            stream.flush().unwrap();
+        }
+        let logger = logger.lock().unwrap();
+        assert!(logger.is_null());
+        assert_eq!(logger.stats(), (0, Vec::new(), 0));
+    }
+
     #[test]
     fn test_one_section_self_cleaning() {
         let tmp_dir = TempDir::new().expect("could not create a tmp dir");
@@ -809,28 +1559,18 @@ mod tests {
             let _stream =
                 stream_write::<()>(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
             assert_eq!(
-                logger
-                    .lock()
-                    .unwrap()
-                    .front_slab
-                    .sections_offsets_in_flight
-                    .len(),
+                logger.lock().unwrap().front_slab().sections_in_flight.len(),
                 1
             );
         }
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             0
         );
         let logger = logger.lock().unwrap();
         assert_eq!(
-            logger.front_slab.flushed_until_offset,
-            logger.front_slab.current_global_position
+            logger.front_slab().flushed_until_offset,
+            logger.front_slab().current_global_position
         );
     }
 
@@ -840,40 +1580,25 @@ mod tests {
         let (logger, _) = make_a_logger(&tmp_dir, LARGE_SLAB);
         let s1 = stream_write::<()>(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             1
         );
         let s2 = stream_write::<()>(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             2
         );
         drop(s2);
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             1
         );
         drop(s1);
         let lg = logger.lock().unwrap();
-        assert_eq!(lg.front_slab.sections_offsets_in_flight.len(), 0);
+        assert_eq!(lg.front_slab().sections_in_flight.len(), 0);
         assert_eq!(
-            lg.front_slab.flushed_until_offset,
-            lg.front_slab.current_global_position
+            lg.front_slab().flushed_until_offset,
+            lg.front_slab().current_global_position
         );
     }
 
@@ -883,40 +1608,25 @@ mod tests {
         let (logger, _) = make_a_logger(&tmp_dir, LARGE_SLAB);
         let s1 = stream_write::<()>(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             1
         );
         let s2 = stream_write::<()>(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             2
         );
         drop(s1);
         assert_eq!(
-            logger
-                .lock()
-                .unwrap()
-                .front_slab
-                .sections_offsets_in_flight
-                .len(),
+            logger.lock().unwrap().front_slab().sections_in_flight.len(),
             1
         );
         drop(s2);
         let lg = logger.lock().unwrap();
-        assert_eq!(lg.front_slab.sections_offsets_in_flight.len(), 0);
+        assert_eq!(lg.front_slab().sections_in_flight.len(), 0);
         assert_eq!(
-            lg.front_slab.flushed_until_offset,
-            lg.front_slab.current_global_position
+            lg.front_slab().flushed_until_offset,
+            lg.front_slab().current_global_position
         );
     }
 
@@ -945,14 +1655,115 @@ mod tests {
         let section = section.unwrap();
 
         let mut reader = BufReader::new(&section[..]);
-        let v1: u32 = decode_from_reader(&mut reader, standard()).unwrap();
-        let v2: u32 = decode_from_reader(&mut reader, standard()).unwrap();
-        let v3: u32 = decode_from_reader(&mut reader, standard()).unwrap();
+        let v1: u32 = decode_from_reader(&mut reader, CuWireFormat::CONFIG).unwrap();
+        let v2: u32 = decode_from_reader(&mut reader, CuWireFormat::CONFIG).unwrap();
+        let v3: u32 = decode_from_reader(&mut reader, CuWireFormat::CONFIG).unwrap();
         assert_eq!(v1, 1);
         assert_eq!(v2, 2);
         assert_eq!(v3, 3);
     }
 
+    fn write_to_memory(storage: &MemorySlabStorage) {
+        let UnifiedLogger::Write(logger) = UnifiedLoggerBuilder::new()
+            .write(true)
+            .create(true)
+            .storage(storage.clone())
+            .preallocated_size(SMALL_SLAB)
+            .build()
+            .expect("Failed to create logger")
+        else {
+            panic!("Failed to create logger")
+        };
+        let logger = Arc::new(Mutex::new(logger));
+        let mut stream = stream_write(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
+        for i in 0..20000u32 {
+            stream.log(&i).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        let storage = MemorySlabStorage::new(usize::MAX);
+        write_to_memory(&storage);
+        let slabs = storage.slabs();
+        assert!(slabs.len() > 2);
+
+        // Written as files, the slabs are a regular log.
+        let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+        let file_path = tmp_dir.path().join("test.bin");
+        for (index, slab) in slabs.iter().enumerate() {
+            std::fs::write(build_slab_path(&file_path, index), slab).unwrap();
+        }
+        let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+            .file_base_name(&file_path)
+            .build()
+            .expect("Failed to build logger")
+        else {
+            panic!("Failed to build logger");
+        };
+        let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::StructuredLogLine);
+        for i in 0..20000u32 {
+            let value: u32 = decode_from_std_read(&mut reader, CuWireFormat::CONFIG).unwrap();
+            assert_eq!(value, i);
+        }
+
+        // The ring only keeps the last slabs.
+        let ring = MemorySlabStorage::new(2);
+        write_to_memory(&ring);
+        assert_eq!(ring.slabs(), slabs[slabs.len() - 2..]);
+    }
+
+    #[test]
+    fn test_sync_before_crash() {
+        let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+        let (logger, f) = make_a_logger(&tmp_dir, LARGE_SLAB);
+        let mut stream = stream_write(logger.clone(), UnifiedLogType::StructuredLogLine, 4096);
+        for i in 0..100u32 {
+            stream.log(&i).unwrap();
+        }
+        {
+            let mut logger = logger.lock().unwrap();
+            logger.sync().unwrap();
+            logger.write_section(UnifiedLogType::Event, &42u64).unwrap();
+            logger
+                .write_section(UnifiedLogType::LastEntry, &())
+                .unwrap();
+        }
+        // The process dies: nothing is dropped.
+        std::mem::forget(stream);
+        std::mem::forget(logger);
+
+        let UnifiedLogger::Read(mut dl) = UnifiedLoggerBuilder::new()
+            .file_base_name(&f)
+            .build()
+            .expect("Failed to build logger")
+        else {
+            panic!("Failed to build logger");
+        };
+        let record = dl
+            .read_next_section_type(UnifiedLogType::Event)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decode_from_slice::<u64, _>(&record, CuWireFormat::CONFIG)
+                .unwrap()
+                .0,
+            42
+        );
+        let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+            .file_base_name(&f)
+            .build()
+            .expect("Failed to build logger")
+        else {
+            panic!("Failed to build logger");
+        };
+        let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::StructuredLogLine);
+        for i in 0..100u32 {
+            let value: u32 = decode_from_std_read(&mut reader, CuWireFormat::CONFIG).unwrap();
+            assert_eq!(value, i);
+        }
+    }
+
     /// Mimic a basic CopperList implementation.
 
     #[derive(Debug, Encode, Decode)]
@@ -1001,12 +1812,255 @@ mod tests {
         let section = section.unwrap();
 
         let mut reader = BufReader::new(&section[..]);
-        let cl0: CopperList<(u32, u32, u32)> = decode_from_reader(&mut reader, standard()).unwrap();
-        let cl1: CopperList<(u32, u32, u32)> = decode_from_reader(&mut reader, standard()).unwrap();
+        let cl0: CopperList<(u32, u32, u32)> =
+            decode_from_reader(&mut reader, CuWireFormat::CONFIG).unwrap();
+        let cl1: CopperList<(u32, u32, u32)> =
+            decode_from_reader(&mut reader, CuWireFormat::CONFIG).unwrap();
         assert_eq!(cl0.payload.1, 2);
         assert_eq!(cl1.payload.2, 6);
     }
 
+    #[test]
+    fn test_seek_back_to_a_position() {
+        let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+        let (logger, f) = make_a_logger(&tmp_dir, SMALL_SLAB);
+        {
+            // Small sections over several slabs.
+            let mut stream = stream_write(logger.clone(), UnifiedLogType::CopperList, 1024);
+            for i in 0..2000u32 {
+                stream.log(&i).unwrap();
+            }
+        }
+        drop(logger);
+
+        let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+            .file_base_name(&f)
+            .build()
+            .expect("Failed to build logger")
+        else {
+            panic!("Failed to build logger");
+        };
+        let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::CopperList);
+        let mut positions = Vec::new();
+        for i in 0..2000u32 {
+            positions.push(reader.position());
+            let value: u32 = decode_from_std_read(&mut reader, CuWireFormat::CONFIG).unwrap();
+            assert_eq!(value, i);
+        }
+        for i in [1500u32, 3, 0, 1999, 700] {
+            reader.seek(&positions[i as usize]).unwrap();
+            let value: u32 = decode_from_std_read(&mut reader, CuWireFormat::CONFIG).unwrap();
+            assert_eq!(value, i);
+        }
+        let value: u32 = decode_from_std_read(&mut reader, CuWireFormat::CONFIG).unwrap();
+        assert_eq!(value, 701);
+    }
+
+    #[test]
+    fn test_flush_closes_the_section() {
+        let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+        let (logger, f) = make_a_logger(&tmp_dir, LARGE_SLAB);
+        {
+            let mut stream = stream_write(logger.clone(), UnifiedLogType::Event, 1024);
+            stream.log(&1u32).unwrap();
+            stream.flush().unwrap();
+            stream.flush().unwrap(); // nothing to flush
+            stream.log(&2u32).unwrap();
+        }
+        drop(logger);
+
+        let UnifiedLogger::Read(mut dl) = UnifiedLoggerBuilder::new()
+            .file_base_name(&f)
+            .build()
+            .expect("Failed to build logger")
+        else {
+            panic!("Failed to build logger");
+        };
+        for expected in [1u32, 2u32] {
+            let section = dl
+                .read_next_section_type(UnifiedLogType::Event)
+                .expect("Failed to read section")
+                .expect("Missing section");
+            let (value, size): (u32, usize) =
+                decode_from_slice(&section, CuWireFormat::CONFIG).unwrap();
+            assert_eq!(value, expected);
+            assert_eq!(size, section.len());
+        }
+        assert!(dl
+            .read_next_section_type(UnifiedLogType::Event)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_follow_a_log_being_written() {
+        let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+        let (logger, f) = make_a_logger(&tmp_dir, SMALL_SLAB);
+        let mut stream = stream_write(logger.clone(), UnifiedLogType::CopperList, 1024);
+        stream.log(&0u32).unwrap();
+        stream.flush().unwrap();
+
+        let follower = thread::spawn(move || {
+            let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+                .file_base_name(&f)
+                .follow(true)
+                .build()
+                .expect("Failed to build logger")
+            else {
+                panic!("Failed to build logger");
+            };
+            let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::CopperList);
+            let mut values = Vec::new();
+            while let Ok(value) =
+                decode_from_std_read::<u32, _, _>(&mut reader, CuWireFormat::CONFIG)
+            {
+                values.push(value);
+            }
+            values
+        });
+
+        // Enough sections for a few slabs, the follower has to wait for them.
+        for i in 1..2000u32 {
+            stream.log(&i).unwrap();
+            if i % 100 == 0 {
+                stream.flush().unwrap();
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+        drop(stream);
+        drop(logger);
+
+        let values = follower.join().unwrap();
+        assert_eq!(values, (0..2000).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_salvage_damaged_and_truncated_log() {
+        let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+        let (logger, f) = make_a_logger(&tmp_dir, LARGE_SLAB);
+        {
+            // One section per page after the main header, then an empty one and the end of the log.
+            let mut stream = stream_write(logger.clone(), UnifiedLogType::CopperList, 1024);
+            for i in 1..=3u32 {
+                stream.log(&i).unwrap();
+                stream.flush().unwrap();
+            }
+        }
+        drop(logger);
+
+        let page_size = page_size::get();
+        let slab_path = build_slab_path(&f, 0);
+        let mut slab = std::fs::read(&slab_path).unwrap();
+        slab[2 * page_size + MAX_HEADER_SIZE] = 7;
+        std::fs::write(&slab_path, &slab).unwrap();
+
+        let read_all = |salvage: bool| {
+            let UnifiedLogger::Read(dl) = UnifiedLoggerBuilder::new()
+                .file_base_name(&f)
+                .salvage(salvage)
+                .build()
+                .expect("Failed to build logger")
+            else {
+                panic!("Failed to build logger");
+            };
+            let mut reader = UnifiedLoggerIOReader::new(dl, UnifiedLogType::CopperList);
+            let mut values = Vec::new();
+            while let Ok(value) =
+                decode_from_std_read::<u32, _, _>(&mut reader, CuWireFormat::CONFIG)
+            {
+                values.push(value);
+            }
+            (values, reader.logger.salvage_report().clone())
+        };
+
+        // The damaged section stops a normal reading.
+        let (values, _) = read_all(false);
+        assert_eq!(values, vec![1]);
+
+        let (values, report) = read_all(true);
+        assert_eq!(values, vec![1, 3]);
+        assert_eq!(report.sections, 2);
+        assert_eq!(report.damaged_sections.len(), 1);
+        assert_eq!(report.damaged_sections[0].offset, 2 * page_size);
+        assert!(!report.truncated);
+
+        // Power loss: the log is not closed and the header of the last section is garbage.
+        slab.truncate(5 * page_size);
+        slab[3 * page_size] = 0xFF;
+        std::fs::write(&slab_path, &slab).unwrap();
+        let (values, report) = read_all(true);
+        assert_eq!(values, vec![1]);
+        assert_eq!(report.damaged_sections.len(), 2);
+        assert_eq!(report.damaged_sections[1].offset, 3 * page_size);
+        assert!(report.truncated);
+    }
+
+    #[test]
+    fn test_full_log_policies() {
+        // Logs more than the capacity with the policy and reads back what the log kept.
+        let log_with = |on_full: OnLogFull| {
+            let tmp_dir = TempDir::new().expect("could not create a tmp dir");
+            let (logger, f) = make_a_logger(&tmp_dir, SMALL_SLAB);
+            logger
+                .lock()
+                .unwrap()
+                .set_capacity(4 * SMALL_SLAB, on_full)
+                .unwrap();
+            {
+                let mut stream =
+                    stream_write(logger.clone(), UnifiedLogType::StructuredLogLine, 1024);
+                for i in 0..100_000u32 {
+                    stream.log(&i).unwrap();
+                }
+            }
+            let (capacity, events) = {
+                let mut logger = logger.lock().unwrap();
+                (logger.capacity(), logger.take_full_events())
+            };
+            assert!(capacity.remaining().unwrap() < SMALL_SLAB);
+            drop(logger);
+
+            let UnifiedLogger::Read(mut dl) = UnifiedLoggerBuilder::new()
+                .file_base_name(&f)
+                .build()
+                .expect("Failed to build logger")
+            else {
+                panic!("Failed to build logger");
+            };
+            let mut values = Vec::new();
+            while let Some(section) = dl
+                .read_next_section_type(UnifiedLogType::StructuredLogLine)
+                .unwrap()
+            {
+                let mut reader = BufReader::new(&section[..]);
+                while let Ok(value) =
+                    decode_from_reader::<u32, _, _>(&mut reader, CuWireFormat::CONFIG)
+                {
+                    values.push(value);
+                }
+            }
+            (capacity, events, values)
+        };
+
+        // The beginning of the run is kept.
+        let (capacity, events, values) = log_with(OnLogFull::Stop);
+        assert!(capacity.stopped);
+        assert_eq!(events, vec![LogFullEvent::Stopped]);
+        assert!(!values.is_empty() && values.len() < 100_000);
+        assert!(values.iter().enumerate().all(|(i, v)| *v == i as u32));
+
+        // The first slab and the end of the run are kept.
+        let (capacity, events, values) = log_with(OnLogFull::Rotate);
+        assert!(!capacity.stopped);
+        assert!(events.contains(&LogFullEvent::Rotated(1)));
+        assert!(events
+            .iter()
+            .all(|event| matches!(event, LogFullEvent::Rotated(_))));
+        assert_eq!(values[0], 0);
+        assert_eq!(values.last(), Some(&99_999));
+        assert!(values.len() < 100_000);
+    }
+
     #[test]
     fn test_multi_slab_end2end() {
         let tmp_dir = TempDir::new().expect("could not create a tmp dir");
@@ -1046,7 +2100,7 @@ mod tests {
             let mut reader = BufReader::new(&section[..]);
             loop {
                 let maybe_cl: Result<CopperList<(u32, u32, u32)>, _> =
-                    decode_from_reader(&mut reader, standard());
+                    decode_from_reader(&mut reader, CuWireFormat::CONFIG);
                 if maybe_cl.is_ok() {
                     total_readback += 1;
                 } else {
@@ -0,0 +1,202 @@
+//! The storage of the slabs of the unified log.
+//!
+//! The logger writes its sections in large preallocated slabs of memory, the storage provides them
+//! and persists what has been written: by default memory mapped files, see [FileSlabStorage].
+//! Other backends plug in by implementing [SlabStorage], for example an in-memory ring for the
+//! tests ([MemorySlabStorage]), a multipart upload of the slabs to an object store, or a writer
+//! batching the flushes for the flash of an embedded board.
+//!
+//! The slabs are written from their start and their sections are closed in any order: a slab is
+//! flushed by increasing ranges once all the sections before their end have been closed, then closed
+//! once no more section will be added to it.
+
+use crate::build_slab_path;
+use memmap2::MmapMut;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A backend creating the slabs of the unified log.
+pub trait SlabStorage: Send {
+    /// Creates the slab `index` of `size` bytes, the slabs are created in order from 0.
+    fn create_slab(&mut self, index: usize, size: usize) -> io::Result<Box<dyn LogSlab>>;
+
+    /// Removes the closed slab `index` to make room for the next ones, see
+    /// [crate::OnLogFull::Rotate].
+    fn remove_slab(&mut self, _index: usize) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "This storage cannot remove its slabs",
+        ))
+    }
+
+    /// The closed slab `index` has been removed by someone else, an uploader for example. A full
+    /// log waits for it with [crate::OnLogFull::Block].
+    fn is_slab_removed(&self, _index: usize) -> bool {
+        false
+    }
+}
+
+/// A slab of the unified log, being written.
+pub trait LogSlab: Send {
+    /// The memory of the slab. It must not move until the slab is dropped, the sections being
+    /// written point into it.
+    fn buffer(&mut self) -> &mut [u8];
+
+    /// The range of the buffer has been written, it can be persisted.
+    fn flush(&mut self, range: Range<usize>) -> io::Result<()>;
+
+    /// The slab is complete with `used` bytes from its start, nothing will be written to it anymore.
+    /// This is called once before it is dropped.
+    fn close(&mut self, used: usize) -> io::Result<()>;
+}
+
+/// The default storage: the slabs are memory mapped files, "something/toto.copper" giving
+/// "something/toto_0.copper", "something/toto_1.copper" etc.
+/// The pages are flushed asynchronously by the OS and the files are trimmed to what has been written.
+pub struct FileSlabStorage {
+    base_file_path: PathBuf,
+}
+
+impl FileSlabStorage {
+    pub fn new(base_file_path: &Path) -> Self {
+        Self {
+            base_file_path: base_file_path.to_path_buf(),
+        }
+    }
+
+    /// The file of the slab `index`.
+    pub fn slab_path(&self, index: usize) -> PathBuf {
+        build_slab_path(&self.base_file_path, index)
+    }
+}
+
+impl SlabStorage for FileSlabStorage {
+    fn create_slab(&mut self, index: usize, size: usize) -> io::Result<Box<dyn LogSlab>> {
+        let file_path = self.slab_path(index);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&file_path)
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Failed to open file {}: {e}", file_path.display()),
+                )
+            })?;
+        file.set_len(size as u64)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Box::new(FileSlab {
+            file,
+            mmap: Some(mmap),
+        }))
+    }
+
+    fn remove_slab(&mut self, index: usize) -> io::Result<()> {
+        std::fs::remove_file(self.slab_path(index))
+    }
+
+    fn is_slab_removed(&self, index: usize) -> bool {
+        !self.slab_path(index).exists()
+    }
+}
+
+struct FileSlab {
+    file: File,
+    /// Unmapped when the slab is closed, before the file is trimmed.
+    mmap: Option<MmapMut>,
+}
+
+impl LogSlab for FileSlab {
+    fn buffer(&mut self) -> &mut [u8] {
+        self.mmap.as_mut().expect("The slab is closed")
+    }
+
+    fn flush(&mut self, range: Range<usize>) -> io::Result<()> {
+        match &self.mmap {
+            Some(mmap) => mmap.flush_async_range(range.start, range.len()),
+            None => Ok(()),
+        }
+    }
+
+    fn close(&mut self, used: usize) -> io::Result<()> {
+        self.mmap = None;
+        self.file.set_len(used as u64)
+    }
+}
+
+/// A storage keeping the slabs in memory, the last `max_slabs` closed ones are kept and can be read
+/// back with [MemorySlabStorage::slabs]. This is for the tests, and for keeping the end of a run in
+/// memory without writing to the disk.
+#[derive(Clone)]
+pub struct MemorySlabStorage {
+    closed_slabs: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    max_slabs: usize,
+}
+
+impl MemorySlabStorage {
+    pub fn new(max_slabs: usize) -> Self {
+        Self {
+            closed_slabs: Arc::new(Mutex::new(VecDeque::new())),
+            max_slabs,
+        }
+    }
+
+    /// The closed slabs kept, oldest first, trimmed to what has been written. Keep a clone of the
+    /// storage given to the logger to get them, all of them are closed once the logger is dropped.
+    pub fn slabs(&self) -> Vec<Vec<u8>> {
+        self.closed_slabs.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl SlabStorage for MemorySlabStorage {
+    fn create_slab(&mut self, _index: usize, size: usize) -> io::Result<Box<dyn LogSlab>> {
+        Ok(Box::new(MemorySlab {
+            buffer: vec![0u8; size],
+            storage: self.clone(),
+        }))
+    }
+}
+
+struct MemorySlab {
+    buffer: Vec<u8>,
+    storage: MemorySlabStorage,
+}
+
+impl LogSlab for MemorySlab {
+    fn buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn flush(&mut self, _range: Range<usize>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn close(&mut self, used: usize) -> io::Result<()> {
+        let mut slab = std::mem::take(&mut self.buffer);
+        slab.truncate(used);
+        let mut closed_slabs = self.storage.closed_slabs.lock().unwrap();
+        closed_slabs.push_back(slab);
+        while closed_slabs.len() > self.storage.max_slabs {
+            closed_slabs.pop_front();
+        }
+        Ok(())
+    }
+}
+
+/// The storage of the null logger, it never creates a slab.
+pub(crate) struct NoSlabStorage;
+
+impl SlabStorage for NoSlabStorage {
+    fn create_slab(&mut self, _index: usize, _size: usize) -> io::Result<Box<dyn LogSlab>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The null logger has no slab",
+        ))
+    }
+}
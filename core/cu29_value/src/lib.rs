@@ -11,9 +11,11 @@ use std::hash::{Hash, Hasher};
 mod bdec;
 mod benc;
 mod de;
+mod pool;
 mod ser;
 
 pub use de::*;
+pub use pool::recycle;
 pub use ser::*;
 
 #[derive(Clone, Debug)]
@@ -326,6 +328,37 @@ mod tests {
         assert_eq!(expected, value);
     }
 
+    #[test]
+    fn test_recycled_buffers() {
+        let value = to_value(("hello", 1u8)).unwrap();
+        let Value::Seq(values) = &value else {
+            panic!("Expected a sequence, got {value:?}");
+        };
+        let Value::String(hello) = &values[0] else {
+            panic!("Expected a string, got {:?}", values[0]);
+        };
+        let buffer = hello.as_ptr();
+        recycle(value);
+
+        // The next string of this thread reuses the buffer, cleared.
+        let Value::String(bye) = to_value("bye").unwrap() else {
+            panic!("Expected a string");
+        };
+        assert_eq!(bye, "bye");
+        assert_eq!(bye.as_ptr(), buffer);
+
+        let bytes = crate::pool::pooled_bytes(b"hi");
+        let buffer = bytes.as_ptr();
+        recycle(Value::Bytes(bytes));
+        let bytes = crate::pool::pooled_bytes(b"ho");
+        assert_eq!(bytes, b"ho");
+        assert_eq!(bytes.as_ptr(), buffer);
+
+        // The big buffers are not kept.
+        recycle(Value::String("x".repeat(1000)));
+        assert_eq!(to_value("small").unwrap(), Value::String("small".into()));
+    }
+
     #[test]
     fn deserialize_into_enum() {
         #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -0,0 +1,78 @@
+//! Buffers of the strings and bytes of the values, reused on each thread so the structured logging
+//! of a string in the hot loop does not allocate once the loop runs.
+//! The serializer takes its buffers from here and the logger gives them back with [recycle] once
+//! an entry is written.
+
+use crate::Value;
+use std::cell::RefCell;
+
+/// Buffers kept per thread and per kind at most.
+const MAX_POOLED_BUFFERS: usize = 32;
+/// Bigger buffers are dropped instead of being kept for the small strings.
+const MAX_POOLED_CAPACITY: usize = 256;
+
+thread_local! {
+    static STRINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static BYTES: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A copy of the string, in a recycled buffer if there is one.
+pub(crate) fn pooled_string(v: &str) -> String {
+    let buffer = STRINGS
+        .try_with(|pool| pool.borrow_mut().pop())
+        .ok()
+        .flatten();
+    match buffer {
+        Some(mut buffer) => {
+            buffer.push_str(v);
+            buffer
+        }
+        None => v.to_string(),
+    }
+}
+
+/// A copy of the bytes, in a recycled buffer if there is one.
+pub(crate) fn pooled_bytes(v: &[u8]) -> Vec<u8> {
+    let buffer = BYTES
+        .try_with(|pool| pool.borrow_mut().pop())
+        .ok()
+        .flatten();
+    match buffer {
+        Some(mut buffer) => {
+            buffer.extend_from_slice(v);
+            buffer
+        }
+        None => v.to_vec(),
+    }
+}
+
+/// Gives the buffers of a value that is not needed anymore back to the pool of this thread.
+pub fn recycle(value: Value) {
+    match value {
+        Value::String(mut s) if s.capacity() <= MAX_POOLED_CAPACITY => {
+            s.clear();
+            let _ = STRINGS.try_with(|pool| {
+                let mut pool = pool.borrow_mut();
+                if pool.len() < MAX_POOLED_BUFFERS {
+                    pool.push(s);
+                }
+            });
+        }
+        Value::Bytes(mut b) if b.capacity() <= MAX_POOLED_CAPACITY => {
+            b.clear();
+            let _ = BYTES.try_with(|pool| {
+                let mut pool = pool.borrow_mut();
+                if pool.len() < MAX_POOLED_BUFFERS {
+                    pool.push(b);
+                }
+            });
+        }
+        Value::Option(Some(v)) | Value::Newtype(v) => recycle(*v),
+        Value::Seq(values) => values.into_iter().for_each(recycle),
+        Value::Map(map) => map.into_iter().for_each(|(k, v)| {
+            recycle(k);
+            recycle(v);
+        }),
+        _ => {}
+    }
+}
@@ -1,3 +1,4 @@
+use crate::pool::{pooled_bytes, pooled_string};
 use crate::Value;
 use cu29_clock::CuTime;
 use serde::ser;
@@ -136,11 +137,11 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(Value::String(v.to_string()))
+        Ok(Value::String(pooled_string(v)))
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        Ok(Value::Bytes(v.to_vec()))
+        Ok(Value::Bytes(pooled_bytes(v)))
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
@@ -6,7 +6,7 @@ use cu29::clock::RobotClock;
 use cu29::config::ComponentConfig;
 use cu29::cutask::{CuMsg, CuSrcTask, CuTask, Freezable};
 use cu29::{input_msg, output_msg, CuResult};
-use cu_rp_gpio::RPGpioPayload;
+use cu_led::{LedPattern, LedPayload};
 
 #[derive(Default)]
 pub struct CaterpillarSource {
@@ -25,7 +25,7 @@ impl Freezable for CaterpillarSource {
 }
 
 impl<'cl> CuSrcTask<'cl> for CaterpillarSource {
-    type Output = output_msg!('cl, RPGpioPayload);
+    type Output = output_msg!('cl, LedPayload);
 
     fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
     where
@@ -37,7 +37,7 @@ impl<'cl> CuSrcTask<'cl> for CaterpillarSource {
     fn process(&mut self, _clock: &RobotClock, output: Self::Output) -> CuResult<()> {
         // forward the state to the next task
         self.state = !self.state;
-        output.set_payload(RPGpioPayload { on: self.state });
+        output.set_payload(LedPayload::from(self.state));
         output.metadata.set_status(self.state);
         Ok(())
     }
@@ -48,8 +48,8 @@ pub struct CaterpillarTask {}
 impl Freezable for CaterpillarTask {}
 
 impl<'cl> CuTask<'cl> for CaterpillarTask {
-    type Input = input_msg!('cl, RPGpioPayload);
-    type Output = output_msg!('cl, RPGpioPayload);
+    type Input = input_msg!('cl, LedPayload);
+    type Output = output_msg!('cl, LedPayload);
 
     fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
     where
@@ -67,7 +67,9 @@ impl<'cl> CuTask<'cl> for CaterpillarTask {
         // forward the state to the next task
         let incoming = *input.payload().unwrap();
         output.set_payload(incoming);
-        output.metadata.set_status(incoming.on);
+        output
+            .metadata
+            .set_status(incoming.pattern == LedPattern::On);
         Ok(())
     }
 }
@@ -48,7 +48,7 @@ fn main() {
                 .get_node_mut(node_index.index() as NodeId, None)
                 .unwrap();
             if node.get_id() == "dst" {
-                node.set_param("pin", 42);
+                node.set_param("line", 42);
             }
         });
 
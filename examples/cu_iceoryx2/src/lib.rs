@@ -1,7 +1,7 @@
 use cu_iceoryx2_sink::IceoryxSink;
 use cu_iceoryx2_src::IceoryxSrc;
-use cu_rp_gpio::RPGpioPayload;
+use cu_led::LedPayload;
 
 // Type aliases for the config.
-pub type MyIceoryxSink = IceoryxSink<RPGpioPayload>;
-pub type MyIceoryxSrc = IceoryxSrc<RPGpioPayload>;
+pub type MyIceoryxSink = IceoryxSink<LedPayload>;
+pub type MyIceoryxSrc = IceoryxSrc<LedPayload>;
@@ -0,0 +1,9 @@
+pub mod tasks;
+
+use cu29::prelude::*;
+
+gen_cumsgs!("copperconfig.ron");
+
+fn main() {
+    copper_plot::run::<CuMsgs>().expect("Failed to run the plotter");
+}
@@ -0,0 +1,5 @@
+//! Library side of `cargo copper`: the component registry can be used from the build.rs of an
+//! application to check its configuration.
+
+pub mod registry;
+pub mod stubs;
@@ -0,0 +1,208 @@
+mod scaffold;
+
+use cargo_copper::registry::Registry;
+use cargo_copper::stubs::{add_to_module, by_module, task_stubs};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use cu29_runtime::config::{read_configuration, CuConfig};
+use cu29_traits::{CuError, CuResult};
+use scaffold::{new_application, new_component, ComponentKind, CopperSource};
+use std::path::{Path, PathBuf};
+
+/// Cargo calls its subcommands with their name as the first argument: `cargo-copper copper new ...`.
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum CargoCli {
+    Copper(CopperCli),
+}
+
+#[derive(Args)]
+#[command(author, version, about)]
+struct CopperCli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Source {
+    /// The latest release from crates.io
+    CratesIo,
+    /// The master branch of the public repository
+    Git,
+    /// A local clone, see --copper-root
+    Local,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Creates a new application or component crate
+    New {
+        /// Directory of the new crate, its name is used as the project name
+        path: PathBuf,
+        /// Scaffolds a component crate with a sample task of this kind instead of an application
+        #[arg(long, value_enum)]
+        component: Option<ComponentKind>,
+        /// Where to get Copper from
+        #[arg(long, value_enum, default_value_t = Source::CratesIo)]
+        copper_source: Source,
+        /// Path of the local copper-rs clone, relative to the new crate
+        #[arg(long, default_value = "../..")]
+        copper_root: String,
+    },
+    /// Lists the Copper components available in the dependency tree
+    Components {
+        /// Path to the Cargo.toml of the application, the current directory by default
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
+    },
+    /// Checks that the node types of a configuration exist in the dependency tree
+    Check {
+        /// The RON configuration to check
+        #[arg(long, default_value = "copperconfig.ron")]
+        config: PathBuf,
+        /// Path to the Cargo.toml of the application, the current directory by default
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
+        /// Writes compilable stubs of the task types of the configuration the application does
+        /// not declare yet in its modules, `tasks::Camera` in src/tasks.rs, before checking
+        #[arg(long)]
+        emit_stubs: bool,
+    },
+}
+
+fn discover(manifest_path: Option<PathBuf>) -> Registry {
+    Registry::discover(manifest_path.as_deref(), false).unwrap_or_else(|e| {
+        eprintln!("Could not list the Copper components: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn print_components(registry: &Registry) {
+    if registry.crates.is_empty() {
+        println!("No Copper component found in the dependency tree.");
+    }
+    for copper_crate in &registry.crates {
+        println!(
+            "{} {} ({})",
+            copper_crate.package, copper_crate.version, copper_crate.plugin_type
+        );
+        for component in &copper_crate.components {
+            println!("    {} [{}]", component.type_path, component.plugin_type);
+            if !component.input.is_empty() {
+                println!("        input:  {}", component.input.join(", "));
+            }
+            if !component.output.is_empty() {
+                println!("        output: {}", component.output.join(", "));
+            }
+            for (key, field) in &component.config {
+                let required = match &field.default {
+                    _ if field.required => ", required".to_string(),
+                    Some(default) => format!(", default {default}"),
+                    None => String::new(),
+                };
+                match &field.doc {
+                    Some(doc) => println!(
+                        "        config \"{key}\": {}{required} - {doc}",
+                        field.value_type
+                    ),
+                    None => println!("        config \"{key}\": {}{required}", field.value_type),
+                }
+            }
+        }
+    }
+}
+
+/// Writes the stubs of the configuration in the modules of the application.
+fn emit_stubs(
+    registry: &Registry,
+    cuconfig: &CuConfig,
+    manifest_path: Option<&Path>,
+) -> CuResult<()> {
+    let src = manifest_path
+        .and_then(Path::parent)
+        .unwrap_or(Path::new("."))
+        .join("src");
+    let stubs = task_stubs(cuconfig, |type_path| registry.is_from_dependency(type_path))?;
+    for (module, stubs) in by_module(&stubs) {
+        let path = src.join(format!("{module}.rs"));
+        let existing = if path.exists() {
+            Some(std::fs::read_to_string(&path).map_err(|e| {
+                CuError::new_with_cause(&format!("Could not read {}", path.display()), e)
+            })?)
+        } else {
+            None
+        };
+        let (content, added) = add_to_module(existing.as_deref(), &stubs);
+        if added.is_empty() {
+            continue;
+        }
+        std::fs::write(&path, content).map_err(|e| {
+            CuError::new_with_cause(&format!("Could not write {}", path.display()), e)
+        })?;
+        match existing {
+            Some(_) => println!("    added {} to {}", added.join(", "), path.display()),
+            None => println!(
+                "    created {} with {}, declare it with `mod {module};`",
+                path.display(),
+                added.join(", ")
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let CargoCli::Copper(cli) = CargoCli::parse();
+    match cli.command {
+        Command::New {
+            path,
+            component,
+            copper_source,
+            copper_root,
+        } => {
+            let source = match copper_source {
+                Source::CratesIo => CopperSource::CratesIo,
+                Source::Git => CopperSource::Git,
+                Source::Local => CopperSource::Local(copper_root),
+            };
+            let result = match component {
+                Some(kind) => new_component(&path, kind, &source),
+                None => new_application(&path, &source),
+            };
+            match result {
+                Ok(files) => {
+                    for file in files {
+                        println!("    created {}", file.display());
+                    }
+                    if component.is_none() {
+                        println!("Run it with: cd {} && cargo run", path.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Could not create {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Components { manifest_path } => print_components(&discover(manifest_path)),
+        Command::Check {
+            config,
+            manifest_path,
+            emit_stubs: stubs,
+        } => {
+            let registry = discover(manifest_path.clone());
+            let result = read_configuration(&config.to_string_lossy()).and_then(|cuconfig| {
+                if stubs {
+                    emit_stubs(&registry, &cuconfig, manifest_path.as_deref())?;
+                }
+                registry.check_config(&cuconfig)
+            });
+            match result {
+                Ok(()) => println!("{} is valid.", config.display()),
+                Err(e) => {
+                    eprintln!("{}: {e}", config.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,670 @@
+//! Discovery of the Copper components available to an application.
+//!
+//! A crate advertises its components in its Cargo.toml:
+//!
+//! ```toml
+//! [package.metadata.copper]
+//! plugin_type = "task"  # source, task, sink, monitor or payloads
+//!
+//! [[package.metadata.copper.components]]
+//! type = "cu_pid::GenericPIDTask"
+//! input = ["I"]
+//! output = ["cu_pid::PIDControlOutputPayload"]
+//! config.kp = { type = "f64", required = true, doc = "Proportional gain" }
+//! config.ki = { type = "f64", default = "0.0" }
+//! ```
+//!
+//! `plugin_type` can be overridden per component if a crate mixes several kinds.
+//! The registry is built from `cargo metadata` so it sees the whole dependency tree of the
+//! application, and it can check a RON configuration against it before the runtime is generated.
+//! When a component declares its config keys, any other key in the configuration is reported as a
+//! probable typo.
+
+use cu29_runtime::config::{
+    closest_key, read_configuration, ComponentConfig, ConfigGraphs, CuConfig, Value,
+};
+use cu29_traits::{CuError, CuResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginType {
+    Source,
+    Task,
+    Sink,
+    Monitor,
+    /// Only message definitions, nothing to instantiate.
+    Payloads,
+}
+
+impl Display for PluginType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PluginType::Source => "source",
+            PluginType::Task => "task",
+            PluginType::Sink => "sink",
+            PluginType::Monitor => "monitor",
+            PluginType::Payloads => "payloads",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A config key of a component.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConfigField {
+    /// Rust type the component reads the value as (bool, String, u32, f64...).
+    #[serde(rename = "type")]
+    pub value_type: String,
+    #[serde(default)]
+    pub required: bool,
+    /// The value used when the key is absent, for the documentation.
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub doc: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ComponentRepresentation {
+    #[serde(rename = "type")]
+    type_path: String,
+    plugin_type: Option<PluginType>,
+    #[serde(default)]
+    input: Vec<String>,
+    #[serde(default)]
+    output: Vec<String>,
+    #[serde(default)]
+    config: BTreeMap<String, ConfigField>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct CopperMetadata {
+    plugin_type: PluginType,
+    #[serde(default)]
+    components: Vec<ComponentRepresentation>,
+}
+
+/// A source, task, sink or monitor a crate exports.
+#[derive(Clone, Debug)]
+pub struct ComponentInfo {
+    /// Path of the type as written in a configuration, without its generic parameters.
+    pub type_path: String,
+    pub plugin_type: PluginType,
+    pub input: Vec<String>,
+    pub output: Vec<String>,
+    pub config: BTreeMap<String, ConfigField>,
+}
+
+/// A crate of the dependency tree with Copper metadata.
+#[derive(Clone, Debug)]
+pub struct CopperCrate {
+    pub package: String,
+    pub version: String,
+    pub crate_name: String,
+    pub plugin_type: PluginType,
+    pub components: Vec<ComponentInfo>,
+}
+
+// The subset of the `cargo metadata` output the registry needs.
+#[derive(Deserialize)]
+struct CargoMetadata {
+    packages: Vec<CargoPackage>,
+    resolve: Option<CargoResolve>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    id: String,
+    name: String,
+    version: String,
+    manifest_path: PathBuf,
+    targets: Vec<CargoTarget>,
+    metadata: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct CargoTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CargoResolve {
+    root: Option<String>,
+    nodes: Vec<CargoNode>,
+}
+
+#[derive(Deserialize)]
+struct CargoNode {
+    id: String,
+    deps: Vec<CargoDep>,
+}
+
+#[derive(Deserialize)]
+struct CargoDep {
+    /// Name of the crate as seen from the dependent crate, renames included.
+    name: String,
+    pkg: String,
+}
+
+impl CargoPackage {
+    fn crate_name(&self) -> String {
+        self.targets
+            .iter()
+            .find(|target| {
+                target.kind.iter().any(|kind| {
+                    matches!(
+                        kind.as_str(),
+                        "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro"
+                    )
+                })
+            })
+            .map(|target| target.name.replace('-', "_"))
+            .unwrap_or_else(|| self.name.replace('-', "_"))
+    }
+
+    fn copper_crate(&self) -> CuResult<Option<CopperCrate>> {
+        let Some(copper) = self.metadata.as_ref().and_then(|m| m.get("copper")) else {
+            return Ok(None);
+        };
+        let copper: CopperMetadata = serde_json::from_value(copper.clone()).map_err(|e| {
+            CuError::new_with_cause(
+                &format!("Invalid [package.metadata.copper] in {}", self.name),
+                e,
+            )
+        })?;
+        let components = copper
+            .components
+            .into_iter()
+            .map(|component| ComponentInfo {
+                type_path: base_type(&component.type_path).to_string(),
+                plugin_type: component.plugin_type.unwrap_or(copper.plugin_type),
+                input: component.input,
+                output: component.output,
+                config: component.config,
+            })
+            .collect();
+        Ok(Some(CopperCrate {
+            package: self.name.clone(),
+            version: self.version.clone(),
+            crate_name: self.crate_name(),
+            plugin_type: copper.plugin_type,
+            components,
+        }))
+    }
+}
+
+/// Strips the generic parameters: `cu_pid::GenericPIDTask<f32>` -> `cu_pid::GenericPIDTask`.
+fn base_type(type_path: &str) -> &str {
+    type_path
+        .split('<')
+        .next()
+        .unwrap_or(type_path)
+        .trim()
+        .trim_start_matches("::")
+}
+
+/// Splits `a::b::C` into `a` and `b::C`.
+fn split_first_segment(type_path: &str) -> Option<(&str, &str)> {
+    type_path.split_once("::")
+}
+
+fn value_matches(value_type: &str, value: &Value) -> bool {
+    use cu29_runtime::config::accepts as is;
+    match value_type {
+        "bool" => is::<bool>(value),
+        "String" | "string" | "&str" => is::<String>(value),
+        "u8" => is::<u8>(value),
+        "u16" => is::<u16>(value),
+        "u32" => is::<u32>(value),
+        "u64" => is::<u64>(value),
+        "usize" => is::<usize>(value),
+        "i8" => is::<i8>(value),
+        "i16" => is::<i16>(value),
+        "i32" => is::<i32>(value),
+        "i64" => is::<i64>(value),
+        "isize" => is::<isize>(value),
+        "f32" | "f64" => is::<f64>(value),
+        // Anything richer is left to the component.
+        _ => true,
+    }
+}
+
+/// The components available to an application.
+#[derive(Debug, Default)]
+pub struct Registry {
+    /// Crates of the dependency tree carrying Copper metadata.
+    pub crates: Vec<CopperCrate>,
+    /// Crate names the application can refer to, to the index of their Copper crate if any.
+    dependencies: HashMap<String, Option<usize>>,
+    /// Name and directory of the application, if the registry was built for a package.
+    root: Option<(String, PathBuf)>,
+}
+
+impl Registry {
+    /// Runs `cargo metadata` on the given manifest, or on the current directory.
+    pub fn discover(manifest_path: Option<&Path>, offline: bool) -> CuResult<Self> {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let mut command = Command::new(cargo);
+        command.args(["metadata", "--format-version", "1"]);
+        if let Some(manifest_path) = manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+        if offline {
+            command.arg("--offline");
+        }
+        let output = command
+            .output()
+            .map_err(|e| CuError::new_with_cause("Could not run cargo metadata", e))?;
+        if !output.status.success() {
+            return Err(CuError::from(format!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        let json = String::from_utf8_lossy(&output.stdout);
+        Self::from_metadata(&json)
+    }
+
+    /// Builds the registry from the JSON output of `cargo metadata --format-version 1`.
+    pub fn from_metadata(json: &str) -> CuResult<Self> {
+        let metadata: CargoMetadata = serde_json::from_str(json)
+            .map_err(|e| CuError::new_with_cause("Could not parse the cargo metadata", e))?;
+        let packages: HashMap<&str, &CargoPackage> = metadata
+            .packages
+            .iter()
+            .map(|package| (package.id.as_str(), package))
+            .collect();
+
+        let root = metadata
+            .resolve
+            .as_ref()
+            .and_then(|resolve| resolve.root.as_deref())
+            .and_then(|root| packages.get(root).copied());
+        let nodes: HashMap<&str, &CargoNode> = metadata
+            .resolve
+            .iter()
+            .flat_map(|resolve| resolve.nodes.iter())
+            .map(|node| (node.id.as_str(), node))
+            .collect();
+
+        // Only list what the application actually depends on, everything for a virtual workspace.
+        let in_tree: Vec<&CargoPackage> = match root {
+            Some(root) => {
+                let mut seen = HashSet::new();
+                let mut stack = vec![root.id.as_str()];
+                while let Some(id) = stack.pop() {
+                    if !seen.insert(id) {
+                        continue;
+                    }
+                    if let Some(node) = nodes.get(id) {
+                        stack.extend(node.deps.iter().map(|dep| dep.pkg.as_str()));
+                    }
+                }
+                metadata
+                    .packages
+                    .iter()
+                    .filter(|package| seen.contains(package.id.as_str()))
+                    .collect()
+            }
+            None => metadata.packages.iter().collect(),
+        };
+
+        let mut registry = Registry::default();
+        for package in in_tree {
+            if let Some(copper_crate) = package.copper_crate()? {
+                registry.crates.push(copper_crate);
+            }
+        }
+        registry.crates.sort_by(|a, b| a.package.cmp(&b.package));
+        let by_package: HashMap<String, usize> = registry
+            .crates
+            .iter()
+            .enumerate()
+            .map(|(index, copper_crate)| (copper_crate.package.clone(), index))
+            .collect();
+
+        if let Some(root) = root {
+            if let Some(node) = nodes.get(root.id.as_str()) {
+                for dep in &node.deps {
+                    let index = packages
+                        .get(dep.pkg.as_str())
+                        .and_then(|package| by_package.get(&package.name).copied());
+                    registry.dependencies.insert(dep.name.clone(), index);
+                }
+            }
+            let index = by_package.get(&root.name).copied();
+            registry.dependencies.insert(root.crate_name(), index);
+            let directory = root
+                .manifest_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            registry.root = Some((root.name.clone(), directory));
+        }
+        Ok(registry)
+    }
+
+    /// Finds a component from its type as written in a configuration.
+    pub fn find(&self, type_path: &str) -> Option<&ComponentInfo> {
+        let (first, rest) = split_first_segment(base_type(type_path))?;
+        let copper_crate = &self.crates[(*self.dependencies.get(first)?)?];
+        copper_crate.components.iter().find(|component| {
+            split_first_segment(&component.type_path).map(|(_, tail)| tail) == Some(rest)
+        })
+    }
+
+    /// Does a type of a configuration come from a dependency of the application?
+    pub fn is_from_dependency(&self, type_path: &str) -> bool {
+        split_first_segment(base_type(type_path))
+            .is_some_and(|(first, _)| self.dependencies.contains_key(first))
+    }
+
+    /// Is `module` a top level module of the application? It is a textual check on its sources.
+    fn is_local_module(&self, module: &str) -> bool {
+        if matches!(module, "crate" | "self" | "super") {
+            return true;
+        }
+        let Some((_, directory)) = &self.root else {
+            return true; // nothing to check against
+        };
+        let src = directory.join("src");
+        if src.join(format!("{module}.rs")).exists() || src.join(module).join("mod.rs").exists() {
+            return true;
+        }
+        let declaration = format!("mod {module}");
+        ["main.rs", "lib.rs"].iter().any(|file| {
+            std::fs::read_to_string(src.join(file))
+                .map(|content| content.contains(&declaration))
+                .unwrap_or(false)
+        })
+    }
+
+    fn check_node(
+        &self,
+        errors: &mut Vec<String>,
+        node_id: &str,
+        type_path: &str,
+        config: Option<&ComponentConfig>,
+        monitor: bool,
+    ) {
+        let base = base_type(type_path);
+        // A type without a path was brought in scope by a use in the application.
+        let Some((first, _)) = split_first_segment(base) else {
+            return;
+        };
+        let copper_crate = match self.dependencies.get(first) {
+            Some(Some(index)) => &self.crates[*index],
+            // A dependency without Copper metadata, the compiler will check it.
+            Some(None) => return,
+            None => {
+                if !self.is_local_module(first) {
+                    errors.push(format!(
+                        "\"{node_id}\": {type_path} refers to \"{first}\" which is neither a dependency nor a module of the application."
+                    ));
+                }
+                return;
+            }
+        };
+        // Application types are not required to be declared in its own metadata.
+        if self
+            .root
+            .as_ref()
+            .is_some_and(|(name, _)| *name == copper_crate.package)
+        {
+            return;
+        }
+        let Some(component) = self.find(base) else {
+            let available: Vec<&str> = copper_crate
+                .components
+                .iter()
+                .map(|component| component.type_path.as_str())
+                .collect();
+            errors.push(format!(
+                "\"{node_id}\": {type_path} is not a component of {}, it provides: {}.",
+                copper_crate.package,
+                if available.is_empty() {
+                    "nothing".to_string()
+                } else {
+                    available.join(", ")
+                }
+            ));
+            return;
+        };
+        match (monitor, component.plugin_type) {
+            (true, PluginType::Monitor) => {}
+            (false, PluginType::Source | PluginType::Task | PluginType::Sink) => {}
+            (_, plugin_type) => errors.push(format!(
+                "\"{node_id}\": {type_path} is a {plugin_type} and cannot be used as a {}.",
+                if monitor { "monitor" } else { "task" }
+            )),
+        }
+        for (key, field) in &component.config {
+            match config.and_then(|config| config.0.get(key)) {
+                None if field.required => errors.push(format!(
+                    "\"{node_id}\": {type_path} requires the config key \"{key}\" ({}).",
+                    field.value_type
+                )),
+                Some(value) if !value_matches(&field.value_type, value) => errors.push(format!(
+                    "\"{node_id}\": the config key \"{key}\" of {type_path} expects a {}, got {value}.",
+                    field.value_type
+                )),
+                _ => {}
+            }
+        }
+        // Without declared keys there is nothing to compare to.
+        if component.config.is_empty() {
+            return;
+        }
+        let mut keys: Vec<&String> = config
+            .iter()
+            .flat_map(|config| config.0.keys())
+            .filter(|key| !component.config.contains_key(*key))
+            .collect();
+        keys.sort();
+        for key in keys {
+            match closest_key(key, component.config.keys().map(String::as_str)) {
+                Some(closest) => errors.push(format!(
+                    "\"{node_id}\": {type_path} has no config key \"{key}\", did you mean \"{closest}\"?"
+                )),
+                None => errors.push(format!(
+                    "\"{node_id}\": {type_path} has no config key \"{key}\", it accepts: {}.",
+                    component.config.keys().map(String::as_str).collect::<Vec<_>>().join(", ")
+                )),
+            }
+        }
+    }
+
+    /// Checks that every node type of the configuration exists, and that the declared config
+    /// keys of the components are present with the right type and are the only ones.
+    pub fn check_config(&self, config: &CuConfig) -> CuResult<()> {
+        let mut errors = Vec::new();
+        let graphs = match &config.graphs {
+            ConfigGraphs::Simple(graph) => vec![graph],
+            ConfigGraphs::Missions(graphs) => {
+                let mut missions: Vec<_> = graphs.iter().collect();
+                missions.sort_by(|a, b| a.0.cmp(b.0));
+                missions.into_iter().map(|(_, graph)| graph).collect()
+            }
+        };
+        let mut checked = HashSet::new();
+        for graph in graphs {
+            for node in graph.node_indices().map(|index| &graph[index]) {
+                // Missions share most of their nodes.
+                if !checked.insert((node.get_id(), node.get_type().to_string())) {
+                    continue;
+                }
+                self.check_node(
+                    &mut errors,
+                    &node.get_id(),
+                    node.get_type(),
+                    node.get_instance_config(),
+                    false,
+                );
+            }
+        }
+        if let Some(monitor) = &config.monitor {
+            self.check_node(
+                &mut errors,
+                "monitor",
+                monitor.get_type(),
+                monitor.get_config(),
+                true,
+            );
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CuError::from(errors.join("\n")))
+        }
+    }
+}
+
+/// Checks a configuration file of the package being built against its dependencies.
+/// Call it from the build.rs of an application so mistakes are reported before the runtime
+/// generation, for example:
+///
+/// ```no_run
+/// cargo_copper::registry::check_in_build_script("copperconfig.ron");
+/// ```
+pub fn check_in_build_script(config_filename: &str) {
+    let directory =
+        PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("Not called from a build script"));
+    let config_path = directory.join(config_filename);
+    let manifest_path = directory.join("Cargo.toml");
+    println!("cargo:rerun-if-changed={}", config_path.display());
+    println!("cargo:rerun-if-changed={}", manifest_path.display());
+
+    let config = read_configuration(&config_path.to_string_lossy())
+        .unwrap_or_else(|e| panic!("Invalid configuration {config_filename}: {e}"));
+    // Everything is already downloaded when a build script runs.
+    let registry = Registry::discover(Some(&manifest_path), true)
+        .unwrap_or_else(|e| panic!("Could not list the Copper components: {e}"));
+    if let Err(e) = registry.check_config(&config) {
+        panic!("{config_filename} does not match the available Copper components:\n{e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu29_runtime::config::read_configuration_str;
+
+    /// An application depending on cu-pid, and on cu-rp-gpio renamed as gpio.
+    const METADATA: &str = r#"{
+        "packages": [
+            {
+                "id": "app 0.1.0", "name": "app", "version": "0.1.0",
+                "manifest_path": "/nonexistent/app/Cargo.toml",
+                "targets": [{ "name": "app", "kind": ["bin"] }],
+                "metadata": null
+            },
+            {
+                "id": "cu-pid 0.7.0", "name": "cu-pid", "version": "0.7.0",
+                "manifest_path": "/nonexistent/cu_pid/Cargo.toml",
+                "targets": [{ "name": "cu_pid", "kind": ["lib"] }],
+                "metadata": { "copper": {
+                    "plugin_type": "task",
+                    "components": [{
+                        "type": "cu_pid::GenericPIDTask",
+                        "input": ["I"],
+                        "output": ["cu_pid::PIDControlOutputPayload"],
+                        "config": {
+                            "kp": { "type": "f64", "required": true },
+                            "sampling_ms": { "type": "u32", "default": "10" }
+                        }
+                    }]
+                }}
+            },
+            {
+                "id": "cu-rp-gpio 0.7.0", "name": "cu-rp-gpio", "version": "0.7.0",
+                "manifest_path": "/nonexistent/cu_rp_gpio/Cargo.toml",
+                "targets": [{ "name": "cu_rp_gpio", "kind": ["lib"] }],
+                "metadata": { "copper": {
+                    "plugin_type": "sink",
+                    "components": [{ "type": "cu_rp_gpio::RPGpio" }]
+                }}
+            },
+            {
+                "id": "cu-unused 0.7.0", "name": "cu-unused", "version": "0.7.0",
+                "manifest_path": "/nonexistent/cu_unused/Cargo.toml",
+                "targets": [{ "name": "cu_unused", "kind": ["lib"] }],
+                "metadata": { "copper": { "plugin_type": "source" }}
+            }
+        ],
+        "resolve": {
+            "root": "app 0.1.0",
+            "nodes": [
+                { "id": "app 0.1.0", "deps": [
+                    { "name": "cu_pid", "pkg": "cu-pid 0.7.0" },
+                    { "name": "gpio", "pkg": "cu-rp-gpio 0.7.0" }
+                ]},
+                { "id": "cu-pid 0.7.0", "deps": [] },
+                { "id": "cu-rp-gpio 0.7.0", "deps": [] },
+                { "id": "cu-unused 0.7.0", "deps": [] }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_discovery() {
+        let registry = Registry::from_metadata(METADATA).unwrap();
+        let packages: Vec<&str> = registry
+            .crates
+            .iter()
+            .map(|copper_crate| copper_crate.package.as_str())
+            .collect();
+        assert_eq!(packages, vec!["cu-pid", "cu-rp-gpio"]);
+
+        let pid = registry
+            .find("cu_pid::GenericPIDTask<cu_ads7883::ADSReadingPayload>")
+            .unwrap();
+        assert_eq!(pid.plugin_type, PluginType::Task);
+        assert!(pid.config["kp"].required);
+        assert_eq!(
+            registry.find("gpio::RPGpio").unwrap().plugin_type,
+            PluginType::Sink
+        );
+        assert!(registry.find("cu_pid::PIDController").is_none());
+    }
+
+    #[test]
+    fn test_check_config() {
+        let registry = Registry::from_metadata(METADATA).unwrap();
+        let valid = r#"(
+            tasks: [
+                (id: "pid", type: "cu_pid::GenericPIDTask<f32>", config: { "kp": 1.0, "sampling_ms": 10 }),
+                (id: "gpio", type: "gpio::RPGpio"),
+                (id: "local", type: "crate::tasks::MyTask"),
+            ],
+            cnx: [],
+        )"#;
+        let config = read_configuration_str(valid.to_string()).unwrap();
+        registry.check_config(&config).unwrap();
+
+        let invalid = r#"(
+            tasks: [
+                (id: "pid", type: "cu_pid::GenericPIDTask<f32>", config: { "sampling_ms": "10" }),
+                (id: "typo", type: "cu_pid::PIDTask"),
+                (id: "pid2", type: "cu_pid::GenericPIDTask<f32>", config: { "kp": 1.0, "kq": 1.0, "verbose": true }),
+            ],
+            cnx: [],
+        )"#;
+        let config = read_configuration_str(invalid.to_string()).unwrap();
+        let error = registry.check_config(&config).unwrap_err().to_string();
+        assert!(error.contains("requires the config key \"kp\""), "{error}");
+        assert!(error.contains("\"sampling_ms\" of cu_pid::GenericPIDTask<f32> expects a u32"));
+        assert!(error.contains(
+            "cu_pid::PIDTask is not a component of cu-pid, it provides: cu_pid::GenericPIDTask."
+        ));
+        assert!(error.contains(
+            "\"pid2\": cu_pid::GenericPIDTask<f32> has no config key \"kq\", did you mean \"kp\"?"
+        ));
+        assert!(error.contains("has no config key \"verbose\", it accepts: kp, sampling_ms."));
+    }
+}
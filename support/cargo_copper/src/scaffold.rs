@@ -0,0 +1,383 @@
+//! Renders the project templates.
+//! The templates use the `cargo generate` placeholders syntax so the same files serve both tools,
+//! only the few filters they need are implemented here.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the generated project gets Copper from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CopperSource {
+    CratesIo,
+    Git,
+    /// Path of a local clone of copper-rs, relative to the generated project.
+    Local(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ComponentKind {
+    Source,
+    Task,
+    Sink,
+}
+
+impl ComponentKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ComponentKind::Source => "source",
+            ComponentKind::Task => "task",
+            ComponentKind::Sink => "sink",
+        }
+    }
+}
+
+const APP_MAIN: &str = include_str!("../../../templates/cu_full/src/main.rs");
+const APP_TASKS: &str = include_str!("../../../templates/cu_full/src/tasks.rs");
+const APP_LOGREADER: &str = include_str!("../../../templates/cu_full/src/logreader.rs");
+const APP_CONFIG: &str = include_str!("../../../templates/cu_full/copperconfig.ron");
+const APP_BUILD: &str = include_str!("../../../templates/cu_full/build.rs");
+const APP_BIN_LOG: &str = include_str!("../../../templates/cu_full/bin/log");
+const APP_BIN_CL: &str = include_str!("../../../templates/cu_full/bin/cl");
+const APP_BIN_RCFG: &str = include_str!("../../../templates/cu_full/bin/rcfg");
+const COMPONENT_SOURCE: &str = include_str!("../templates/component/lib_source.rs");
+const COMPONENT_TASK: &str = include_str!("../templates/component/lib_task.rs");
+const COMPONENT_SINK: &str = include_str!("../templates/component/lib_sink.rs");
+const COMPONENT_README: &str = include_str!("../templates/component/README.md");
+
+pub fn kebab_case(name: &str) -> String {
+    words(name).join("-")
+}
+
+pub fn snake_case(name: &str) -> String {
+    words(name).join("_")
+}
+
+pub fn upper_camel_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Splits a name on separators and on the lower to upper case transitions.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Replaces the `{{ variable | filter }}` placeholders.
+fn render(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start + 2..start + end];
+        let mut parts = placeholder.split('|').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        match variables.iter().find(|(variable, _)| *variable == name) {
+            Some((_, value)) => {
+                let value = match parts.next() {
+                    Some("kebab_case") => kebab_case(value),
+                    Some("snake_case") => snake_case(value),
+                    Some("upper_camel_case") => upper_camel_case(value),
+                    _ => value.to_string(),
+                };
+                output.push_str(&value);
+            }
+            None => output.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The dependency specification of a Copper crate for the generated Cargo.toml.
+fn copper_dependency(source: &CopperSource, crate_name: &str, crate_dir: &str) -> String {
+    match source {
+        CopperSource::CratesIo => {
+            format!(
+                "{crate_name} = {{ version = \"{}\" }}",
+                env!("CARGO_PKG_VERSION")
+            )
+        }
+        CopperSource::Git => format!(
+            "{crate_name} = {{ git = \"https://github.com/copper-project/copper-rs.git\" }}"
+        ),
+        CopperSource::Local(root) => {
+            format!("{crate_name} = {{ path = \"{root}/core/{crate_dir}\" }}")
+        }
+    }
+}
+
+fn write_file(path: &Path, content: &str, executable: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    #[cfg(unix)]
+    if executable {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = executable;
+    Ok(())
+}
+
+fn check_destination(destination: &Path) -> io::Result<()> {
+    if destination.exists() && fs::read_dir(destination)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists and is not empty", destination.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn project_name(destination: &Path) -> io::Result<String> {
+    // "." or ".." only have a name once resolved.
+    let resolved = destination.canonicalize();
+    destination
+        .file_name()
+        .or_else(|| resolved.as_ref().ok().and_then(|path| path.file_name()))
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Cannot name a project after {}", destination.display()),
+            )
+        })
+}
+
+/// Scaffolds an application: config, main with the runtime, sample tasks and a log reader.
+pub fn new_application(destination: &Path, source: &CopperSource) -> io::Result<Vec<PathBuf>> {
+    check_destination(destination)?;
+    let name = project_name(destination)?;
+    let root = match source {
+        CopperSource::Local(root) => root.as_str(),
+        _ => "..",
+    };
+    let variables = [("project-name", name.as_str()), ("copper_root_path", root)];
+    let kebab = kebab_case(&name);
+    let workspace = if matches!(source, CopperSource::Local(_)) {
+        "# Standalone from the workspace of the Copper repository.\n[workspace]\n\n"
+    } else {
+        ""
+    };
+    let cargo_toml = format!(
+        r#"[package]
+name = "{kebab}"
+version = "0.1.0"
+edition = "2021"
+default-run = "{kebab}"
+
+{workspace}# The main executable of your application
+[[bin]]
+name = "{kebab}"
+path = "src/main.rs"
+
+# A custom made log reader application for your application.
+[[bin]]
+name = "{kebab}-logreader"
+path = "src/logreader.rs"
+required-features = ["logreader"]
+
+[features]
+default = []
+logreader = ["dep:cu29-export"]
+
+[dependencies]
+{}
+bincode = {{ version = "2.0.1", features = ["derive"] }}
+{}
+{}
+"#,
+        copper_dependency(source, "cu29", "cu29"),
+        copper_dependency(source, "cu29-helpers", "cu29_helpers"),
+        copper_dependency(source, "cu29-export", "cu29_export").replacen(
+            " }",
+            ", optional = true }",
+            1
+        ),
+    );
+
+    let files: [(&str, String, bool); 9] = [
+        ("Cargo.toml", cargo_toml, false),
+        ("build.rs", render(APP_BUILD, &variables), false),
+        ("copperconfig.ron", render(APP_CONFIG, &variables), false),
+        ("src/main.rs", render(APP_MAIN, &variables), false),
+        ("src/tasks.rs", render(APP_TASKS, &variables), false),
+        ("src/logreader.rs", render(APP_LOGREADER, &variables), false),
+        ("bin/log", render(APP_BIN_LOG, &variables), true),
+        ("bin/cl", render(APP_BIN_CL, &variables), true),
+        ("bin/rcfg", render(APP_BIN_RCFG, &variables), true),
+    ];
+    write_files(destination, &files)
+}
+
+/// Scaffolds a component crate with a sample source, task or sink.
+pub fn new_component(
+    destination: &Path,
+    kind: ComponentKind,
+    source: &CopperSource,
+) -> io::Result<Vec<PathBuf>> {
+    check_destination(destination)?;
+    let name = project_name(destination)?;
+    let variables = [
+        ("component-name", name.as_str()),
+        ("component-kind", kind.as_str()),
+    ];
+    let crate_name = snake_case(&name);
+    let type_name = upper_camel_case(&name);
+    let payload = format!("{crate_name}::{type_name}Payload");
+    let (input, output) = match kind {
+        ComponentKind::Source => (String::new(), payload),
+        ComponentKind::Task => (payload.clone(), payload),
+        ComponentKind::Sink => (payload, String::new()),
+    };
+    let cargo_toml = format!(
+        r#"[package]
+name = "{}"
+version = "0.1.0"
+edition = "2021"
+description = "A Copper {kind}."
+
+[dependencies]
+{}
+bincode = {{ version = "2.0.1", features = ["derive"] }}
+
+# Lets `cargo copper components` list this crate and `cargo copper check` validate its use.
+[package.metadata.copper]
+plugin_type = "{kind}"
+
+[[package.metadata.copper.components]]
+type = "{crate_name}::{type_name}"
+input = [{}]
+output = [{}]
+"#,
+        kebab_case(&name),
+        copper_dependency(source, "cu29", "cu29"),
+        toml_array(&input),
+        toml_array(&output),
+        kind = kind.as_str(),
+    );
+    let lib = match kind {
+        ComponentKind::Source => COMPONENT_SOURCE,
+        ComponentKind::Task => COMPONENT_TASK,
+        ComponentKind::Sink => COMPONENT_SINK,
+    };
+    let files: [(&str, String, bool); 4] = [
+        ("Cargo.toml", cargo_toml, false),
+        ("build.rs", render(APP_BUILD, &variables), false),
+        ("README.md", render(COMPONENT_README, &variables), false),
+        ("src/lib.rs", render(lib, &variables), false),
+    ];
+    write_files(destination, &files)
+}
+
+fn toml_array(item: &str) -> String {
+    if item.is_empty() {
+        String::new()
+    } else {
+        format!("\"{item}\"")
+    }
+}
+
+fn write_files(destination: &Path, files: &[(&str, String, bool)]) -> io::Result<Vec<PathBuf>> {
+    files
+        .iter()
+        .map(|(path, content, executable)| {
+            let path = destination.join(path);
+            write_file(&path, content, *executable)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cases() {
+        assert_eq!(kebab_case("my_robot"), "my-robot");
+        assert_eq!(kebab_case("MyRobot"), "my-robot");
+        assert_eq!(snake_case("my-robot2"), "my_robot2");
+        assert_eq!(upper_camel_case("my-robot"), "MyRobot");
+        assert_eq!(
+            render(
+                "struct {{project-name | upper_camel_case}}App {{other}}",
+                &[("project-name", "my_bot")]
+            ),
+            "struct MyBotApp {{other}}"
+        );
+    }
+
+    #[test]
+    fn test_new_application() {
+        let tmp = tempfile::tempdir().unwrap();
+        let destination = tmp.path().join("my_bot");
+        let files =
+            new_application(&destination, &CopperSource::Local("../..".to_string())).unwrap();
+        assert_eq!(files.len(), 9);
+        for file in files {
+            let content = fs::read_to_string(&file).unwrap();
+            assert!(
+                !content.contains("{{"),
+                "{} is not fully rendered",
+                file.display()
+            );
+        }
+        let main = fs::read_to_string(destination.join("src/main.rs")).unwrap();
+        assert!(main.contains("struct MyBotApplication"));
+        let cargo = fs::read_to_string(destination.join("Cargo.toml")).unwrap();
+        assert!(cargo.contains("path = \"../../core/cu29_export\", optional = true"));
+
+        // Never overwrite an existing project.
+        assert!(new_application(&destination, &CopperSource::CratesIo).is_err());
+    }
+
+    #[test]
+    fn test_new_component() {
+        let tmp = tempfile::tempdir().unwrap();
+        let destination = tmp.path().join("cu_my_lidar");
+        new_component(&destination, ComponentKind::Source, &CopperSource::Git).unwrap();
+        let lib = fs::read_to_string(destination.join("src/lib.rs")).unwrap();
+        assert!(lib.contains("impl<'cl> CuSrcTask<'cl> for CuMyLidar"));
+        let cargo = fs::read_to_string(destination.join("Cargo.toml")).unwrap();
+        assert!(cargo.contains("name = \"cu-my-lidar\""));
+        assert!(cargo.contains("type = \"cu_my_lidar::CuMyLidar\""));
+        assert!(cargo.contains("output = [\"cu_my_lidar::CuMyLidarPayload\"]"));
+    }
+}
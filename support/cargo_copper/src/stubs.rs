@@ -0,0 +1,281 @@
+//! Task skeletons generated from a configuration, for a config first workflow: the graph is
+//! written first with the types of its tasks, `cargo copper check --emit-stubs` then writes
+//! compilable stubs of the types that do not exist yet, with the Input and Output their
+//! connections need.
+//!
+//! A type `tasks::Camera` goes in the module `tasks` of the application (`src/tasks.rs`), the
+//! types the module already declares are left alone.
+
+use cu29_runtime::config::{ConfigGraphs, CuConfig, Node, NodeId};
+use cu29_traits::CuResult;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// The stub of a task type of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStub {
+    /// The module of the application the type goes in, `tasks` for `tasks::Camera`.
+    pub module: String,
+    pub name: String,
+    /// The declaration of the type and of its task trait implementation.
+    pub source: String,
+}
+
+/// The header of a module created for stubs.
+const MODULE_HEADER: &str = "use cu29::prelude::*;\n\n#[allow(unused_imports)]\nuse super::*;\n";
+
+/// Splits `crate::tasks::Camera` or `tasks::Camera` into `tasks` and `Camera`, None for the
+/// other shapes of paths which are left to the developer.
+fn module_and_name(type_path: &str) -> Option<(&str, &str)> {
+    let path = type_path.strip_prefix("crate::").unwrap_or(type_path);
+    let (module, name) = path.split_once("::")?;
+    let is_ident = |s: &str| {
+        s.chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+    (is_ident(module) && is_ident(name)).then_some((module, name))
+}
+
+/// The Input of a task from its incoming connections in their declaration order, the keyed
+/// connections of a same message type grouped in a CuKeyedMsgs like the generated runtime does.
+fn input_type(inputs: &[(String, Option<String>)]) -> String {
+    let mut positions: Vec<(&str, bool)> = Vec::new();
+    for (msg_type, key) in inputs {
+        let keyed = key.is_some();
+        if keyed && positions.contains(&(msg_type.as_str(), true)) {
+            continue;
+        }
+        positions.push((msg_type, keyed));
+    }
+    if positions.iter().all(|(_, keyed)| !keyed) {
+        let msg_types: Vec<&str> = positions.iter().map(|(msg_type, _)| *msg_type).collect();
+        return format!("input_msg!('cl, {})", msg_types.join(", "));
+    }
+    let positions: Vec<String> = positions
+        .iter()
+        .map(|(msg_type, keyed)| match keyed {
+            true => format!("CuKeyedMsgs<'cl, {msg_type}>"),
+            false => format!("&'cl CuMsg<{msg_type}>"),
+        })
+        .collect();
+    match positions.as_slice() {
+        [single] => single.clone(),
+        _ => format!("({})", positions.join(", ")),
+    }
+}
+
+fn task_stub(
+    config: &CuConfig,
+    mission: Option<&str>,
+    node_id: NodeId,
+    node: &Node,
+    name: &str,
+) -> CuResult<String> {
+    let mut inputs = config.get_dst_edges(node_id, mission)?;
+    inputs.sort();
+    let inputs: Vec<(String, Option<String>)> = inputs
+        .into_iter()
+        .filter_map(|edge| config.get_edge_weight(edge, mission))
+        .map(|cnx| Ok((config.resolve_msg_type(&cnx.msg)?, cnx.key)))
+        .collect::<CuResult<_>>()?;
+    let output = match config.get_src_edges(node_id, mission)?.first() {
+        Some(edge) => config
+            .get_edge_weight(*edge, mission)
+            .map(|cnx| config.resolve_msg_type(&cnx.msg))
+            .transpose()?,
+        None => None,
+    };
+
+    let id = node.get_id();
+    let mut keys: Vec<&String> = node
+        .get_instance_config()
+        .iter()
+        .flat_map(|config| config.0.keys())
+        .collect();
+    keys.sort();
+    let keys = match keys.as_slice() {
+        [] => String::new(),
+        keys => {
+            let keys: Vec<String> = keys.iter().map(|key| format!("\"{key}\"")).collect();
+            format!("        // The configuration sets: {}.\n", keys.join(", "))
+        }
+    };
+    let unconnected = match node.get_unconnected_inputs() {
+        [] => String::new(),
+        positions => format!(
+            "    // TODO: the configuration leaves the optional inputs at {positions:?} unconnected, add them as ?T.\n"
+        ),
+    };
+
+    let (task_trait, associated_types, process) = match (inputs.is_empty(), output) {
+        (true, Some(output)) => (
+            "CuSrcTask",
+            format!("    type Output = output_msg!('cl, {output});\n"),
+            "    fn process(&mut self, _clock: &RobotClock, _output: Self::Output) -> CuResult<()> {\n        // TODO: produce the output, it stays an empty message until then.\n        Ok(())\n    }\n".to_string(),
+        ),
+        (false, Some(output)) => (
+            "CuTask",
+            format!(
+                "    type Input = {};\n{unconnected}    type Output = output_msg!('cl, {output});\n",
+                input_type(&inputs)
+            ),
+            "    fn process(\n        &mut self,\n        _clock: &RobotClock,\n        _input: Self::Input,\n        _output: Self::Output,\n    ) -> CuResult<()> {\n        // TODO: compute the output from the input, it stays an empty message until then.\n        Ok(())\n    }\n".to_string(),
+        ),
+        (false, None) => (
+            "CuSinkTask",
+            format!("    type Input = {};\n{unconnected}", input_type(&inputs)),
+            "    fn process(&mut self, _clock: &RobotClock, _input: Self::Input) -> CuResult<()> {\n        // TODO: act on the input.\n        Ok(())\n    }\n".to_string(),
+        ),
+        (true, None) => return Err(format!("\"{id}\" is connected to nothing.").into()),
+    };
+
+    Ok(format!(
+        "/// Stub of the task \"{id}\" generated from the configuration.
+#[derive(Default)]
+pub struct {name} {{}}
+
+// Needs to be fully implemented if you want to have a stateful task.
+impl Freezable for {name} {{}}
+
+impl<'cl> {task_trait}<'cl> for {name} {{
+{associated_types}
+    fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {{
+{keys}        Ok(Self {{}})
+    }}
+
+{process}}}
+"
+    ))
+}
+
+/// The stubs of the task types of the configuration, one per type, except the ones `exists`
+/// reports, for example the components of the dependencies. The types which are not in a module
+/// of the application are skipped.
+pub fn task_stubs(config: &CuConfig, exists: impl Fn(&str) -> bool) -> CuResult<Vec<TaskStub>> {
+    let missions: Vec<Option<&str>> = match &config.graphs {
+        ConfigGraphs::Simple(_) => vec![None],
+        ConfigGraphs::Missions(graphs) => {
+            let mut missions: Vec<&str> = graphs.keys().map(String::as_str).collect();
+            missions.sort();
+            missions.into_iter().map(Some).collect()
+        }
+    };
+    let mut stubs: Vec<TaskStub> = Vec::new();
+    for mission in missions {
+        for (node_id, node) in config.get_all_nodes(mission) {
+            let type_path = node.get_type();
+            let Some((module, name)) = module_and_name(type_path) else {
+                continue;
+            };
+            // Missions share most of their nodes, a type used by several nodes gets one stub.
+            if exists(type_path)
+                || stubs
+                    .iter()
+                    .any(|stub| stub.module == module && stub.name == name)
+            {
+                continue;
+            }
+            stubs.push(TaskStub {
+                module: module.to_string(),
+                name: name.to_string(),
+                source: task_stub(config, mission, node_id, node, name)?,
+            });
+        }
+    }
+    Ok(stubs)
+}
+
+/// Groups the stubs by module.
+pub fn by_module(stubs: &[TaskStub]) -> BTreeMap<&str, Vec<&TaskStub>> {
+    let mut modules: BTreeMap<&str, Vec<&TaskStub>> = BTreeMap::new();
+    for stub in stubs {
+        modules.entry(&stub.module).or_default().push(stub);
+    }
+    modules
+}
+
+/// Adds to the source of a module, None if it does not exist yet, the stubs of the types it does
+/// not declare. Returns the new source and the names of the types added.
+pub fn add_to_module(source: Option<&str>, stubs: &[&TaskStub]) -> (String, Vec<String>) {
+    let mut content = source.unwrap_or(MODULE_HEADER).to_string();
+    let mut added = Vec::new();
+    for stub in stubs {
+        let declared = [
+            format!("struct {} ", stub.name),
+            format!("struct {};", stub.name),
+            format!("struct {}<", stub.name),
+        ];
+        if declared
+            .iter()
+            .any(|declaration| content.contains(declaration))
+        {
+            continue;
+        }
+        if !content.ends_with("\n\n") {
+            content.push('\n');
+        }
+        let _ = write!(content, "{}", stub.source);
+        added.push(stub.name.clone());
+    }
+    (content, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu29_runtime::config::read_configuration_str;
+
+    #[test]
+    fn test_task_stubs() {
+        let txt = r#"(
+            tasks: [
+                (id: "camera", type: "tasks::Camera", config: {"fps": 30, "device": "/dev/video0"}),
+                (id: "left", type: "tasks::Wheel"),
+                (id: "right", type: "tasks::Wheel"),
+                (id: "odometry", type: "crate::tasks::Odometry"),
+                (id: "motors", type: "actuators::Motors"),
+                (id: "pid", type: "cu_pid::GenericPIDTask<f32>"),
+            ],
+            cnx: [
+                (src: "camera", dst: "motors", msg: "messages::Image"),
+                (src: "left", dst: "odometry", msg: "i32", key: "left"),
+                (src: "right", dst: "odometry", msg: "i32", key: "right"),
+                (src: "odometry", dst: "pid", msg: "f32"),
+                (src: "pid", dst: "motors", msg: "cu_pid::PIDControlOutputPayload"),
+            ],
+        )"#;
+        let config = read_configuration_str(txt.to_string()).unwrap();
+        let stubs = task_stubs(&config, |type_path| type_path.starts_with("cu_pid::")).unwrap();
+        let names: Vec<&str> = stubs.iter().map(|stub| stub.name.as_str()).collect();
+        assert_eq!(names, vec!["Camera", "Wheel", "Odometry", "Motors"]);
+
+        let camera = &stubs[0].source;
+        assert!(camera.contains("impl<'cl> CuSrcTask<'cl> for Camera {"));
+        assert!(camera.contains("type Output = output_msg!('cl, messages::Image);"));
+        assert!(camera.contains("// The configuration sets: \"device\", \"fps\"."));
+        let odometry = &stubs[2].source;
+        assert!(odometry.contains("impl<'cl> CuTask<'cl> for Odometry {"));
+        assert!(odometry.contains("type Input = CuKeyedMsgs<'cl, i32>;"));
+        let motors = &stubs[3].source;
+        assert_eq!(stubs[3].module, "actuators");
+        assert!(motors.contains("impl<'cl> CuSinkTask<'cl> for Motors {"));
+        assert!(motors.contains(
+            "type Input = input_msg!('cl, messages::Image, cu_pid::PIDControlOutputPayload);"
+        ));
+
+        let modules = by_module(&stubs);
+        let (created, added) = add_to_module(None, &modules["tasks"]);
+        assert!(created.starts_with(MODULE_HEADER));
+        assert_eq!(added, vec!["Camera", "Wheel", "Odometry"]);
+        // The types already written are kept.
+        let existing = "use cu29::prelude::*;\n\npub struct Wheel {}\n";
+        let (updated, added) = add_to_module(Some(existing), &modules["tasks"]);
+        assert_eq!(added, vec!["Camera", "Odometry"]);
+        assert!(updated.starts_with(existing));
+    }
+}
@@ -0,0 +1,40 @@
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+
+/// The message consumed by this sink.
+#[derive(Default, Debug, Clone, Encode, Decode)]
+pub struct {{component-name | upper_camel_case}}Payload {
+    pub value: i32,
+}
+
+/// A sink (ie. an actuation or an interface to an external system).
+#[derive(Default)]
+pub struct {{component-name | upper_camel_case}} {}
+
+// Needs to be fully implemented if you want to have a stateful task.
+impl Freezable for {{component-name | upper_camel_case}} {}
+
+impl<'cl> CuSinkTask<'cl> for {{component-name | upper_camel_case}} {
+    type Input = input_msg!('cl, {{component-name | upper_camel_case}}Payload);
+
+    fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {})
+    }
+
+    // don't forget the other lifecycle methods if you need them: start, stop, preprocess, postprocess
+
+    fn process(&mut self, _clock: &RobotClock, input: Self::Input) -> CuResult<()> {
+        if let Some(payload) = input.payload() {
+            debug!("{{component-name}} received: {}", payload.value);
+        }
+        Ok(())
+    }
+
+    // Called instead of process while the emergency stop is engaged.
+    fn safe_state(&mut self, _clock: &RobotClock) -> CuResult<()> {
+        Ok(())
+    }
+}
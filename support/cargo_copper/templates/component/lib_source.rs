@@ -0,0 +1,34 @@
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+
+/// The message produced by this source.
+#[derive(Default, Debug, Clone, Encode, Decode)]
+pub struct {{component-name | upper_camel_case}}Payload {
+    pub value: i32,
+}
+
+/// A source (ie. a driver) bringing data into Copper.
+#[derive(Default)]
+pub struct {{component-name | upper_camel_case}} {}
+
+// Needs to be fully implemented if you want to have a stateful task.
+impl Freezable for {{component-name | upper_camel_case}} {}
+
+impl<'cl> CuSrcTask<'cl> for {{component-name | upper_camel_case}} {
+    type Output = output_msg!('cl, {{component-name | upper_camel_case}}Payload);
+
+    fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {})
+    }
+
+    // don't forget the other lifecycle methods if you need them: start, stop, preprocess, postprocess
+
+    fn process(&mut self, clock: &RobotClock, output: Self::Output) -> CuResult<()> {
+        output.set_payload({{component-name | upper_camel_case}}Payload { value: 42 });
+        output.metadata.tov = clock.now().into();
+        Ok(())
+    }
+}
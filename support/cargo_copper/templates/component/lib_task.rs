@@ -0,0 +1,45 @@
+use bincode::{Decode, Encode};
+use cu29::prelude::*;
+
+/// The message produced by this task.
+#[derive(Default, Debug, Clone, Encode, Decode)]
+pub struct {{component-name | upper_camel_case}}Payload {
+    pub value: i32,
+}
+
+/// A task producing an output out of an input, the input type is up to you.
+pub struct {{component-name | upper_camel_case}} {
+    // if you add some task state here, you need to implement the Freezable trait
+}
+
+// Needs to be fully implemented if you want to have a stateful task.
+impl Freezable for {{component-name | upper_camel_case}} {}
+
+impl<'cl> CuTask<'cl> for {{component-name | upper_camel_case}} {
+    type Input = input_msg!('cl, {{component-name | upper_camel_case}}Payload);
+    type Output = output_msg!('cl, {{component-name | upper_camel_case}}Payload);
+
+    fn new(_config: Option<&ComponentConfig>) -> CuResult<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {})
+    }
+
+    // don't forget the other lifecycle methods if you need them: start, stop, preprocess, postprocess
+
+    fn process(
+        &mut self,
+        _clock: &RobotClock,
+        input: Self::Input,
+        output: Self::Output,
+    ) -> CuResult<()> {
+        if let Some(payload) = input.payload() {
+            output.set_payload({{component-name | upper_camel_case}}Payload {
+                value: payload.value + 1,
+            });
+        }
+        output.metadata.tov = input.metadata.tov;
+        Ok(())
+    }
+}
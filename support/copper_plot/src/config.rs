@@ -0,0 +1,81 @@
+//! The dashboard is described in a RON file, one plot per row of the window:
+//!
+//! ```ron
+//! (
+//!     plots: [
+//!         (
+//!             title: "Rail position",
+//!             series: [
+//!                 (cnx: "balpos", expr: "msg.analog_value", label: Some("angle")),
+//!                 (cnx: "railpos", expr: "msg.ticks"),
+//!             ],
+//!         ),
+//!         (title: "Motor", series: [(cnx: "pid", expr: "msg.output")]),
+//!     ],
+//!     // Seconds of data kept on the live plots.
+//!     history_s: 20.0,
+//! )
+//! ```
+
+use cu29::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::read_to_string;
+
+fn default_history() -> f64 {
+    30.0
+}
+
+/// A curve: the values of a field of the messages of a connection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeriesConfig {
+    /// The connection as src->dst or just the id of the source task.
+    pub cnx: String,
+    /// The field plotted, see [crate::expr].
+    pub expr: String,
+    /// The name of the curve in the legend, the connection and the expression by default.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl SeriesConfig {
+    /// The task emitting the messages of the connection.
+    pub fn src(&self) -> &str {
+        self.cnx.split("->").next().unwrap_or_default().trim()
+    }
+
+    pub fn label(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.src(), self.expr))
+    }
+}
+
+/// A plot of the dashboard, its series share the same axes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlotConfig {
+    pub title: String,
+    pub series: Vec<SeriesConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardConfig {
+    pub plots: Vec<PlotConfig>,
+    /// The seconds of data kept when plotting a running application.
+    #[serde(default = "default_history")]
+    pub history_s: f64,
+}
+
+impl DashboardConfig {
+    pub fn from_ron(content: &str) -> CuResult<Self> {
+        ron::from_str(content)
+            .map_err(|e| CuError::from("Invalid plot configuration").add_cause(&e.to_string()))
+    }
+
+    pub fn from_file(path: &str) -> CuResult<Self> {
+        let content = read_to_string(path).map_err(|e| {
+            CuError::from(format!("Failed to read the plot configuration: {path}"))
+                .add_cause(&e.to_string())
+        })?;
+        Self::from_ron(&content)
+    }
+}
@@ -0,0 +1,131 @@
+//! The data of the dashboard: the points of every series, fed with the messages from a log or a
+//! running application.
+
+use crate::config::DashboardConfig;
+use crate::expr::{DebugValue, FieldPath};
+use cu29::prelude::*;
+use std::collections::VecDeque;
+
+pub struct Series {
+    /// The index of the plot of the series in the configuration.
+    pub plot: usize,
+    pub label: String,
+    src: String,
+    path: FieldPath,
+    /// Time in seconds and value.
+    pub points: VecDeque<[f64; 2]>,
+    /// The messages the field could not be found in.
+    pub misses: u64,
+}
+
+pub struct Dashboard {
+    pub config: DashboardConfig,
+    pub series: Vec<Series>,
+}
+
+impl Dashboard {
+    pub fn new(config: DashboardConfig) -> CuResult<Self> {
+        let mut series = Vec::new();
+        for (plot, plot_config) in config.plots.iter().enumerate() {
+            for series_config in &plot_config.series {
+                series.push(Series {
+                    plot,
+                    label: series_config.label(),
+                    src: series_config.src().to_string(),
+                    path: FieldPath::parse(&series_config.expr)?,
+                    points: VecDeque::new(),
+                    misses: 0,
+                });
+            }
+        }
+        Ok(Self { config, series })
+    }
+
+    /// The source tasks of the connections plotted.
+    pub fn connections(&self) -> Vec<String> {
+        let mut connections: Vec<String> = self.series.iter().map(|s| s.src.clone()).collect();
+        connections.sort();
+        connections.dedup();
+        connections
+    }
+
+    pub fn is_plotted(&self, src: &str) -> bool {
+        self.series.iter().any(|series| series.src == src)
+    }
+
+    /// Adds a message of a connection, its payload as rendered by Debug, at `time` in seconds.
+    pub fn push(&mut self, src: &str, time: f64, payload: &str) {
+        let value = DebugValue::parse(payload).ok();
+        for series in self.series.iter_mut().filter(|series| series.src == src) {
+            match value.as_ref().and_then(|value| series.path.eval(value)) {
+                Some(y) => series.points.push_back([time, y]),
+                None => series.misses += 1,
+            }
+        }
+    }
+
+    /// Forgets the points older than `time`.
+    pub fn trim(&mut self, time: f64) {
+        for series in self.series.iter_mut() {
+            while series.points.front().is_some_and(|point| point[0] < time) {
+                series.points.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(dashboard: &Dashboard, series: usize) -> Vec<[f64; 2]> {
+        dashboard.series[series].points.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_dashboard() {
+        let config = DashboardConfig::from_ron(
+            r#"(
+                plots: [
+                    (title: "Velocity", series: [
+                        (cnx: "odom->ctrl", expr: "msg.linear.x"),
+                        (cnx: "odom", expr: "msg.angular.z", label: Some("yaw rate")),
+                    ]),
+                    (title: "Battery", series: [(cnx: "power", expr: "msg.voltage")]),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(config.history_s, 30.0);
+        let mut dashboard = Dashboard::new(config).unwrap();
+        assert_eq!(dashboard.connections(), vec!["odom", "power"]);
+        assert_eq!(dashboard.series[0].label, "odom msg.linear.x");
+        assert_eq!(dashboard.series[1].label, "yaw rate");
+        assert_eq!(dashboard.series[2].plot, 1);
+
+        let twist = "Twist { linear: V { x: 0.5, y: 0.0, z: 0.0 }, angular: V { x: 0.0, y: 0.0, z: -0.1 } }";
+        dashboard.push("odom", 0.0, twist);
+        dashboard.push("odom", 0.1, "Twist { linear: V { x: 0.7 } }");
+        dashboard.push("power", 0.1, "Power { voltage: 12.1 V^1 }");
+        dashboard.push("lidar", 0.1, "[1.0, 2.0]");
+        assert_eq!(points(&dashboard, 0), vec![[0.0, 0.5], [0.1, 0.7]]);
+        assert_eq!(points(&dashboard, 1), vec![[0.0, -0.1]]);
+        assert_eq!(dashboard.series[1].misses, 1);
+        assert_eq!(points(&dashboard, 2), vec![[0.1, 12.1]]);
+
+        dashboard.trim(0.05);
+        assert_eq!(points(&dashboard, 0), vec![[0.1, 0.7]]);
+        assert!(Dashboard::new(DashboardConfig {
+            plots: vec![crate::config::PlotConfig {
+                title: "Broken".to_string(),
+                series: vec![crate::config::SeriesConfig {
+                    cnx: "odom".to_string(),
+                    expr: "linear.x".to_string(),
+                    label: None,
+                }],
+            }],
+            history_s: 10.0,
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! The fields of the messages are found in the Debug rendering of their payloads, the only
+//! representation the runtime has for any payload type, in the logs as in the taps.
+//!
+//! An expression is a path from `msg`, the payload: `msg.twist.linear.x`, `msg.ranges[3]` or
+//! `msg.0` for the first field of a tuple struct. The newtypes and the options are looked through,
+//! `msg.x` works on a `Some(Point { x: 1.0, y: 2.0 })`, and so are the units the quantities print
+//! after their values.
+
+use cu29::prelude::*;
+
+/// A payload parsed back from its Debug rendering.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugValue {
+    Number(f64),
+    Bool(bool),
+    /// A string, a char or a unit variant like `None`.
+    Text(String),
+    /// A struct with named fields or a map.
+    Struct(Vec<(String, DebugValue)>),
+    /// A tuple, a tuple struct, an array or a sequence.
+    Seq(Vec<DebugValue>),
+}
+
+impl DebugValue {
+    /// Parses a Debug rendering, compact or pretty.
+    pub fn parse(text: &str) -> CuResult<Self> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_spaces();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unexpected text after the value"));
+        }
+        Ok(value)
+    }
+
+    /// The number this value stands for, looking through the newtypes.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            DebugValue::Number(number) => Some(*number),
+            DebugValue::Bool(b) => Some(*b as u8 as f64),
+            DebugValue::Seq(items) if items.len() == 1 => items[0].as_f64(),
+            DebugValue::Struct(fields) if fields.len() == 1 => fields[0].1.as_f64(),
+            _ => None,
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&DebugValue> {
+        match self {
+            DebugValue::Struct(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            DebugValue::Seq(items) => match name.parse::<usize>() {
+                Ok(index) => items.get(index),
+                // Some(..) or a newtype around the struct.
+                Err(_) if items.len() == 1 => items[0].field(name),
+                Err(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn index(&self, index: usize) -> Option<&DebugValue> {
+        match self {
+            DebugValue::Seq(items) => match items.as_slice() {
+                // Some([..]) or a newtype around a sequence.
+                [inner @ (DebugValue::Seq(_) | DebugValue::Struct(_))] => inner.index(index),
+                items => items.get(index),
+            },
+            DebugValue::Struct(fields) if fields.len() == 1 => fields[0].1.index(index),
+            _ => None,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, reason: &str) -> CuError {
+        CuError::from(format!(
+            "Could not parse the payload at character {}: {reason}.",
+            self.pos
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_spaces();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> CuResult<DebugValue> {
+        self.skip_spaces();
+        match self.peek() {
+            None => Err(self.error("missing value")),
+            Some('"') | Some('\'') => self.quoted(),
+            Some('[') => {
+                self.pos += 1;
+                Ok(DebugValue::Seq(self.items(']')?))
+            }
+            Some('(') => {
+                self.pos += 1;
+                Ok(DebugValue::Seq(self.items(')')?))
+            }
+            Some('{') => {
+                self.pos += 1;
+                Ok(DebugValue::Struct(self.fields()?))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => self.named(),
+            Some(c) => Err(self.error(&format!("unexpected '{c}'"))),
+        }
+    }
+
+    /// A struct, a tuple struct, a unit variant, a boolean or one of the float special values.
+    fn named(&mut self) -> CuResult<DebugValue> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        if self.eat('{') {
+            return Ok(DebugValue::Struct(self.fields()?));
+        }
+        if self.eat('(') {
+            return Ok(DebugValue::Seq(self.items(')')?));
+        }
+        Ok(match name.as_str() {
+            "true" => DebugValue::Bool(true),
+            "false" => DebugValue::Bool(false),
+            "NaN" => DebugValue::Number(f64::NAN),
+            "inf" => DebugValue::Number(f64::INFINITY),
+            _ => DebugValue::Text(name),
+        })
+    }
+
+    fn quoted(&mut self) -> CuResult<DebugValue> {
+        let quote = self.chars[self.pos];
+        self.pos += 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' => {
+                    if let Some(escaped) = self.peek() {
+                        text.push(escaped);
+                        self.pos += 1;
+                    }
+                }
+                c if c == quote => return Ok(DebugValue::Text(text)),
+                c => text.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    /// A number, followed by the unit of a quantity if any, up to the next delimiter.
+    fn number(&mut self) -> CuResult<DebugValue> {
+        let start = self.pos;
+        self.pos += 1;
+        while self.peek().is_some_and(|c| {
+            c.is_ascii_alphanumeric()
+                || c == '.'
+                || c == '_'
+                || ((c == '-' || c == '+') && matches!(self.chars[self.pos - 1], 'e' | 'E'))
+        }) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        let number = match text.as_str() {
+            "-inf" => f64::NEG_INFINITY,
+            "+inf" => f64::INFINITY,
+            "-NaN" => f64::NAN,
+            text => text
+                .parse::<f64>()
+                .map_err(|_| self.error(&format!("invalid number \"{text}\"")))?,
+        };
+        while self
+            .peek()
+            .is_some_and(|c| !matches!(c, ',' | ')' | ']' | '}'))
+        {
+            self.pos += 1;
+        }
+        Ok(DebugValue::Number(number))
+    }
+
+    fn items(&mut self, close: char) -> CuResult<Vec<DebugValue>> {
+        let mut items = Vec::new();
+        loop {
+            if self.eat(close) {
+                return Ok(items);
+            }
+            items.push(self.value()?);
+            if self.eat(close) {
+                return Ok(items);
+            }
+            if !self.eat(',') {
+                return Err(self.error(&format!("expected ',' or '{close}'")));
+            }
+        }
+    }
+
+    fn fields(&mut self) -> CuResult<Vec<(String, DebugValue)>> {
+        let mut fields = Vec::new();
+        loop {
+            if self.eat('}') {
+                return Ok(fields);
+            }
+            let key = match self.value()? {
+                DebugValue::Text(key) => key,
+                DebugValue::Number(key) => key.to_string(),
+                _ => return Err(self.error("invalid field name")),
+            };
+            if !self.eat(':') {
+                return Err(self.error("expected ':'"));
+            }
+            fields.push((key, self.value()?));
+            if self.eat('}') {
+                return Ok(fields);
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected ',' or '}'"));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Field(String),
+    Index(usize),
+}
+
+/// A path to a numeric field of a payload, `msg.twist.linear.x` for example.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPath {
+    steps: Vec<Step>,
+}
+
+impl FieldPath {
+    pub fn parse(expr: &str) -> CuResult<Self> {
+        let invalid = |reason: &str| {
+            CuError::from(format!(
+                "Invalid expression \"{expr}\": {reason}, expected msg.field.subfield[index]..."
+            ))
+        };
+        let rest = expr
+            .trim()
+            .strip_prefix("msg")
+            .ok_or_else(|| invalid("it doesn't start with msg"))?;
+        let mut steps = Vec::new();
+        let mut chars = rest.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    let mut name = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                        name.push(c);
+                    }
+                    if name.is_empty() {
+                        return Err(invalid("a field name is missing"));
+                    }
+                    steps.push(Step::Field(name));
+                }
+                '[' => {
+                    let mut index = String::new();
+                    while let Some(c) = chars.next_if(|c| *c != ']') {
+                        index.push(c);
+                    }
+                    if chars.next() != Some(']') {
+                        return Err(invalid("a ']' is missing"));
+                    }
+                    let index = index
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid("an index is not a number"))?;
+                    steps.push(Step::Index(index));
+                }
+                _ => return Err(invalid(&format!("unexpected '{c}'"))),
+            }
+        }
+        Ok(Self { steps })
+    }
+
+    /// The value of the field in a payload, None if it doesn't have it or it is not a number.
+    pub fn eval(&self, payload: &DebugValue) -> Option<f64> {
+        let mut value = payload;
+        for step in &self.steps {
+            value = match step {
+                Step::Field(name) => value.field(name)?,
+                Step::Index(index) => value.index(*index)?,
+            };
+        }
+        value.as_f64()
+    }
+}
+
+#[cfg(test)]
+#[allow(dead_code)] // the payloads are only read through their Debug rendering
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Vector3 {
+        x: f64,
+        y: f64,
+        z: f32,
+    }
+
+    #[derive(Debug)]
+    struct Twist {
+        linear: Vector3,
+        angular: Vector3,
+    }
+
+    #[derive(Debug)]
+    struct Odometry {
+        frame: &'static str,
+        twist: Option<Twist>,
+        ranges: [u16; 3],
+        valid: bool,
+        distance: Meters,
+    }
+
+    #[derive(Debug)]
+    struct Meters(f32);
+
+    fn eval(expr: &str, payload: &impl std::fmt::Debug) -> Option<f64> {
+        let path = FieldPath::parse(expr).unwrap();
+        let compact = path.eval(&DebugValue::parse(&format!("{payload:?}")).unwrap());
+        let pretty = path.eval(&DebugValue::parse(&format!("{payload:#?}")).unwrap());
+        assert_eq!(compact.map(f64::to_bits), pretty.map(f64::to_bits));
+        compact
+    }
+
+    #[test]
+    fn test_field_paths() {
+        let vector = |x| Vector3 {
+            x,
+            y: 0.0,
+            z: -1e-7,
+        };
+        let odometry = Odometry {
+            frame: "odom, \"base\"",
+            twist: Some(Twist {
+                linear: vector(1.5),
+                angular: vector(-0.25),
+            }),
+            ranges: [10, 20, 30],
+            valid: true,
+            distance: Meters(2.5),
+        };
+        assert_eq!(eval("msg.twist.linear.x", &odometry), Some(1.5));
+        assert_eq!(eval("msg.twist.angular.x", &odometry), Some(-0.25));
+        assert_eq!(eval("msg.twist.linear.z", &odometry), Some(-1e-7));
+        assert_eq!(eval("msg.ranges[2]", &odometry), Some(30.0));
+        assert_eq!(eval("msg.ranges.1", &odometry), Some(20.0));
+        assert_eq!(eval("msg.valid", &odometry), Some(1.0));
+        assert_eq!(eval("msg.distance", &odometry), Some(2.5));
+        assert_eq!(eval("msg.distance.0", &odometry), Some(2.5));
+        assert_eq!(eval("msg.frame", &odometry), None);
+        assert_eq!(eval("msg.twist.linear", &odometry), None);
+        assert_eq!(eval("msg.ranges[3]", &odometry), None);
+        assert_eq!(eval("msg", &42i8), Some(42.0));
+        assert_eq!(eval("msg", &Some(-3.5f64)), Some(-3.5));
+        assert_eq!(
+            eval("msg[1]", &(1u8, f32::NAN)).map(f64::is_nan),
+            Some(true)
+        );
+
+        // The quantities print their unit after the value.
+        let quantity =
+            DebugValue::parse("Reading { distance: 0.5 m^1, temperature: 2.9315e2 K^1 }").unwrap();
+        assert_eq!(
+            FieldPath::parse("msg.temperature").unwrap().eval(&quantity),
+            Some(293.15)
+        );
+
+        assert!(FieldPath::parse("twist.x").is_err());
+        assert!(FieldPath::parse("msg.ranges[a]").is_err());
+        assert!(FieldPath::parse("msg..x").is_err());
+        assert!(DebugValue::parse("Twist { linear: ").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! The window of the dashboard, one plot per row, their time axes linked.
+
+use crate::dashboard::Dashboard;
+use cu29::prelude::*;
+use eframe::egui;
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct DashboardApp {
+    dashboard: Arc<Mutex<Dashboard>>,
+    live: bool,
+}
+
+impl eframe::App for DashboardApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        draw(ctx, &self.dashboard.lock().unwrap());
+        if self.live {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+}
+
+fn draw(ctx: &egui::Context, dashboard: &Dashboard) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let plots = dashboard.config.plots.len().max(1) as f32;
+        let title_height =
+            ui.text_style_height(&egui::TextStyle::Body) + ui.spacing().item_spacing.y;
+        let height =
+            (ui.available_height() / plots - title_height - ui.spacing().item_spacing.y).max(50.0);
+        for (index, plot) in dashboard.config.plots.iter().enumerate() {
+            let series: Vec<_> = dashboard
+                .series
+                .iter()
+                .filter(|series| series.plot == index)
+                .collect();
+            ui.horizontal(|ui| {
+                ui.strong(&plot.title);
+                // Most likely a typo in the expression.
+                for missing in series
+                    .iter()
+                    .filter(|series| series.points.is_empty() && series.misses > 0)
+                {
+                    ui.colored_label(
+                        egui::Color32::ORANGE,
+                        format!(
+                            "{}: not found in {} messages",
+                            missing.label, missing.misses
+                        ),
+                    );
+                }
+            });
+            Plot::new(("copper_plot", index))
+                .height(height)
+                .legend(Legend::default())
+                .link_axis("copper_plot_time", [true, false])
+                .link_cursor("copper_plot_time", [true, false].into())
+                .x_axis_label("s")
+                .show(ui, |plot_ui| {
+                    for series in &series {
+                        let points: PlotPoints = series.points.iter().copied().collect();
+                        plot_ui.line(Line::new(points).name(&series.label));
+                    }
+                });
+        }
+    });
+}
+
+/// Opens the window until it is closed, `live` when the dashboard is being fed.
+pub(crate) fn show(dashboard: Arc<Mutex<Dashboard>>, live: bool) -> CuResult<()> {
+    let app = DashboardApp { dashboard, live };
+    eframe::run_native(
+        "Copper plot",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(app))),
+    )
+    .map_err(|e| CuError::from(format!("The plot window failed: {e}")))
+}
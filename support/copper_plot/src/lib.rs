@@ -0,0 +1,101 @@
+//! A dashboard plotting the numeric fields of the messages of some connections, from a log or from
+//! a running application, without writing an exporter for every quick plot: a "plotjuggler-lite".
+//!
+//! The plots are described in a RON file, see [config], and their series are expressions like
+//! `msg.twist.linear.x` on the payloads of the connections, see [expr].
+//!
+//! The payloads are the types of the application, so like the log reader the plotter is generated
+//! by the application from its configuration:
+//!
+//! ```rust,ignore
+//! use cu29::prelude::*;
+//!
+//! gen_cumsgs!("copperconfig.ron");
+//!
+//! fn main() {
+//!     copper_plot::run::<CuMsgs>().expect("Failed to run the plotter");
+//! }
+//! ```
+//!
+//! The window needs the `gui` feature (default), plotting a running application the `live` feature
+//! and the application to listen for taps, see `cu29::tap`.
+
+pub mod config;
+pub mod dashboard;
+pub mod expr;
+#[cfg(feature = "gui")]
+mod gui;
+pub mod source;
+
+#[cfg(feature = "gui")]
+pub use cli::*;
+
+#[cfg(feature = "gui")]
+mod cli {
+    use crate::config::DashboardConfig;
+    use crate::dashboard::Dashboard;
+    use crate::{gui, source};
+    use clap::{Parser, Subcommand};
+    use cu29::export::CuOutputsDebug;
+    use cu29::prelude::*;
+    use cu29::schema::CuSchemaTagged;
+    #[cfg(feature = "live")]
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Parser)]
+    #[command(author, version, about)]
+    pub struct PlotCli {
+        /// The RON description of the plots
+        #[arg(short, long, default_value = "plots.ron")]
+        pub config: PathBuf,
+
+        #[command(subcommand)]
+        pub source: Source,
+    }
+
+    #[derive(Subcommand)]
+    pub enum Source {
+        /// Plots a recorded log
+        Log {
+            /// The base path is the name with no _0 _1 et the end.
+            /// for example for toto_0.copper, toto_1.copper ... the base name is toto.copper
+            unifiedlog_base: PathBuf,
+            /// Skips the damaged sections of a log, after a power loss for example
+            #[arg(long)]
+            salvage: bool,
+        },
+        /// Plots a running application listening for taps
+        #[cfg(feature = "live")]
+        Live {
+            /// The address the application listens to for taps
+            app: SocketAddr,
+        },
+    }
+
+    /// Loads the plots given on the command line and shows them until the window is closed.
+    pub fn run<P>() -> CuResult<()>
+    where
+        P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+    {
+        let args = PlotCli::parse();
+        let config = DashboardConfig::from_file(&args.config.to_string_lossy())?;
+        let mut dashboard = Dashboard::new(config)?;
+        match args.source {
+            Source::Log {
+                unifiedlog_base,
+                salvage,
+            } => {
+                source::load_log::<P>(&mut dashboard, &unifiedlog_base, salvage)?;
+                gui::show(Arc::new(Mutex::new(dashboard)), false)
+            }
+            #[cfg(feature = "live")]
+            Source::Live { app } => {
+                let dashboard = Arc::new(Mutex::new(dashboard));
+                let _source = source::LiveSource::start(dashboard.clone(), app)?;
+                gui::show(dashboard, true)
+            }
+        }
+    }
+}
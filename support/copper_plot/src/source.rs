@@ -0,0 +1,164 @@
+//! Where the messages plotted come from: a log, or a running application through its taps.
+
+use crate::dashboard::Dashboard;
+use cu29::export::CuOutputsDebug;
+use cu29::prelude::*;
+use cu29::schema::{check_schema, CuSchemaTagged};
+use cu29_export::{copperlists_dump, read_schema_tags};
+use std::path::Path;
+
+fn open_reader(unifiedlog_base: &Path, salvage: bool) -> CuResult<UnifiedLoggerRead> {
+    match UnifiedLoggerBuilder::new()
+        .file_base_name(unifiedlog_base)
+        .salvage(salvage)
+        .build()
+        .map_err(|e| CuError::new_with_cause("Could not open the log", e))?
+    {
+        UnifiedLogger::Read(dl) => Ok(dl),
+        UnifiedLogger::Write(_) => Err(CuError::from("The log was opened for writing.")),
+    }
+}
+
+/// Feeds the dashboard with the messages of a log, the time being the seconds since its first
+/// copperlist.
+pub fn load_log<P>(dashboard: &mut Dashboard, unifiedlog_base: &Path, salvage: bool) -> CuResult<()>
+where
+    P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+{
+    if let Some(recorded) = read_schema_tags(open_reader(unifiedlog_base, salvage)?)? {
+        check_schema(&recorded, &P::schema_tags())?;
+    }
+    let mut reader = UnifiedLoggerIOReader::new(
+        open_reader(unifiedlog_base, salvage)?,
+        UnifiedLogType::CopperList,
+    );
+    let mut origin: Option<CuTime> = None;
+    let mut last = 0.0;
+    for copperlist in copperlists_dump::<P>(&mut reader) {
+        let mut start: Option<CuTime> = None;
+        let mut messages = Vec::new();
+        copperlist
+            .msgs
+            .visit_outputs(&mut |task_id, metadata, payload| {
+                if !metadata.process_time.start.is_none() {
+                    let time = metadata.process_time.start.unwrap();
+                    start = Some(start.map_or(time, |start| start.min(time)));
+                }
+                if let Some(payload) = payload.filter(|_| dashboard.is_plotted(task_id)) {
+                    messages.push((task_id, format!("{payload:?}")));
+                }
+            });
+        // The copperlists without any message keep the time of the previous one.
+        if let Some(start) = start {
+            let origin = *origin.get_or_insert(start);
+            last = (start.as_nanos().saturating_sub(origin.as_nanos())) as f64 / 1e9;
+        }
+        for (task_id, payload) in messages {
+            dashboard.push(task_id, last, &payload);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "live")]
+pub use live::LiveSource;
+
+#[cfg(feature = "live")]
+mod live {
+    use crate::dashboard::Dashboard;
+    use cu29::bincode::{decode_from_slice, encode_to_vec};
+    use cu29::prelude::*;
+    use cu29::tap::{TapFrame, TapRequest, MAX_TAP_DATAGRAM};
+    use std::net::{SocketAddr, UdpSocket};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::{Duration, Instant};
+
+    /// Feeds the dashboard from a thread with the messages of a running application listening for
+    /// taps, see `cu29::tap`, the time being the seconds since the source started. The taps are
+    /// removed when it is dropped.
+    pub struct LiveSource {
+        socket: UdpSocket,
+        app: SocketAddr,
+        connections: Vec<String>,
+        running: Arc<AtomicBool>,
+        receiver: Option<JoinHandle<()>>,
+    }
+
+    impl LiveSource {
+        pub fn start(dashboard: Arc<Mutex<Dashboard>>, app: SocketAddr) -> CuResult<Self> {
+            let bind: SocketAddr = if app.is_ipv4() {
+                ([0, 0, 0, 0], 0).into()
+            } else {
+                ([0u16; 8], 0).into()
+            };
+            let socket = UdpSocket::bind(bind)
+                .map_err(|e| CuError::new_with_cause("Could not bind the tap socket", e))?;
+            // Lets the receiver check if it has to stop.
+            socket
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .map_err(|e| CuError::new_with_cause("Could not configure the tap socket", e))?;
+            let connections = dashboard.lock().unwrap().connections();
+            let mut source = Self {
+                socket: socket
+                    .try_clone()
+                    .map_err(|e| CuError::new_with_cause("Could not clone the tap socket", e))?,
+                app,
+                connections,
+                running: Arc::new(AtomicBool::new(true)),
+                receiver: None,
+            };
+            for cnx in &source.connections {
+                source.send(&TapRequest::Echo { cnx: cnx.clone() })?;
+            }
+
+            let running = source.running.clone();
+            let receiver = std::thread::spawn(move || {
+                let start = Instant::now();
+                let mut buffer = vec![0u8; MAX_TAP_DATAGRAM];
+                while running.load(Ordering::Relaxed) {
+                    let Ok(len) = socket.recv(&mut buffer) else {
+                        continue;
+                    };
+                    let Ok((frame, _)) =
+                        decode_from_slice::<TapFrame, _>(&buffer[..len], CuWireFormat::CONFIG)
+                    else {
+                        continue;
+                    };
+                    let Some(payload) = frame.payload else {
+                        continue;
+                    };
+                    let time = start.elapsed().as_secs_f64();
+                    let mut dashboard = dashboard.lock().unwrap();
+                    dashboard.push(&frame.src, time, &payload);
+                    let history = dashboard.config.history_s;
+                    dashboard.trim(time - history);
+                }
+            });
+            source.receiver = Some(receiver);
+            Ok(source)
+        }
+
+        fn send(&self, request: &TapRequest) -> CuResult<()> {
+            let bytes = encode_to_vec(request, CuWireFormat::CONFIG)
+                .map_err(|e| CuError::new_with_cause("Could not encode the tap request", e))?;
+            self.socket
+                .send_to(&bytes, self.app)
+                .map_err(|e| CuError::new_with_cause("Could not send the tap request", e))?;
+            Ok(())
+        }
+    }
+
+    impl Drop for LiveSource {
+        fn drop(&mut self) {
+            for cnx in &self.connections {
+                let _ = self.send(&TapRequest::Stop { cnx: cnx.clone() });
+            }
+            self.running.store(false, Ordering::Relaxed);
+            if let Some(receiver) = self.receiver.take() {
+                let _ = receiver.join();
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! The index of a log: when every copperlist happened and which connections had a message in it,
+//! built in one pass so the viewer can chart and scrub through logs much bigger than the memory.
+
+use cu29::clock::CuTime;
+
+/// A connection of the log, named after the task emitting its messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub task_id: String,
+    pub msg_type: String,
+    /// The number of messages in the log.
+    pub count: usize,
+}
+
+pub struct LogIndex {
+    pub connections: Vec<Connection>,
+    ids: Vec<u32>,
+    times: Vec<CuTime>,
+    /// If the connection had a message in the copperlist, one row of connections per copperlist.
+    presence: Vec<bool>,
+}
+
+impl LogIndex {
+    pub fn new(connections: Vec<Connection>) -> Self {
+        Self {
+            connections,
+            ids: Vec::new(),
+            times: Vec::new(),
+            presence: Vec::new(),
+        }
+    }
+
+    /// Adds the next copperlist of the log, `present` having one entry per connection.
+    pub fn push(&mut self, culist_id: u32, time: CuTime, present: &[bool]) {
+        assert_eq!(present.len(), self.connections.len());
+        for (connection, present) in self.connections.iter_mut().zip(present) {
+            connection.count += *present as usize;
+        }
+        self.ids.push(culist_id);
+        // The clock is monotonic but the copperlists without any message have no time of their own.
+        let time = self.times.last().map_or(time, |last| time.max(*last));
+        self.times.push(time);
+        self.presence.extend_from_slice(present);
+    }
+
+    /// The number of copperlists.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn culist_id(&self, culist: usize) -> u32 {
+        self.ids[culist]
+    }
+
+    pub fn time(&self, culist: usize) -> CuTime {
+        self.times[culist]
+    }
+
+    /// The time of the first and the last copperlists.
+    pub fn span(&self) -> Option<(CuTime, CuTime)> {
+        Some((*self.times.first()?, *self.times.last()?))
+    }
+
+    /// The average number of messages per second of a connection over the log.
+    pub fn rate(&self, connection: usize) -> f64 {
+        let Some((start, end)) = self.span() else {
+            return 0.0;
+        };
+        let seconds = (end - start).as_secs_f64();
+        if seconds > 0.0 {
+            self.connections[connection].count as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// The number of messages of a connection in each of `buckets` equal slices of the log.
+    pub fn histogram(&self, connection: usize, buckets: usize) -> Vec<u64> {
+        let mut histogram = vec![0; buckets];
+        let Some((start, end)) = self.span() else {
+            return histogram;
+        };
+        if buckets == 0 {
+            return histogram;
+        }
+        let duration = (end - start).as_nanos().max(1);
+        for culist in (0..self.len()).filter(|culist| self.is_present(*culist, connection)) {
+            let offset = (self.times[culist] - start).as_nanos();
+            let bucket = (offset as u128 * buckets as u128 / duration as u128) as usize;
+            histogram[bucket.min(buckets - 1)] += 1;
+        }
+        histogram
+    }
+
+    /// The last copperlist at or before `time`, the first one before the start of the log.
+    pub fn culist_at(&self, time: CuTime) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.times.partition_point(|t| *t <= time).max(1) - 1)
+    }
+
+    /// The last copperlist up to `culist` with a message on the connection.
+    pub fn last_message(&self, connection: usize, culist: usize) -> Option<usize> {
+        (0..=culist)
+            .rev()
+            .find(|culist| self.is_present(*culist, connection))
+    }
+
+    /// The first copperlist after `culist` with a message on the connection.
+    pub fn next_message(&self, connection: usize, culist: usize) -> Option<usize> {
+        (culist + 1..self.len()).find(|culist| self.is_present(*culist, connection))
+    }
+
+    fn is_present(&self, culist: usize, connection: usize) -> bool {
+        self.presence[culist * self.connections.len() + connection]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cu29::clock::CuDuration;
+
+    fn connection(task_id: &str) -> Connection {
+        Connection {
+            task_id: task_id.to_string(),
+            msg_type: "u32".to_string(),
+            count: 0,
+        }
+    }
+
+    #[test]
+    fn test_log_index() {
+        let mut index = LogIndex::new(vec![connection("imu"), connection("gps")]);
+        assert_eq!(index.culist_at(CuDuration::from(0)), None);
+        // The imu at every copperlist, every 10ms, the gps every 4 of them, over 1s.
+        for i in 0..101u32 {
+            let time = CuDuration::from(i as u64 * 10_000_000);
+            index.push(i + 1000, time, &[true, i % 4 == 0]);
+        }
+        // A copperlist without any message.
+        index.push(1101, CuDuration::from(0), &[false, false]);
+
+        assert_eq!(index.len(), 102);
+        assert_eq!(index.connections[0].count, 101);
+        assert_eq!(index.connections[1].count, 26);
+        assert_eq!(index.time(101), CuDuration::from(1_000_000_000));
+        assert!((index.rate(0) - 101.0).abs() < 1e-9);
+        assert_eq!(index.histogram(0, 4), vec![25, 25, 25, 26]);
+        assert_eq!(index.histogram(1, 2).iter().sum::<u64>(), 26);
+
+        let culist = index.culist_at(CuDuration::from(55_000_000)).unwrap();
+        assert_eq!((culist, index.culist_id(culist)), (5, 1005));
+        assert_eq!(index.culist_at(CuDuration::from(0)), Some(0));
+        assert_eq!(index.last_message(1, culist), Some(4));
+        assert_eq!(index.next_message(1, culist), Some(8));
+        assert_eq!(index.next_message(1, 100), None);
+    }
+}
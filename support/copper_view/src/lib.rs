@@ -0,0 +1,183 @@
+//! A terminal viewer of the Copper logs: it lists the connections of a log with their message
+//! rates over time and lets you scrub through the run, pretty printing the messages at the cursor.
+//!
+//! The payloads are the types of the application, so like the log reader the viewer is generated
+//! by the application from its configuration:
+//!
+//! ```rust,ignore
+//! use cu29::prelude::*;
+//!
+//! gen_cumsgs!("copperconfig.ron");
+//!
+//! fn main() {
+//!     copper_view::run::<CuMsgs>().expect("Failed to run the log viewer");
+//! }
+//! ```
+
+pub mod index;
+mod ui;
+
+use clap::Parser;
+use cu29::export::CuOutputsDebug;
+use cu29::prelude::*;
+use cu29::schema::{check_schema, CuSchemaTagged};
+use cu29_export::{copperlists_dump, read_schema_tags};
+use index::{Connection, LogIndex};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The viewer keeps the position of one copperlist out of CHECKPOINT_PERIOD to decode any of
+/// them without reading the log from its start.
+const CHECKPOINT_PERIOD: usize = 100;
+
+#[derive(Parser)]
+#[command(author, version, about)]
+pub struct ViewerCli {
+    /// The base path is the name with no _0 _1 et the end.
+    /// for example for toto_0.copper, toto_1.copper ... the base name is toto.copper
+    pub unifiedlog_base: PathBuf,
+
+    /// Skips the damaged sections of a log, after a power loss for example
+    #[arg(long)]
+    pub salvage: bool,
+}
+
+/// Opens the log given on the command line, indexes it and runs the viewer until it is quit.
+pub fn run<P>() -> CuResult<()>
+where
+    P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+{
+    let args = ViewerCli::parse();
+    let log = LogDecoder::<P>::open(&args.unifiedlog_base, args.salvage)?;
+    ui::run(log)
+}
+
+fn open_reader(unifiedlog_base: &Path, salvage: bool) -> CuResult<UnifiedLoggerRead> {
+    match UnifiedLoggerBuilder::new()
+        .file_base_name(unifiedlog_base)
+        .salvage(salvage)
+        .build()
+        .map_err(|e| CuError::new_with_cause("Could not open the log", e))?
+    {
+        UnifiedLogger::Read(dl) => Ok(dl),
+        UnifiedLogger::Write(_) => Err(CuError::from("The log was opened for writing.")),
+    }
+}
+
+/// A log indexed for browsing, decoding the copperlists on demand.
+pub struct LogDecoder<P: CopperListTuple + CuOutputsDebug> {
+    pub index: LogIndex,
+    reader: UnifiedLoggerIOReader,
+    /// The position of every CHECKPOINT_PERIOD copperlist.
+    checkpoints: Vec<LogPosition>,
+    /// The last message decoded: copperlist, connection and its text.
+    cache: Option<(usize, usize, String)>,
+    _payload: std::marker::PhantomData<P>,
+}
+
+impl<P> LogDecoder<P>
+where
+    P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+{
+    /// Reads the whole log once to index it.
+    pub fn open(unifiedlog_base: &Path, salvage: bool) -> CuResult<Self> {
+        let tags = P::schema_tags();
+        if let Some(recorded) = read_schema_tags(open_reader(unifiedlog_base, salvage)?)? {
+            check_schema(&recorded, &tags)?;
+        }
+        let connections = tags
+            .into_iter()
+            .map(|tag| Connection {
+                task_id: tag.task_id,
+                msg_type: tag.msg_type,
+                count: 0,
+            })
+            .collect();
+        let mut index = LogIndex::new(connections);
+        let mut reader = UnifiedLoggerIOReader::new(
+            open_reader(unifiedlog_base, salvage)?,
+            UnifiedLogType::CopperList,
+        );
+        let mut checkpoints = Vec::new();
+        let mut present = vec![false; index.connections.len()];
+        loop {
+            let position = reader.position();
+            let Some(copperlist) = copperlists_dump::<P>(&mut reader).next() else {
+                break;
+            };
+            if index.len().is_multiple_of(CHECKPOINT_PERIOD) {
+                checkpoints.push(position);
+            }
+            present.fill(false);
+            let mut time: Option<CuTime> = None;
+            copperlist
+                .msgs
+                .visit_outputs(&mut |task_id, metadata, payload| {
+                    if let Some(connection) = index
+                        .connections
+                        .iter()
+                        .position(|connection| connection.task_id == task_id)
+                    {
+                        present[connection] = payload.is_some();
+                    }
+                    if !metadata.process_time.start.is_none() {
+                        let start = metadata.process_time.start.unwrap();
+                        time = Some(time.map_or(start, |time| time.min(start)));
+                    }
+                });
+            index.push(copperlist.id, time.unwrap_or(CuDuration::from(0)), &present);
+        }
+        Ok(Self {
+            index,
+            reader,
+            checkpoints,
+            cache: None,
+            _payload: std::marker::PhantomData,
+        })
+    }
+
+    /// The message of a connection in a copperlist as text, its metadata then its payload.
+    pub fn message(&mut self, culist: usize, connection: usize) -> CuResult<&str> {
+        if !matches!(&self.cache, Some((c, n, _)) if *c == culist && *n == connection) {
+            let text = self.decode(culist, connection)?;
+            self.cache = Some((culist, connection, text));
+        }
+        Ok(self
+            .cache
+            .as_ref()
+            .map(|(_, _, text)| text.as_str())
+            .unwrap())
+    }
+
+    fn decode(&mut self, culist: usize, connection: usize) -> CuResult<String> {
+        self.reader
+            .seek(&self.checkpoints[culist / CHECKPOINT_PERIOD])
+            .map_err(|e| CuError::new_with_cause("Could not seek in the log", e))?;
+        let copperlist = copperlists_dump::<P>(&mut self.reader)
+            .nth(culist % CHECKPOINT_PERIOD)
+            .ok_or_else(|| CuError::from("The copperlist could not be read again."))?;
+        let task_id = &self.index.connections[connection].task_id;
+        let mut text = String::new();
+        copperlist.msgs.visit_outputs(&mut |id, metadata, payload| {
+            if id != task_id {
+                return;
+            }
+            let _ = writeln!(text, "{metadata}");
+            let _ = writeln!(
+                text,
+                "tov: {:?}, validity: {}",
+                metadata.tov, metadata.validity
+            );
+            if !metadata.status_txt.0.is_empty() {
+                let _ = writeln!(text, "status: {}", metadata.status_txt.0);
+            }
+            match payload {
+                Some(payload) => {
+                    let _ = write!(text, "\n{payload:#?}");
+                }
+                None => text.push_str("\nno payload"),
+            }
+        });
+        Ok(text)
+    }
+}
@@ -0,0 +1,288 @@
+//! The screen of the viewer: the connections with their rates, the message rate of the selected one
+//! over the log with the cursor under it, and its message at the cursor.
+
+use crate::LogDecoder;
+use cu29::clock::{CuDuration, CuTime};
+use cu29::export::CuOutputsDebug;
+use cu29::prelude::*;
+use cu29::schema::CuSchemaTagged;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{Event, KeyCode};
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::crossterm::{event, execute};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::{Frame, Terminal};
+use std::io::stdout;
+use std::time::Duration;
+
+const HELP: &str = " [↑↓] connection  [←→] scrub  [PgUp PgDn] scrub x10  [Home End] start, end  [n p] next, previous message  [u d] scroll  [q] quit ";
+
+struct Viewer<P: CopperListTuple + CuOutputsDebug> {
+    log: LogDecoder<P>,
+    selected: usize,
+    cursor: CuTime,
+    /// The number of slices of the log in the sparkline, from the last draw.
+    buckets: usize,
+    scroll: u16,
+}
+
+impl<P> Viewer<P>
+where
+    P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+{
+    fn span(&self) -> (CuTime, CuTime) {
+        self.log
+            .index
+            .span()
+            .unwrap_or((CuDuration::from(0), CuDuration::from(0)))
+    }
+
+    /// Moves the cursor by a number of slices of the sparkline, staying in the log.
+    fn scrub(&mut self, buckets: i64) {
+        let (start, end) = self.span();
+        let step = ((end - start).as_nanos() / self.buckets.max(1) as u64).max(1) as i64;
+        let cursor = (self.cursor.as_nanos() as i64 + buckets * step)
+            .clamp(start.as_nanos() as i64, end.as_nanos() as i64);
+        self.cursor = CuDuration::from(cursor as u64);
+    }
+
+    /// Moves the cursor to another message of the selected connection.
+    fn jump(&mut self, forward: bool) {
+        let index = &self.log.index;
+        let Some(culist) = index.culist_at(self.cursor) else {
+            return;
+        };
+        let target = if forward {
+            index.next_message(self.selected, culist)
+        } else {
+            // The previous message strictly before the cursor.
+            match index.last_message(self.selected, culist) {
+                Some(found) if index.time(found) >= self.cursor => found
+                    .checked_sub(1)
+                    .and_then(|c| index.last_message(self.selected, c)),
+                found => found,
+            }
+        };
+        if let Some(target) = target {
+            self.cursor = index.time(target);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(5),
+                Constraint::Length(7),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(layout[1]);
+
+        let index = &self.log.index;
+        let culist = index.culist_at(self.cursor);
+        let header = match culist {
+            Some(culist) => format!(
+                " {} copperlists, cursor at {} (culist {})",
+                index.len(),
+                self.cursor,
+                index.culist_id(culist)
+            ),
+            None => " This log has no copperlist.".to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(header).style(Style::default().add_modifier(Modifier::BOLD)),
+            layout[0],
+        );
+
+        self.draw_connections(frame, panes[0]);
+        self.draw_rates(frame, layout[2]);
+        self.draw_message(frame, panes[1], culist);
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().fg(Color::Black).bg(Color::Gray)),
+            layout[3],
+        );
+    }
+
+    fn draw_connections(&self, frame: &mut Frame, area: Rect) {
+        let index = &self.log.index;
+        let rows = index.connections.iter().enumerate().map(|(i, connection)| {
+            Row::new(vec![
+                Cell::from(connection.task_id.as_str()),
+                Cell::from(connection.msg_type.as_str()),
+                Cell::from(connection.count.to_string()),
+                Cell::from(format!("{:.1} Hz", index.rate(i))),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(40),
+                Constraint::Percentage(15),
+                Constraint::Percentage(15),
+            ],
+        )
+        .header(
+            Row::new(vec!["Task", "Message", "Count", "Rate"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::default().bg(Color::DarkGray))
+        .block(
+            Block::default()
+                .title(" Connections ")
+                .borders(Borders::ALL),
+        );
+        let mut state = TableState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn draw_rates(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .title(" Messages over time ")
+            .borders(Borders::ALL);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let index = &self.log.index;
+        self.buckets = inner.width.max(1) as usize;
+        if index.connections.is_empty() {
+            return;
+        }
+        let histogram = index.histogram(self.selected, self.buckets);
+        frame.render_widget(
+            Sparkline::default()
+                .data(&histogram)
+                .style(Style::default().fg(Color::Green)),
+            rows[0],
+        );
+
+        let (start, end) = self.span();
+        let duration = (end - start).as_nanos().max(1);
+        let bucket = ((self.cursor - start).as_nanos() as u128 * self.buckets as u128
+            / duration as u128) as usize;
+        let marker = format!("{}▲", " ".repeat(bucket.min(self.buckets - 1)));
+        frame.render_widget(
+            Paragraph::new(marker).style(Style::default().fg(Color::Yellow)),
+            rows[1],
+        );
+        let bounds = format!("{start}");
+        let padding = (inner.width as usize).saturating_sub(bounds.len() + end.to_string().len());
+        frame.render_widget(
+            Paragraph::new(Line::from(format!("{bounds}{}{end}", " ".repeat(padding)))),
+            rows[2],
+        );
+    }
+
+    fn draw_message(&mut self, frame: &mut Frame, area: Rect, culist: Option<usize>) {
+        let block = Block::default().borders(Borders::ALL);
+        let Some(connection) = self.log.index.connections.get(self.selected) else {
+            frame.render_widget(block.title(" Message "), area);
+            return;
+        };
+        let block = block.title(format!(" {} at the cursor ", connection.task_id));
+        let message = culist.and_then(|culist| self.log.index.last_message(self.selected, culist));
+        let text = match message {
+            Some(message) => match self.log.message(message, self.selected) {
+                Ok(text) => text.to_string(),
+                Err(e) => format!("Could not decode the message: {e}"),
+            },
+            None => "No message yet.".to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(text).block(block).scroll((self.scroll, 0)),
+            area,
+        );
+    }
+}
+
+fn setup_terminal() {
+    enable_raw_mode().expect("Could not enter raw mode: check terminal compatibility.");
+    execute!(stdout(), EnterAlternateScreen)
+        .expect("Could not enter alternateScreen: check terminal compatibility.");
+}
+
+fn restore_terminal() {
+    execute!(stdout(), LeaveAlternateScreen).expect("Could not leave alternate screen");
+    disable_raw_mode().expect("Could not restore the terminal.");
+}
+
+pub(crate) fn run<P>(log: LogDecoder<P>) -> CuResult<()>
+where
+    P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+{
+    let cursor = log
+        .index
+        .span()
+        .map_or(CuDuration::from(0), |(start, _)| start);
+    let mut viewer = Viewer {
+        log,
+        selected: 0,
+        cursor,
+        buckets: 1,
+        scroll: 0,
+    };
+    setup_terminal();
+    let result = event_loop(&mut viewer);
+    restore_terminal();
+    result.map_err(|e| CuError::new_with_cause("The terminal failed", e))
+}
+
+fn event_loop<P>(viewer: &mut Viewer<P>) -> std::io::Result<()>
+where
+    P: CopperListTuple + CuSchemaTagged + CuOutputsDebug,
+{
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    loop {
+        terminal.draw(|frame| viewer.draw(frame))?;
+
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        let connections = viewer.log.index.connections.len();
+        let (start, end) = viewer.span();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                viewer.selected = viewer.selected.saturating_sub(1);
+                viewer.scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                viewer.selected = (viewer.selected + 1).min(connections.saturating_sub(1));
+                viewer.scroll = 0;
+            }
+            KeyCode::Left | KeyCode::Char('h') => viewer.scrub(-1),
+            KeyCode::Right | KeyCode::Char('l') => viewer.scrub(1),
+            KeyCode::PageUp => viewer.scrub(-10),
+            KeyCode::PageDown => viewer.scrub(10),
+            KeyCode::Home => viewer.cursor = start,
+            KeyCode::End => viewer.cursor = end,
+            KeyCode::Char('n') => viewer.jump(true),
+            KeyCode::Char('p') => viewer.jump(false),
+            KeyCode::Char('u') => viewer.scroll = viewer.scroll.saturating_sub(5),
+            KeyCode::Char('d') => viewer.scroll = viewer.scroll.saturating_add(5),
+            KeyCode::Char('q') => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}